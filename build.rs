@@ -0,0 +1,24 @@
+//! Embeds the `git describe` output of the source tree when the extension is built from a
+//! git checkout, for [`build_info`](src/build_info.rs). Builds from a source archive, or
+//! without git, go without it.
+
+use std::{path::Path, process::Command};
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    // A missing path would rebuild every time.
+    for path in [".git/HEAD", ".git/index"] {
+        if Path::new(path).exists() {
+            println!("cargo:rerun-if-changed={path}");
+        }
+    }
+    let describe = Command::new("git")
+        .args(["describe", "--tags", "--always", "--dirty"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok());
+    if let Some(describe) = describe.as_deref().map(str::trim).filter(|d| !d.is_empty()) {
+        println!("cargo:rustc-env=TYPOS_EXTENSION_GIT_DESCRIBE={describe}");
+    }
+}
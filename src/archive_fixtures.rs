@@ -0,0 +1,116 @@
+//! Synthetic release archives reproducing how typos-lsp releases were packaged over time.
+//!
+//! Each fixture is the asset of one release and the entries its archive extracts to, as
+//! [`FakeDownloader`] extracts them. The installation tests run every fixture through
+//! the whole pipeline, from the release lookup to the binary handed out, so that a
+//! packaging change upstream fails a test before it fails an installation. Add a
+//! fixture here whenever a release is packaged differently.
+
+use zed_extension_api::{Architecture, Os};
+
+use crate::test_support::{FakeDownloader, FakeReleases};
+
+/// The archive of one release, for one platform.
+pub(crate) struct Fixture {
+    /// The packaging it reproduces.
+    pub layout: &'static str,
+    pub tag: &'static str,
+    pub platform: Os,
+    pub architecture: Architecture,
+    /// The name of the release asset.
+    pub asset: &'static str,
+    /// The entries of the archive, relative to where it is extracted.
+    pub entries: &'static [&'static str],
+    /// Where the binary is expected, relative to the version directory.
+    pub binary: &'static [&'static str],
+}
+
+pub(crate) const FIXTURES: &[Fixture] = &[
+    Fixture {
+        layout: "flat unix tar",
+        tag: "v0.1.23",
+        platform: Os::Linux,
+        architecture: Architecture::X8664,
+        asset: "typos-lsp-v0.1.23-x86_64-unknown-linux-gnu.tar.gz",
+        entries: &["typos-lsp"],
+        binary: &["typos-lsp"],
+    },
+    Fixture {
+        layout: "flat unix tar, untagged name",
+        tag: "v0.1.5",
+        platform: Os::Mac,
+        architecture: Architecture::Aarch64,
+        asset: "typos-lsp-aarch64-apple-darwin.tar.gz",
+        entries: &["typos-lsp"],
+        binary: &["typos-lsp"],
+    },
+    Fixture {
+        layout: "unix tar with a top-level directory",
+        tag: "v0.1.34",
+        platform: Os::Linux,
+        architecture: Architecture::X8664,
+        asset: "typos-lsp-v0.1.34-x86_64-unknown-linux-gnu.tar.gz",
+        entries: &[
+            "typos-lsp-v0.1.34-x86_64-unknown-linux-gnu/typos-lsp",
+            "typos-lsp-v0.1.34-x86_64-unknown-linux-gnu/LICENSE",
+            "typos-lsp-v0.1.34-x86_64-unknown-linux-gnu/README.md",
+        ],
+        binary: &["typos-lsp"],
+    },
+    Fixture {
+        layout: "nested windows zip",
+        tag: "v0.1.23",
+        platform: Os::Windows,
+        architecture: Architecture::X8664,
+        asset: "typos-lsp-v0.1.23-x86_64-pc-windows-msvc.zip",
+        entries: &["target/x86_64-pc-windows-msvc/release/typos-lsp.exe"],
+        binary: &[
+            "target",
+            "x86_64-pc-windows-msvc",
+            "release",
+            "typos-lsp.exe",
+        ],
+    },
+    Fixture {
+        layout: "flat windows zip",
+        tag: "v0.1.5",
+        platform: Os::Windows,
+        architecture: Architecture::X8664,
+        asset: "typos-lsp-x86_64-pc-windows-msvc.zip",
+        entries: &["typos-lsp.exe"],
+        binary: &[
+            "target",
+            "x86_64-pc-windows-msvc",
+            "release",
+            "typos-lsp.exe",
+        ],
+    },
+    Fixture {
+        layout: "flat aarch64 windows zip",
+        tag: "v0.1.23",
+        platform: Os::Windows,
+        architecture: Architecture::Aarch64,
+        asset: "typos-lsp-v0.1.23-aarch64-pc-windows-msvc.zip",
+        entries: &["typos-lsp.exe", "LICENSE"],
+        binary: &[
+            "target",
+            "aarch64-pc-windows-msvc",
+            "release",
+            "typos-lsp.exe",
+        ],
+    },
+];
+
+impl Fixture {
+    /// Publish the release of the fixture with its asset alone, and extract the
+    /// following downloads to its entries.
+    pub(crate) fn serve(&self, releases: &FakeReleases, downloader: &FakeDownloader) {
+        releases.publish_assets(self.tag, &[self.asset]);
+        downloader.extract_to(self.entries);
+    }
+
+    /// The URL of the asset of the fixture.
+    pub(crate) fn url(&self) -> String {
+        format!("https://example.com/{}", self.asset)
+    }
+}
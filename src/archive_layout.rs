@@ -0,0 +1,282 @@
+//! Where the binary of an extracted release archive ends up.
+//!
+//! The Unix archives hold the binary at their root and the Windows ones under
+//! `target/<triple>/release/`, which already changed once. So that packaging changes
+//! upstream do not break installations, files next to the binary are left alone, a
+//! binary nested in a directory is moved where the extension expects it, and only
+//! entries known to be of no use to the extension are pruned.
+
+use std::{io, path::Path};
+
+use crate::{host::Fs, manifest::Manifest, messages};
+
+/// How deep a nested binary is looked for, below the version directory.
+const MAX_DEPTH: usize = 4;
+
+/// How many files of an archive without the binary are listed in the error.
+const MAX_LISTED: usize = 20;
+
+/// Entries of the archives the extension has no use for, by name at any depth.
+const EXTRANEOUS: &[&str] = &[
+    "README",
+    "README.md",
+    "LICENSE",
+    "LICENSE.md",
+    "LICENSE-MIT",
+    "LICENSE-APACHE",
+    "CHANGELOG.md",
+    "completions",
+    "doc",
+];
+
+/// Make the binary extracted into `dir` available at `binary`, relative to `dir`, and
+/// prune the entries the extension has no use for when `prune` is set.
+pub(crate) fn settle(fs: &dyn Fs, dir: &Path, binary: &Path, prune: bool) -> Result<(), String> {
+    let expected = dir.join(binary);
+    if !fs.is_file(&expected) {
+        let name = binary
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or_default();
+        let found = find(fs, dir, name, MAX_DEPTH).ok_or_else(|| {
+            messages::archive_without_binary(name, dir, &entries(fs, dir, MAX_DEPTH))
+        })?;
+        if let Some(parent) = expected.parent() {
+            fs.create_dir_all(parent)
+                .map_err(|e| messages::create_failed(parent, e))?;
+        }
+        fs.rename(&found, &expected)
+            .map_err(|e| messages::move_into_place_failed(&found, e))?;
+    }
+    if prune {
+        self::prune(fs, dir, MAX_DEPTH);
+    }
+    Ok(())
+}
+
+/// The first file named `name` below `dir`, breadth first so that the shallowest wins.
+fn find(fs: &dyn Fs, dir: &Path, name: &str, depth: usize) -> Option<std::path::PathBuf> {
+    let mut level = vec![dir.to_path_buf()];
+    for _ in 0..=depth {
+        let mut next = Vec::new();
+        for dir in level {
+            let Ok(mut entries) = fs.read_dir(&dir) else {
+                continue;
+            };
+            entries.sort();
+            for entry in entries {
+                let path = dir.join(&entry);
+                if entry == name && fs.is_file(&path) {
+                    return Some(path);
+                }
+                next.push(path);
+            }
+        }
+        level = next;
+    }
+    None
+}
+
+/// The files below `dir`, relative to it and sorted, at most [`MAX_LISTED`] of them.
+fn entries(fs: &dyn Fs, dir: &Path, depth: usize) -> Vec<String> {
+    let mut files = Vec::new();
+    let mut pending = vec![(String::new(), depth)];
+    while let Some((relative, depth)) = pending.pop() {
+        let Ok(names) = fs.read_dir(&dir.join(&relative)) else {
+            continue;
+        };
+        for name in names {
+            let path = if relative.is_empty() {
+                name
+            } else {
+                format!("{relative}/{name}")
+            };
+            if fs.is_file(&dir.join(&path)) {
+                files.push(path);
+            } else if depth > 0 {
+                pending.push((path, depth - 1));
+            }
+        }
+    }
+    files.sort();
+    files.truncate(MAX_LISTED);
+    files
+}
+
+fn prune(fs: &dyn Fs, dir: &Path, depth: usize) {
+    let Ok(entries) = fs.read_dir(dir) else {
+        return;
+    };
+    for entry in entries {
+        let path = dir.join(&entry);
+        if entry == Manifest::FILE_NAME {
+            continue;
+        }
+        if EXTRANEOUS.contains(&entry.as_str()) {
+            let removed = fs.remove_file(&path).or_else(|_| fs.remove_dir_all(&path));
+            if let Err(e) = removed.or_else(ignore_missing) {
+                crate::log::debug(messages::prune_failed(&path, e));
+            }
+        } else if depth > 0 && !fs.is_file(&path) {
+            prune(fs, &path, depth - 1);
+        }
+    }
+}
+
+fn ignore_missing(e: io::Error) -> io::Result<()> {
+    match e.kind() {
+        io::ErrorKind::NotFound => Ok(()),
+        _ => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::Path};
+
+    use super::settle;
+    use crate::{host::StdFs, test_support::TempDir};
+
+    /// A version directory extracted from an archive holding `files`.
+    fn extracted(name: &str, files: &[&str]) -> TempDir {
+        let temp = TempDir::new(name);
+        for file in files {
+            let path = temp.path().join("typos-lsp-0.1.23").join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, file).unwrap();
+        }
+        temp
+    }
+
+    fn listing(root: &Path) -> Vec<String> {
+        let mut files = Vec::new();
+        let mut pending = vec![root.to_path_buf()];
+        while let Some(dir) = pending.pop() {
+            for entry in fs::read_dir(dir).unwrap() {
+                let path = entry.unwrap().path();
+                if path.is_dir() {
+                    pending.push(path);
+                } else {
+                    let relative = path.strip_prefix(root).unwrap();
+                    files.push(relative.to_str().unwrap().replace('\\', "/"));
+                }
+            }
+        }
+        files.sort();
+        files
+    }
+
+    fn settled(temp: &TempDir, binary: &str) -> Vec<String> {
+        let fs = StdFs::new(temp.path());
+        settle(&fs, Path::new("typos-lsp-0.1.23"), Path::new(binary), true).unwrap();
+        listing(temp.path())
+    }
+
+    #[test]
+    fn current_layout() {
+        let temp = extracted("layout-current", &["typos-lsp", ".manifest.json"]);
+        assert_eq!(
+            settled(&temp, "typos-lsp"),
+            [
+                "typos-lsp-0.1.23/.manifest.json",
+                "typos-lsp-0.1.23/typos-lsp"
+            ]
+        );
+
+        let windows = "target/x86_64-pc-windows-msvc/release/typos-lsp.exe";
+        let temp = extracted("layout-windows", &[windows]);
+        assert_eq!(
+            settled(&temp, windows),
+            [format!("typos-lsp-0.1.23/{windows}")]
+        );
+    }
+
+    #[test]
+    fn extra_files_beside_the_binary() {
+        let temp = extracted(
+            "layout-extra",
+            &[
+                "typos-lsp",
+                "README.md",
+                "LICENSE",
+                "completions/typos-lsp.bash",
+                "typos-lsp.1",
+            ],
+        );
+        // Unknown entries are kept, they may be needed by the binary.
+        assert_eq!(
+            settled(&temp, "typos-lsp"),
+            ["typos-lsp-0.1.23/typos-lsp", "typos-lsp-0.1.23/typos-lsp.1"]
+        );
+
+        // Nothing is pruned with cleanups off.
+        let temp = extracted("layout-unpruned", &["typos-lsp", "LICENSE"]);
+        let fs = StdFs::new(temp.path());
+        settle(
+            &fs,
+            Path::new("typos-lsp-0.1.23"),
+            Path::new("typos-lsp"),
+            false,
+        )
+        .unwrap();
+        assert_eq!(
+            listing(temp.path()),
+            ["typos-lsp-0.1.23/LICENSE", "typos-lsp-0.1.23/typos-lsp"]
+        );
+    }
+
+    #[test]
+    fn nested_layout() {
+        let temp = extracted(
+            "layout-nested",
+            &[
+                "typos-lsp-v0.1.23-x86_64-unknown-linux-gnu/typos-lsp",
+                "typos-lsp-v0.1.23-x86_64-unknown-linux-gnu/LICENSE",
+                "typos-lsp-v0.1.23-x86_64-unknown-linux-gnu/doc/typos-lsp.md",
+            ],
+        );
+        assert_eq!(settled(&temp, "typos-lsp"), ["typos-lsp-0.1.23/typos-lsp"]);
+        // Settling again finds the binary in place.
+        assert_eq!(settled(&temp, "typos-lsp"), ["typos-lsp-0.1.23/typos-lsp"]);
+
+        // The shallowest binary wins.
+        let temp = extracted(
+            "layout-shallowest",
+            &["a/b/typos-lsp", "dist/typos-lsp", "z/typos-lsp"],
+        );
+        assert_eq!(
+            settled(&temp, "typos-lsp"),
+            [
+                "typos-lsp-0.1.23/a/b/typos-lsp",
+                "typos-lsp-0.1.23/typos-lsp",
+                "typos-lsp-0.1.23/z/typos-lsp"
+            ]
+        );
+
+        let windows = "target/x86_64-pc-windows-msvc/release/typos-lsp.exe";
+        let temp = extracted("layout-windows-flat", &["typos-lsp.exe", "README.md"]);
+        assert_eq!(
+            settled(&temp, windows),
+            [format!("typos-lsp-0.1.23/{windows}")]
+        );
+    }
+
+    #[test]
+    fn missing_binary() {
+        let temp = extracted("layout-missing", &["README.md", "bin/typos"]);
+        let fs = StdFs::new(temp.path());
+        assert_eq!(
+            settle(
+                &fs,
+                Path::new("typos-lsp-0.1.23"),
+                Path::new("typos-lsp"),
+                true
+            ),
+            Err(
+                "the typos-lsp archive has no typos-lsp binary, extracted into \
+                 typos-lsp-0.1.23 which holds [\"README.md\", \"bin/typos\"]"
+                    .to_string()
+            )
+        );
+    }
+}
@@ -0,0 +1,159 @@
+//! Telling typos-lsp apart from the typos CLI it is often mistaken for in `binary.path`.
+//!
+//! The typos CLI checks the files named on its command line and exits, so started as a
+//! language server it stops right away and Zed only reports a generic failure. Both
+//! binaries say which one they are when run with `--version` or `--help`. Running them
+//! needs processes, which the extension API the extension is built against does not
+//! allow yet (see [`crate::capabilities`]): until it does, this is reported as not
+//! available, and only a binary named like the CLI is reported, as a wrapper script may
+//! be named anything.
+
+use crate::log;
+
+/// Running `binary.path`, as reported when processes cannot be run.
+pub(crate) const FEATURE: &str = "telling typos-lsp from the typos CLI by running `binary.path`";
+
+/// What happens when processes cannot be run.
+pub(crate) const WITHOUT_PROCESSES: &str =
+    "only a `binary.path` named like the typos CLI is reported";
+
+/// Runs the binary with an argument, returning its output if it ran.
+pub(crate) type Probe<'a> = &'a dyn Fn(&str) -> Option<String>;
+
+/// What a binary identifies as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Identity {
+    TyposLsp,
+    TyposCli,
+    Unknown,
+}
+
+/// What the `output` of a binary run with `--version` or `--help` identifies it as.
+pub(crate) fn identify(output: &str) -> Identity {
+    let output = output.to_ascii_lowercase();
+    if output.contains("typos-lsp") {
+        return Identity::TyposLsp;
+    }
+    let cli = output.split_whitespace().next() == Some("typos-cli")
+        || output.contains("source code spelling correction")
+        || ["usage: typos ", "usage: typos.exe "]
+            .iter()
+            .any(|usage| output.contains(usage));
+    if cli {
+        Identity::TyposCli
+    } else {
+        Identity::Unknown
+    }
+}
+
+/// Check that the binary at `path`, set in `binary.path`, is not the typos CLI, from
+/// the output `probe` gets running it with an argument when processes can be run, or
+/// else from its name.
+pub(crate) fn check(path: &str, probe: Option<Probe<'_>>) -> Result<(), String> {
+    let Some(probe) = probe else {
+        if named_like_cli(path) {
+            log::warn(named_like_cli_warning(path));
+        }
+        return Ok(());
+    };
+    for argument in ["--version", "--help"] {
+        match probe(argument).as_deref().map(identify) {
+            Some(Identity::TyposLsp) => return Ok(()),
+            Some(Identity::TyposCli) => return Err(mistaken_cli(path)),
+            Some(Identity::Unknown) | None => {}
+        }
+    }
+    // A wrapper may not answer either, it gets the benefit of the doubt.
+    Ok(())
+}
+
+/// Whether the binary at `path` bears the name of the typos CLI.
+fn named_like_cli(path: &str) -> bool {
+    // Either separator, as the path may be the one of a remote host.
+    let name = path
+        .rsplit(['/', '\\'])
+        .next()
+        .unwrap_or(path)
+        .to_ascii_lowercase();
+    name == "typos" || name == "typos.exe"
+}
+
+/// The binary at `path` identified as the typos CLI.
+pub(crate) fn mistaken_cli(path: &str) -> String {
+    format!(
+        "`binary.path` is set to {path}, which is the typos CLI rather than typos-lsp: the \
+         `typos` CLI checks files from the command line and exits, while `typos-lsp` is the \
+         language server Zed starts. Set `binary.path` to a typos-lsp binary, or remove it \
+         to let the extension install typos-lsp"
+    )
+}
+
+fn named_like_cli_warning(path: &str) -> String {
+    format!(
+        "`binary.path` is set to {path}, which is named like the typos CLI: if typos-lsp \
+         stops right after starting, set it to a `typos-lsp` binary instead, as the `typos` \
+         CLI is not a language server"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check, identify, mistaken_cli, named_like_cli, Identity};
+
+    const LSP_VERSION: &str = "typos-lsp 0.1.27\n";
+    const LSP_HELP: &str = "Usage: typos-lsp [OPTIONS]\n\nOptions:\n  -l, --log-level \
+                            <LOG_LEVEL>  [default: warn]\n  -h, --help  Print help\n  -V, \
+                            --version  Print version\n";
+    const CLI_VERSION: &str = "typos-cli 1.23.6\n";
+    const CLI_HELP: &str = "Source Code Spelling Correction\n\nUsage: typos [OPTIONS] \
+                            [PATH]...\n\nArguments:\n  [PATH]...  Paths to check with `-` for \
+                            stdin [default: .]\n";
+    const CLI_HELP_WINDOWS: &str = "Usage: typos.exe [OPTIONS] [PATH]...\n";
+
+    #[test]
+    fn outputs() {
+        for output in [LSP_VERSION, LSP_HELP] {
+            assert_eq!(identify(output), Identity::TyposLsp, "{output}");
+        }
+        for output in [CLI_VERSION, CLI_HELP, CLI_HELP_WINDOWS] {
+            assert_eq!(identify(output), Identity::TyposCli, "{output}");
+        }
+        for output in ["", "wrapper 1.0\n", "Usage: typos-wrapper\n"] {
+            assert_eq!(identify(output), Identity::Unknown, "{output}");
+        }
+    }
+
+    #[test]
+    fn probed_binaries() {
+        let outputs = |version: &'static str, help: &'static str| {
+            move |argument: &str| match argument {
+                "--version" => Some(version.to_string()),
+                _ => Some(help.to_string()),
+            }
+        };
+        let lsp = outputs(LSP_VERSION, LSP_HELP);
+        assert_eq!(check("/usr/local/bin/typos", Some(&lsp)), Ok(()));
+        let cli = outputs(CLI_VERSION, CLI_HELP);
+        assert_eq!(
+            check("/usr/bin/typos", Some(&cli)),
+            Err(mistaken_cli("/usr/bin/typos"))
+        );
+        // Only the help of an older CLI tells.
+        let older_cli = outputs("", CLI_HELP);
+        assert!(check("typos", Some(&older_cli)).is_err());
+        let silent = |_: &str| None;
+        assert_eq!(check("/opt/wrapper", Some(&silent)), Ok(()));
+    }
+
+    #[test]
+    fn unprobed_binaries() {
+        for path in ["/usr/bin/typos", "typos", "C:\\Tools\\TYPOS.EXE"] {
+            assert!(named_like_cli(path), "{path}");
+            assert_eq!(check(path, None), Ok(()), "{path}");
+        }
+        for path in ["/usr/bin/typos-lsp", "typos-lsp.exe", "/opt/typos/wrapper"] {
+            assert!(!named_like_cli(path), "{path}");
+        }
+        assert!(mistaken_cli("/usr/bin/typos").contains("rather than typos-lsp"));
+    }
+}
@@ -0,0 +1,110 @@
+//! Running installed binaries with `--version` before handing them out.
+//!
+//! A binary can exist in the work directory and still be unusable, e.g. when an
+//! extraction went wrong, and a server that crashes at once only shows up as a generic
+//! failure in Zed. Before a binary the extension installed is handed out for the first
+//! time in a session, it is run with `--version`: it must exit successfully and report
+//! typos-lsp in the expected version. An installation failing the probe is removed and
+//! downloaded again.
+//!
+//! The probe needs to run processes, which the extension API the extension is built
+//! against does not allow yet (see [`crate::capabilities`]): until it does, the probe is
+//! reported as not available, and binaries are only checked without being run, when
+//! they are installed.
+
+use crate::{
+    binary_identity::{self, Identity},
+    host::Runner,
+};
+
+/// The probe, as reported when processes cannot be run.
+pub(crate) const FEATURE: &str = "probing installed typos-lsp binaries with `--version`";
+
+/// What happens when processes cannot be run.
+pub(crate) const WITHOUT_PROCESSES: &str =
+    "they are started without being run first, and one that cannot run only shows up as \
+     a failed server start";
+
+/// What probing a binary found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Probed {
+    /// The binary reports the expected version.
+    Passed,
+    /// Why the binary cannot be used.
+    Failed(String),
+}
+
+/// Run the binary at `path`, relative to the work directory, of the release `tag`, with
+/// a `runner` that can run processes.
+pub(crate) fn probe(runner: &dyn Runner, path: &str, tag: &str) -> Probed {
+    let output = match runner.run(path, &["--version"]) {
+        Err(e) => return Probed::Failed(format!("it failed to run: {e}")),
+        Ok(output) => output,
+    };
+    let reported = output.lines().next().unwrap_or_default().trim();
+    if binary_identity::identify(&output) != Identity::TyposLsp {
+        return Probed::Failed(format!(
+            "it does not identify as typos-lsp, reporting {reported:?}"
+        ));
+    }
+    let expected = tag.trim_start_matches('v');
+    let matches = reported
+        .split_whitespace()
+        .any(|word| word.trim_start_matches('v') == expected);
+    if matches {
+        Probed::Passed
+    } else {
+        Probed::Failed(format!(
+            "it reports {reported:?} rather than version {expected}"
+        ))
+    }
+}
+
+/// Why the installed binary at `path` is installed again.
+pub(crate) fn reinstalling(path: &str, reason: &str) -> String {
+    format!("typos-lsp {path} failed its `--version` probe, installing it again: {reason}")
+}
+
+/// Why the binary at `path`, just downloaded, cannot be used.
+pub(crate) fn downloaded_failed(path: &str, reason: &str) -> String {
+    format!(
+        "typos-lsp {path} failed its `--version` probe right after being downloaded: \
+         {reason}. The release may not run on this system; set `binary.path` to a \
+         typos-lsp built for it, or pin another release with `settings.version`"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::{probe, Probed};
+    use crate::test_support::{FakeRunner, TempDir};
+
+    #[test]
+    fn probes() {
+        let temp = TempDir::new("binary-probe");
+        let runner = FakeRunner::new(temp.path());
+        let binary = |content: &str| {
+            fs::write(temp.path().join("typos-lsp"), content).unwrap();
+            probe(&runner, "typos-lsp", "v0.1.23")
+        };
+        assert_eq!(binary("typos-lsp 0.1.23\n"), Probed::Passed);
+        assert_eq!(binary("typos-lsp v0.1.23"), Probed::Passed);
+        assert_eq!(
+            binary(""),
+            Probed::Failed("it failed to run: exit status: 101".to_string())
+        );
+        assert_eq!(
+            binary("typos-cli 1.16.0\n"),
+            Probed::Failed(
+                "it does not identify as typos-lsp, reporting \"typos-cli 1.16.0\"".to_string()
+            )
+        );
+        assert_eq!(
+            binary("typos-lsp 0.1.2"),
+            Probed::Failed("it reports \"typos-lsp 0.1.2\" rather than version 0.1.23".to_string())
+        );
+        assert_eq!(runner.runs().len(), 5);
+    }
+}
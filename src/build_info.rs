@@ -0,0 +1,44 @@
+//! The version of the extension itself, as opposed to the one of typos-lsp.
+//!
+//! Bug reports and the artifacts the extension leaves behind are traced back to the
+//! extension that produced them through it: the crate version, followed by the
+//! `git describe` output of the source tree when built from a git checkout (see
+//! `build.rs`), unless that is the tag of the same version.
+
+/// The version of the crate.
+pub(crate) const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// The `git describe` output of the source tree the extension was built from, if any.
+const GIT_DESCRIBE: Option<&str> = option_env!("TYPOS_EXTENSION_GIT_DESCRIBE");
+
+/// The version of the extension, with its source revision when known.
+pub(crate) fn describe() -> String {
+    describe_with(VERSION, GIT_DESCRIBE)
+}
+
+fn describe_with(version: &str, git_describe: Option<&str>) -> String {
+    match git_describe.filter(|describe| {
+        !describe.is_empty() && describe.strip_prefix('v').unwrap_or(describe) != version
+    }) {
+        Some(describe) => format!("{version} ({describe})"),
+        None => version.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{describe, describe_with, VERSION};
+
+    #[test]
+    fn descriptions() {
+        assert_eq!(describe_with("0.0.4", None), "0.0.4");
+        assert_eq!(describe_with("0.0.4", Some("")), "0.0.4");
+        assert_eq!(describe_with("0.0.4", Some("v0.0.4")), "0.0.4");
+        assert_eq!(
+            describe_with("0.0.4", Some("v0.0.4-12-g36ed7a2-dirty")),
+            "0.0.4 (v0.0.4-12-g36ed7a2-dirty)"
+        );
+        assert_eq!(describe_with("0.0.4", Some("36ed7a2")), "0.0.4 (36ed7a2)");
+        assert!(describe().starts_with(VERSION), "{}", describe());
+    }
+}
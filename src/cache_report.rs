@@ -0,0 +1,313 @@
+//! What the extension keeps in its work directory, for disk-usage investigations.
+//!
+//! With `cache_report`, the installed typos-lsp versions are logged with their size and
+//! when a server was last started from them, along with the generated configurations,
+//! the state files, and the directories the next cleanup would remove.
+
+use std::{collections::BTreeSet, fmt, path::Path};
+
+use crate::{build_info, disk_space, host::Fs, manifest::Manifest, typos_config, version::Version};
+
+/// An installed typos-lsp version.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Installation {
+    pub dir: String,
+    pub size: u64,
+    /// When a server was last started from it, in seconds since the Unix epoch.
+    pub last_used: Option<u64>,
+    /// Whether a server of this session runs it.
+    pub in_use: bool,
+    /// The version of the extension that installed it, when known.
+    pub installed_by: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct CacheReport {
+    /// The version of the extension, see [`crate::build_info`].
+    pub extension: String,
+    /// From the newest version.
+    pub installations: Vec<Installation>,
+    /// The size of the custom configurations written for typos-lsp.
+    pub generated_configs: u64,
+    /// The size of the files recording the state of the extension, such as pending
+    /// updates or the files written into worktrees.
+    pub state: u64,
+    /// The directories the next cleanup removes, in the order it does.
+    pub next_removals: Vec<String>,
+    /// The current time, in seconds since the Unix epoch.
+    pub now: u64,
+}
+
+impl CacheReport {
+    /// Take stock of the work directory `fs`, with the versions `in_use` by servers of
+    /// this session, the `state_entries` of the extension and the directories whose
+    /// removal is `deferred` to the next session.
+    pub(crate) fn collect(
+        fs: &dyn Fs,
+        in_use: &BTreeSet<Version>,
+        state_entries: &[&str],
+        deferred: &[String],
+        now: u64,
+    ) -> Self {
+        let size = |name: &str| fs.size(Path::new(name)).unwrap_or(0);
+        let mut versions: Vec<(Version, String)> = fs
+            .read_dir(Path::new("."))
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|name| Some((Version::from_dir_name(&name)?, name)))
+            .collect();
+        versions.sort();
+        let installations: Vec<Installation> = versions
+            .into_iter()
+            .rev()
+            .map(|(version, dir)| {
+                let manifest = Manifest::read(fs, Path::new(&dir));
+                Installation {
+                    size: size(&dir),
+                    last_used: manifest.as_ref().and_then(|manifest| manifest.last_used),
+                    in_use: in_use.contains(&version),
+                    installed_by: manifest.and_then(|manifest| manifest.extension_version),
+                    dir,
+                }
+            })
+            .collect();
+
+        // Removals deferred by this session come first, then the versions no server
+        // runs, which installing another release removes, least recently used first.
+        let mut idle: Vec<&Installation> = installations
+            .iter()
+            .filter(|installation| !installation.in_use && !deferred.contains(&installation.dir))
+            .collect();
+        idle.sort_by_key(|installation| installation.last_used);
+        let next_removals = deferred
+            .iter()
+            .cloned()
+            .chain(
+                idle.into_iter()
+                    .map(|installation| installation.dir.clone()),
+            )
+            .collect();
+
+        Self {
+            extension: build_info::describe(),
+            generated_configs: size(typos_config::GENERATED_DIR),
+            state: state_entries
+                .iter()
+                .filter(|entry| **entry != typos_config::GENERATED_DIR)
+                .map(|entry| size(entry))
+                .sum(),
+            installations,
+            next_removals,
+            now,
+        }
+    }
+
+    /// How long ago `time` was, roughly.
+    fn ago(&self, time: u64) -> String {
+        let elapsed = self.now.saturating_sub(time);
+        let (count, unit) = match elapsed {
+            0..=59 => return "just now".to_string(),
+            60..=3599 => (elapsed / 60, "minute"),
+            3600..=86_399 => (elapsed / 3600, "hour"),
+            _ => (elapsed / 86_400, "day"),
+        };
+        format!("{count} {unit}{} ago", if count == 1 { "" } else { "s" })
+    }
+}
+
+impl fmt::Display for CacheReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let total = self
+            .installations
+            .iter()
+            .map(|installation| installation.size)
+            .sum::<u64>()
+            + self.generated_configs
+            + self.state;
+        write!(
+            f,
+            "work directory of the typos extension {}: {} in total",
+            self.extension,
+            disk_space::format_size(total)
+        )?;
+        for installation in &self.installations {
+            write!(
+                f,
+                "\n  {}: {}, last used {}{}",
+                installation.dir,
+                disk_space::format_size(installation.size),
+                installation
+                    .last_used
+                    .map_or_else(|| "unknown".to_string(), |time| self.ago(time)),
+                if installation.in_use { ", in use" } else { "" },
+            )?;
+            if let Some(extension) = &installation.installed_by {
+                write!(f, ", installed by the extension {extension}")?;
+            }
+        }
+        write!(
+            f,
+            "\n  {}: {}\n  state files: {}\n  next cleanup removes: {}",
+            typos_config::GENERATED_DIR,
+            disk_space::format_size(self.generated_configs),
+            disk_space::format_size(self.state),
+            if self.next_removals.is_empty() {
+                "nothing".to_string()
+            } else {
+                self.next_removals.join(", ")
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeSet, fs, path::Path};
+
+    use super::{CacheReport, Installation};
+    use crate::{
+        host::StdFs, manifest::Manifest, test_support::TempDir, typos_config, version::Version,
+    };
+
+    const NOW: u64 = 1_700_000_000;
+    const DAY: u64 = 86_400;
+
+    fn install(root: &Path, dir: &str, binary_size: usize, last_used: Option<u64>) {
+        fs::create_dir_all(root.join(dir)).unwrap();
+        fs::write(root.join(dir).join("typos-lsp"), "x".repeat(binary_size)).unwrap();
+        Manifest {
+            version: format!("v{}", dir.trim_start_matches("typos-lsp-")),
+            asset: "asset.tar.gz".to_string(),
+            variant: None,
+            sha256: None,
+            worktree: None,
+            last_used,
+            failed_starts: 0,
+            extension_version: None,
+            platform: None,
+        }
+        .write(&StdFs::new(root), Path::new(dir))
+        .unwrap();
+    }
+
+    fn manifest_size(root: &Path, dir: &str) -> u64 {
+        fs::metadata(root.join(dir).join(Manifest::FILE_NAME))
+            .unwrap()
+            .len()
+    }
+
+    #[test]
+    fn synthetic_layout() {
+        let temp = TempDir::new("cache-report");
+        let root = temp.path();
+        install(root, "typos-lsp-0.1.21", 1000, None);
+        install(root, "typos-lsp-0.1.22", 2000, Some(NOW - 3 * DAY));
+        install(root, "typos-lsp-0.1.23", 3000, Some(NOW - 90));
+        install(root, "typos-lsp-0.1.20", 500, Some(NOW - 40 * DAY));
+        fs::create_dir_all(root.join(typos_config::GENERATED_DIR)).unwrap();
+        fs::write(
+            root.join(typos_config::GENERATED_DIR).join("a.toml"),
+            [0; 300],
+        )
+        .unwrap();
+        fs::write(
+            root.join(typos_config::GENERATED_DIR).join("b.toml"),
+            [0; 200],
+        )
+        .unwrap();
+        fs::write(root.join("pending-update.txt"), "1700000000\n").unwrap();
+        fs::write(root.join("deferred-cleanup.txt"), "typos-lsp-0.1.20\n").unwrap();
+        fs::create_dir(root.join("unrelated")).unwrap();
+
+        let in_use = BTreeSet::from([Version::parse("v0.1.23").unwrap()]);
+        let state_entries = [
+            "deferred-cleanup.txt",
+            "pending-update.txt",
+            "metrics.tsv",
+            typos_config::GENERATED_DIR,
+        ];
+        let report = CacheReport::collect(
+            &StdFs::new(root),
+            &in_use,
+            &state_entries,
+            &["typos-lsp-0.1.20".to_string()],
+            NOW,
+        );
+        let installation = |dir: &str, binary: u64, last_used, in_use| Installation {
+            dir: dir.to_string(),
+            size: binary + manifest_size(root, dir),
+            last_used,
+            in_use,
+            installed_by: None,
+        };
+        assert_eq!(
+            report.installations,
+            [
+                installation("typos-lsp-0.1.23", 3000, Some(NOW - 90), true),
+                installation("typos-lsp-0.1.22", 2000, Some(NOW - 3 * DAY), false),
+                installation("typos-lsp-0.1.21", 1000, None, false),
+                installation("typos-lsp-0.1.20", 500, Some(NOW - 40 * DAY), false),
+            ]
+        );
+        assert_eq!(report.generated_configs, 500);
+        assert_eq!(report.state, 11 + 17);
+        assert_eq!(
+            report.next_removals,
+            ["typos-lsp-0.1.20", "typos-lsp-0.1.21", "typos-lsp-0.1.22"]
+        );
+    }
+
+    #[test]
+    fn rendering() {
+        let installation = |dir: &str, size, last_used, in_use| Installation {
+            dir: dir.to_string(),
+            size,
+            last_used,
+            in_use,
+            installed_by: None,
+        };
+        let report = CacheReport {
+            extension: "0.0.4".to_string(),
+            installations: vec![
+                Installation {
+                    installed_by: Some("0.0.4 (v0.0.4-1-g36ed7a2)".to_string()),
+                    ..installation("typos-lsp-0.1.23", 12 * 1024 * 1024, Some(NOW - 90), true)
+                },
+                installation("typos-lsp-0.1.22", 11 * 1024 * 1024, Some(NOW - DAY), false),
+                installation("typos-lsp-0.1.21", 10 * 1024 * 1024, None, false),
+            ],
+            generated_configs: 2048,
+            state: 100,
+            next_removals: vec![
+                "typos-lsp-0.1.21".to_string(),
+                "typos-lsp-0.1.22".to_string(),
+            ],
+            now: NOW,
+        };
+        assert_eq!(
+            report.to_string(),
+            "work directory of the typos extension 0.0.4: 33.0 MiB in total\n  \
+             typos-lsp-0.1.23: 12.0 MiB, last used 1 minute ago, in use, installed by the \
+             extension 0.0.4 (v0.0.4-1-g36ed7a2)\n  \
+             typos-lsp-0.1.22: 11.0 MiB, last used 1 day ago\n  \
+             typos-lsp-0.1.21: 10.0 MiB, last used unknown\n  \
+             generated-config: 2.0 KiB\n  \
+             state files: 100 B\n  \
+             next cleanup removes: typos-lsp-0.1.21, typos-lsp-0.1.22"
+        );
+
+        let empty = CacheReport {
+            extension: "0.0.4".to_string(),
+            installations: Vec::new(),
+            generated_configs: 0,
+            state: 0,
+            next_removals: Vec::new(),
+            now: NOW,
+        };
+        assert_eq!(
+            empty.to_string(),
+            "work directory of the typos extension 0.0.4: 0 B in total\n  generated-config: 0 B\n  state files: 0 B\n  \
+             next cleanup removes: nothing"
+        );
+    }
+}
@@ -0,0 +1,277 @@
+//! Extension API capabilities the extension relies on, and what to do without them.
+//!
+//! Zed refuses to load an extension built against a newer extension API than it
+//! supports, so gating on the API version the extension is built with is enough to
+//! know what the host provides. Every optional API usage goes through this table so
+//! that a missing capability degrades in one place, with one message.
+
+use std::fmt;
+
+use crate::log;
+
+/// The `zed_extension_api` version the extension is built against.
+pub(crate) const API_VERSION: ApiVersion = ApiVersion(0, 1, 0);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct ApiVersion(pub u32, pub u32, pub u32);
+
+impl fmt::Display for ApiVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.0, self.1, self.2)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Capability {
+    /// Looking up a GitHub release by its tag, needed to pin a version.
+    ReleaseByTag,
+    /// Reading the worktree shell environment.
+    ShellEnv,
+    /// Setting the executable bit of downloaded files.
+    MakeFileExecutable,
+    /// Fetching URLs, needed to look releases up on a mirror.
+    HttpClient,
+    /// Spawning processes, needed to probe a binary with `--version`.
+    ProcessSpawn,
+    /// Installing the server on the host of a remote worktree.
+    RemoteInstall,
+    /// Resolving the symlinks of a path, which WASI does not provide.
+    ResolveSymlinks,
+}
+
+struct CapabilityInfo {
+    capability: Capability,
+    description: &'static str,
+    /// The first API version providing it, `None` when no version does yet.
+    since: Option<ApiVersion>,
+    /// What the extension does instead.
+    fallback: &'static str,
+}
+
+const CAPABILITIES: &[CapabilityInfo] = &[
+    CapabilityInfo {
+        capability: Capability::ReleaseByTag,
+        description: "looking up a release by tag",
+        since: Some(ApiVersion(0, 1, 0)),
+        fallback: "the latest release is used",
+    },
+    CapabilityInfo {
+        capability: Capability::ShellEnv,
+        description: "reading the worktree environment",
+        since: Some(ApiVersion(0, 0, 1)),
+        fallback: "the worktree is assumed to be local",
+    },
+    CapabilityInfo {
+        capability: Capability::MakeFileExecutable,
+        description: "marking files as executable",
+        since: Some(ApiVersion(0, 0, 4)),
+        fallback: "the archive permissions are kept",
+    },
+    CapabilityInfo {
+        capability: Capability::HttpClient,
+        description: "fetching URLs",
+        since: Some(ApiVersion(0, 1, 0)),
+        fallback: "releases are looked up on GitHub",
+    },
+    CapabilityInfo {
+        capability: Capability::ProcessSpawn,
+        description: "running processes",
+        since: Some(ApiVersion(0, 2, 0)),
+        fallback: "binaries are validated without being run",
+    },
+    CapabilityInfo {
+        capability: Capability::RemoteInstall,
+        description: "installing on a remote host",
+        since: None,
+        fallback: "typos-lsp must be installed on the remote host",
+    },
+    CapabilityInfo {
+        capability: Capability::ResolveSymlinks,
+        description: "resolving symlinks",
+        since: None,
+        fallback: "paths are compared as spelled, once `.` and `..` segments are resolved",
+    },
+];
+
+fn info(capability: Capability) -> &'static CapabilityInfo {
+    CAPABILITIES
+        .iter()
+        .find(|info| info.capability == capability)
+        .expect("every capability is listed in CAPABILITIES")
+}
+
+fn available_in(capability: Capability, version: ApiVersion) -> bool {
+    info(capability)
+        .since
+        .map_or(false, |since| since <= version)
+}
+
+/// Whether the capability can be used.
+pub(crate) fn available(capability: Capability) -> bool {
+    available_in(capability, API_VERSION)
+}
+
+/// Whether the capability can be used, logging the fallback once when it cannot.
+pub(crate) fn degrade(capability: Capability) -> bool {
+    let available = available(capability);
+    if !available {
+        let info = info(capability);
+        log::once(
+            format!("capability-{capability:?}"),
+            log::Level::Info,
+            format!("{} is not supported, {}", info.description, info.fallback),
+        );
+    }
+    available
+}
+
+/// Fail with a single clear message when `setting` needs a missing capability.
+pub(crate) fn require(capability: Capability, setting: &str) -> Result<(), String> {
+    require_in(capability, setting, API_VERSION)
+}
+
+fn require_in(capability: Capability, setting: &str, version: ApiVersion) -> Result<(), String> {
+    if available_in(capability, version) {
+        return Ok(());
+    }
+    let info = info(capability);
+    Err(match info.since {
+        Some(since) => format!(
+            "`{setting}` requires {description}, available from the Zed extension API \
+             {since} while this extension is built against {version}: update the extension",
+            description = info.description,
+        ),
+        None => format!(
+            "`{setting}` requires {description}, which no Zed extension API supports yet",
+            description = info.description,
+        ),
+    })
+}
+
+/// Report once, as a warning, that `feature` is not available in this Zed as it needs
+/// the missing `capability`, and what the extension does `instead`.
+pub(crate) fn report_unavailable(capability: Capability, feature: &str, instead: &str) {
+    log::once(
+        format!("unavailable-{feature}"),
+        log::Level::Warn,
+        unavailable_in(capability, feature, instead, API_VERSION),
+    );
+}
+
+fn unavailable_in(
+    capability: Capability,
+    feature: &str,
+    instead: &str,
+    version: ApiVersion,
+) -> String {
+    let info = info(capability);
+    let requirement = match info.since {
+        Some(since) => format!(
+            "{description}, available from the Zed extension API {since} while this \
+             extension is built against {version}",
+            description = info.description,
+        ),
+        None => format!(
+            "{description}, which no Zed extension API supports yet",
+            description = info.description,
+        ),
+    };
+    format!("{feature} is not available in this Zed: it requires {requirement}; {instead}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{available_in, require_in, unavailable_in, ApiVersion, Capability, CAPABILITIES};
+
+    #[test]
+    fn every_capability_is_listed_once() {
+        for capability in [
+            Capability::ReleaseByTag,
+            Capability::ShellEnv,
+            Capability::MakeFileExecutable,
+            Capability::HttpClient,
+            Capability::ProcessSpawn,
+            Capability::RemoteInstall,
+            Capability::ResolveSymlinks,
+        ] {
+            assert_eq!(
+                CAPABILITIES
+                    .iter()
+                    .filter(|info| info.capability == capability)
+                    .count(),
+                1,
+                "{capability:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn version_gating() {
+        assert!(available_in(Capability::ReleaseByTag, ApiVersion(0, 1, 0)));
+        assert!(!available_in(Capability::ReleaseByTag, ApiVersion(0, 0, 6)));
+        assert!(!available_in(Capability::ProcessSpawn, ApiVersion(0, 1, 0)));
+        assert!(available_in(Capability::ProcessSpawn, ApiVersion(0, 2, 0)));
+        assert!(!available_in(
+            Capability::RemoteInstall,
+            ApiVersion(99, 0, 0)
+        ));
+    }
+
+    #[test]
+    fn require_messages() {
+        assert_eq!(
+            require_in(
+                Capability::ReleaseByTag,
+                "sync_with_cli",
+                ApiVersion(0, 1, 0)
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            require_in(
+                Capability::ReleaseByTag,
+                "sync_with_cli",
+                ApiVersion(0, 0, 6)
+            ),
+            Err(
+                "`sync_with_cli` requires looking up a release by tag, available from the \
+                 Zed extension API 0.1.0 while this extension is built against 0.0.6: \
+                 update the extension"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            require_in(Capability::RemoteInstall, "remote", ApiVersion(0, 1, 0)),
+            Err(
+                "`remote` requires installing on a remote host, which no Zed extension API \
+                 supports yet"
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn unavailable_messages() {
+        assert_eq!(
+            unavailable_in(
+                Capability::ProcessSpawn,
+                "probing binaries",
+                "they are used unprobed",
+                ApiVersion(0, 1, 0)
+            ),
+            "probing binaries is not available in this Zed: it requires running processes, \
+             available from the Zed extension API 0.2.0 while this extension is built \
+             against 0.1.0; they are used unprobed"
+        );
+        assert_eq!(
+            unavailable_in(
+                Capability::RemoteInstall,
+                "installing remotely",
+                "install it yourself",
+                ApiVersion(0, 1, 0)
+            ),
+            "installing remotely is not available in this Zed: it requires installing on a \
+             remote host, which no Zed extension API supports yet; install it yourself"
+        );
+    }
+}
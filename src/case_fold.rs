@@ -0,0 +1,75 @@
+//! Telling words apart the way typos does, regardless of their case.
+//!
+//! typos looks the keys of `extend-words` and `extend-identifiers` up caselessly, with
+//! Unicode case folding, so `Größe` and `GRÖSSE` are the same word to it, and only one
+//! of them should be configured. The standard library has no case folding: mapping a
+//! word to lower, upper then lower case comes close, as these mappings are full ones,
+//! changing the length of words like `ß` into `ss` and merging the several lower case
+//! forms of `σ` or `s`. Unlike case folding, it also takes the dotless `ı` for an `i`.
+
+/// The form of `word` that its case variants share.
+pub(crate) fn fold(word: &str) -> String {
+    word.to_lowercase().to_uppercase().to_lowercase()
+}
+
+/// Whether typos takes `a` and `b` for the same word.
+pub(crate) fn same_word(a: &str, b: &str) -> bool {
+    a == b || fold(a) == fold(b)
+}
+
+/// Whether the keys of `table` name words typos looks up caselessly.
+pub(crate) fn is_word_table(table: &str) -> bool {
+    table.ends_with(".extend-words") || table.ends_with(".extend-identifiers")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{fold, is_word_table, same_word};
+
+    #[test]
+    fn case_variants() {
+        for (a, b) in [
+            ("naïve", "NAÏVE"),
+            ("Größe", "GRÖSSE"),
+            ("Straße", "strasse"),
+            ("ΣΊΣΥΦΟΣ", "σίσυφος"),
+            ("σίσυφος", "σίσυφοσ"),
+            ("ſtate", "STATE"),
+            ("ǅemal", "ǆemal"),
+            ("\u{212a}elvin", "kelvin"),
+            ("𐐀𐐨", "𐐨𐐨"),
+        ] {
+            assert!(same_word(a, b), "{a} {b}");
+        }
+        for (a, b) in [
+            ("naïve", "naive"),
+            ("Größe", "Grose"),
+            ("שלום", "שלוס"),
+            ("𝒜lpha", "alpha"),
+        ] {
+            assert!(!same_word(a, b), "{a} {b}");
+        }
+    }
+
+    #[test]
+    fn length_changes() {
+        assert_eq!(fold("ß"), "ss");
+        assert_eq!(fold("ẞ"), "ss");
+        assert_eq!(fold("İstanbul"), "i\u{307}stanbul");
+        assert_eq!(fold("ŉ"), "\u{2bc}n");
+        assert_eq!(fold("ﬁle"), "file");
+        // Combining marks are kept, and not composed with the letter they follow.
+        assert_eq!(fold("NAI\u{308}VE"), "nai\u{308}ve");
+        assert!(!same_word("nai\u{308}ve", "naïve"));
+        // Scripts without case are left as they are.
+        assert_eq!(fold("مرحبا"), "مرحبا");
+    }
+
+    #[test]
+    fn word_tables() {
+        assert!(is_word_table("default.extend-words"));
+        assert!(is_word_table("type.rust.extend-identifiers"));
+        assert!(!is_word_table("default"));
+        assert!(!is_word_table("files.extend-exclude"));
+    }
+}
@@ -0,0 +1,239 @@
+//! Keeping typos-lsp in lockstep with the typos CLI version a project pins for CI.
+//!
+//! typos-lsp embeds the typos crate, so the dictionary it checks against is decided by
+//! the typos version it was built with. A project pinning the CLI therefore needs the
+//! typos-lsp release built against the same typos version to get the same diagnostics.
+
+use crate::version::Version;
+
+/// typos CLI `major.minor` versions and the latest typos-lsp release built against them.
+///
+/// Patch releases of typos only ship dictionary corrections, so the mapping does not
+/// distinguish them. Each row links the `Cargo.lock` of the typos-lsp release it names,
+/// which records the `typos` version that release embeds: check a row there before
+/// changing it, and add one, with its link, when typos-lsp bumps its typos dependency.
+const KNOWN_PAIRS: &[(&str, &str)] = &[
+    // https://github.com/tekumara/typos-lsp/blob/v0.1.7/Cargo.lock
+    ("1.16", "v0.1.7"),
+    // https://github.com/tekumara/typos-lsp/blob/v0.1.9/Cargo.lock
+    ("1.17", "v0.1.9"),
+    // https://github.com/tekumara/typos-lsp/blob/v0.1.12/Cargo.lock
+    ("1.19", "v0.1.12"),
+    // https://github.com/tekumara/typos-lsp/blob/v0.1.15/Cargo.lock
+    ("1.20", "v0.1.15"),
+    // https://github.com/tekumara/typos-lsp/blob/v0.1.17/Cargo.lock
+    ("1.21", "v0.1.17"),
+    // https://github.com/tekumara/typos-lsp/blob/v0.1.18/Cargo.lock
+    ("1.22", "v0.1.18"),
+    // https://github.com/tekumara/typos-lsp/blob/v0.1.22/Cargo.lock
+    ("1.23", "v0.1.22"),
+    // https://github.com/tekumara/typos-lsp/blob/v0.1.26/Cargo.lock
+    ("1.24", "v0.1.26"),
+    // https://github.com/tekumara/typos-lsp/blob/v0.1.29/Cargo.lock
+    ("1.26", "v0.1.29"),
+    // https://github.com/tekumara/typos-lsp/blob/v0.1.31/Cargo.lock
+    ("1.27", "v0.1.31"),
+    // https://github.com/tekumara/typos-lsp/blob/v0.1.33/Cargo.lock
+    ("1.28", "v0.1.33"),
+];
+
+/// Files a project can pin the typos CLI version in, relative to the worktree root.
+pub(crate) const VERSION_FILE: &str = ".typos-version";
+pub(crate) const CARGO_MANIFEST: &str = "Cargo.toml";
+
+/// The outcome of looking for a typos CLI pin in a worktree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum CliPin {
+    /// The project does not pin the typos CLI.
+    None,
+    /// The pinned CLI version maps to a known typos-lsp release.
+    Mapped {
+        cli_version: String,
+        source: &'static str,
+        lsp_tag: &'static str,
+    },
+    /// The pinned CLI version is not in the mapping table.
+    Unmapped {
+        cli_version: String,
+        source: &'static str,
+    },
+}
+
+impl CliPin {
+    /// Look for a pinned typos CLI version, `read_file` returning the content of a
+    /// worktree-relative file when it exists.
+    pub(crate) fn resolve(read_file: impl Fn(&str) -> Option<String>) -> Self {
+        let found = read_file(VERSION_FILE)
+            .and_then(|content| parse_version_file(&content))
+            .map(|version| (version, VERSION_FILE))
+            .or_else(|| {
+                read_file(CARGO_MANIFEST)
+                    .and_then(|content| parse_cargo_metadata(&content))
+                    .map(|version| (version, CARGO_MANIFEST))
+            });
+        let Some((cli_version, source)) = found else {
+            return Self::None;
+        };
+        match lsp_release_for(&cli_version) {
+            Some(lsp_tag) => Self::Mapped {
+                cli_version,
+                source,
+                lsp_tag,
+            },
+            None => Self::Unmapped {
+                cli_version,
+                source,
+            },
+        }
+    }
+}
+
+/// The typos-lsp release matching a typos CLI version.
+pub(crate) fn lsp_release_for(cli_version: &str) -> Option<&'static str> {
+    let mut parts = cli_version.trim_start_matches('v').split('.');
+    let major_minor = format!("{}.{}", parts.next()?, parts.next()?);
+    KNOWN_PAIRS
+        .iter()
+        .find(|(cli, _)| *cli == major_minor)
+        .map(|(_, lsp)| *lsp)
+}
+
+/// The typos `major.minor` version a typos-lsp release is built against, when the
+/// mapping tells.
+///
+/// Releases between two known ones are built against the later typos version, as the
+/// table lists the last release for each. Releases before the first one are unknown.
+pub(crate) fn cli_release_for(lsp_tag: &str) -> Option<&'static str> {
+    let version = Version::parse(lsp_tag)?;
+    let mut previous: Option<Version> = None;
+    for (cli, lsp) in KNOWN_PAIRS {
+        let lsp = Version::parse(lsp).expect("KNOWN_PAIRS holds valid tags");
+        let covered = match &previous {
+            Some(previous) => *previous < version && version <= lsp,
+            None => version == lsp,
+        };
+        if covered {
+            return Some(cli);
+        }
+        previous = Some(lsp);
+    }
+    None
+}
+
+/// The first non-empty, non-comment line of a `.typos-version` file.
+fn parse_version_file(content: &str) -> Option<String> {
+    content
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or_default().trim())
+        .find(|line| !line.is_empty())
+        .map(|line| line.trim_start_matches('v').to_string())
+}
+
+/// The `version` key of a `[package.metadata.typos]` or `[workspace.metadata.typos]` table.
+///
+/// Only the subset of TOML used for such a table is understood, which is enough to
+/// avoid pulling a TOML parser into the extension.
+fn parse_cargo_metadata(content: &str) -> Option<String> {
+    let mut in_table = false;
+    for line in content.lines() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if line.starts_with('[') {
+            let table = line.trim_matches(|c| c == '[' || c == ']').trim();
+            in_table = table == "package.metadata.typos" || table == "workspace.metadata.typos";
+            continue;
+        }
+        if !in_table {
+            continue;
+        }
+        if let Some((key, value)) = line.split_once('=') {
+            if key.trim() == "version" {
+                let value = value.trim().trim_matches(|c| c == '"' || c == '\'');
+                return Some(value.trim_start_matches('v').to_string());
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        cli_release_for, lsp_release_for, parse_cargo_metadata, parse_version_file, CliPin,
+    };
+
+    #[test]
+    fn version_file() {
+        assert_eq!(parse_version_file("1.23.2\n"), Some("1.23.2".to_string()));
+        assert_eq!(
+            parse_version_file("# pinned for CI\n\n  v1.23.2  # latest\n"),
+            Some("1.23.2".to_string())
+        );
+        assert_eq!(parse_version_file("# nothing\n"), None);
+    }
+
+    #[test]
+    fn cargo_metadata() {
+        let manifest = r#"
+[package]
+name = "proj"
+version = "0.3.0"
+
+[package.metadata.typos]
+version = "1.24.1" # keep in sync with CI
+"#;
+        assert_eq!(parse_cargo_metadata(manifest), Some("1.24.1".to_string()));
+
+        let manifest = "[workspace.metadata.typos]\nversion = 'v1.16.0'\n";
+        assert_eq!(parse_cargo_metadata(manifest), Some("1.16.0".to_string()));
+
+        let manifest =
+            "[package]\nversion = \"0.3.0\"\n[package.metadata.other]\nversion = \"1\"\n";
+        assert_eq!(parse_cargo_metadata(manifest), None);
+    }
+
+    #[test]
+    fn mapping() {
+        assert_eq!(lsp_release_for("1.23.2"), Some("v0.1.22"));
+        assert_eq!(lsp_release_for("v1.16.0"), Some("v0.1.7"));
+        assert_eq!(lsp_release_for("1.2.0"), None);
+        assert_eq!(lsp_release_for("garbage"), None);
+
+        assert_eq!(cli_release_for("v0.1.22"), Some("1.23"));
+        assert_eq!(cli_release_for("v0.1.23"), Some("1.24"));
+        assert_eq!(cli_release_for("0.1.7"), Some("1.16"));
+        assert_eq!(cli_release_for("v0.1.3"), None);
+        assert_eq!(cli_release_for("v0.2.0"), None);
+        assert_eq!(cli_release_for("garbage"), None);
+    }
+
+    #[test]
+    fn resolution_order() {
+        let both = |path: &str| match path {
+            ".typos-version" => Some("1.24.0".to_string()),
+            "Cargo.toml" => Some("[package.metadata.typos]\nversion = \"1.16.0\"".to_string()),
+            _ => None,
+        };
+        assert_eq!(
+            CliPin::resolve(both),
+            CliPin::Mapped {
+                cli_version: "1.24.0".to_string(),
+                source: ".typos-version",
+                lsp_tag: "v0.1.26",
+            }
+        );
+
+        let cargo_only = |path: &str| match path {
+            "Cargo.toml" => Some("[package.metadata.typos]\nversion = \"1.99.0\"".to_string()),
+            _ => None,
+        };
+        assert_eq!(
+            CliPin::resolve(cargo_only),
+            CliPin::Unmapped {
+                cli_version: "1.99.0".to_string(),
+                source: "Cargo.toml",
+            }
+        );
+
+        assert_eq!(CliPin::resolve(|_| None), CliPin::None);
+    }
+}
@@ -0,0 +1,256 @@
+//! Lenient reading of user-controlled settings values.
+//!
+//! Settings are hand-written JSON, so common alternative encodings are accepted when
+//! their meaning is unambiguous. Anything else is reported as an `Err` holding a
+//! warning that names the key, the type found and the type expected.
+
+use std::collections::BTreeMap;
+
+use zed_extension_api::serde_json::Value;
+
+use crate::error::TyposExtensionError;
+
+/// The diagnostic severities typos-lsp accepts, by LSP `DiagnosticSeverity` number.
+pub(crate) const SEVERITIES: [&str; 4] = ["Error", "Warning", "Information", "Hint"];
+
+/// The JSON type of `value`, for warnings.
+pub(crate) fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn invalid(key: &str, found: &Value, expected: &str) -> TyposExtensionError {
+    TyposExtensionError::invalid_setting(
+        key,
+        format_args!("expected {expected}, found {} {found}", type_name(found)),
+    )
+}
+
+/// A boolean, also accepted as the strings `"true"` and `"false"`.
+pub(crate) fn boolean(
+    key: &str,
+    value: Option<&Value>,
+) -> Result<Option<bool>, TyposExtensionError> {
+    match value {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::Bool(value)) => Ok(Some(*value)),
+        Some(Value::String(text)) if text.eq_ignore_ascii_case("true") => Ok(Some(true)),
+        Some(Value::String(text)) if text.eq_ignore_ascii_case("false") => Ok(Some(false)),
+        Some(value) => Err(invalid(key, value, "a boolean")),
+    }
+}
+
+/// A string.
+pub(crate) fn string(
+    key: &str,
+    value: Option<&Value>,
+) -> Result<Option<String>, TyposExtensionError> {
+    match value {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::String(text)) => Ok(Some(text.clone())),
+        Some(value) => Err(invalid(key, value, "a string")),
+    }
+}
+
+/// A list of strings, a single string standing for a list of one.
+pub(crate) fn string_list(
+    key: &str,
+    value: Option<&Value>,
+) -> Result<Option<Vec<String>>, TyposExtensionError> {
+    match value {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::String(text)) => Ok(Some(vec![text.clone()])),
+        Some(Value::Array(items)) => items
+            .iter()
+            .map(|item| match item {
+                Value::String(text) => Ok(text.clone()),
+                item => Err(invalid(key, item, "a list of strings")),
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map(Some),
+        Some(value) => Err(invalid(key, value, "a list of strings")),
+    }
+}
+
+/// An object of strings.
+pub(crate) fn string_map(
+    key: &str,
+    value: Option<&Value>,
+) -> Result<Option<BTreeMap<String, String>>, TyposExtensionError> {
+    let expected = "an object of strings";
+    match value {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::Object(members)) => members
+            .iter()
+            .map(|(name, member)| match member {
+                Value::String(text) => Ok((name.clone(), text.clone())),
+                member => Err(invalid(&format!("{key}.{name}"), member, expected)),
+            })
+            .collect::<Result<BTreeMap<_, _>, _>>()
+            .map(Some),
+        Some(value) => Err(invalid(key, value, expected)),
+    }
+}
+
+/// A non-negative integer, also accepted as a string of digits.
+pub(crate) fn unsigned(
+    key: &str,
+    value: Option<&Value>,
+) -> Result<Option<u64>, TyposExtensionError> {
+    match value {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::Number(number)) if number.is_u64() => Ok(number.as_u64()),
+        Some(Value::String(text)) if text.parse::<u64>().is_ok() => Ok(text.parse().ok()),
+        Some(value) => Err(invalid(key, value, "a non-negative integer")),
+    }
+}
+
+/// A diagnostic severity name as typos-lsp expects it, also accepted as a numeric LSP
+/// severity (1 to 4) or in any case.
+pub(crate) fn severity(
+    key: &str,
+    value: Option<&Value>,
+) -> Result<Option<&'static str>, TyposExtensionError> {
+    let expected = "one of \"Error\", \"Warning\", \"Information\", \"Hint\" or 1 to 4";
+    match value {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::Number(number)) => number
+            .as_u64()
+            .and_then(|number| SEVERITIES.get(usize::try_from(number).ok()?.checked_sub(1)?))
+            .map(|severity| Some(*severity))
+            .ok_or_else(|| invalid(key, &Value::Number(number.clone()), expected)),
+        Some(Value::String(text)) => SEVERITIES
+            .iter()
+            .find(|severity| severity.eq_ignore_ascii_case(text))
+            .map(|severity| Some(*severity))
+            .ok_or_else(|| invalid(key, &Value::String(text.clone()), expected)),
+        Some(value) => Err(invalid(key, value, expected)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use zed_extension_api::serde_json::{json, Value};
+
+    use std::collections::BTreeMap;
+
+    use super::{boolean, severity, string, string_list, string_map, unsigned};
+
+    #[test]
+    fn booleans() {
+        let table: &[(Value, Result<Option<bool>, ()>)] = &[
+            (json!(true), Ok(Some(true))),
+            (json!(false), Ok(Some(false))),
+            (json!("true"), Ok(Some(true))),
+            (json!("FALSE"), Ok(Some(false))),
+            (json!(null), Ok(None)),
+            (json!("yes"), Err(())),
+            (json!(1), Err(())),
+            (json!({}), Err(())),
+        ];
+        for (value, expected) in table {
+            assert_eq!(
+                boolean("key", Some(value)).map_err(|_| ()),
+                *expected,
+                "{value}"
+            );
+        }
+        assert_eq!(boolean("key", None), Ok(None));
+    }
+
+    #[test]
+    fn unsigned_integers() {
+        let table: &[(Value, Result<Option<u64>, ()>)] = &[
+            (json!(512), Ok(Some(512))),
+            (json!("64"), Ok(Some(64))),
+            (json!(0), Ok(Some(0))),
+            (json!(null), Ok(None)),
+            (json!(-1), Err(())),
+            (json!(1.5), Err(())),
+            (json!("1k"), Err(())),
+        ];
+        for (value, expected) in table {
+            assert_eq!(
+                unsigned("key", Some(value)).map_err(|_| ()),
+                *expected,
+                "{value}"
+            );
+        }
+    }
+
+    #[test]
+    fn severities() {
+        let table: &[(Value, Result<Option<&str>, ()>)] = &[
+            (json!(1), Ok(Some("Error"))),
+            (json!(2), Ok(Some("Warning"))),
+            (json!(3), Ok(Some("Information"))),
+            (json!(4), Ok(Some("Hint"))),
+            (json!("hint"), Ok(Some("Hint"))),
+            (json!("Warning"), Ok(Some("Warning"))),
+            (json!(0), Err(())),
+            (json!(5), Err(())),
+            (json!(-1), Err(())),
+            (json!(2.5), Err(())),
+            (json!("loud"), Err(())),
+            (json!(["Error"]), Err(())),
+        ];
+        for (value, expected) in table {
+            assert_eq!(
+                severity("key", Some(value)).map_err(|_| ()),
+                *expected,
+                "{value}"
+            );
+        }
+    }
+
+    #[test]
+    fn strings() {
+        assert_eq!(string("key", Some(&json!("a"))), Ok(Some("a".to_string())));
+        assert_eq!(
+            string_list("key", Some(&json!("a"))),
+            Ok(Some(vec!["a".to_string()]))
+        );
+        assert_eq!(
+            string_list("key", Some(&json!(["a", "b"]))),
+            Ok(Some(vec!["a".to_string(), "b".to_string()]))
+        );
+        assert_eq!(
+            string_map("key", Some(&json!({"a": "b"}))),
+            Ok(Some(BTreeMap::from([("a".to_string(), "b".to_string())])))
+        );
+        assert_eq!(
+            string_map("key", Some(&json!({"a": 1}))).map_err(String::from),
+            Err("ignoring `key.a`: expected an object of strings, found number 1".to_string())
+        );
+        assert!(string_map("key", Some(&json!(["a"]))).is_err());
+    }
+
+    #[test]
+    fn warnings_name_key_and_types() {
+        assert_eq!(
+            string("settings.version", Some(&json!(0.1))).map_err(String::from),
+            Err("ignoring `settings.version`: expected a string, found number 0.1".to_string())
+        );
+        assert_eq!(
+            string_list("asset_preference", Some(&json!(["musl", 2]))).map_err(String::from),
+            Err(
+                "ignoring `asset_preference`: expected a list of strings, found number 2"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            severity("diagnosticSeverity", Some(&json!(true))).map_err(String::from),
+            Err(
+                "ignoring `diagnosticSeverity`: expected one of \"Error\", \"Warning\", \
+                 \"Information\", \"Hint\" or 1 to 4, found boolean true"
+                    .to_string()
+            )
+        );
+    }
+}
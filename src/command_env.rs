@@ -0,0 +1,77 @@
+//! The environment typos-lsp is started with, from the `env` setting.
+//!
+//! Zed starts language servers in its own environment, adding the one a command
+//! brings. With `env` set, the command brings the shell environment of the worktree,
+//! e.g. with the `$PATH` of a project tool, with the entries of the setting winning.
+//! Without it, the command brings nothing and the environment is left as Zed has it.
+
+use std::collections::BTreeMap;
+
+/// The environment of the command: the `shell_env` of the worktree, asked for only when
+/// needed, with the `overrides` of the settings, whose names are kept verbatim. Nothing
+/// without overrides.
+pub(crate) fn merge(
+    shell_env: impl FnOnce() -> Vec<(String, String)>,
+    overrides: &BTreeMap<String, String>,
+) -> Vec<(String, String)> {
+    if overrides.is_empty() {
+        return Vec::new();
+    }
+    let mut env: Vec<(String, String)> = shell_env()
+        .into_iter()
+        .filter(|(name, _)| !overrides.contains_key(name))
+        .collect();
+    env.extend(
+        overrides
+            .iter()
+            .map(|(name, value)| (name.clone(), value.clone())),
+    );
+    env
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::merge;
+
+    fn pairs(entries: &[(&str, &str)]) -> Vec<(String, String)> {
+        entries
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn overrides_win_over_the_shell() {
+        let shell = || pairs(&[("PATH", "/usr/bin"), ("RUST_LOG", "warn"), ("LANG", "C")]);
+        let overrides = BTreeMap::from([
+            ("RUST_LOG".to_string(), "typos_lsp=debug".to_string()),
+            ("LC_ALL".to_string(), "en_US.UTF-8".to_string()),
+        ]);
+        assert_eq!(
+            merge(shell, &overrides),
+            pairs(&[
+                ("PATH", "/usr/bin"),
+                ("LANG", "C"),
+                ("LC_ALL", "en_US.UTF-8"),
+                ("RUST_LOG", "typos_lsp=debug"),
+            ])
+        );
+    }
+
+    #[test]
+    fn names_are_verbatim() {
+        let overrides = BTreeMap::from([("rust_log".to_string(), "debug".to_string())]);
+        assert_eq!(
+            merge(|| pairs(&[("RUST_LOG", "warn")]), &overrides),
+            pairs(&[("RUST_LOG", "warn"), ("rust_log", "debug")])
+        );
+    }
+
+    #[test]
+    fn no_overrides_leave_the_environment_alone() {
+        let shell = || -> Vec<(String, String)> { panic!("the shell environment is not needed") };
+        assert_eq!(merge(shell, &BTreeMap::new()), Vec::new());
+    }
+}
@@ -0,0 +1,140 @@
+//! A small word list of technical terms typos flags, shipped with the extension.
+//!
+//! New users run into the same false positives, such as cloud acronyms or Rust jargon,
+//! before they learn to configure typos. With `community_dictionary`, the words of
+//! `community_dictionary.txt` are accepted in the generated configuration, layered
+//! below every word the user or the project configured: a word they accept or correct
+//! keeps their meaning.
+
+use std::collections::BTreeSet;
+
+use crate::typos_config::{TomlValue, TyposConfig};
+
+/// The list, compiled into the extension.
+const LIST: &str = include_str!("community_dictionary.txt");
+
+/// The version of the list, as stated in its header.
+pub(crate) const VERSION: u32 = 2;
+
+/// The table the words are accepted in.
+const TABLE: &str = "default.extend-words";
+
+/// The words of the list.
+pub(crate) fn words() -> BTreeSet<&'static str> {
+    LIST.lines()
+        .map(|line| line.split_once('#').map_or(line, |(words, _)| words).trim())
+        .filter(|word| !word.is_empty())
+        .collect()
+}
+
+/// Accept the words of the list in `config`, the generated configuration, unless
+/// `config` or the `explicit` configuration of the project already has them, in any
+/// case. Returns how many were accepted.
+pub(crate) fn apply(config: &mut TyposConfig, explicit: &TyposConfig) -> usize {
+    let mut added = 0;
+    for word in words() {
+        if config.get_word(TABLE, word).is_none() && explicit.get_word(TABLE, word).is_none() {
+            config.set(TABLE, word, TomlValue::String(word.to_string()));
+            added += 1;
+        }
+    }
+    added
+}
+
+/// What the list adds, logged once.
+pub(crate) fn notice(added: usize) -> String {
+    format!(
+        "`community_dictionary` accepts {added} words of the community dictionary \
+         version {VERSION}, below the configured ones"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{apply, words, LIST, TABLE, VERSION};
+    use crate::typos_config::{TomlValue, TyposConfig};
+
+    /// Misspellings the dictionary of typos corrects (`crates/typos-dict/assets/words.csv`
+    /// in the typos repository) that look like acronyms or jargon, and once were in the
+    /// list.
+    const KNOWN_TYPOS: &[&str] = &[
+        "ans", "arange", "aks", "atleast", "ba", "cas", "doas", "fo", "hsa", "lod", "mape", "nd",
+        "od", "ois", "ot", "pn", "seeked", "ser", "typ", "ue", "wan",
+    ];
+
+    #[test]
+    fn list() {
+        assert!(LIST.starts_with(&format!(
+            "# The community dictionary of the typos extension, version {VERSION}.\n"
+        )));
+        let words = words();
+        assert!(words.len() >= 150, "{}", words.len());
+        for word in &words {
+            assert!(
+                word.chars().all(|c| c.is_ascii_lowercase()),
+                "{word:?} is not a lowercase word"
+            );
+            // Shorter words are too likely to be typos of common ones.
+            assert!(word.len() >= 3, "{word:?} is shorter than three letters");
+        }
+        assert!(words.contains("iam"));
+        assert!(!words.contains("atleast"));
+    }
+
+    #[test]
+    fn no_known_typos() {
+        for word in words() {
+            assert!(
+                !KNOWN_TYPOS.contains(&word),
+                "{word:?} is a misspelling typos corrects"
+            );
+        }
+    }
+
+    #[test]
+    fn configured_words_win() {
+        let explicit = TyposConfig::parse("[default.extend-words]\nEKS = \"eks\"\n").unwrap();
+        let mut config = TyposConfig::default();
+        config.set(TABLE, "ptr", TomlValue::String("pointer".to_string()));
+        assert_eq!(apply(&mut config, &explicit), words().len() - 2);
+
+        // In any case, as typos ignores it.
+        assert_eq!(config.get(TABLE, "eks"), None);
+        assert_eq!(
+            config.get(TABLE, "ptr"),
+            Some(&TomlValue::String("pointer".to_string()))
+        );
+        assert_eq!(
+            config.get(TABLE, "iam"),
+            Some(&TomlValue::String("iam".to_string()))
+        );
+        let accepted = config
+            .tables()
+            .find(|(table, _)| *table == TABLE)
+            .map_or(0, |(_, entries)| entries.len());
+        assert_eq!(accepted, words().len() - 1);
+    }
+
+    #[test]
+    fn disabled_list_adds_nothing() {
+        let generated = |enabled: bool| {
+            let mut config = TyposConfig::default();
+            config.set(
+                TABLE,
+                "terraform",
+                TomlValue::String("terraform".to_string()),
+            );
+            if enabled {
+                apply(&mut config, &TyposConfig::default());
+            }
+            config
+        };
+        let enabled = generated(true);
+        let disabled = generated(false);
+        for word in words() {
+            assert!(enabled.get(TABLE, word).is_some(), "{word}");
+            assert_eq!(disabled.get(TABLE, word), None, "{word}");
+        }
+        assert!(disabled.get(TABLE, "terraform").is_some());
+    }
+}
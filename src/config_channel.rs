@@ -0,0 +1,108 @@
+//! How the custom configuration reaches typos-lsp.
+//!
+//! typos-lsp reads one custom configuration, the file named by its `config`
+//! initialization option, on top of the configuration files it discovers in the
+//! worktree (see the `config` option in the typos-lsp README). Every release the
+//! extension installs reads it the same way, so the generated configuration is always
+//! written to a file and handed over through `config`.
+//!
+//! A word list both in the generated configuration and in a discovered file would be
+//! read twice, so the words a discovered file already accepts with the same meaning are
+//! left out of the generated configuration.
+
+use zed_extension_api::serde_json::{Map, Value};
+
+use crate::{case_fold, typos_config::TyposConfig};
+
+/// The initialization option naming the configuration file.
+pub(crate) const CONFIG_KEY: &str = "config";
+
+/// Hand the configuration file at `path`, if any, in `options`.
+pub(crate) fn deliver(options: &mut Map<String, Value>, path: Option<String>) {
+    if let Some(path) = path {
+        options.insert(CONFIG_KEY.to_string(), path.into());
+    }
+}
+
+/// The `generated` configuration without the words `discovered`, a file typos-lsp
+/// reads by itself, already accepts with the same meaning.
+pub(crate) fn dedupe(generated: &TyposConfig, discovered: &TyposConfig) -> TyposConfig {
+    let mut deduped = TyposConfig::default();
+    for (table, entries) in generated.tables() {
+        for (key, value) in entries {
+            let known = case_fold::is_word_table(table)
+                && discovered.get_word(table, key).map(|(_, below)| below) == Some(value);
+            if !known {
+                deduped.set(table, key, value.clone());
+            }
+        }
+    }
+    deduped
+}
+
+#[cfg(test)]
+mod tests {
+    use zed_extension_api::serde_json::json;
+
+    use super::{dedupe, deliver};
+    use crate::typos_config::TyposConfig;
+
+    #[test]
+    fn delivery() {
+        let mut options = json!({"diagnosticSeverity": "Warning"})
+            .as_object()
+            .unwrap()
+            .clone();
+        deliver(&mut options, None);
+        assert_eq!(
+            options,
+            *json!({"diagnosticSeverity": "Warning"})
+                .as_object()
+                .unwrap()
+        );
+        deliver(
+            &mut options,
+            Some("/work/generated-config/0123.toml".to_string()),
+        );
+        assert_eq!(
+            options,
+            *json!({
+                "diagnosticSeverity": "Warning",
+                "config": "/work/generated-config/0123.toml",
+            })
+            .as_object()
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn discovered_words_are_left_out() {
+        let generated = TyposConfig::parse(
+            "[default]\n\
+             check-filename = false\n\
+             \n\
+             [default.extend-words]\n\
+             k8s = \"k8s\"\n\
+             teh = \"the\"\n\
+             odf = \"odf\"\n",
+        )
+        .unwrap();
+        let discovered = TyposConfig::parse(
+            "[default.extend-words]\n\
+             K8S = \"k8s\"\n\
+             teh = \"teh\"\n",
+        )
+        .unwrap();
+        let deduped = dedupe(&generated, &discovered);
+        assert_eq!(
+            deduped.render(),
+            "[default]\n\
+             check-filename = false\n\
+             \n\
+             [default.extend-words]\n\
+             odf = \"odf\"\n\
+             teh = \"the\"\n"
+        );
+        assert_eq!(dedupe(&generated, &TyposConfig::default()), generated);
+    }
+}
@@ -0,0 +1,231 @@
+//! Where the typos configuration of a worktree comes from, when several are set.
+//!
+//! typos-lsp reads a single custom configuration, so the explicit ones exclude each
+//! other: `config` is a file handed over as is, `config_toml` an inline configuration
+//! and `config_files` files merged in order. A file typos discovers in the worktree,
+//! e.g. `_typos.toml`, only counts when none of them is set.
+//!
+//! typos-lsp looks for configuration files next to the files it checks, which misses
+//! the one at the root of the worktree for files in nested packages of a monorepo. The
+//! discovered file is passed with `--config` for that, unless the user's arguments
+//! already name one.
+
+use std::path::Path;
+
+use crate::default_args::ArgumentsMode;
+
+/// The configuration files typos looks for, in order of precedence.
+pub(crate) const FILE_NAMES: &[&str] = &["typos.toml", "_typos.toml", ".typos.toml"];
+
+/// The configuration chosen for a worktree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum ConfigSource {
+    /// The `config` initialization option.
+    Path(String),
+    /// The `config_toml` setting.
+    Inline(String),
+    /// The `config_files` setting, later files winning.
+    Files(Vec<String>),
+    /// A configuration file found in the worktree.
+    Discovered(String),
+    /// typos defaults.
+    None,
+}
+
+/// Pick the configuration source, or explain why the settings are ambiguous.
+///
+/// Empty values count as unset.
+pub(crate) fn decide(
+    config: Option<&str>,
+    config_toml: Option<&str>,
+    config_files: &[String],
+    discovered: Option<&str>,
+) -> Result<ConfigSource, String> {
+    let config = config.filter(|config| !config.trim().is_empty());
+    let config_toml = config_toml.filter(|toml| !toml.trim().is_empty());
+    let config_files = Some(config_files).filter(|files| !files.is_empty());
+    match (config, config_toml, config_files) {
+        (Some(_), Some(_), Some(_)) => Err(
+            "`config`, `config_toml` and `config_files` are all set, but typos-lsp reads a \
+             single configuration: keep only one of them"
+                .to_string(),
+        ),
+        (Some(_), Some(_), None) => Err(
+            "both `config` and `config_toml` are set, but typos-lsp reads a single \
+             configuration: move the inline TOML into the `config` file, or remove `config`"
+                .to_string(),
+        ),
+        (Some(_), None, Some(_)) => Err(
+            "both `config` and `config_files` are set: list the `config` file in \
+             `config_files` to merge it with the others, or remove `config_files`"
+                .to_string(),
+        ),
+        (None, Some(_), Some(_)) => Err(
+            "both `config_toml` and `config_files` are set: move the inline TOML into one \
+             of the `config_files`, or remove `config_files`"
+                .to_string(),
+        ),
+        (Some(config), None, None) => Ok(ConfigSource::Path(config.to_string())),
+        (None, Some(toml), None) => Ok(ConfigSource::Inline(toml.to_string())),
+        (None, None, Some(files)) => Ok(ConfigSource::Files(files.to_vec())),
+        (None, None, None) => Ok(discovered.map_or(ConfigSource::None, |path| {
+            ConfigSource::Discovered(path.to_string())
+        })),
+    }
+}
+
+/// The first of the [`FILE_NAMES`] that `exists` at the root of the worktree.
+pub(crate) fn discover(exists: impl Fn(&str) -> bool) -> Option<&'static str> {
+    FILE_NAMES.iter().copied().find(|name| exists(name))
+}
+
+/// The arguments handing the configuration file discovered at the root of the worktree
+/// at `root` to typos-lsp, following the `user_arguments` composed in `mode`.
+pub(crate) fn arguments(
+    source: &ConfigSource,
+    root: &str,
+    mode: ArgumentsMode,
+    user_arguments: &[String],
+) -> Vec<String> {
+    let ConfigSource::Discovered(file) = source else {
+        return Vec::new();
+    };
+    let configured = user_arguments
+        .iter()
+        .any(|argument| argument == "--config" || argument.starts_with("--config="));
+    if mode != ArgumentsMode::Append || configured {
+        return Vec::new();
+    }
+    vec![
+        "--config".to_string(),
+        Path::new(root).join(file).display().to_string(),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{arguments, decide, discover, ConfigSource};
+    use crate::default_args::ArgumentsMode;
+
+    const CONFIG: Option<&str> = Some("ci/typos.toml");
+    const TOML: Option<&str> = Some("[default]\nlocale = \"en-gb\"\n");
+    const DISCOVERED: Option<&str> = Some("_typos.toml");
+
+    fn files() -> Vec<String> {
+        vec!["base.toml".to_string(), "local.toml".to_string()]
+    }
+
+    #[test]
+    fn single_sources() {
+        assert_eq!(
+            decide(CONFIG, None, &[], None),
+            Ok(ConfigSource::Path("ci/typos.toml".to_string()))
+        );
+        assert_eq!(
+            decide(None, TOML, &[], None),
+            Ok(ConfigSource::Inline(TOML.unwrap().to_string()))
+        );
+        assert_eq!(
+            decide(None, None, &files(), None),
+            Ok(ConfigSource::Files(files()))
+        );
+        assert_eq!(
+            decide(None, None, &[], DISCOVERED),
+            Ok(ConfigSource::Discovered("_typos.toml".to_string()))
+        );
+        assert_eq!(decide(None, None, &[], None), Ok(ConfigSource::None));
+        assert_eq!(
+            decide(Some(""), Some(" "), &[], None),
+            Ok(ConfigSource::None)
+        );
+    }
+
+    #[test]
+    fn explicit_sources_conflict() {
+        let conflict =
+            |config, toml, files: &[String]| decide(config, toml, files, DISCOVERED).unwrap_err();
+        let error = conflict(CONFIG, TOML, &[]);
+        assert!(
+            error.starts_with("both `config` and `config_toml` are set"),
+            "{error}"
+        );
+        let error = conflict(CONFIG, None, &files());
+        assert!(
+            error.starts_with("both `config` and `config_files` are set"),
+            "{error}"
+        );
+        let error = conflict(None, TOML, &files());
+        assert!(
+            error.starts_with("both `config_toml` and `config_files` are set"),
+            "{error}"
+        );
+        let error = conflict(CONFIG, TOML, &files());
+        assert!(error.starts_with("`config`, `config_toml` and `config_files` are all set"));
+    }
+
+    #[test]
+    fn discovered_files_lose() {
+        assert_eq!(
+            decide(CONFIG, None, &[], DISCOVERED),
+            Ok(ConfigSource::Path("ci/typos.toml".to_string()))
+        );
+        assert_eq!(
+            decide(None, TOML, &[], DISCOVERED),
+            Ok(ConfigSource::Inline(TOML.unwrap().to_string()))
+        );
+        assert_eq!(
+            decide(None, None, &files(), DISCOVERED),
+            Ok(ConfigSource::Files(files()))
+        );
+    }
+
+    #[test]
+    fn discovery_order() {
+        assert_eq!(discover(|_| true), Some("typos.toml"));
+        for name in ["typos.toml", "_typos.toml", ".typos.toml"] {
+            assert_eq!(discover(|file| file == name), Some(name));
+        }
+        assert_eq!(discover(|file| file != "typos.toml"), Some("_typos.toml"));
+        assert_eq!(discover(|file| file == "typos.yaml"), None);
+    }
+
+    #[test]
+    fn discovered_file_arguments() {
+        let discovered = ConfigSource::Discovered("_typos.toml".to_string());
+        let root = "/work/monorepo";
+        assert_eq!(
+            arguments(&discovered, root, ArgumentsMode::Append, &[]),
+            ["--config", "/work/monorepo/_typos.toml"]
+        );
+        assert_eq!(
+            arguments(
+                &discovered,
+                root,
+                ArgumentsMode::Append,
+                &["--log-level".to_string(), "warn".to_string()]
+            ),
+            ["--config", "/work/monorepo/_typos.toml"]
+        );
+
+        // The user's own configuration wins.
+        for user in [
+            vec!["--config".to_string(), "ci/typos.toml".to_string()],
+            vec!["--config=ci/typos.toml".to_string()],
+        ] {
+            assert!(arguments(&discovered, root, ArgumentsMode::Append, &user).is_empty());
+        }
+        let configured = decide(Some("ci/typos.toml"), None, &[], Some("typos.toml")).unwrap();
+        assert!(arguments(&configured, root, ArgumentsMode::Append, &[]).is_empty());
+        for source in [
+            ConfigSource::Inline("[default]\n".to_string()),
+            ConfigSource::Files(files()),
+            ConfigSource::None,
+        ] {
+            assert!(arguments(&source, root, ArgumentsMode::Append, &[]).is_empty());
+        }
+        // As are arguments meant to be passed alone, or not at all.
+        for mode in [ArgumentsMode::Replace, ArgumentsMode::None] {
+            assert!(arguments(&discovered, root, mode, &[]).is_empty());
+        }
+    }
+}
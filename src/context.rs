@@ -0,0 +1,337 @@
+//! Detection of the environment in which the language server command will run.
+//!
+//! The v0.1.0 extension API has no notion of a remote worktree: `current_platform`
+//! and the extension work directory always describe the host running the extension,
+//! while `Worktree::which` and `Worktree::shell_env` are resolved by Zed in the
+//! worktree's own environment. When those two disagree, a downloaded binary would
+//! not be reachable (or runnable) where the command is spawned, so the only safe
+//! option is to rely on a `typos-lsp` found through the worktree's `$PATH`.
+
+use zed_extension_api::{Architecture, Os};
+
+use crate::capabilities::{self, Capability};
+
+/// Where the command returned to Zed is going to be executed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum ExecutionContext {
+    /// Same host as the extension, the work directory is reachable by the server.
+    Local,
+    /// The worktree lives on another host, as far as we can tell from its environment.
+    Remote(RemoteHost),
+}
+
+/// What could be inferred about a remote host from the worktree environment.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub(crate) struct RemoteHost {
+    /// The remote host name or address, from `SSH_CONNECTION` when available.
+    pub host: Option<String>,
+    pub os: Option<Os>,
+    pub architecture: Option<Architecture>,
+}
+
+impl ExecutionContext {
+    /// Infer the execution context from the worktree shell environment and root path.
+    pub(crate) fn detect(
+        shell_env: &[(String, String)],
+        root_path: &str,
+        local: (Os, Architecture),
+    ) -> Self {
+        let var = |name: &str| env_var(shell_env, name);
+
+        let ssh_connection = var("SSH_CONNECTION").or_else(|| var("SSH_CLIENT"));
+        let os = infer_os(
+            var("OS"),
+            var("OSTYPE"),
+            root_path,
+            ssh_connection.is_some(),
+        );
+        let architecture = infer_architecture(var("HOSTTYPE"), var("PROCESSOR_ARCHITECTURE"));
+
+        let os_mismatch = os.map_or(false, |os| os != local.0);
+        let arch_mismatch = architecture.map_or(false, |arch| !same_arch(arch, local.1));
+
+        // An SSH session alone is not enough: Zed itself may have been started from one,
+        // or may run the extension on the remote host. Only a platform disagreement
+        // proves the work directory and the spawned process live on different hosts.
+        if os_mismatch || arch_mismatch {
+            return Self::Remote(RemoteHost {
+                host: ssh_connection.and_then(remote_address),
+                os,
+                architecture,
+            });
+        }
+        Self::Local
+    }
+
+    /// The platform a managed binary must be installed for.
+    ///
+    /// Returns an error when the command will run on a remote host, because the
+    /// extension API can only download into the local extension work directory.
+    pub(crate) fn install_platform(
+        &self,
+        local: (Os, Architecture),
+    ) -> Result<(Os, Architecture), String> {
+        match self {
+            Self::Local => Ok(local),
+            Self::Remote(remote) => Err(format!(
+                "the worktree runs on a remote {host} ({platform}): {reason}, install \
+                 typos-lsp on the remote host and make it available on its PATH",
+                host = remote
+                    .host
+                    .as_deref()
+                    .map_or("host".to_string(), |host| format!("host {host}")),
+                platform = describe(remote.os, remote.architecture),
+                reason = capabilities::require(Capability::RemoteInstall, "remote install")
+                    .err()
+                    .unwrap_or_else(|| "installing there is not implemented".to_string()),
+            )),
+        }
+    }
+}
+
+fn env_var<'a>(shell_env: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    shell_env
+        .iter()
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value.as_str())
+}
+
+fn infer_os(
+    os_var: Option<&str>,
+    ostype: Option<&str>,
+    root_path: &str,
+    over_ssh: bool,
+) -> Option<Os> {
+    if os_var == Some("Windows_NT") || is_windows_path(root_path) {
+        return Some(Os::Windows);
+    }
+    match ostype {
+        Some(ostype) if ostype.starts_with("darwin") => return Some(Os::Mac),
+        Some(ostype) if ostype.starts_with("linux") => return Some(Os::Linux),
+        _ => {}
+    }
+    // Home directory conventions are only trusted when we know we are behind SSH,
+    // otherwise any custom layout would be misreported.
+    if over_ssh {
+        if root_path.starts_with("/Users/") {
+            return Some(Os::Mac);
+        }
+        if root_path.starts_with("/home/") || root_path.starts_with("/root/") {
+            return Some(Os::Linux);
+        }
+    }
+    None
+}
+
+fn infer_architecture(hosttype: Option<&str>, processor: Option<&str>) -> Option<Architecture> {
+    let name = hosttype.or(processor)?.to_ascii_lowercase();
+    match name.as_str() {
+        "aarch64" | "arm64" => Some(Architecture::Aarch64),
+        "x86_64" | "amd64" => Some(Architecture::X8664),
+        "x86" | "i386" | "i686" => Some(Architecture::X86),
+        _ => None,
+    }
+}
+
+fn is_windows_path(path: &str) -> bool {
+    let bytes = path.as_bytes();
+    path.starts_with(r"\\")
+        || (bytes.len() >= 3
+            && bytes[0].is_ascii_alphabetic()
+            && bytes[1] == b':'
+            && (bytes[2] == b'\\' || bytes[2] == b'/'))
+}
+
+/// Architectures served the same release, e.g. both x86 variants when x86 hosts get the
+/// x86_64 builds, are not a mismatch.
+fn same_arch(a: Architecture, b: Architecture) -> bool {
+    let release = crate::TyposExtension::release_architecture;
+    a == b || matches!((release(&a), release(&b)), (Ok(a), Ok(b)) if a == b)
+}
+
+/// `SSH_CONNECTION` is "client_ip client_port server_ip server_port".
+fn remote_address(ssh_connection: &str) -> Option<String> {
+    ssh_connection
+        .split_whitespace()
+        .nth(2)
+        .map(|address| address.to_string())
+}
+
+fn describe(os: Option<Os>, architecture: Option<Architecture>) -> String {
+    let os = match os {
+        Some(Os::Mac) => "macOS",
+        Some(Os::Linux) => "Linux",
+        Some(Os::Windows) => "Windows",
+        None => "unknown OS",
+    };
+    let arch = match architecture {
+        Some(Architecture::Aarch64) => "aarch64",
+        Some(Architecture::X86) => "x86",
+        Some(Architecture::X8664) => "x86_64",
+        None => "unknown architecture",
+    };
+    format!("{os}, {arch}")
+}
+
+/// A container the worktree environment appears to run in.
+///
+/// Containers commonly come with a different userland (musl, another architecture)
+/// and a read-only extension directory, so installation decisions depend on it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Container {
+    /// A Dev Container, as set up by the `devcontainer` tooling.
+    Devcontainer,
+    /// A GitHub Codespace.
+    Codespaces,
+    /// Any other container, detected from the runtime's `container` variable or layout.
+    Generic,
+}
+
+impl Container {
+    /// Infer whether the worktree environment is a container.
+    pub(crate) fn detect(shell_env: &[(String, String)], root_path: &str) -> Option<Self> {
+        let var = |name: &str| env_var(shell_env, name);
+
+        if var("CODESPACES") == Some("true") {
+            return Some(Self::Codespaces);
+        }
+        if var("REMOTE_CONTAINERS") == Some("true") || var("DEVCONTAINER").is_some() {
+            return Some(Self::Devcontainer);
+        }
+        // Set by podman and systemd-nspawn, `/workspaces` is the devcontainer mount point.
+        if var("container").is_some() || root_path.starts_with("/workspaces/") {
+            return Some(Self::Generic);
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use zed_extension_api::{Architecture, Os};
+
+    use super::{Container, ExecutionContext, RemoteHost};
+
+    const MAC: (Os, Architecture) = (Os::Mac, Architecture::Aarch64);
+
+    fn env(vars: &[(&str, &str)]) -> Vec<(String, String)> {
+        vars.iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn local_without_hints() {
+        let context = ExecutionContext::detect(&env(&[]), "/Users/me/project", MAC);
+        assert_eq!(context, ExecutionContext::Local);
+        assert_eq!(context.install_platform(MAC), Ok(MAC));
+    }
+
+    #[test]
+    fn ssh_session_on_same_platform_is_local() {
+        let context = ExecutionContext::detect(
+            &env(&[
+                ("SSH_CONNECTION", "10.0.0.1 5000 10.0.0.2 22"),
+                ("OSTYPE", "darwin23"),
+                ("HOSTTYPE", "arm64"),
+            ]),
+            "/Users/me/project",
+            MAC,
+        );
+        assert_eq!(context, ExecutionContext::Local);
+    }
+
+    #[test]
+    fn ssh_to_linux_from_mac_is_remote() {
+        let context = ExecutionContext::detect(
+            &env(&[
+                ("SSH_CONNECTION", "10.0.0.1 5000 10.0.0.2 22"),
+                ("HOSTTYPE", "x86_64"),
+            ]),
+            "/home/me/project",
+            MAC,
+        );
+        assert_eq!(
+            context,
+            ExecutionContext::Remote(RemoteHost {
+                host: Some("10.0.0.2".to_string()),
+                os: Some(Os::Linux),
+                architecture: Some(Architecture::X8664),
+            })
+        );
+        let error = context.install_platform(MAC).unwrap_err();
+        assert!(error.contains("host 10.0.0.2"), "{error}");
+        assert!(error.contains("Linux, x86_64"), "{error}");
+    }
+
+    #[test]
+    fn home_layout_ignored_without_ssh() {
+        let context = ExecutionContext::detect(&env(&[]), "/home/me/project", MAC);
+        assert_eq!(context, ExecutionContext::Local);
+    }
+
+    #[test]
+    fn windows_root_from_linux_is_remote() {
+        let linux = (Os::Linux, Architecture::X8664);
+        let context = ExecutionContext::detect(&env(&[]), r"C:\Users\me\project", linux);
+        assert!(matches!(
+            context,
+            ExecutionContext::Remote(RemoteHost {
+                os: Some(Os::Windows),
+                ..
+            })
+        ));
+        let context = ExecutionContext::detect(&env(&[]), r"\\server\share\project", linux);
+        assert!(matches!(context, ExecutionContext::Remote(_)));
+    }
+
+    #[test]
+    fn x86_variants_match_when_served_the_same_release() {
+        let local = (Os::Linux, Architecture::X8664);
+        let context = ExecutionContext::detect(
+            &env(&[("OSTYPE", "linux-gnu"), ("HOSTTYPE", "i686")]),
+            "/srv/project",
+            local,
+        );
+        assert_eq!(
+            context == ExecutionContext::Local,
+            cfg!(feature = "x86-as-x86_64")
+        );
+    }
+
+    #[test]
+    fn architecture_mismatch_is_remote() {
+        let local = (Os::Linux, Architecture::X8664);
+        let context = ExecutionContext::detect(
+            &env(&[("OSTYPE", "linux-gnu"), ("HOSTTYPE", "aarch64")]),
+            "/srv/project",
+            local,
+        );
+        assert!(context.install_platform(local).is_err());
+    }
+
+    #[test]
+    fn container_detection() {
+        assert_eq!(Container::detect(&env(&[]), "/home/me/project"), None);
+        assert_eq!(
+            Container::detect(&env(&[("REMOTE_CONTAINERS", "true")]), "/src"),
+            Some(Container::Devcontainer)
+        );
+        assert_eq!(
+            Container::detect(&env(&[("CODESPACES", "true")]), "/workspaces/project"),
+            Some(Container::Codespaces)
+        );
+        assert_eq!(
+            Container::detect(&env(&[("container", "podman")]), "/src"),
+            Some(Container::Generic)
+        );
+        assert_eq!(
+            Container::detect(&env(&[]), "/workspaces/project"),
+            Some(Container::Generic)
+        );
+        assert_eq!(
+            Container::detect(&env(&[("REMOTE_CONTAINERS", "false")]), "/src"),
+            None
+        );
+    }
+}
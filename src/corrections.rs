@@ -0,0 +1,212 @@
+//! Project-specific corrections, for the misspellings typos flags without a fix.
+//!
+//! They come from the `corrections` setting and from the file named by
+//! `corrections_file`, which holds one `wrong=right` entry per line with `#` starting a
+//! comment. Both are added to the generated configuration as `[default.extend-words]`,
+//! where they give the diagnostics of `wrong` a quick-fix; the setting wins over the file
+//! for a word both correct.
+
+use std::collections::BTreeMap;
+
+use crate::{
+    case_fold,
+    typos_config::{TomlValue, TyposConfig},
+};
+
+/// Why the correction of `wrong` into `right` cannot be used, if it cannot.
+pub(crate) fn invalid(wrong: &str, right: &str) -> Option<&'static str> {
+    if wrong.is_empty() {
+        Some("the misspelling is empty")
+    } else if wrong.chars().any(char::is_whitespace) {
+        Some("the misspelling contains spaces")
+    } else if right.is_empty() {
+        Some("the correction is empty")
+    } else {
+        None
+    }
+}
+
+/// The corrections of a `corrections_file`, and its entries that could not be used.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct Corrections {
+    /// The corrections, by misspelling. A misspelling listed twice keeps its last
+    /// correction.
+    pub entries: BTreeMap<String, String>,
+    pub errors: Vec<String>,
+}
+
+impl Corrections {
+    /// Parse the content of the corrections file at `path`, as named in errors.
+    pub(crate) fn parse(path: &str, content: &str) -> Self {
+        let mut corrections = Self::default();
+        for (number, line) in content.lines().enumerate() {
+            let entry = line.split_once('#').map_or(line, |(entry, _)| entry).trim();
+            if entry.is_empty() {
+                continue;
+            }
+            let error = match entry.split_once('=') {
+                None => Some("expected `wrong=right`"),
+                Some((wrong, right)) => {
+                    let (wrong, right) = (wrong.trim(), right.trim());
+                    let error = invalid(wrong, right);
+                    if error.is_none() {
+                        corrections
+                            .entries
+                            .insert(wrong.to_string(), right.to_string());
+                    }
+                    error
+                }
+            };
+            if let Some(error) = error {
+                corrections.errors.push(format!(
+                    "{path}:{}: ignoring {entry:?}: {error}",
+                    number + 1
+                ));
+            }
+        }
+        corrections
+    }
+
+    /// The corrections of the file at `path`, relative to the root of the worktree, from
+    /// its `content` as the worktree read it.
+    pub(crate) fn from_file(path: &str, content: Result<String, String>) -> Self {
+        match content {
+            Ok(content) => Self::parse(path, &content),
+            Err(e) => Self {
+                entries: BTreeMap::new(),
+                errors: vec![format!("cannot read the corrections file {path}: {e}")],
+            },
+        }
+    }
+
+    /// Layer the `inline` corrections of the settings over the ones of the file, case
+    /// variants of a misspelling included.
+    pub(crate) fn overridden_by(mut self, inline: &BTreeMap<String, String>) -> Self {
+        self.entries.retain(|wrong, _| {
+            !inline
+                .keys()
+                .any(|inline| case_fold::same_word(inline, wrong))
+        });
+        self.entries.extend(inline.clone());
+        self
+    }
+
+    /// Correct the misspellings in the generated configuration.
+    pub(crate) fn apply(&self, config: &mut TyposConfig) {
+        for (wrong, right) in &self.entries {
+            config.set_word(
+                "default.extend-words",
+                wrong,
+                TomlValue::String(right.clone()),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::{invalid, Corrections};
+    use crate::typos_config::{TomlValue, TyposConfig};
+
+    fn entries(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs
+            .iter()
+            .map(|(wrong, right)| (wrong.to_string(), right.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn validation() {
+        assert_eq!(invalid("teh", "the"), None);
+        assert_eq!(invalid("", "the"), Some("the misspelling is empty"));
+        assert_eq!(
+            invalid("te h", "the"),
+            Some("the misspelling contains spaces")
+        );
+        assert_eq!(invalid("teh", ""), Some("the correction is empty"));
+        // Corrections may be several words.
+        assert_eq!(invalid("alot", "a lot"), None);
+    }
+
+    #[test]
+    fn parsing() {
+        let content = "# Project misspellings\n\
+                       recieve=receive\n\
+                       \n\
+                       kuberentes = kubernetes # ops\n\
+                       seperate\n\
+                       bad key=value\n\
+                       empty=\n\
+                       =orphan\n\
+                       recieve=receives\n";
+        let corrections = Corrections::parse("corrections.txt", content);
+        assert_eq!(
+            corrections.entries,
+            entries(&[("kuberentes", "kubernetes"), ("recieve", "receives")])
+        );
+        assert_eq!(
+            corrections.errors,
+            [
+                "corrections.txt:5: ignoring \"seperate\": expected `wrong=right`",
+                "corrections.txt:6: ignoring \"bad key=value\": the misspelling contains spaces",
+                "corrections.txt:7: ignoring \"empty=\": the correction is empty",
+                "corrections.txt:8: ignoring \"=orphan\": the misspelling is empty",
+            ]
+        );
+    }
+
+    #[test]
+    fn reading() {
+        let corrections = Corrections::from_file("ci/fixes.txt", Ok("teh=the\n".to_string()));
+        assert_eq!(corrections.entries, entries(&[("teh", "the")]));
+        assert!(corrections.errors.is_empty());
+
+        let missing = Corrections::from_file("missing.txt", Err("not found".to_string()));
+        assert!(missing.entries.is_empty());
+        assert_eq!(
+            missing.errors,
+            ["cannot read the corrections file missing.txt: not found"]
+        );
+    }
+
+    #[test]
+    fn inline_wins_over_file() {
+        let file = Corrections::parse("fixes.txt", "teh=the\nrecieve=receive\n");
+        let corrections = file.overridden_by(&entries(&[("teh", "tech"), ("adn", "and")]));
+        assert_eq!(
+            corrections.entries,
+            entries(&[("adn", "and"), ("recieve", "receive"), ("teh", "tech")])
+        );
+
+        let mut config = TyposConfig::default();
+        corrections.apply(&mut config);
+        assert_eq!(
+            config.get("default.extend-words", "teh"),
+            Some(&TomlValue::String("tech".to_string()))
+        );
+        assert_eq!(
+            config.render(),
+            "[default.extend-words]\nadn = \"and\"\nrecieve = \"receive\"\nteh = \"tech\"\n"
+        );
+    }
+
+    #[test]
+    fn case_variants() {
+        let file = Corrections::parse("fixes.txt", "Recieve=receive\nGRÖẞE=Größe\n");
+        let corrections = file.overridden_by(&entries(&[("recieve", "Receive")]));
+        assert_eq!(
+            corrections.entries,
+            entries(&[("GRÖẞE", "Größe"), ("recieve", "Receive")])
+        );
+
+        let mut config = TyposConfig::default();
+        corrections.apply(&mut config);
+        Corrections::parse("more.txt", "grösse=Grösse\n").apply(&mut config);
+        assert_eq!(
+            config.render(),
+            "[default.extend-words]\n\"grösse\" = \"Grösse\"\nrecieve = \"Receive\"\n"
+        );
+    }
+}
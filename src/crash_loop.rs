@@ -0,0 +1,124 @@
+//! Suspending the custom configuration of a server Zed keeps restarting.
+//!
+//! A configuration the extension accepts may still crash typos-lsp while it
+//! initializes, e.g. a regex its older regex engine rejects. Zed then restarts the
+//! server, asking for the same command and options every time. When a server is
+//! resolved [`RESOLUTIONS`] times within [`WINDOW_SECS`] seconds with the same
+//! settings, it is taken for such a crash loop: the next starts use the defaults of the
+//! extension alone, without the configuration sources and generated options, until
+//! the settings change.
+
+/// The resolutions within [`WINDOW_SECS`] taken for a crash loop.
+pub(crate) const RESOLUTIONS: usize = 4;
+
+/// The time, in seconds, in which [`RESOLUTIONS`] resolutions are a crash loop.
+pub(crate) const WINDOW_SECS: u64 = 60;
+
+/// The recent resolutions of one server, with the settings they used.
+#[derive(Debug, Default)]
+pub(crate) struct CrashLoop {
+    /// The hash of the settings of the resolutions.
+    fingerprint: Option<String>,
+    /// When the resolutions within the window happened, in seconds since the Unix epoch.
+    resolutions: Vec<u64>,
+    safe_mode: bool,
+}
+
+/// What a resolution found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Outcome {
+    /// The server starts with its configuration.
+    Configured,
+    /// A crash loop was just detected: the server starts with the defaults.
+    Suspended,
+    /// The configuration is still suspended.
+    StillSuspended,
+}
+
+impl CrashLoop {
+    /// Record a resolution at `now` with the settings of hash `fingerprint`. Changed
+    /// settings start over, restoring the configuration.
+    pub(crate) fn record(&mut self, now: u64, fingerprint: &str) -> Outcome {
+        if self.fingerprint.as_deref() != Some(fingerprint) {
+            *self = Self {
+                fingerprint: Some(fingerprint.to_string()),
+                ..Self::default()
+            };
+        }
+        if self.safe_mode {
+            return Outcome::StillSuspended;
+        }
+        self.resolutions
+            .retain(|at| now.saturating_sub(*at) < WINDOW_SECS);
+        self.resolutions.push(now);
+        if self.resolutions.len() < RESOLUTIONS {
+            return Outcome::Configured;
+        }
+        self.safe_mode = true;
+        Outcome::Suspended
+    }
+
+    /// Whether the server starts with the defaults of the extension alone.
+    pub(crate) fn safe_mode(&self) -> bool {
+        self.safe_mode
+    }
+}
+
+/// Why the configuration of the server of `root` is suspended.
+pub(crate) fn suspended(root: &str) -> String {
+    format!(
+        "typos-lsp was started {RESOLUTIONS} times within {WINDOW_SECS} seconds for {root}, \
+         which a configuration crashing it causes: starting it with the default options \
+         alone, without the typos configuration of the settings or the worktree, nor the \
+         generated options. Fix the configuration, then change the settings or restart Zed \
+         to use it again"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CrashLoop, Outcome, RESOLUTIONS, WINDOW_SECS};
+
+    #[test]
+    fn detection() {
+        let mut crash_loop = CrashLoop::default();
+        for at in 0..RESOLUTIONS as u64 - 1 {
+            assert_eq!(crash_loop.record(at * 10, "a"), Outcome::Configured);
+        }
+        assert!(!crash_loop.safe_mode());
+        assert_eq!(crash_loop.record(30, "a"), Outcome::Suspended);
+        assert!(crash_loop.safe_mode());
+        assert_eq!(crash_loop.record(40, "a"), Outcome::StillSuspended);
+        // Suspended for the session, however long ago the loop was.
+        assert_eq!(crash_loop.record(10_000, "a"), Outcome::StillSuspended);
+    }
+
+    #[test]
+    fn spaced_resolutions() {
+        let mut crash_loop = CrashLoop::default();
+        for start in 0..10 {
+            assert_eq!(
+                crash_loop.record(start * WINDOW_SECS / 2, "a"),
+                Outcome::Configured
+            );
+        }
+        assert!(!crash_loop.safe_mode());
+    }
+
+    #[test]
+    fn settings_change_recovers() {
+        let mut crash_loop = CrashLoop::default();
+        for _ in 0..RESOLUTIONS {
+            crash_loop.record(0, "a");
+        }
+        assert!(crash_loop.safe_mode());
+
+        assert_eq!(crash_loop.record(1, "b"), Outcome::Configured);
+        assert!(!crash_loop.safe_mode());
+        // Counted again from the change.
+        for _ in 0..RESOLUTIONS - 2 {
+            assert_eq!(crash_loop.record(2, "b"), Outcome::Configured);
+        }
+        assert_eq!(crash_loop.record(3, "b"), Outcome::Suspended);
+    }
+}
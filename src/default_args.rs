@@ -0,0 +1,222 @@
+//! Arguments the extension adds when starting the typos-lsp releases it installs.
+//!
+//! Some releases change a default in a way a command line flag compensates for, and
+//! this is where such mitigations go until upstream ships a fix. Binaries found on
+//! the `$PATH` are the user's own and are started exactly as configured.
+//!
+//! The `arguments_mode` setting overrides this for wrappers inspecting their arguments:
+//! only the user's arguments, or none at all as before arguments were supported.
+
+use crate::{report::Provenance, version::Version};
+
+/// The first and last affected releases, both included, and the arguments to add.
+type Mitigation = (&'static str, &'static str, &'static [&'static str]);
+
+/// Mitigations for the managed releases, applied in order, each citing the upstream
+/// issue it works around. No release needs one yet.
+///
+/// For instance `("v0.1.40", "v0.1.41", &["--log-level", "warn"])` would quiet a
+/// logging default spamming stderr in those two releases.
+const MITIGATIONS: &[Mitigation] = &[];
+
+/// How the arguments to start typos-lsp with are composed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) enum ArgumentsMode {
+    /// The defaults of managed releases, then the user's arguments.
+    #[default]
+    Append,
+    /// The user's arguments alone.
+    Replace,
+    /// No arguments at all, ignoring the user's.
+    None,
+}
+
+impl ArgumentsMode {
+    pub(crate) fn parse(value: &str) -> Option<Self> {
+        match value {
+            "append" => Some(Self::Append),
+            "replace" => Some(Self::Replace),
+            "none" => Some(Self::None),
+            _ => None,
+        }
+    }
+}
+
+/// The default arguments of `version` according to `table`.
+fn lookup(table: &[Mitigation], version: &Version) -> Vec<&'static str> {
+    table
+        .iter()
+        .filter(
+            |(first, last, _)| match (Version::parse(first), Version::parse(last)) {
+                (Some(first), Some(last)) => (first..=last).contains(version),
+                _ => false,
+            },
+        )
+        .flat_map(|(_, _, arguments)| arguments.iter().copied())
+        .collect()
+}
+
+/// The arguments to start a binary with: the defaults of managed releases, then the
+/// ones the user configured, so that the user always has the last word, unless `mode`
+/// says otherwise.
+pub(crate) fn compose(
+    mode: ArgumentsMode,
+    provenance: Provenance,
+    version: Option<&str>,
+    user_arguments: Vec<String>,
+) -> Vec<String> {
+    compose_with(MITIGATIONS, mode, provenance, version, user_arguments)
+}
+
+fn compose_with(
+    table: &[Mitigation],
+    mode: ArgumentsMode,
+    provenance: Provenance,
+    version: Option<&str>,
+    user_arguments: Vec<String>,
+) -> Vec<String> {
+    let version = version.and_then(Version::parse);
+    let defaults = match (mode, provenance, version) {
+        (ArgumentsMode::None, _, _) => return Vec::new(),
+        (ArgumentsMode::Replace, _, _)
+        | (_, Provenance::Settings | Provenance::Path, _)
+        | (_, _, None) => Vec::new(),
+        (ArgumentsMode::Append, Provenance::Cached | Provenance::Downloaded, Some(version)) => {
+            lookup(table, &version)
+        }
+    };
+    defaults
+        .into_iter()
+        .map(str::to_string)
+        .chain(user_arguments)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{compose, compose_with, lookup, ArgumentsMode, Mitigation};
+    use crate::{report::Provenance, version::Version};
+
+    const TABLE: &[Mitigation] = &[
+        ("v0.1.40", "v0.1.41", &["--log-level", "warn"]),
+        ("v0.1.41", "v0.1.41", &["--no-progress"]),
+        ("v0.2.0", "not a version", &["--never"]),
+    ];
+
+    fn defaults(tag: &str) -> Vec<&'static str> {
+        lookup(TABLE, &Version::parse(tag).unwrap())
+    }
+
+    #[test]
+    fn lookups() {
+        assert!(defaults("v0.1.39").is_empty());
+        assert_eq!(defaults("0.1.40"), ["--log-level", "warn"]);
+        assert_eq!(
+            defaults("v0.1.41"),
+            ["--log-level", "warn", "--no-progress"]
+        );
+        // A pre-release comes before the release it leads to.
+        assert!(defaults("v0.1.40-rc.1").is_empty());
+        assert!(defaults("v0.1.42").is_empty());
+        assert!(defaults("v0.2.0").is_empty());
+    }
+
+    #[test]
+    fn defaults_come_before_user_arguments() {
+        let user = vec!["--log-level".to_string(), "debug".to_string()];
+        for provenance in [Provenance::Cached, Provenance::Downloaded] {
+            assert_eq!(
+                compose_with(
+                    TABLE,
+                    ArgumentsMode::Append,
+                    provenance,
+                    Some("v0.1.40"),
+                    user.clone()
+                ),
+                ["--log-level", "warn", "--log-level", "debug"]
+            );
+        }
+        assert_eq!(
+            compose_with(
+                TABLE,
+                ArgumentsMode::Append,
+                Provenance::Downloaded,
+                Some("v0.1.39"),
+                user.clone()
+            ),
+            user
+        );
+    }
+
+    #[test]
+    fn path_binaries_are_untouched() {
+        let user = vec!["--stdio".to_string()];
+        assert_eq!(
+            compose_with(
+                TABLE,
+                ArgumentsMode::Append,
+                Provenance::Path,
+                Some("v0.1.40"),
+                user.clone()
+            ),
+            user
+        );
+        assert_eq!(
+            compose_with(
+                TABLE,
+                ArgumentsMode::Append,
+                Provenance::Path,
+                None,
+                Vec::new()
+            ),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            compose_with(
+                TABLE,
+                ArgumentsMode::Append,
+                Provenance::Downloaded,
+                None,
+                user.clone()
+            ),
+            user
+        );
+        assert_eq!(
+            compose(ArgumentsMode::Append, Provenance::Path, None, user.clone()),
+            user
+        );
+    }
+
+    #[test]
+    fn modes() {
+        let user = vec!["--stdio".to_string()];
+        let compose = |mode, provenance, user: &Vec<String>| {
+            compose_with(TABLE, mode, provenance, Some("v0.1.40"), user.clone())
+        };
+        for provenance in [Provenance::Cached, Provenance::Downloaded] {
+            assert_eq!(
+                compose(ArgumentsMode::Append, provenance, &user),
+                ["--log-level", "warn", "--stdio"]
+            );
+            assert_eq!(compose(ArgumentsMode::Replace, provenance, &user), user);
+            assert!(compose(ArgumentsMode::None, provenance, &user).is_empty());
+            assert!(compose(ArgumentsMode::Replace, provenance, &Vec::new()).is_empty());
+        }
+        for provenance in [Provenance::Path, Provenance::Settings] {
+            assert_eq!(compose(ArgumentsMode::Append, provenance, &user), user);
+            assert_eq!(compose(ArgumentsMode::Replace, provenance, &user), user);
+            assert!(compose(ArgumentsMode::None, provenance, &user).is_empty());
+        }
+    }
+
+    #[test]
+    fn mode_names() {
+        assert_eq!(ArgumentsMode::parse("append"), Some(ArgumentsMode::Append));
+        assert_eq!(
+            ArgumentsMode::parse("replace"),
+            Some(ArgumentsMode::Replace)
+        );
+        assert_eq!(ArgumentsMode::parse("none"), Some(ArgumentsMode::None));
+        assert_eq!(ArgumentsMode::parse("empty"), None);
+    }
+}
@@ -0,0 +1,92 @@
+//! The `disable_default_excludes` setting, checking the files typos skips by default.
+//!
+//! typos skips hidden files, the files ignored by `.gitignore` and the other ignore
+//! files, binary files, and lock files. The setting turns each of these off in the
+//! generated configuration. Patterns excluded with `extend-exclude` are not defaults but
+//! explicit excludes: they still apply.
+
+use crate::typos_config::{TomlValue, TyposConfig};
+
+/// The options turning the default excludes off, by table.
+pub(crate) const OPTIONS: &[(&str, &str, bool)] = &[
+    ("files", "ignore-hidden", false),
+    ("files", "ignore-files", false),
+    ("files", "ignore-dot", false),
+    ("files", "ignore-vcs", false),
+    ("files", "ignore-global", false),
+    ("files", "ignore-parent", false),
+    ("default", "binary", true),
+    ("type.lock", "check-file", true),
+];
+
+/// Add the options turning the default excludes off to `config`.
+pub(crate) fn apply(config: &mut TyposConfig) {
+    for (table, key, value) in OPTIONS {
+        config.set(table, key, TomlValue::Bool(*value));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::apply;
+    use crate::{
+        effective_config,
+        typos_config::{TomlValue, TyposConfig},
+    };
+
+    fn generated(disable_default_excludes: bool) -> String {
+        let mut config = TyposConfig::default();
+        config.set(
+            "files",
+            "extend-exclude",
+            TomlValue::Array(vec![
+                TomlValue::String("vendor/".to_string()),
+                TomlValue::String("*.min.js".to_string()),
+            ]),
+        );
+        if disable_default_excludes {
+            apply(&mut config);
+        }
+        config.render()
+    }
+
+    #[test]
+    fn explicit_excludes_still_apply() {
+        assert_eq!(
+            generated(false),
+            "[files]\nextend-exclude = [\"vendor/\", \"*.min.js\"]\n"
+        );
+        assert_eq!(
+            generated(true),
+            "[default]\n\
+             binary = true\n\
+             \n\
+             [files]\n\
+             extend-exclude = [\"vendor/\", \"*.min.js\"]\n\
+             ignore-dot = false\n\
+             ignore-files = false\n\
+             ignore-global = false\n\
+             ignore-hidden = false\n\
+             ignore-parent = false\n\
+             ignore-vcs = false\n\
+             \n\
+             [type.lock]\n\
+             check-file = true\n"
+        );
+    }
+
+    #[test]
+    fn effective_configuration() {
+        let source = "[files]\nextend-exclude = [\"vendor/\"]\n";
+        let mut generated = TyposConfig::default();
+        apply(&mut generated);
+        let rendered = effective_config::render(Some(("typos.toml", source)), &generated);
+        for line in [
+            "ignore-vcs = false\n",
+            "extend-exclude = [\"vendor/\"]\n",
+            "[type.lock]\ncheck-file = true\n",
+        ] {
+            assert!(rendered.contains(line), "{rendered}");
+        }
+    }
+}
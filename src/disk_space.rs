@@ -0,0 +1,74 @@
+//! Whether the work directory can hold a typos-lsp download.
+//!
+//! The extension API reports neither the size of release assets nor the free space of
+//! the disk, so the space an installation takes is estimated from the typos-lsp
+//! releases, and the check is skipped when the filesystem does not tell. WASI never
+//! does, so in Zed a full disk only shows as a failed download or extraction.
+
+const MIB: u64 = 1024 * 1024;
+
+/// A generous estimate of the size of a typos-lsp release archive.
+pub(crate) const ESTIMATED_ARCHIVE_SIZE: u64 = 10 * MIB;
+
+/// How much larger than its archive the extracted binary is, at most.
+const EXTRACTION_FACTOR: u64 = 3;
+
+/// The space installing an archive of `archive_size` takes: the archive itself while it
+/// is extracted, and the extracted binary.
+pub(crate) fn required(archive_size: u64) -> u64 {
+    archive_size.saturating_add(archive_size.saturating_mul(EXTRACTION_FACTOR))
+}
+
+/// The error for a disk without the `required` space, even once cleaned up.
+pub(crate) fn insufficient(version: &str, required: u64, available: u64) -> String {
+    format!(
+        "not enough disk space to install typos-lsp {version}: about {} are needed in the \
+         extension directory, {} are available once previous versions are removed",
+        format_size(required),
+        format_size(available),
+    )
+}
+
+pub(crate) fn format_size(bytes: u64) -> String {
+    const UNITS: &[&str] = &["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit + 1 < UNITS.len() {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{size:.1} {}", UNITS[unit])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format_size, insufficient, required, ESTIMATED_ARCHIVE_SIZE, MIB};
+
+    #[test]
+    fn space_math() {
+        assert_eq!(required(ESTIMATED_ARCHIVE_SIZE), 40 * MIB);
+        assert_eq!(required(0), 0);
+        assert_eq!(required(u64::MAX), u64::MAX);
+    }
+
+    #[test]
+    fn sizes() {
+        assert_eq!(format_size(12), "12 B");
+        assert_eq!(format_size(1536), "1.5 KiB");
+        assert_eq!(format_size(40 * MIB), "40.0 MiB");
+        assert_eq!(format_size(3 * 1024 * 1024 * MIB), "3.0 TiB");
+        assert_eq!(format_size(u64::MAX), "16777216.0 TiB");
+    }
+
+    #[test]
+    fn insufficient_space() {
+        assert_eq!(
+            insufficient("v0.1.23", 40 * MIB, 3 * MIB / 2),
+            "not enough disk space to install typos-lsp v0.1.23: about 40.0 MiB are needed in \
+             the extension directory, 1.5 MiB are available once previous versions are removed"
+        );
+    }
+}
@@ -0,0 +1,208 @@
+//! Download failures caused by a host blocked after a redirect.
+//!
+//! Release assets are listed on github.com, but downloading one redirects to another
+//! host. Networks allowing github.com alone let the release lookup through and then
+//! block the download, which only reports a connection error. The download API does not
+//! expose the redirects it followed: the blocked host is taken from the error when it
+//! names one, and otherwise inferred from the host of the asset URL.
+
+/// The hosts that downloads from a host redirect to.
+const REDIRECTS: &[(&str, &[&str])] = &[(
+    "github.com",
+    &[
+        "objects.githubusercontent.com",
+        "release-assets.githubusercontent.com",
+    ],
+)];
+
+/// Errors telling that a connection was refused or cut, rather than that the asset is
+/// missing or the archive broken.
+const BLOCKED: &[&str] = &[
+    "connection refused",
+    "connection reset",
+    "connection closed",
+    "forbidden",
+    "403",
+    "blocked",
+    "proxy",
+    "dns error",
+    "failed to lookup address",
+    "could not resolve",
+    "no such host",
+];
+
+/// The host of `url`, without its port.
+fn host(url: &str) -> Option<&str> {
+    let (_, rest) = url.split_once("://")?;
+    let authority = rest.split(['/', '?', '#']).next()?;
+    let authority = authority
+        .rsplit_once('@')
+        .map_or(authority, |(_, host)| host);
+    let host = authority.split(':').next()?;
+    (!host.is_empty()).then_some(host)
+}
+
+/// The hosts of the URLs in `text`, in order.
+fn hosts_in(text: &str) -> Vec<&str> {
+    text.match_indices("http")
+        .filter_map(|(start, _)| {
+            let url = text[start..]
+                .split(|c: char| c.is_whitespace() || matches!(c, ')' | '"' | '\'' | '>'))
+                .next()?;
+            url.contains("://").then(|| host(url)).flatten()
+        })
+        .collect()
+}
+
+/// Where downloading from `url` failed with `error`, when it is a host the download was
+/// redirected to and a connection to it was refused.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct BlockedRedirect<'a> {
+    /// The host of the asset URL.
+    pub from: &'a str,
+    /// The hosts that were blocked, or that the download is known to redirect to.
+    pub to: Vec<&'a str>,
+    /// Whether `to` was named by the error rather than inferred.
+    pub reported: bool,
+}
+
+/// The blocked redirect explaining `error`, if any.
+pub(crate) fn blocked_redirect<'a>(url: &'a str, error: &'a str) -> Option<BlockedRedirect<'a>> {
+    let lowercase = error.to_lowercase();
+    if !BLOCKED.iter().any(|pattern| lowercase.contains(pattern)) {
+        return None;
+    }
+    let from = host(url)?;
+    let (_, redirects) = REDIRECTS
+        .iter()
+        .find(|(origin, _)| from.eq_ignore_ascii_case(origin))?;
+    let hosts = hosts_in(error);
+    if hosts.is_empty() {
+        return Some(BlockedRedirect {
+            from,
+            to: redirects.to_vec(),
+            reported: false,
+        });
+    }
+    // A download failing on the host of the asset URL itself was not redirected.
+    let named: Vec<&str> = hosts
+        .into_iter()
+        .filter(|host| !host.eq_ignore_ascii_case(from))
+        .collect();
+    (!named.is_empty()).then_some(BlockedRedirect {
+        from,
+        to: named,
+        reported: true,
+    })
+}
+
+/// `error`, downloading `url`, with what to do about it when a redirect was blocked.
+pub(crate) fn explain(url: &str, error: &str) -> String {
+    let Some(blocked) = blocked_redirect(url, error) else {
+        return error.to_string();
+    };
+    let (to, them) = (blocked.to.join(" and "), blocked.to.len() > 1);
+    let found = if blocked.reported {
+        format!("its download was redirected to {to}, which refused the connection",)
+    } else {
+        let seem = if them { "seem" } else { "seems" };
+        format!("its downloads redirect to {to}, which {seem} blocked")
+    };
+    format!(
+        "{error}; the release was found on {}, but {found}: allow-list {}, or install \
+         typos-lsp yourself and set its `path` under `lsp.typos.initialization_options`",
+        blocked.from,
+        if them { "these hosts" } else { "this host" },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{blocked_redirect, explain, host, BlockedRedirect};
+
+    const ASSET: &str = "https://github.com/tekumara/typos-lsp/releases/download/v0.1.23/\
+                         typos-lsp-v0.1.23-x86_64-unknown-linux-gnu.tar.gz";
+
+    #[test]
+    fn hosts() {
+        assert_eq!(host(ASSET), Some("github.com"));
+        assert_eq!(
+            host("http://user@mirror.local:8080/a"),
+            Some("mirror.local")
+        );
+        assert_eq!(host("https://example.com?x"), Some("example.com"));
+        assert_eq!(host("github.com/a"), None);
+        assert_eq!(host("https:///a"), None);
+    }
+
+    #[test]
+    fn classification() {
+        // The redirect chain is not reported: inferred from the asset host.
+        assert_eq!(
+            blocked_redirect(ASSET, "Connection refused (os error 111)"),
+            Some(BlockedRedirect {
+                from: "github.com",
+                to: vec![
+                    "objects.githubusercontent.com",
+                    "release-assets.githubusercontent.com"
+                ],
+                reported: false,
+            })
+        );
+        // The error names the host it failed to reach.
+        let error = "error sending request for url \
+                     (https://objects.githubusercontent.com/github-production-release-asset/1): \
+                     connection closed before message completed";
+        assert_eq!(
+            blocked_redirect(ASSET, error),
+            Some(BlockedRedirect {
+                from: "github.com",
+                to: vec!["objects.githubusercontent.com"],
+                reported: true,
+            })
+        );
+        let proxy =
+            "status 403 Forbidden from proxy for https://release-assets.githubusercontent.com/x";
+        assert_eq!(
+            blocked_redirect(ASSET, proxy).map(|blocked| blocked.to),
+            Some(vec!["release-assets.githubusercontent.com"])
+        );
+
+        // Failing on github.com itself is not a redirect.
+        assert_eq!(
+            blocked_redirect(ASSET, "connection refused: https://github.com/tekumara"),
+            None
+        );
+        // Nor are errors other than refused connections.
+        assert_eq!(blocked_redirect(ASSET, "status 404 Not Found"), None);
+        assert_eq!(blocked_redirect(ASSET, "invalid gzip header"), None);
+        // Other hosts have no known redirect.
+        assert_eq!(
+            blocked_redirect("https://example.com/typos-lsp.tar.gz", "connection refused"),
+            None
+        );
+    }
+
+    #[test]
+    fn explanations() {
+        assert_eq!(
+            explain(ASSET, "connection refused"),
+            "connection refused; the release was found on github.com, but its downloads \
+             redirect to objects.githubusercontent.com and release-assets.githubusercontent.com, \
+             which seem blocked: allow-list these hosts, or install typos-lsp yourself and set \
+             its `path` under `lsp.typos.initialization_options`"
+        );
+        assert_eq!(
+            explain(
+                ASSET,
+                "connection reset for https://objects.githubusercontent.com/a"
+            ),
+            "connection reset for https://objects.githubusercontent.com/a; the release was \
+             found on github.com, but its download was redirected to \
+             objects.githubusercontent.com, which refused the connection: allow-list this \
+             host, or install typos-lsp yourself and set its `path` under \
+             `lsp.typos.initialization_options`"
+        );
+        assert_eq!(explain(ASSET, "invalid gzip header"), "invalid gzip header");
+    }
+}
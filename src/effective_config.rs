@@ -0,0 +1,239 @@
+//! The typos configuration in effect for a worktree, written where other tools can use it.
+//!
+//! The extension sandbox cannot write into worktrees, so the file is kept in the work
+//! directory, at a path only depending on the worktree root and the setting, which is
+//! logged for other tools to use, e.g. `typos --config` in a pre-commit hook. It is
+//! rewritten whenever the server starts or the settings change, and removed once
+//! `write_effective_config` is turned off.
+
+use std::{
+    ffi::OsStr,
+    path::{Path, PathBuf},
+};
+
+use crate::{hashing, host::Fs, messages, typos_config::TyposConfig, worktree_files};
+
+/// The file written by default, of which only the name is used.
+pub(crate) const DEFAULT_PATH: &str = ".zed/typos-effective.toml";
+
+/// Where the files are kept, in the work directory, one directory per worktree.
+pub(crate) const DIR: &str = "effective-configs";
+
+/// The configuration typos-lsp reads: its source file if any, with the options
+/// generated from the settings layered over it.
+pub(crate) fn render(source: Option<(&str, &str)>, generated: &TyposConfig) -> String {
+    let mut rendered = String::from(
+        "# Generated by the typos Zed extension, do not edit: changes are overwritten.\n\
+         # The configuration typos-lsp uses for this worktree, e.g. for `typos --config`.\n",
+    );
+    match source {
+        Some((path, content)) => {
+            rendered.push_str(&format!("# Source: {path}\n\n"));
+            rendered.push_str(&generated.merge_into(content));
+        }
+        None if generated.is_empty() => {
+            rendered.push_str("# Source: none, typos defaults apply.\n")
+        }
+        None => {
+            rendered.push_str("# Source: the extension settings.\n\n");
+            rendered.push_str(&generated.render());
+        }
+    }
+    rendered
+}
+
+/// Where the file is written for the worktree at `root` when the setting is `target`,
+/// relative to the work directory: the file name of `target`, in the directory of the
+/// worktree.
+pub(crate) fn path(root: &str, target: &str) -> PathBuf {
+    let name = Path::new(target)
+        .file_name()
+        .or_else(|| Path::new(DEFAULT_PATH).file_name())
+        .unwrap_or(OsStr::new(DEFAULT_PATH));
+    dir(root).join(name)
+}
+
+fn dir(root: &str) -> PathBuf {
+    Path::new(DIR).join(&hashing::sha256_hex(root.as_bytes())[..16])
+}
+
+/// Bring the file of the worktree at `root` in line with the setting: write `content`
+/// for `target`, [`worktree_files::normalized`], unless the file already has it, or
+/// remove the file when `target` is `None`. Returns the path of the file written, if
+/// any, relative to the work directory.
+///
+/// When `content` fails, the file is left as it is.
+pub(crate) fn sync(
+    fs: &dyn Fs,
+    root: &str,
+    target: Option<&str>,
+    content: impl FnOnce() -> Result<String, String>,
+) -> Result<Option<PathBuf>, String> {
+    let Some(target) = target else {
+        remove(fs, root);
+        return Ok(None);
+    };
+    let path = path(root, target);
+    let content = worktree_files::normalized(&content()?);
+    if fs.read_to_string(&path).ok().as_deref() == Some(content.as_str()) {
+        return Ok(Some(path));
+    }
+    // The setting may have named the file otherwise before.
+    remove(fs, root);
+    fs.create_dir_all(&dir(root))
+        .and_then(|()| fs.write_atomically(&path, content.as_bytes()))
+        .map_err(|e| messages::write_failed(&path, e))?;
+    Ok(Some(path))
+}
+
+/// Remove the file of the worktree at `root`, returning whether there was one.
+pub(crate) fn remove(fs: &dyn Fs, root: &str) -> bool {
+    fs.remove_dir_all(&dir(root)).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::Path};
+
+    use super::{path, remove, render, sync, DEFAULT_PATH, DIR};
+    use crate::{
+        host::StdFs,
+        test_support::TempDir,
+        typos_config::{TomlValue, TyposConfig},
+    };
+
+    const ROOT: &str = "/work/project";
+
+    struct Setup {
+        temp: TempDir,
+        fs: StdFs,
+    }
+
+    impl Setup {
+        fn new(name: &str) -> Self {
+            let temp = TempDir::new(name);
+            let fs = StdFs::new(temp.path());
+            Self { temp, fs }
+        }
+
+        fn sync(&self, target: Option<&str>, content: &str) {
+            sync(&self.fs, ROOT, target, || Ok(content.to_string())).unwrap();
+        }
+
+        fn read(&self, target: &str) -> Option<String> {
+            fs::read_to_string(self.temp.path().join(path(ROOT, target))).ok()
+        }
+
+        fn modified(&self, target: &str) -> std::time::SystemTime {
+            fs::metadata(self.temp.path().join(path(ROOT, target)))
+                .unwrap()
+                .modified()
+                .unwrap()
+        }
+    }
+
+    #[test]
+    fn paths() {
+        let path = path(ROOT, DEFAULT_PATH);
+        assert!(path.starts_with(DIR));
+        assert_eq!(path.file_name().unwrap(), "typos-effective.toml");
+        assert_eq!(
+            super::path(ROOT, "ci/typos.toml"),
+            path.with_file_name("typos.toml")
+        );
+        assert_ne!(super::path("/work/other", DEFAULT_PATH), path);
+        // Nothing escapes the directory of the worktree.
+        assert_eq!(super::path(ROOT, "../.."), path);
+        assert_eq!(super::path(ROOT, "/etc/passwd").parent(), path.parent());
+        assert!(!Path::new(&super::path(ROOT, "/etc/passwd")).is_absolute());
+    }
+
+    #[test]
+    fn rendering() {
+        let none = TyposConfig::default();
+        let rendered = render(Some(("typos.toml", "[default]\nlocale = \"en-gb\"")), &none);
+        assert!(rendered.starts_with("# Generated by the typos Zed extension"));
+        assert!(rendered.ends_with("# Source: typos.toml\n\n[default]\nlocale = \"en-gb\"\n"));
+        assert!(render(None, &none).ends_with("# Source: none, typos defaults apply.\n"));
+
+        let mut generated = TyposConfig::default();
+        generated.set("default", "check-filename", TomlValue::Bool(false));
+        let rendered = render(
+            Some(("typos.toml", "[default]\nlocale = \"en-gb\"")),
+            &generated,
+        );
+        assert!(rendered.ends_with("[default]\ncheck-filename = false\nlocale = \"en-gb\"\n"));
+        let rendered = render(None, &generated);
+        assert!(rendered.ends_with("settings.\n\n[default]\ncheck-filename = false\n"));
+    }
+
+    #[test]
+    fn updates_and_no_op() {
+        let setup = Setup::new("effective-update");
+        setup.sync(Some(DEFAULT_PATH), "a = 1\n");
+        assert_eq!(setup.read(DEFAULT_PATH).as_deref(), Some("a = 1\n"));
+        let written = setup.modified(DEFAULT_PATH);
+
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        setup.sync(Some(DEFAULT_PATH), "a = 1\n");
+        assert_eq!(setup.modified(DEFAULT_PATH), written);
+
+        setup.sync(Some(DEFAULT_PATH), "a = 2\n");
+        assert_eq!(setup.read(DEFAULT_PATH).as_deref(), Some("a = 2\n"));
+        // The file is left as it is when its content fails.
+        assert!(sync(&setup.fs, ROOT, Some(DEFAULT_PATH), || Err("e".to_string())).is_err());
+        assert_eq!(setup.read(DEFAULT_PATH).as_deref(), Some("a = 2\n"));
+    }
+
+    #[test]
+    fn regenerating_is_byte_identical() {
+        let setup = Setup::new("effective-regenerate");
+        let mut generated = TyposConfig::default();
+        generated.set("default", "check-filename", TomlValue::Bool(false));
+        generated.set("type.po", "check-file", TomlValue::Bool(false));
+        let sources = [
+            None,
+            Some(("typos.toml", "")),
+            Some(("typos.toml", "[default]\r\nlocale = \"en-gb\"\r\n\r\n")),
+        ];
+        for source in sources {
+            let rendered = render(source, &generated);
+            assert_eq!(render(source, &generated), rendered);
+            setup.sync(Some(DEFAULT_PATH), &rendered);
+            let written = setup.read(DEFAULT_PATH).unwrap();
+            assert!(
+                written.ends_with('\n') && !written.ends_with("\n\n"),
+                "{written:?}"
+            );
+            assert!(!written.contains('\r'), "{written:?}");
+
+            let modified = setup.modified(DEFAULT_PATH);
+            std::thread::sleep(std::time::Duration::from_millis(20));
+            setup.sync(Some(DEFAULT_PATH), &render(source, &generated));
+            assert_eq!(setup.read(DEFAULT_PATH).unwrap(), written);
+            assert_eq!(setup.modified(DEFAULT_PATH), modified);
+        }
+    }
+
+    #[test]
+    fn cleanup() {
+        let setup = Setup::new("effective-cleanup");
+        setup.sync(Some(DEFAULT_PATH), "a = 1\n");
+
+        // Renaming the file removes the previous one.
+        setup.sync(Some("ci/typos.toml"), "a = 1\n");
+        assert_eq!(setup.read(DEFAULT_PATH), None);
+        assert_eq!(setup.read("ci/typos.toml").as_deref(), Some("a = 1\n"));
+
+        // Turning the setting off removes it.
+        setup.sync(None, "");
+        assert_eq!(setup.read("ci/typos.toml"), None);
+        assert!(!remove(&setup.fs, ROOT));
+        setup.sync(Some(DEFAULT_PATH), "a = 1\n");
+        assert!(remove(&setup.fs, ROOT));
+        assert_eq!(
+            fs::read_dir(setup.temp.path().join(DIR)).unwrap().count(),
+            0
+        );
+    }
+}
@@ -0,0 +1,263 @@
+//! The errors of resolving and installing typos-lsp, and of reading the settings.
+//!
+//! Each kind of failure is a variant of [`TyposExtensionError`], whose message says what
+//! failed and what to do about it: a network problem reads differently from an
+//! unsupported platform, a work directory that cannot be written, or a wrong setting.
+//! The extension API takes errors as strings, so a variant is only rendered when it is
+//! returned to Zed, by the entry points of the extension, or logged. Failures described
+//! by a message alone convert into [`TyposExtensionError::Failed`] with `?`.
+
+use std::fmt::{self, Display};
+
+/// Where the releases are listed, for lookups that fail.
+const RELEASES_URL: &str = "https://github.com/tekumara/typos-lsp/releases";
+
+/// What failed, with what the user needs to act on it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum TyposExtensionError {
+    /// Looking up the release `tag`, or the latest release when `None`, failed.
+    ReleaseLookup { tag: Option<String>, reason: String },
+    /// No asset of the release `tag` has one of the `wanted` names.
+    AssetNotFound {
+        tag: String,
+        wanted: Vec<String>,
+        available: Vec<String>,
+    },
+    /// GitHub refused release lookups for its rate limit, until `retry_at` in seconds
+    /// since the Unix epoch, `minutes` from now.
+    RateLimited { retry_at: u64, minutes: u64 },
+    /// Downloading an asset failed, as `reason` explains.
+    Download { reason: String },
+    /// The binary extracted to `binary` cannot be run, as it is `problem`.
+    Extraction { binary: String, problem: String },
+    /// No typos-lsp release is built for `platform`, as `reason` tells when known.
+    UnsupportedPlatform {
+        platform: String,
+        reason: Option<String>,
+    },
+    /// The setting `key` is ignored, as `reason` explains.
+    InvalidSetting { key: String, reason: String },
+    /// Doing `action` in the extension work directory failed with `error`.
+    Io { action: String, error: String },
+    /// Resolving the binary for the worktree at `root`, which shares the server with
+    /// others, failed with `error`.
+    Worktree { root: String, error: Box<Self> },
+    /// A failure without a variant of its own, described by its message.
+    Failed(String),
+}
+
+impl TyposExtensionError {
+    /// The setting `key` is ignored, as `reason` explains.
+    pub(crate) fn invalid_setting(key: &str, reason: impl Display) -> Self {
+        Self::InvalidSetting {
+            key: key.to_string(),
+            reason: reason.to_string(),
+        }
+    }
+
+    /// Resolving the binary for the worktree at `root` failed with `error`.
+    pub(crate) fn in_worktree(root: &str, error: Self) -> Self {
+        Self::Worktree {
+            root: root.to_string(),
+            error: Box::new(error),
+        }
+    }
+
+    /// Doing `action` in the work directory failed with `error`.
+    pub(crate) fn io(action: impl Display, error: impl Display) -> Self {
+        Self::Io {
+            action: action.to_string(),
+            error: error.to_string(),
+        }
+    }
+}
+
+impl Display for TyposExtensionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ReleaseLookup {
+                tag: Some(tag),
+                reason,
+            } => write!(
+                f,
+                "failed to find typos-lsp release {tag}: {reason}. Check that {tag} is listed \
+                 at {RELEASES_URL}, or pin another `version`"
+            ),
+            Self::ReleaseLookup { tag: None, reason } => write!(
+                f,
+                "failed to look up the latest typos-lsp release: {reason}. Check the network \
+                 connection and the `download` setting, or pin a `version` installed already"
+            ),
+            Self::AssetNotFound {
+                tag,
+                wanted,
+                available,
+            } => write!(
+                f,
+                "no asset found matching {wanted:?} in typos-lsp {tag}, which has \
+                 {available:?}: set `binary.path` to a typos-lsp built for this platform, or \
+                 report these names at https://github.com/tekumara/typos-lsp/issues"
+            ),
+            Self::RateLimited { retry_at, minutes } => write!(
+                f,
+                "GitHub is rate limiting release lookups from this network, so typos-lsp \
+                 releases cannot be looked up before {time} UTC, in {minutes} minutes, when \
+                 the next attempt happens. Until then an installed typos-lsp is used when \
+                 there is one; pin a `version` installed already, or set up a `download` \
+                 mirror",
+                time = utc_time(*retry_at),
+            ),
+            Self::Download { reason } => write!(
+                f,
+                "failed to download file: {reason}. The download is tried again at the next \
+                 start; check the network connection and the `download` setting"
+            ),
+            Self::Extraction { binary, problem } => write!(
+                f,
+                "the extracted {binary} is {problem}, the archive may be corrupted. Nothing \
+                 was installed, the download is tried again at the next start"
+            ),
+            Self::UnsupportedPlatform { platform, reason } => {
+                write!(f, "{platform} is not supported by typos-lsp releases")?;
+                if let Some(reason) = reason {
+                    write!(f, ": {reason}")?;
+                }
+                write!(f, ". Set `binary.path` to a typos-lsp built for this host")
+            }
+            Self::InvalidSetting { key, reason } => write!(f, "ignoring `{key}`: {reason}"),
+            Self::Io { action, error } => write!(
+                f,
+                "failed to {action}: {error}. Check that the extension work directory is \
+                 writable and has free space"
+            ),
+            Self::Worktree { root, error } => write!(f, "worktree {root}: {error}"),
+            Self::Failed(message) => f.write_str(message),
+        }
+    }
+}
+
+/// The time of day of `seconds` since the Unix epoch, in UTC, e.g. `14:05`.
+fn utc_time(seconds: u64) -> String {
+    let of_day = seconds % (24 * 60 * 60);
+    format!("{:02}:{:02}", of_day / 3600, of_day % 3600 / 60)
+}
+
+impl From<TyposExtensionError> for String {
+    fn from(error: TyposExtensionError) -> Self {
+        error.to_string()
+    }
+}
+
+impl From<String> for TyposExtensionError {
+    fn from(message: String) -> Self {
+        Self::Failed(message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TyposExtensionError;
+
+    #[test]
+    fn messages() {
+        assert_eq!(
+            TyposExtensionError::ReleaseLookup {
+                tag: Some("v9.9.9".to_string()),
+                reason: "404 Not Found".to_string(),
+            }
+            .to_string(),
+            "failed to find typos-lsp release v9.9.9: 404 Not Found. Check that v9.9.9 is \
+             listed at https://github.com/tekumara/typos-lsp/releases, or pin another \
+             `version`"
+        );
+        assert_eq!(
+            TyposExtensionError::ReleaseLookup {
+                tag: None,
+                reason: "network unreachable".to_string(),
+            }
+            .to_string(),
+            "failed to look up the latest typos-lsp release: network unreachable. Check the \
+             network connection and the `download` setting, or pin a `version` installed \
+             already"
+        );
+        assert_eq!(
+            TyposExtensionError::AssetNotFound {
+                tag: "v0.1.23".to_string(),
+                wanted: vec!["typos-lsp-v0.1.23-aarch64-unknown-linux-musl.tar.gz".to_string()],
+                available: vec!["typos-lsp-v0.1.23-x86_64-unknown-linux-gnu.tar.gz".to_string()],
+            }
+            .to_string(),
+            "no asset found matching [\"typos-lsp-v0.1.23-aarch64-unknown-linux-musl.tar.gz\"] \
+             in typos-lsp v0.1.23, which has \
+             [\"typos-lsp-v0.1.23-x86_64-unknown-linux-gnu.tar.gz\"]: set `binary.path` to a \
+             typos-lsp built for this platform, or report these names at \
+             https://github.com/tekumara/typos-lsp/issues"
+        );
+        assert_eq!(
+            TyposExtensionError::RateLimited {
+                retry_at: 1_700_003_600,
+                minutes: 60,
+            }
+            .to_string(),
+            "GitHub is rate limiting release lookups from this network, so typos-lsp releases \
+             cannot be looked up before 23:13 UTC, in 60 minutes, when the next attempt \
+             happens. Until then an installed typos-lsp is used when there is one; pin a \
+             `version` installed already, or set up a `download` mirror"
+        );
+        assert_eq!(
+            TyposExtensionError::Download {
+                reason: "connection reset".to_string(),
+            }
+            .to_string(),
+            "failed to download file: connection reset. The download is tried again at the \
+             next start; check the network connection and the `download` setting"
+        );
+        assert_eq!(
+            TyposExtensionError::Extraction {
+                binary: "typos-lsp-0.1.23/typos-lsp".to_string(),
+                problem: "empty".to_string(),
+            }
+            .to_string(),
+            "the extracted typos-lsp-0.1.23/typos-lsp is empty, the archive may be corrupted. \
+             Nothing was installed, the download is tried again at the next start"
+        );
+        assert_eq!(
+            TyposExtensionError::UnsupportedPlatform {
+                platform: "32-bit x86".to_string(),
+                reason: Some("v0.1.23 has no i686 build".to_string()),
+            }
+            .to_string(),
+            "32-bit x86 is not supported by typos-lsp releases: v0.1.23 has no i686 build. Set \
+             `binary.path` to a typos-lsp built for this host"
+        );
+        assert_eq!(
+            TyposExtensionError::invalid_setting("update_timing", "expected \"on_open\"")
+                .to_string(),
+            "ignoring `update_timing`: expected \"on_open\""
+        );
+        assert_eq!(
+            String::from(TyposExtensionError::io(
+                "list the extension work directory",
+                "permission denied"
+            )),
+            "failed to list the extension work directory: permission denied. Check that the \
+             extension work directory is writable and has free space"
+        );
+        let error = TyposExtensionError::in_worktree(
+            "/work/p",
+            TyposExtensionError::Download {
+                reason: "connection reset".to_string(),
+            },
+        );
+        assert_eq!(
+            error.to_string(),
+            "worktree /work/p: failed to download file: connection reset. The download is \
+             tried again at the next start; check the network connection and the `download` \
+             setting"
+        );
+        assert_eq!(
+            TyposExtensionError::from("typos-lsp is disabled".to_string()).to_string(),
+            "typos-lsp is disabled"
+        );
+    }
+}
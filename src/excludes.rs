@@ -0,0 +1,133 @@
+//! The `exclude` setting, keeping files out of spell checking without a typos
+//! configuration file.
+//!
+//! Its globs are added to the `extend-exclude` patterns of `[files]` in the generated
+//! configuration, after the ones of the project. typos reads them as `.gitignore`
+//! patterns: a glob without a `/` matches a file name at any depth, e.g. `*.pb.go`, and
+//! `**` matches any number of directories. The globs typos would reject are caught here,
+//! so that the warning names the offending pattern.
+
+use crate::{extend_settings, typos_config::TyposConfig};
+
+/// The table and key of the excluded patterns.
+const TABLE: &str = "files";
+const KEY: &str = "extend-exclude";
+
+/// Why typos would reject `glob`, if it would.
+pub(crate) fn check(glob: &str) -> Result<(), String> {
+    if glob.trim().is_empty() {
+        return Err("the glob is empty".to_string());
+    }
+    let chars: Vec<char> = glob.chars().collect();
+    let mut braces = 0usize;
+    let mut i = 0;
+    while let Some(&c) = chars.get(i) {
+        match c {
+            '\\' if i + 1 == chars.len() => {
+                return Err("it ends with an escaping `\\`".to_string());
+            }
+            '\\' => i += 1,
+            '[' => {
+                // A `]` right after the opening bracket, or its negation, is a member.
+                let mut end = i + 1;
+                if matches!(chars.get(end), Some('!' | '^')) {
+                    end += 1;
+                }
+                if chars.get(end) == Some(&']') {
+                    end += 1;
+                }
+                while chars.get(end).is_some_and(|&c| c != ']') {
+                    end += 1;
+                }
+                if end == chars.len() {
+                    return Err("a `[` is not closed".to_string());
+                }
+                i = end;
+            }
+            '{' if braces > 0 => return Err("`{` cannot be nested".to_string()),
+            '{' => braces += 1,
+            '}' if braces == 0 => return Err("a `}` closes nothing".to_string()),
+            '}' => braces -= 1,
+            '*' if chars.get(i + 1) == Some(&'*') => {
+                let before = i.checked_sub(1).and_then(|before| chars.get(before));
+                let after = chars.get(i + 2);
+                if before.is_some_and(|&c| c != '/') || after.is_some_and(|&c| c != '/') {
+                    return Err("`**` must be a whole path component, e.g. `a/**/b`".to_string());
+                }
+                i += 1;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    if braces > 0 {
+        return Err("a `{` is not closed".to_string());
+    }
+    Ok(())
+}
+
+/// Add the excluded `globs` to the `generated` configuration, layered over the
+/// `explicit` configuration of the project.
+pub(crate) fn apply(generated: &mut TyposConfig, explicit: &TyposConfig, globs: &[String]) {
+    extend_settings::append(generated, explicit, TABLE, KEY, globs);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{apply, check};
+    use crate::typos_config::TyposConfig;
+
+    #[test]
+    fn globs() {
+        for glob in [
+            "**/vendor/**",
+            "*.pb.go",
+            "CHANGELOG.md",
+            "/generated/",
+            "docs/**/*.{md,txt}",
+            "[]a]*.rs",
+            "[!a-z]",
+            "file\\[1\\].txt",
+        ] {
+            assert_eq!(check(glob), Ok(()), "{glob}");
+        }
+        for (glob, error) in [
+            ("", "the glob is empty"),
+            (" ", "the glob is empty"),
+            ("src/[ab.rs", "a `[` is not closed"),
+            ("*.{md,txt", "a `{` is not closed"),
+            ("*.md}", "a `}` closes nothing"),
+            ("{a,{b,c}}", "`{` cannot be nested"),
+            (
+                "vendor**",
+                "`**` must be a whole path component, e.g. `a/**/b`",
+            ),
+            (
+                "**.rs",
+                "`**` must be a whole path component, e.g. `a/**/b`",
+            ),
+            ("a\\", "it ends with an escaping `\\`"),
+        ] {
+            assert_eq!(check(glob), Err(error.to_string()), "{glob}");
+        }
+    }
+
+    #[test]
+    fn project_excludes_are_kept() {
+        let explicit = TyposConfig::parse("[files]\nextend-exclude = [\"target/\"]\n").unwrap();
+        let mut generated = TyposConfig::default();
+        apply(
+            &mut generated,
+            &explicit,
+            &["**/vendor/**".to_string(), "target/".to_string()],
+        );
+        assert_eq!(
+            generated.render(),
+            "[files]\nextend-exclude = [\"target/\", \"**/vendor/**\"]\n"
+        );
+
+        let mut generated = TyposConfig::default();
+        apply(&mut generated, &explicit, &[]);
+        assert!(generated.is_empty());
+    }
+}
@@ -0,0 +1,130 @@
+//! The `extend_words` and `extend_ignore_identifiers` settings, declaring project jargon
+//! without a typos configuration file.
+//!
+//! Both are added to the generated configuration, which typos-lsp reads on top of the
+//! configuration of the project: `extend_words` to `[default.extend-words]`, below the
+//! corrections, and `extend_ignore_identifiers` to the `extend-ignore-identifiers-re`
+//! patterns of `[default]`. The generated configuration replaces the patterns of the
+//! project rather than extending them, so these are appended to the project ones.
+
+use std::collections::BTreeMap;
+
+use crate::typos_config::{TomlValue, TyposConfig};
+
+/// The key of the ignored identifier patterns, in `default`.
+pub(crate) const IDENTIFIERS_KEY: &str = "extend-ignore-identifiers-re";
+
+/// Add the accepted `words` and the ignored `identifiers` patterns to the `generated`
+/// configuration, layered over the `explicit` configuration of the project.
+pub(crate) fn apply(
+    generated: &mut TyposConfig,
+    explicit: &TyposConfig,
+    words: &BTreeMap<String, String>,
+    identifiers: &[String],
+) {
+    for (word, meaning) in words {
+        generated.set_word(
+            "default.extend-words",
+            word,
+            TomlValue::String(meaning.clone()),
+        );
+    }
+    append(generated, explicit, "default", IDENTIFIERS_KEY, identifiers);
+}
+
+/// Append `values` to the array `table.key` of the `generated` configuration, after
+/// the values of the `explicit` configuration of the project and the ones generated
+/// already, which the generated array would otherwise replace. Nothing is set for no
+/// `values`.
+pub(crate) fn append(
+    generated: &mut TyposConfig,
+    explicit: &TyposConfig,
+    table: &str,
+    key: &str,
+    values: &[String],
+) {
+    if values.is_empty() {
+        return;
+    }
+    let mut merged = Vec::new();
+    for config in [explicit, &*generated] {
+        if let Some(TomlValue::Array(below)) = config.get(table, key) {
+            merged.extend(below.iter().cloned());
+        }
+    }
+    merged.extend(values.iter().cloned().map(TomlValue::String));
+    let mut unique = Vec::new();
+    for value in merged {
+        if !unique.contains(&value) {
+            unique.push(value);
+        }
+    }
+    generated.set(table, key, TomlValue::Array(unique));
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::apply;
+    use crate::typos_config::TyposConfig;
+
+    fn words(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs
+            .iter()
+            .map(|(word, meaning)| (word.to_string(), meaning.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn generated_entries() {
+        let mut generated = TyposConfig::default();
+        apply(
+            &mut generated,
+            &TyposConfig::default(),
+            &words(&[("ODF", "ODF"), ("typ", "typ")]),
+            &["ba".to_string()],
+        );
+        assert_eq!(
+            generated.render(),
+            "[default]\n\
+             extend-ignore-identifiers-re = [\"ba\"]\n\
+             \n\
+             [default.extend-words]\n\
+             ODF = \"ODF\"\n\
+             typ = \"typ\"\n"
+        );
+
+        // Nothing is generated from empty settings.
+        let mut generated = TyposConfig::default();
+        apply(
+            &mut generated,
+            &TyposConfig::default(),
+            &BTreeMap::new(),
+            &[],
+        );
+        assert!(generated.is_empty());
+    }
+
+    #[test]
+    fn project_patterns_are_kept() {
+        let explicit =
+            TyposConfig::parse("[default]\nextend-ignore-identifiers-re = [\"^x_\", \"ba\"]\n")
+                .unwrap();
+        let mut generated = TyposConfig::default();
+        apply(
+            &mut generated,
+            &explicit,
+            &BTreeMap::new(),
+            &["ba".to_string(), "_tmp$".to_string()],
+        );
+        assert_eq!(
+            generated.merge_into(
+                "[default]\nextend-ignore-identifiers-re = [\"^x_\", \"ba\"]\nlocale = \"en\"\n"
+            ),
+            "[default]\n\
+             extend-ignore-identifiers-re = [\"^x_\", \"ba\", \"_tmp$\"]\n\
+             locale = \"en\"\n"
+        );
+    }
+}
@@ -0,0 +1,92 @@
+//! SHA-256 digests, as published next to typos-lsp releases and recorded in lockfiles.
+
+use std::{io, path::Path};
+
+use sha2::{Digest, Sha256};
+use zed_extension_api::serde_json::{self, Value};
+
+use crate::{host::Fs, messages};
+
+/// The lowercase hexadecimal SHA-256 digest of `bytes`.
+pub(crate) fn sha256_hex(bytes: &[u8]) -> String {
+    hex(&Sha256::digest(bytes))
+}
+
+/// The lowercase hexadecimal SHA-256 digest of the file at `path`.
+pub(crate) fn sha256_file(fs: &dyn Fs, path: &Path) -> Result<String, String> {
+    let bytes = fs.read(path).map_err(|e| messages::read_failed(path, e))?;
+    Ok(sha256_hex(&bytes))
+}
+
+/// The lowercase hexadecimal SHA-256 digest of `value` serialized as JSON, which is
+/// hashed as it is serialized rather than rendered first.
+pub(crate) fn sha256_json(value: &Value) -> String {
+    struct Hasher(Sha256);
+
+    impl io::Write for Hasher {
+        fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+            self.0.update(bytes);
+            Ok(bytes.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let mut hasher = Hasher(Sha256::new());
+    serde_json::to_writer(&mut hasher, value).expect("JSON values always serialize");
+    hex(&hasher.0.finalize())
+}
+
+fn hex(bytes: &[u8]) -> String {
+    use std::fmt::Write;
+
+    bytes.iter().fold(String::new(), |mut hex, byte| {
+        write!(hex, "{byte:02x}").expect("writing to a String does not fail");
+        hex
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use zed_extension_api::serde_json::json;
+
+    use super::{sha256_file, sha256_hex, sha256_json};
+    use crate::{host::StdFs, test_support::TempDir};
+
+    #[test]
+    fn known_digests() {
+        assert_eq!(
+            sha256_hex(b""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(
+            sha256_hex(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn file_digest() {
+        let temp = TempDir::new("sha256");
+        std::fs::write(temp.path().join("binary"), b"abc").unwrap();
+        let fs = StdFs::new(temp.path());
+        assert_eq!(
+            sha256_file(&fs, Path::new("binary")),
+            Ok(sha256_hex(b"abc"))
+        );
+        assert!(sha256_file(&fs, Path::new("missing")).is_err());
+    }
+
+    #[test]
+    fn json_digest() {
+        let value = json!({"settings": {"words": ["a", "b"]}});
+        assert_eq!(
+            sha256_json(&value),
+            sha256_hex(value.to_string().as_bytes())
+        );
+    }
+}
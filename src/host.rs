@@ -0,0 +1,318 @@
+//! The interactions of the installation logic with the outside world.
+//!
+//! GitHub lookups, other HTTP requests, downloads and the work directory filesystem go
+//! through these traits so that the installation flows can be exercised without network
+//! access, with failures injected at any step.
+
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use zed_extension_api::{self as zed, DownloadedFileType, GithubRelease};
+
+use crate::capabilities::{self, Capability};
+
+/// Looks up typos-lsp releases.
+pub(crate) trait ReleaseProvider: Send + Sync {
+    /// The latest release with assets, which may be a pre-release when `pre_release`.
+    fn latest_release(&self, repository: &str, pre_release: bool) -> Result<GithubRelease, String>;
+
+    /// The release published under `tag`.
+    fn release_by_tag(&self, repository: &str, tag: &str) -> Result<GithubRelease, String>;
+}
+
+/// Downloads and extracts release assets.
+pub(crate) trait Downloader: Send + Sync {
+    /// Download `url` and extract it into `destination`, relative to the work directory.
+    fn download(
+        &self,
+        url: &str,
+        destination: &str,
+        file_type: DownloadedFileType,
+    ) -> Result<(), String>;
+
+    /// Mark the file at `path`, relative to the work directory, as executable.
+    fn make_executable(&self, path: &str) -> Result<(), String>;
+}
+
+/// Fetches URLs, e.g. the release metadata of a mirror.
+pub(crate) trait Http: Send + Sync {
+    /// The body served at `url`.
+    fn get(&self, url: &str) -> Result<Vec<u8>, String>;
+}
+
+/// Runs the binaries of the work directory.
+pub(crate) trait Runner: Send + Sync {
+    /// Whether processes can be run at all, which the features running binaries are
+    /// gated on.
+    fn available(&self) -> bool;
+
+    /// Run the binary at `path`, relative to the work directory, with `arguments`,
+    /// returning its output once it exits successfully.
+    fn run(&self, path: &str, arguments: &[&str]) -> Result<String, String>;
+}
+
+/// A filesystem rooted at a directory, the work directory unless told otherwise;
+/// relative paths are resolved against its root.
+pub(crate) trait Fs: Send + Sync {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>>;
+
+    fn read_to_string(&self, path: &Path) -> io::Result<String> {
+        String::from_utf8(self.read(path)?)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()>;
+
+    /// Write a file through a temporary one renamed over it, so that it is never found
+    /// half written, e.g. after a crash.
+    fn write_atomically(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        let mut temporary = path.as_os_str().to_owned();
+        temporary.push(".tmp");
+        let temporary = Path::new(&temporary);
+        self.write(temporary, contents)?;
+        self.rename(temporary, path).inspect_err(|_| {
+            self.remove_file(temporary).ok();
+        })
+    }
+
+    /// Write a file that must not exist yet, failing with `AlreadyExists` otherwise.
+    fn create_new(&self, path: &Path, contents: &[u8]) -> io::Result<()>;
+
+    fn create_dir_all(&self, path: &Path) -> io::Result<()>;
+
+    fn is_file(&self, path: &Path) -> bool;
+
+    /// Whether the file can be run, which only filesystems with permission bits tell.
+    fn is_executable(&self, path: &Path) -> bool {
+        self.is_file(path)
+    }
+
+    /// The names of the entries of the directory, skipping names that are not UTF-8.
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<String>>;
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()>;
+
+    fn remove_file(&self, path: &Path) -> io::Result<()>;
+
+    fn remove_dir_all(&self, path: &Path) -> io::Result<()>;
+
+    /// The bytes taken by a file, or by the files of a directory and its subdirectories.
+    fn size(&self, path: &Path) -> io::Result<u64> {
+        if self.is_file(path) {
+            return Ok(self.read(path)?.len() as u64);
+        }
+        self.read_dir(path)?
+            .iter()
+            .try_fold(0, |total, name| Ok(total + self.size(&path.join(name))?))
+    }
+
+    /// The bytes available on the disk of the work directory, unknown unless the
+    /// platform tells, which WASI does not.
+    fn available_space(&self) -> Option<u64> {
+        None
+    }
+}
+
+/// The extension API, as provided by Zed.
+pub(crate) struct Zed;
+
+impl ReleaseProvider for Zed {
+    fn latest_release(&self, repository: &str, pre_release: bool) -> Result<GithubRelease, String> {
+        zed::latest_github_release(
+            repository,
+            zed::GithubReleaseOptions {
+                require_assets: true,
+                pre_release,
+            },
+        )
+    }
+
+    fn release_by_tag(&self, repository: &str, tag: &str) -> Result<GithubRelease, String> {
+        zed::github_release_by_tag_name(repository, tag)
+    }
+}
+
+impl Downloader for Zed {
+    fn download(
+        &self,
+        url: &str,
+        destination: &str,
+        file_type: DownloadedFileType,
+    ) -> Result<(), String> {
+        zed::download_file(url, destination, file_type)
+    }
+
+    fn make_executable(&self, path: &str) -> Result<(), String> {
+        zed::make_file_executable(path)
+    }
+}
+
+impl Http for Zed {
+    fn get(&self, url: &str) -> Result<Vec<u8>, String> {
+        capabilities::require(Capability::HttpClient, "download.release_api_url")?;
+        zed::http_client::HttpRequest::builder()
+            .method(zed::http_client::HttpMethod::Get)
+            .url(url)
+            .header("Accept", "application/json")
+            .build()?
+            .fetch()
+            .map(|response| response.body)
+    }
+}
+
+impl Runner for Zed {
+    fn available(&self) -> bool {
+        capabilities::available(Capability::ProcessSpawn)
+    }
+
+    fn run(&self, _path: &str, _arguments: &[&str]) -> Result<String, String> {
+        // Not before the extension API lets extensions spawn processes.
+        Err("the Zed extension API cannot run processes".to_string())
+    }
+}
+
+/// The standard library filesystem, rooted at a directory.
+pub(crate) struct StdFs {
+    root: PathBuf,
+}
+
+impl StdFs {
+    pub(crate) fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    fn resolve(&self, path: &Path) -> PathBuf {
+        self.root.join(path)
+    }
+}
+
+impl Fs for StdFs {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        fs::read(self.resolve(path))
+    }
+
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        fs::write(self.resolve(path), contents)
+    }
+
+    fn create_new(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(self.resolve(path))?
+            .write_all(contents)
+    }
+
+    fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        fs::create_dir_all(self.resolve(path))
+    }
+
+    fn is_file(&self, path: &Path) -> bool {
+        fs::metadata(self.resolve(path)).map_or(false, |stat| stat.is_file())
+    }
+
+    #[cfg(unix)]
+    fn is_executable(&self, path: &Path) -> bool {
+        use std::os::unix::fs::PermissionsExt;
+        fs::metadata(self.resolve(path)).map_or(false, |stat| {
+            stat.is_file() && stat.permissions().mode() & 0o111 != 0
+        })
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<String>> {
+        let mut names = Vec::new();
+        for entry in fs::read_dir(self.resolve(path))? {
+            if let Some(name) = entry?.file_name().to_str() {
+                names.push(name.to_string());
+            }
+        }
+        Ok(names)
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        fs::rename(self.resolve(from), self.resolve(to))
+    }
+
+    fn remove_file(&self, path: &Path) -> io::Result<()> {
+        fs::remove_file(self.resolve(path))
+    }
+
+    fn remove_dir_all(&self, path: &Path) -> io::Result<()> {
+        fs::remove_dir_all(self.resolve(path))
+    }
+
+    fn size(&self, path: &Path) -> io::Result<u64> {
+        let stat = fs::symlink_metadata(self.resolve(path))?;
+        if !stat.is_dir() {
+            return Ok(stat.len());
+        }
+        self.read_dir(path)?
+            .iter()
+            .try_fold(0, |total, name| Ok(total + self.size(&path.join(name))?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::{Fs, StdFs};
+    use crate::test_support::TempDir;
+
+    #[test]
+    fn std_fs_is_rooted() {
+        let temp = TempDir::new("std-fs");
+        std::fs::create_dir(temp.path().join("dir")).unwrap();
+        let fs = StdFs::new(temp.path());
+
+        fs.write(Path::new("dir/file"), b"content").unwrap();
+        assert!(fs.is_file(Path::new("dir/file")));
+        assert!(!fs.is_file(Path::new("dir")));
+        assert_eq!(fs.read_to_string(Path::new("dir/file")).unwrap(), "content");
+        assert_eq!(
+            fs.read_dir(Path::new(".")).unwrap(),
+            vec!["dir".to_string()]
+        );
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert!(!fs.is_executable(Path::new("dir/file")));
+            let permissions = std::fs::Permissions::from_mode(0o755);
+            std::fs::set_permissions(temp.path().join("dir/file"), permissions).unwrap();
+            assert!(fs.is_executable(Path::new("dir/file")));
+            assert!(!fs.is_executable(Path::new("dir")));
+        }
+
+        fs.write(Path::new("dir/other"), b"more").unwrap();
+        assert_eq!(fs.size(Path::new("dir/file")).unwrap(), 7);
+        assert_eq!(fs.size(Path::new(".")).unwrap(), 11);
+        assert!(fs.size(Path::new("missing")).is_err());
+        fs.remove_file(Path::new("dir/other")).unwrap();
+
+        fs.create_new(Path::new("dir/new"), b"first").unwrap();
+        let error = fs.create_new(Path::new("dir/new"), b"second").unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::AlreadyExists);
+        assert_eq!(fs.read_to_string(Path::new("dir/new")).unwrap(), "first");
+        fs.remove_file(Path::new("dir/new")).unwrap();
+
+        // Replaced whole, without leaving the temporary file behind.
+        fs.write_atomically(Path::new("dir/new"), b"replaced")
+            .unwrap();
+        fs.write_atomically(Path::new("dir/new"), b"again").unwrap();
+        assert_eq!(fs.read_to_string(Path::new("dir/new")).unwrap(), "again");
+        assert!(!fs.is_file(Path::new("dir/new.tmp")));
+        fs.remove_file(Path::new("dir/new")).unwrap();
+
+        fs.rename(Path::new("dir/file"), Path::new("dir/moved"))
+            .unwrap();
+        assert!(temp.path().join("dir/moved").exists());
+        fs.remove_file(Path::new("dir/moved")).unwrap();
+        assert!(!temp.path().join("dir/moved").exists());
+        fs.remove_dir_all(Path::new("dir")).unwrap();
+        assert!(!temp.path().join("dir").exists());
+    }
+}
@@ -0,0 +1,373 @@
+//! Checks of the `extend-ignore-re` patterns given through the extension settings.
+//!
+//! typos compiles these patterns with the Rust `regex` crate, which has no
+//! look-around, backreferences or the other backtracking constructs of PCRE. Patterns
+//! pasted from other tools are checked here, so that the server does not start with a
+//! pattern typos rejects or reads differently, and the error names the construct.
+//!
+//! The check is not a full regex parser: besides the PCRE constructs, it only catches
+//! unbalanced groups and character classes, and typos reports anything else.
+
+use crate::typos_config::{TomlValue, TyposConfig};
+
+/// The key of the ignored patterns, in `default` and in the file type tables.
+const KEY: &str = "extend-ignore-re";
+
+/// Check every `extend-ignore-re` pattern of a configuration.
+pub(crate) fn validate(config: &TyposConfig) -> Result<(), String> {
+    for (table, value) in config.entries(KEY) {
+        let TomlValue::Array(patterns) = value else {
+            return Err(format!("`{table}.{KEY}` must be an array of strings"));
+        };
+        for pattern in patterns {
+            let TomlValue::String(pattern) = pattern else {
+                return Err(format!("`{table}.{KEY}` must be an array of strings"));
+            };
+            check(pattern).map_err(|e| format!("`{table}.{KEY}` pattern {pattern:?}: {e}"))?;
+        }
+    }
+    Ok(())
+}
+
+/// An error for a PCRE construct, with how to do without it.
+fn unsupported(construct: &str, what: &str, instead: &str) -> String {
+    format!(
+        "`{construct}` is {what}, which typos does not support as it reads patterns in the \
+         syntax of the Rust `regex` crate; {instead}"
+    )
+}
+
+fn lookahead(construct: &str) -> String {
+    unsupported(
+        construct,
+        "a lookahead",
+        "match the text that follows as part of the pattern instead",
+    )
+}
+
+fn lookbehind(construct: &str) -> String {
+    unsupported(
+        construct,
+        "a lookbehind",
+        "match the text that precedes as part of the pattern instead",
+    )
+}
+
+fn backreference(construct: &str) -> String {
+    unsupported(
+        construct,
+        "a backreference",
+        "spell out the alternatives the reference stands for instead",
+    )
+}
+
+/// Check that `pattern` only uses constructs of the Rust `regex` syntax.
+pub(crate) fn check(pattern: &str) -> Result<(), String> {
+    let chars: Vec<char> = pattern.chars().collect();
+    let at = |i: usize| chars.get(i).copied();
+    let mut open_groups = 0usize;
+    // Whether the previous token is a quantifier, which a `+` would make possessive.
+    let mut after_quantifier = false;
+    let mut i = 0;
+    while let Some(c) = at(i) {
+        let quantifier = match c {
+            '\\' => {
+                i = escape(&chars, i)?;
+                false
+            }
+            '[' => {
+                i = class_end(&chars, i)?;
+                false
+            }
+            '(' => {
+                if at(i + 1) == Some('?') {
+                    check_group(&chars, i)?;
+                }
+                open_groups += 1;
+                i += 1;
+                false
+            }
+            ')' => {
+                open_groups = open_groups
+                    .checked_sub(1)
+                    .ok_or_else(|| "unopened group: `)` closes no `(`".to_string())?;
+                i += 1;
+                false
+            }
+            '+' if after_quantifier => {
+                let quantifier = at(i - 1).map(String::from).unwrap_or_default();
+                return Err(unsupported(
+                    &format!("{quantifier}+"),
+                    "a possessive quantifier",
+                    "drop the trailing `+`",
+                ));
+            }
+            '*' | '+' | '?' => {
+                i += 1;
+                // A lazy quantifier, which the `regex` crate supports.
+                if at(i) == Some('?') {
+                    i += 1;
+                    false
+                } else {
+                    true
+                }
+            }
+            '{' => match repetition_end(&chars, i) {
+                Some(end) => {
+                    i = end;
+                    if at(i) == Some('?') {
+                        i += 1;
+                        false
+                    } else {
+                        true
+                    }
+                }
+                None => {
+                    i += 1;
+                    false
+                }
+            },
+            _ => {
+                i += 1;
+                false
+            }
+        };
+        after_quantifier = quantifier;
+    }
+    if open_groups > 0 {
+        return Err("unclosed group: a `(` is never closed".to_string());
+    }
+    Ok(())
+}
+
+/// Whether `chars` has `prefix` at `i`.
+fn starts_with(chars: &[char], i: usize, prefix: &str) -> bool {
+    let mut rest = chars.get(i..).unwrap_or_default().iter();
+    prefix.chars().all(|c| rest.next() == Some(&c))
+}
+
+/// Check the group starting with `(?` at `i`.
+fn check_group(chars: &[char], i: usize) -> Result<(), String> {
+    let after = |offset: usize| chars.get(i + offset).copied();
+    for construct in ["(?=", "(?!"] {
+        if starts_with(chars, i, construct) {
+            return Err(lookahead(construct));
+        }
+    }
+    for construct in ["(?<=", "(?<!"] {
+        if starts_with(chars, i, construct) {
+            return Err(lookbehind(construct));
+        }
+    }
+    if starts_with(chars, i, "(?P=") {
+        return Err(backreference("(?P="));
+    }
+    if starts_with(chars, i, "(?>") {
+        return Err(unsupported(
+            "(?>",
+            "an atomic group",
+            "use a non-capturing group `(?:` instead",
+        ));
+    }
+    if starts_with(chars, i, "(?#") {
+        return Err(unsupported(
+            "(?#",
+            "an inline comment",
+            "remove it, or comment with `#` under the `x` flag",
+        ));
+    }
+    // Unlike `(?R)`, `(?Rm)` sets flags, and so does `(?-i)` unlike `(?-1)`.
+    let recursion = match (after(2), after(3)) {
+        (Some('R'), Some(')')) => Some("(?R)".to_string()),
+        (Some('&'), _) => Some("(?&".to_string()),
+        (Some('P'), Some('>')) => Some("(?P>".to_string()),
+        (Some(digit), _) if digit.is_ascii_digit() => Some(format!("(?{digit}")),
+        (Some(sign @ ('+' | '-')), Some(digit)) if digit.is_ascii_digit() => {
+            Some(format!("(?{sign}"))
+        }
+        _ => None,
+    };
+    match recursion {
+        Some(construct) => Err(unsupported(
+            &construct,
+            "a recursion",
+            "repeat the pattern it refers to instead",
+        )),
+        None => Ok(()),
+    }
+}
+
+/// The index after the escape sequence starting at `i`.
+fn escape(chars: &[char], i: usize) -> Result<usize, String> {
+    let Some(escaped) = chars.get(i + 1).copied() else {
+        return Err("incomplete escape: the pattern ends with `\\`".to_string());
+    };
+    let next = chars.get(i + 2).copied();
+    match escaped {
+        'K' => Err(unsupported(
+            "\\K",
+            "a match reset",
+            "match the text that precedes as part of the pattern instead",
+        )),
+        '1'..='9' => Err(backreference(&format!("\\{escaped}"))),
+        'g' if next.is_some_and(|c| c == '{' || c.is_ascii_digit()) => Err(backreference("\\g")),
+        'k' if matches!(next, Some('<' | '{' | '\'')) => Err(backreference("\\k")),
+        // `\x{…}`, `\u{…}`, `\p{…}` and the like, whose braces are no repetition.
+        _ if next == Some('{') => Ok(chars[i + 2..]
+            .iter()
+            .position(|&c| c == '}')
+            .map_or(chars.len(), |end| i + 2 + end + 1)),
+        _ => Ok(i + 2),
+    }
+}
+
+/// The index after the character class starting at `i`, nested classes included.
+fn class_end(chars: &[char], i: usize) -> Result<usize, String> {
+    let mut depth = 0usize;
+    let mut i = i;
+    while i < chars.len() {
+        match chars[i] {
+            '[' => {
+                depth += 1;
+                i += 1;
+                if chars.get(i) == Some(&'^') {
+                    i += 1;
+                }
+                // A `]` right after the opening bracket is a literal.
+                if chars.get(i) == Some(&']') {
+                    i += 1;
+                }
+            }
+            ']' => {
+                depth -= 1;
+                i += 1;
+                if depth == 0 {
+                    return Ok(i);
+                }
+            }
+            '\\' => i += 2,
+            _ => i += 1,
+        }
+    }
+    Err("unclosed character class: a `[` is never closed".to_string())
+}
+
+/// The index after the counted repetition `{m}`, `{m,}` or `{m,n}` starting at `i`, if
+/// there is one there.
+fn repetition_end(chars: &[char], i: usize) -> Option<usize> {
+    let end = i + chars[i..].iter().position(|&c| c == '}')?;
+    let inner: String = chars[i + 1..end].iter().collect();
+    let (min, max) = inner.split_once(',').unwrap_or((&inner, "0"));
+    let is_number = |text: &str| !text.is_empty() && text.chars().all(|c| c.is_ascii_digit());
+    (is_number(min) && (max.is_empty() || is_number(max))).then_some(end + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check, validate};
+    use crate::typos_config::TyposConfig;
+
+    #[test]
+    fn supported_patterns() {
+        // Patterns found in typos configurations, including its documentation.
+        for pattern in [
+            r"(?Rm)^.*(#|//)\s*spellchecker:disable-line$",
+            r"(?s)(#|//)\s*spellchecker:off.*?\n\s*(#|//)\s*spellchecker:on",
+            r"\b[0-9A-Fa-f]{7,40}\b",
+            r"[a-zA-Z0-9+/]{40,}={0,2}",
+            r#""[^"]*""#,
+            r"0x[0-9a-fA-F]+",
+            r"(?<name>\w+)@(?P<domain>\w+)",
+            r"[[:alpha:]]+[\]]*[]a]?",
+            r"\p{Han}+\x{1F600}{2}",
+            r"#[0-9a-f]{6}\b",
+            r"(?-u:\w)+?(?i:ab)*?",
+            r"\\K\\1",
+            r"\d+\.\d+\.\d+(-rc\.\d+)?",
+        ] {
+            assert_eq!(check(pattern), Ok(()), "{pattern}");
+        }
+    }
+
+    #[test]
+    fn pcre_constructs() {
+        for (pattern, construct) in [
+            (r"\w+(?=\.md)", "`(?=` is a lookahead"),
+            (r"foo(?!bar)", "`(?!` is a lookahead"),
+            (r"(?<=api_key=)\w+", "`(?<=` is a lookbehind"),
+            (r"(?<!\w)id(?!\w)", "`(?<!` is a lookbehind"),
+            (r"api_key=\K\w+", "`\\K` is a match reset"),
+            (r#"(["'])\w+\1"#, "`\\1` is a backreference"),
+            (r"(?<q>')\w+\k<q>", "`\\k` is a backreference"),
+            (r"(\w)\g{1}", "`\\g` is a backreference"),
+            (r"(?P<q>')\w+(?P=q)", "`(?P=` is a backreference"),
+            (r"(?>a+)b", "`(?>` is an atomic group"),
+            (r"\d++", "`++` is a possessive quantifier"),
+            (r"x{2}+", "`}+` is a possessive quantifier"),
+            (r"a*+b", "`*+` is a possessive quantifier"),
+            (r"(?#a comment)abc", "`(?#` is an inline comment"),
+            (r"\((?:[^()]|(?R))*\)", "`(?R)` is a recursion"),
+            (r"(a|b(?1))", "`(?1` is a recursion"),
+            (r"(a|b(?-1))", "`(?-` is a recursion"),
+        ] {
+            let error = check(pattern).unwrap_err();
+            assert!(error.starts_with(construct), "{pattern}: {error}");
+            assert!(error.contains("Rust `regex` crate"), "{pattern}: {error}");
+        }
+        assert_eq!(
+            check(r"\w+(?=\.md)"),
+            Err(
+                "`(?=` is a lookahead, which typos does not support as it reads patterns in \
+                 the syntax of the Rust `regex` crate; match the text that follows as part of \
+                 the pattern instead"
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn unbalanced_patterns() {
+        assert_eq!(
+            check("(abc"),
+            Err("unclosed group: a `(` is never closed".to_string())
+        );
+        assert_eq!(
+            check("abc)"),
+            Err("unopened group: `)` closes no `(`".to_string())
+        );
+        assert_eq!(
+            check("[abc"),
+            Err("unclosed character class: a `[` is never closed".to_string())
+        );
+        assert_eq!(
+            check("[[:alpha:]"),
+            Err("unclosed character class: a `[` is never closed".to_string())
+        );
+        assert_eq!(
+            check("abc\\"),
+            Err("incomplete escape: the pattern ends with `\\`".to_string())
+        );
+    }
+
+    #[test]
+    fn configurations() {
+        let config = TyposConfig::parse(
+            "[default]\nextend-ignore-re = ['''\\d+''']\n\
+             [type.md]\nextend-ignore-re = ['''\\w+(?=\\.md)''']\n",
+        )
+        .unwrap();
+        let error = validate(&config).unwrap_err();
+        assert!(
+            error.starts_with("`type.md.extend-ignore-re` pattern \"\\\\w+(?=\\\\.md)\": `(?=`"),
+            "{error}"
+        );
+
+        let config = TyposConfig::parse("[default]\nextend-ignore-re = '''\\d+'''\n").unwrap();
+        assert_eq!(
+            validate(&config),
+            Err("`default.extend-ignore-re` must be an array of strings".to_string())
+        );
+        let config = TyposConfig::parse("[default]\nlocale = \"en-gb\"\n").unwrap();
+        assert_eq!(validate(&config), Ok(()));
+    }
+}
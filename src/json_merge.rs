@@ -0,0 +1,91 @@
+//! Layering JSON settings over one another, as Zed does for its settings files.
+
+use zed_extension_api::serde_json::Value;
+
+/// Merge `over` into `base`, objects member by member and anything else, arrays
+/// included, replacing the value below. A `null` member unsets the one below.
+pub(crate) fn deep_merge(base: &mut Value, over: Value) {
+    match (base, over) {
+        (Value::Object(base), Value::Object(over)) => {
+            for (key, value) in over {
+                if value.is_null() {
+                    base.remove(&key);
+                    continue;
+                }
+                match base.get_mut(&key) {
+                    Some(below) => deep_merge(below, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, over) => *base = over,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use zed_extension_api::serde_json::{json, Value};
+
+    use super::deep_merge;
+
+    fn merged(mut base: Value, over: Value) -> Value {
+        deep_merge(&mut base, over);
+        base
+    }
+
+    #[test]
+    fn empty_overrides() {
+        let defaults = json!({"diagnosticSeverity": "Hint", "logLevel": "warn"});
+        assert_eq!(merged(defaults.clone(), json!({})), defaults);
+        assert_eq!(merged(json!({}), defaults.clone()), defaults);
+    }
+
+    #[test]
+    fn partial_overrides() {
+        assert_eq!(
+            merged(
+                json!({"diagnosticSeverity": "Hint", "logLevel": "warn"}),
+                json!({"diagnosticSeverity": "Error", "config": "ci/typos.toml"}),
+            ),
+            json!({
+                "diagnosticSeverity": "Error",
+                "logLevel": "warn",
+                "config": "ci/typos.toml",
+            })
+        );
+    }
+
+    #[test]
+    fn nested_objects() {
+        assert_eq!(
+            merged(
+                json!({"trace": {"server": "off", "format": "text"}, "ids": [1, 2]}),
+                json!({"trace": {"server": "verbose"}, "ids": [3]}),
+            ),
+            json!({"trace": {"server": "verbose", "format": "text"}, "ids": [3]})
+        );
+        // Values of another kind replace objects, and the other way around.
+        assert_eq!(
+            merged(json!({"trace": {"server": "off"}}), json!({"trace": "off"})),
+            json!({"trace": "off"})
+        );
+        assert_eq!(
+            merged(json!({"trace": null}), json!({"trace": {"server": "off"}})),
+            json!({"trace": {"server": "off"}})
+        );
+        assert_eq!(merged(json!({"a": 1}), json!(null)), json!(null));
+    }
+
+    #[test]
+    fn null_unsets() {
+        assert_eq!(
+            merged(
+                json!({"config": "typos.toml", "trace": {"server": "off", "format": "text"}}),
+                json!({"config": null, "trace": {"format": null}, "logLevel": null}),
+            ),
+            json!({"trace": {"server": "off"}})
+        );
+    }
+}
@@ -0,0 +1,141 @@
+//! Parsing of the JSON-with-comments flavour used by Zed's settings files.
+
+use zed_extension_api::serde_json::{self, Value};
+
+/// Parse JSON allowing `//` and `/* */` comments and trailing commas.
+pub(crate) fn parse(text: &str) -> Result<Value, String> {
+    serde_json::from_str(&strip(text)).map_err(|e| e.to_string())
+}
+
+/// Like [`parse`], with the line and column of an error apart from its message.
+pub(crate) fn parse_at(text: &str) -> Result<Value, (usize, usize, String)> {
+    serde_json::from_str(&strip(text)).map_err(|e| {
+        let message = e.to_string();
+        let message = message
+            .rsplit_once(" at line ")
+            .map_or(message.as_str(), |(message, _)| message)
+            .to_string();
+        (e.line(), e.column(), message)
+    })
+}
+
+/// Remove comments and trailing commas, keeping line breaks so that error positions
+/// still match the original text.
+fn strip(text: &str) -> String {
+    strip_trailing_commas(&strip_comments(text))
+}
+
+fn strip_comments(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    let mut in_string = false;
+    while let Some(c) = chars.next() {
+        if in_string {
+            output.push(c);
+            match c {
+                '\\' => output.extend(chars.next()),
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match (c, chars.peek()) {
+            ('/', Some('/')) => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        output.push('\n');
+                        break;
+                    }
+                }
+            }
+            ('/', Some('*')) => {
+                chars.next();
+                let mut previous = ' ';
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        output.push('\n');
+                    }
+                    if previous == '*' && c == '/' {
+                        break;
+                    }
+                    previous = c;
+                }
+            }
+            _ => {
+                in_string = c == '"';
+                output.push(c);
+            }
+        }
+    }
+    output
+}
+
+fn strip_trailing_commas(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut chars = text.char_indices().peekable();
+    let mut in_string = false;
+    while let Some((index, c)) = chars.next() {
+        if in_string {
+            output.push(c);
+            match c {
+                '\\' => output.extend(chars.next().map(|(_, c)| c)),
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        if c == ',' {
+            let next = text[index + 1..].trim_start().chars().next();
+            if matches!(next, Some('}' | ']')) {
+                continue;
+            }
+        }
+        in_string = c == '"';
+        output.push(c);
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use zed_extension_api::serde_json::json;
+
+    use super::{parse, parse_at};
+
+    #[test]
+    fn comments_and_trailing_commas() {
+        let text = r#"{
+            // Line comment
+            "lsp": {
+                "typos": { /* block
+                comment */ "binary": {"path": "a//b", "arguments": ["x",],},
+                },
+            },
+            "url": "http://example.com/*not a comment*/",
+        }"#;
+        assert_eq!(
+            parse(text),
+            Ok(json!({
+                "lsp": {"typos": {"binary": {"path": "a//b", "arguments": ["x"]}}},
+                "url": "http://example.com/*not a comment*/",
+            }))
+        );
+    }
+
+    #[test]
+    fn escaped_quotes() {
+        assert_eq!(
+            parse(r#"{"a": "say \"hi\" // still a string"}"#),
+            Ok(json!({"a": "say \"hi\" // still a string"}))
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(parse("{").is_err());
+        assert_eq!(
+            parse_at("{\n  // comment\n  \"a\" 1\n}"),
+            Err((3, 7, "expected `:`".to_string()))
+        );
+    }
+}
@@ -0,0 +1,57 @@
+//! The last installation of typos-lsp a server initialized from, kept until a newer one
+//! does.
+//!
+//! A download is checked before it replaces the installed releases, but until the
+//! extension can run processes (see [`crate::capabilities`]) the check cannot tell
+//! whether the binary starts. The release a server last initialized from is recorded in
+//! the work directory: the cleanup following a download spares it, and an update that
+//! fails falls back to it. Once a server initializes from another installation, that one
+//! is recorded instead, and the previous one goes with the next cleanup.
+
+use std::path::Path;
+
+use crate::{host::Fs, log, version::Version};
+
+/// Records the tag of the last known good release.
+pub(crate) const FILE_NAME: &str = "known-good.txt";
+
+/// The last known good release recorded in the work directory, if any.
+pub(crate) fn read(fs: &dyn Fs) -> Option<Version> {
+    let content = fs.read_to_string(Path::new(FILE_NAME)).ok()?;
+    Version::parse(content.trim())
+}
+
+/// Record the release `tag` as the last known good one.
+pub(crate) fn record(fs: &dyn Fs, tag: &str) {
+    if read(fs).is_some_and(|known_good| Version::parse(tag) == Some(known_good)) {
+        return;
+    }
+    if let Err(e) = fs.write_atomically(Path::new(FILE_NAME), format!("{tag}\n").as_bytes()) {
+        log::warn(format!(
+            "failed to record typos-lsp {tag} as the last known good release: {e}"
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::{read, record, FILE_NAME};
+    use crate::{host::StdFs, test_support::TempDir, version::Version};
+
+    #[test]
+    fn persistence() {
+        let temp = TempDir::new("known-good");
+        let fs = StdFs::new(temp.path());
+        assert_eq!(read(&fs), None);
+
+        record(&fs, "v0.1.22");
+        assert_eq!(read(&fs), Version::parse("v0.1.22"));
+        record(&fs, "v0.1.23");
+        assert_eq!(read(&fs), Version::parse("v0.1.23"));
+
+        fs::write(temp.path().join(FILE_NAME), "latest").unwrap();
+        assert_eq!(read(&fs), None);
+    }
+}
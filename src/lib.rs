@@ -1,294 +1,5822 @@
-use std::{fs, path::Path};
+#[cfg(test)]
+mod archive_fixtures;
+mod archive_layout;
+mod binary_identity;
+mod binary_probe;
+mod build_info;
+mod cache_report;
+mod capabilities;
+mod case_fold;
+mod cli_sync;
+mod coercion;
+mod command_env;
+mod community_dictionary;
+mod config_channel;
+mod config_source;
+mod context;
+mod corrections;
+mod crash_loop;
+mod default_args;
+mod default_excludes;
+mod disk_space;
+mod download_errors;
+mod effective_config;
+mod error;
+mod excludes;
+mod extend_settings;
+mod hashing;
+mod host;
+mod ignore_patterns;
+mod json_merge;
+mod jsonc;
+mod known_good;
+mod libc_fallback;
+mod lockfile;
+mod log;
+mod manifest;
+mod messages;
+mod metrics;
+mod migration;
+mod mirror;
+mod options_file;
+mod packaging;
+mod path_diagnostics;
+mod paths;
+mod pattern_match;
+mod pinning;
+mod platform_settings;
+mod rate_limit;
+mod release_checksums;
+mod reload;
+mod report;
+mod server_configuration;
+mod server_logs;
+mod settings;
+mod settings_size;
+mod spellcheckers;
+mod start_failures;
+mod status;
+#[cfg(test)]
+mod test_support;
+mod tool_versions;
+mod typos_config;
+mod update_timing;
+mod version;
+mod warmup;
+mod word_fragments;
+mod word_overlap;
+mod work_dir;
+mod work_lock;
+mod worktree_files;
+mod write_consent;
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fmt::Display,
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
+use binary_probe::Probed;
+use cache_report::CacheReport;
+use capabilities::Capability;
+use cli_sync::CliPin;
+use config_source::ConfigSource;
+use context::{Container, ExecutionContext};
+use corrections::Corrections;
+use crash_loop::CrashLoop;
+use error::TyposExtensionError;
+use host::{Downloader, Fs, Http, ReleaseProvider, Runner, StdFs};
+use lockfile::Lockfile;
+use manifest::Manifest;
+use metrics::{Metrics, Phase};
+use mirror::Mirror;
+use pinning::{PinCandidates, PinSource};
+use report::{BinaryMeta, Plan, Provenance, StartupState, Step};
+use server_configuration::ServerConfiguration;
+use settings::{Cleanup, ExtensionSettings, WorktreeSettings};
+use start_failures::Strategy;
+use status::{Status, StatusTracker};
+use typos_config::TyposConfig;
+use update_timing::{LastCheck, PendingUpdate, UpdateTiming};
+use version::Version;
+use warmup::Warmup;
+use word_fragments::Fragments;
+use work_lock::{Attempt, WorkLock};
 use zed_extension_api::{
-    self as zed, settings::LspSettings, Architecture, Command, LanguageServerId, Os, Result,
-    Worktree,
+    self as zed, Architecture, Command, LanguageServerId, Os, Result, Worktree,
 };
 
 struct TyposBinary {
     path: String,
-    args: Option<Vec<String>>,
+    /// The release tag, unknown for binaries found on the `$PATH`.
+    version: Option<String>,
+    provenance: Provenance,
+    /// What a dry run skipped to make the binary available.
+    skipped: Vec<Step>,
 }
 
-struct TyposExtension {
+/// The C library a Linux release is built against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LinuxLibc {
+    Gnu,
+    /// Statically linked, runs on any Linux userland.
+    Musl,
+}
+
+impl LinuxLibc {
+    /// Parse an `asset_preference` entry.
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "gnu" => Some(Self::Gnu),
+            "musl" => Some(Self::Musl),
+            _ => None,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Gnu => "gnu",
+            Self::Musl => "musl",
+        }
+    }
+}
+
+/// What was resolved for one language server ID during this session.
+#[derive(Debug, Default)]
+struct ServerState {
     cached_binary_path: Option<String>,
+    cached_binary_version: Option<String>,
+    /// What the cached binary was selected for.
+    cached_selection: Option<Selection>,
+    /// Whether the cached binary was used without looking for a newer release, as
+    /// `auto_update` was off.
+    held_back: bool,
+    /// The binary the server was last started with.
+    running: Option<BinaryMeta>,
+    /// The settings last seen for the server, to tell what a settings change takes.
+    settings: Option<WorktreeSettings>,
+    /// The workspace configuration of oversized settings.
+    workspace_configuration: settings_size::Cache,
+    /// The recent resolutions of the server, to tell a crash loop.
+    crash_loop: CrashLoop,
+}
+
+/// What a worktree requires from a managed binary, gathered before installing it.
+struct InstallRequest {
+    platform: Os,
+    architecture: Architecture,
+    container: Option<Container>,
+    lockfile: Option<Lockfile>,
+    /// The release tag to install, the latest release when `None`.
+    pinned_version: Option<String>,
+    /// The Linux builds to look for, in order of preference.
+    asset_preference: Vec<LinuxLibc>,
+    /// Plan the download and cleanups instead of making them.
+    dry_run: bool,
+    /// The asset to install instead of the one matching the platform, in which
+    /// `{version}` stands for the release tag.
+    asset_name: Option<String>,
+    /// The root of the worktree whose settings the request follows.
+    worktree: String,
+    /// Whether an installed release may be used while a newer one is pending.
+    update_timing: UpdateTiming,
+    /// Whether previous installations, incomplete downloads and the archive entries of
+    /// no use may be removed, which `cleanup` turns off.
+    cleanup: bool,
+    /// How many installations the cleanup keeps, the newest ones along with the one
+    /// installed.
+    keep_versions: usize,
+    /// How long the last lookup of the latest release is trusted for.
+    update_check_interval: Duration,
+    /// Whether the latest release may be a pre-release.
+    prefer_prerelease: bool,
+    /// Whether a newer release than the installed ones may be installed.
+    auto_update: bool,
+    /// Where releases are looked up and downloaded from instead of GitHub.
+    mirror: Mirror,
+}
+
+impl InstallRequest {
+    /// Whether `version` may be used as the latest release, which a pre-release only is
+    /// when asked for.
+    fn accepts(&self, version: &Version) -> bool {
+        self.prefer_prerelease || version.pre.is_none()
+    }
+
+    fn selection(&self) -> Selection {
+        Selection {
+            platform: self.platform,
+            architecture: self.architecture,
+            asset_preference: self.asset_preference.clone(),
+            asset_name: self.asset_name.clone(),
+            locked_asset: self
+                .lockfile
+                .as_ref()
+                .map(|lockfile| lockfile.asset.clone()),
+        }
+    }
+}
+
+/// What decides the build of a release a request installs, so that a cached binary is
+/// resolved again once the settings of its worktree ask for another build.
+#[derive(Debug, Clone, PartialEq)]
+struct Selection {
+    platform: Os,
+    architecture: Architecture,
+    asset_preference: Vec<LinuxLibc>,
+    asset_name: Option<String>,
+    locked_asset: Option<String>,
+}
+
+struct TyposExtension {
+    /// By language server ID and worktree root, so that neither several registrations
+    /// nor the folders of a workspace, whose settings may differ, share a cache.
+    servers: HashMap<(String, String), ServerState>,
+    /// Versions of the binaries handed out to Zed during this session, which may still
+    /// be running and must survive cleanups.
+    in_use_versions: BTreeSet<Version>,
+    statuses: StatusTracker,
+    releases: Box<dyn ReleaseProvider>,
+    downloader: Box<dyn Downloader>,
+    /// Looks releases up on a mirror.
+    http: Box<dyn Http>,
+    runner: Box<dyn Runner>,
+    /// The extension work directory.
+    fs: Box<dyn Fs>,
+    /// The absolute path of the work directory, which `fs` is rooted in, resolved when
+    /// the extension loaded.
+    work_dir: PathBuf,
+    /// The current time, in seconds since the Unix epoch.
+    clock: Box<dyn Fn() -> u64 + Send + Sync>,
+    /// Waits, while another Zed process holds the work directory lock.
+    sleep: Box<dyn Fn(Duration) + Send + Sync>,
+    /// When the extension was loaded, which is when Zed started or reloaded it.
+    session_start: u64,
+    /// This extension instance, in the work directory lock.
+    instance: String,
+    /// Whether the work directory was tidied up for this session, which waits for the
+    /// first server to start so that its `cleanup` setting is known.
+    tidied: bool,
+    /// The work directory as scanned when the extension loaded, until the first
+    /// resolution uses it.
+    warmup: Option<Warmup>,
+    /// The installed binaries that passed their probe this session.
+    probed: BTreeSet<String>,
 }
 
 impl TyposExtension {
-    #[allow(dead_code)]
-    pub const LANGUAGE_SERVER_ID: &'static str = "typos";
+    const GITHUB_REPOSITORY: &'static str = "tekumara/typos-lsp";
+    /// How long to wait before each download attempt after the first one.
+    const DOWNLOAD_RETRY_DELAYS: &'static [Duration] =
+        &[Duration::from_secs(1), Duration::from_secs(4)];
+    /// Lists the version directories to remove at the next startup.
+    const DEFERRED_CLEANUP_FILE: &'static str = "deferred-cleanup.txt";
+    /// Entries of the work directory holding the extension state, never cleaned up.
+    const STATE_ENTRIES: &'static [&'static str] = &[
+        Self::DEFERRED_CLEANUP_FILE,
+        update_timing::PENDING_FILE,
+        update_timing::CHECK_FILE,
+        migration::LAYOUT_FILE,
+        effective_config::DIR,
+        typos_config::GENERATED_DIR,
+        metrics::FILE_NAME,
+        libc_fallback::FILE_NAME,
+        libc_fallback::PROBES_FILE,
+        work_lock::FILE_NAME,
+        worktree_files::FALLBACK_DIR,
+        worktree_files::REGISTRY_FILE,
+        known_good::FILE_NAME,
+        rate_limit::FILE_NAME,
+    ];
+
+    fn with_host(
+        releases: Box<dyn ReleaseProvider>,
+        downloader: Box<dyn Downloader>,
+        fs: Box<dyn Fs>,
+        work_dir: PathBuf,
+    ) -> Self {
+        Self {
+            servers: HashMap::new(),
+            in_use_versions: BTreeSet::new(),
+            statuses: StatusTracker::default(),
+            releases,
+            downloader,
+            http: Box::new(host::Zed),
+            runner: Box::new(host::Zed),
+            fs,
+            work_dir,
+            clock: Box::new(update_timing::now),
+            sleep: Box::new(std::thread::sleep),
+            session_start: update_timing::now(),
+            instance: work_lock::instance_id(),
+            tidied: false,
+            warmup: None,
+            probed: BTreeSet::new(),
+        }
+    }
+
+    /// Do what needs no worktree ahead of the first resolution, the binaries being
+    /// looked for on `platform` (see [`warmup`]).
+    fn warm_up(&mut self, platform: (Os, Architecture)) {
+        self.warmup = Warmup::scan(self.fs.as_ref(), platform);
+    }
+
+    /// Remove what previous sessions left for this one to remove, and migrate the
+    /// layout of the work directory for `platform`, once per session and unless
+    /// `settings` turn cleanups off or plan a dry run.
+    fn tidy_work_dir(&mut self, settings: &ExtensionSettings, platform: (Os, Architecture)) {
+        if self.tidied || settings.dry_run {
+            return;
+        }
+        self.tidied = true;
+        if settings.cleanup == Cleanup::Off {
+            Self::skip_cleanup();
+            return;
+        }
+        let fs = self.fs.as_ref();
+        // No server spawned by a previous extension instance can rely on these anymore.
+        // While another Zed process installs, they are left to the next session.
+        if let Ok(Attempt::Acquired(_lock)) =
+            WorkLock::try_acquire(fs, &self.instance, (self.clock)())
+        {
+            let removed = Self::run_deferred_cleanup(fs);
+            if let Some(warmup) = &mut self.warmup {
+                warmup.forget(&removed);
+            }
+        }
+        migration::migrate(fs, platform.0, platform.1);
+    }
+
+    /// Tell, once, that nothing is removed as `cleanup` is off.
+    fn skip_cleanup() {
+        log::once("cleanup-off", log::Level::Info, messages::CLEANUP_OFF);
+    }
+
+    fn server_state(&mut self, language_server_id: &str, root_path: &str) -> &mut ServerState {
+        self.servers
+            .entry((language_server_id.to_string(), root_path.to_string()))
+            .or_default()
+    }
+
+    /// The workspace configuration, with the metadata of the running binary when the
+    /// settings ask for it.
+    fn expose_meta(
+        &self,
+        server_id: &LanguageServerId,
+        root_path: &str,
+        settings: &WorktreeSettings,
+        workspace_settings: zed::serde_json::Value,
+    ) -> zed::serde_json::Value {
+        if !settings.extension.expose_meta {
+            return workspace_settings;
+        }
+        let running = self
+            .servers
+            .get(&(server_id.as_ref().to_string(), root_path.to_string()))
+            .and_then(|state| state.running.as_ref());
+        BinaryMeta::expose(running, workspace_settings)
+    }
 
     fn language_server_binary(
         &mut self,
         language_server_id: &LanguageServerId,
         worktree: &zed::Worktree,
-    ) -> Result<TyposBinary> {
-        if let Some(path) = worktree.which("typos-lsp") {
-            return Ok(TyposBinary {
+    ) -> Result<TyposBinary, TyposExtensionError> {
+        let mut metrics = Metrics::default();
+        let binary = self.resolve_binary(language_server_id, worktree, &mut metrics);
+        metrics.flush(&self.work_dir);
+        Self::conclude(
+            &mut self.statuses,
+            language_server_id.as_ref(),
+            &binary,
+            &|status| {
+                zed::set_language_server_installation_status(language_server_id, &status.to_zed())
+            },
+        );
+        let binary = binary?;
+        let settings = WorktreeSettings::for_worktree(language_server_id, worktree);
+        if settings.extension.dry_run {
+            return Err(self.dry_run_plan(worktree, &settings, &binary)?.into());
+        }
+        Ok(binary)
+    }
+
+    /// The rendered plan of a dry run, from what resolving `binary` skipped and what
+    /// starting the server would write.
+    fn dry_run_plan(
+        &self,
+        worktree: &Worktree,
+        settings: &WorktreeSettings,
+        binary: &TyposBinary,
+    ) -> Result<String, String> {
+        let mut steps = binary.skipped.clone();
+        let root_path = paths::canonical_root(&worktree.root_path());
+        let source = Self::config_source(worktree, settings)?;
+        let generated = Self::generated_config(worktree, settings);
+        if let Some(content) = Self::custom_config(worktree, &source, &generated)? {
+            steps.push(Step::WriteConfig {
+                path: self.absolute_work_dir_path(&typos_config::path(&root_path)),
+                content,
+            });
+        }
+        if settings.extension.export_lockfile && binary.provenance.is_installed() {
+            steps.push(Step::WriteWorktreeFile(lockfile::FILE_NAME.to_string()));
+        }
+        if let Some(target) = &settings.extension.write_effective_config {
+            let path = effective_config::path(&root_path, target);
+            steps.push(Step::WriteEffectiveConfig(
+                self.work_dir.join(path).display().to_string(),
+            ));
+        }
+        let arguments = Self::arguments(settings, binary, &source, &root_path);
+        Ok(Plan {
+            extension_version: &build_info::describe(),
+            server_version: binary.version.as_deref(),
+            provenance: binary.provenance,
+            binary_path: &binary.path,
+            arguments: &arguments,
+            steps: &steps,
+        }
+        .to_string())
+    }
+
+    /// The binary the user provides, set in `binary.path` or found on the `$PATH` by
+    /// `which`, before any installation of the extension in `work_dir` is considered.
+    /// A `binary.path` is run with `runner` to tell it from the typos CLI.
+    fn user_binary(
+        settings: &WorktreeSettings,
+        root_path: &str,
+        work_dir: &str,
+        runner: &dyn Runner,
+        which: impl Fn(&str) -> Option<String>,
+    ) -> Result<Option<TyposBinary>, TyposExtensionError> {
+        // Both resolved by Zed in the worktree's environment, which is the remote host
+        // for remote worktrees, so a hit here is always usable.
+        if let Some(path) = settings.binary_path() {
+            let path = paths::configured_binary(&path, root_path, &which)?;
+            let run = |argument: &str| runner.run(&path, &[argument]).ok();
+            let probe = Self::can_run(
+                runner,
+                binary_identity::FEATURE,
+                binary_identity::WITHOUT_PROCESSES,
+            )
+            .then_some(&run as binary_identity::Probe<'_>);
+            binary_identity::check(&path, probe)?;
+            return Ok(Some(TyposBinary {
+                path,
+                version: None,
+                provenance: Provenance::Settings,
+                skipped: Vec::new(),
+            }));
+        }
+        // A work directory added to the `$PATH` yields an installation of the extension,
+        // which is managed like any other.
+        let found = (!settings.extension.ignore_system_version)
+            .then(|| which("typos-lsp"))
+            .flatten()
+            .filter(|path| {
+                let managed = paths::is_within(Path::new(path), Path::new(work_dir));
+                if managed {
+                    log::debug(messages::managed_binary_on_path(path));
+                }
+                !managed
+            });
+        if let Some(path) = found {
+            log::once(
+                format!("path-binary:{path}"),
+                log::Level::Info,
+                messages::system_binary_on_path(&path),
+            );
+            return Ok(Some(TyposBinary {
                 path,
-                args: Some(vec![]),
+                version: None,
+                provenance: Provenance::Path,
+                skipped: Vec::new(),
+            }));
+        }
+        Ok(None)
+    }
+
+    fn resolve_binary(
+        &mut self,
+        language_server_id: &LanguageServerId,
+        worktree: &zed::Worktree,
+        metrics: &mut Metrics,
+    ) -> Result<TyposBinary, TyposExtensionError> {
+        let settings = metrics.time(Phase::Settings, || {
+            WorktreeSettings::for_worktree(language_server_id, worktree)
+        });
+        metrics.enabled = settings.extension.local_metrics && !settings.extension.dry_run;
+
+        let work_dir = self.absolute_work_dir_path(".");
+        let user_binary = Self::user_binary(
+            &settings,
+            &worktree.root_path(),
+            &work_dir,
+            self.runner.as_ref(),
+            |name| worktree.which(name),
+        )?;
+        if let Some(binary) = user_binary {
+            return Ok(binary);
+        }
+
+        // Everything below installs into the local extension work directory, which
+        // only makes sense if the command runs on the same host.
+        let shell_env = if capabilities::degrade(Capability::ShellEnv) {
+            worktree.shell_env()
+        } else {
+            Vec::new()
+        };
+        let root_path = paths::canonical_root(&worktree.root_path());
+        let (platform, architecture) =
+            ExecutionContext::detect(&shell_env, &root_path, zed::current_platform())
+                .install_platform(zed::current_platform())?;
+        let container = Container::detect(&shell_env, &root_path);
+
+        if let Some(notice) = &settings.version.notice {
+            log::once(
+                format!("version-notice:{root_path}:{notice}"),
+                log::Level::Info,
+                notice,
+            );
+        }
+        let lockfile = Self::read_lockfile(worktree)?;
+        let candidates = PinCandidates {
+            lockfile: lockfile.as_ref().map(|lockfile| lockfile.version.clone()),
+            settings: settings.version.preference.tag().map(str::to_string),
+            tool_versions: worktree
+                .read_text_file(tool_versions::FILE_NAME)
+                .ok()
+                .and_then(|content| tool_versions::parse(&content)),
+        };
+        let pin = pinning::pinned_release(&candidates, || {
+            settings
+                .extension
+                .sync_with_cli
+                .then(|| Self::cli_pinned_version(worktree))
+                .flatten()
+        });
+        if let Some((tag, source)) = &pin {
+            capabilities::require(Capability::ReleaseByTag, source.name())?;
+            if *source == PinSource::ToolVersions {
+                log::once(
+                    format!("tool-versions-pin:{root_path}:{tag}"),
+                    log::Level::Info,
+                    messages::pinned_in_tool_versions(tag, tool_versions::FILE_NAME),
+                );
+            }
+        }
+        let pinned_version = pin.map(|(tag, _)| tag);
+        let asset_preference = settings
+            .extension
+            .asset_preference
+            .clone()
+            .unwrap_or_else(|| {
+                let fallback = libc_fallback::read(self.fs.as_ref()).is_some();
+                Self::libc_preference(container, fallback).to_vec()
             });
+
+        let request = InstallRequest {
+            platform,
+            architecture,
+            container,
+            lockfile,
+            pinned_version,
+            asset_preference,
+            dry_run: settings.extension.dry_run,
+            asset_name: settings.extension.asset_name.clone(),
+            worktree: root_path,
+            update_timing: settings.extension.update_timing,
+            cleanup: settings.extension.cleanup != Cleanup::Off,
+            keep_versions: usize::try_from(settings.extension.keep_versions).unwrap_or(usize::MAX),
+            update_check_interval: Duration::from_secs(
+                settings
+                    .extension
+                    .update_check_interval_hours
+                    .saturating_mul(3600),
+            ),
+            prefer_prerelease: settings.extension.prefer_prerelease,
+            auto_update: settings.extension.auto_update,
+            mirror: settings.extension.download.clone(),
+        };
+        self.install(language_server_id.as_ref(), &request, metrics, &|status| {
+            zed::set_language_server_installation_status(language_server_id, &status.to_zed())
+        })
+    }
+
+    /// Make the binary `request` describes available in the work directory, reusing
+    /// the one this server was last given when it still fits.
+    ///
+    /// `report_status` is only given the statuses to show, ending on a final one.
+    fn install(
+        &mut self,
+        language_server_id: &str,
+        request: &InstallRequest,
+        metrics: &mut Metrics,
+        report_status: &dyn Fn(Status),
+    ) -> Result<TyposBinary, TyposExtensionError> {
+        self.statuses.begin(language_server_id);
+        let binary = self
+            .install_release(language_server_id, request, metrics, report_status)
+            // Several worktrees with their own settings may share the server ID.
+            .map_err(|e| TyposExtensionError::in_worktree(&request.worktree, e));
+        if let Some(status) = self.statuses.finish(language_server_id, &binary) {
+            report_status(status);
         }
+        binary
+    }
+
+    fn install_release(
+        &mut self,
+        language_server_id: &str,
+        request: &InstallRequest,
+        metrics: &mut Metrics,
+        report_status: &dyn Fn(Status),
+    ) -> Result<TyposBinary, TyposExtensionError> {
+        let fs = self.fs.as_ref();
+        let lockfile = &request.lockfile;
+        let pinned_version = &request.pinned_version;
+        let now = (self.clock)();
+        // Only the first resolution finds the work directory as it was scanned.
+        let warmup = self
+            .warmup
+            .take()
+            .filter(|warmup| warmup.serves((request.platform, request.architecture)));
+        let warmup = warmup.as_ref();
+        let deferred = request.update_timing == UpdateTiming::Deferred && pinned_version.is_none();
+        let pending = match warmup {
+            Some(warmup) => warmup.pending_update,
+            None => PendingUpdate::read(fs),
+        };
+        let update_due =
+            deferred && pending.is_some_and(|pending| pending.is_due(now, self.session_start));
 
-        if let Some(path) = &self.cached_binary_path {
-            if fs::metadata(path).map_or(false, |stat| stat.is_file()) {
+        // Borrowing the field alone, as the in-use directories are updated meanwhile.
+        let state = self
+            .servers
+            .entry((language_server_id.to_string(), request.worktree.clone()))
+            .or_default();
+        let cached_version = state
+            .cached_binary_version
+            .as_deref()
+            .and_then(Version::parse);
+        // A pre-release installed before `prefer_prerelease` was turned off is replaced.
+        let cache_matches_pin = match pinned_version {
+            None => cached_version
+                .as_ref()
+                .map_or(true, |version| request.accepts(version)),
+            Some(pinned) => Version::parse(pinned) == cached_version,
+        };
+        // A binary kept while `auto_update` was off is replaced once it is back on.
+        let held_back = state.held_back && request.auto_update;
+        let selected = state.cached_selection == Some(request.selection());
+        if let Some(path) = state
+            .cached_binary_path
+            .as_ref()
+            .filter(|_| cache_matches_pin && selected && !update_due && !held_back)
+        {
+            // Installations only ever lie in the work directory, whatever the current
+            // directory turned into.
+            let contained = work_dir::contains(&self.work_dir, path);
+            if !contained {
+                log::debug(messages::cached_binary_outside_work_dir(path));
+            }
+            if contained && fs.is_file(Path::new(path)) {
+                if let Some(lockfile) = lockfile {
+                    metrics.time(Phase::Validation, || {
+                        Self::verify_locked_binary(fs, lockfile, path)
+                    })?;
+                }
+                self.in_use_versions.extend(cached_version);
                 return Ok(TyposBinary {
                     path: path.clone(),
-                    args: Some(vec![]),
+                    version: state.cached_binary_version.clone(),
+                    provenance: Provenance::Cached,
+                    skipped: Vec::new(),
+                });
+            }
+        }
+
+        // A pinned release installed by an earlier session needs no release lookup.
+        if let Some(pinned) = pinned_version.as_deref().and_then(Version::parse) {
+            if let Some((tag, path)) = Self::installation(fs, warmup, request, &pinned) {
+                if let Some(lockfile) = lockfile {
+                    metrics.time(Phase::Validation, || {
+                        Self::verify_locked_binary(fs, lockfile, &path)
+                    })?;
+                }
+                if let Some(binary) =
+                    self.use_installation(language_server_id, request, pinned, tag, path)
+                {
+                    return Ok(binary);
+                }
+            }
+        }
+
+        // Without `auto_update`, GitHub is only queried when nothing is installed.
+        let fs = self.fs.as_ref();
+        if !request.auto_update && pinned_version.is_none() {
+            if let Some((installed, tag, path)) = Self::newest_installation(fs, warmup, request) {
+                let message = messages::update_disabled(&tag);
+                if let Some(binary) =
+                    self.use_installation(language_server_id, request, installed, tag, path)
+                {
+                    self.server_state(language_server_id, &request.worktree)
+                        .held_back = true;
+                    log::debug(message);
+                    return Ok(binary);
+                }
+            }
+        }
+
+        let fs = self.fs.as_ref();
+        if deferred && !update_due {
+            if let Some((installed, tag, path)) = Self::newest_installation(fs, warmup, request) {
+                let message = messages::update_deferred(&tag);
+                if let Some(binary) =
+                    self.use_installation(language_server_id, request, installed, tag, path)
+                {
+                    if !request.dry_run {
+                        PendingUpdate::record(self.fs.as_ref(), now);
+                    }
+                    log::info(message);
+                    return Ok(binary);
+                }
+            }
+        }
+
+        // The latest release looked up recently is used without another lookup, as long
+        // as it is installed.
+        let fs = self.fs.as_ref();
+        if pinned_version.is_none() {
+            let last_check = match warmup {
+                Some(warmup) => warmup.last_check.clone(),
+                None => LastCheck::read(fs),
+            };
+            let recent = last_check
+                .filter(|check| {
+                    check.pre_release == request.prefer_prerelease
+                        && check.is_fresh(now, request.update_check_interval)
+                })
+                .and_then(|check| {
+                    let version = Version::parse(&check.version)?;
+                    let (tag, path) = Self::installation(fs, warmup, request, &version)?;
+                    Some((version, tag, path))
                 });
+            if let Some((installed, tag, path)) = recent {
+                let message = messages::recently_checked(&tag);
+                if let Some(binary) =
+                    self.use_installation(language_server_id, request, installed, tag, path)
+                {
+                    if !request.dry_run {
+                        PendingUpdate::clear(self.fs.as_ref());
+                    }
+                    log::debug(message);
+                    return Ok(binary);
+                }
             }
         }
 
-        zed::set_language_server_installation_status(
+        let corrupted = warmup.map_or_else(BTreeSet::new, |warmup| {
+            warmup
+                .versions()
+                .filter(|version| warmup.is_corrupted(version))
+                .cloned()
+                .collect()
+        });
+        let fetched = self.fetch_release(
             language_server_id,
-            &zed::LanguageServerInstallationStatus::CheckingForUpdate,
+            request,
+            &corrupted,
+            metrics,
+            report_status,
         );
-        let release = zed::latest_github_release(
-            "tekumara/typos-lsp",
-            zed::GithubReleaseOptions {
-                require_assets: true,
-                pre_release: false,
-            },
-        )?;
+        // Without a network, an installation from an earlier session beats no server.
+        // The last known good one comes first, as newer ones may never have started.
+        match fetched {
+            Err(error) if pinned_version.is_none() && !request.dry_run => {
+                let fs = self.fs.as_ref();
+                let known_good = known_good::read(fs)
+                    .filter(|version| request.accepts(version))
+                    .and_then(|version| {
+                        let (tag, path) = Self::installation(fs, None, request, &version)?;
+                        Some((version, tag, path))
+                    });
+                let Some((installed, tag, path)) =
+                    known_good.or_else(|| Self::newest_installation(fs, None, request))
+                else {
+                    return Err(error);
+                };
+                let message = messages::fetch_failed_using_installed(&tag, &error);
+                // Unlike a lookup failing offline, a failed download shows.
+                let failed_update = self
+                    .statuses
+                    .downloading(language_server_id)
+                    .then(|| messages::update_failed(&tag, &error));
+                match self.use_installation(language_server_id, request, installed, tag, path) {
+                    Some(binary) => {
+                        log::warn(message);
+                        if let Some(status) = failed_update.and_then(|failure| {
+                            self.statuses.fail_over(language_server_id, failure)
+                        }) {
+                            report_status(status);
+                        }
+                        Ok(binary)
+                    }
+                    None => Err(error),
+                }
+            }
+            fetched => fetched,
+        }
+    }
+
+    /// Hand the installation of `installed` out to the server, as the binary at `path`
+    /// of the release `tag`, unless it fails its probe.
+    fn use_installation(
+        &mut self,
+        language_server_id: &str,
+        request: &InstallRequest,
+        installed: Version,
+        tag: String,
+        path: String,
+    ) -> Option<TyposBinary> {
+        if !self.probe_installation(&installed, &path, &tag, request.dry_run) {
+            return None;
+        }
+        self.in_use_versions.insert(installed);
+        let state = self.server_state(language_server_id, &request.worktree);
+        state.cached_binary_path = Some(path.clone());
+        state.cached_binary_version = Some(tag.clone());
+        state.cached_selection = Some(request.selection());
+        state.held_back = false;
+        Some(TyposBinary {
+            path,
+            version: Some(tag),
+            provenance: Provenance::Cached,
+            skipped: Vec::new(),
+        })
+    }
+
+    /// Whether `runner` can run processes for `feature`, which is reported as not
+    /// available otherwise, with what the extension does `instead`.
+    fn can_run(runner: &dyn Runner, feature: &str, instead: &str) -> bool {
+        let available = runner.available();
+        if !available {
+            capabilities::report_unavailable(Capability::ProcessSpawn, feature, instead);
+        }
+        available
+    }
+
+    /// Probe the binary at `path` of the installation of `installed`, release `tag`,
+    /// once per session. An installation failing its probe is removed so that it is
+    /// downloaded again, unless in a `dry_run`.
+    fn probe_installation(
+        &mut self,
+        installed: &Version,
+        path: &str,
+        tag: &str,
+        dry_run: bool,
+    ) -> bool {
+        if self.probed.contains(path) {
+            return true;
+        }
+        if !Self::can_run(
+            self.runner.as_ref(),
+            binary_probe::FEATURE,
+            binary_probe::WITHOUT_PROCESSES,
+        ) {
+            return true;
+        }
+        match binary_probe::probe(self.runner.as_ref(), path, tag) {
+            Probed::Passed => {
+                self.probed.insert(path.to_string());
+                true
+            }
+            Probed::Failed(reason) => {
+                log::warn(binary_probe::reinstalling(path, &reason));
+                if !dry_run {
+                    Self::remove_entry(self.fs.as_ref(), Path::new(&installed.dir_name()));
+                }
+                false
+            }
+        }
+    }
+
+    /// Look up the release `request` asks for and install it, unless it already is with
+    /// a binary not known to be `corrupted`.
+    fn fetch_release(
+        &mut self,
+        language_server_id: &str,
+        request: &InstallRequest,
+        corrupted: &BTreeSet<Version>,
+        metrics: &mut Metrics,
+        report_status: &dyn Fn(Status),
+    ) -> Result<TyposBinary, TyposExtensionError> {
+        let fs = self.fs.as_ref();
+        let lockfile = &request.lockfile;
+        let pinned_version = &request.pinned_version;
+        let (platform, architecture) = (request.platform, request.architecture);
+
+        Self::report(
+            &mut self.statuses,
+            language_server_id,
+            Status::CheckingForUpdate,
+            report_status,
+        );
+        let mirror = &request.mirror;
+        let http = self.http.as_ref();
+        let mut release = metrics.time(Phase::ReleaseLookup, || {
+            match (pinned_version, &mirror.release_api_url, &mirror.base_url) {
+                (Some(tag), Some(api_url), _) => mirror::release_by_tag(http, api_url, tag)
+                    .map_err(|e| Self::release_lookup_failed(Some(tag), e)),
+                // The mirror serves the pinned release under the names it is looked for.
+                (Some(tag), None, Some(base_url)) => Ok(mirror::unlisted_release(
+                    base_url,
+                    tag,
+                    &Self::expected_asset_names(request, tag)?,
+                )),
+                (Some(tag), None, None) => {
+                    self.github_lookup(Some(tag), request.dry_run, |releases| {
+                        releases.release_by_tag(Self::GITHUB_REPOSITORY, tag)
+                    })
+                }
+                (None, Some(api_url), _) => {
+                    mirror::latest_release(http, api_url, request.prefer_prerelease)
+                        .map_err(|e| Self::release_lookup_failed(None, e))
+                }
+                (None, None, _) => self.github_lookup(None, request.dry_run, |releases| {
+                    releases.latest_release(Self::GITHUB_REPOSITORY, request.prefer_prerelease)
+                }),
+            }
+        })?;
+        mirror.redirect(&mut release);
+        if pinned_version.is_none() && !request.dry_run {
+            LastCheck::record(
+                fs,
+                &release.version,
+                (self.clock)(),
+                request.prefer_prerelease,
+            );
+        }
 
-        let (platform, architecture) = zed::current_platform();
+        Self::ensure_uploaded_assets(&release)?;
+        packaging::predates(&release.version, &release.assets)?;
         let version = release.version;
 
-        let asset_name = Self::binary_release_name(&version, &platform, &architecture);
-        let asset = release
-            .assets
-            .iter()
-            .find(|asset| asset.name == asset_name)
-            .ok_or_else(|| format!("no asset found matching {:?}", asset_name))?;
+        let preference = &request.asset_preference;
+        let (asset, libc) = match (lockfile, &request.asset_name) {
+            (Some(lockfile), _) => Self::locked_asset(lockfile, &release.assets, preference)?,
+            (None, Some(template)) => {
+                Self::named_asset(template, &release.assets, &version, preference)?
+            }
+            (None, None) => Self::select_asset(
+                &release.assets,
+                &version,
+                &platform,
+                &architecture,
+                preference,
+            )?,
+        };
 
-        let version_dir = format!("typos-lsp-{}", version);
+        let parsed_version = Self::parse_version(&version)?;
+        let version_dir = parsed_version.dir_name();
         let binary_path = Path::new(&version_dir)
-            .join(Self::binary_path_within_archive(&platform, &architecture))
+            .join(Self::binary_path_within_archive(&platform, &architecture)?)
             .to_str()
             .expect("Could not convert binary path to str")
             .to_string();
 
-        if !fs::metadata(&binary_path).map_or(false, |stat| stat.is_file()) {
-            zed::set_language_server_installation_status(
+        // The latest release is not started again once set aside, unless it is all
+        // there is.
+        let set_aside = Manifest::read(fs, Path::new(&version_dir))
+            .is_some_and(|manifest| start_failures::set_aside(&manifest));
+        if set_aside && pinned_version.is_none() {
+            if let Some((installed, tag, path)) = Self::newest_installation(fs, None, request) {
+                let message = start_failures::using_previous(&version, &tag);
+                if let Some(binary) =
+                    self.use_installation(language_server_id, request, installed, tag, path)
+                {
+                    log::warn(message);
+                    return Ok(binary);
+                }
+            }
+        }
+
+        // An installation failing its probe is removed, to be downloaded again.
+        if self.fs.is_file(Path::new(&binary_path)) && !corrupted.contains(&parsed_version) {
+            self.probe_installation(&parsed_version, &binary_path, &version, request.dry_run);
+        }
+        let fs = self.fs.as_ref();
+        let installed = |fs: &dyn Fs| {
+            fs.is_file(Path::new(&binary_path)) && !corrupted.contains(&parsed_version)
+        };
+        if !installed(fs) && request.dry_run {
+            let (removed, deferred) = if request.cleanup {
+                Self::other_installations(
+                    fs,
+                    &parsed_version,
+                    request.keep_versions,
+                    &self.in_use_versions,
+                )?
+            } else {
+                Default::default()
+            };
+            let download = Step::Download {
+                url: asset.download_url.clone(),
+                directory: version_dir,
+            };
+            return Ok(TyposBinary {
+                path: binary_path,
+                version: Some(version),
+                provenance: Provenance::Downloaded,
+                skipped: std::iter::once(download)
+                    .chain(removed.into_iter().map(Step::Remove))
+                    .chain(deferred.into_iter().map(Step::DeferRemoval))
+                    .collect(),
+            });
+        }
+        if !installed(fs) {
+            Self::report(
+                &mut self.statuses,
                 language_server_id,
-                &zed::LanguageServerInstallationStatus::Downloading,
+                Status::Downloading,
+                report_status,
             );
-            let file_kind = match platform {
-                zed::Os::Windows => zed::DownloadedFileType::Zip,
-                _ => zed::DownloadedFileType::GzipTar,
-            };
-            zed::download_file(&asset.download_url, &version_dir, file_kind)
-                .map_err(|e| format!("failed to download file: {e}"))?;
+            Self::ensure_writable_work_dir(fs, request.container)?;
+            // Other Zed processes sharing the work directory neither install nor clean
+            // up meanwhile.
+            let _lock = WorkLock::acquire(fs, &self.instance, &*self.clock, &*self.sleep)?;
+            // Unless one installed the release while this one waited.
+            if !installed(fs) {
+                Self::ensure_disk_space(
+                    fs,
+                    &version,
+                    &parsed_version,
+                    &self.in_use_versions,
+                    request.cleanup.then_some(request.keep_versions),
+                )?;
+                let file_kind = packaging::archive_type(&asset.name).unwrap_or(match platform {
+                    zed::Os::Windows => zed::DownloadedFileType::Zip,
+                    _ => zed::DownloadedFileType::GzipTar,
+                });
+                let explain = |url: &str, error: String| TyposExtensionError::Download {
+                    reason: mirror.download_failed(download_errors::explain(url, &error)),
+                };
+                // Extracted next to the version directory, which only takes its name once
+                // complete: an interrupted download never passes for an installation.
+                let staging_dir = parsed_version.staging_dir_name();
+                Self::remove_entry(fs, Path::new(&staging_dir));
+                metrics.time(Phase::Download, || {
+                    let mut attempt = 1;
+                    loop {
+                        let result = match release_checksums::asset(&release.assets, &asset.name) {
+                            Some(checksum) => release_checksums::check(
+                                fs,
+                                self.downloader.as_ref(),
+                                asset,
+                                checksum,
+                                explain,
+                            )
+                            .map(|()| log::info(messages::archive_checksum_checked(&asset.name))),
+                            None => {
+                                log::info(messages::archive_checksum_unpublished(&asset.name));
+                                Ok(())
+                            }
+                        }
+                        .and_then(|()| {
+                            self.downloader
+                                .download(&asset.download_url, &staging_dir, file_kind)
+                                .map_err(|e| explain(&asset.download_url, e))
+                        });
+                        let Err(error) = result else {
+                            return Ok(());
+                        };
+                        let Some(delay) = Self::DOWNLOAD_RETRY_DELAYS.get(attempt - 1) else {
+                            // A failed extraction, e.g. on a full disk, leaves part of it.
+                            Self::discard(fs, &staging_dir, request.cleanup);
+                            return Err(error);
+                        };
+                        log::warn(messages::download_retried(attempt, *delay, &error));
+                        (self.sleep)(*delay);
+                        Self::remove_entry(fs, Path::new(&staging_dir));
+                        attempt += 1;
+                    }
+                })?;
+                let staged_binary = Path::new(&staging_dir).join(
+                    Path::new(&binary_path)
+                        .strip_prefix(&version_dir)
+                        .expect("the binary is in the version directory"),
+                );
+                let settled = archive_layout::settle(
+                    fs,
+                    Path::new(&staging_dir),
+                    Path::new(&Self::binary_path_within_archive(&platform, &architecture)?),
+                    request.cleanup,
+                )
+                .map_err(TyposExtensionError::from)
+                .and_then(|()| {
+                    Self::prepare_binary(fs, self.downloader.as_ref(), &staged_binary, platform)
+                })
+                .and_then(|()| {
+                    let runner = self.runner.as_ref();
+                    if !Self::can_run(
+                        runner,
+                        binary_probe::FEATURE,
+                        binary_probe::WITHOUT_PROCESSES,
+                    ) {
+                        return Ok(());
+                    }
+                    let staged = staged_binary
+                        .to_str()
+                        .expect("Could not convert binary path to str");
+                    match binary_probe::probe(runner, staged, &version) {
+                        Probed::Failed(reason) => {
+                            Err(binary_probe::downloaded_failed(&binary_path, &reason).into())
+                        }
+                        Probed::Passed => Ok(()),
+                    }
+                })
+                .and_then(|()| {
+                    Manifest {
+                        version: version.clone(),
+                        asset: asset.name.clone(),
+                        variant: (platform == Os::Linux).then(|| libc.name().to_string()),
+                        sha256: hashing::sha256_file(fs, &staged_binary).ok(),
+                        worktree: Some(request.worktree.clone()),
+                        last_used: None,
+                        failed_starts: 0,
+                        extension_version: Some(build_info::describe()),
+                        platform: Some(manifest::platform(platform, architecture)),
+                    }
+                    .write(fs, Path::new(&staging_dir))
+                    .map_err(TyposExtensionError::from)
+                })
+                .and_then(|()| {
+                    // Left without its binary by an earlier release of the extension.
+                    Self::remove_entry(fs, Path::new(&version_dir));
+                    fs.rename(Path::new(&staging_dir), Path::new(&version_dir))
+                        .map_err(|e| {
+                            TyposExtensionError::io(messages::move_into_place(&staging_dir), e)
+                        })
+                });
+                settled.inspect_err(|_| Self::discard(fs, &staging_dir, request.cleanup))?;
+                self.probed.insert(binary_path.clone());
+
+                // Only once the new binary is known to be in place.
+                if !request.cleanup {
+                    Self::skip_cleanup();
+                } else if fs.is_file(Path::new(&binary_path)) {
+                    let deferred = Self::clean_other_installations(
+                        fs,
+                        &parsed_version,
+                        request.keep_versions,
+                        &self.in_use_versions,
+                    )?;
+                    Self::defer_cleanup(fs, &deferred);
+                }
+            }
+        }
 
-            Self::clean_other_installations(&version_dir)?;
+        if let Some(lockfile) = lockfile {
+            metrics.time(Phase::Validation, || {
+                Self::verify_locked_binary(fs, lockfile, &binary_path)
+            })?;
+        }
+        if pinned_version.is_none() && !request.dry_run {
+            // The latest release is installed, whenever the update was due.
+            PendingUpdate::clear(fs);
         }
 
-        self.cached_binary_path = Some(binary_path.clone());
+        self.in_use_versions.insert(parsed_version);
+        let state = self.server_state(language_server_id, &request.worktree);
+        state.cached_binary_path = Some(binary_path.clone());
+        state.cached_binary_version = Some(version.clone());
+        state.cached_selection = Some(request.selection());
+        state.held_back = false;
         Ok(TyposBinary {
             path: binary_path,
-            args: Some(vec![]),
+            version: Some(version),
+            provenance: Provenance::Downloaded,
+            skipped: Vec::new(),
         })
     }
 
-    /// The name of the archive found under the "Release" tabs of the GitHub repository,
-    /// depending on the version, platform and architecture.
-    fn binary_release_name(version: &String, platform: &Os, architecture: &Architecture) -> String {
-        format!(
-            "typos-lsp-{version}-{arch}-{os}.{ext}",
-            version = version,
-            arch = match architecture {
-                Architecture::Aarch64 => "aarch64",
-                Architecture::X86 | Architecture::X8664 => "x86_64",
-            },
-            os = match platform {
-                zed::Os::Mac => "apple-darwin",
-                zed::Os::Linux => "unknown-linux-gnu",
-                zed::Os::Windows => "pc-windows-msvc",
-            },
-            ext = match platform {
-                zed::Os::Windows => "zip",
-                _ => "tar.gz",
-            }
-        )
+    /// Make the extracted `binary` runnable on `platform`, then check that it is, so that
+    /// a broken extraction fails the installation rather than the start of the server.
+    fn prepare_binary(
+        fs: &dyn Fs,
+        downloader: &dyn Downloader,
+        binary: &Path,
+        platform: Os,
+    ) -> Result<(), TyposExtensionError> {
+        let path = binary
+            .to_str()
+            .expect("Could not convert binary path to str");
+        let windows = platform == Os::Windows;
+        if !windows && capabilities::degrade(Capability::MakeFileExecutable) {
+            downloader
+                .make_executable(path)
+                .map_err(|e| TyposExtensionError::io(messages::make_executable(path), e))?;
+        }
+        if fs.size(binary).map_or(true, |size| size == 0) {
+            return Err(Self::unusable_binary(path, "empty"));
+        }
+        if !windows && !fs.is_executable(binary) {
+            return Err(Self::unusable_binary(path, "not executable"));
+        }
+        Ok(())
     }
 
-    /// The path of the binary inside the archive.
-    fn binary_path_within_archive(platform: &Os, architecture: &Architecture) -> String {
-        let path = match platform {
-            zed::Os::Windows => Path::new("target")
-                .join(format!(
-                    "{arch}-pc-windows-msvc",
-                    arch = match architecture {
-                        Architecture::Aarch64 => "aarch64",
-                        Architecture::X86 | Architecture::X8664 => "x86_64",
-                    },
-                ))
-                .join("release")
-                .join("typos-lsp.exe")
-                .as_path()
-                .to_owned(),
-            _ => Path::new("typos-lsp").to_owned(),
+    /// The extracted binary at `path` cannot be run, as it is `problem`.
+    fn unusable_binary(path: &str, problem: &str) -> TyposExtensionError {
+        TyposExtensionError::Extraction {
+            binary: path.to_string(),
+            problem: problem.to_string(),
+        }
+    }
+
+    /// End the resolution of a binary on the final status of its `result`, handed to
+    /// `report_status`. Installations end on theirs already: this ends the other
+    /// resolutions, failing e.g. on an unsupported platform or a `binary.path` not found,
+    /// and clears the failure of an earlier attempt once a binary is found elsewhere.
+    fn conclude(
+        statuses: &mut StatusTracker,
+        language_server_id: &str,
+        result: &Result<TyposBinary, TyposExtensionError>,
+        report_status: &dyn Fn(Status),
+    ) {
+        if let Some(status) = statuses.finish(language_server_id, result) {
+            report_status(status);
+        }
+    }
+
+    /// Hand `status` to `report_status` unless it would repeat or go back. Taking the
+    /// tracker alone, as the rest of the extension is borrowed meanwhile.
+    fn report(
+        statuses: &mut StatusTracker,
+        language_server_id: &str,
+        status: Status,
+        report_status: &dyn Fn(Status),
+    ) {
+        if let Some(status) = statuses.update(language_server_id, status) {
+            report_status(status);
+        }
+    }
+
+    /// The lockfile of the worktree, if it has one.
+    fn read_lockfile(worktree: &Worktree) -> Result<Option<Lockfile>, String> {
+        match worktree.read_text_file(lockfile::FILE_NAME) {
+            Ok(content) => Lockfile::parse(&content).map(Some),
+            Err(_) => Ok(None),
+        }
+    }
+
+    /// The release asset a lockfile requires.
+    fn locked_asset<'a>(
+        lockfile: &Lockfile,
+        assets: &'a [zed::GithubReleaseAsset],
+        preference: &[LinuxLibc],
+    ) -> Result<(&'a zed::GithubReleaseAsset, LinuxLibc), String> {
+        let asset = assets
+            .iter()
+            .find(|asset| asset.name == lockfile.asset)
+            .ok_or_else(|| {
+                let available: Vec<&str> = assets.iter().map(|asset| asset.name.as_str()).collect();
+                messages::missing_locked_asset(&lockfile.asset, &lockfile.version, &available)
+            })?;
+        let libc = if lockfile.target.ends_with("-musl") {
+            LinuxLibc::Musl
+        } else {
+            preference.first().copied().unwrap_or(LinuxLibc::Gnu)
         };
-        path.to_str()
-            .expect("Could not convert binary path to str")
-            .to_string()
+        Ok((asset, libc))
     }
 
-    /// Remove every typos-lsp version directories within its Zed extension directory,
-    /// except for the version specified as [`version_to_keep`].
-    fn clean_other_installations(version_to_keep: &String) -> Result<(), String> {
-        let entries =
-            fs::read_dir(".").map_err(|e| format!("failed to list working directory {e}"))?;
-        for entry in entries {
-            let entry = entry.map_err(|e| format!("failed to load directory entry {e}"))?;
-            if entry.file_name().to_str() != Some(version_to_keep) {
-                fs::remove_dir_all(entry.path()).ok();
+    /// The release asset the `asset_name` setting names.
+    fn named_asset<'a>(
+        template: &str,
+        assets: &'a [zed::GithubReleaseAsset],
+        version: &str,
+        preference: &[LinuxLibc],
+    ) -> Result<(&'a zed::GithubReleaseAsset, LinuxLibc), String> {
+        let name = Self::expand_asset_name(template, version);
+        let asset = assets
+            .iter()
+            .find(|asset| asset.name == name)
+            .ok_or_else(|| {
+                let available: Vec<&str> = assets.iter().map(|asset| asset.name.as_str()).collect();
+                messages::missing_named_asset(&name, version, &available)
+            })?;
+        let libc = if Self::asset_target(&name, version).ends_with("-musl") {
+            LinuxLibc::Musl
+        } else {
+            preference.first().copied().unwrap_or(LinuxLibc::Gnu)
+        };
+        Ok((asset, libc))
+    }
+
+    /// An `asset_name` setting, with `{version}` replaced by the release tag.
+    fn expand_asset_name(template: &str, version: &str) -> String {
+        template.replace("{version}", version)
+    }
+
+    /// Fail when the binary is not the one the lockfile describes.
+    fn verify_locked_binary(
+        fs: &dyn Fs,
+        lockfile: &Lockfile,
+        binary_path: &str,
+    ) -> Result<(), String> {
+        let found = hashing::sha256_file(fs, Path::new(binary_path))?;
+        if found != lockfile.sha256 {
+            return Err(messages::checksum_mismatch(
+                binary_path,
+                &lockfile.sha256,
+                &found,
+            ));
+        }
+        Ok(())
+    }
+
+    /// Write the lockfile describing the managed binary into the worktree.
+    fn export_lockfile(
+        &self,
+        worktree: &Worktree,
+        settings: &WorktreeSettings,
+        typos_binary: &TyposBinary,
+    ) -> Result<(), String> {
+        let Some(version) = &typos_binary.version else {
+            return Ok(());
+        };
+        let fs = self.fs.as_ref();
+        let version_dir = Self::parse_version(version)?.dir_name();
+        let manifest = Manifest::read(fs, Path::new(&version_dir))
+            .ok_or_else(|| messages::lockfile_without_manifest(&version_dir))?;
+
+        let mut config = BTreeMap::new();
+        let options = settings.initialization_options();
+        let configured = options.get("config").and_then(|config| config.as_str());
+        for path in config_source::FILE_NAMES.iter().copied().chain(configured) {
+            if let Ok(content) = worktree.read_text_file(path) {
+                config.insert(path.to_string(), lockfile::config_digest(&content));
             }
         }
+
+        let lockfile = Lockfile {
+            version: version.clone(),
+            target: Self::asset_target(&manifest.asset, version),
+            asset: manifest.asset,
+            sha256: hashing::sha256_file(fs, Path::new(&typos_binary.path))?,
+            config,
+        };
+        let current = worktree.read_text_file(lockfile::FILE_NAME).ok();
+        let root_path = paths::canonical_root(&worktree.root_path());
+        worktree_files::write_if_changed(
+            &settings.write_consent(&root_path),
+            &StdFs::new(&root_path),
+            fs,
+            Path::new(&root_path),
+            lockfile::FILE_NAME,
+            &lockfile.render(),
+            current.as_deref(),
+        )?;
         Ok(())
     }
-}
 
-impl zed::Extension for TyposExtension {
-    fn new() -> Self {
-        Self {
-            cached_binary_path: None,
+    /// Remove what the extension wrote for the worktree at `root`, in it and in the work
+    /// directory, and describe what was done. `read` reads a file of the worktree.
+    fn clean_up(&self, root: &str, read: impl Fn(&str) -> Option<String>) -> String {
+        let cleaned =
+            worktree_files::clean(&StdFs::new(root), self.fs.as_ref(), Path::new(root), read);
+        let generated = typos_config::path(root);
+        let mut removed: Vec<String> = cleaned
+            .removed
+            .iter()
+            .map(|path| path.display().to_string())
+            .collect();
+        if self.fs.remove_file(Path::new(&generated)).is_ok() {
+            removed.push(self.work_dir.join(&generated).display().to_string());
+        }
+        if effective_config::remove(self.fs.as_ref(), root) {
+            let dir = effective_config::path(root, effective_config::DEFAULT_PATH);
+            let dir = self.work_dir.join(dir.parent().unwrap_or(&dir));
+            removed.push(dir.display().to_string());
         }
+        let kept: Vec<String> = cleaned
+            .kept
+            .iter()
+            .map(|path| path.display().to_string())
+            .collect();
+        messages::cleaned_up(root, &removed, &kept)
     }
 
-    fn language_server_command(
-        &mut self,
-        language_server_id: &LanguageServerId,
+    /// Keep the effective configuration file of the worktree in line with the settings.
+    fn write_effective_config(&self, worktree: &Worktree, settings: &WorktreeSettings) {
+        if settings.extension.dry_run {
+            return;
+        }
+        let root_path = paths::canonical_root(&worktree.root_path());
+        let result = effective_config::sync(
+            self.fs.as_ref(),
+            &root_path,
+            settings.extension.write_effective_config.as_deref(),
+            || Self::effective_config(worktree, settings),
+        );
+        match result {
+            Ok(Some(path)) => {
+                let path = self.work_dir.join(path);
+                log::once(
+                    format!("effective-config:{}", path.display()),
+                    log::Level::Info,
+                    messages::effective_config_written(&root_path, &path),
+                );
+            }
+            Ok(None) => {}
+            Err(e) => log::warn(messages::effective_config_write_failed(e)),
+        }
+    }
+
+    /// The configuration typos-lsp uses for the worktree, as written for other tools.
+    fn effective_config(
         worktree: &Worktree,
-    ) -> Result<Command> {
-        let typos_binary = self.language_server_binary(language_server_id, worktree)?;
+        settings: &WorktreeSettings,
+    ) -> Result<String, String> {
+        let generated = Self::generated_config(worktree, settings);
+        let source = Self::config_source(worktree, settings)?;
+        let label = match &source {
+            ConfigSource::Path(path) | ConfigSource::Discovered(path) => {
+                let content = Self::read_typos_config(worktree, path);
+                return Ok(effective_config::render(
+                    content.as_deref().map(|content| (path.as_str(), content)),
+                    &generated,
+                ));
+            }
+            ConfigSource::None => return Ok(effective_config::render(None, &generated)),
+            ConfigSource::Inline(_) => messages::CONFIG_TOML_LABEL.to_string(),
+            ConfigSource::Files(files) => messages::config_files_label(files),
+        };
+        let content = Self::custom_config(worktree, &source, &generated)?;
+        // The generated options are already layered over it.
+        Ok(effective_config::render(
+            Some((&label, content.as_deref().unwrap_or_default())),
+            &TyposConfig::default(),
+        ))
+    }
 
-        Ok(zed::Command {
-            command: typos_binary.path,
-            args: typos_binary.args.unwrap(),
-            env: Default::default(),
-        })
+    /// Read a typos configuration file, relative to the worktree root or absolute.
+    ///
+    /// Only the worktree can be read from the extension sandbox: a file outside of it is
+    /// reported as unreadable, although typos-lsp still reads it.
+    fn read_typos_config(worktree: &Worktree, path: &str) -> Option<String> {
+        let Some(relative) = paths::worktree_relative(path, &worktree.root_path()) else {
+            log::warn(messages::config_outside_worktree(path));
+            return None;
+        };
+        let content = worktree.read_text_file(&relative).ok();
+        if content.is_none() {
+            log::warn(messages::unreadable_config(path));
+        }
+        content
     }
 
-    fn language_server_initialization_options(
-        &mut self,
-        server_id: &LanguageServerId,
-        worktree: &zed_extension_api::Worktree,
-    ) -> Result<Option<zed_extension_api::serde_json::Value>> {
-        let settings = LspSettings::for_worktree(server_id.as_ref(), worktree)
-            .ok()
-            .and_then(|lsp_settings| lsp_settings.initialization_options.clone())
-            .unwrap_or_default();
-        Ok(Some(settings))
+    /// The arguments to start `binary` with, for the configuration `source` of the
+    /// worktree at `root_path`.
+    fn arguments(
+        settings: &WorktreeSettings,
+        binary: &TyposBinary,
+        source: &ConfigSource,
+        root_path: &str,
+    ) -> Vec<String> {
+        let user_arguments = settings.binary_arguments();
+        let mut arguments = default_args::compose(
+            settings.extension.arguments_mode,
+            binary.provenance,
+            binary.version.as_deref(),
+            user_arguments.clone(),
+        );
+        arguments.extend(config_source::arguments(
+            source,
+            root_path,
+            settings.extension.arguments_mode,
+            &user_arguments,
+        ));
+        arguments
     }
 
-    fn language_server_workspace_configuration(
-        &mut self,
-        server_id: &LanguageServerId,
-        worktree: &zed_extension_api::Worktree,
-    ) -> Result<Option<zed_extension_api::serde_json::Value>> {
-        let settings = LspSettings::for_worktree(server_id.as_ref(), worktree)
-            .ok()
-            .and_then(|lsp_settings| lsp_settings.settings.clone())
-            .unwrap_or_default();
-        Ok(Some(settings))
+    /// Where the typos configuration of the worktree comes from, failing on conflicting
+    /// settings.
+    fn config_source(
+        worktree: &Worktree,
+        settings: &WorktreeSettings,
+    ) -> Result<ConfigSource, String> {
+        let options = settings.initialization_options();
+        let discovered = config_source::discover(|path| worktree.read_text_file(path).is_ok());
+        config_source::decide(
+            options.get("config").and_then(|config| config.as_str()),
+            settings.extension.config_toml.as_deref(),
+            &settings.extension.config_files,
+            discovered,
+        )
+        .map_err(messages::invalid_config_settings)
     }
-}
 
-zed::register_extension!(TyposExtension);
+    /// The content of the custom configuration the extension hands to typos-lsp, with
+    /// the options generated from the settings layered over the configured source.
+    ///
+    /// A `config` file is handed over as is, so there is none for it.
+    fn custom_config(
+        worktree: &Worktree,
+        source: &ConfigSource,
+        generated: &TyposConfig,
+    ) -> Result<Option<String>, String> {
+        match source {
+            ConfigSource::Path(_) => Ok(None),
+            ConfigSource::Inline(toml) => {
+                TyposConfig::parse(toml)
+                    .and_then(|config| ignore_patterns::validate(&config))
+                    .map_err(messages::invalid_config_toml)?;
+                Ok(Some(generated.merge_into(toml)))
+            }
+            ConfigSource::Files(files) => {
+                let mut merged = TyposConfig::default();
+                for file in files {
+                    let content = worktree
+                        .read_text_file(file)
+                        .map_err(|e| messages::unreadable_config_file(file, e))?;
+                    let config = TyposConfig::parse(&content)
+                        .and_then(|config| ignore_patterns::validate(&config).map(|()| config))
+                        .map_err(|e| messages::invalid_config_file(file, e))?;
+                    merged.merge(&config);
+                }
+                merged.merge(generated);
+                Ok(Some(merged.render()))
+            }
+            // typos-lsp reads the discovered file itself, under the custom configuration,
+            // so what it already accepts is not handed over twice.
+            ConfigSource::Discovered(path) => {
+                let discovered = worktree
+                    .read_text_file(path)
+                    .ok()
+                    .and_then(|content| TyposConfig::parse(&content).ok())
+                    .unwrap_or_default();
+                let generated = config_channel::dedupe(generated, &discovered);
+                Ok((!generated.is_empty()).then(|| generated.render()))
+            }
+            ConfigSource::None => Ok((!generated.is_empty()).then(|| generated.render())),
+        }
+    }
+
+    /// The target triple of a release asset, e.g. `x86_64-unknown-linux-gnu`.
+    fn asset_target(asset: &str, version: &str) -> String {
+        let target = asset
+            .strip_prefix(&format!("typos-lsp-{version}-"))
+            .or_else(|| asset.strip_prefix("typos-lsp-"))
+            .unwrap_or(asset);
+        target
+            .strip_suffix(".tar.gz")
+            .or_else(|| target.strip_suffix(".zip"))
+            .unwrap_or(target)
+            .to_string()
+    }
+
+    /// The typos-lsp release matching the typos CLI version pinned by the worktree,
+    /// if any and if it is known.
+    fn cli_pinned_version(worktree: &Worktree) -> Option<String> {
+        match CliPin::resolve(|path| worktree.read_text_file(path).ok()) {
+            CliPin::None => None,
+            CliPin::Mapped {
+                cli_version,
+                source,
+                lsp_tag,
+            } => {
+                log::info(messages::cli_pin_mapped(&cli_version, source, lsp_tag));
+                Some(lsp_tag.to_string())
+            }
+            CliPin::Unmapped {
+                cli_version,
+                source,
+            } => {
+                log::warn(messages::cli_pin_unmapped(&cli_version, source));
+                None
+            }
+        }
+    }
+
+    /// The Linux builds to look for, in order of preference.
+    ///
+    /// Containers often ship a musl or minimal userland where the glibc build cannot
+    /// start, so the static musl build is preferred there, as it is once a glibc build
+    /// failed to start (see [`libc_fallback`]).
+    fn libc_preference(container: Option<Container>, fallback: bool) -> [LinuxLibc; 2] {
+        match (container, fallback) {
+            (Some(_), _) | (None, true) => [LinuxLibc::Musl, LinuxLibc::Gnu],
+            (None, false) => [LinuxLibc::Gnu, LinuxLibc::Musl],
+        }
+    }
+
+    /// The first asset of the release matching the Linux builds in order of `preference`,
+    /// with the build it matched. The names assets were published under are tried for
+    /// every build before an asset is matched by its target alone.
+    fn select_asset<'a>(
+        assets: &'a [zed::GithubReleaseAsset],
+        version: &str,
+        platform: &Os,
+        architecture: &Architecture,
+        preference: &[LinuxLibc],
+    ) -> Result<(&'a zed::GithubReleaseAsset, LinuxLibc), String> {
+        let candidates: Vec<(LinuxLibc, Vec<String>)> = preference
+            .iter()
+            .map(|libc| {
+                let names = Self::release_name_variants(version, platform, architecture, libc)?;
+                Ok((*libc, names))
+            })
+            .collect::<Result<_, String>>()?;
+        let named = || {
+            candidates.iter().find_map(|(libc, names)| {
+                let asset = names
+                    .iter()
+                    .find_map(|name| assets.iter().find(|asset| &asset.name == name))?;
+                Some((asset, *libc))
+            })
+        };
+        let targeted = || {
+            preference.iter().find_map(|libc| {
+                let (arch, os) = Self::release_target(platform, architecture, libc).ok()?;
+                let asset = assets
+                    .iter()
+                    .find(|asset| packaging::names_target(&asset.name, arch, os))?;
+                Some((asset, *libc))
+            })
+        };
+        named().or_else(targeted).ok_or_else(|| {
+            if Self::release_architecture(architecture) == Ok("i686") {
+                return TyposExtensionError::UnsupportedPlatform {
+                    platform: "32-bit x86".to_string(),
+                    reason: Some(messages::no_i686_build(version)),
+                }
+                .into();
+            }
+            TyposExtensionError::AssetNotFound {
+                tag: version.to_string(),
+                wanted: candidates
+                    .iter()
+                    .map(|(_, names)| names[0].clone())
+                    .collect(),
+                available: assets.iter().map(|asset| asset.name.clone()).collect(),
+            }
+            .into()
+        })
+    }
+
+    /// Look the release `tag`, or the latest one when `None`, up on GitHub with
+    /// `lookup`, unless GitHub refused lookups for its rate limit until later. A lookup
+    /// it refuses records until when the next ones wait, unless in a `dry_run`.
+    fn github_lookup(
+        &self,
+        tag: Option<&str>,
+        dry_run: bool,
+        lookup: impl FnOnce(&dyn ReleaseProvider) -> Result<zed::GithubRelease>,
+    ) -> Result<zed::GithubRelease> {
+        let fs = self.fs.as_ref();
+        let now = (self.clock)();
+        if let Some(until) = rate_limit::waiting_until(fs, now) {
+            return Err(rate_limit::error(until, now).into());
+        }
+        match lookup(self.releases.as_ref()) {
+            Ok(release) => {
+                if !dry_run {
+                    rate_limit::clear(fs);
+                }
+                Ok(release)
+            }
+            Err(e) if rate_limit::is_rate_limited(&e) => {
+                log::debug(messages::release_lookup_refused(&e));
+                let until = rate_limit::backoff_until(&e, now);
+                if !dry_run {
+                    rate_limit::record(fs, until);
+                }
+                Err(rate_limit::error(until, now).into())
+            }
+            Err(e) => Err(Self::release_lookup_failed(tag, e)),
+        }
+    }
+
+    /// Looking up the release `tag`, or the latest one when `None`, failed with `error`.
+    fn release_lookup_failed(tag: Option<&str>, error: impl Display) -> String {
+        TyposExtensionError::ReleaseLookup {
+            tag: tag.map(str::to_string),
+            reason: error.to_string(),
+        }
+        .into()
+    }
+
+    /// Fail on a release without assets, e.g. a pinned tag whose assets are still being
+    /// uploaded, which fetching by tag does not rule out.
+    fn ensure_uploaded_assets(release: &zed::GithubRelease) -> Result<(), String> {
+        if release.assets.is_empty() {
+            return Err(messages::release_without_assets(&release.version));
+        }
+        Ok(())
+    }
+
+    /// Make room for the download of `version` when the disk is nearly full, by running
+    /// the cleanup of other versions that otherwise follows the installation, keeping
+    /// `cleanup` installations in all, unless it is off, and fail when that is not enough
+    /// rather than halfway through the extraction.
+    fn ensure_disk_space(
+        fs: &dyn Fs,
+        tag: &str,
+        version: &Version,
+        in_use: &BTreeSet<Version>,
+        cleanup: Option<usize>,
+    ) -> Result<(), String> {
+        let required = disk_space::required(disk_space::ESTIMATED_ARCHIVE_SIZE);
+        if fs
+            .available_space()
+            .map_or(true, |available| available >= required)
+        {
+            return Ok(());
+        }
+        if let Some(keep_versions) = cleanup {
+            let deferred = Self::clean_other_installations(fs, version, keep_versions, in_use)?;
+            Self::defer_cleanup(fs, &deferred);
+        } else {
+            Self::skip_cleanup();
+        }
+        match fs.available_space() {
+            Some(available) if available < required => {
+                Err(disk_space::insufficient(tag, required, available))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Fail early when nothing can be downloaded into the extension work directory,
+    /// e.g. when a container mounts it as a read-only overlay.
+    fn ensure_writable_work_dir(fs: &dyn Fs, container: Option<Container>) -> Result<(), String> {
+        let probe = Path::new(".write-probe");
+        match fs.write(probe, b"") {
+            Ok(()) => {
+                fs.remove_file(probe).ok();
+                Ok(())
+            }
+            Err(e) => Err(messages::read_only_work_dir(e, container.is_some())),
+        }
+    }
+
+    /// The configuration of the server of `server_id` for `worktree` and its `settings`,
+    /// which both its initialization options and its workspace configuration are
+    /// projected from.
+    fn effective_configuration(
+        &self,
+        server_id: &LanguageServerId,
+        worktree: &Worktree,
+        settings: &WorktreeSettings,
+    ) -> Result<ServerConfiguration, String> {
+        let options = self.server_options(server_id.as_ref(), settings, worktree)?;
+        Ok(ServerConfiguration::new(
+            options,
+            settings.workspace_settings(),
+        ))
+    }
+
+    /// Record a resolution of the server of `server_id` for `root_path`, with settings
+    /// of hash `fingerprint`, returning whether a crash loop suspends its configuration.
+    fn record_resolution(&mut self, server_id: &str, root_path: &str, fingerprint: &str) -> bool {
+        let now = (self.clock)();
+        let state = self.server_state(server_id, root_path);
+        match state.crash_loop.record(now, fingerprint) {
+            crash_loop::Outcome::Configured => false,
+            crash_loop::Outcome::Suspended => {
+                log::warn(crash_loop::suspended(root_path));
+                true
+            }
+            crash_loop::Outcome::StillSuspended => true,
+        }
+    }
+
+    /// The initialization options of the server of `server_id`, the defaults of the
+    /// extension alone while a crash loop suspends its configuration.
+    fn server_options(
+        &self,
+        server_id: &str,
+        settings: &WorktreeSettings,
+        worktree: &Worktree,
+    ) -> Result<zed::serde_json::Value, String> {
+        let root_path = paths::canonical_root(&worktree.root_path());
+        let suspended = self
+            .servers
+            .get(&(server_id.to_string(), root_path))
+            .is_some_and(|state| state.crash_loop.safe_mode());
+        if suspended {
+            return Ok(settings::default_initialization_options());
+        }
+        self.initialization_options(settings, worktree)
+    }
+
+    /// The initialization options handed to typos-lsp for this worktree, with the
+    /// custom configuration written to a file named by `config`.
+    fn initialization_options(
+        &self,
+        settings: &WorktreeSettings,
+        worktree: &Worktree,
+    ) -> Result<zed::serde_json::Value, String> {
+        // Rejected rather than ignored, as the diagnostics would not be as loud as asked.
+        settings.severity()?;
+        let source = Self::config_source(worktree, settings)?;
+        let generated = Self::generated_config(worktree, settings);
+        let mut options = settings.initialization_options();
+        // typos-lsp resolves relative paths against its own working directory, which is
+        // seldom the worktree.
+        let root_path = paths::canonical_root(&worktree.root_path());
+        if let Some(error) = settings
+            .options_file
+            .as_ref()
+            .and_then(|file| file.error.as_ref())
+        {
+            log::once(
+                format!("options-file:{root_path}:{error}"),
+                log::Level::Warn,
+                error,
+            );
+        }
+        let shell_env = worktree.shell_env();
+        let home = shell_env
+            .iter()
+            .find(|(name, _)| name == "HOME" || name == "USERPROFILE")
+            .map(|(_, home)| home.as_str());
+        Self::resolve_config_path(&mut options, &root_path, home, |path| {
+            worktree.read_text_file(path).is_ok()
+        })?;
+        if matches!(source, ConfigSource::Path(_)) && !generated.is_empty() {
+            log::once(
+                format!("generated-config-ignored:{root_path}"),
+                log::Level::Warn,
+                messages::GENERATED_CONFIG_IGNORED,
+            );
+        }
+        let custom = Self::custom_config(worktree, &source, &generated)?;
+        let path = custom.and_then(|content| self.write_custom_config(&content, &root_path));
+        if !options.is_object() {
+            options = zed::serde_json::json!({});
+        }
+        if let Some(options) = options.as_object_mut() {
+            config_channel::deliver(options, path);
+        }
+        Ok(options)
+    }
+
+    /// The typos options derived from the extension settings and the word fragments of
+    /// the worktree.
+    fn generated_config(worktree: &Worktree, settings: &WorktreeSettings) -> TyposConfig {
+        let mut generated = TyposConfig::default();
+        settings.extension.paths_and_filenames.apply(&mut generated);
+        if settings.extension.disable_default_excludes {
+            default_excludes::apply(&mut generated);
+        }
+        Self::word_fragments(worktree, settings).apply(&mut generated);
+        let extension = &settings.extension;
+        if !extension.extend_words.is_empty()
+            || !extension.extend_ignore_identifiers.is_empty()
+            || !extension.exclude.is_empty()
+        {
+            // Appended to the patterns of the project, which the generated ones replace.
+            let explicit =
+                if extension.extend_ignore_identifiers.is_empty() && extension.exclude.is_empty() {
+                    TyposConfig::default()
+                } else {
+                    Self::explicit_config(worktree, settings)
+                };
+            extend_settings::apply(
+                &mut generated,
+                &explicit,
+                &extension.extend_words,
+                &extension.extend_ignore_identifiers,
+            );
+            excludes::apply(&mut generated, &explicit, &extension.exclude);
+        }
+        Self::corrections(worktree, settings).apply(&mut generated);
+        if settings.extension.community_dictionary {
+            // Below the words configured in the extension settings, the fragments and
+            // the project configuration alike.
+            let added = community_dictionary::apply(
+                &mut generated,
+                &Self::explicit_config(worktree, settings),
+            );
+            let root_path = paths::canonical_root(&worktree.root_path());
+            log::once(
+                format!("community-dictionary:{root_path}:{added}"),
+                log::Level::Info,
+                community_dictionary::notice(added),
+            );
+        }
+        generated
+    }
+
+    /// The typos configuration the project sets up, which the generated options are
+    /// layered over; empty when it cannot be read.
+    fn explicit_config(worktree: &Worktree, settings: &WorktreeSettings) -> TyposConfig {
+        let parse = |content: String| TyposConfig::parse(&content).unwrap_or_default();
+        match Self::config_source(worktree, settings) {
+            Ok(ConfigSource::Discovered(path)) => worktree
+                .read_text_file(&path)
+                .map(parse)
+                .unwrap_or_default(),
+            Ok(ConfigSource::Inline(toml)) => parse(toml),
+            Ok(ConfigSource::Files(files)) => {
+                let mut merged = TyposConfig::default();
+                for content in files
+                    .iter()
+                    .filter_map(|file| worktree.read_text_file(file).ok())
+                {
+                    merged.merge(&parse(content));
+                }
+                merged
+            }
+            // A `config` file leaves out the generated options altogether.
+            Ok(ConfigSource::Path(_) | ConfigSource::None) | Err(_) => TyposConfig::default(),
+        }
+    }
+
+    /// The corrections of the settings and of `corrections_file`, with the entries that
+    /// could not be used logged.
+    fn corrections(worktree: &Worktree, settings: &WorktreeSettings) -> Corrections {
+        let root_path = paths::canonical_root(&worktree.root_path());
+        let file = match &settings.extension.corrections_file {
+            // Through the worktree, as the extension cannot reach its files otherwise.
+            Some(path) => Corrections::from_file(path, worktree.read_text_file(path)),
+            None => Corrections::default(),
+        };
+        for error in &file.errors {
+            log::once(
+                format!("corrections:{root_path}:{error}"),
+                log::Level::Warn,
+                error,
+            );
+        }
+        file.overridden_by(&settings.extension.corrections)
+    }
+
+    /// Log once each word of the configuration in effect that an ignore pattern also
+    /// matches, with what `server_version` does about it.
+    fn log_word_overlaps(
+        worktree: &Worktree,
+        settings: &WorktreeSettings,
+        server_version: Option<&str>,
+    ) {
+        let Some(config) = Self::effective_config(worktree, settings)
+            .ok()
+            .and_then(|content| TyposConfig::parse(&content).ok())
+        else {
+            return;
+        };
+        let root_path = paths::canonical_root(&worktree.root_path());
+        for overlap in word_overlap::detect(&config) {
+            log::once(
+                format!(
+                    "word-overlap:{root_path}:{}:{}",
+                    overlap.word, overlap.pattern
+                ),
+                log::Level::Info,
+                overlap.message(server_version),
+            );
+        }
+    }
+
+    /// The words of the `word_lists` of `.typos/words.d/`, with the fragments that could
+    /// not be read logged.
+    fn word_fragments(worktree: &Worktree, settings: &WorktreeSettings) -> Fragments {
+        let root_path = paths::canonical_root(&worktree.root_path());
+        // Through the worktree, which cannot list the directory: only named files are read.
+        let fragments = Fragments::from_files(settings.extension.word_lists.iter().map(|name| {
+            (
+                name.as_str(),
+                worktree.read_text_file(&word_fragments::path(name)),
+            )
+        }));
+        for error in &fragments.errors {
+            log::once(
+                format!("word-fragments:{root_path}:{error}"),
+                log::Level::Warn,
+                error,
+            );
+        }
+        fragments
+    }
+
+    /// Write the custom configuration of a worktree, returning its absolute path.
+    fn write_custom_config(&self, content: &str, root_path: &str) -> Option<String> {
+        let written = typos_config::write(self.fs.as_ref(), root_path, content).and_then(|path| {
+            // The server does not run in the work directory, the path must be absolute.
+            work_dir::absolute(&self.work_dir, &path)
+                .ok_or_else(|| messages::WORK_DIR_NOT_UTF8.to_string())
+        });
+        written
+            .map_err(|e| log::warn(messages::custom_config_write_failed(e)))
+            .ok()
+    }
+
+    /// A path relative to the work directory made absolute, unless the work directory is
+    /// not UTF-8. Absolute paths, e.g. of `$PATH` binaries, are returned as they are.
+    fn absolute_work_dir_path(&self, path: &str) -> String {
+        work_dir::absolute(&self.work_dir, path).unwrap_or_else(|| path.to_string())
+    }
+
+    /// Make the `config` initialization option absolute: a relative path is taken from
+    /// the worktree root, where it must be `readable`, and a leading `~` is the `home`
+    /// directory.
+    fn resolve_config_path(
+        settings: &mut zed::serde_json::Value,
+        root_path: &str,
+        home: Option<&str>,
+        readable: impl Fn(&str) -> bool,
+    ) -> Result<(), String> {
+        let Some(config) = settings.get_mut("config") else {
+            return Ok(());
+        };
+        let Some(path) = config.as_str().filter(|path| !path.trim().is_empty()) else {
+            return Ok(());
+        };
+        let resolved = if let Some(rest) = path
+            .strip_prefix('~')
+            .filter(|rest| rest.is_empty() || rest.starts_with(['/', '\\']))
+        {
+            let home = home.ok_or_else(|| messages::config_home_unknown(path))?;
+            format!("{home}{rest}")
+        } else if Path::new(path).is_relative() {
+            if !readable(path) {
+                let resolved = Path::new(root_path).join(path);
+                return Err(messages::missing_config(path, &resolved.display()));
+            }
+            Path::new(root_path).join(path).display().to_string()
+        } else {
+            return Ok(());
+        };
+        *config = resolved.into();
+        Ok(())
+    }
+
+    /// The architecture of each target typos-lsp is released for, as it is named in the
+    /// target triples. A variant missing here, e.g. one added by a newer extension API, is
+    /// reported as unsupported rather than mapped to another architecture's build.
+    ///
+    /// Asset names, paths within archives and host mismatches are all derived from it, so
+    /// that they agree on the architecture.
+    const ARCHITECTURES: &'static [(Architecture, &'static str)] = &[
+        (Architecture::Aarch64, "aarch64"),
+        // typos-lsp publishes no 32-bit builds so far, so a release is only usable on
+        // 32-bit hosts if it has an i686 asset (see [`messages::unsupported_x86`]).
+        #[cfg(not(feature = "x86-as-x86_64"))]
+        (Architecture::X86, "i686"),
+        // The x86_64 builds only run on 32-bit hosts that are 64-bit capable, like
+        // 32-bit userlands on x86_64 kernels.
+        #[cfg(feature = "x86-as-x86_64")]
+        (Architecture::X86, "x86_64"),
+        (Architecture::X8664, "x86_64"),
+    ];
+
+    /// The name of `architecture` in the target triples of the releases.
+    fn release_architecture(architecture: &Architecture) -> Result<&'static str, String> {
+        Self::find_architecture(Self::ARCHITECTURES, architecture)
+    }
+
+    fn find_architecture(
+        architectures: &[(Architecture, &'static str)],
+        architecture: &Architecture,
+    ) -> Result<&'static str, String> {
+        architectures
+            .iter()
+            .find(|(known, _)| known == architecture)
+            .map(|(_, name)| *name)
+            .ok_or_else(|| {
+                TyposExtensionError::UnsupportedPlatform {
+                    platform: messages::unsupported_architecture(architecture),
+                    reason: None,
+                }
+                .into()
+            })
+    }
+
+    /// The name of the archive found under the "Release" tabs of the GitHub repository,
+    /// depending on the version, platform and architecture.
+    ///
+    /// The `libc` is only relevant on Linux.
+    fn binary_release_name(
+        version: &str,
+        platform: &Os,
+        architecture: &Architecture,
+        libc: &LinuxLibc,
+    ) -> Result<String, String> {
+        Ok(Self::release_name_variants(version, platform, architecture, libc)?.remove(0))
+    }
+
+    /// The names the asset of `binary_release_name` may have been published under, the
+    /// current one first (see [`packaging::asset_names`]).
+    fn release_name_variants(
+        version: &str,
+        platform: &Os,
+        architecture: &Architecture,
+        libc: &LinuxLibc,
+    ) -> Result<Vec<String>, String> {
+        let (arch, os) = Self::release_target(platform, architecture, libc)?;
+        Ok(packaging::asset_names(
+            version,
+            &format!("{arch}-{os}"),
+            *platform == Os::Windows,
+        ))
+    }
+
+    /// The architecture and the rest of the target triple of the builds for `platform`.
+    fn release_target(
+        platform: &Os,
+        architecture: &Architecture,
+        libc: &LinuxLibc,
+    ) -> Result<(&'static str, &'static str), String> {
+        let os = match platform {
+            zed::Os::Mac => "apple-darwin",
+            zed::Os::Linux => match libc {
+                LinuxLibc::Gnu => "unknown-linux-gnu",
+                LinuxLibc::Musl => "unknown-linux-musl",
+            },
+            zed::Os::Windows => "pc-windows-msvc",
+        };
+        Ok((Self::release_architecture(architecture)?, os))
+    }
+
+    /// The names of the assets of the release `tag` that `request` would install, in
+    /// order of preference.
+    fn expected_asset_names(request: &InstallRequest, tag: &str) -> Result<Vec<String>, String> {
+        if let Some(lockfile) = &request.lockfile {
+            return Ok(vec![lockfile.asset.clone()]);
+        }
+        if let Some(template) = &request.asset_name {
+            return Ok(vec![Self::expand_asset_name(template, tag)]);
+        }
+        let libcs = match request.platform {
+            Os::Linux => request.asset_preference.as_slice(),
+            _ => &[LinuxLibc::Gnu],
+        };
+        libcs
+            .iter()
+            .map(|libc| {
+                Self::binary_release_name(tag, &request.platform, &request.architecture, libc)
+            })
+            .collect()
+    }
+
+    /// Where the binary of a release lies in its version directory, wherever its archive
+    /// holds it (see [`archive_layout::settle`]).
+    fn binary_path_within_archive(
+        platform: &Os,
+        architecture: &Architecture,
+    ) -> Result<String, String> {
+        // Even where it is not part of the path, as there is no archive to find it in.
+        let arch = Self::release_architecture(architecture)?;
+        let path = match platform {
+            zed::Os::Windows => Path::new("target")
+                .join(format!("{arch}-pc-windows-msvc"))
+                .join("release")
+                .join("typos-lsp.exe")
+                .as_path()
+                .to_owned(),
+            _ => Path::new("typos-lsp").to_owned(),
+        };
+        Ok(path
+            .to_str()
+            .expect("Could not convert binary path to str")
+            .to_string())
+    }
+
+    /// The newest installed release `request` can use, with its tag and binary, for
+    /// deferred updates. Pre-releases are skipped unless `request` accepts them. The
+    /// installations are the ones of the `warmup` scan when given.
+    fn newest_installation(
+        fs: &dyn Fs,
+        warmup: Option<&Warmup>,
+        request: &InstallRequest,
+    ) -> Option<(Version, String, String)> {
+        let mut versions: Vec<Version> = match warmup {
+            Some(warmup) => warmup.versions().cloned().collect(),
+            None => fs
+                .read_dir(Path::new("."))
+                .unwrap_or_default()
+                .iter()
+                .filter_map(|name| Version::from_dir_name(name))
+                .collect(),
+        };
+        versions.retain(|version| request.accepts(version));
+        versions.sort();
+        versions.into_iter().rev().find_map(|version| {
+            let (tag, binary) = Self::installation(fs, warmup, request, &version)?;
+            Some((version, tag, binary))
+        })
+    }
+
+    /// The release tag and binary of the installation of `version`, if `request` can use
+    /// it, as found by the `warmup` scan when given, which only knows intact binaries.
+    fn installation(
+        fs: &dyn Fs,
+        warmup: Option<&Warmup>,
+        request: &InstallRequest,
+        version: &Version,
+    ) -> Option<(String, String)> {
+        let (manifest, binary) = match warmup {
+            Some(warmup) => {
+                let installation = warmup
+                    .installation(version)
+                    .filter(|installation| installation.intact)?;
+                (installation.manifest.clone(), installation.binary.clone())
+            }
+            None => {
+                let dir = version.dir_name();
+                let manifest = Manifest::read(fs, Path::new(&dir))?;
+                let binary = Path::new(&dir)
+                    .join(
+                        Self::binary_path_within_archive(&request.platform, &request.architecture)
+                            .ok()?,
+                    )
+                    .to_str()
+                    .expect("Could not convert binary path to str")
+                    .to_string();
+                (manifest, binary)
+            }
+        };
+        // Another Zed process may have removed it since the scan.
+        if !fs.is_file(Path::new(&binary)) {
+            return None;
+        }
+        let variant_preferred = match &manifest.variant {
+            Some(variant) => request
+                .asset_preference
+                .iter()
+                .any(|libc| libc.name() == variant),
+            None => true,
+        };
+        let asset_named = request.asset_name.as_ref().map_or(true, |template| {
+            Self::expand_asset_name(template, &manifest.version) == manifest.asset
+        });
+        // Unless copied from another machine.
+        let platform_matches = manifest.platform.as_ref().map_or(true, |installed| {
+            *installed == manifest::platform(request.platform, request.architecture)
+        });
+        let usable = variant_preferred
+            && asset_named
+            && platform_matches
+            && !start_failures::set_aside(&manifest);
+        usable.then_some((manifest.version, binary))
+    }
+
+    /// The version of a release tag, which names its directory in the work directory.
+    fn parse_version(tag: &str) -> Result<Version, String> {
+        Version::parse(tag).ok_or_else(|| messages::unsupported_release_tag(tag))
+    }
+
+    /// Remove every typos-lsp version directories within `fs`, the Zed extension directory,
+    /// except for the version specified as [`version_to_keep`], the newest others up to
+    /// `keep_versions` installations in all, and the ones `in_use`, and the staging
+    /// directories of interrupted installations. Entries not named after a version, like
+    /// the state of the extension, are left alone.
+    ///
+    /// Returns the directories that were kept because they are in use.
+    fn clean_other_installations(
+        fs: &dyn Fs,
+        version_to_keep: &Version,
+        keep_versions: usize,
+        in_use: &BTreeSet<Version>,
+    ) -> Result<Vec<String>, String> {
+        let (removed, deferred) =
+            Self::other_installations(fs, version_to_keep, keep_versions, in_use)?;
+        for name in removed {
+            Self::remove_entry(fs, Path::new(&name));
+        }
+        Ok(deferred)
+    }
+
+    /// Remove the incomplete installation in `dir`, unless `cleanup` is off.
+    fn discard(fs: &dyn Fs, dir: &str, cleanup: bool) {
+        if cleanup {
+            fs.remove_dir_all(Path::new(dir)).ok();
+        } else {
+            Self::skip_cleanup();
+        }
+    }
+
+    /// Remove `path`, whether a directory or a file, e.g. left by an interrupted
+    /// download.
+    fn remove_entry(fs: &dyn Fs, path: &Path) {
+        if fs.is_file(path) {
+            fs.remove_file(path).ok();
+        } else {
+            fs.remove_dir_all(path).ok();
+        }
+    }
+
+    /// The entries [`Self::clean_other_installations`] removes, and the directories it
+    /// keeps because they are in use.
+    fn other_installations(
+        fs: &dyn Fs,
+        version_to_keep: &Version,
+        keep_versions: usize,
+        in_use: &BTreeSet<Version>,
+    ) -> Result<(Vec<String>, Vec<String>), String> {
+        let names = fs
+            .read_dir(Path::new("."))
+            .map_err(|e| TyposExtensionError::io("list the extension work directory", e))?;
+        let (mut removed, mut deferred) = (Vec::new(), Vec::new());
+        // Kept until a newer installation initialized, see [`known_good`].
+        let known_good = known_good::read(fs);
+        let retained = Self::retained_versions(&names, version_to_keep, keep_versions);
+        for name in names {
+            // Staging directories are only left by interrupted installations, as the
+            // lock is held until they are renamed.
+            if Version::from_staging_dir_name(&name).is_some() {
+                removed.push(name);
+                continue;
+            }
+            match Version::from_dir_name(&name) {
+                None => {}
+                Some(version) if &version == version_to_keep => {}
+                Some(version) if retained.contains(&version) => {}
+                Some(version) if Some(&version) == known_good.as_ref() => {}
+                Some(version) if in_use.contains(&version) => deferred.push(name),
+                Some(_) => removed.push(name),
+            }
+        }
+        // Listed in no particular order by the filesystem.
+        removed.sort();
+        deferred.sort();
+        Ok((removed, deferred))
+    }
+
+    /// The versions among the installations `names` the cleanup keeps besides
+    /// `version_to_keep`: the newest others, up to `keep_versions` installations in all.
+    /// Versions are compared as such, so that 0.1.10 is newer than 0.1.9.
+    fn retained_versions(
+        names: &[String],
+        version_to_keep: &Version,
+        keep_versions: usize,
+    ) -> BTreeSet<Version> {
+        let others: BTreeSet<Version> = names
+            .iter()
+            .filter_map(|name| Version::from_dir_name(name))
+            .filter(|version| version != version_to_keep)
+            .collect();
+        others
+            .into_iter()
+            .rev()
+            .take(keep_versions.saturating_sub(1))
+            .collect()
+    }
+
+    /// Record directories to remove once no server of this session can use them.
+    fn defer_cleanup(fs: &dyn Fs, directories: &[String]) {
+        if directories.is_empty() {
+            return;
+        }
+        let path = Path::new(Self::DEFERRED_CLEANUP_FILE);
+        let mut pending: BTreeSet<String> = fs
+            .read_to_string(path)
+            .unwrap_or_default()
+            .lines()
+            .map(str::to_string)
+            .collect();
+        pending.extend(directories.iter().cloned());
+        let content: String = pending.iter().flat_map(|name| [name, "\n"]).collect();
+        if let Err(e) = fs.write(path, content.as_bytes()) {
+            log::warn(messages::deferred_cleanup_record_failed(e));
+        }
+    }
+
+    /// Remove the directories whose cleanup was deferred by a previous session,
+    /// returning them.
+    fn run_deferred_cleanup(fs: &dyn Fs) -> Vec<String> {
+        let path = Path::new(Self::DEFERRED_CLEANUP_FILE);
+        let Ok(pending) = fs.read_to_string(path) else {
+            return Vec::new();
+        };
+        let removed: Vec<String> = pending
+            .lines()
+            .filter(|name| Version::from_dir_name(name).is_some())
+            .map(str::to_string)
+            .collect();
+        for name in &removed {
+            Self::remove_entry(fs, Path::new(name));
+        }
+        fs.remove_file(path).ok();
+        removed
+    }
+
+    /// Count a failed start when the installation last started for the server did not
+    /// initialize, then switch to the musl builds or set the installation aside (see
+    /// [`start_failures`]). The musl builds are not switched to when the builds to
+    /// install are `explicit`, and the glibc build is only removed with `cleanup`.
+    fn check_start_probe(
+        &mut self,
+        server_id: &str,
+        root_path: &str,
+        explicit: bool,
+        cleanup: bool,
+    ) {
+        let fs = self.fs.as_ref();
+        let Some(tag) = libc_fallback::pending_probe(fs, server_id, root_path) else {
+            return;
+        };
+        libc_fallback::end_probe(fs, server_id, root_path);
+        // The installation may be set aside or removed below.
+        self.warmup = None;
+        let Some(version) = Version::parse(&tag) else {
+            return;
+        };
+        let dir = version.dir_name();
+        let Some(manifest) = Manifest::read(fs, Path::new(&dir)) else {
+            return;
+        };
+        let failures = match Manifest::record_start(fs, Path::new(&dir), false) {
+            Ok(failures) => failures,
+            Err(e) => {
+                log::warn(messages::start_record_failed(&dir, e));
+                return;
+            }
+        };
+        let musl_allowed = manifest.variant.as_deref() == Some(LinuxLibc::Gnu.name())
+            && !explicit
+            && libc_fallback::read(fs).is_none();
+        let strategy = start_failures::decide(failures, musl_allowed);
+        match strategy {
+            Strategy::Retry => {
+                log::warn(start_failures::notice(&tag, failures, strategy));
+                return;
+            }
+            Strategy::AlternateVariant => {
+                log::warn(libc_fallback::notice(&tag));
+                libc_fallback::record(fs, &tag);
+                Self::discard(fs, &dir, cleanup);
+                self.in_use_versions.remove(&version);
+            }
+            Strategy::PreviousVersion => {
+                log::warn(start_failures::notice(&tag, failures, strategy));
+            }
+        }
+        for state in self.servers.values_mut() {
+            if state.cached_binary_version.as_deref() == Some(tag.as_str()) {
+                state.cached_binary_path = None;
+                state.cached_binary_version = None;
+            }
+        }
+    }
+
+    /// Probe the start of `binary` when it is installed, until the server initializes.
+    fn start_probe(&self, server_id: &str, root_path: &str, binary: &TyposBinary) {
+        let fs = self.fs.as_ref();
+        let installed = binary
+            .provenance
+            .is_installed()
+            .then(|| binary.version.as_deref().and_then(Version::parse))
+            .flatten()
+            .and_then(|version| Manifest::read(fs, Path::new(&version.dir_name())));
+        match installed {
+            Some(manifest) => {
+                Self::can_run(
+                    self.runner.as_ref(),
+                    start_failures::FEATURE,
+                    start_failures::WITHOUT_PROCESSES,
+                );
+                libc_fallback::start_probe(fs, server_id, root_path, &manifest.version)
+            }
+            None => libc_fallback::end_probe(fs, server_id, root_path),
+        }
+    }
+
+    /// End the probe of the server, which initialized, reset the failed starts of its
+    /// installation, and record it as the last known good one.
+    fn end_start_probe(&self, server_id: &str, root_path: &str) {
+        let fs = self.fs.as_ref();
+        let Some(tag) = libc_fallback::pending_probe(fs, server_id, root_path) else {
+            return;
+        };
+        libc_fallback::end_probe(fs, server_id, root_path);
+        if let Some(version) = Version::parse(&tag) {
+            Manifest::record_start(fs, Path::new(&version.dir_name()), true).ok();
+            known_good::record(fs, &tag);
+        }
+    }
+
+    /// Record that a server starts from the installation of `version`, for the cache
+    /// report.
+    fn record_use(&self, version: Option<&str>) {
+        let Some(version) = version.and_then(Version::parse) else {
+            return;
+        };
+        let dir = version.dir_name();
+        if let Err(e) = Manifest::touch(self.fs.as_ref(), Path::new(&dir), (self.clock)()) {
+            log::warn(messages::use_record_failed(&dir, e));
+        }
+    }
+
+    fn cache_report(&self) -> CacheReport {
+        let fs = self.fs.as_ref();
+        let deferred: Vec<String> = fs
+            .read_to_string(Path::new(Self::DEFERRED_CLEANUP_FILE))
+            .unwrap_or_default()
+            .lines()
+            .filter(|name| Version::from_dir_name(name).is_some())
+            .map(str::to_string)
+            .collect();
+        CacheReport::collect(
+            fs,
+            &self.in_use_versions,
+            Self::STATE_ENTRIES,
+            &deferred,
+            (self.clock)(),
+        )
+    }
+}
+
+// The entry points of `zed::Extension`, which prefixes their errors with the version of
+// the extension.
+impl TyposExtension {
+    fn server_command(
+        &mut self,
+        language_server_id: &LanguageServerId,
+        worktree: &Worktree,
+    ) -> Result<Command, TyposExtensionError> {
+        let root_path = paths::canonical_root(&worktree.root_path());
+        let settings = WorktreeSettings::for_worktree(language_server_id, worktree);
+        // Before the `$PATH` and the releases are looked at, so that a disabled worktree
+        // costs nothing.
+        // Zed shows the error on the server of the worktree, the only way the extension
+        // API has to not start one.
+        if !settings.extension.enabled {
+            return Err(messages::disabled(&root_path).into());
+        }
+        let extension_settings = settings.extension;
+        if extension_settings.remove_worktree_artifacts {
+            return Err(self
+                .clean_up(&root_path, |path| worktree.read_text_file(path).ok())
+                .into());
+        }
+        self.tidy_work_dir(&extension_settings, zed::current_platform());
+        self.check_start_probe(
+            language_server_id.as_ref(),
+            &root_path,
+            extension_settings.asset_preference.is_some(),
+            extension_settings.cleanup != Cleanup::Off,
+        );
+        let typos_binary = self.language_server_binary(language_server_id, worktree)?;
+        self.start_probe(language_server_id.as_ref(), &root_path, &typos_binary);
+        let path = self.absolute_work_dir_path(&typos_binary.path);
+        let state = self.server_state(language_server_id.as_ref(), &root_path);
+        state.running = Some(BinaryMeta {
+            provenance: typos_binary.provenance,
+            path,
+            version: typos_binary.version.clone(),
+        });
+
+        let settings = WorktreeSettings::for_worktree(language_server_id, worktree);
+        self.server_state(language_server_id.as_ref(), &root_path)
+            .settings = Some(settings.clone());
+        let fingerprint = settings.fingerprint();
+        let source =
+            if self.record_resolution(language_server_id.as_ref(), &root_path, &fingerprint) {
+                ConfigSource::None
+            } else {
+                // Conflicting configuration settings fail the start rather than picking one.
+                Self::config_source(worktree, &settings)?
+            };
+        Self::log_word_overlaps(worktree, &settings, typos_binary.version.as_deref());
+        let spellcheckers = spellcheckers::detect(
+            |file| worktree.read_text_file(file).is_ok(),
+            |server| {
+                zed::settings::LspSettings::for_worktree(server, worktree).is_ok_and(|lsp| {
+                    lsp.binary.is_some()
+                        || lsp.settings.is_some()
+                        || lsp.initialization_options.is_some()
+                })
+            },
+        );
+        spellcheckers::advise(&root_path, &spellcheckers);
+        if settings.extension.export_lockfile && typos_binary.provenance.is_installed() {
+            if let Err(e) = self.export_lockfile(worktree, &settings, &typos_binary) {
+                log::warn(messages::lockfile_export_failed(e));
+            }
+        }
+        if typos_binary.provenance.is_installed() {
+            self.record_use(typos_binary.version.as_deref());
+        }
+        if settings.extension.cache_report {
+            log::info(self.cache_report());
+        }
+        self.write_effective_config(worktree, &settings);
+        if settings.extension.local_metrics {
+            log::once(
+                "metrics-summary",
+                log::Level::Info,
+                metrics::Summary::from_file(&self.work_dir),
+            );
+        }
+        if settings.extension.verbose_startup_log {
+            let options = self
+                .effective_configuration(language_server_id, worktree, &settings)?
+                .initialization_options();
+            log::info(StartupState {
+                extension_version: &build_info::describe(),
+                server_version: typos_binary.version.as_deref(),
+                provenance: typos_binary.provenance,
+                binary_path: &typos_binary.path,
+                config: options
+                    .get(config_channel::CONFIG_KEY)
+                    .and_then(|config| config.as_str()),
+                channel: options
+                    .get(config_channel::CONFIG_KEY)
+                    .map(|_| config_channel::CONFIG_KEY),
+                extra_words: Self::word_fragments(worktree, &settings).words.len(),
+                severity: options
+                    .get("diagnosticSeverity")
+                    .and_then(|severity| severity.as_str())
+                    .unwrap_or(settings::DEFAULT_SEVERITY),
+            });
+        }
+
+        let command = Self::command(
+            &settings,
+            typos_binary,
+            &self.work_dir,
+            &source,
+            &root_path,
+            zed::current_platform().0,
+            || {
+                if capabilities::degrade(Capability::ShellEnv) {
+                    worktree.shell_env()
+                } else {
+                    Vec::new()
+                }
+            },
+        )?;
+        Ok(command)
+    }
+
+    /// The command starting `binary`, installed into `work_dir` unless found elsewhere,
+    /// for the worktree at `root_path` on `platform`, with the environment of the settings
+    /// over the `shell_env` of the worktree, and its logs as `log_level` and `log_file`
+    /// set them.
+    fn command(
+        settings: &WorktreeSettings,
+        binary: TyposBinary,
+        work_dir: &Path,
+        source: &ConfigSource,
+        root_path: &str,
+        platform: Os,
+        shell_env: impl FnOnce() -> Vec<(String, String)>,
+    ) -> Result<Command> {
+        let mut env = settings.extension.env.clone();
+        if let Some(level) = settings.log_level()? {
+            // Unless `env` sets a filter of its own.
+            let (name, filter) = server_logs::filter(level);
+            env.entry(name).or_insert(filter);
+        }
+        // Neither Zed nor the shell of `log_file` resolve the command from the work
+        // directory.
+        let mut command = Some(&binary.path)
+            .filter(|_| binary.provenance.is_installed())
+            .and_then(|path| work_dir::absolute(work_dir, path))
+            .unwrap_or_else(|| binary.path.clone());
+        let mut args = Self::arguments(settings, &binary, source, root_path);
+        if let Some(log_file) = &settings.extension.log_file {
+            match server_logs::redirect(
+                command.clone(),
+                args.clone(),
+                log_file,
+                root_path,
+                platform,
+            ) {
+                Some(redirected) => (command, args) = redirected,
+                None => {
+                    log::once(
+                        "log-file-unsupported",
+                        log::Level::Warn,
+                        server_logs::UNSUPPORTED,
+                    );
+                }
+            }
+        }
+        Ok(zed::Command {
+            command,
+            args,
+            env: command_env::merge(shell_env, &env),
+        })
+    }
+
+    fn server_initialization_options(
+        &mut self,
+        server_id: &LanguageServerId,
+        worktree: &zed_extension_api::Worktree,
+    ) -> Result<Option<zed_extension_api::serde_json::Value>, TyposExtensionError> {
+        let settings = WorktreeSettings::for_worktree(server_id, worktree);
+        let configuration = self.effective_configuration(server_id, worktree, &settings)?;
+        Ok(Some(configuration.initialization_options()))
+    }
+
+    fn server_workspace_configuration(
+        &mut self,
+        server_id: &LanguageServerId,
+        worktree: &zed_extension_api::Worktree,
+    ) -> Result<Option<zed_extension_api::serde_json::Value>, TyposExtensionError> {
+        let settings = WorktreeSettings::for_worktree(server_id, worktree);
+        // Called again by Zed whenever the settings change.
+        let root_path = paths::canonical_root(&worktree.root_path());
+        // Only asked for once the server initialized.
+        self.end_start_probe(server_id.as_ref(), &root_path);
+        let oversized = settings.oversized();
+        let fingerprint = (!oversized.is_empty()).then(|| {
+            log::once(
+                format!("oversized-settings:{root_path}"),
+                log::Level::Warn,
+                settings_size::warning(&oversized),
+            );
+            settings.fingerprint()
+        });
+        if let Some(fingerprint) = &fingerprint {
+            let state = self.server_state(server_id.as_ref(), &root_path);
+            if let Some(cached) = state.workspace_configuration.get(fingerprint).cloned() {
+                return Ok(Some(
+                    self.expose_meta(server_id, &root_path, &settings, cached),
+                ));
+            }
+        }
+        let previous = self
+            .server_state(server_id.as_ref(), &root_path)
+            .settings
+            .replace(settings.clone());
+        if let Some(previous) = previous {
+            for (level, message) in reload::messages(&settings.changes_since(&previous)) {
+                log::log(level, message);
+            }
+        }
+        self.write_effective_config(worktree, &settings);
+        let workspace_settings = self
+            .effective_configuration(server_id, worktree, &settings)?
+            .workspace_configuration();
+        if let Some(fingerprint) = fingerprint {
+            self.server_state(server_id.as_ref(), &root_path)
+                .workspace_configuration
+                .set(fingerprint, workspace_settings.clone());
+        }
+        Ok(Some(self.expose_meta(
+            server_id,
+            &root_path,
+            &settings,
+            workspace_settings,
+        )))
+    }
+}
+
+impl zed::Extension for TyposExtension {
+    fn new() -> Self {
+        let work_dir = work_dir::resolve();
+        let mut extension = Self::with_host(
+            Box::new(host::Zed),
+            Box::new(host::Zed),
+            Box::new(StdFs::new(&work_dir)),
+            work_dir,
+        );
+        extension.warm_up(zed::current_platform());
+        extension
+    }
+
+    fn language_server_command(
+        &mut self,
+        language_server_id: &LanguageServerId,
+        worktree: &Worktree,
+    ) -> Result<Command> {
+        self.server_command(language_server_id, worktree)
+            .map_err(messages::extension_error)
+    }
+
+    fn language_server_initialization_options(
+        &mut self,
+        server_id: &LanguageServerId,
+        worktree: &zed_extension_api::Worktree,
+    ) -> Result<Option<zed_extension_api::serde_json::Value>> {
+        self.server_initialization_options(server_id, worktree)
+            .map_err(messages::extension_error)
+    }
+
+    fn language_server_workspace_configuration(
+        &mut self,
+        server_id: &LanguageServerId,
+        worktree: &zed_extension_api::Worktree,
+    ) -> Result<Option<zed_extension_api::serde_json::Value>> {
+        self.server_workspace_configuration(server_id, worktree)
+            .map_err(messages::extension_error)
+    }
+}
+
+zed::register_extension!(TyposExtension);
 
 #[cfg(test)]
 mod tests {
-    use zed_extension_api::{Architecture, Os};
+    use zed_extension_api::{
+        serde_json::json, Architecture, GithubRelease, GithubReleaseAsset, Os,
+    };
+
+    use std::{
+        cell::RefCell,
+        collections::BTreeSet,
+        fs,
+        path::Path,
+        sync::{
+            atomic::{AtomicU64, Ordering},
+            Arc, Mutex,
+        },
+        time::Duration,
+    };
+
+    use crate::{
+        archive_fixtures, binary_identity, build_info,
+        config_source::ConfigSource,
+        context::{Container, ExecutionContext},
+        crash_loop,
+        error::TyposExtensionError,
+        hashing,
+        host::{self, Fs, StdFs},
+        libc_fallback,
+        lockfile::{self, Lockfile},
+        manifest::Manifest,
+        messages,
+        metrics::Metrics,
+        mirror::Mirror,
+        rate_limit, release_checksums,
+        report::{Provenance, Step},
+        settings::{Cleanup, ExtensionSettings, WorktreeSettings},
+        start_failures,
+        status::Status,
+        test_support::{
+            FakeDownloader, FakeHttp, FakeReleases, FakeRunner, ReadOnlyFs, SpaceFs, TempDir,
+        },
+        typos_config,
+        update_timing::{self, LastCheck, PendingUpdate, UpdateTiming},
+        version::Version,
+        work_lock, worktree_files,
+        write_consent::Consent,
+        InstallRequest, LinuxLibc, TyposBinary, TyposExtension,
+    };
+
+    #[test]
+    fn release_name() {
+        assert_eq!(
+            TyposExtension::binary_release_name(
+                "v0.1.23",
+                &Os::Mac,
+                &Architecture::Aarch64,
+                &LinuxLibc::Gnu
+            ),
+            Ok("typos-lsp-v0.1.23-aarch64-apple-darwin.tar.gz".to_string())
+        );
+        assert_eq!(
+            TyposExtension::binary_release_name(
+                "v0.1.23",
+                &Os::Windows,
+                &Architecture::Aarch64,
+                &LinuxLibc::Gnu
+            ),
+            Ok("typos-lsp-v0.1.23-aarch64-pc-windows-msvc.zip".to_string())
+        );
+        assert_eq!(
+            TyposExtension::binary_release_name(
+                "v0.1.23",
+                &Os::Linux,
+                &Architecture::Aarch64,
+                &LinuxLibc::Gnu
+            ),
+            Ok("typos-lsp-v0.1.23-aarch64-unknown-linux-gnu.tar.gz".to_string())
+        );
+        assert_eq!(
+            TyposExtension::binary_release_name(
+                "v0.1.23",
+                &Os::Mac,
+                &Architecture::X8664,
+                &LinuxLibc::Gnu
+            ),
+            Ok("typos-lsp-v0.1.23-x86_64-apple-darwin.tar.gz".to_string())
+        );
+        assert_eq!(
+            TyposExtension::binary_release_name(
+                "v0.1.23",
+                &Os::Windows,
+                &Architecture::X8664,
+                &LinuxLibc::Gnu
+            ),
+            Ok("typos-lsp-v0.1.23-x86_64-pc-windows-msvc.zip".to_string())
+        );
+        assert_eq!(
+            TyposExtension::binary_release_name(
+                "v0.1.23",
+                &Os::Linux,
+                &Architecture::X8664,
+                &LinuxLibc::Gnu
+            ),
+            Ok("typos-lsp-v0.1.23-x86_64-unknown-linux-gnu.tar.gz".to_string())
+        );
+    }
+
+    #[test]
+    fn architectures() {
+        for (architecture, name) in [
+            (Architecture::Aarch64, Ok("aarch64")),
+            (
+                Architecture::X86,
+                if cfg!(feature = "x86-as-x86_64") {
+                    Ok("x86_64")
+                } else {
+                    Ok("i686")
+                },
+            ),
+            (Architecture::X8664, Ok("x86_64")),
+        ] {
+            assert_eq!(TyposExtension::release_architecture(&architecture), name);
+        }
+        // Like a variant added by a newer extension API.
+        let known = [(Architecture::X8664, "x86_64")];
+        assert_eq!(
+            TyposExtension::find_architecture(&known, &Architecture::Aarch64),
+            Err("the architecture Architecture::Aarch64 is not supported by typos-lsp releases. Set \
+                 `binary.path` to a typos-lsp built for this host"
+                .to_string())
+        );
+    }
+
+    #[test]
+    fn architecture_derivations_agree() {
+        let version = "v0.1.23".to_string();
+        for os in [Os::Mac, Os::Linux, Os::Windows] {
+            for architecture in [
+                Architecture::Aarch64,
+                Architecture::X86,
+                Architecture::X8664,
+            ] {
+                let arch = TyposExtension::release_architecture(&architecture);
+                let name = TyposExtension::binary_release_name(
+                    &version,
+                    &os,
+                    &architecture,
+                    &LinuxLibc::Gnu,
+                );
+                let path = TyposExtension::binary_path_within_archive(&os, &architecture);
+                let Ok(arch) = arch else {
+                    assert_eq!(
+                        name,
+                        arch.clone().map(str::to_string),
+                        "{os:?} {architecture:?}"
+                    );
+                    assert_eq!(path, arch.map(str::to_string), "{os:?} {architecture:?}");
+                    continue;
+                };
+                let target = TyposExtension::asset_target(&name.unwrap(), &version);
+                assert!(target.starts_with(&format!("{arch}-")), "{target}");
+                if os == Os::Windows {
+                    assert!(path.unwrap().contains(&format!("/{target}/")), "{target}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn older_release_names() {
+        let name = |version: &str, os| {
+            TyposExtension::binary_release_name(version, &os, &Architecture::X8664, &LinuxLibc::Gnu)
+        };
+        assert_eq!(
+            name("v0.1.5", Os::Linux),
+            Ok("typos-lsp-v0.1.5-x86_64-unknown-linux-gnu.tar.gz".to_string())
+        );
+        // The names a release was actually published under are read from its assets.
+        for (os, published) in [
+            (Os::Linux, "typos-lsp-x86_64-unknown-linux-gnu.tar.gz"),
+            (Os::Windows, "typos-lsp-x86_64-pc-windows-msvc.zip"),
+        ] {
+            let assets = [asset(published)];
+            let selected = TyposExtension::select_asset(
+                &assets,
+                "v0.1.5",
+                &os,
+                &Architecture::X8664,
+                &[LinuxLibc::Gnu],
+            );
+            assert_eq!(
+                selected.map(|(asset, _)| asset.name.clone()),
+                Ok(published.to_string())
+            );
+        }
+        assert_eq!(
+            TyposExtension::asset_target("typos-lsp-x86_64-pc-windows-msvc.zip", "v0.1.5"),
+            "x86_64-pc-windows-msvc"
+        );
+    }
+
+    #[test]
+    fn release_without_builds_predates_packaging() {
+        let temp = TempDir::new("predates-packaging");
+        let (releases, downloader) = (FakeReleases::default(), FakeDownloader::new(temp.path()));
+        releases.publish_assets("v0.1.2", &["typos-lsp", "typos-vscode-0.1.2.vsix"]);
+        let mut pinned = request();
+        pinned.pinned_version = Some("v0.1.2".to_string());
+        let mut extension = extension(&temp, &releases, &downloader);
+        let (binary, _) = install(&mut extension, "typos", &pinned);
+        assert_eq!(
+            binary.map(|binary| binary.path),
+            Err(messages::for_worktree(
+                ROOT,
+                "version v0.1.2 predates supported packaging"
+            ))
+        );
+        assert!(downloader.downloads().is_empty());
+    }
+
+    #[test]
+    fn release_name_musl() {
+        assert_eq!(
+            TyposExtension::binary_release_name(
+                "v0.1.23",
+                &Os::Linux,
+                &Architecture::X8664,
+                &LinuxLibc::Musl
+            ),
+            Ok("typos-lsp-v0.1.23-x86_64-unknown-linux-musl.tar.gz".to_string())
+        );
+        assert_eq!(
+            TyposExtension::binary_release_name(
+                "v0.1.23",
+                &Os::Linux,
+                &Architecture::Aarch64,
+                &LinuxLibc::Musl
+            ),
+            Ok("typos-lsp-v0.1.23-aarch64-unknown-linux-musl.tar.gz".to_string())
+        );
+        assert_eq!(
+            TyposExtension::binary_release_name(
+                "v0.1.23",
+                &Os::Mac,
+                &Architecture::Aarch64,
+                &LinuxLibc::Musl
+            ),
+            Ok("typos-lsp-v0.1.23-aarch64-apple-darwin.tar.gz".to_string())
+        );
+    }
+
+    #[test]
+    fn containers_prefer_musl() {
+        assert_eq!(
+            TyposExtension::libc_preference(Some(Container::Devcontainer), false),
+            [LinuxLibc::Musl, LinuxLibc::Gnu]
+        );
+        assert_eq!(
+            TyposExtension::libc_preference(None, false),
+            [LinuxLibc::Gnu, LinuxLibc::Musl]
+        );
+        assert_eq!(
+            TyposExtension::libc_preference(None, true),
+            [LinuxLibc::Musl, LinuxLibc::Gnu]
+        );
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn config_path_resolution() {
+        let resolve = |mut settings, home| {
+            TyposExtension::resolve_config_path(
+                &mut settings,
+                "/workspaces/project",
+                home,
+                |path| path == "ci/typos.toml",
+            )
+            .map(|()| settings)
+        };
+        assert_eq!(
+            resolve(json!({"config": "ci/typos.toml", "logLevel": "info"}), None),
+            Ok(json!({"config": "/workspaces/project/ci/typos.toml", "logLevel": "info"}))
+        );
+        assert_eq!(
+            resolve(json!({"config": "/etc/typos.toml"}), None),
+            Ok(json!({"config": "/etc/typos.toml"}))
+        );
+        assert_eq!(
+            resolve(json!({"config": "~/typos.toml"}), Some("/home/me")),
+            Ok(json!({"config": "/home/me/typos.toml"}))
+        );
+        // Only a leading `~` directory is the home.
+        assert_eq!(
+            resolve(json!({"config": "~typos.toml"}), Some("/home/me")),
+            Err(messages::missing_config(
+                "~typos.toml",
+                &"/workspaces/project/~typos.toml"
+            ))
+        );
+        assert_eq!(
+            resolve(json!({"config": "~/typos.toml"}), None),
+            Err(messages::config_home_unknown("~/typos.toml"))
+        );
+        assert_eq!(
+            resolve(json!({"config": "ci/missing.toml"}), None),
+            Err(
+                "the `config` initialization option names ci/missing.toml, which does not \
+                 exist at /workspaces/project/ci/missing.toml: typos-lsp would silently use \
+                 its defaults. Set a path relative to the worktree root, or an absolute one"
+                    .to_string()
+            )
+        );
+        assert_eq!(resolve(json!({}), None), Ok(json!({})));
+        assert_eq!(
+            resolve(json!({"config": ""}), None),
+            Ok(json!({"config": ""}))
+        );
+    }
+
+    fn asset(name: &str) -> GithubReleaseAsset {
+        GithubReleaseAsset {
+            name: name.to_string(),
+            download_url: format!("https://example.com/{name}"),
+        }
+    }
+
+    #[test]
+    fn asset_preference() {
+        let assets = [
+            asset("typos-lsp-v0.1.23-x86_64-unknown-linux-gnu.tar.gz"),
+            asset("typos-lsp-v0.1.23-x86_64-unknown-linux-musl.tar.gz"),
+            asset("typos-lsp-v0.1.23-aarch64-unknown-linux-gnu.tar.gz"),
+        ];
+        let select = |architecture, preference: &[LinuxLibc]| {
+            TyposExtension::select_asset(&assets, "v0.1.23", &Os::Linux, &architecture, preference)
+                .map(|(asset, libc)| (asset.name.clone(), libc))
+        };
+
+        assert_eq!(
+            select(Architecture::X8664, &[LinuxLibc::Musl, LinuxLibc::Gnu]),
+            Ok((
+                "typos-lsp-v0.1.23-x86_64-unknown-linux-musl.tar.gz".to_string(),
+                LinuxLibc::Musl
+            ))
+        );
+        assert_eq!(
+            select(Architecture::X8664, &[LinuxLibc::Gnu, LinuxLibc::Musl]),
+            Ok((
+                "typos-lsp-v0.1.23-x86_64-unknown-linux-gnu.tar.gz".to_string(),
+                LinuxLibc::Gnu
+            ))
+        );
+        // No musl build for aarch64, falls back down the list.
+        assert_eq!(
+            select(Architecture::Aarch64, &[LinuxLibc::Musl, LinuxLibc::Gnu]),
+            Ok((
+                "typos-lsp-v0.1.23-aarch64-unknown-linux-gnu.tar.gz".to_string(),
+                LinuxLibc::Gnu
+            ))
+        );
+        let error = select(Architecture::Aarch64, &[LinuxLibc::Musl]).unwrap_err();
+        assert_eq!(
+            error,
+            "no asset found matching [\"typos-lsp-v0.1.23-aarch64-unknown-linux-musl.tar.gz\"] \
+             in typos-lsp v0.1.23, which has [\"typos-lsp-v0.1.23-x86_64-unknown-linux-gnu.tar.gz\", \
+             \"typos-lsp-v0.1.23-x86_64-unknown-linux-musl.tar.gz\", \
+             \"typos-lsp-v0.1.23-aarch64-unknown-linux-gnu.tar.gz\"]: set `binary.path` to a \
+             typos-lsp built for this platform, or report these names at \
+             https://github.com/tekumara/typos-lsp/issues"
+        );
+    }
+
+    #[test]
+    fn historical_asset_names() {
+        // Release assets in each naming variant, with the one each platform gets.
+        let releases: &[(&str, &[&str], Os, Architecture, &str)] = &[
+            (
+                "v0.1.34",
+                &[
+                    "typos-lsp-v0.1.34-aarch64-apple-darwin.tar.gz",
+                    "typos-lsp-v0.1.34-x86_64-apple-darwin.tar.gz",
+                    "typos-lsp-v0.1.34-x86_64-pc-windows-msvc.zip",
+                    "typos-lsp-v0.1.34-x86_64-unknown-linux-gnu.tar.gz",
+                    "typos-lsp-v0.1.34-x86_64-unknown-linux-musl.tar.gz",
+                ],
+                Os::Linux,
+                Architecture::X8664,
+                "typos-lsp-v0.1.34-x86_64-unknown-linux-gnu.tar.gz",
+            ),
+            (
+                "v0.1.5",
+                &[
+                    "typos-lsp-aarch64-apple-darwin.tar.gz",
+                    "typos-lsp-x86_64-apple-darwin.tar.gz",
+                    "typos-lsp-x86_64-pc-windows-msvc.zip",
+                    "typos-lsp-x86_64-unknown-linux-gnu.tar.gz",
+                ],
+                Os::Windows,
+                Architecture::X8664,
+                "typos-lsp-x86_64-pc-windows-msvc.zip",
+            ),
+            // Without the leading `v` of the tag.
+            (
+                "v0.1.8",
+                &[
+                    "typos-lsp-0.1.8-aarch64-apple-darwin.tar.gz",
+                    "typos-lsp-0.1.8-x86_64-unknown-linux-gnu.tar.gz",
+                ],
+                Os::Mac,
+                Architecture::Aarch64,
+                "typos-lsp-0.1.8-aarch64-apple-darwin.tar.gz",
+            ),
+            // Zips only.
+            (
+                "v0.1.6",
+                &[
+                    "typos-lsp-x86_64-apple-darwin.zip",
+                    "typos-lsp-x86_64-unknown-linux-gnu.zip",
+                    "typos-lsp-x86_64-pc-windows-msvc.zip",
+                ],
+                Os::Linux,
+                Architecture::X8664,
+                "typos-lsp-x86_64-unknown-linux-gnu.zip",
+            ),
+            // `_` separators.
+            (
+                "v0.1.4",
+                &[
+                    "typos-lsp_x86_64-apple-darwin.tar.gz",
+                    "typos-lsp_x86_64-unknown-linux-gnu.tar.gz",
+                ],
+                Os::Mac,
+                Architecture::X8664,
+                "typos-lsp_x86_64-apple-darwin.tar.gz",
+            ),
+            // Matched by the target alone.
+            (
+                "v0.1.9",
+                &[
+                    "typos-lsp.v0.1.9.x86_64-unknown-linux-gnu.tgz",
+                    "typos-lsp.v0.1.9.x86_64-unknown-linux-gnu.tgz.sha256",
+                ],
+                Os::Linux,
+                Architecture::X8664,
+                "typos-lsp.v0.1.9.x86_64-unknown-linux-gnu.tgz",
+            ),
+        ];
+        for (tag, names, platform, architecture, expected) in releases {
+            let assets: Vec<_> = names.iter().map(|name| asset(name)).collect();
+            let selected = TyposExtension::select_asset(
+                &assets,
+                tag,
+                platform,
+                architecture,
+                &[LinuxLibc::Gnu, LinuxLibc::Musl],
+            );
+            assert_eq!(
+                selected.map(|(asset, _)| asset.name.as_str()),
+                Ok(*expected),
+                "{tag}"
+            );
+        }
+    }
+
+    #[test]
+    fn release_without_assets() {
+        let release = |assets| GithubRelease {
+            version: "v0.1.23".to_string(),
+            assets,
+        };
+        assert_eq!(
+            TyposExtension::ensure_uploaded_assets(&release(vec![])),
+            Err(
+                "release v0.1.23 has no uploaded assets yet; retrying later or pinning the \
+                 previous version will help"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            TyposExtension::ensure_uploaded_assets(&release(vec![asset("typos-lsp.zip")])),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn asset_target() {
+        assert_eq!(
+            TyposExtension::asset_target(
+                "typos-lsp-v0.1.23-x86_64-unknown-linux-musl.tar.gz",
+                "v0.1.23"
+            ),
+            "x86_64-unknown-linux-musl"
+        );
+        assert_eq!(
+            TyposExtension::asset_target(
+                "typos-lsp-v0.1.23-aarch64-pc-windows-msvc.zip",
+                "v0.1.23"
+            ),
+            "aarch64-pc-windows-msvc"
+        );
+    }
+
+    #[test]
+    fn asset_name_templates() {
+        assert_eq!(
+            TyposExtension::expand_asset_name(
+                "typos-lsp-{version}-x86_64-unknown-linux-musl.tar.gz",
+                "v0.1.23"
+            ),
+            "typos-lsp-v0.1.23-x86_64-unknown-linux-musl.tar.gz"
+        );
+        assert_eq!(
+            TyposExtension::expand_asset_name("typos-lsp-local.tar.gz", "v0.1.23"),
+            "typos-lsp-local.tar.gz"
+        );
+
+        let assets = [
+            asset("typos-lsp-v0.1.23-x86_64-unknown-linux-gnu.tar.gz"),
+            asset("typos-lsp-v0.1.23-x86_64-unknown-linux-musl.tar.gz"),
+        ];
+        let named = |template| {
+            TyposExtension::named_asset(template, &assets, "v0.1.23", &[LinuxLibc::Gnu])
+                .map(|(asset, libc)| (asset.name.clone(), libc))
+        };
+        assert_eq!(
+            named("typos-lsp-{version}-x86_64-unknown-linux-musl.tar.gz"),
+            Ok((
+                "typos-lsp-v0.1.23-x86_64-unknown-linux-musl.tar.gz".to_string(),
+                LinuxLibc::Musl
+            ))
+        );
+        assert_eq!(
+            named("typos-lsp-v0.1.23-x86_64-unknown-linux-gnu.tar.gz"),
+            Ok((
+                "typos-lsp-v0.1.23-x86_64-unknown-linux-gnu.tar.gz".to_string(),
+                LinuxLibc::Gnu
+            ))
+        );
+        assert_eq!(
+            named("typos-lsp-{version}-armv7-unknown-linux-gnueabihf.tar.gz"),
+            Err("`asset_name` requires the asset \
+                 typos-lsp-v0.1.23-armv7-unknown-linux-gnueabihf.tar.gz of typos-lsp v0.1.23, \
+                 but the release only has [\"typos-lsp-v0.1.23-x86_64-unknown-linux-gnu.tar.gz\", \
+                 \"typos-lsp-v0.1.23-x86_64-unknown-linux-musl.tar.gz\"]"
+                .to_string())
+        );
+    }
+
+    #[test]
+    fn locked_asset_and_binary() {
+        let lockfile = Lockfile {
+            version: "v0.1.23".to_string(),
+            target: "x86_64-unknown-linux-musl".to_string(),
+            asset: "typos-lsp-v0.1.23-x86_64-unknown-linux-musl.tar.gz".to_string(),
+            sha256: hashing::sha256_hex(b"binary"),
+            config: Default::default(),
+        };
+        let assets = [
+            asset("typos-lsp-v0.1.23-x86_64-unknown-linux-gnu.tar.gz"),
+            asset("typos-lsp-v0.1.23-x86_64-unknown-linux-musl.tar.gz"),
+        ];
+        let (found, libc) =
+            TyposExtension::locked_asset(&lockfile, &assets, &[LinuxLibc::Gnu]).unwrap();
+        assert_eq!(found.name, lockfile.asset);
+        assert_eq!(libc, LinuxLibc::Musl);
+
+        let error =
+            TyposExtension::locked_asset(&lockfile, &assets[..1], &[LinuxLibc::Gnu]).unwrap_err();
+        assert!(error.contains(&lockfile.asset), "{error}");
+        assert!(error.contains("x86_64-unknown-linux-gnu.tar.gz"), "{error}");
+
+        let temp = TempDir::new("locked-binary");
+        let work_dir = StdFs::new(temp.path());
+        fs::write(temp.path().join("typos-lsp"), b"binary").unwrap();
+        assert_eq!(
+            TyposExtension::verify_locked_binary(&work_dir, &lockfile, "typos-lsp"),
+            Ok(())
+        );
+        fs::write(temp.path().join("typos-lsp"), b"tampered").unwrap();
+        let error =
+            TyposExtension::verify_locked_binary(&work_dir, &lockfile, "typos-lsp").unwrap_err();
+        assert!(error.contains(&lockfile.sha256), "{error}");
+        assert!(error.contains(&hashing::sha256_hex(b"tampered")), "{error}");
+    }
+
+    #[test]
+    fn crash_loops_suspend_the_configuration() {
+        let temp = TempDir::new("crash-loop");
+        let downloader = FakeDownloader::new(temp.path());
+        let now = Arc::new(AtomicU64::new(0));
+        let mut extension = session(&temp, &FakeReleases::default(), &downloader, 1000, &now);
+        for _ in 1..crash_loop::RESOLUTIONS {
+            assert!(!extension.record_resolution("typos", ROOT, "settings"));
+            now.fetch_add(5, Ordering::SeqCst);
+        }
+        assert!(extension.record_resolution("typos", ROOT, "settings"));
+        assert!(extension.server_state("typos", ROOT).crash_loop.safe_mode());
+        // Other servers keep their configuration.
+        assert!(!extension.record_resolution("typos", "/work/other", "settings"));
+
+        // Until the settings change.
+        now.fetch_add(5, Ordering::SeqCst);
+        assert!(extension.record_resolution("typos", ROOT, "settings"));
+        assert!(!extension.record_resolution("typos", ROOT, "fixed settings"));
+        assert!(!extension.server_state("typos", ROOT).crash_loop.safe_mode());
+    }
+
+    #[test]
+    fn server_states_are_isolated() {
+        let temp = TempDir::new("server-states");
+        let downloader = FakeDownloader::new(temp.path());
+        let mut extension = extension(&temp, &FakeReleases::default(), &downloader);
+        let state = extension.server_state("typos", ROOT);
+        state.cached_binary_path = Some("typos-lsp-v0.1.23/typos-lsp".to_string());
+        state.cached_binary_version = Some("v0.1.23".to_string());
+
+        let other = extension.server_state("typos-fork", ROOT);
+        assert_eq!(other.cached_binary_path, None);
+        assert_eq!(other.cached_binary_version, None);
+        other.cached_binary_version = Some("v0.1.30".to_string());
+
+        assert_eq!(
+            extension
+                .server_state("typos", ROOT)
+                .cached_binary_version
+                .as_deref(),
+            Some("v0.1.23")
+        );
+        assert_eq!(extension.servers.len(), 2);
+    }
+
+    #[test]
+    fn in_use_versions_survive_cleanup() {
+        let temp = TempDir::new("in-use-cleanup");
+        for name in ["typos-lsp-v0.1.1", "typos-lsp-v0.1.2", "typos-lsp-v0.1.3"] {
+            fs::create_dir(temp.path().join(name)).unwrap();
+        }
+        let in_use = BTreeSet::from([Version::parse("v0.1.2").unwrap()]);
+        let work_dir = StdFs::new(temp.path());
+
+        let deferred = TyposExtension::clean_other_installations(
+            &work_dir,
+            &Version::parse("v0.1.3").unwrap(),
+            1,
+            &in_use,
+        )
+        .unwrap();
+        assert_eq!(deferred, vec!["typos-lsp-v0.1.2".to_string()]);
+        assert!(!temp.path().join("typos-lsp-v0.1.1").exists());
+        assert!(temp.path().join("typos-lsp-v0.1.2").exists());
+        assert!(temp.path().join("typos-lsp-v0.1.3").exists());
+
+        TyposExtension::defer_cleanup(&work_dir, &deferred);
+        TyposExtension::clean_other_installations(
+            &work_dir,
+            &Version::parse("v0.1.3").unwrap(),
+            1,
+            &in_use,
+        )
+        .unwrap();
+        assert!(temp
+            .path()
+            .join(TyposExtension::DEFERRED_CLEANUP_FILE)
+            .exists());
+
+        // The next extension instance removes it.
+        TyposExtension::run_deferred_cleanup(&work_dir);
+        assert!(!temp.path().join("typos-lsp-v0.1.2").exists());
+        assert!(temp.path().join("typos-lsp-v0.1.3").exists());
+        assert!(!temp
+            .path()
+            .join(TyposExtension::DEFERRED_CLEANUP_FILE)
+            .exists());
+    }
+
+    #[test]
+    fn recent_versions_are_kept() {
+        let temp = TempDir::new("keep-versions");
+        for name in [
+            "typos-lsp-0.1.8",
+            "typos-lsp-0.1.9",
+            "typos-lsp-0.1.10",
+            "typos-lsp-0.1.11-rc.1",
+            "typos-lsp-0.1.11",
+        ] {
+            fs::create_dir(temp.path().join(name)).unwrap();
+        }
+        let work_dir = StdFs::new(temp.path());
+        let names = work_dir.read_dir(Path::new(".")).unwrap();
+        let version = |tag| Version::parse(tag).unwrap();
+        // Compared as versions: 0.1.10 is newer than 0.1.9, a release than its
+        // pre-release.
+        assert_eq!(
+            TyposExtension::retained_versions(&names, &version("0.1.11"), 3),
+            BTreeSet::from([version("0.1.10"), version("0.1.11-rc.1")])
+        );
+        // A pinned previous release is kept along with the newest ones.
+        assert_eq!(
+            TyposExtension::retained_versions(&names, &version("0.1.8"), 2),
+            BTreeSet::from([version("0.1.11")])
+        );
+        assert!(TyposExtension::retained_versions(&names, &version("0.1.11"), 1).is_empty());
+        assert_eq!(
+            TyposExtension::retained_versions(&names, &version("0.1.11"), 10).len(),
+            4
+        );
+
+        let deferred = TyposExtension::clean_other_installations(
+            &work_dir,
+            &version("0.1.11"),
+            3,
+            &BTreeSet::new(),
+        )
+        .unwrap();
+        assert!(deferred.is_empty());
+        let mut survivors = work_dir.read_dir(Path::new(".")).unwrap();
+        survivors.sort();
+        assert_eq!(
+            survivors,
+            [
+                "typos-lsp-0.1.10",
+                "typos-lsp-0.1.11",
+                "typos-lsp-0.1.11-rc.1"
+            ]
+        );
+    }
+
+    #[test]
+    fn cleanup_only_removes_versions() {
+        let temp = TempDir::new("mixed-cleanup");
+        for dir in [
+            "typos-lsp-0.1.21",
+            "typos-lsp-0.1.22",
+            "typos-lsp-0.1.23",
+            "typos-lsp-nightly",
+            "logs",
+            typos_config::GENERATED_DIR,
+        ] {
+            fs::create_dir(temp.path().join(dir)).unwrap();
+        }
+        for file in [
+            "typos-lsp-0.1.20",
+            "typos-lsp-0.1.23.tar.gz",
+            "notes.txt",
+            update_timing::PENDING_FILE,
+            work_lock::FILE_NAME,
+        ] {
+            fs::write(temp.path().join(file), "").unwrap();
+        }
+        let in_use = BTreeSet::from([Version::parse("v0.1.22").unwrap()]);
+
+        let deferred = TyposExtension::clean_other_installations(
+            &StdFs::new(temp.path()),
+            &Version::parse("v0.1.23").unwrap(),
+            1,
+            &in_use,
+        )
+        .unwrap();
+        assert_eq!(deferred, ["typos-lsp-0.1.22"]);
+        let mut survivors: Vec<String> = fs::read_dir(temp.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        survivors.sort();
+        assert_eq!(
+            survivors,
+            [
+                typos_config::GENERATED_DIR,
+                work_lock::FILE_NAME,
+                "logs",
+                "notes.txt",
+                update_timing::PENDING_FILE,
+                "typos-lsp-0.1.22",
+                "typos-lsp-0.1.23",
+                "typos-lsp-0.1.23.tar.gz",
+                "typos-lsp-nightly",
+            ]
+        );
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn binary_name_within_extension() {
+        assert_eq!(
+            TyposExtension::binary_path_within_archive(&Os::Mac, &Architecture::X8664),
+            Ok("typos-lsp".to_string())
+        );
+        assert_eq!(
+            TyposExtension::binary_path_within_archive(&Os::Windows, &Architecture::X8664),
+            Ok("target/x86_64-pc-windows-msvc/release/typos-lsp.exe".to_string())
+        );
+    }
+
+    /// The worktree the test requests are made for.
+    const ROOT: &str = "/work/project";
+
+    /// A Linux x86_64 worktree without any pin.
+    fn request() -> InstallRequest {
+        InstallRequest {
+            platform: Os::Linux,
+            architecture: Architecture::X8664,
+            container: None,
+            lockfile: None,
+            pinned_version: None,
+            asset_preference: vec![LinuxLibc::Gnu, LinuxLibc::Musl],
+            dry_run: false,
+            asset_name: None,
+            worktree: ROOT.to_string(),
+            update_timing: UpdateTiming::OnOpen,
+            cleanup: true,
+            keep_versions: 1,
+            // Looking up the latest release every time, unless a test says otherwise.
+            update_check_interval: Duration::ZERO,
+            prefer_prerelease: false,
+            auto_update: true,
+            mirror: Mirror::default(),
+        }
+    }
+
+    fn extension(
+        temp: &TempDir,
+        releases: &FakeReleases,
+        downloader: &FakeDownloader,
+    ) -> TyposExtension {
+        let mut extension = TyposExtension::with_host(
+            Box::new(releases.clone()),
+            Box::new(downloader.clone()),
+            Box::new(StdFs::new(temp.path())),
+            temp.path().to_path_buf(),
+        );
+        // Failed downloads are retried right away.
+        extension.sleep = Box::new(|_| {});
+        extension
+    }
+
+    /// Tidy the work directory up as the first server to start would, on Linux.
+    fn tidy(extension: &mut TyposExtension, cleanup: Cleanup) {
+        let settings = ExtensionSettings {
+            cleanup,
+            ..ExtensionSettings::default()
+        };
+        extension.tidy_work_dir(&settings, (Os::Linux, Architecture::X8664));
+    }
+
+    /// Install for `server`, with the names of the installation statuses that were
+    /// reported.
+    fn install(
+        extension: &mut TyposExtension,
+        server: &str,
+        request: &InstallRequest,
+    ) -> (Result<TyposBinary, String>, Vec<String>) {
+        let statuses = RefCell::new(Vec::new());
+        let binary = extension.install(server, request, &mut Metrics::default(), &|status| {
+            let status = format!("{status:?}");
+            let name = status.split('(').next().unwrap_or_default().to_string();
+            statuses.borrow_mut().push(name)
+        });
+        (binary.map_err(String::from), statuses.into_inner())
+    }
+
+    fn gnu_url(version: &str) -> String {
+        format!("https://example.com/typos-lsp-{version}-x86_64-unknown-linux-gnu.tar.gz")
+    }
+
+    #[test]
+    fn resolutions_end_on_a_final_status() {
+        let temp = TempDir::new("resolution-status");
+        let (releases, downloader) = (FakeReleases::default(), FakeDownloader::new(temp.path()));
+        releases.fail_with(Some("offline"));
+        let mut extension = extension(&temp, &releases, &downloader);
+        let conclude = |extension: &mut TyposExtension,
+                        result: &Result<TyposBinary, TyposExtensionError>| {
+            let statuses = RefCell::new(Vec::new());
+            TyposExtension::conclude(&mut extension.statuses, "typos", result, &|status| {
+                statuses.borrow_mut().push(status)
+            });
+            statuses.into_inner()
+        };
+
+        // An installation ends on its own status.
+        let (binary, statuses) = install(&mut extension, "typos", &request());
+        assert_eq!(statuses.last().map(String::as_str), Some("Failed"));
+        assert!(conclude(&mut extension, &binary.map_err(TyposExtensionError::from)).is_empty());
+
+        // Failing before installing anything, e.g. on an unsupported platform.
+        let error = TyposExtension::find_architecture(&[], &Architecture::X86).unwrap_err();
+        assert_eq!(
+            conclude(&mut extension, &Err(error.clone().into())),
+            [Status::Failed(error)]
+        );
+
+        // A binary found outside the work directory clears the failure.
+        let found = || {
+            Ok(TyposBinary {
+                path: "/usr/bin/typos-lsp".to_string(),
+                version: None,
+                provenance: Provenance::Path,
+                skipped: Vec::new(),
+            })
+        };
+        assert_eq!(conclude(&mut extension, &found()), [Status::None]);
+        assert!(conclude(&mut extension, &found()).is_empty());
+    }
+
+    #[test]
+    fn install_then_reuse() {
+        let temp = TempDir::new("install-flow");
+        let (releases, downloader) = (FakeReleases::default(), FakeDownloader::new(temp.path()));
+        releases.publish("v0.1.23");
+        let mut extension = extension(&temp, &releases, &downloader);
+
+        let (binary, statuses) = install(&mut extension, "typos", &request());
+        let binary = binary.unwrap();
+        assert_eq!(binary.path, "typos-lsp-0.1.23/typos-lsp");
+        assert_eq!(binary.version.as_deref(), Some("v0.1.23"));
+        assert_eq!(binary.provenance, Provenance::Downloaded);
+        assert_eq!(statuses, ["CheckingForUpdate", "Downloading", "None"]);
+        assert_eq!(downloader.downloads(), [gnu_url("v0.1.23")]);
+        let manifest =
+            Manifest::read(&StdFs::new(temp.path()), "typos-lsp-0.1.23".as_ref()).unwrap();
+        assert_eq!(manifest.variant.as_deref(), Some("gnu"));
+        assert_eq!(manifest.extension_version, Some(build_info::describe()));
+
+        let (binary, statuses) = install(&mut extension, "typos", &request());
+        assert_eq!(binary.unwrap().provenance, Provenance::Cached);
+        assert!(statuses.is_empty());
+        assert_eq!(releases.lookups(), 1);
+
+        // Another server finds the binary in place, without downloading it again.
+        let (binary, _) = install(&mut extension, "typos-fork", &request());
+        assert_eq!(binary.unwrap().provenance, Provenance::Downloaded);
+        assert_eq!(downloader.downloads().len(), 1);
+    }
+
+    #[test]
+    fn installed_binaries_are_probed() {
+        let temp = TempDir::new("binary-probe");
+        let (releases, downloader) = (FakeReleases::default(), FakeDownloader::new(temp.path()));
+        releases.publish("v0.1.23");
+        downloader.serve(&gnu_url("v0.1.23"), b"typos-lsp 0.1.23\n");
+        // Left crashing by an earlier session.
+        fs::create_dir_all(temp.path().join("typos-lsp-0.1.23")).unwrap();
+        fs::write(temp.path().join("typos-lsp-0.1.23/typos-lsp"), "").unwrap();
+        let runner = FakeRunner::new(temp.path());
+        let mut extension = extension(&temp, &releases, &downloader);
+        extension.runner = Box::new(runner.clone());
+
+        let (binary, _) = install(&mut extension, "typos", &request());
+        assert_eq!(binary.unwrap().provenance, Provenance::Downloaded);
+        assert_eq!(downloader.downloads(), [gnu_url("v0.1.23")]);
+        assert_eq!(
+            runner.runs(),
+            [
+                "typos-lsp-0.1.23/typos-lsp",
+                "typos-lsp-0.1.23.partial/typos-lsp"
+            ]
+        );
+
+        // Once per session.
+        let (binary, _) = install(&mut extension, "typos-fork", &request());
+        assert_eq!(binary.unwrap().provenance, Provenance::Downloaded);
+        assert_eq!(downloader.downloads().len(), 1);
+        assert_eq!(runner.runs().len(), 2);
+    }
+
+    #[test]
+    fn downloads_failing_their_probe_are_discarded() {
+        let temp = TempDir::new("binary-probe-download");
+        let (releases, downloader) = (FakeReleases::default(), FakeDownloader::new(temp.path()));
+        releases.publish("v0.1.23");
+        downloader.serve(&gnu_url("v0.1.23"), b"typos-lsp 0.1.22\n");
+        let mut extension = extension(&temp, &releases, &downloader);
+        extension.runner = Box::new(FakeRunner::new(temp.path()));
+
+        let (binary, statuses) = install(&mut extension, "typos", &request());
+        let error = binary.map(|binary| binary.path).unwrap_err();
+        assert!(
+            error.contains(
+                "typos-lsp typos-lsp-0.1.23/typos-lsp failed its `--version` probe right after \
+                 being downloaded: it reports \"typos-lsp 0.1.22\" rather than version 0.1.23"
+            ),
+            "{error}"
+        );
+        assert_eq!(statuses.last().map(String::as_str), Some("Failed"));
+        assert!(!temp.path().join("typos-lsp-0.1.23").exists());
+        assert!(!temp.path().join("typos-lsp-0.1.23.partial").exists());
+
+        // Offline, an installation failing its probe is not fallen back on either.
+        let dir = temp.path().join("typos-lsp-0.1.22");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("typos-lsp"), "").unwrap();
+        Manifest {
+            version: "v0.1.22".to_string(),
+            asset: "typos-lsp-v0.1.22-x86_64-unknown-linux-gnu.tar.gz".to_string(),
+            variant: Some("gnu".to_string()),
+            sha256: None,
+            worktree: None,
+            last_used: None,
+            failed_starts: 0,
+            extension_version: None,
+            platform: None,
+        }
+        .write(&StdFs::new(temp.path()), &dir)
+        .unwrap();
+        releases.fail_with(Some("offline"));
+        let (binary, _) = install(&mut extension, "typos", &request());
+        assert_eq!(
+            binary.map(|binary| binary.path),
+            Err(messages::for_worktree(
+                ROOT,
+                TyposExtensionError::ReleaseLookup {
+                    tag: None,
+                    reason: "offline".to_string(),
+                }
+            ))
+        );
+        assert!(!temp.path().join("typos-lsp-0.1.22").exists());
+    }
+
+    #[test]
+    fn lookup_failures_are_retried() {
+        let temp = TempDir::new("lookup-failure");
+        let (releases, downloader) = (FakeReleases::default(), FakeDownloader::new(temp.path()));
+        releases.publish("v0.1.23");
+        releases.fail_with(Some("connection reset"));
+        let mut extension = extension(&temp, &releases, &downloader);
+
+        let (binary, statuses) = install(&mut extension, "typos", &request());
+        assert_eq!(
+            binary.map(|binary| binary.path),
+            Err(messages::for_worktree(
+                ROOT,
+                TyposExtensionError::ReleaseLookup {
+                    tag: None,
+                    reason: "connection reset".to_string(),
+                }
+            ))
+        );
+        assert_eq!(statuses, ["CheckingForUpdate", "Failed"]);
+        assert!(downloader.downloads().is_empty());
+
+        let mut pinned = request();
+        pinned.pinned_version = Some("v0.1.23".to_string());
+        let (binary, _) = install(&mut extension, "typos", &pinned);
+        assert_eq!(
+            binary.map(|binary| binary.path),
+            Err(messages::for_worktree(
+                ROOT,
+                TyposExtensionError::ReleaseLookup {
+                    tag: Some("v0.1.23".to_string()),
+                    reason: "connection reset".to_string(),
+                }
+            ))
+        );
+
+        releases.fail_with(None);
+        let (binary, _) = install(&mut extension, "typos", &request());
+        assert_eq!(binary.unwrap().provenance, Provenance::Downloaded);
+    }
+
+    #[test]
+    fn download_failures_leave_no_cache() {
+        let temp = TempDir::new("download-failure");
+        let (releases, downloader) = (FakeReleases::default(), FakeDownloader::new(temp.path()));
+        releases.publish("v0.1.23");
+        downloader.fail_with(Some("connection reset"));
+        let mut extension = extension(&temp, &releases, &downloader);
+
+        let (binary, _) = install(&mut extension, "typos", &request());
+        assert_eq!(
+            binary.map(|binary| binary.path),
+            Err(messages::for_worktree(
+                ROOT,
+                TyposExtensionError::Download {
+                    reason: "connection reset".to_string(),
+                }
+            ))
+        );
+        assert_eq!(
+            extension.server_state("typos", ROOT).cached_binary_path,
+            None
+        );
+        assert!(extension.in_use_versions.is_empty());
+        assert!(!temp.path().join("typos-lsp-0.1.23").exists());
+        assert!(!temp.path().join("typos-lsp-0.1.23.partial").exists());
+
+        downloader.fail_with(None);
+        let (binary, _) = install(&mut extension, "typos", &request());
+        assert_eq!(binary.unwrap().provenance, Provenance::Downloaded);
+        assert_eq!(downloader.downloads(), [gnu_url("v0.1.23")]);
+    }
+
+    #[test]
+    fn failed_downloads_are_retried() {
+        let temp = TempDir::new("download-retries");
+        let (releases, downloader) = (FakeReleases::default(), FakeDownloader::new(temp.path()));
+        releases.publish("v0.1.23");
+        // Left by interrupted installations.
+        for staging in ["typos-lsp-0.1.22.partial", "typos-lsp-0.1.23.partial"] {
+            fs::create_dir_all(temp.path().join(staging)).unwrap();
+            fs::write(temp.path().join(staging).join("typos-lsp"), "trunc").unwrap();
+        }
+        downloader.fail_times(2, "connection reset");
+        let mut extension = extension(&temp, &releases, &downloader);
+        let delays = Arc::new(Mutex::new(Vec::new()));
+        let slept = delays.clone();
+        extension.sleep = Box::new(move |delay| slept.lock().unwrap().push(delay));
+
+        let (binary, _) = install(&mut extension, "typos", &request());
+        assert_eq!(binary.unwrap().path, "typos-lsp-0.1.23/typos-lsp");
+        assert_eq!(
+            *delays.lock().unwrap(),
+            [Duration::from_secs(1), Duration::from_secs(4)]
+        );
+        assert_eq!(downloader.downloads(), [gnu_url("v0.1.23")]);
+        assert_eq!(
+            fs::read_to_string(temp.path().join("typos-lsp-0.1.23/typos-lsp")).unwrap(),
+            gnu_url("v0.1.23")
+        );
+        let manifest =
+            Manifest::read(&StdFs::new(temp.path()), "typos-lsp-0.1.23".as_ref()).unwrap();
+        assert_eq!(
+            manifest.sha256,
+            Some(hashing::sha256_hex(gnu_url("v0.1.23").as_bytes()))
+        );
+        for staging in ["typos-lsp-0.1.22.partial", "typos-lsp-0.1.23.partial"] {
+            assert!(!temp.path().join(staging).exists(), "{staging}");
+        }
+
+        // Until the attempts run out.
+        releases.publish("v0.1.24");
+        downloader.fail_times(3, "connection reset");
+        let (binary, _) = install(&mut extension, "typos-other", &request());
+        assert_eq!(
+            binary.unwrap().version.as_deref(),
+            Some("v0.1.23"),
+            "the installed release is used meanwhile"
+        );
+        assert_eq!(delays.lock().unwrap().len(), 4);
+        assert!(!temp.path().join("typos-lsp-0.1.24").exists());
+        assert!(!temp.path().join("typos-lsp-0.1.24.partial").exists());
+    }
+
+    #[test]
+    fn extracted_binaries_are_made_executable() {
+        let temp = TempDir::new("executable");
+        let (releases, downloader) = (FakeReleases::default(), FakeDownloader::new(temp.path()));
+        releases.publish("v0.1.23");
+        let mut extension = extension(&temp, &releases, &downloader);
+        let work_dir = StdFs::new(temp.path());
+
+        downloader.fail_make_executable(Some("operation not permitted"));
+        let (binary, statuses) = install(&mut extension, "typos", &request());
+        assert_eq!(
+            binary.map(|binary| binary.path),
+            Err(messages::for_worktree(
+                ROOT,
+                "failed to make typos-lsp-0.1.23.partial/typos-lsp executable: operation not \
+                 permitted. Check that the extension work directory is writable and has free \
+                 space"
+            ))
+        );
+        assert_eq!(statuses.last().map(String::as_str), Some("Failed"));
+        assert!(!temp.path().join("typos-lsp-0.1.23").exists());
+        assert!(!temp.path().join("typos-lsp-0.1.23.partial").exists());
+
+        // An empty extraction is not installed either, and downloaded again next time.
+        downloader.fail_make_executable(None);
+        downloader.serve(&gnu_url("v0.1.23"), b"");
+        let (binary, _) = install(&mut extension, "typos", &request());
+        let error = binary.map(|binary| binary.path).unwrap_err();
+        assert!(
+            error.contains("typos-lsp-0.1.23.partial/typos-lsp is empty"),
+            "{error}"
+        );
+        assert!(!temp.path().join("typos-lsp-0.1.23").exists());
+
+        downloader.serve(&gnu_url("v0.1.23"), b"binary");
+        let (binary, _) = install(&mut extension, "typos", &request());
+        let binary = binary.unwrap();
+        assert_eq!(binary.provenance, Provenance::Downloaded);
+        assert!(work_dir.is_executable(binary.path.as_ref()));
+        assert_eq!(downloader.downloads().len(), 3);
+
+        // Windows has no executable bit.
+        let temp = TempDir::new("executable-windows");
+        let downloader = FakeDownloader::new(temp.path());
+        downloader.fail_make_executable(Some("unsupported"));
+        downloader.extract_to(&["typos-lsp.exe"]);
+        let mut windows = self::extension(&temp, &releases, &downloader);
+        let mut request = request();
+        request.platform = Os::Windows;
+        let (binary, _) = install(&mut windows, "typos", &request);
+        assert!(binary.unwrap().path.ends_with("/typos-lsp.exe"));
+    }
+
+    #[test]
+    fn published_checksums_are_checked() {
+        let temp = TempDir::new("checksum-flow");
+        let (releases, downloader) = (FakeReleases::default(), FakeDownloader::new(temp.path()));
+        releases.publish("v0.1.23");
+        releases.publish_asset(
+            "v0.1.23",
+            "typos-lsp-v0.1.23-x86_64-unknown-linux-gnu.tar.gz.sha256",
+        );
+        let (archive, checksum) = (gnu_url("v0.1.23"), format!("{}.sha256", gnu_url("v0.1.23")));
+        downloader.serve(&checksum, hashing::sha256_hex(b"tampered").as_bytes());
+        let mut extension = extension(&temp, &releases, &downloader);
+
+        let (binary, statuses) = install(&mut extension, "typos", &request());
+        let error = binary.map(|binary| binary.path).unwrap_err();
+        assert!(
+            error.contains("does not match its published checksum"),
+            "{error}"
+        );
+        assert_eq!(statuses.last().map(String::as_str), Some("Failed"));
+        // Like any failed download, in case it was corrupted on the way.
+        assert_eq!(
+            downloader.downloads(),
+            [checksum.as_str(), archive.as_str()].repeat(3)
+        );
+        assert!(!temp.path().join("typos-lsp-0.1.23").exists());
+
+        // The archive is extracted once it matches.
+        downloader.serve(
+            &checksum,
+            hashing::sha256_hex(archive.as_bytes()).as_bytes(),
+        );
+        let (binary, _) = install(&mut extension, "typos", &request());
+        assert_eq!(binary.unwrap().path, "typos-lsp-0.1.23/typos-lsp");
+        assert_eq!(
+            downloader.downloads()[6..],
+            [checksum, archive.clone(), archive]
+        );
+        for file in [
+            release_checksums::ARCHIVE_FILE,
+            release_checksums::CHECKSUM_FILE,
+        ] {
+            assert!(!temp.path().join(file).exists(), "{file}");
+        }
+    }
+
+    #[test]
+    fn offline_sessions_use_installed_binaries() {
+        let temp = TempDir::new("offline");
+        let (releases, downloader) = (FakeReleases::default(), FakeDownloader::new(temp.path()));
+        releases.publish("v0.1.22");
+        install(
+            &mut extension(&temp, &releases, &downloader),
+            "typos",
+            &request(),
+        )
+        .0
+        .unwrap();
+
+        // The release lookup fails.
+        releases.fail_with(Some("network unreachable"));
+        let mut extension = extension(&temp, &releases, &downloader);
+        let (binary, statuses) = install(&mut extension, "typos", &request());
+        let binary = binary.unwrap();
+        assert_eq!(binary.provenance, Provenance::Cached);
+        assert_eq!(binary.version.as_deref(), Some("v0.1.22"));
+        assert_eq!(statuses, ["CheckingForUpdate", "None"]);
+        assert_eq!(
+            extension.server_state("typos", ROOT).cached_binary_path,
+            Some(binary.path)
+        );
+
+        // The download of a newer release fails.
+        releases.fail_with(None);
+        releases.publish("v0.1.23");
+        downloader.fail_with(Some("connection reset"));
+        let mut extension = self::extension(&temp, &releases, &downloader);
+        let (binary, statuses) = install(&mut extension, "typos", &request());
+        assert_eq!(binary.unwrap().version.as_deref(), Some("v0.1.22"));
+        // Unlike the lookup, the failed update shows.
+        assert_eq!(statuses, ["CheckingForUpdate", "Downloading", "Failed"]);
+
+        // Pinned releases are not replaced by another.
+        let mut pinned = request();
+        pinned.pinned_version = Some("v0.1.23".to_string());
+        let (binary, _) = install(&mut extension, "typos-pinned", &pinned);
+        assert_eq!(
+            binary.map(|binary| binary.path),
+            Err(messages::for_worktree(
+                ROOT,
+                TyposExtensionError::Download {
+                    reason: "connection reset".to_string(),
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn rate_limits_defer_lookups() {
+        const START: u64 = 1_700_000_000;
+        let temp = TempDir::new("rate-limit");
+        let (releases, downloader) = (FakeReleases::default(), FakeDownloader::new(temp.path()));
+        releases.publish("v0.1.22");
+        let now = Arc::new(AtomicU64::new(0));
+        let mut extension = session(&temp, &releases, &downloader, START, &now);
+        install(&mut extension, "typos", &request()).0.unwrap();
+
+        // The refused lookup falls back to the installed release.
+        releases.fail_with(Some("API rate limit exceeded for 203.0.113.7."));
+        let mut extension = session(&temp, &releases, &downloader, START, &now);
+        let (binary, _) = install(&mut extension, "typos", &request());
+        assert_eq!(binary.unwrap().version.as_deref(), Some("v0.1.22"));
+        assert_eq!(releases.lookups(), 2);
+        assert_eq!(
+            fs::read_to_string(temp.path().join(rate_limit::FILE_NAME)).unwrap(),
+            format!("{}\n", START + 3600)
+        );
+
+        // Until the limit resets, GitHub is not asked, by other processes either.
+        now.store(START + 1800, Ordering::SeqCst);
+        let mut extension = session(&temp, &releases, &downloader, START + 1800, &now);
+        let (binary, _) = install(&mut extension, "typos", &request());
+        assert_eq!(binary.unwrap().version.as_deref(), Some("v0.1.22"));
+        assert_eq!(releases.lookups(), 2);
+        let mut pinned = request();
+        pinned.pinned_version = Some("v0.1.23".to_string());
+        let (binary, _) = install(&mut extension, "typos-pinned", &pinned);
+        assert_eq!(
+            binary.map(|binary| binary.path),
+            Err(messages::for_worktree(
+                ROOT,
+                TyposExtensionError::RateLimited {
+                    retry_at: START + 3600,
+                    minutes: 30,
+                }
+            ))
+        );
+        assert_eq!(releases.lookups(), 2);
+
+        // Once it has, the next lookup goes through and forgets the wait.
+        releases.fail_with(None);
+        releases.publish("v0.1.23");
+        let mut extension = session(&temp, &releases, &downloader, START + 3600, &now);
+        let (binary, _) = install(&mut extension, "typos", &request());
+        assert_eq!(binary.unwrap().version.as_deref(), Some("v0.1.23"));
+        assert_eq!(releases.lookups(), 3);
+        assert!(!temp.path().join(rate_limit::FILE_NAME).exists());
+    }
+
+    #[test]
+    fn read_only_work_dir() {
+        let temp = TempDir::new("read-only");
+        let (releases, downloader) = (FakeReleases::default(), FakeDownloader::new(temp.path()));
+        releases.publish("v0.1.23");
+        let mut extension = TyposExtension::with_host(
+            Box::new(releases.clone()),
+            Box::new(downloader.clone()),
+            Box::new(ReadOnlyFs(StdFs::new(temp.path()))),
+            temp.path().to_path_buf(),
+        );
+
+        let mut request = request();
+        request.container = Some(Container::Devcontainer);
+        let (binary, statuses) = install(&mut extension, "typos", &request);
+        let error = binary.map(|binary| binary.path).unwrap_err();
+        assert!(error.contains("not writable"), "{error}");
+        assert!(error.contains("read-only container overlays"), "{error}");
+        assert_eq!(statuses, ["CheckingForUpdate", "Downloading", "Failed"]);
+        assert!(downloader.downloads().is_empty());
+    }
+
+    #[test]
+    fn pinned_version_bypasses_cache() {
+        let temp = TempDir::new("pinned-cache");
+        let (releases, downloader) = (FakeReleases::default(), FakeDownloader::new(temp.path()));
+        releases.publish("v0.1.22");
+        releases.publish("v0.1.23");
+        let mut extension = extension(&temp, &releases, &downloader);
+
+        let (binary, _) = install(&mut extension, "typos", &request());
+        assert_eq!(binary.unwrap().version.as_deref(), Some("v0.1.23"));
+
+        let mut pinned = request();
+        pinned.pinned_version = Some("v0.1.22".to_string());
+        let (binary, _) = install(&mut extension, "typos", &pinned);
+        let binary = binary.unwrap();
+        assert_eq!(binary.version.as_deref(), Some("v0.1.22"));
+        assert_eq!(binary.provenance, Provenance::Downloaded);
+        assert_eq!(
+            downloader.downloads(),
+            [gnu_url("v0.1.23"), gnu_url("v0.1.22")]
+        );
+        // The latest version is still running for this session.
+        assert!(temp.path().join("typos-lsp-0.1.23").exists());
+    }
+
+    #[test]
+    fn installed_pin_needs_no_lookup() {
+        let temp = TempDir::new("installed-pin");
+        let (releases, downloader) = (FakeReleases::default(), FakeDownloader::new(temp.path()));
+        releases.publish("v0.1.22");
+        releases.publish("v0.1.23");
+        let mut pinned = request();
+        pinned.pinned_version = Some("v0.1.22".to_string());
+        let (binary, _) = install(
+            &mut extension(&temp, &releases, &downloader),
+            "typos",
+            &pinned,
+        );
+        assert_eq!(binary.unwrap().provenance, Provenance::Downloaded);
+        assert_eq!(releases.lookups(), 1);
+
+        // A later session finds the pinned release in its directory.
+        let mut extension = extension(&temp, &releases, &downloader);
+        let (binary, statuses) = install(&mut extension, "typos", &pinned);
+        let binary = binary.unwrap();
+        assert_eq!(binary.version.as_deref(), Some("v0.1.22"));
+        assert_eq!(binary.provenance, Provenance::Cached);
+        assert_eq!(releases.lookups(), 1);
+        assert_eq!(downloader.downloads(), [gnu_url("v0.1.22")]);
+        assert!(statuses.is_empty(), "{statuses:?}");
+
+        // A pin that is not installed is looked up, and fails when it does not exist.
+        pinned.pinned_version = Some("v9.9.9".to_string());
+        let (binary, statuses) = install(&mut extension, "typos", &pinned);
+        let error = binary.map(|binary| binary.path).unwrap_err();
+        assert!(
+            error.contains("failed to find typos-lsp release v9.9.9"),
+            "{error}"
+        );
+        assert_eq!(statuses.last().map(String::as_str), Some("Failed"));
+        assert_eq!(releases.lookups(), 2);
+    }
+
+    #[test]
+    fn other_process_holds_the_work_dir() {
+        let temp = TempDir::new("work-lock-flow");
+        let (releases, downloader) = (FakeReleases::default(), FakeDownloader::new(temp.path()));
+        releases.publish("v0.1.23");
+        let lock = temp.path().join(work_lock::FILE_NAME);
+        let start = update_timing::now();
+        let held = format!("other\t{start}\n");
+        fs::write(&lock, &held).unwrap();
+        fs::create_dir(temp.path().join("typos-lsp-0.1.22")).unwrap();
+        fs::write(
+            temp.path().join(TyposExtension::DEFERRED_CLEANUP_FILE),
+            "typos-lsp-0.1.22\n",
+        )
+        .unwrap();
+
+        // Deferred cleanups wait for the next session.
+        let now = Arc::new(AtomicU64::new(0));
+        let mut extension = session(&temp, &releases, &downloader, start, &now);
+        tidy(&mut extension, Cleanup::Auto);
+        assert!(temp
+            .path()
+            .join(TyposExtension::DEFERRED_CLEANUP_FILE)
+            .exists());
+
+        // The installation waits for the other process, then gives up.
+        let clock = now.clone();
+        extension.sleep = Box::new(move |_| {
+            clock.fetch_add(10, Ordering::SeqCst);
+        });
+        let (binary, _) = install(&mut extension, "typos", &request());
+        let error = binary.map(|binary| binary.path).unwrap_err();
+        assert!(
+            error.contains("another Zed process has been installing typos-lsp for 120s;"),
+            "{error}"
+        );
+        assert!(downloader.downloads().is_empty());
+        assert_eq!(fs::read_to_string(&lock).unwrap(), held);
+
+        // Once released, it goes through and releases the lock in turn.
+        fs::remove_file(&lock).unwrap();
+        let (binary, _) = install(&mut extension, "typos", &request());
+        assert_eq!(binary.unwrap().provenance, Provenance::Downloaded);
+        assert!(!lock.exists());
+        assert!(!temp.path().join("typos-lsp-0.1.22").exists());
+    }
+
+    #[test]
+    fn failed_glibc_start_switches_to_musl() {
+        let temp = TempDir::new("libc-fallback-flow");
+        let (releases, downloader) = (FakeReleases::default(), FakeDownloader::new(temp.path()));
+        releases.publish("v0.1.23");
+        let mut extension = extension(&temp, &releases, &downloader);
+        let (binary, _) = install(&mut extension, "typos", &request());
+        let binary = binary.unwrap();
+        extension.start_probe("typos", ROOT, &binary);
+
+        // Initializing ends the probe.
+        libc_fallback::end_probe(extension.fs.as_ref(), "typos", ROOT);
+        extension.check_start_probe("typos", ROOT, false, true);
+        assert_eq!(libc_fallback::read(extension.fs.as_ref()), None);
+
+        // Starting again without having initialized fails the probe, unless the builds
+        // are chosen in the settings.
+        extension.start_probe("typos", ROOT, &binary);
+        extension.check_start_probe("typos", ROOT, true, true);
+        assert_eq!(libc_fallback::read(extension.fs.as_ref()), None);
+        extension.start_probe("typos", ROOT, &binary);
+        extension.check_start_probe("typos", ROOT, false, true);
+        assert_eq!(
+            libc_fallback::read(extension.fs.as_ref()).as_deref(),
+            Some("v0.1.23")
+        );
+        assert!(!temp.path().join("typos-lsp-0.1.23").exists());
+
+        let mut musl = request();
+        musl.asset_preference = TyposExtension::libc_preference(None, true).to_vec();
+        let (binary, _) = install(&mut extension, "typos", &musl);
+        assert_eq!(binary.unwrap().provenance, Provenance::Downloaded);
+        assert_eq!(
+            downloader.downloads(),
+            [
+                gnu_url("v0.1.23"),
+                "https://example.com/typos-lsp-v0.1.23-x86_64-unknown-linux-musl.tar.gz"
+                    .to_string(),
+            ]
+        );
+        let manifest =
+            Manifest::read(&StdFs::new(temp.path()), "typos-lsp-0.1.23".as_ref()).unwrap();
+        assert_eq!(manifest.variant.as_deref(), Some("musl"));
+    }
+
+    #[test]
+    fn failing_installations_are_set_aside() {
+        let temp = TempDir::new("start-failures");
+        let (releases, downloader) = (FakeReleases::default(), FakeDownloader::new(temp.path()));
+        releases.publish("v0.1.22");
+        let mut musl = request();
+        musl.asset_preference = vec![LinuxLibc::Musl];
+        let mut extension = extension(&temp, &releases, &downloader);
+        install(&mut extension, "typos", &musl).0.unwrap();
+        // Kept by the installation of the next release, as the server still runs it.
+        releases.publish("v0.1.23");
+        let (binary, _) = install(&mut extension, "typos-next", &musl);
+        let binary = binary.unwrap();
+        let failed_starts = || {
+            Manifest::read(&StdFs::new(temp.path()), "typos-lsp-0.1.23".as_ref())
+                .unwrap()
+                .failed_starts
+        };
+
+        // Initializing resets the failed starts.
+        extension.start_probe("typos-next", ROOT, &binary);
+        extension.check_start_probe("typos-next", ROOT, true, true);
+        assert_eq!(failed_starts(), 1);
+        extension.start_probe("typos-next", ROOT, &binary);
+        extension.end_start_probe("typos-next", ROOT);
+        assert_eq!(failed_starts(), 0);
+
+        // The first failure is retried.
+        extension.start_probe("typos-next", ROOT, &binary);
+        extension.check_start_probe("typos-next", ROOT, true, true);
+        let (binary, _) = install(&mut extension, "typos-next", &musl);
+        let binary = binary.unwrap();
+        assert_eq!(binary.path, "typos-lsp-0.1.23/typos-lsp");
+        assert_eq!(binary.provenance, Provenance::Cached);
+
+        // The next one sets the installation aside for the previous one.
+        extension.start_probe("typos-next", ROOT, &binary);
+        extension.check_start_probe("typos-next", ROOT, true, true);
+        assert_eq!(failed_starts(), start_failures::THRESHOLD);
+        let (binary, statuses) = install(&mut extension, "typos-next", &musl);
+        let binary = binary.unwrap();
+        assert_eq!(binary.path, "typos-lsp-0.1.22/typos-lsp");
+        assert_eq!(binary.provenance, Provenance::Cached);
+        assert_eq!(statuses, ["CheckingForUpdate", "None"]);
+        assert_eq!(downloader.downloads().len(), 2);
+
+        // A pinned release is started regardless.
+        let mut pinned = request();
+        pinned.asset_preference = vec![LinuxLibc::Musl];
+        pinned.pinned_version = Some("v0.1.23".to_string());
+        let (binary, _) = install(&mut extension, "typos-pinned", &pinned);
+        assert_eq!(binary.unwrap().path, "typos-lsp-0.1.23/typos-lsp");
+    }
+
+    #[test]
+    fn known_good_installation_outlives_updates() {
+        let temp = TempDir::new("known-good");
+        let (releases, downloader) = (FakeReleases::default(), FakeDownloader::new(temp.path()));
+        releases.publish("v0.1.22");
+        let mut extension = extension(&temp, &releases, &downloader);
+        let (binary, _) = install(&mut extension, "typos", &request());
+        let binary = binary.unwrap();
+        extension.start_probe("typos", ROOT, &binary);
+        extension.end_start_probe("typos", ROOT);
+        let installed = |version: &str| temp.path().join(format!("typos-lsp-{version}")).exists();
+
+        // An update failing its checks falls back to it, and the failure shows.
+        releases.publish("v0.1.23");
+        downloader.serve(&gnu_url("v0.1.23"), b"");
+        let mut extension = self::extension(&temp, &releases, &downloader);
+        let (binary, statuses) = install(&mut extension, "typos", &request());
+        assert_eq!(binary.unwrap().path, "typos-lsp-0.1.22/typos-lsp");
+        assert_eq!(statuses, ["CheckingForUpdate", "Downloading", "Failed"]);
+        assert!(!installed("0.1.23"));
+
+        // A working update does not remove it before starting once.
+        releases.publish("v0.1.24");
+        let mut extension = self::extension(&temp, &releases, &downloader);
+        let (binary, statuses) = install(&mut extension, "typos", &request());
+        let binary = binary.unwrap();
+        assert_eq!(binary.path, "typos-lsp-0.1.24/typos-lsp");
+        assert_eq!(statuses, ["CheckingForUpdate", "Downloading", "None"]);
+        assert!(installed("0.1.22"));
+
+        // Downloaded but never started, it is not fallen back on.
+        releases.publish("v0.1.25");
+        downloader.serve(&gnu_url("v0.1.25"), b"");
+        let mut extension = self::extension(&temp, &releases, &downloader);
+        let (binary, _) = install(&mut extension, "typos", &request());
+        assert_eq!(binary.unwrap().path, "typos-lsp-0.1.22/typos-lsp");
+
+        // Once the update initialized, it replaces the known good installation.
+        let mut extension = self::extension(&temp, &releases, &downloader);
+        let mut pinned = request();
+        pinned.pinned_version = Some("v0.1.24".to_string());
+        let (binary, _) = install(&mut extension, "typos", &pinned);
+        extension.start_probe("typos", ROOT, &binary.unwrap());
+        extension.end_start_probe("typos", ROOT);
+        releases.publish("v0.1.26");
+        let mut extension = self::extension(&temp, &releases, &downloader);
+        install(&mut extension, "typos", &request()).0.unwrap();
+        assert!(!installed("0.1.22"));
+        assert!(installed("0.1.24"));
+    }
+
+    #[test]
+    fn installations_of_other_platforms_are_ignored() {
+        let temp = TempDir::new("other-platform");
+        let (releases, downloader) = (FakeReleases::default(), FakeDownloader::new(temp.path()));
+        releases.publish("v0.1.23");
+        let mut extension = extension(&temp, &releases, &downloader);
+        install(&mut extension, "typos", &request()).0.unwrap();
+        let manifest =
+            Manifest::read(&StdFs::new(temp.path()), "typos-lsp-0.1.23".as_ref()).unwrap();
+        assert_eq!(manifest.platform.as_deref(), Some("linux-x86_64"));
+
+        // The work directory was copied to a Mac, which is offline.
+        releases.fail_with(Some("network unreachable"));
+        let mut mac = request();
+        mac.platform = Os::Mac;
+        mac.architecture = Architecture::Aarch64;
+        let mut extension = self::extension(&temp, &releases, &downloader);
+        let (binary, _) = install(&mut extension, "typos", &mac);
+        assert_eq!(
+            binary.map(|binary| binary.path),
+            Err(messages::for_worktree(
+                ROOT,
+                TyposExtensionError::ReleaseLookup {
+                    tag: None,
+                    reason: "network unreachable".to_string(),
+                }
+            ))
+        );
+        let (binary, _) = install(&mut extension, "typos", &request());
+        assert_eq!(binary.unwrap().path, "typos-lsp-0.1.23/typos-lsp");
+    }
+
+    #[test]
+    fn cache_report_tracks_use() {
+        let temp = TempDir::new("cache-report-use");
+        let (releases, downloader) = (FakeReleases::default(), FakeDownloader::new(temp.path()));
+        releases.publish("v0.1.22");
+        let mut pinned = request();
+        pinned.pinned_version = Some("v0.1.22".to_string());
+        install(
+            &mut extension(&temp, &releases, &downloader),
+            "typos",
+            &pinned,
+        )
+        .0
+        .unwrap();
+        releases.publish("v0.1.23");
+
+        let now = Arc::new(AtomicU64::new(0));
+        let mut extension = session(&temp, &releases, &downloader, 1000, &now);
+        let (binary, _) = install(&mut extension, "typos", &request());
+        now.store(5000, Ordering::SeqCst);
+        extension.record_use(binary.unwrap().version.as_deref());
+        let report = extension.cache_report();
+        let installations: Vec<_> = report
+            .installations
+            .iter()
+            .map(|installation| {
+                (
+                    installation.dir.as_str(),
+                    installation.last_used,
+                    installation.in_use,
+                )
+            })
+            .collect();
+        assert_eq!(installations, [("typos-lsp-0.1.23", Some(5000), true)]);
+        assert!(report.next_removals.is_empty());
+        assert_eq!(report.now, 5000);
+    }
+
+    #[test]
+    fn lockfile_mismatch_fails_after_download() {
+        let temp = TempDir::new("lockfile-mismatch");
+        let (releases, downloader) = (FakeReleases::default(), FakeDownloader::new(temp.path()));
+        releases.publish("v0.1.23");
+        let mut extension = extension(&temp, &releases, &downloader);
+
+        let asset = "typos-lsp-v0.1.23-x86_64-unknown-linux-musl.tar.gz";
+        let mut request = request();
+        request.pinned_version = Some("v0.1.23".to_string());
+        request.lockfile = Some(Lockfile {
+            version: "v0.1.23".to_string(),
+            target: "x86_64-unknown-linux-musl".to_string(),
+            asset: asset.to_string(),
+            sha256: hashing::sha256_hex(b"another binary"),
+            config: Default::default(),
+        });
+        let (binary, _) = install(&mut extension, "typos", &request);
+        let error = binary.map(|binary| binary.path).unwrap_err();
+        assert!(error.contains("does not match"), "{error}");
+        assert_eq!(
+            downloader.downloads(),
+            [format!("https://example.com/{asset}")]
+        );
+        assert_eq!(
+            extension.server_state("typos", ROOT).cached_binary_path,
+            None
+        );
+
+        let content = format!("https://example.com/{asset}");
+        request.lockfile.as_mut().unwrap().sha256 = hashing::sha256_hex(content.as_bytes());
+        // The download kept in its directory is used as it is.
+        let (binary, _) = install(&mut extension, "typos", &request);
+        assert_eq!(binary.unwrap().provenance, Provenance::Cached);
+        assert_eq!(downloader.downloads().len(), 1);
+    }
+
+    #[test]
+    fn binary_removed_by_another_instance() {
+        let temp = TempDir::new("concurrent-instances");
+        let (releases, downloader) = (FakeReleases::default(), FakeDownloader::new(temp.path()));
+        releases.publish("v0.1.22");
+        let mut first = extension(&temp, &releases, &downloader);
+        let (binary, _) = install(&mut first, "typos", &request());
+        assert_eq!(binary.unwrap().version.as_deref(), Some("v0.1.22"));
+
+        // Another Zed window installs a newer release, and cleans up the previous one it
+        // knows nothing about, while the first window still has it cached.
+        releases.publish("v0.1.23");
+        let mut second = extension(&temp, &releases, &downloader);
+        let (binary, _) = install(&mut second, "typos", &request());
+        assert_eq!(binary.unwrap().version.as_deref(), Some("v0.1.23"));
+        assert!(!temp.path().join("typos-lsp-0.1.22").exists());
+
+        // The first window notices the stale cache and picks up the new install.
+        let (binary, statuses) = install(&mut first, "typos", &request());
+        let binary = binary.unwrap();
+        assert_eq!(binary.version.as_deref(), Some("v0.1.23"));
+        assert_eq!(binary.provenance, Provenance::Downloaded);
+        assert_eq!(statuses, ["CheckingForUpdate", "None"]);
+        assert_eq!(downloader.downloads().len(), 2);
+    }
+
+    #[test]
+    fn cleanup_spares_running_versions_and_state() {
+        let temp = TempDir::new("cleanup-invariants");
+        let (releases, downloader) = (FakeReleases::default(), FakeDownloader::new(temp.path()));
+        releases.publish("v0.1.22");
+        fs::write(temp.path().join(crate::metrics::FILE_NAME), "").unwrap();
+        let mut session = extension(&temp, &releases, &downloader);
+        install(&mut session, "typos", &request()).0.unwrap();
+
+        releases.publish("v0.1.23");
+        install(&mut session, "typos-fork", &request()).0.unwrap();
+        for kept in [
+            "typos-lsp-0.1.22",
+            "typos-lsp-0.1.23",
+            crate::metrics::FILE_NAME,
+            TyposExtension::DEFERRED_CLEANUP_FILE,
+        ] {
+            assert!(temp.path().join(kept).exists(), "{kept}");
+        }
+
+        // The previous session is over, its version goes away with the next instance.
+        drop(session);
+        let mut next = extension(&temp, &releases, &downloader);
+        tidy(&mut next, Cleanup::Auto);
+        assert!(!temp.path().join("typos-lsp-0.1.22").exists());
+        assert!(!temp
+            .path()
+            .join(TyposExtension::DEFERRED_CLEANUP_FILE)
+            .exists());
+        assert!(temp.path().join(crate::metrics::FILE_NAME).exists());
+        let (binary, _) = install(&mut next, "typos", &request());
+        assert_eq!(binary.unwrap().version.as_deref(), Some("v0.1.23"));
+        assert_eq!(downloader.downloads().len(), 2);
+    }
+
+    #[test]
+    fn cleanup_off_removes_nothing() {
+        let temp = TempDir::new("cleanup-off");
+        let (releases, downloader) = (FakeReleases::default(), FakeDownloader::new(temp.path()));
+        releases.publish("v0.1.21");
+        releases.publish("v0.1.22");
+        let mut pinned = request();
+        pinned.pinned_version = Some("v0.1.21".to_string());
+        let mut session = extension(&temp, &releases, &downloader);
+        install(&mut session, "typos", &pinned).0.unwrap();
+        install(&mut session, "typos-fork", &request()).0.unwrap();
+        assert!(temp
+            .path()
+            .join(TyposExtension::DEFERRED_CLEANUP_FILE)
+            .exists());
+        drop(session);
+        fn paths(dir: &Path, found: &mut Vec<std::path::PathBuf>) {
+            for entry in fs::read_dir(dir).unwrap() {
+                let path = entry.unwrap().path();
+                if path.is_dir() {
+                    paths(&path, found);
+                }
+                found.push(path);
+            }
+        }
+        let mut before = Vec::new();
+        paths(temp.path(), &mut before);
+
+        let mut off = request();
+        off.cleanup = false;
+        let mut session = extension(&temp, &releases, &downloader);
+        tidy(&mut session, Cleanup::Off);
+        assert!(temp.path().join("typos-lsp-0.1.21").exists());
+
+        // An incomplete download is kept, the installed release is used meanwhile.
+        releases.publish("v0.1.23");
+        downloader.fail_with(Some("connection reset"));
+        let (binary, _) = install(&mut session, "typos", &off);
+        assert_eq!(binary.unwrap().version.as_deref(), Some("v0.1.22"));
+        assert!(temp.path().join("typos-lsp-0.1.23.partial").exists());
 
-    use crate::TyposExtension;
+        // So are the previous versions and the archive entries of no use.
+        downloader.fail_with(None);
+        downloader.extract_to(&["typos-lsp", "LICENSE"]);
+        let (binary, _) = install(&mut session, "typos-other", &off);
+        assert_eq!(binary.unwrap().path, "typos-lsp-0.1.23/typos-lsp");
+        for kept in [
+            "typos-lsp-0.1.21",
+            "typos-lsp-0.1.22",
+            "typos-lsp-0.1.23/LICENSE",
+            TyposExtension::DEFERRED_CLEANUP_FILE,
+        ] {
+            assert!(temp.path().join(kept).exists(), "{kept}");
+        }
+        // Nothing at all was removed.
+        for path in before {
+            assert!(path.exists(), "{}", path.display());
+        }
+    }
 
     #[test]
-    fn release_name() {
+    fn active_installation_survives_tag_variants() {
+        for tag in ["v0.1.23", "0.1.23", "V0.1.23", "v0.1.23+build.7"] {
+            let temp = TempDir::new("tag-variants");
+            let (releases, downloader) =
+                (FakeReleases::default(), FakeDownloader::new(temp.path()));
+            releases.publish(tag);
+            fs::create_dir(temp.path().join("typos-lsp-0.1.22")).unwrap();
+            let mut extension = extension(&temp, &releases, &downloader);
+
+            let (binary, _) = install(&mut extension, "typos", &request());
+            assert_eq!(binary.unwrap().path, "typos-lsp-0.1.23/typos-lsp", "{tag}");
+            assert!(!temp.path().join("typos-lsp-0.1.22").exists(), "{tag}");
+
+            // Whichever way the version to keep is spelled, it is the same directory.
+            for spelling in ["v0.1.23", "0.1.23", "V0.1.23", "0.1.23+other"] {
+                let keep = TyposExtension::parse_version(spelling).unwrap();
+                let deferred = TyposExtension::clean_other_installations(
+                    extension.fs.as_ref(),
+                    &keep,
+                    1,
+                    &BTreeSet::new(),
+                )
+                .unwrap();
+                assert!(deferred.is_empty(), "{tag} {spelling}");
+                assert!(
+                    temp.path().join("typos-lsp-0.1.23/typos-lsp").exists(),
+                    "{tag} {spelling}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn dry_run_changes_nothing() {
+        let temp = TempDir::new("dry-run");
+        let (releases, downloader) = (FakeReleases::default(), FakeDownloader::new(temp.path()));
+        releases.publish("v0.1.23");
+        fs::create_dir(temp.path().join("typos-lsp-0.1.21")).unwrap();
+        fs::create_dir(temp.path().join("typos-lsp-0.1.22")).unwrap();
+        let mut extension = extension(&temp, &releases, &downloader);
+        extension
+            .in_use_versions
+            .insert(Version::parse("v0.1.22").unwrap());
+
+        let mut request = request();
+        request.dry_run = true;
+        let (binary, statuses) = install(&mut extension, "typos", &request);
+        let binary = binary.unwrap();
+        assert_eq!(binary.path, "typos-lsp-0.1.23/typos-lsp");
+        assert_eq!(binary.provenance, Provenance::Downloaded);
         assert_eq!(
-            TyposExtension::binary_release_name(
-                &"v0.1.23".to_string(),
-                &Os::Mac,
-                &Architecture::Aarch64
-            ),
-            "typos-lsp-v0.1.23-aarch64-apple-darwin.tar.gz".to_string()
+            binary.skipped,
+            [
+                Step::Download {
+                    url: gnu_url("v0.1.23"),
+                    directory: "typos-lsp-0.1.23".to_string(),
+                },
+                Step::Remove("typos-lsp-0.1.21".to_string()),
+                Step::DeferRemoval("typos-lsp-0.1.22".to_string()),
+            ]
         );
+        assert_eq!(statuses, ["CheckingForUpdate", "None"]);
+        assert!(downloader.downloads().is_empty());
+        assert!(temp.path().join("typos-lsp-0.1.21").exists());
+        assert!(!temp.path().join("typos-lsp-0.1.23").exists());
+        assert!(!temp
+            .path()
+            .join(TyposExtension::DEFERRED_CLEANUP_FILE)
+            .exists());
         assert_eq!(
-            TyposExtension::binary_release_name(
-                &"v0.1.23".to_string(),
-                &Os::Windows,
-                &Architecture::Aarch64
-            ),
-            "typos-lsp-v0.1.23-aarch64-pc-windows-msvc.zip".to_string()
+            extension.server_state("typos", ROOT).cached_binary_path,
+            None
         );
+
+        // Once installed for real, a dry run has nothing left to do.
+        request.dry_run = false;
+        install(&mut extension, "typos", &request).0.unwrap();
+        request.dry_run = true;
+        let (binary, _) = install(&mut extension, "typos-fork", &request);
+        assert!(binary.unwrap().skipped.is_empty());
+    }
+
+    #[test]
+    fn archive_layouts() {
+        let temp = TempDir::new("archive-layouts");
+        let (releases, downloader) = (FakeReleases::default(), FakeDownloader::new(temp.path()));
+        releases.publish("v0.1.23");
+        downloader.extract_to(&[
+            "typos-lsp-v0.1.23-x86_64-unknown-linux-gnu/typos-lsp",
+            "typos-lsp-v0.1.23-x86_64-unknown-linux-gnu/LICENSE",
+            "typos-lsp-v0.1.23-x86_64-unknown-linux-gnu/README.md",
+        ]);
+        let mut nested = extension(&temp, &releases, &downloader);
+        let (binary, _) = install(&mut nested, "typos", &request());
+        assert_eq!(binary.unwrap().path, "typos-lsp-0.1.23/typos-lsp");
+        assert!(temp.path().join("typos-lsp-0.1.23/typos-lsp").is_file());
+        assert!(!temp.path().join("typos-lsp-0.1.23/LICENSE").exists());
+
+        // An archive without the binary is not kept, to be downloaded again.
+        let temp = TempDir::new("archive-without-binary");
+        let downloader = FakeDownloader::new(temp.path());
+        downloader.extract_to(&["LICENSE", "typos"]);
+        let mut broken = extension(&temp, &releases, &downloader);
+        let (binary, statuses) = install(&mut broken, "typos", &request());
         assert_eq!(
-            TyposExtension::binary_release_name(
-                &"v0.1.23".to_string(),
-                &Os::Linux,
-                &Architecture::Aarch64
-            ),
-            "typos-lsp-v0.1.23-aarch64-unknown-linux-gnu.tar.gz".to_string()
+            binary.err().unwrap(),
+            format!(
+                "worktree {ROOT}: the typos-lsp archive has no typos-lsp binary, extracted into \
+                 typos-lsp-0.1.23.partial which holds [\"LICENSE\", \"typos\"]"
+            )
+        );
+        assert_eq!(statuses.last().map(String::as_str), Some("Failed"));
+        assert!(!temp.path().join("typos-lsp-0.1.23").exists());
+        assert!(!temp.path().join("typos-lsp-0.1.23.partial").exists());
+    }
+
+    #[test]
+    fn mirrored_releases() {
+        const API: &str = "https://mirror.corp/api/releases";
+        const BASE: &str = "https://mirror.corp/typos-lsp";
+        let temp = TempDir::new("mirror");
+        let (releases, downloader) = (FakeReleases::default(), FakeDownloader::new(temp.path()));
+        releases.fail_with(Some("github.com is blocked"));
+        let http = FakeHttp::default();
+        let asset = "typos-lsp-v0.1.23-x86_64-unknown-linux-gnu.tar.gz";
+        http.serve(
+            &format!("{API}/latest"),
+            &json!({
+                "tag_name": "v0.1.23",
+                "assets": [{"name": asset, "browser_download_url": gnu_url("v0.1.23")}],
+            })
+            .to_string(),
         );
+        let mut extension = extension(&temp, &releases, &downloader);
+        extension.http = Box::new(http.clone());
+        let mut request = request();
+        request.mirror = Mirror {
+            base_url: Some(BASE.to_string()),
+            release_api_url: Some(API.to_string()),
+        };
+        let (binary, _) = install(&mut extension, "typos", &request);
+        assert_eq!(binary.unwrap().path, "typos-lsp-0.1.23/typos-lsp");
+        assert_eq!(downloader.downloads(), [format!("{BASE}/{asset}")]);
+        assert_eq!(releases.lookups(), 0);
+
+        // A pinned release needs no metadata from a mirror serving its assets.
+        request.mirror.release_api_url = None;
+        request.pinned_version = Some("v0.1.5".to_string());
+        let (binary, _) = install(&mut extension, "typos", &request);
+        assert_eq!(binary.unwrap().path, "typos-lsp-0.1.5/typos-lsp");
         assert_eq!(
-            TyposExtension::binary_release_name(
-                &"v0.1.23".to_string(),
-                &Os::Mac,
-                &Architecture::X86
-            ),
-            "typos-lsp-v0.1.23-x86_64-apple-darwin.tar.gz".to_string()
+            downloader.downloads().last(),
+            Some(&format!(
+                "{BASE}/typos-lsp-v0.1.5-x86_64-unknown-linux-gnu.tar.gz"
+            ))
         );
+        assert_eq!(http.requests(), [format!("{API}/latest")]);
+        assert_eq!(releases.lookups(), 0);
+
+        // Failures name the mirror.
+        downloader.fail_with(Some("connection refused"));
+        request.pinned_version = Some("v0.1.6".to_string());
+        let (binary, _) = install(&mut extension, "typos", &request);
         assert_eq!(
-            TyposExtension::binary_release_name(
-                &"v0.1.23".to_string(),
-                &Os::Windows,
-                &Architecture::X86
-            ),
-            "typos-lsp-v0.1.23-x86_64-pc-windows-msvc.zip".to_string()
+            binary.map(|binary| binary.path),
+            Err(messages::for_worktree(
+                ROOT,
+                TyposExtensionError::Download {
+                    reason: format!(
+                        "connection refused (from the mirror {BASE} of `download.base_url`)"
+                    ),
+                }
+            ))
         );
+        request.mirror.release_api_url = Some(API.to_string());
+        let (binary, _) = install(&mut extension, "typos", &request);
         assert_eq!(
-            TyposExtension::binary_release_name(
-                &"v0.1.23".to_string(),
-                &Os::Linux,
-                &Architecture::X86
-            ),
-            "typos-lsp-v0.1.23-x86_64-unknown-linux-gnu.tar.gz".to_string()
+            binary.map(|binary| binary.path),
+            Err(messages::for_worktree(
+                ROOT,
+                TyposExtensionError::ReleaseLookup {
+                    tag: Some("v0.1.6".to_string()),
+                    reason: format!(
+                        "the release mirror {API}/tags/v0.1.6 of `download.release_api_url` \
+                         failed: 404 Not Found"
+                    ),
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn commands() {
+        let command = |settings| {
+            let settings = WorktreeSettings::resolve(settings, None);
+            let binary = TyposBinary {
+                path: "/usr/bin/typos-lsp".to_string(),
+                version: None,
+                provenance: Provenance::Path,
+                skipped: Vec::new(),
+            };
+            let shell_env = || vec![("PATH".to_string(), "/usr/bin".to_string())];
+            TyposExtension::command(
+                &settings,
+                binary,
+                Path::new("/extensions/work/typos"),
+                &ConfigSource::None,
+                ROOT,
+                Os::Linux,
+                shell_env,
+            )
+            .map(|command| (command.command, command.args, command.env))
+        };
+        let env = |entries: &[(&str, &str)]| {
+            entries
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect::<Vec<_>>()
+        };
+
+        // Without the logging settings, the command is what it always was.
+        let plain = ("/usr/bin/typos-lsp".to_string(), Vec::new(), Vec::new());
+        assert_eq!(command(json!({})), Ok(plain.clone()));
+        assert_eq!(
+            command(json!({"settings": {"log_level": null, "log_file": ""}})),
+            Ok(plain)
+        );
+
+        let (_, _, debug) = command(json!({"settings": {"log_level": "debug"}})).unwrap();
+        assert_eq!(
+            debug,
+            env(&[("PATH", "/usr/bin"), ("RUST_LOG", "typos_lsp=debug")])
         );
+        let (_, _, filtered) = command(json!({"settings": {
+            "log_level": "debug",
+            "env": {"RUST_LOG": "typos_lsp=trace,tower_lsp=debug"},
+        }}))
+        .unwrap();
         assert_eq!(
-            TyposExtension::binary_release_name(
-                &"v0.1.23".to_string(),
-                &Os::Mac,
-                &Architecture::X8664
+            filtered,
+            env(&[
+                ("PATH", "/usr/bin"),
+                ("RUST_LOG", "typos_lsp=trace,tower_lsp=debug")
+            ])
+        );
+        assert_eq!(
+            command(json!({"settings": {"log_level": "loud"}})),
+            Err(
+                "`settings.log_level` is \"loud\", expected one of \"error\", \"warn\", \
+                 \"info\", \"debug\", \"trace\""
+                    .to_string()
+            )
+        );
+
+        let (shell, args, env) =
+            command(json!({"settings": {"log_file": "typos-lsp.log"}})).unwrap();
+        assert_eq!(shell, "/bin/sh");
+        assert_eq!(
+            args[3..],
+            [
+                format!("{ROOT}/typos-lsp.log"),
+                "/usr/bin/typos-lsp".to_string()
+            ]
+        );
+        assert!(env.is_empty());
+    }
+
+    #[test]
+    fn installed_binaries_start_from_the_work_dir() {
+        let settings = WorktreeSettings::resolve(json!({}), None);
+        let command = |path: &str, provenance| {
+            let binary = TyposBinary {
+                path: path.to_string(),
+                version: Some("v0.1.23".to_string()),
+                provenance,
+                skipped: Vec::new(),
+            };
+            TyposExtension::command(
+                &settings,
+                binary,
+                Path::new("/extensions/work/typos"),
+                &ConfigSource::None,
+                ROOT,
+                Os::Linux,
+                Vec::new,
+            )
+            .unwrap()
+            .command
+        };
+        for provenance in [Provenance::Cached, Provenance::Downloaded] {
+            assert_eq!(
+                command("typos-lsp-0.1.23/typos-lsp", provenance),
+                "/extensions/work/typos/typos-lsp-0.1.23/typos-lsp"
+            );
+        }
+        // Binaries found elsewhere start as they were found.
+        assert_eq!(
+            command("/usr/bin/typos-lsp", Provenance::Path),
+            "/usr/bin/typos-lsp"
+        );
+        assert_eq!(command("typos-lsp", Provenance::Settings), "typos-lsp");
+    }
+
+    #[test]
+    fn cached_binaries_outside_the_work_dir_are_not_started() {
+        let (temp, elsewhere) = (TempDir::new("cache-outside"), TempDir::new("elsewhere"));
+        let (releases, downloader) = (FakeReleases::default(), FakeDownloader::new(temp.path()));
+        releases.publish("v0.1.23");
+        let mut extension = extension(&temp, &releases, &downloader);
+        install(&mut extension, "typos", &request()).0.unwrap();
+
+        let outside = elsewhere.path().join("typos-lsp");
+        fs::write(&outside, "").unwrap();
+        extension.server_state("typos", ROOT).cached_binary_path =
+            Some(outside.to_str().unwrap().to_string());
+        let binary = install(&mut extension, "typos", &request()).0.unwrap();
+        assert_eq!(binary.path, "typos-lsp-0.1.23/typos-lsp");
+        assert_eq!(
+            extension.server_state("typos", ROOT).cached_binary_path,
+            Some(binary.path)
+        );
+    }
+
+    #[test]
+    fn user_binaries() {
+        const WORK_DIR: &str = "/extensions/work/typos";
+        let resolve = |settings, on_path: &'static str| {
+            let settings = WorktreeSettings::resolve(settings, None);
+            let which = |name: &str| match name {
+                "typos-lsp" => Some(on_path.to_string()),
+                "/opt/typos-lsp" => Some(name.to_string()),
+                _ => None,
+            };
+            TyposExtension::user_binary(&settings, ROOT, WORK_DIR, &host::Zed, which)
+                .map(|binary| binary.map(|binary| (binary.path, binary.provenance)))
+        };
+
+        // A typos-lsp on the `$PATH` wins over any installation.
+        assert_eq!(
+            resolve(json!({}), "/usr/bin/typos-lsp"),
+            Ok(Some(("/usr/bin/typos-lsp".to_string(), Provenance::Path)))
+        );
+        // Unless it is one, or it is ignored.
+        assert_eq!(
+            resolve(
+                json!({}),
+                "/extensions/work/typos/typos-lsp-0.1.23/typos-lsp"
             ),
-            "typos-lsp-v0.1.23-x86_64-apple-darwin.tar.gz".to_string()
+            Ok(None)
         );
         assert_eq!(
-            TyposExtension::binary_release_name(
-                &"v0.1.23".to_string(),
-                &Os::Windows,
-                &Architecture::X8664
+            resolve(
+                json!({"settings": {"ignore_system_version": true}}),
+                "/usr/bin/typos-lsp"
             ),
-            "typos-lsp-v0.1.23-x86_64-pc-windows-msvc.zip".to_string()
+            Ok(None)
         );
+        // `binary.path` wins over the `$PATH`, and must be found.
         assert_eq!(
-            TyposExtension::binary_release_name(
-                &"v0.1.23".to_string(),
-                &Os::Linux,
-                &Architecture::X8664
+            resolve(
+                json!({"binary": {"path": "/opt/typos-lsp"}}),
+                "/usr/bin/typos-lsp"
             ),
-            "typos-lsp-v0.1.23-x86_64-unknown-linux-gnu.tar.gz".to_string()
+            Ok(Some(("/opt/typos-lsp".to_string(), Provenance::Settings)))
         );
+        assert!(resolve(
+            json!({"binary": {"path": "/opt/missing/typos-lsp"}}),
+            "/usr/bin/typos-lsp"
+        )
+        .is_err());
     }
 
     #[test]
-    #[cfg(target_os = "linux")]
-    fn binary_name_within_extension() {
+    fn user_binaries_are_run_when_processes_can_be() {
+        let temp = TempDir::new("user-binary-identity");
+        let runner = FakeRunner::new(temp.path());
+        let binary = temp.path().join("typos-wrapper");
+        let path = binary.to_str().unwrap().to_string();
+        let settings = WorktreeSettings::resolve(json!({"binary": {"path": path}}), None);
+        let resolve = |content: &str| {
+            fs::write(&binary, content).unwrap();
+            TyposExtension::user_binary(&settings, ROOT, "/extensions/work/typos", &runner, |_| {
+                Some(path.clone())
+            })
+            .map(|binary| binary.map(|binary| binary.provenance))
+        };
+
         assert_eq!(
-            TyposExtension::binary_path_within_archive(&Os::Mac, &Architecture::X8664),
-            "typos-lsp".to_string()
+            resolve("typos-lsp 0.1.23\n"),
+            Ok(Some(Provenance::Settings))
         );
         assert_eq!(
-            TyposExtension::binary_path_within_archive(&Os::Windows, &Architecture::X8664),
-            "target/x86_64-pc-windows-msvc/release/typos-lsp.exe".to_string()
+            resolve("typos-cli 1.16.0\n"),
+            Err(binary_identity::mistaken_cli(&path).into())
+        );
+        assert_eq!(runner.runs(), [path.as_str(), path.as_str()]);
+    }
+
+    #[test]
+    fn packaging_fixtures() {
+        for fixture in archive_fixtures::FIXTURES {
+            let layout = fixture.layout;
+            let temp = TempDir::new("packaging-fixture");
+            let (releases, downloader) =
+                (FakeReleases::default(), FakeDownloader::new(temp.path()));
+            fixture.serve(&releases, &downloader);
+            let mut request = request();
+            request.platform = fixture.platform;
+            request.architecture = fixture.architecture;
+            request.pinned_version = Some(fixture.tag.to_string());
+            let dir = Version::parse(fixture.tag).unwrap().dir_name();
+            let expected = fixture
+                .binary
+                .iter()
+                .fold(Path::new(&dir).to_path_buf(), |path, part| path.join(part));
+
+            let (binary, _) = install(
+                &mut extension(&temp, &releases, &downloader),
+                "typos",
+                &request,
+            );
+            let binary = binary.unwrap_or_else(|e| panic!("{layout}: {e}"));
+            assert_eq!(Path::new(&binary.path), expected, "{layout}");
+            assert_eq!(binary.provenance, Provenance::Downloaded, "{layout}");
+            assert!(temp.path().join(&binary.path).is_file(), "{layout}");
+            assert!(!temp.path().join(&dir).join("LICENSE").exists(), "{layout}");
+            let manifest = Manifest::read(&StdFs::new(temp.path()), Path::new(&dir)).unwrap();
+            assert_eq!(manifest.asset, fixture.asset, "{layout}");
+
+            // Found again by the next session, without another download.
+            let (binary, _) = install(
+                &mut extension(&temp, &releases, &downloader),
+                "typos",
+                &request,
+            );
+            let binary = binary.unwrap_or_else(|e| panic!("{layout}: {e}"));
+            assert_eq!(Path::new(&binary.path), expected, "{layout}");
+            assert_eq!(binary.provenance, Provenance::Cached, "{layout}");
+            assert_eq!(downloader.downloads(), [fixture.url()], "{layout}");
+        }
+    }
+
+    /// An extension whose session started at `start`, with a clock set to `now`.
+    fn session(
+        temp: &TempDir,
+        releases: &FakeReleases,
+        downloader: &FakeDownloader,
+        start: u64,
+        now: &Arc<AtomicU64>,
+    ) -> TyposExtension {
+        let mut extension = extension(temp, releases, downloader);
+        let clock = now.clone();
+        extension.clock = Box::new(move || clock.load(Ordering::SeqCst));
+        extension.session_start = start;
+        now.store(start, Ordering::SeqCst);
+        extension
+    }
+
+    #[test]
+    fn update_checks_are_throttled() {
+        const HOUR: u64 = 3600;
+        let temp = TempDir::new("update-check-interval");
+        let (releases, downloader) = (FakeReleases::default(), FakeDownloader::new(temp.path()));
+        releases.publish("v0.1.22");
+        let mut request = request();
+        request.update_check_interval = Duration::from_secs(24 * HOUR);
+
+        let now = Arc::new(AtomicU64::new(0));
+        let mut extension = session(&temp, &releases, &downloader, 1000, &now);
+        install(&mut extension, "typos", &request).0.unwrap();
+        assert_eq!(releases.lookups(), 1);
+        releases.publish("v0.1.23");
+
+        // A later session within the interval starts the release found then.
+        let mut extension = session(&temp, &releases, &downloader, 1000 + HOUR, &now);
+        let (binary, statuses) = install(&mut extension, "typos", &request);
+        let binary = binary.unwrap();
+        assert_eq!(binary.path, "typos-lsp-0.1.22/typos-lsp");
+        assert_eq!(binary.provenance, Provenance::Cached);
+        assert!(statuses.is_empty());
+        assert_eq!(releases.lookups(), 1);
+
+        // Once the interval is over, the latest release is looked up again.
+        let mut extension = session(&temp, &releases, &downloader, 1000 + 24 * HOUR, &now);
+        let (binary, _) = install(&mut extension, "typos", &request);
+        assert_eq!(binary.unwrap().path, "typos-lsp-0.1.23/typos-lsp");
+        assert_eq!(releases.lookups(), 2);
+        assert_eq!(
+            LastCheck::read(&StdFs::new(temp.path())),
+            Some(LastCheck {
+                version: "v0.1.23".to_string(),
+                at: 1000 + 24 * HOUR,
+                pre_release: false,
+            })
+        );
+
+        // Without the release found installed, it is looked up regardless.
+        fs::remove_dir_all(temp.path().join("typos-lsp-0.1.23")).unwrap();
+        let mut extension = session(&temp, &releases, &downloader, 1001 + 24 * HOUR, &now);
+        let (binary, _) = install(&mut extension, "typos", &request);
+        assert_eq!(binary.unwrap().provenance, Provenance::Downloaded);
+        assert_eq!(releases.lookups(), 3);
+    }
+
+    #[test]
+    fn warm_start() {
+        const LINUX: (Os, Architecture) = (Os::Linux, Architecture::X8664);
+        let temp = TempDir::new("warm-start");
+        let (releases, downloader) = (FakeReleases::default(), FakeDownloader::new(temp.path()));
+        releases.publish("v0.1.23");
+        let mut request = request();
+        request.update_check_interval = Duration::from_secs(3600);
+        let now = Arc::new(AtomicU64::new(0));
+        let mut extension = session(&temp, &releases, &downloader, 1000, &now);
+        tidy(&mut extension, Cleanup::Auto);
+        install(&mut extension, "typos", &request).0.unwrap();
+
+        // Loading reads the work directory, and nothing that needs a worktree.
+        let mut extension = session(&temp, &releases, &downloader, 1001, &now);
+        extension.warm_up(LINUX);
+        let warmup = extension.warmup.as_ref().unwrap();
+        assert_eq!(
+            warmup
+                .last_check
+                .as_ref()
+                .map(|check| check.version.as_str()),
+            Some("v0.1.23")
+        );
+        assert_eq!(warmup.versions().count(), 1);
+        assert!(extension.servers.is_empty());
+        assert!(!extension.tidied);
+        tidy(&mut extension, Cleanup::Auto);
+        let (binary, statuses) = install(&mut extension, "typos", &request);
+        assert_eq!(binary.unwrap().provenance, Provenance::Cached);
+        assert!(statuses.is_empty());
+        assert_eq!(releases.lookups(), 1);
+        // Later resolutions read the work directory again.
+        assert!(extension.warmup.is_none());
+
+        // A corrupted binary is installed again by the first resolution.
+        fs::write(temp.path().join("typos-lsp-0.1.23/typos-lsp"), "corrupted").unwrap();
+        let mut extension = session(&temp, &releases, &downloader, 1002, &now);
+        extension.warm_up(LINUX);
+        let (binary, _) = install(&mut extension, "typos", &request);
+        assert_eq!(binary.unwrap().provenance, Provenance::Downloaded);
+        assert_eq!(downloader.downloads().len(), 2);
+        assert_eq!(
+            fs::read_to_string(temp.path().join("typos-lsp-0.1.23/typos-lsp")).unwrap(),
+            gnu_url("v0.1.23")
+        );
+
+        // Nor is the scan used for a worktree running elsewhere, e.g. in a container.
+        fs::write(temp.path().join("typos-lsp-0.1.23/typos-lsp"), "corrupted").unwrap();
+        let mut extension = session(&temp, &releases, &downloader, 1003, &now);
+        extension.warm_up((Os::Mac, Architecture::Aarch64));
+        let (binary, _) = install(&mut extension, "typos", &request);
+        assert_eq!(binary.unwrap().provenance, Provenance::Cached);
+        assert_eq!(downloader.downloads().len(), 2);
+    }
+
+    #[test]
+    fn pre_releases_are_opt_in() {
+        let temp = TempDir::new("pre-releases");
+        let (releases, downloader) = (FakeReleases::default(), FakeDownloader::new(temp.path()));
+        releases.publish("v0.1.23");
+        releases.publish("v0.2.0-rc.1+build.4");
+        let mut stable = request();
+        stable.update_check_interval = Duration::from_secs(3600);
+        let mut pre_release = request();
+        pre_release.update_check_interval = Duration::from_secs(3600);
+        pre_release.prefer_prerelease = true;
+
+        let mut extension = extension(&temp, &releases, &downloader);
+        let (binary, _) = install(&mut extension, "typos", &stable);
+        assert_eq!(binary.unwrap().path, "typos-lsp-0.1.23/typos-lsp");
+
+        // The stable release checked recently does not hold the pre-release back.
+        let mut extension = self::extension(&temp, &releases, &downloader);
+        let (binary, _) = install(&mut extension, "typos", &pre_release);
+        let binary = binary.unwrap();
+        assert_eq!(binary.path, "typos-lsp-0.2.0-rc.1/typos-lsp");
+        assert_eq!(binary.version.as_deref(), Some("v0.2.0-rc.1+build.4"));
+        assert!(!temp.path().join("typos-lsp-0.1.23").exists());
+
+        // Turned off, the stable release replaces it, even in the same session and with
+        // the pre-release being newer.
+        let (binary, _) = install(&mut extension, "typos", &stable);
+        let binary = binary.unwrap();
+        assert_eq!(binary.path, "typos-lsp-0.1.23/typos-lsp");
+        assert_eq!(binary.provenance, Provenance::Downloaded);
+        assert_eq!(releases.lookups(), 3);
+        drop(extension);
+        let mut extension = self::extension(&temp, &releases, &downloader);
+        tidy(&mut extension, Cleanup::Auto);
+        assert!(!temp.path().join("typos-lsp-0.2.0-rc.1").exists());
+        let (binary, _) = install(&mut extension, "typos", &stable);
+        assert_eq!(binary.unwrap().provenance, Provenance::Cached);
+        assert_eq!(releases.lookups(), 3);
+
+        // Deferred updates do not start an installed pre-release either.
+        install(&mut extension, "typos-fork", &pre_release)
+            .0
+            .unwrap();
+        assert!(temp.path().join("typos-lsp-0.2.0-rc.1").exists());
+        stable.update_timing = UpdateTiming::Deferred;
+        let mut extension = self::extension(&temp, &releases, &downloader);
+        let (binary, _) = install(&mut extension, "typos", &stable);
+        let binary = binary.unwrap();
+        assert_eq!(binary.path, "typos-lsp-0.1.23/typos-lsp");
+        assert_eq!(binary.provenance, Provenance::Cached);
+    }
+
+    #[test]
+    fn deferred_updates() {
+        let temp = TempDir::new("deferred-updates");
+        let (releases, downloader) = (FakeReleases::default(), FakeDownloader::new(temp.path()));
+        releases.publish("v0.1.22");
+        install(
+            &mut extension(&temp, &releases, &downloader),
+            "typos",
+            &request(),
+        )
+        .0
+        .unwrap();
+        releases.publish("v0.1.23");
+
+        let now = Arc::new(AtomicU64::new(0));
+        let mut extension = session(&temp, &releases, &downloader, 1000, &now);
+        let mut request = request();
+        request.update_timing = UpdateTiming::Deferred;
+        let (binary, statuses) = install(&mut extension, "typos", &request);
+        let binary = binary.unwrap();
+        assert_eq!(binary.path, "typos-lsp-0.1.22/typos-lsp");
+        assert_eq!(binary.version.as_deref(), Some("v0.1.22"));
+        assert_eq!(binary.provenance, Provenance::Cached);
+        assert!(statuses.is_empty());
+        assert_eq!(releases.lookups(), 1);
+        assert_eq!(
+            PendingUpdate::read(&StdFs::new(temp.path())),
+            Some(PendingUpdate { since: 1000 })
+        );
+
+        // Within the grace period, the installed release is kept.
+        now.store(
+            1000 + update_timing::GRACE_PERIOD.as_secs() - 1,
+            Ordering::SeqCst,
+        );
+        let (binary, _) = install(&mut extension, "typos", &request);
+        assert_eq!(binary.unwrap().path, "typos-lsp-0.1.22/typos-lsp");
+        assert_eq!(releases.lookups(), 1);
+
+        now.store(
+            1000 + update_timing::GRACE_PERIOD.as_secs(),
+            Ordering::SeqCst,
+        );
+        let (binary, _) = install(&mut extension, "typos", &request);
+        let binary = binary.unwrap();
+        assert_eq!(binary.path, "typos-lsp-0.1.23/typos-lsp");
+        assert_eq!(binary.provenance, Provenance::Downloaded);
+        assert_eq!(PendingUpdate::read(&StdFs::new(temp.path())), None);
+        // The server started with the previous release may still be running.
+        assert!(temp.path().join("typos-lsp-0.1.22").exists());
+    }
+
+    #[test]
+    fn deferred_update_on_restart() {
+        let temp = TempDir::new("deferred-restart");
+        let (releases, downloader) = (FakeReleases::default(), FakeDownloader::new(temp.path()));
+        releases.publish("v0.1.22");
+        install(
+            &mut extension(&temp, &releases, &downloader),
+            "typos",
+            &request(),
+        )
+        .0
+        .unwrap();
+        releases.publish("v0.1.23");
+        let mut request = request();
+        request.update_timing = UpdateTiming::Deferred;
+
+        let now = Arc::new(AtomicU64::new(0));
+        let mut first = session(&temp, &releases, &downloader, 1000, &now);
+        let (binary, _) = install(&mut first, "typos", &request);
+        assert_eq!(binary.unwrap().path, "typos-lsp-0.1.22/typos-lsp");
+
+        // The next session updates right away, well within the grace period.
+        let mut second = session(&temp, &releases, &downloader, 1060, &now);
+        let (binary, _) = install(&mut second, "typos", &request);
+        assert_eq!(binary.unwrap().path, "typos-lsp-0.1.23/typos-lsp");
+        assert_eq!(PendingUpdate::read(&StdFs::new(temp.path())), None);
+    }
+
+    #[test]
+    fn deferred_update_without_installation() {
+        let temp = TempDir::new("deferred-missing");
+        let (releases, downloader) = (FakeReleases::default(), FakeDownloader::new(temp.path()));
+        releases.publish("v0.1.23");
+        let now = Arc::new(AtomicU64::new(0));
+        let mut extension = session(&temp, &releases, &downloader, 1000, &now);
+        let mut request = request();
+        request.update_timing = UpdateTiming::Deferred;
+        let (binary, _) = install(&mut extension, "typos", &request);
+        assert_eq!(binary.unwrap().path, "typos-lsp-0.1.23/typos-lsp");
+        assert_eq!(downloader.downloads(), [gnu_url("v0.1.23")]);
+        assert_eq!(PendingUpdate::read(&StdFs::new(temp.path())), None);
+
+        // An installation of another build is not used instead of looking up releases.
+        releases.publish("v0.1.24");
+        request.asset_preference = vec![LinuxLibc::Musl];
+        let (binary, _) = install(&mut extension, "typos-musl", &request);
+        assert_eq!(binary.unwrap().path, "typos-lsp-0.1.24/typos-lsp");
+        assert_eq!(
+            downloader.downloads().last().map(String::as_str),
+            Some("https://example.com/typos-lsp-v0.1.24-x86_64-unknown-linux-musl.tar.gz")
+        );
+    }
+
+    #[test]
+    fn auto_update_off() {
+        let temp = TempDir::new("auto-update-off");
+        let (releases, downloader) = (FakeReleases::default(), FakeDownloader::new(temp.path()));
+        releases.publish("v0.1.22");
+        let mut request = request();
+        request.auto_update = false;
+        // Nothing is installed yet, so the latest release is.
+        let (binary, _) = install(
+            &mut extension(&temp, &releases, &downloader),
+            "typos",
+            &request,
+        );
+        assert_eq!(binary.unwrap().provenance, Provenance::Downloaded);
+        assert_eq!(releases.lookups(), 1);
+
+        releases.publish("v0.1.23");
+        let mut extension = extension(&temp, &releases, &downloader);
+        tidy(&mut extension, Cleanup::Auto);
+        let (binary, statuses) = install(&mut extension, "typos", &request);
+        let binary = binary.unwrap();
+        assert_eq!(binary.path, "typos-lsp-0.1.22/typos-lsp");
+        assert_eq!(binary.provenance, Provenance::Cached);
+        assert!(statuses.is_empty(), "{statuses:?}");
+        assert_eq!(releases.lookups(), 1);
+
+        // Turned back on, the next start updates.
+        request.auto_update = true;
+        let (binary, _) = install(&mut extension, "typos", &request);
+        let binary = binary.unwrap();
+        assert_eq!(binary.path, "typos-lsp-0.1.23/typos-lsp");
+        assert_eq!(binary.provenance, Provenance::Downloaded);
+        assert_eq!(
+            downloader.downloads(),
+            [gnu_url("v0.1.22"), gnu_url("v0.1.23")]
+        );
+    }
+
+    #[test]
+    fn clean_up() {
+        let temp = TempDir::new("clean-up");
+        let (root, work_dir) = (temp.path().join("project"), temp.path().join("work"));
+        fs::create_dir_all(&root).unwrap();
+        fs::create_dir_all(&work_dir).unwrap();
+        // The work directory is what the extension was given, never the current one.
+        assert_ne!(std::env::current_dir().unwrap(), work_dir);
+        let extension = TyposExtension::with_host(
+            Box::new(FakeReleases::default()),
+            Box::new(FakeDownloader::new(&work_dir)),
+            Box::new(StdFs::new(&work_dir)),
+            work_dir.clone(),
+        );
+        let root_path = root.to_str().unwrap();
+        typos_config::write(extension.fs.as_ref(), root_path, "[default]\n").unwrap();
+        for path in [lockfile::FILE_NAME, "edited.toml"] {
+            worktree_files::write_if_changed(
+                &Consent::Granted,
+                &StdFs::new(&root),
+                extension.fs.as_ref(),
+                &root,
+                path,
+                "generated\n",
+                None,
+            )
+            .unwrap();
+        }
+        fs::write(root.join("edited.toml"), "mine\n").unwrap();
+        assert!(work_dir.join(worktree_files::REGISTRY_FILE).exists());
+        assert!(!Path::new(worktree_files::REGISTRY_FILE).exists());
+
+        let read = |path: &str| fs::read_to_string(root.join(path)).ok();
+        assert_eq!(
+            extension.clean_up(root_path, read),
+            format!(
+                "cleaned up {root_path}, typos-lsp was not started: removed {}, {}; kept {}, \
+                 changed since the extension wrote them. Unset `remove_worktree_artifacts` to \
+                 start typos-lsp again",
+                root.join(lockfile::FILE_NAME).display(),
+                work_dir.join(typos_config::path(root_path)).display(),
+                root.join("edited.toml").display(),
+            )
+        );
+        assert!(!root.join(lockfile::FILE_NAME).exists());
+        assert!(root.join("edited.toml").exists());
+        assert_eq!(
+            fs::read_to_string(work_dir.join(worktree_files::REGISTRY_FILE)).unwrap(),
+            ""
+        );
+        assert!(!Path::new(worktree_files::REGISTRY_FILE).exists());
+        assert_eq!(
+            extension.clean_up(root_path, read),
+            format!(
+                "cleaned up {root_path}, typos-lsp was not started: nothing to remove. Unset \
+                 `remove_worktree_artifacts` to start typos-lsp again"
+            )
+        );
+    }
+
+    #[test]
+    fn low_disk_space_cleans_up_first() {
+        const MIB: u64 = 1024 * 1024;
+        let temp = TempDir::new("low-space");
+        let (releases, downloader) = (FakeReleases::default(), FakeDownloader::new(temp.path()));
+        releases.publish("v0.1.23");
+        for old in ["typos-lsp-0.1.21", "typos-lsp-0.1.22"] {
+            fs::create_dir(temp.path().join(old)).unwrap();
+        }
+
+        // Enough room once the version no server runs is removed.
+        let space = SpaceFs::new(temp.path(), 25 * MIB, 20 * MIB);
+        let mut extension = TyposExtension::with_host(
+            Box::new(releases.clone()),
+            Box::new(downloader.clone()),
+            Box::new(space.clone()),
+            temp.path().to_path_buf(),
+        );
+        extension
+            .in_use_versions
+            .insert(Version::parse("v0.1.22").unwrap());
+        let (binary, _) = install(&mut extension, "typos", &request());
+        assert_eq!(binary.unwrap().provenance, Provenance::Downloaded);
+        assert_eq!(
+            space.events(),
+            [
+                format!("available {}", 25 * MIB),
+                "remove typos-lsp-0.1.21".to_string(),
+                format!("available {}", 45 * MIB),
+            ]
+        );
+        assert_eq!(downloader.downloads(), [gnu_url("v0.1.23")]);
+        assert!(temp.path().join("typos-lsp-0.1.22").exists());
+    }
+
+    #[test]
+    fn unknown_disk_space_still_discards_a_failed_extraction() {
+        let temp = TempDir::new("unknown-space");
+        let (releases, downloader) = (FakeReleases::default(), FakeDownloader::new(temp.path()));
+        releases.publish("v0.1.23");
+        let mut extension = extension(&temp, &releases, &downloader);
+        // As in Zed's extension runtime, so the check before the download is skipped.
+        assert_eq!(extension.fs.available_space(), None);
+
+        downloader.fail_with(Some("No space left on device (os error 28)"));
+        let (binary, statuses) = install(&mut extension, "typos", &request());
+        assert_eq!(
+            binary.map(|binary| binary.path),
+            Err(messages::for_worktree(
+                ROOT,
+                TyposExtensionError::Download {
+                    reason: "No space left on device (os error 28)".to_string(),
+                }
+            ))
+        );
+        assert_eq!(statuses.last().map(String::as_str), Some("Failed"));
+        assert!(!temp.path().join("typos-lsp-0.1.23.partial").exists());
+        assert!(!temp.path().join("typos-lsp-0.1.23").exists());
+    }
+
+    #[test]
+    fn insufficient_disk_space() {
+        const MIB: u64 = 1024 * 1024;
+        let temp = TempDir::new("no-space");
+        let (releases, downloader) = (FakeReleases::default(), FakeDownloader::new(temp.path()));
+        releases.publish("v0.1.23");
+        fs::create_dir(temp.path().join("typos-lsp-0.1.22")).unwrap();
+
+        let space = SpaceFs::new(temp.path(), 5 * MIB, 10 * MIB);
+        let mut extension = TyposExtension::with_host(
+            Box::new(releases.clone()),
+            Box::new(downloader.clone()),
+            Box::new(space.clone()),
+            temp.path().to_path_buf(),
+        );
+        let (binary, statuses) = install(&mut extension, "typos", &request());
+        assert_eq!(
+            binary.map(|binary| binary.path),
+            Err(
+                "worktree /work/project: not enough disk space to install typos-lsp v0.1.23: \
+                 about 40.0 MiB are needed in the extension directory, 15.0 MiB are available \
+                 once previous versions are removed"
+                    .to_string()
+            )
+        );
+        assert_eq!(statuses, ["CheckingForUpdate", "Downloading", "Failed"]);
+        assert_eq!(
+            space.events(),
+            [
+                format!("available {}", 5 * MIB),
+                "remove typos-lsp-0.1.22".to_string(),
+                format!("available {}", 15 * MIB),
+            ]
+        );
+        assert!(downloader.downloads().is_empty());
+
+        // With plenty of space, the check does not clean up before the download.
+        let space = SpaceFs::new(temp.path(), 100 * MIB, 0);
+        let mut extension = TyposExtension::with_host(
+            Box::new(releases.clone()),
+            Box::new(downloader.clone()),
+            Box::new(space.clone()),
+            temp.path().to_path_buf(),
+        );
+        install(&mut extension, "typos", &request()).0.unwrap();
+        assert_eq!(space.events(), [format!("available {}", 100 * MIB)]);
+    }
+
+    #[test]
+    #[cfg(not(feature = "x86-as-x86_64"))]
+    fn x86_hosts_need_an_i686_build() {
+        let temp = TempDir::new("x86-hosts");
+        let (releases, downloader) = (FakeReleases::default(), FakeDownloader::new(temp.path()));
+        releases.publish("v0.1.23");
+        let mut extension = extension(&temp, &releases, &downloader);
+        let mut request = request();
+        request.architecture = Architecture::X86;
+
+        // The x86_64 builds are not handed out instead.
+        let (binary, statuses) = install(&mut extension, "typos", &request);
+        let error = binary.map(|binary| binary.path).unwrap_err();
+        assert_eq!(
+            error,
+            format!(
+                "worktree {ROOT}: 32-bit x86 is not supported by typos-lsp releases: v0.1.23 has \
+                 no i686 build. Set `binary.path` to a typos-lsp built for this host"
+            )
+        );
+        assert_eq!(statuses.last().map(String::as_str), Some("Failed"));
+        assert!(downloader.downloads().is_empty());
+
+        releases.publish_asset("v0.1.23", "typos-lsp-v0.1.23-i686-unknown-linux-gnu.tar.gz");
+        let (binary, _) = install(&mut extension, "typos", &request);
+        assert_eq!(binary.unwrap().path, "typos-lsp-0.1.23/typos-lsp");
+        assert_eq!(
+            downloader.downloads(),
+            ["https://example.com/typos-lsp-v0.1.23-i686-unknown-linux-gnu.tar.gz"]
+        );
+    }
+
+    #[test]
+    fn assets_follow_the_host_of_the_worktree() {
+        let assets = [
+            asset("typos-lsp-v0.1.23-aarch64-apple-darwin.tar.gz"),
+            asset("typos-lsp-v0.1.23-x86_64-unknown-linux-gnu.tar.gz"),
+        ];
+        let env = |vars: &[(&str, &str)]| {
+            vars.iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect::<Vec<_>>()
+        };
+        let mac = (Os::Mac, Architecture::Aarch64);
+        let select = |shell_env: &[(String, String)], root: &str| {
+            let (platform, architecture) =
+                ExecutionContext::detect(shell_env, root, mac).install_platform(mac)?;
+            TyposExtension::select_asset(
+                &assets,
+                "v0.1.23",
+                &platform,
+                &architecture,
+                &[LinuxLibc::Gnu],
+            )
+            .map(|(asset, _)| asset.name.clone())
+        };
+
+        // An SSH session on a host of the same platform installs for it.
+        let same_platform = env(&[
+            ("SSH_CONNECTION", "10.0.0.1 5000 10.0.0.2 22"),
+            ("OSTYPE", "darwin23"),
+            ("HOSTTYPE", "arm64"),
+        ]);
+        assert_eq!(
+            select(&same_platform, "/Users/me/project"),
+            Ok("typos-lsp-v0.1.23-aarch64-apple-darwin.tar.gz".to_string())
         );
+
+        // A Linux host gets no binary downloaded for the Mac running Zed, but an error
+        // telling to install typos-lsp there.
+        let linux_host = env(&[
+            ("SSH_CONNECTION", "10.0.0.1 5000 10.0.0.2 22"),
+            ("HOSTTYPE", "x86_64"),
+        ]);
+        assert_eq!(
+            select(&linux_host, "/home/me/project"),
+            Err(
+                "the worktree runs on a remote host 10.0.0.2 (Linux, x86_64): `remote install` \
+                 requires installing on a remote host, which no Zed extension API supports \
+                 yet, install typos-lsp on the remote host and make it available on its PATH"
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn asset_name_bypasses_detection() {
+        let temp = TempDir::new("asset-name");
+        let (releases, downloader) = (FakeReleases::default(), FakeDownloader::new(temp.path()));
+        releases.publish("v0.1.23");
+        let mut extension = extension(&temp, &releases, &downloader);
+
+        let mut request = request();
+        request.asset_name = Some("typos-lsp-{version}-x86_64-unknown-linux-musl.tar.gz".into());
+        let (binary, _) = install(&mut extension, "typos", &request);
+        assert_eq!(binary.unwrap().path, "typos-lsp-0.1.23/typos-lsp");
+        assert_eq!(
+            downloader.downloads(),
+            ["https://example.com/typos-lsp-v0.1.23-x86_64-unknown-linux-musl.tar.gz"]
+        );
+        let manifest =
+            Manifest::read(&StdFs::new(temp.path()), "typos-lsp-0.1.23".as_ref()).unwrap();
+        assert_eq!(
+            manifest.asset,
+            "typos-lsp-v0.1.23-x86_64-unknown-linux-musl.tar.gz"
+        );
+        assert_eq!(manifest.variant.as_deref(), Some("musl"));
+
+        let (binary, _) = install(&mut extension, "typos", &request);
+        assert_eq!(binary.unwrap().provenance, Provenance::Cached);
+
+        let mut request = self::request();
+        request.asset_name = Some("typos-lsp-{version}-riscv64.tar.gz".into());
+        let (binary, statuses) = install(&mut extension, "typos-fork", &request);
+        let error = binary.map(|binary| binary.path).unwrap_err();
+        assert!(
+            error.starts_with(
+                "worktree /work/project: `asset_name` requires the asset \
+                 typos-lsp-v0.1.23-riscv64.tar.gz"
+            ),
+            "{error}"
+        );
+        assert_eq!(statuses, ["CheckingForUpdate", "Failed"]);
+    }
+
+    #[test]
+    fn worktrees_are_isolated() {
+        let temp = TempDir::new("worktrees");
+        let (releases, downloader) = (FakeReleases::default(), FakeDownloader::new(temp.path()));
+        releases.publish("v0.1.22");
+        releases.publish("v0.1.23");
+        let mut extension = extension(&temp, &releases, &downloader);
+
+        let mut pinned = request();
+        pinned.worktree = "/work/pinned".to_string();
+        pinned.pinned_version = Some("v0.1.22".to_string());
+        let mut broken = request();
+        broken.worktree = "/work/broken".to_string();
+        broken.pinned_version = Some("v9.9.9".to_string());
+
+        let (binary, _) = install(&mut extension, "typos", &pinned);
+        assert_eq!(binary.unwrap().version.as_deref(), Some("v0.1.22"));
+        // The same server in another folder follows its own settings, not the cache.
+        let (binary, _) = install(&mut extension, "typos", &request());
+        assert_eq!(binary.unwrap().version.as_deref(), Some("v0.1.23"));
+
+        let (binary, statuses) = install(&mut extension, "typos", &broken);
+        let error = binary.map(|binary| binary.path).unwrap_err();
+        assert!(
+            error.starts_with("worktree /work/broken: failed to find typos-lsp release v9.9.9"),
+            "{error}"
+        );
+        assert_eq!(statuses, ["CheckingForUpdate", "Failed"]);
+
+        // The failure leaves the other folders alone, which still reuse their binaries,
+        // the first one clearing the failure shown for the server.
+        for (request, version, shown) in [
+            (&pinned, "v0.1.22", &["None"][..]),
+            (&request(), "v0.1.23", &[]),
+        ] {
+            let (binary, statuses) = install(&mut extension, "typos", request);
+            let binary = binary.unwrap();
+            assert_eq!(binary.provenance, Provenance::Cached);
+            assert_eq!(binary.version.as_deref(), Some(version));
+            assert_eq!(statuses, shown);
+        }
+        assert!(extension
+            .server_state("typos", "/work/broken")
+            .cached_binary_path
+            .is_none());
+        let manifest =
+            Manifest::read(&StdFs::new(temp.path()), "typos-lsp-0.1.22".as_ref()).unwrap();
+        assert_eq!(manifest.worktree.as_deref(), Some("/work/pinned"));
+    }
+
+    #[test]
+    fn worktrees_resolve_their_own_binaries() {
+        let temp = TempDir::new("worktree-binaries");
+        let (releases, downloader) = (FakeReleases::default(), FakeDownloader::new(temp.path()));
+        releases.publish("v0.1.22");
+        releases.publish("v0.1.23");
+        let mut extension = extension(&temp, &releases, &downloader);
+
+        // One worktree finds typos-lsp on its `$PATH`, e.g. through direnv, the others
+        // do not and install it.
+        let settings = WorktreeSettings::resolve(json!({}), None);
+        let on_path = |root: &str| {
+            TyposExtension::user_binary(
+                &settings,
+                root,
+                "/extensions/work/typos",
+                &host::Zed,
+                |name| {
+                    (root == "/work/direnv" && name == "typos-lsp")
+                        .then(|| "/work/direnv/.direnv/bin/typos-lsp".to_string())
+                },
+            )
+            .unwrap()
+            .map(|binary| binary.provenance)
+        };
+        assert_eq!(on_path("/work/direnv"), Some(Provenance::Path));
+        assert_eq!(on_path(ROOT), None);
+        assert_eq!(on_path("/work/pinned"), None);
+
+        let mut pinned = request();
+        pinned.worktree = "/work/pinned".to_string();
+        pinned.pinned_version = Some("v0.1.22".to_string());
+        let (latest, _) = install(&mut extension, "typos", &request());
+        let (old, _) = install(&mut extension, "typos", &pinned);
+        assert_eq!(latest.unwrap().path, "typos-lsp-0.1.23/typos-lsp");
+        assert_eq!(old.unwrap().path, "typos-lsp-0.1.22/typos-lsp");
+        for request in [&request(), &pinned] {
+            let (binary, _) = install(&mut extension, "typos", request);
+            assert_eq!(binary.unwrap().provenance, Provenance::Cached);
+        }
+        let lookups = releases.lookups();
+
+        // Asking for another build resolves the binary of that worktree again, alone.
+        let mut musl = request();
+        musl.asset_name = Some("typos-lsp-{version}-x86_64-unknown-linux-musl.tar.gz".into());
+        let (binary, _) = install(&mut extension, "typos", &musl);
+        assert_ne!(binary.unwrap().provenance, Provenance::Cached);
+        assert_eq!(releases.lookups(), lookups + 1);
+        let (binary, _) = install(&mut extension, "typos", &musl);
+        assert_eq!(binary.unwrap().provenance, Provenance::Cached);
+        let (binary, _) = install(&mut extension, "typos", &pinned);
+        assert_eq!(binary.unwrap().provenance, Provenance::Cached);
     }
 }
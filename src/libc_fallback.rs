@@ -0,0 +1,144 @@
+//! Switching to the musl builds of typos-lsp when the glibc ones do not start.
+//!
+//! The glibc builds need a glibc at least as recent as the one they were built against,
+//! which older distributions do not ship. The extension cannot run the binary to find
+//! out, so each start of an installed build is a probe, recorded until Zed asks for the
+//! workspace configuration, which only happens once the server initialized. A server
+//! started again while its probe is still recorded, in this session or a later one,
+//! did not get that far: when it ran a glibc build, from then on the statically linked
+//! musl builds are preferred, unless `asset_preference` is set. Other failed starts are
+//! handled by [`crate::start_failures`].
+
+use std::{collections::BTreeMap, path::Path};
+
+use crate::{host::Fs, log, messages};
+
+/// Records the release whose glibc build failed to start, once one did.
+pub(crate) const FILE_NAME: &str = "libc-fallback.txt";
+
+/// The pending probes, as lines of the release tag, server ID and worktree root,
+/// separated by tabs.
+pub(crate) const PROBES_FILE: &str = "libc-probes.txt";
+
+/// The release whose glibc build failed to start, if one did.
+pub(crate) fn read(fs: &dyn Fs) -> Option<String> {
+    let content = fs.read_to_string(Path::new(FILE_NAME)).ok()?;
+    Some(content.trim().to_string()).filter(|tag| !tag.is_empty())
+}
+
+/// Record that the glibc build of the release `tag` failed to start.
+pub(crate) fn record(fs: &dyn Fs, tag: &str) {
+    if let Err(e) = fs.write(Path::new(FILE_NAME), format!("{tag}\n").as_bytes()) {
+        log::warn(format!(
+            "failed to record the switch to the musl builds of typos-lsp: {e}"
+        ));
+    }
+}
+
+fn read_probes(fs: &dyn Fs) -> BTreeMap<String, String> {
+    fs.read_to_string(Path::new(PROBES_FILE))
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| {
+            let (tag, server) = line.split_once('\t')?;
+            Some((server.to_string(), tag.to_string()))
+        })
+        .collect()
+}
+
+fn write_probes(fs: &dyn Fs, probes: &BTreeMap<String, String>) {
+    if probes.is_empty() {
+        fs.remove_file(Path::new(PROBES_FILE)).ok();
+        return;
+    }
+    let content: String = probes
+        .iter()
+        .flat_map(|(server, tag)| [tag, "\t", server, "\n"])
+        .collect();
+    if let Err(e) = fs.write(Path::new(PROBES_FILE), content.as_bytes()) {
+        log::warn(messages::record_failed("typos-lsp startup probes", e));
+    }
+}
+
+/// The key of the probes of the server `server_id` for the worktree at `root`.
+fn key(server_id: &str, root: &str) -> String {
+    format!("{server_id}\t{root}")
+}
+
+/// Record that the glibc build of the release `tag` starts for a server, replacing
+/// any probe of the server.
+pub(crate) fn start_probe(fs: &dyn Fs, server_id: &str, root: &str, tag: &str) {
+    let mut probes = read_probes(fs);
+    probes.insert(key(server_id, root), tag.to_string());
+    write_probes(fs, &probes);
+}
+
+/// Forget the probe of a server, which initialized or no longer runs a glibc build.
+pub(crate) fn end_probe(fs: &dyn Fs, server_id: &str, root: &str) {
+    let mut probes = read_probes(fs);
+    if probes.remove(&key(server_id, root)).is_some() {
+        write_probes(fs, &probes);
+    }
+}
+
+/// The release of the probe still recorded for a server, which means it failed.
+pub(crate) fn pending_probe(fs: &dyn Fs, server_id: &str, root: &str) -> Option<String> {
+    read_probes(fs).remove(&key(server_id, root))
+}
+
+/// Why the musl builds are used from now on.
+pub(crate) fn notice(tag: &str) -> String {
+    format!(
+        "typos-lsp {tag} stopped before initializing, as the glibc build does when the \
+         system glibc is older than the one it was built against: using the musl builds \
+         from now on. Set `asset_preference` to choose the build yourself, or remove \
+         {FILE_NAME} from the extension directory to try the glibc build again"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{end_probe, pending_probe, read, record, start_probe, PROBES_FILE};
+    use crate::{host::StdFs, test_support::TempDir};
+
+    #[test]
+    fn fallback_round_trip() {
+        let temp = TempDir::new("libc-fallback");
+        let fs = StdFs::new(temp.path());
+        assert_eq!(read(&fs), None);
+        record(&fs, "v0.1.23");
+        assert_eq!(read(&fs).as_deref(), Some("v0.1.23"));
+        std::fs::write(temp.path().join(super::FILE_NAME), "\n").unwrap();
+        assert_eq!(read(&fs), None);
+    }
+
+    #[test]
+    fn probes() {
+        let temp = TempDir::new("libc-probes");
+        let fs = StdFs::new(temp.path());
+        assert_eq!(pending_probe(&fs, "typos", "/work/a"), None);
+
+        start_probe(&fs, "typos", "/work/a", "v0.1.22");
+        start_probe(&fs, "typos", "/work/b", "v0.1.23");
+        start_probe(&fs, "typos", "/work/a", "v0.1.23");
+        assert_eq!(
+            pending_probe(&fs, "typos", "/work/a").as_deref(),
+            Some("v0.1.23")
+        );
+        assert_eq!(
+            std::fs::read_to_string(temp.path().join(PROBES_FILE)).unwrap(),
+            "v0.1.23\ttypos\t/work/a\nv0.1.23\ttypos\t/work/b\n"
+        );
+
+        // Servers end their own probe only.
+        end_probe(&fs, "typos", "/work/a");
+        assert_eq!(pending_probe(&fs, "typos", "/work/a"), None);
+        assert_eq!(
+            pending_probe(&fs, "typos", "/work/b").as_deref(),
+            Some("v0.1.23")
+        );
+        end_probe(&fs, "typos", "/work/b");
+        assert!(!temp.path().join(PROBES_FILE).exists());
+        end_probe(&fs, "typos", "/work/b");
+    }
+}
@@ -0,0 +1,190 @@
+//! `typos-extension.lock`, describing an installation for the whole team to replay.
+
+use std::collections::BTreeMap;
+
+use crate::{hashing, typos_config::quote};
+
+/// The lockfile name, at the worktree root.
+pub(crate) const FILE_NAME: &str = "typos-extension.lock";
+
+const HEADER: &str = "# Generated by the Zed typos extension. Commit it so that every contributor \
+                      installs the same typos-lsp.\n";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Lockfile {
+    /// The release tag.
+    pub version: String,
+    /// The target triple of the installed build.
+    pub target: String,
+    /// The name of the release asset.
+    pub asset: String,
+    /// The SHA-256 digest of the installed binary.
+    pub sha256: String,
+    /// The SHA-256 digests of the configuration files in effect, by worktree path, as
+    /// computed by [`config_digest`].
+    pub config: BTreeMap<String, String>,
+}
+
+/// The digest of a configuration file, with LF line endings so that checkouts
+/// converting them to CRLF lock the same content.
+pub(crate) fn config_digest(content: &str) -> String {
+    hashing::sha256_hex(content.replace("\r\n", "\n").as_bytes())
+}
+
+impl Lockfile {
+    /// Render the lockfile, identical inputs always producing identical bytes.
+    pub(crate) fn render(&self) -> String {
+        let mut output = String::from(HEADER);
+        output.push_str(&format!("version = {}\n", quote(&self.version)));
+        output.push_str(&format!("target = {}\n", quote(&self.target)));
+        output.push_str(&format!("asset = {}\n", quote(&self.asset)));
+        output.push_str(&format!("sha256 = {}\n", quote(&self.sha256)));
+        if !self.config.is_empty() {
+            output.push_str("\n[config]\n");
+            for (path, digest) in &self.config {
+                output.push_str(&format!("{} = {}\n", quote(path), quote(digest)));
+            }
+        }
+        output
+    }
+
+    /// Parse a lockfile rendered by [`Lockfile::render`].
+    pub(crate) fn parse(content: &str) -> Result<Self, String> {
+        let mut top = BTreeMap::new();
+        let mut config = BTreeMap::new();
+        let mut in_config = false;
+        for (number, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if line == "[config]" {
+                in_config = true;
+                continue;
+            }
+            let (key, value) = line
+                .split_once(" = ")
+                .and_then(|(key, value)| Some((unquote(key.trim())?, unquote(value.trim())?)))
+                .ok_or_else(|| format!("{FILE_NAME}:{}: invalid line {line:?}", number + 1))?;
+            if in_config {
+                config.insert(key, value);
+            } else {
+                top.insert(key, value);
+            }
+        }
+        let mut field = |name: &str| {
+            top.remove(name)
+                .ok_or_else(|| format!("{FILE_NAME}: missing `{name}`"))
+        };
+        Ok(Self {
+            version: field("version")?,
+            target: field("target")?,
+            asset: field("asset")?,
+            sha256: field("sha256")?,
+            config,
+        })
+    }
+}
+
+/// Bare keys are accepted as they are, quoted ones are unescaped.
+fn unquote(value: &str) -> Option<String> {
+    let Some(inner) = value.strip_prefix('"') else {
+        return Some(value.to_string());
+    };
+    let inner = inner.strip_suffix('"')?;
+    let mut unquoted = String::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => unquoted.push(match chars.next()? {
+                'n' => '\n',
+                't' => '\t',
+                'u' => {
+                    let code: String = chars.by_ref().take(4).collect();
+                    char::from_u32(u32::from_str_radix(&code, 16).ok()?)?
+                }
+                c => c,
+            }),
+            c => unquoted.push(c),
+        }
+    }
+    Some(unquoted)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::{config_digest, Lockfile};
+
+    fn lockfile() -> Lockfile {
+        Lockfile {
+            version: "v0.1.23".to_string(),
+            target: "x86_64-unknown-linux-gnu".to_string(),
+            asset: "typos-lsp-v0.1.23-x86_64-unknown-linux-gnu.tar.gz".to_string(),
+            sha256: "ab".repeat(32),
+            config: BTreeMap::from([
+                ("_typos.toml".to_string(), "cd".repeat(32)),
+                ("ci/\"odd\".toml".to_string(), "ef".repeat(32)),
+            ]),
+        }
+    }
+
+    #[test]
+    fn render() {
+        assert_eq!(
+            lockfile().render(),
+            format!(
+                "# Generated by the Zed typos extension. Commit it so that every contributor \
+                 installs the same typos-lsp.\n\
+                 version = \"v0.1.23\"\n\
+                 target = \"x86_64-unknown-linux-gnu\"\n\
+                 asset = \"typos-lsp-v0.1.23-x86_64-unknown-linux-gnu.tar.gz\"\n\
+                 sha256 = \"{}\"\n\
+                 \n\
+                 [config]\n\
+                 \"_typos.toml\" = \"{}\"\n\
+                 \"ci/\\\"odd\\\".toml\" = \"{}\"\n",
+                "ab".repeat(32),
+                "cd".repeat(32),
+                "ef".repeat(32),
+            )
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        assert_eq!(Lockfile::parse(&lockfile().render()), Ok(lockfile()));
+
+        let mut odd = lockfile();
+        odd.config
+            .insert("tab\tnew\nline\u{1}.toml".to_string(), "01".repeat(32));
+        let rendered = odd.render();
+        assert!(rendered.contains("\"tab\\tnew\\nline\\u0001.toml\" = "));
+        let parsed = Lockfile::parse(&rendered).unwrap();
+        assert_eq!(parsed, odd);
+        // Rendering what was parsed changes nothing.
+        assert_eq!(parsed.render(), rendered);
+    }
+
+    #[test]
+    fn config_digests_ignore_line_endings() {
+        assert_eq!(
+            config_digest("[default]\r\nlocale = \"en-us\"\r\n"),
+            config_digest("[default]\nlocale = \"en-us\"\n")
+        );
+        assert_ne!(config_digest("a\n"), config_digest("b\n"));
+    }
+
+    #[test]
+    fn invalid() {
+        assert_eq!(
+            Lockfile::parse("version = \"v0.1.23\"\n"),
+            Err("typos-extension.lock: missing `target`".to_string())
+        );
+        assert_eq!(
+            Lockfile::parse("garbage\n"),
+            Err("typos-extension.lock:1: invalid line \"garbage\"".to_string())
+        );
+    }
+}
@@ -0,0 +1,49 @@
+//! Logging for the extension.
+//!
+//! Extensions have no dedicated logging API: whatever is written to stderr ends up
+//! in Zed's log, prefixed here so that lines can be attributed to this extension.
+
+use std::{collections::BTreeSet, fmt::Display, sync::Mutex};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Level {
+    Debug,
+    Info,
+    Warn,
+}
+
+/// Log at a level decided at runtime.
+pub(crate) fn log(level: Level, message: impl Display) {
+    let level = match level {
+        Level::Debug => "debug",
+        Level::Info => "info",
+        Level::Warn => "warning",
+    };
+    eprintln!("[typos] {level}: {message}");
+}
+
+/// Details only useful when investigating an issue.
+pub(crate) fn debug(message: impl Display) {
+    log(Level::Debug, message);
+}
+
+/// Decisions the user may want to know about.
+pub(crate) fn info(message: impl Display) {
+    log(Level::Info, message);
+}
+
+/// Something is off but the extension can carry on.
+pub(crate) fn warn(message: impl Display) {
+    log(Level::Warn, message);
+}
+
+/// Log a message only the first time `key` is seen during this session, returning
+/// whether it was.
+pub(crate) fn once(key: impl Into<String>, level: Level, message: impl Display) -> bool {
+    static SEEN: Mutex<BTreeSet<String>> = Mutex::new(BTreeSet::new());
+    let first = SEEN.lock().map_or(true, |mut seen| seen.insert(key.into()));
+    if first {
+        log(level, message);
+    }
+    first
+}
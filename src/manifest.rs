@@ -0,0 +1,216 @@
+//! The manifest recording how a version directory was installed.
+//!
+//! It is written atomically, and the platform it records keeps an installation copied
+//! from another machine, e.g. with the rest of a home directory, from being started.
+
+use std::path::Path;
+
+use zed_extension_api::{
+    serde_json::{self, json, Value},
+    Architecture, Os,
+};
+
+use crate::{host::Fs, messages};
+
+/// Stored inside each version directory, so that it goes away with it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Manifest {
+    /// The release tag.
+    pub version: String,
+    /// The name of the release asset that was downloaded.
+    pub asset: String,
+    /// The Linux build variant, e.g. `musl`, `None` on other platforms.
+    pub variant: Option<String>,
+    /// The SHA-256 digest of the binary when it was installed.
+    pub sha256: Option<String>,
+    /// The root of the worktree whose settings decided the installation, unknown for
+    /// installations of older extension versions.
+    pub worktree: Option<String>,
+    /// When a server was last started from the directory, in seconds since the Unix
+    /// epoch, unknown until then.
+    pub last_used: Option<u64>,
+    /// How many times in a row a server started from the directory stopped before
+    /// initializing.
+    pub failed_starts: u32,
+    /// The version of the extension that installed it, see [`crate::build_info`],
+    /// unknown for installations of older extension versions.
+    pub extension_version: Option<String>,
+    /// The platform it was installed for, see [`platform`], unknown for installations
+    /// of older extension versions.
+    pub platform: Option<String>,
+}
+
+/// The name of the platform of `os` and `architecture`, as manifests record it.
+pub(crate) fn platform(os: Os, architecture: Architecture) -> String {
+    let os = match os {
+        Os::Mac => "macos",
+        Os::Linux => "linux",
+        Os::Windows => "windows",
+    };
+    let architecture = match architecture {
+        Architecture::Aarch64 => "aarch64",
+        Architecture::X86 => "x86",
+        Architecture::X8664 => "x86_64",
+    };
+    format!("{os}-{architecture}")
+}
+
+impl Manifest {
+    pub(crate) const FILE_NAME: &'static str = ".manifest.json";
+
+    fn to_json(&self) -> Value {
+        json!({
+            "version": self.version,
+            "asset": self.asset,
+            "variant": self.variant,
+            "sha256": self.sha256,
+            "worktree": self.worktree,
+            "last_used": self.last_used,
+            "failed_starts": self.failed_starts,
+            "extension_version": self.extension_version,
+            "platform": self.platform,
+        })
+    }
+
+    fn from_json(value: &Value) -> Option<Self> {
+        Some(Self {
+            version: value.get("version")?.as_str()?.to_string(),
+            asset: value.get("asset")?.as_str()?.to_string(),
+            variant: value
+                .get("variant")
+                .and_then(Value::as_str)
+                .map(str::to_string),
+            sha256: value
+                .get("sha256")
+                .and_then(Value::as_str)
+                .map(str::to_string),
+            worktree: value
+                .get("worktree")
+                .and_then(Value::as_str)
+                .map(str::to_string),
+            last_used: value.get("last_used").and_then(Value::as_u64),
+            failed_starts: value
+                .get("failed_starts")
+                .and_then(Value::as_u64)
+                .and_then(|count| u32::try_from(count).ok())
+                .unwrap_or(0),
+            extension_version: value
+                .get("extension_version")
+                .and_then(Value::as_str)
+                .map(str::to_string),
+            platform: value
+                .get("platform")
+                .and_then(Value::as_str)
+                .map(str::to_string),
+        })
+    }
+
+    /// Write the manifest into the version directory `dir`.
+    pub(crate) fn write(&self, fs: &dyn Fs, dir: &Path) -> Result<(), String> {
+        let content = serde_json::to_string_pretty(&self.to_json())
+            .map_err(messages::manifest_unserializable)?;
+        fs.write_atomically(&dir.join(Self::FILE_NAME), (content + "\n").as_bytes())
+            .map_err(|e| messages::manifest_write_failed(dir, e))
+    }
+
+    /// Read the manifest of the version directory `dir`, if any.
+    pub(crate) fn read(fs: &dyn Fs, dir: &Path) -> Option<Self> {
+        let content = fs.read_to_string(&dir.join(Self::FILE_NAME)).ok()?;
+        Self::from_json(&serde_json::from_str(&content).ok()?)
+    }
+
+    /// Record that a server is started from the version directory `dir` at `now`.
+    pub(crate) fn touch(fs: &dyn Fs, dir: &Path, now: u64) -> Result<(), String> {
+        let mut manifest = Self::read(fs, dir).ok_or_else(|| messages::manifest_missing(dir))?;
+        manifest.last_used = Some(now);
+        manifest.write(fs, dir)
+    }
+
+    /// Record whether a server started from the version directory `dir` initialized,
+    /// returning the failed starts in a row since.
+    pub(crate) fn record_start(fs: &dyn Fs, dir: &Path, initialized: bool) -> Result<u32, String> {
+        let mut manifest = Self::read(fs, dir).ok_or_else(|| messages::manifest_missing(dir))?;
+        let failed_starts = if initialized {
+            0
+        } else {
+            manifest.failed_starts.saturating_add(1)
+        };
+        if failed_starts != manifest.failed_starts {
+            manifest.failed_starts = failed_starts;
+            manifest.write(fs, dir)?;
+        }
+        Ok(failed_starts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use zed_extension_api::{Architecture, Os};
+
+    use super::{platform, Manifest};
+    use crate::{host::StdFs, test_support::TempDir};
+
+    #[test]
+    fn round_trip() {
+        let temp = TempDir::new("manifest");
+        let manifest = Manifest {
+            version: "v0.1.23".to_string(),
+            asset: "typos-lsp-v0.1.23-x86_64-unknown-linux-musl.tar.gz".to_string(),
+            variant: Some("musl".to_string()),
+            sha256: Some("e3b0c442".to_string()),
+            worktree: Some("/work/project".to_string()),
+            last_used: None,
+            failed_starts: 0,
+            extension_version: Some("0.0.4 (v0.0.4-1-g36ed7a2)".to_string()),
+            platform: Some(platform(Os::Linux, Architecture::X8664)),
+        };
+        let fs = StdFs::new(temp.path());
+        manifest.write(&fs, Path::new(".")).unwrap();
+        assert_eq!(Manifest::read(&fs, Path::new(".")), Some(manifest.clone()));
+        let written = std::fs::read_to_string(temp.path().join(Manifest::FILE_NAME)).unwrap();
+        assert!(
+            written.contains("\"extension_version\": \"0.0.4 (v0.0.4-1-g36ed7a2)\""),
+            "{written}"
+        );
+
+        Manifest::touch(&fs, Path::new("."), 1_700_000_000).unwrap();
+        assert_eq!(
+            Manifest::read(&fs, Path::new(".")),
+            Some(Manifest {
+                last_used: Some(1_700_000_000),
+                ..manifest.clone()
+            })
+        );
+    }
+
+    #[test]
+    fn failed_starts() {
+        let temp = TempDir::new("manifest-failed-starts");
+        let fs = StdFs::new(temp.path());
+        let dir = Path::new(".");
+        assert!(Manifest::record_start(&fs, dir, false).is_err());
+        std::fs::write(
+            temp.path().join(Manifest::FILE_NAME),
+            "{\"version\": \"v0.1.23\", \"asset\": \"a.tar.gz\"}",
+        )
+        .unwrap();
+        assert_eq!(Manifest::read(&fs, dir).unwrap().failed_starts, 0);
+        assert_eq!(Manifest::record_start(&fs, dir, false), Ok(1));
+        assert_eq!(Manifest::record_start(&fs, dir, false), Ok(2));
+        assert_eq!(Manifest::read(&fs, dir).unwrap().failed_starts, 2);
+        assert_eq!(Manifest::record_start(&fs, dir, true), Ok(0));
+        assert_eq!(Manifest::read(&fs, dir).unwrap().failed_starts, 0);
+    }
+
+    #[test]
+    fn missing_or_invalid() {
+        let temp = TempDir::new("manifest-invalid");
+        let fs = StdFs::new(temp.path());
+        assert_eq!(Manifest::read(&fs, Path::new(".")), None);
+        assert!(Manifest::touch(&fs, Path::new("."), 1).is_err());
+        std::fs::write(temp.path().join(Manifest::FILE_NAME), "{\"version\": 1}").unwrap();
+        assert_eq!(Manifest::read(&fs, Path::new(".")), None);
+    }
+}
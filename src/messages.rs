@@ -0,0 +1,724 @@
+//! The messages of the installation and startup flows, shown in Zed's logs or as the
+//! error of a server that failed to start.
+//!
+//! Each message is built here from typed parameters, so that the wording stays
+//! consistent across call sites and can be checked without running the flows. The
+//! failures users act on are variants of [`crate::error::TyposExtensionError`], whose
+//! details come from here too.
+//!
+//! Two kinds of text stay next to their logic. The diagnostics of parsers, such as
+//! [`crate::typos_config`], [`crate::pattern_match`] or [`crate::excludes`], name the
+//! offending character or line and only make sense with the parsing state. Advisories
+//! assembled from a decision of their own, such as [`crate::disk_space`],
+//! [`crate::download_errors`] or [`crate::word_overlap`], keep their wording with it.
+
+use std::{fmt::Display, path::Path, time::Duration};
+
+use crate::{build_info, lockfile, work_lock};
+
+/// An error returned to Zed, which shows it along the ones of other extensions.
+pub(crate) fn extension_error(error: impl Display) -> String {
+    format!("typos extension {}: {error}", build_info::describe())
+}
+
+/// An error resolving the binary for the worktree at `root`, which shares the server
+/// with others.
+pub(crate) fn for_worktree(root: &str, error: impl Display) -> String {
+    format!("worktree {root}: {error}")
+}
+
+pub(crate) fn release_without_assets(tag: &str) -> String {
+    format!(
+        "release {tag} has no uploaded assets yet; retrying later or pinning the previous \
+         version will help"
+    )
+}
+
+pub(crate) fn unsupported_release_tag(tag: &str) -> String {
+    format!("unsupported typos-lsp release tag {tag:?}")
+}
+
+/// The release `tag` has no `asset`, which the lockfile requires.
+pub(crate) fn missing_locked_asset(asset: &str, tag: &str, available: &[&str]) -> String {
+    format!(
+        "{file} requires the asset {asset} of typos-lsp {tag}, but the release only has \
+         {available:?}",
+        file = lockfile::FILE_NAME,
+    )
+}
+
+/// The release `tag` has no `asset`, which the `asset_name` setting names.
+pub(crate) fn missing_named_asset(asset: &str, tag: &str, available: &[&str]) -> String {
+    format!(
+        "`asset_name` requires the asset {asset} of typos-lsp {tag}, but the release only \
+         has {available:?}"
+    )
+}
+
+/// Download attempt `attempt` failed with `error`, and is tried again after `delay`.
+pub(crate) fn download_retried(attempt: usize, delay: Duration, error: impl Display) -> String {
+    format!(
+        "download attempt {attempt} failed, trying again in {}s: {error}",
+        delay.as_secs()
+    )
+}
+
+pub(crate) fn read_only_work_dir(error: impl Display, in_container: bool) -> String {
+    format!(
+        "the extension work directory is not writable ({error}){hint}: install typos-lsp \
+         manually and make it available on the PATH",
+        hint = if in_container {
+            ", which is common for read-only container overlays"
+        } else {
+            ""
+        }
+    )
+}
+
+pub(crate) const WORK_DIR_NOT_UTF8: &str = "the work directory path is not UTF-8";
+
+/// The binary at `path` is not the one the lockfile describes.
+pub(crate) fn checksum_mismatch(path: &str, expected: &str, found: &str) -> String {
+    format!(
+        "{path} does not match {file}: expected sha256 {expected}, found {found}",
+        file = lockfile::FILE_NAME,
+    )
+}
+
+pub(crate) fn lockfile_export_failed(error: impl Display) -> String {
+    format!("failed to export {}: {error}", lockfile::FILE_NAME)
+}
+
+/// The installation in `dir` cannot be described by a lockfile.
+pub(crate) fn lockfile_without_manifest(dir: &str) -> String {
+    format!(
+        "cannot export {}: {dir} has no manifest",
+        lockfile::FILE_NAME
+    )
+}
+
+/// The cached binary at `path` is not used, as it lies outside the work directory.
+pub(crate) fn cached_binary_outside_work_dir(path: &str) -> String {
+    format!(
+        "not starting the cached typos-lsp {path}, which lies outside the extension work \
+         directory, looking it up again"
+    )
+}
+
+/// typos-lsp is not started for the worktree at `root`, as `enabled` is `false`.
+pub(crate) fn disabled(root: &str) -> String {
+    format!(
+        "typos is disabled for {root} by `lsp.typos.settings.enabled`, typos-lsp was not \
+         started: set it to `true`, or remove it, to check this worktree again"
+    )
+}
+
+/// The result of the `remove_worktree_artifacts` setting for the worktree at `root`, with the files that
+/// were `removed` and the ones `kept` as changed since they were written.
+pub(crate) fn cleaned_up(root: &str, removed: &[String], kept: &[String]) -> String {
+    let mut message = format!("cleaned up {root}, typos-lsp was not started: ");
+    if removed.is_empty() {
+        message.push_str("nothing to remove");
+    } else {
+        message.push_str(&format!("removed {}", removed.join(", ")));
+    }
+    if !kept.is_empty() {
+        message.push_str(&format!(
+            "; kept {}, changed since the extension wrote them",
+            kept.join(", ")
+        ));
+    }
+    message.push_str(". Unset `remove_worktree_artifacts` to start typos-lsp again");
+    message
+}
+
+/// An installed release used without looking for a newer one.
+pub(crate) fn update_deferred(tag: &str) -> String {
+    format!(
+        "using the installed typos-lsp {tag}, looking for updates later as `update_timing` \
+         is \"deferred\""
+    )
+}
+
+pub(crate) fn update_disabled(tag: &str) -> String {
+    format!(
+        "using the installed typos-lsp {tag} without looking for updates, as `auto_update` is off"
+    )
+}
+
+/// Looking up or downloading the latest release failed with `error`, so the installed
+/// release `tag` is used instead.
+pub(crate) fn fetch_failed_using_installed(tag: &str, error: impl Display) -> String {
+    format!("using the installed typos-lsp {tag}, as installing the latest release failed: {error}")
+}
+
+/// Downloading the latest release failed with `error`, so the installed release `tag`
+/// is still used.
+pub(crate) fn update_failed(tag: &str, error: impl Display) -> String {
+    format!("updating typos-lsp failed, still using typos-lsp {tag}: {error}")
+}
+
+/// The latest release, `tag`, was looked up recently and is installed.
+pub(crate) fn recently_checked(tag: &str) -> String {
+    format!(
+        "using the installed typos-lsp {tag}, the latest release when last looked up less \
+         than `update_check_interval_hours` ago"
+    )
+}
+
+pub(crate) fn pinned_in_tool_versions(tag: &str, file: &str) -> String {
+    format!("typos-lsp {tag} is pinned in {file}")
+}
+
+pub(crate) fn cli_pin_mapped(cli_version: &str, source: &str, lsp_tag: &str) -> String {
+    format!("typos {cli_version} is pinned in {source}, using typos-lsp {lsp_tag}")
+}
+
+pub(crate) fn cli_pin_unmapped(cli_version: &str, source: &str) -> String {
+    format!(
+        "typos {cli_version} is pinned in {source} but no typos-lsp release is known to \
+         match it, using the latest release instead"
+    )
+}
+
+/// The binary found on the `$PATH` at `path` is used as it is.
+pub(crate) fn system_binary_on_path(path: &str) -> String {
+    format!(
+        "using typos-lsp on the $PATH at {path}, which the extension does not update; set \
+         `ignore_system_version` to use the release the extension installs instead"
+    )
+}
+
+/// The binary found on the `$PATH` at `path` is one of the extension installations.
+pub(crate) fn managed_binary_on_path(path: &str) -> String {
+    format!("typos-lsp on the $PATH at {path} is installed by the extension")
+}
+
+/// Zed failed to parse the `lsp.<server>` settings with `error`, so the last ones that
+/// parsed are used when `fallback`, else the defaults.
+pub(crate) fn unparsable_settings(server: &str, error: &str, fallback: bool) -> String {
+    format!(
+        "the `lsp.{server}` settings do not parse, using {} until they are fixed: {error}",
+        if fallback {
+            "the last ones that did"
+        } else {
+            "the defaults"
+        }
+    )
+}
+
+/// The project settings `file` of a worktree does not parse.
+pub(crate) fn unparsable_project_settings(file: &str, error: &str) -> String {
+    format!(
+        "{file} does not parse, so the user-only settings cannot be told apart from the \
+         project ones and none is applied until it is fixed: {error}"
+    )
+}
+
+/// The `severity` setting is `found`, none of the `accepted` names.
+pub(crate) fn invalid_severity(found: &str, accepted: &[&str]) -> String {
+    let accepted: Vec<String> = accepted.iter().map(|name| format!("{name:?}")).collect();
+    format!(
+        "`settings.severity` is {found}, expected one of {}, in any case",
+        accepted.join(", ")
+    )
+}
+
+pub(crate) fn invalid_config_settings(error: impl Display) -> String {
+    format!("invalid typos configuration settings: {error}")
+}
+
+pub(crate) fn invalid_config_toml(error: impl Display) -> String {
+    format!("invalid `config_toml`: {error}")
+}
+
+pub(crate) fn unreadable_config_file(file: &str, error: impl Display) -> String {
+    format!("cannot read `config_files` entry {file}: {error}")
+}
+
+pub(crate) fn invalid_config_file(file: &str, error: impl Display) -> String {
+    format!("invalid `config_files` entry {file}: {error}")
+}
+
+/// The relative `config` initialization option `path` names no file of the worktree,
+/// as it would be `resolved`.
+pub(crate) fn missing_config(path: &str, resolved: &dyn Display) -> String {
+    format!(
+        "the `config` initialization option names {path}, which does not exist at \
+         {resolved}: typos-lsp would silently use its defaults. Set a path relative to the \
+         worktree root, or an absolute one"
+    )
+}
+
+/// The `config` initialization option `path` starts with `~`, but the home directory
+/// is not known.
+pub(crate) fn config_home_unknown(path: &str) -> String {
+    format!(
+        "cannot expand `~` in the `config` initialization option {path}: neither HOME nor \
+         USERPROFILE is set in the worktree environment, set an absolute path instead"
+    )
+}
+
+/// The `config` file at `path` cannot be read by the extension.
+pub(crate) fn unreadable_config(path: &str) -> String {
+    format!("cannot read the configured typos config {path}")
+}
+
+pub(crate) fn config_outside_worktree(path: &str) -> String {
+    format!(
+        "cannot read the configured typos config {path} from outside of the worktree: \
+         typos-lsp still reads it, but the extension leaves it out of what it derives from \
+         the configuration"
+    )
+}
+
+/// The label of merged `config_files` in the effective configuration.
+pub(crate) fn config_files_label(files: &[String]) -> String {
+    format!("the `config_files` {}", files.join(", "))
+}
+
+pub(crate) const CONFIG_TOML_LABEL: &str = "the `config_toml` setting";
+
+pub(crate) const GENERATED_CONFIG_IGNORED: &str =
+    "typos-lsp reads a single custom `config`, the options derived from the extension \
+     settings are ignored because `config` is set";
+
+pub(crate) const CLEANUP_OFF: &str =
+    "`cleanup` is \"off\": previous typos-lsp installations, incomplete downloads and \
+     unused archive entries are kept in the extension directory";
+
+pub(crate) fn custom_config_write_failed(error: impl Display) -> String {
+    format!("failed to write the custom typos config: {error}")
+}
+
+pub(crate) fn effective_config_write_failed(error: impl Display) -> String {
+    format!("failed to write the effective typos configuration: {error}")
+}
+
+/// The effective configuration of the worktree at `root` was written to `path`.
+pub(crate) fn effective_config_written(root: &str, path: &Path) -> String {
+    format!(
+        "the typos configuration in effect for {root} is written to {}, e.g. for `typos \
+         --config`",
+        path.display()
+    )
+}
+
+pub(crate) fn deferred_cleanup_record_failed(error: impl Display) -> String {
+    format!("failed to record directories to clean up later: {error}")
+}
+
+pub(crate) fn start_record_failed(dir: &str, error: impl Display) -> String {
+    format!("failed to record the failed start of {dir}: {error}")
+}
+
+pub(crate) fn use_record_failed(dir: &str, error: impl Display) -> String {
+    format!("failed to record the use of {dir}: {error}")
+}
+
+/// The action of moving the installation `from` into place, for an I/O error.
+pub(crate) fn move_into_place(from: impl Display) -> String {
+    format!("move {from} into place")
+}
+
+/// The action of making the binary at `path` executable, for an I/O error.
+pub(crate) fn make_executable(path: &str) -> String {
+    format!("make {path} executable")
+}
+
+/// None of the assets of the release `tag` is a typos-lsp build.
+pub(crate) fn unsupported_packaging(tag: &str) -> String {
+    format!("version {tag} predates supported packaging")
+}
+
+/// The platform the release `version` has no 32-bit x86 build for.
+pub(crate) fn no_i686_build(version: &str) -> String {
+    format!("{version} has no i686 build")
+}
+
+/// The platform of an `architecture` typos-lsp is not built for.
+pub(crate) fn unsupported_architecture(architecture: impl std::fmt::Debug) -> String {
+    format!("the architecture {architecture:?}")
+}
+
+pub(crate) fn release_lookup_refused(error: impl Display) -> String {
+    format!("GitHub refused the release lookup: {error}")
+}
+
+pub(crate) fn create_failed(path: &Path, error: impl Display) -> String {
+    format!("failed to create {}: {error}", path.display())
+}
+
+pub(crate) fn write_failed(path: &Path, error: impl Display) -> String {
+    format!("failed to write {}: {error}", path.display())
+}
+
+pub(crate) fn read_failed(path: &Path, error: impl Display) -> String {
+    format!("failed to read {}: {error}", path.display())
+}
+
+/// Recording `what` in the work directory failed with `error`.
+pub(crate) fn record_failed(what: &str, error: impl Display) -> String {
+    format!("failed to record {what}: {error}")
+}
+
+pub(crate) fn manifest_unserializable(error: impl Display) -> String {
+    format!("failed to serialize the manifest: {error}")
+}
+
+pub(crate) fn manifest_write_failed(dir: &Path, error: impl Display) -> String {
+    format!("failed to write the manifest in {}: {error}", dir.display())
+}
+
+pub(crate) fn manifest_missing(dir: &Path) -> String {
+    format!("no manifest in {}", dir.display())
+}
+
+pub(crate) fn remove_failed(path: &Path, error: impl Display) -> String {
+    format!("failed to remove {}: {error}", path.display())
+}
+
+/// `target` could not be written into a worktree with `error`, so a copy was written to
+/// `fallback`, relative to the work directory.
+pub(crate) fn worktree_write_fallback(
+    target: &Path,
+    error: impl Display,
+    fallback: &Path,
+) -> String {
+    format!(
+        "could not write {} ({error}), a copy was written to {} in the extension work \
+         directory: copy it into the worktree to use it",
+        target.display(),
+        fallback.display(),
+    )
+}
+
+pub(crate) fn worktree_files_record_failed(error: impl Display) -> String {
+    format!("failed to register the files written into worktrees: {error}")
+}
+
+/// The lock file changed between every attempt at reading it.
+pub(crate) fn work_lock_unsettled() -> String {
+    format!("{} keeps changing", work_lock::FILE_NAME)
+}
+
+pub(crate) fn work_lock_failed(error: impl Display) -> String {
+    format!("failed to lock the extension work directory: {error}")
+}
+
+/// Another instance has held the lock for `held_for` seconds, longer than the extension
+/// waits for it.
+pub(crate) fn work_lock_wait_exceeded(held_for: u64) -> String {
+    format!(
+        "another Zed process has been installing typos-lsp for {held_for}s; restart the \
+         language server once it is done, or remove {} from the extension directory if no \
+         other Zed process runs",
+        work_lock::FILE_NAME,
+    )
+}
+
+/// Waiting for the lock extension instance `owner` has held for `held_for` seconds.
+pub(crate) fn work_lock_waiting(owner: &str, held_for: u64) -> String {
+    format!(
+        "waiting for another Zed process to finish installing typos-lsp: extension instance \
+         {owner} has held {} for {held_for}s",
+        work_lock::FILE_NAME,
+    )
+}
+
+/// Taking over the stale lock extension instance `owner` has held for `held_for`
+/// seconds, both unknown when the lock file cannot be read.
+pub(crate) fn work_lock_taken_over(holder: Option<(&str, u64)>) -> String {
+    match holder {
+        Some((owner, held_for)) => format!(
+            "taking over {}, held by extension instance {owner} for {held_for}s, longer than \
+             an installation takes: the Zed process holding it likely quit during one",
+            work_lock::FILE_NAME,
+        ),
+        None => format!("taking over {}, which cannot be read", work_lock::FILE_NAME),
+    }
+}
+
+/// The checksum file of `archive` does not start with a sha256 digest.
+pub(crate) fn checksum_not_sha256(archive: &str, content: &str) -> String {
+    format!("the checksum of {archive} is not a sha256 digest: {content:?}")
+}
+
+/// The checksum file of `archive` names the file `name` instead.
+pub(crate) fn checksum_of_other_file(archive: &str, name: &str) -> String {
+    format!("the checksum published for {archive} is the one of {name}")
+}
+
+pub(crate) fn checksum_unreadable(archive: &str, error: impl Display) -> String {
+    format!("failed to read the checksum of {archive}: {error}")
+}
+
+/// The download of `archive` does not match the digest its release publishes.
+pub(crate) fn archive_checksum_mismatch(archive: &str, expected: &str, found: &str) -> String {
+    format!(
+        "the downloaded {archive} does not match its published checksum: expected sha256 \
+         {expected}, found {found}. Nothing was installed; the download may have been \
+         corrupted or tampered with"
+    )
+}
+
+/// What checking the archive `archive` covered, once it matched its checksum.
+pub(crate) fn archive_checksum_checked(archive: &str) -> String {
+    format!(
+        "{archive} matches its published sha256 checksum; the checked download is not \
+         the one extracted, which the extension cannot hash, so this is a best-effort check"
+    )
+}
+
+/// Why the archive `archive` is installed unchecked.
+pub(crate) fn archive_checksum_unpublished(archive: &str) -> String {
+    format!("no sha256 checksum is published for {archive}, installing it unchecked")
+}
+
+pub(crate) fn migration_failed(error: impl Display) -> String {
+    format!("failed to migrate previous installations: {error}")
+}
+
+pub(crate) fn layout_record_failed(error: impl Display) -> String {
+    format!("failed to record the work directory layout: {error}")
+}
+
+/// The installation `name` has no binary, left by an interrupted download.
+pub(crate) fn removing_incomplete_installation(name: &str) -> String {
+    format!("removing incomplete installation {name}")
+}
+
+/// The installation `name` duplicates the one in the directory `canonical`.
+pub(crate) fn removing_duplicate_installation(name: &str, canonical: &str) -> String {
+    format!("removing {name}, already installed as {canonical}")
+}
+
+pub(crate) fn rename_failed(from: &str, to: &str, error: impl Display) -> String {
+    format!("failed to rename {from} to {to}: {error}")
+}
+
+pub(crate) fn renamed(from: &str, to: &str) -> String {
+    format!("renamed {from} to {to}")
+}
+
+/// The archive extracted into `dir`, which holds `entries`, has no binary `name`.
+pub(crate) fn archive_without_binary(name: &str, dir: &Path, entries: &[String]) -> String {
+    format!(
+        "the typos-lsp archive has no {name} binary, extracted into {} which holds \
+         {entries:?}",
+        dir.display(),
+    )
+}
+
+pub(crate) fn move_into_place_failed(path: &Path, error: impl Display) -> String {
+    format!("failed to move {} into place: {error}", path.display())
+}
+
+pub(crate) fn prune_failed(path: &Path, error: impl Display) -> String {
+    format!("failed to prune {}: {error}", path.display())
+}
+
+/// A setting is `found`, none of the `expected` values.
+pub(crate) fn unexpected_value(expected: &str, found: &str) -> String {
+    format!("expected {expected}, found {found:?}")
+}
+
+/// The user-only `setting`, set in the project settings `file`, is ignored, unless
+/// `trustable` and trusted by `settings.trust_project_binary`.
+pub(crate) fn user_only_setting(setting: &str, file: &str, trustable: bool) -> String {
+    format!(
+        "ignoring `{setting}` from {file}: it can only be set in the user settings{}",
+        if trustable {
+            ", or allowed with `settings.trust_project_binary` there"
+        } else {
+            ""
+        }
+    )
+}
+
+/// The `kind` entry `entry` of the list setting `key` is ignored, being invalid.
+pub(crate) fn ignored_entry(key: &str, kind: &str, entry: &str, error: impl Display) -> String {
+    format!("ignoring `{key}` {kind} {entry:?}: {error}")
+}
+
+pub(crate) fn unknown_asset_preference(entry: &str) -> String {
+    format!("ignoring unknown `asset_preference` entry {entry:?}, expected \"gnu\" or \"musl\"")
+}
+
+/// The `key` of the mirror setting is not a URL.
+pub(crate) fn mirror_url_invalid(key: &str, url: &str) -> String {
+    format!("`{key}` must be an http or https URL, found {url:?}")
+}
+
+pub(crate) fn mirror_key_unknown(key: &str) -> String {
+    format!("unknown key `{key}`, expected `base_url` or `release_api_url`")
+}
+
+/// Downloading from the mirror at `base_url`, set in `setting`, failed with `error`.
+pub(crate) fn mirror_download_failed(error: impl Display, base_url: &str, setting: &str) -> String {
+    format!("{error} (from the mirror {base_url} of `{setting}.base_url`)")
+}
+
+/// Looking up releases at `url`, set in `setting`, failed with `error`.
+pub(crate) fn mirror_lookup_failed(url: &str, setting: &str, error: impl Display) -> String {
+    format!("the release mirror {url} of `{setting}.release_api_url` failed: {error}")
+}
+
+pub(crate) const MIRROR_NO_RELEASE: &str = "no release with assets";
+
+pub(crate) const MIRROR_NOT_A_LIST: &str = "expected a list of releases";
+
+pub(crate) fn mirror_not_json(error: impl Display) -> String {
+    format!("not JSON: {error}")
+}
+
+pub(crate) fn mirror_key_missing(key: &str) -> String {
+    format!("`{key}` is missing")
+}
+
+/// The `assets` of a mirrored release are a `found` type instead of a list.
+pub(crate) fn mirror_assets_not_a_list(found: &str) -> String {
+    format!("expected a list of `assets`, found {found}")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::{
+        archive_checksum_mismatch, checksum_mismatch, cleaned_up, download_retried,
+        extension_error, ignored_entry, missing_locked_asset, read_only_work_dir,
+        release_without_assets, unexpected_value, user_only_setting, worktree_write_fallback,
+    };
+    use crate::build_info;
+
+    #[test]
+    fn extension_errors() {
+        assert_eq!(
+            extension_error("worktree /work/p: failed to download file: connection reset"),
+            format!(
+                "typos extension {}: worktree /work/p: failed to download file: connection \
+                 reset",
+                build_info::describe()
+            )
+        );
+        assert!(
+            extension_error("").starts_with(&format!("typos extension {}", build_info::VERSION))
+        );
+    }
+
+    #[test]
+    fn installation_failures() {
+        assert_eq!(
+            release_without_assets("v0.1.23"),
+            "release v0.1.23 has no uploaded assets yet; retrying later or pinning the \
+             previous version will help"
+        );
+        assert_eq!(
+            missing_locked_asset(
+                "typos-lsp-v0.1.23-x86_64-unknown-linux-musl.tar.gz",
+                "v0.1.23",
+                &[]
+            ),
+            "typos-extension.lock requires the asset \
+             typos-lsp-v0.1.23-x86_64-unknown-linux-musl.tar.gz of typos-lsp v0.1.23, but the \
+             release only has []"
+        );
+        assert_eq!(
+            download_retried(
+                1,
+                std::time::Duration::from_secs(4),
+                "failed to download file: connection reset"
+            ),
+            "download attempt 1 failed, trying again in 4s: failed to download file: \
+             connection reset"
+        );
+        assert_eq!(
+            read_only_work_dir("read-only file system", true),
+            "the extension work directory is not writable (read-only file system), which is \
+             common for read-only container overlays: install typos-lsp manually and make it \
+             available on the PATH"
+        );
+        assert_eq!(
+            read_only_work_dir("permission denied", false),
+            "the extension work directory is not writable (permission denied): install \
+             typos-lsp manually and make it available on the PATH"
+        );
+        assert_eq!(
+            checksum_mismatch("typos-lsp-0.1.23/typos-lsp", "00ff", "ff00"),
+            "typos-lsp-0.1.23/typos-lsp does not match typos-extension.lock: expected sha256 \
+             00ff, found ff00"
+        );
+    }
+
+    #[test]
+    fn cleanup_results() {
+        assert_eq!(
+            cleaned_up("/work/p", &[], &[]),
+            "cleaned up /work/p, typos-lsp was not started: nothing to remove. Unset \
+             `remove_worktree_artifacts` to start typos-lsp again"
+        );
+        assert_eq!(
+            cleaned_up(
+                "/work/p",
+                &["/work/p/typos-extension.lock".to_string()],
+                &["/work/p/.zed/typos-effective.toml".to_string()],
+            ),
+            "cleaned up /work/p, typos-lsp was not started: removed \
+             /work/p/typos-extension.lock; kept /work/p/.zed/typos-effective.toml, changed \
+             since the extension wrote them. Unset `remove_worktree_artifacts` to start \
+             typos-lsp again"
+        );
+    }
+
+    #[test]
+    fn settings_warnings() {
+        assert_eq!(
+            user_only_setting("settings.version", ".zed/settings.json", true),
+            "ignoring `settings.version` from .zed/settings.json: it can only be set in the \
+             user settings, or allowed with `settings.trust_project_binary` there"
+        );
+        assert_eq!(
+            user_only_setting(
+                "settings.allow_worktree_writes",
+                ".zed/settings.json",
+                false
+            ),
+            "ignoring `settings.allow_worktree_writes` from .zed/settings.json: it can only be \
+             set in the user settings"
+        );
+        assert_eq!(
+            unexpected_value("\"on_open\" or \"deferred\"", "later"),
+            "expected \"on_open\" or \"deferred\", found \"later\""
+        );
+        assert_eq!(
+            ignored_entry(
+                "exclude",
+                "glob",
+                "a/**b",
+                "`**` must be a whole path component"
+            ),
+            "ignoring `exclude` glob \"a/**b\": `**` must be a whole path component"
+        );
+    }
+
+    #[test]
+    fn download_and_write_failures() {
+        assert_eq!(
+            archive_checksum_mismatch("typos-lsp.tar.gz", "ab12", "cd34"),
+            "the downloaded typos-lsp.tar.gz does not match its published checksum: expected \
+             sha256 ab12, found cd34. Nothing was installed; the download may have been \
+             corrupted or tampered with"
+        );
+        assert_eq!(
+            worktree_write_fallback(
+                Path::new("/work/p/.zed/typos.lock"),
+                "read-only file system",
+                Path::new("worktree-files/0123/typos.lock"),
+            ),
+            "could not write /work/p/.zed/typos.lock (read-only file system), a copy was \
+             written to worktree-files/0123/typos.lock in the extension work directory: copy \
+             it into the worktree to use it"
+        );
+    }
+}
@@ -0,0 +1,229 @@
+//! Opt-in local timings of the installation and startup phases.
+//!
+//! Nothing ever leaves the machine: timings are appended to a file of the extension
+//! work directory, which only keeps the latest entries.
+
+use std::{
+    fmt, fs,
+    path::Path,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{log, messages};
+
+/// The file timings are recorded in, in the extension work directory.
+pub(crate) const FILE_NAME: &str = "metrics.tsv";
+
+/// How many entries are kept, older ones being dropped first.
+const MAX_ENTRIES: usize = 300;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Phase {
+    /// Reading and translating the Zed settings.
+    Settings,
+    /// Looking up the release to install on GitHub.
+    ReleaseLookup,
+    /// Downloading and extracting the release archive, done in one call by Zed.
+    Download,
+    /// Checking the installed binary.
+    Validation,
+}
+
+impl Phase {
+    const ALL: [Phase; 4] = [
+        Phase::Settings,
+        Phase::ReleaseLookup,
+        Phase::Download,
+        Phase::Validation,
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            Phase::Settings => "settings",
+            Phase::ReleaseLookup => "release_lookup",
+            Phase::Download => "download",
+            Phase::Validation => "validation",
+        }
+    }
+
+    fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|phase| phase.name() == name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Entry {
+    /// Seconds since the Unix epoch.
+    pub timestamp: u64,
+    pub phase: Phase,
+    pub duration: Duration,
+}
+
+impl Entry {
+    fn render(&self) -> String {
+        format!(
+            "{}\t{}\t{}\n",
+            self.timestamp,
+            self.phase.name(),
+            self.duration.as_millis()
+        )
+    }
+
+    fn parse(line: &str) -> Option<Self> {
+        let mut fields = line.split('\t');
+        let timestamp = fields.next()?.parse().ok()?;
+        let phase = Phase::parse(fields.next()?)?;
+        let millis = fields.next()?.parse().ok()?;
+        Some(Self {
+            timestamp,
+            phase,
+            duration: Duration::from_millis(millis),
+        })
+    }
+}
+
+/// Timings of one resolution, only recorded when enabled.
+#[derive(Debug, Default)]
+pub(crate) struct Metrics {
+    /// Set once the settings are known, timings are dropped otherwise.
+    pub enabled: bool,
+    entries: Vec<Entry>,
+}
+
+impl Metrics {
+    /// Run `f`, recording how long it took as `phase`.
+    pub(crate) fn time<T>(&mut self, phase: Phase, f: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let result = f();
+        self.entries.push(Entry {
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |since| since.as_secs()),
+            phase,
+            duration: start.elapsed(),
+        });
+        result
+    }
+
+    /// Append the recorded timings to the metrics file in `dir`, when enabled.
+    pub(crate) fn flush(self, dir: &Path) {
+        if !self.enabled || self.entries.is_empty() {
+            return;
+        }
+        let path = dir.join(FILE_NAME);
+        let existing = fs::read_to_string(&path).unwrap_or_default();
+        if let Err(e) = fs::write(&path, append_rotated(&existing, &self.entries)) {
+            log::warn(messages::record_failed("local metrics", e));
+        }
+    }
+}
+
+/// The metrics file content once `entries` are appended, keeping the latest ones.
+fn append_rotated(existing: &str, entries: &[Entry]) -> String {
+    let mut lines: Vec<String> = existing
+        .lines()
+        .filter(|line| Entry::parse(line).is_some())
+        .map(|line| format!("{line}\n"))
+        .chain(entries.iter().map(Entry::render))
+        .collect();
+    let excess = lines.len().saturating_sub(MAX_ENTRIES);
+    lines.drain(..excess);
+    lines.concat()
+}
+
+/// Per phase statistics over the entries of a metrics file.
+pub(crate) struct Summary(Vec<(Phase, usize, Duration, Duration)>);
+
+impl Summary {
+    pub(crate) fn from_file(dir: &Path) -> Self {
+        Self::new(&fs::read_to_string(dir.join(FILE_NAME)).unwrap_or_default())
+    }
+
+    fn new(content: &str) -> Self {
+        let entries: Vec<Entry> = content.lines().filter_map(Entry::parse).collect();
+        let stats = Phase::ALL
+            .into_iter()
+            .filter_map(|phase| {
+                let durations: Vec<Duration> = entries
+                    .iter()
+                    .filter(|entry| entry.phase == phase)
+                    .map(|entry| entry.duration)
+                    .collect();
+                let count = durations.len();
+                let total: Duration = durations.iter().sum();
+                let max = durations.iter().max().copied()?;
+                Some((phase, count, total / count as u32, max))
+            })
+            .collect();
+        Self(stats)
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0.is_empty() {
+            return f.write_str("local metrics: no timings recorded yet");
+        }
+        f.write_str("local metrics:")?;
+        for (phase, count, mean, max) in &self.0 {
+            write!(
+                f,
+                " {}(n={count}, mean={}ms, max={}ms)",
+                phase.name(),
+                mean.as_millis(),
+                max.as_millis()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{append_rotated, Entry, Phase, Summary, MAX_ENTRIES};
+
+    fn entry(timestamp: u64, phase: Phase, millis: u64) -> Entry {
+        Entry {
+            timestamp,
+            phase,
+            duration: Duration::from_millis(millis),
+        }
+    }
+
+    #[test]
+    fn serialization() {
+        let entry = entry(1700000000, Phase::ReleaseLookup, 250);
+        assert_eq!(entry.render(), "1700000000\trelease_lookup\t250\n");
+        assert_eq!(Entry::parse(entry.render().trim_end()), Some(entry));
+        assert_eq!(Entry::parse("1700000000\tunknown\t250"), None);
+    }
+
+    #[test]
+    fn rotation() {
+        let existing = append_rotated("corrupted line\n", &[entry(1, Phase::Settings, 1)]);
+        assert_eq!(existing, "1\tsettings\t1\n");
+
+        let entries: Vec<Entry> = (0..MAX_ENTRIES as u64 + 10)
+            .map(|timestamp| entry(timestamp, Phase::Download, 5))
+            .collect();
+        let content = append_rotated(&existing, &entries);
+        assert_eq!(content.lines().count(), MAX_ENTRIES);
+        assert!(content.starts_with("10\tdownload\t5\n"));
+        assert!(content.ends_with(&format!("{}\tdownload\t5\n", MAX_ENTRIES + 9)));
+    }
+
+    #[test]
+    fn summary() {
+        let content = "1\tsettings\t2\n2\tsettings\t4\n3\tdownload\t900\n";
+        assert_eq!(
+            Summary::new(content).to_string(),
+            "local metrics: settings(n=2, mean=3ms, max=4ms) download(n=1, mean=900ms, max=900ms)"
+        );
+        assert_eq!(
+            Summary::new("").to_string(),
+            "local metrics: no timings recorded yet"
+        );
+    }
+}
@@ -0,0 +1,243 @@
+//! Upgrades of the work directory layout left by earlier releases of the extension.
+//!
+//! Earlier releases named version directories after the raw release tag and wrote no
+//! manifest. Those installations are brought to the current layout in place, so that
+//! upgrading the extension does not download typos-lsp again.
+
+use std::path::Path;
+
+use zed_extension_api::{Architecture, Os};
+
+use crate::{
+    error::TyposExtensionError, hashing, host::Fs, log, manifest::Manifest, messages,
+    version::Version, LinuxLibc,
+};
+
+/// Records the layout the work directory was last migrated to.
+pub(crate) const LAYOUT_FILE: &str = "layout-version";
+
+/// The current layout: canonical version directory names, each with a manifest.
+const LAYOUT_VERSION: &str = "2";
+
+/// Files earlier releases could leave behind, never needed again.
+const LEFTOVERS: &[&str] = &[".write-probe"];
+
+/// Migrate the work directory to the current layout, unless already done.
+///
+/// Every step is idempotent, an interrupted migration is resumed by the next one.
+pub(crate) fn migrate(fs: &dyn Fs, platform: Os, architecture: Architecture) {
+    if is_current(fs) {
+        return;
+    }
+    if let Err(e) = migrate_entries(fs, platform, architecture) {
+        log::warn(messages::migration_failed(e));
+        return;
+    }
+    if let Err(e) = fs.write(Path::new(LAYOUT_FILE), LAYOUT_VERSION.as_bytes()) {
+        log::debug(messages::layout_record_failed(e));
+    }
+}
+
+/// Whether the work directory already has the current layout.
+pub(crate) fn is_current(fs: &dyn Fs) -> bool {
+    fs.read_to_string(Path::new(LAYOUT_FILE)).ok().as_deref() == Some(LAYOUT_VERSION)
+}
+
+fn migrate_entries(fs: &dyn Fs, platform: Os, architecture: Architecture) -> Result<(), String> {
+    let names = fs
+        .read_dir(Path::new("."))
+        .map_err(|e| TyposExtensionError::io("list the extension work directory", e))?;
+    for name in &names {
+        if LEFTOVERS.contains(&name.as_str()) {
+            fs.remove_file(Path::new(name)).ok();
+            continue;
+        }
+        let Some(version) = Version::from_dir_name(name) else {
+            continue;
+        };
+        let canonical = version.dir_name();
+        let Ok(binary) =
+            crate::TyposExtension::binary_path_within_archive(&platform, &architecture)
+        else {
+            // Older than any packaging the extension knows, left alone.
+            continue;
+        };
+        if !fs.is_file(&Path::new(name).join(&binary)) {
+            // A download interrupted by an earlier release, nothing to keep.
+            log::info(messages::removing_incomplete_installation(name));
+            fs.remove_dir_all(Path::new(name)).ok();
+            continue;
+        }
+        if name != &canonical {
+            if fs.is_file(&Path::new(&canonical).join(&binary)) {
+                log::info(messages::removing_duplicate_installation(name, &canonical));
+                fs.remove_dir_all(Path::new(name)).ok();
+                continue;
+            }
+            // An incomplete canonical directory would make the rename fail.
+            fs.remove_dir_all(Path::new(&canonical)).ok();
+            fs.rename(Path::new(name), Path::new(&canonical))
+                .map_err(|e| messages::rename_failed(name, &canonical, e))?;
+            log::info(messages::renamed(name, &canonical));
+        }
+        write_missing_manifest(fs, name, &version, &binary, platform, architecture)?;
+    }
+    Ok(())
+}
+
+/// Describe an installation made before manifests existed.
+fn write_missing_manifest(
+    fs: &dyn Fs,
+    legacy_name: &str,
+    version: &Version,
+    binary: &str,
+    platform: Os,
+    architecture: Architecture,
+) -> Result<(), String> {
+    let canonical = version.dir_name();
+    let dir = Path::new(&canonical);
+    if Manifest::read(fs, dir).is_some() {
+        return Ok(());
+    }
+    // The tag is only known from names that embed it, typos-lsp tags are v-prefixed.
+    let tag = legacy_name
+        .strip_prefix(crate::version::DIR_PREFIX)
+        .filter(|tag| legacy_name != canonical && !tag.is_empty())
+        .map_or_else(|| format!("v{version}"), str::to_string);
+    // Releases writing no manifest only ever installed the glibc build.
+    let libc = LinuxLibc::Gnu;
+    Manifest {
+        asset: crate::TyposExtension::binary_release_name(&tag, &platform, &architecture, &libc)?,
+        variant: (platform == Os::Linux).then(|| libc.name().to_string()),
+        sha256: Some(hashing::sha256_file(fs, &dir.join(binary))?),
+        worktree: None,
+        last_used: None,
+        failed_starts: 0,
+        // Installed by an earlier release of the extension, which one is not known.
+        extension_version: None,
+        platform: None,
+        version: tag,
+    }
+    .write(fs, dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::Path};
+
+    use zed_extension_api::{Architecture, Os};
+
+    use super::{migrate, LAYOUT_FILE};
+    use crate::{
+        hashing,
+        host::{Fs, StdFs},
+        manifest::Manifest,
+        test_support::TempDir,
+    };
+
+    fn install(root: &Path, dir: &str, binary: &str) {
+        fs::create_dir_all(root.join(dir)).unwrap();
+        fs::write(root.join(dir).join("typos-lsp"), binary).unwrap();
+    }
+
+    fn listing(root: &Path) -> Vec<String> {
+        let mut names = StdFs::new(root).read_dir(Path::new(".")).unwrap();
+        names.sort();
+        names
+    }
+
+    /// A layout as left by a release naming directories after tags, without manifests.
+    fn legacy_layout(root: &Path) {
+        install(root, "typos-lsp-v0.1.22", "old binary");
+        install(root, "typos-lsp-v0.1.23", "legacy copy");
+        install(root, "typos-lsp-0.1.23", "current binary");
+        Manifest {
+            version: "v0.1.23".to_string(),
+            asset: "typos-lsp-v0.1.23-x86_64-unknown-linux-musl.tar.gz".to_string(),
+            variant: Some("musl".to_string()),
+            sha256: None,
+            worktree: None,
+            last_used: None,
+            failed_starts: 0,
+            extension_version: None,
+            platform: None,
+        }
+        .write(&StdFs::new(root), Path::new("typos-lsp-0.1.23"))
+        .unwrap();
+        fs::create_dir(root.join("typos-lsp-v0.1.21")).unwrap();
+        fs::write(root.join(".write-probe"), "").unwrap();
+        fs::write(root.join(crate::metrics::FILE_NAME), "").unwrap();
+        fs::create_dir(root.join("unrelated")).unwrap();
+    }
+
+    #[test]
+    fn legacy_layout_is_migrated() {
+        let temp = TempDir::new("migration");
+        legacy_layout(temp.path());
+        let work_dir = StdFs::new(temp.path());
+
+        migrate(&work_dir, Os::Linux, Architecture::X8664);
+        assert_eq!(
+            listing(temp.path()),
+            [
+                LAYOUT_FILE,
+                crate::metrics::FILE_NAME,
+                "typos-lsp-0.1.22",
+                "typos-lsp-0.1.23",
+                "unrelated"
+            ]
+        );
+        assert_eq!(
+            Manifest::read(&work_dir, Path::new("typos-lsp-0.1.22")),
+            Some(Manifest {
+                version: "v0.1.22".to_string(),
+                asset: "typos-lsp-v0.1.22-x86_64-unknown-linux-gnu.tar.gz".to_string(),
+                variant: Some("gnu".to_string()),
+                sha256: Some(hashing::sha256_hex(b"old binary")),
+                worktree: None,
+                last_used: None,
+                failed_starts: 0,
+                extension_version: None,
+                platform: None,
+            })
+        );
+        // The existing manifest and binary win over the legacy copy.
+        let current = Manifest::read(&work_dir, Path::new("typos-lsp-0.1.23")).unwrap();
+        assert_eq!(current.variant.as_deref(), Some("musl"));
+        assert_eq!(
+            fs::read_to_string(temp.path().join("typos-lsp-0.1.23/typos-lsp")).unwrap(),
+            "current binary"
+        );
+    }
+
+    #[test]
+    fn migration_is_idempotent() {
+        let temp = TempDir::new("migration-idempotent");
+        legacy_layout(temp.path());
+        let work_dir = StdFs::new(temp.path());
+        migrate(&work_dir, Os::Linux, Architecture::X8664);
+        let migrated = listing(temp.path());
+        let manifest = Manifest::read(&work_dir, Path::new("typos-lsp-0.1.22"));
+
+        migrate(&work_dir, Os::Linux, Architecture::X8664);
+        assert_eq!(listing(temp.path()), migrated);
+
+        // Even when the layout record is lost, e.g. after an interrupted migration.
+        fs::remove_file(temp.path().join(LAYOUT_FILE)).unwrap();
+        migrate(&work_dir, Os::Linux, Architecture::X8664);
+        assert_eq!(listing(temp.path()), migrated);
+        assert_eq!(
+            Manifest::read(&work_dir, Path::new("typos-lsp-0.1.22")),
+            manifest
+        );
+    }
+
+    #[test]
+    fn migrated_layouts_are_left_alone() {
+        let temp = TempDir::new("migration-done");
+        fs::write(temp.path().join(LAYOUT_FILE), "2").unwrap();
+        fs::create_dir(temp.path().join("typos-lsp-v0.1.21")).unwrap();
+        migrate(&StdFs::new(temp.path()), Os::Linux, Architecture::X8664);
+        assert!(temp.path().join("typos-lsp-v0.1.21").exists());
+    }
+}
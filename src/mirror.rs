@@ -0,0 +1,328 @@
+//! Installing typos-lsp from a mirror of its releases, for networks that block GitHub.
+//!
+//! With `download.base_url`, release assets are downloaded from `<base_url>/<asset>`
+//! rather than from the URL GitHub lists. With `download.release_api_url`, releases are
+//! looked up there rather than on GitHub, in the shape of the GitHub releases API:
+//! `<url>/latest` for the latest release, `<url>/tags/<tag>` for a pinned one, and
+//! `<url>` for the list the latest pre-release is taken from. A pinned release needs no
+//! lookup when only `base_url` is set: its assets are the ones named after the platform.
+//!
+//! Errors name the mirror and the setting it comes from, so that a wrong URL shows.
+
+use std::collections::BTreeMap;
+
+use zed_extension_api::{
+    serde_json::{self, Value},
+    GithubRelease, GithubReleaseAsset,
+};
+
+use crate::{coercion, error::TyposExtensionError, host::Http, messages};
+
+/// The setting holding the mirror URLs.
+pub(crate) const SETTING: &str = "download";
+
+/// Where releases are looked up and downloaded from, GitHub for what is unset.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct Mirror {
+    /// The URL the assets are downloaded from, without a trailing slash.
+    pub base_url: Option<String>,
+    /// The URL of the release metadata, without a trailing slash.
+    pub release_api_url: Option<String>,
+}
+
+impl Mirror {
+    /// The mirror of the `download` setting `entries`, by URL name. Empty URLs are unset.
+    pub(crate) fn parse(entries: &BTreeMap<String, String>) -> Result<Self, TyposExtensionError> {
+        let mut mirror = Self::default();
+        for (key, url) in entries {
+            let url = url.trim().trim_end_matches('/');
+            if url.is_empty() {
+                continue;
+            }
+            if !url.starts_with("https://") && !url.starts_with("http://") {
+                return Err(TyposExtensionError::invalid_setting(
+                    SETTING,
+                    messages::mirror_url_invalid(key, url),
+                ));
+            }
+            let field = match key.as_str() {
+                "base_url" => &mut mirror.base_url,
+                "release_api_url" => &mut mirror.release_api_url,
+                _ => {
+                    return Err(TyposExtensionError::invalid_setting(
+                        SETTING,
+                        messages::mirror_key_unknown(key),
+                    ))
+                }
+            };
+            *field = Some(url.to_string());
+        }
+        Ok(mirror)
+    }
+
+    /// Point the assets of `release` to the mirror, when it serves them.
+    pub(crate) fn redirect(&self, release: &mut GithubRelease) {
+        let Some(base_url) = &self.base_url else {
+            return;
+        };
+        for asset in &mut release.assets {
+            asset.download_url = format!("{base_url}/{}", asset.name);
+        }
+    }
+
+    /// `error`, downloading from the mirror, naming it.
+    pub(crate) fn download_failed(&self, error: String) -> String {
+        match &self.base_url {
+            Some(base_url) => messages::mirror_download_failed(error, base_url, SETTING),
+            None => error,
+        }
+    }
+}
+
+/// The latest release listed by the metadata at `api_url`, which may be a pre-release
+/// when `pre_release`.
+pub(crate) fn latest_release(
+    http: &dyn Http,
+    api_url: &str,
+    pre_release: bool,
+) -> Result<GithubRelease, String> {
+    if !pre_release {
+        let url = format!("{api_url}/latest");
+        return parse_release(&fetch(http, &url)?).map_err(|e| failed(&url, e));
+    }
+    let Value::Array(releases) = fetch(http, api_url)? else {
+        return Err(failed(api_url, messages::MIRROR_NOT_A_LIST.to_string()));
+    };
+    releases
+        .iter()
+        .filter(|release| release["draft"] != Value::Bool(true))
+        .map(parse_release)
+        .find(|release| {
+            release
+                .as_ref()
+                .map_or(true, |release| !release.assets.is_empty())
+        })
+        .unwrap_or_else(|| Err(messages::MIRROR_NO_RELEASE.to_string()))
+        .map_err(|e| failed(api_url, e))
+}
+
+/// The release published under `tag`, as listed by the metadata at `api_url`.
+pub(crate) fn release_by_tag(
+    http: &dyn Http,
+    api_url: &str,
+    tag: &str,
+) -> Result<GithubRelease, String> {
+    let url = format!("{api_url}/tags/{tag}");
+    parse_release(&fetch(http, &url)?).map_err(|e| failed(&url, e))
+}
+
+/// The release `tag`, served by the mirror at `base_url` under the asset `names`,
+/// without looking it up.
+pub(crate) fn unlisted_release(base_url: &str, tag: &str, names: &[String]) -> GithubRelease {
+    GithubRelease {
+        version: tag.to_string(),
+        assets: names
+            .iter()
+            .map(|name| GithubReleaseAsset {
+                name: name.clone(),
+                download_url: format!("{base_url}/{name}"),
+            })
+            .collect(),
+    }
+}
+
+fn fetch(http: &dyn Http, url: &str) -> Result<Value, String> {
+    let body = http.get(url).map_err(|e| failed(url, e))?;
+    serde_json::from_slice(&body).map_err(|e| failed(url, messages::mirror_not_json(e)))
+}
+
+/// A release of the GitHub releases API.
+fn parse_release(release: &Value) -> Result<GithubRelease, String> {
+    let string = |value: &Value, key: &str| {
+        coercion::string(key, value.get(key))?.ok_or_else(|| messages::mirror_key_missing(key))
+    };
+    let assets = match release.get("assets") {
+        Some(Value::Array(assets)) => assets,
+        Some(value) => {
+            return Err(messages::mirror_assets_not_a_list(coercion::type_name(
+                value,
+            )))
+        }
+        None => return Err(messages::mirror_key_missing("assets")),
+    };
+    Ok(GithubRelease {
+        version: string(release, "tag_name")?,
+        assets: assets
+            .iter()
+            .map(|asset| {
+                Ok(GithubReleaseAsset {
+                    name: string(asset, "name")?,
+                    download_url: string(asset, "browser_download_url")?,
+                })
+            })
+            .collect::<Result<_, String>>()?,
+    })
+}
+
+/// Looking up releases at `url`, set in `download.release_api_url`, failed with `error`.
+fn failed(url: &str, error: String) -> String {
+    messages::mirror_lookup_failed(url, SETTING, error)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use zed_extension_api::{
+        serde_json::{self, json},
+        GithubRelease, GithubReleaseAsset,
+    };
+
+    use super::{latest_release, release_by_tag, unlisted_release, Mirror};
+    use crate::test_support::FakeHttp;
+
+    const API: &str = "https://mirror.corp/api/typos-lsp/releases";
+
+    fn release(tag: &str, assets: &[&str]) -> serde_json::Value {
+        json!({
+            "tag_name": tag,
+            "assets": assets
+                .iter()
+                .map(|name| json!({
+                    "name": name,
+                    "browser_download_url": format!("https://github.com/{name}"),
+                }))
+                .collect::<Vec<_>>(),
+        })
+    }
+
+    #[test]
+    fn settings() {
+        let entries = |pairs: &[(&str, &str)]| {
+            pairs
+                .iter()
+                .map(|(key, url)| (key.to_string(), url.to_string()))
+                .collect::<BTreeMap<_, _>>()
+        };
+        assert_eq!(
+            Mirror::parse(&entries(&[
+                ("base_url", "https://mirror.corp/typos-lsp/"),
+                ("release_api_url", ""),
+            ])),
+            Ok(Mirror {
+                base_url: Some("https://mirror.corp/typos-lsp".to_string()),
+                release_api_url: None,
+            })
+        );
+        assert_eq!(
+            Mirror::parse(&entries(&[("base_url", "mirror.corp")])).map_err(String::from),
+            Err(
+                "ignoring `download`: `base_url` must be an http or https URL, found \
+                 \"mirror.corp\""
+                    .to_string()
+            )
+        );
+        assert!(Mirror::parse(&entries(&[("url", "https://mirror.corp")])).is_err());
+    }
+
+    #[test]
+    fn redirected_assets() {
+        let mirror = Mirror {
+            base_url: Some("https://mirror.corp/typos-lsp".to_string()),
+            release_api_url: None,
+        };
+        let mut release = GithubRelease {
+            version: "v0.1.23".to_string(),
+            assets: vec![GithubReleaseAsset {
+                name: "typos-lsp.zip".to_string(),
+                download_url: "https://github.com/typos-lsp.zip".to_string(),
+            }],
+        };
+        mirror.redirect(&mut release);
+        assert_eq!(
+            release.assets[0].download_url,
+            "https://mirror.corp/typos-lsp/typos-lsp.zip"
+        );
+        assert_eq!(
+            mirror.download_failed("connection refused".to_string()),
+            "connection refused (from the mirror https://mirror.corp/typos-lsp of \
+             `download.base_url`)"
+        );
+        assert_eq!(
+            unlisted_release("https://mirror.corp", "v0.1.5", &["a.zip".to_string()]).assets[0]
+                .download_url,
+            "https://mirror.corp/a.zip"
+        );
+    }
+
+    #[test]
+    fn lookups() {
+        let http = FakeHttp::default();
+        http.serve(
+            &format!("{API}/latest"),
+            &release("v0.1.23", &["a.tar.gz"]).to_string(),
+        );
+        http.serve(
+            &format!("{API}/tags/v0.1.5"),
+            &release("v0.1.5", &["b.tar.gz"]).to_string(),
+        );
+        http.serve(
+            API,
+            &json!([
+                {"tag_name": "v0.1.25-rc.1", "draft": true, "assets": []},
+                release("v0.1.24-rc.1", &[]),
+                release("v0.1.24-rc.2", &["c.tar.gz"]),
+            ])
+            .to_string(),
+        );
+
+        let latest = latest_release(&http, API, false).unwrap();
+        assert_eq!(latest.version, "v0.1.23");
+        assert_eq!(latest.assets[0].name, "a.tar.gz");
+        assert_eq!(latest.assets[0].download_url, "https://github.com/a.tar.gz");
+        assert_eq!(
+            release_by_tag(&http, API, "v0.1.5").unwrap().version,
+            "v0.1.5"
+        );
+        assert_eq!(
+            latest_release(&http, API, true).unwrap().version,
+            "v0.1.24-rc.2"
+        );
+        assert_eq!(
+            http.requests(),
+            [
+                format!("{API}/latest"),
+                format!("{API}/tags/v0.1.5"),
+                API.to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn lookup_errors_name_the_mirror() {
+        let http = FakeHttp::default();
+        assert_eq!(
+            release_by_tag(&http, API, "v0.1.5").unwrap_err(),
+            format!(
+                "the release mirror {API}/tags/v0.1.5 of `download.release_api_url` failed: \
+                 404 Not Found"
+            )
+        );
+        http.serve(&format!("{API}/latest"), "<html>");
+        let error = latest_release(&http, API, false).unwrap_err();
+        assert!(
+            error.starts_with(&format!(
+                "the release mirror {API}/latest of `download.release_api_url` failed: not JSON"
+            )),
+            "{error}"
+        );
+        http.serve(&format!("{API}/latest"), "{\"tag_name\": \"v0.1.23\"}");
+        assert_eq!(
+            latest_release(&http, API, false).unwrap_err(),
+            format!(
+                "the release mirror {API}/latest of `download.release_api_url` failed: \
+                 `assets` is missing"
+            )
+        );
+    }
+}
@@ -0,0 +1,124 @@
+//! Initialization options kept in a JSON file of the worktree, named by `options_file`.
+//!
+//! The file holds one object, merged over the defaults of the extension and below the
+//! inline `initialization_options`, which win for the options both set. It may use the
+//! comments and trailing commas of Zed's settings files. A file that cannot be used is
+//! reported and left out, the inline options applying alone.
+//!
+//! The file is read again whenever the settings are, and the hash of its content tells
+//! when it changed.
+
+use zed_extension_api::serde_json::Value;
+
+use crate::{coercion, hashing, jsonc};
+
+/// The options of an `options_file`, or why they could not be used.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct OptionsFile {
+    /// The options of the file, `None` when it could not be used.
+    pub options: Option<Value>,
+    /// The SHA-256 of the content of the file, `None` when it could not be read.
+    pub sha256: Option<String>,
+    pub error: Option<String>,
+}
+
+impl OptionsFile {
+    /// Parse the content of the options file at `path`, as named in errors.
+    pub(crate) fn parse(path: &str, content: &str) -> Self {
+        let sha256 = Some(hashing::sha256_hex(content.as_bytes()));
+        let error = match jsonc::parse_at(content) {
+            Ok(Value::Object(options)) => {
+                return Self {
+                    options: Some(Value::Object(options)),
+                    sha256,
+                    error: None,
+                }
+            }
+            Ok(value) => format!(
+                "ignoring the options file {path}: expected an object of initialization \
+                 options, found {}",
+                coercion::type_name(&value)
+            ),
+            Err((line, column, message)) => {
+                format!("ignoring the options file {path}:{line}:{column}: {message}")
+            }
+        };
+        Self {
+            options: None,
+            sha256,
+            error: Some(error),
+        }
+    }
+
+    /// The options file at `path`, relative to the root of the worktree, from its
+    /// `content` as the worktree read it.
+    pub(crate) fn from_file(path: &str, content: Result<String, String>) -> Self {
+        match content {
+            Ok(content) => Self::parse(path, &content),
+            Err(e) => Self {
+                options: None,
+                sha256: None,
+                error: Some(format!("cannot read the options file {path}: {e}")),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use zed_extension_api::serde_json::json;
+
+    use super::OptionsFile;
+    use crate::hashing;
+
+    #[test]
+    fn options() {
+        let content = "{\n  // Louder.\n  \"diagnosticSeverity\": \"Error\",\n}\n";
+        let file = OptionsFile::parse(".zed/typos-options.json", content);
+        assert_eq!(
+            file,
+            OptionsFile {
+                options: Some(json!({"diagnosticSeverity": "Error"})),
+                sha256: Some(hashing::sha256_hex(content.as_bytes())),
+                error: None,
+            }
+        );
+    }
+
+    #[test]
+    fn malformed() {
+        let file = OptionsFile::parse("options.json", "{\n  \"logLevel\": \"info\"\n  \"x\": 1\n}");
+        assert_eq!(file.options, None);
+        assert!(file.sha256.is_some());
+        assert_eq!(
+            file.error.as_deref(),
+            Some("ignoring the options file options.json:3:3: expected `,` or `}`")
+        );
+
+        let file = OptionsFile::parse("options.json", "[\"logLevel\"]");
+        assert_eq!(file.options, None);
+        assert_eq!(
+            file.error.as_deref(),
+            Some(
+                "ignoring the options file options.json: expected an object of initialization \
+                 options, found array"
+            )
+        );
+    }
+
+    #[test]
+    fn read() {
+        let path = ".zed/typos-options.json";
+        let file = OptionsFile::from_file(path, Err("No such file or directory".into()));
+        assert_eq!(file.options, None);
+        assert_eq!(file.sha256, None);
+        assert_eq!(
+            file.error.as_deref(),
+            Some("cannot read the options file .zed/typos-options.json: No such file or directory")
+        );
+
+        let file = OptionsFile::from_file(path, Ok("{\"logLevel\": \"debug\"}".into()));
+        assert_eq!(file.options, Some(json!({"logLevel": "debug"})));
+        assert_eq!(file.error, None);
+    }
+}
@@ -0,0 +1,158 @@
+//! How typos-lsp releases are packaged, which changed over time, as their assets tell.
+//!
+//! Releases were published under several names: with the tag, e.g.
+//! `typos-lsp-v0.1.23-<target>.tar.gz`, without its leading `v`, without the tag at
+//! all, with `_` separators, or as a zip on Unix. Rather than a table of which release
+//! used which name, every asset is looked up under each of these [`asset_names`], and
+//! failing that by its target alone (see [`names_target`]). Where an archive holds the
+//! binary changed too, and [`crate::archive_layout`] moves it where the extension
+//! expects it. A release whose assets hold no typos-lsp build at all [`predates`] the
+//! packaging the extension knows; add a fixture to `archive_fixtures.rs` for any
+//! packaging a release turns out to use.
+
+use zed_extension_api::{DownloadedFileType, GithubReleaseAsset};
+
+use crate::messages;
+
+/// The systems of the target triples typos-lsp is built for.
+const SYSTEMS: &[&str] = &[
+    "apple-darwin",
+    "unknown-linux-gnu",
+    "unknown-linux-musl",
+    "pc-windows-msvc",
+];
+
+/// Fail when none of the `assets` of the release `tag` is a typos-lsp build, as
+/// published before the packaging the extension knows.
+pub(crate) fn predates(tag: &str, assets: &[GithubReleaseAsset]) -> Result<(), String> {
+    let is_build = |name: &str| {
+        name.starts_with("typos")
+            && archive_type(name).is_some()
+            && SYSTEMS.iter().any(|system| name.contains(system))
+    };
+    match assets.iter().any(|asset| is_build(&asset.name)) {
+        true => Ok(()),
+        false => Err(messages::unsupported_packaging(tag)),
+    }
+}
+
+/// The names an asset of the release `tag` for the `target` triple may have been
+/// published under, the current one first. Archives are zips on Windows, gzipped
+/// tarballs elsewhere, unless published otherwise.
+pub(crate) fn asset_names(tag: &str, target: &str, windows: bool) -> Vec<String> {
+    let bare = tag.strip_prefix('v').unwrap_or(tag);
+    let extensions = match windows {
+        true => ["zip", "tar.gz"],
+        false => ["tar.gz", "zip"],
+    };
+    let mut names = Vec::new();
+    for tag in [Some(tag), Some(bare), None] {
+        for extension in extensions {
+            for separator in ['-', '_'] {
+                let name = match tag {
+                    Some(tag) => {
+                        format!("typos-lsp{separator}{tag}{separator}{target}.{extension}")
+                    }
+                    None => format!("typos-lsp{separator}{target}.{extension}"),
+                };
+                if !names.contains(&name) {
+                    names.push(name);
+                }
+            }
+        }
+    }
+    names
+}
+
+/// Whether the asset `name` is an archive of a build for the architecture `arch` and
+/// the rest `os` of its target triple, however it is named otherwise.
+pub(crate) fn names_target(name: &str, arch: &str, os: &str) -> bool {
+    archive_type(name).is_some() && name.contains(arch) && name.contains(os)
+}
+
+/// How the asset `name` is archived, from its extension.
+pub(crate) fn archive_type(name: &str) -> Option<DownloadedFileType> {
+    if name.ends_with(".zip") {
+        Some(DownloadedFileType::Zip)
+    } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+        Some(DownloadedFileType::GzipTar)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use zed_extension_api::{DownloadedFileType, GithubReleaseAsset};
+
+    use super::{archive_type, asset_names, names_target, predates};
+
+    #[test]
+    fn packaging_predates() {
+        let assets = |names: &[&str]| -> Vec<GithubReleaseAsset> {
+            names
+                .iter()
+                .map(|name| GithubReleaseAsset {
+                    name: name.to_string(),
+                    download_url: format!("https://example.com/{name}"),
+                })
+                .collect()
+        };
+        for known in [
+            "typos-lsp-v0.1.23-x86_64-unknown-linux-gnu.tar.gz",
+            "typos-lsp-aarch64-apple-darwin.tar.gz",
+            "typos_lsp-x86_64-pc-windows-msvc.zip",
+        ] {
+            assert_eq!(predates("v0.1.23", &assets(&[known, "SHA256SUMS"])), Ok(()));
+        }
+        assert_eq!(
+            predates("v0.1.2", &assets(&["typos-lsp", "typos-vscode-0.1.2.vsix"])),
+            Err("version v0.1.2 predates supported packaging".to_string())
+        );
+    }
+
+    #[test]
+    fn asset_name_variants() {
+        let names = asset_names("v0.1.23", "x86_64-unknown-linux-gnu", false);
+        assert_eq!(
+            names[0],
+            "typos-lsp-v0.1.23-x86_64-unknown-linux-gnu.tar.gz"
+        );
+        assert_eq!(names.len(), 12);
+        for name in [
+            "typos-lsp-0.1.23-x86_64-unknown-linux-gnu.tar.gz",
+            "typos-lsp_v0.1.23_x86_64-unknown-linux-gnu.tar.gz",
+            "typos-lsp-v0.1.23-x86_64-unknown-linux-gnu.zip",
+            "typos-lsp-x86_64-unknown-linux-gnu.tar.gz",
+        ] {
+            assert!(names.iter().any(|known| known == name), "{name}");
+        }
+
+        let names = asset_names("v0.1.5", "x86_64-pc-windows-msvc", true);
+        assert_eq!(names[0], "typos-lsp-v0.1.5-x86_64-pc-windows-msvc.zip");
+        assert_eq!(names[8], "typos-lsp-x86_64-pc-windows-msvc.zip");
+        assert_eq!(names[10], "typos-lsp-x86_64-pc-windows-msvc.tar.gz");
+    }
+
+    #[test]
+    fn targets() {
+        let gnu = |name| names_target(name, "x86_64", "unknown-linux-gnu");
+        assert!(gnu("typos-lsp.v0.1.23.x86_64-unknown-linux-gnu.tgz"));
+        assert!(gnu("typos_lsp-x86_64-unknown-linux-gnu.zip"));
+        assert!(!gnu(
+            "typos-lsp-v0.1.23-x86_64-unknown-linux-gnu.tar.gz.sha256"
+        ));
+        assert!(!gnu("typos-lsp-v0.1.23-x86_64-unknown-linux-musl.tar.gz"));
+        assert!(!gnu("typos-lsp-v0.1.23-aarch64-unknown-linux-gnu.tar.gz"));
+
+        assert!(matches!(
+            archive_type("typos-lsp-x86_64-unknown-linux-gnu.zip"),
+            Some(DownloadedFileType::Zip)
+        ));
+        assert!(matches!(
+            archive_type("typos-lsp-x86_64-pc-windows-msvc.tgz"),
+            Some(DownloadedFileType::GzipTar)
+        ));
+        assert!(archive_type("typos-lsp.exe").is_none());
+    }
+}
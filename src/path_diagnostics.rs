@@ -0,0 +1,118 @@
+//! The `paths_and_filenames` setting, silencing typos found in file names and paths.
+//!
+//! typos has no notion of a path inside a string, so the setting expands into the
+//! options that come closest: file names are no longer checked, and path-like tokens
+//! are ignored through `extend-ignore-re`, which typos applies before splitting words.
+
+use crate::{
+    log,
+    typos_config::{TomlValue, TyposConfig},
+};
+
+/// Tokens made of path components separated by `/`, e.g. `modules/vpc/main.tf`.
+pub(crate) const UNIX_PATH_RE: &str = r"(?:\.{1,2}/|~/|/)?(?:[\w.-]+/)+[\w.-]*";
+/// Tokens made of path components separated by `\`, e.g. `C:\Users\me`.
+pub(crate) const WINDOWS_PATH_RE: &str = r"(?:[A-Za-z]:)?(?:[\w.-]+\\)+[\w.-]*";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) enum PathsAndFilenames {
+    /// Neither file names nor paths are checked.
+    Off,
+    /// Requested as hints, which typos-lsp cannot do: applied as `Off`.
+    Hint,
+    /// Whatever the typos configuration says.
+    #[default]
+    Inherit,
+}
+
+impl PathsAndFilenames {
+    pub(crate) fn parse(value: &str) -> Option<Self> {
+        match value {
+            "off" => Some(Self::Off),
+            "hint" => Some(Self::Hint),
+            "inherit" => Some(Self::Inherit),
+            _ => None,
+        }
+    }
+
+    /// Add the typos options this setting stands for.
+    pub(crate) fn apply(self, config: &mut TyposConfig) {
+        match self {
+            Self::Inherit => {}
+            Self::Hint => {
+                log::once(
+                    "paths-and-filenames-hint",
+                    log::Level::Info,
+                    "typos-lsp reports every typo with the same `diagnosticSeverity`, \
+                     `paths_and_filenames: \"hint\"` silences paths and file names like \"off\"",
+                );
+                Self::Off.apply(config);
+            }
+            Self::Off => {
+                config.set("default", "check-filename", TomlValue::Bool(false));
+                let mut patterns = match config.get("default", "extend-ignore-re") {
+                    Some(TomlValue::Array(patterns)) => patterns.clone(),
+                    _ => Vec::new(),
+                };
+                for pattern in [UNIX_PATH_RE, WINDOWS_PATH_RE] {
+                    let pattern = TomlValue::String(pattern.to_string());
+                    if !patterns.contains(&pattern) {
+                        patterns.push(pattern);
+                    }
+                }
+                config.set("default", "extend-ignore-re", TomlValue::Array(patterns));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PathsAndFilenames;
+    use crate::typos_config::TyposConfig;
+
+    fn expand(setting: PathsAndFilenames) -> String {
+        let mut config = TyposConfig::default();
+        setting.apply(&mut config);
+        config.render()
+    }
+
+    #[test]
+    fn parsing() {
+        assert_eq!(
+            PathsAndFilenames::parse("off"),
+            Some(PathsAndFilenames::Off)
+        );
+        assert_eq!(
+            PathsAndFilenames::parse("hint"),
+            Some(PathsAndFilenames::Hint)
+        );
+        assert_eq!(
+            PathsAndFilenames::parse("inherit"),
+            Some(PathsAndFilenames::Inherit)
+        );
+        assert_eq!(PathsAndFilenames::parse("Off"), None);
+    }
+
+    #[test]
+    fn expansion() {
+        assert_eq!(expand(PathsAndFilenames::Inherit), "");
+        // Keys as documented in typos' reference, regexes as TOML basic strings.
+        let off = "[default]\n\
+                   check-filename = false\n\
+                   extend-ignore-re = [\
+                   \"(?:\\\\.{1,2}/|~/|/)?(?:[\\\\w.-]+/)+[\\\\w.-]*\", \
+                   \"(?:[A-Za-z]:)?(?:[\\\\w.-]+\\\\\\\\)+[\\\\w.-]*\"]\n";
+        assert_eq!(expand(PathsAndFilenames::Off), off);
+        assert_eq!(expand(PathsAndFilenames::Hint), off);
+    }
+
+    #[test]
+    fn expansion_is_idempotent() {
+        let mut config = TyposConfig::default();
+        PathsAndFilenames::Off.apply(&mut config);
+        let once = config.render();
+        PathsAndFilenames::Off.apply(&mut config);
+        assert_eq!(config.render(), once);
+    }
+}
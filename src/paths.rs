@@ -0,0 +1,319 @@
+//! Helpers for the paths handed to the extension by Zed and by the user.
+
+use std::path::{Component, Path, PathBuf};
+
+use crate::capabilities::{self, Capability};
+
+/// A path from the settings in a single spelling: without `./` segments, duplicate
+/// separators nor a trailing separator, e.g. `tools/typos-lsp` for `./tools//typos-lsp/`.
+///
+/// Both `/` and `\` separate segments, joined back with the first one the path uses.
+/// A leading `//` or `\\` of a UNC path, a drive prefix and the root are kept, and `..`
+/// segments are left alone as they depend on symlinks. A path of `.` segments only is
+/// `.`, and an empty path stays empty.
+pub(crate) fn normalize(path: &str) -> String {
+    let path = path.trim();
+    let is_separator = |c: char| c == '/' || c == '\\';
+    let separator = path.chars().find(|c| is_separator(*c)).unwrap_or('/');
+    let mut chars = path.chars();
+    let prefix = match (chars.next(), chars.next(), chars.next()) {
+        (Some(a), Some(b), _) if is_separator(a) && is_separator(b) => format!("{a}{b}"),
+        (Some(drive), Some(':'), third) if drive.is_ascii_alphabetic() => match third {
+            Some(root) if is_separator(root) => format!("{drive}:{root}"),
+            _ => format!("{drive}:"),
+        },
+        (Some(root), _, _) if is_separator(root) => root.to_string(),
+        _ => String::new(),
+    };
+    let segments: Vec<&str> = path[prefix.len()..]
+        .split(is_separator)
+        .filter(|segment| !segment.is_empty() && *segment != ".")
+        .collect();
+    if segments.is_empty() && prefix.is_empty() {
+        return if path.is_empty() {
+            String::new()
+        } else {
+            ".".to_string()
+        };
+    }
+    prefix + &segments.join(&separator.to_string())
+}
+
+/// `path` with its `.` and `..` segments resolved without looking at the filesystem, and
+/// without a trailing separator. `..` segments above the root are dropped, and the
+/// leading ones of a relative path kept.
+pub(crate) fn lexical(path: &Path) -> PathBuf {
+    let mut resolved = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => match resolved.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    resolved.pop();
+                }
+                Some(Component::RootDir | Component::Prefix(_)) => {}
+                Some(Component::ParentDir | Component::CurDir) | None => resolved.push(component),
+            },
+            component => resolved.push(component),
+        }
+    }
+    resolved
+}
+
+/// The worktree root in a single spelling, so that a project opened as `/work/proj/` or
+/// `/work/x/../proj` maps to the same per-project state and config paths.
+///
+/// Symlinks are not resolved: WASI cannot, see [`Capability::ResolveSymlinks`], so a
+/// project opened through a symlink or another mount point keeps a state of its own.
+pub(crate) fn canonical_root(root_path: &str) -> String {
+    capabilities::degrade(Capability::ResolveSymlinks);
+    lexical(Path::new(root_path))
+        .to_str()
+        .filter(|root| !root.is_empty())
+        .unwrap_or(root_path)
+        .to_string()
+}
+
+/// Whether `path` lies within the directory `dir`, once both are resolved [`lexical`]ly.
+///
+/// Symlinks are not resolved, see [`Capability::ResolveSymlinks`]: a path reaching `dir`
+/// through a symlink is taken to lie elsewhere.
+pub(crate) fn is_within(path: &Path, dir: &Path) -> bool {
+    capabilities::degrade(Capability::ResolveSymlinks);
+    lexical(path).starts_with(lexical(dir))
+}
+
+/// `path`, relative to the worktree root at `root_path` or absolute, relative to that
+/// root, the only way the extension reads files of the worktree. `None` when it lies
+/// outside of the worktree.
+pub(crate) fn worktree_relative(path: &str, root_path: &str) -> Option<String> {
+    let path = Path::new(path);
+    if !path.is_absolute() {
+        return path.to_str().map(str::to_string);
+    }
+    let relative = path.strip_prefix(root_path).ok()?;
+    relative
+        .to_str()
+        .filter(|relative| !relative.is_empty())
+        .map(str::to_string)
+}
+
+/// The binary the `binary.path` setting points to, checked with `which`, the lookup of
+/// the worktree environment.
+///
+/// A relative path is taken from the worktree root at `root_path`, and a bare name is
+/// looked up on the `$PATH`, as Zed would when spawning it. `path` is expected to be
+/// [`normalize`]d.
+pub(crate) fn configured_binary(
+    path: &str,
+    root_path: &str,
+    which: impl Fn(&str) -> Option<String>,
+) -> Result<String, String> {
+    let resolved = if Path::new(path).is_absolute() || !path.contains(['/', '\\']) {
+        path.to_string()
+    } else {
+        Path::new(root_path)
+            .join(path)
+            .to_string_lossy()
+            .into_owned()
+    };
+    which(&resolved).ok_or_else(|| {
+        let location = if resolved == path {
+            String::new()
+        } else {
+            format!(" (resolved to {resolved})")
+        };
+        format!(
+            "typos-lsp binary {path:?} set in `binary.path`{location} was not found or is \
+             not executable"
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::{
+        canonical_root, configured_binary, is_within, lexical, normalize, worktree_relative,
+    };
+    use crate::test_support::TempDir;
+
+    #[test]
+    #[cfg(unix)]
+    fn lexical_resolution() {
+        for (path, resolved) in [
+            ("/work/proj", "/work/proj"),
+            ("/work/proj/", "/work/proj"),
+            ("/work/./proj", "/work/proj"),
+            ("/work/x/../proj", "/work/proj"),
+            ("/../work", "/work"),
+            ("../work/./x/..", "../work"),
+            ("a/../..", ".."),
+            ("", ""),
+        ] {
+            assert_eq!(lexical(Path::new(path)), Path::new(resolved), "{path}");
+        }
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn root_spellings_map_to_one_root() {
+        for spelling in [
+            "/work/proj",
+            "/work/proj/",
+            "/work/./proj",
+            "/work/x/../proj",
+        ] {
+            assert_eq!(canonical_root(spelling), "/work/proj", "{spelling}");
+        }
+        assert_eq!(canonical_root(""), "");
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn symlinked_roots_are_not_resolved() {
+        let temp = TempDir::new("canonical-root");
+        let project = temp.path().join("work").join("proj");
+        std::fs::create_dir_all(&project).unwrap();
+        let link = temp.path().join("srv-proj");
+        std::os::unix::fs::symlink(&project, &link).unwrap();
+
+        // As in the extension sandbox, even when the host could resolve it.
+        let through_link = canonical_root(link.to_str().unwrap());
+        assert_eq!(through_link, link.to_str().unwrap());
+        assert_ne!(through_link, canonical_root(project.to_str().unwrap()));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn worktree_relative_paths() {
+        let root = "/work/project";
+        assert_eq!(
+            worktree_relative("typos.toml", root).as_deref(),
+            Some("typos.toml")
+        );
+        assert_eq!(
+            worktree_relative("/work/project/ci/typos.toml", root).as_deref(),
+            Some("ci/typos.toml")
+        );
+        assert_eq!(worktree_relative("/work/project", root), None);
+        assert_eq!(
+            worktree_relative("/work/project-other/typos.toml", root),
+            None
+        );
+        assert_eq!(worktree_relative("/etc/typos.toml", root), None);
+    }
+
+    #[test]
+    fn configured_binaries() {
+        let which = |path: &str| {
+            let found = match path {
+                "typos-lsp" => "/usr/local/bin/typos-lsp",
+                "/opt/typos-lsp/bin/typos-lsp" | "/work/p/tools/typos-lsp" => path,
+                _ => return None,
+            };
+            Some(found.to_string())
+        };
+        assert_eq!(
+            configured_binary("/opt/typos-lsp/bin/typos-lsp", "/work/p", which),
+            Ok("/opt/typos-lsp/bin/typos-lsp".to_string())
+        );
+        assert_eq!(
+            configured_binary("typos-lsp", "/work/p", which),
+            Ok("/usr/local/bin/typos-lsp".to_string())
+        );
+        assert_eq!(
+            configured_binary("tools/typos-lsp", "/work/p", which),
+            Ok("/work/p/tools/typos-lsp".to_string())
+        );
+        assert_eq!(
+            configured_binary("/opt/typos-lsp/target/release/typos-lsp", "/work/p", which),
+            Err(
+                "typos-lsp binary \"/opt/typos-lsp/target/release/typos-lsp\" set in \
+                 `binary.path` was not found or is not executable"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            configured_binary("target/typos-lsp", "/work/p", which),
+            Err(
+                "typos-lsp binary \"target/typos-lsp\" set in `binary.path` (resolved to \
+                 /work/p/target/typos-lsp) was not found or is not executable"
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn normalization() {
+        for (path, normalized) in [
+            ("tools/typos-lsp", "tools/typos-lsp"),
+            ("./tools/typos-lsp", "tools/typos-lsp"),
+            ("././tools/./typos-lsp", "tools/typos-lsp"),
+            ("docs/", "docs"),
+            ("tools//bin///typos-lsp", "tools/bin/typos-lsp"),
+            (" ci/typos.toml ", "ci/typos.toml"),
+            ("../shared/typos.toml", "../shared/typos.toml"),
+            ("typos-lsp", "typos-lsp"),
+            ("/", "/"),
+            ("/opt//typos-lsp/", "/opt/typos-lsp"),
+            ("/./opt/./typos-lsp", "/opt/typos-lsp"),
+            (".", "."),
+            ("./", "."),
+            ("", ""),
+            ("C:\\tools\\", "C:\\tools"),
+            ("C:\\tools\\\\typos-lsp.exe", "C:\\tools\\typos-lsp.exe"),
+            ("C:/tools/typos-lsp.exe", "C:/tools/typos-lsp.exe"),
+            ("C:\\", "C:\\"),
+            ("C:", "C:"),
+            ("C:tools\\", "C:tools"),
+            (".\\tools\\typos-lsp.exe", "tools\\typos-lsp.exe"),
+            ("tools\\bin/typos-lsp.exe", "tools\\bin\\typos-lsp.exe"),
+            ("\\\\server\\share\\tools\\", "\\\\server\\share\\tools"),
+            ("\\\\server\\\\share", "\\\\server\\share"),
+            ("//server/share/", "//server/share"),
+        ] {
+            assert_eq!(normalize(path), normalized, "{path:?}");
+            assert_eq!(normalize(normalized), normalized, "{normalized:?}");
+        }
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn containment() {
+        let work = Path::new("/extensions/work/typos");
+        let within = |path: &str| is_within(Path::new(path), work);
+        assert!(within("/extensions/work/typos/typos-lsp-0.1.23/typos-lsp"));
+        assert!(within(
+            "/extensions/work/./typos//typos-lsp-0.1.23/typos-lsp"
+        ));
+        assert!(within("/extensions/other/../work/typos/typos-lsp"));
+        assert!(is_within(
+            Path::new("/extensions/work/typos/typos-lsp"),
+            Path::new("/extensions/work/typos/")
+        ));
+        assert!(!within("/usr/bin/typos-lsp"));
+        assert!(!within("/extensions/work/typos-extra/typos-lsp"));
+        assert!(!within("/extensions/work/typos/../other/typos-lsp"));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn containment_does_not_resolve_symlinks() {
+        let temp = TempDir::new("within");
+        let work = temp.path().join("work");
+        let binary = work.join("typos-lsp-0.1.23").join("typos-lsp");
+        std::fs::create_dir_all(binary.parent().unwrap()).unwrap();
+        std::fs::write(&binary, "").unwrap();
+        let linked_work = temp.path().join("work-link");
+        std::os::unix::fs::symlink(&work, &linked_work).unwrap();
+
+        // As in the extension sandbox, even when the host could resolve them.
+        assert!(is_within(&binary, &work));
+        assert!(!is_within(
+            &linked_work.join("typos-lsp-0.1.23").join("typos-lsp"),
+            &work
+        ));
+    }
+}
@@ -0,0 +1,612 @@
+//! Matching text against `extend-ignore-re` patterns, within the extension.
+//!
+//! Only typos-lsp compiles the patterns, with the Rust `regex` crate, which the
+//! extension cannot depend on. This backtracking matcher covers the syntax typos
+//! configurations use: literals, `.`, character classes with ranges, escapes and POSIX
+//! names, `\d`, `\w`, `\s`, `\b` and their negations, anchors, groups, alternations,
+//! greedy and lazy repetitions, and the `i`, `m`, `s`, `x` and `R` flags. Patterns using
+//! anything else, such as Unicode classes, are rejected rather than approximated.
+
+use std::cell::Cell;
+
+/// The number of steps after which matching gives up, for patterns that backtrack
+/// exponentially.
+const STEP_LIMIT: usize = 100_000;
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Flags {
+    case_insensitive: bool,
+    dot_all: bool,
+    multi_line: bool,
+    verbose: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Char {
+        c: char,
+        case_insensitive: bool,
+    },
+    Any {
+        dot_all: bool,
+    },
+    Class {
+        class: Class,
+        case_insensitive: bool,
+    },
+    Start {
+        multi_line: bool,
+    },
+    End {
+        multi_line: bool,
+    },
+    WordBoundary {
+        negated: bool,
+    },
+    Concat(Vec<Node>),
+    Alternation(Vec<Node>),
+    Repeat {
+        node: Box<Node>,
+        min: u32,
+        max: Option<u32>,
+        greedy: bool,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Class {
+    negated: bool,
+    items: Vec<ClassItem>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum ClassItem {
+    Range(char, char),
+    Digit { negated: bool },
+    Word { negated: bool },
+    Space { negated: bool },
+    Nested(Class),
+}
+
+fn is_word(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+impl ClassItem {
+    fn contains(&self, c: char) -> bool {
+        match self {
+            Self::Range(start, end) => (*start..=*end).contains(&c),
+            Self::Digit { negated } => c.is_numeric() != *negated,
+            Self::Word { negated } => is_word(c) != *negated,
+            Self::Space { negated } => c.is_whitespace() != *negated,
+            Self::Nested(class) => class.contains(c),
+        }
+    }
+}
+
+impl Class {
+    fn contains(&self, c: char) -> bool {
+        self.items.iter().any(|item| item.contains(c)) != self.negated
+    }
+
+    fn contains_any_case(&self, c: char) -> bool {
+        self.contains(c)
+            || c.to_lowercase()
+                .chain(c.to_uppercase())
+                .any(|c| self.contains(c))
+    }
+
+    /// The class of a POSIX name, e.g. `alpha` in `[[:alpha:]]`, which only covers ASCII.
+    fn posix(name: &str) -> Option<Vec<ClassItem>> {
+        let ranges: &[(char, char)] = match name {
+            "alnum" => &[('0', '9'), ('A', 'Z'), ('a', 'z')],
+            "alpha" => &[('A', 'Z'), ('a', 'z')],
+            "ascii" => &[('\0', '\x7F')],
+            "blank" => &[('\t', '\t'), (' ', ' ')],
+            "cntrl" => &[('\0', '\x1F'), ('\x7F', '\x7F')],
+            "digit" => &[('0', '9')],
+            "graph" => &[('!', '~')],
+            "lower" => &[('a', 'z')],
+            "print" => &[(' ', '~')],
+            "punct" => &[('!', '/'), (':', '@'), ('[', '`'), ('{', '~')],
+            "space" => &[('\t', '\r'), (' ', ' ')],
+            "upper" => &[('A', 'Z')],
+            "word" => &[('0', '9'), ('A', 'Z'), ('_', '_'), ('a', 'z')],
+            "xdigit" => &[('0', '9'), ('A', 'F'), ('a', 'f')],
+            _ => return None,
+        };
+        Some(
+            ranges
+                .iter()
+                .map(|(start, end)| ClassItem::Range(*start, *end))
+                .collect(),
+        )
+    }
+}
+
+/// What an escape sequence stands for.
+enum Escaped {
+    Char(char),
+    Class(ClassItem),
+    Node(Node),
+}
+
+struct Parser {
+    chars: Vec<char>,
+    i: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.i).copied()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek();
+        self.i += usize::from(c.is_some());
+        c
+    }
+
+    fn eat(&mut self, expected: char) -> bool {
+        let found = self.peek() == Some(expected);
+        self.i += usize::from(found);
+        found
+    }
+
+    fn unsupported(what: &str) -> String {
+        format!("{what} is not supported by the extension")
+    }
+
+    fn alternation(&mut self, flags: &mut Flags) -> Result<Node, String> {
+        let mut branches = vec![self.concat(flags)?];
+        while self.eat('|') {
+            branches.push(self.concat(flags)?);
+        }
+        Ok(if branches.len() == 1 {
+            branches.remove(0)
+        } else {
+            Node::Alternation(branches)
+        })
+    }
+
+    fn concat(&mut self, flags: &mut Flags) -> Result<Node, String> {
+        let mut items = Vec::new();
+        while let Some(c) = self.peek().filter(|c| !matches!(c, '|' | ')')) {
+            if flags.verbose && c.is_whitespace() {
+                self.bump();
+                continue;
+            }
+            if flags.verbose && c == '#' {
+                while !matches!(self.bump(), None | Some('\n')) {}
+                continue;
+            }
+            if let Some(atom) = self.atom(flags)? {
+                items.push(self.repetition(atom)?);
+            }
+        }
+        Ok(Node::Concat(items))
+    }
+
+    /// The next atom, or `None` for a group only setting flags.
+    fn atom(&mut self, flags: &mut Flags) -> Result<Option<Node>, String> {
+        let c = self.bump().expect("atoms are only parsed before the end");
+        let node = match c {
+            '(' => return self.group(flags),
+            '[' => Node::Class {
+                class: self.class()?,
+                case_insensitive: flags.case_insensitive,
+            },
+            '.' => Node::Any {
+                dot_all: flags.dot_all,
+            },
+            '^' => Node::Start {
+                multi_line: flags.multi_line,
+            },
+            '$' => Node::End {
+                multi_line: flags.multi_line,
+            },
+            '\\' => match self.escape(false)? {
+                Escaped::Char(c) => Node::Char {
+                    c,
+                    case_insensitive: flags.case_insensitive,
+                },
+                Escaped::Class(item) => Node::Class {
+                    class: Class {
+                        negated: false,
+                        items: vec![item],
+                    },
+                    case_insensitive: flags.case_insensitive,
+                },
+                Escaped::Node(node) => node,
+            },
+            '*' | '+' | '?' | '{' => return Err(format!("`{c}` repeats nothing")),
+            c => Node::Char {
+                c,
+                case_insensitive: flags.case_insensitive,
+            },
+        };
+        Ok(Some(node))
+    }
+
+    /// A group, after its `(`.
+    fn group(&mut self, flags: &mut Flags) -> Result<Option<Node>, String> {
+        let mut inner = *flags;
+        if self.eat('?') {
+            if self.eat('P') || self.peek() == Some('<') {
+                if !self.eat('<') {
+                    return Err(Self::unsupported("`(?P` other than a named group"));
+                }
+                while self.bump().ok_or("unclosed group name")? != '>' {}
+            } else {
+                let mut enable = true;
+                loop {
+                    match self.bump() {
+                        Some('i') => inner.case_insensitive = enable,
+                        Some('s') => inner.dot_all = enable,
+                        Some('m' | 'R') => inner.multi_line = enable,
+                        Some('x') => inner.verbose = enable,
+                        // Unicode is the default, and ASCII-only classes are close enough.
+                        Some('u') => {}
+                        Some('-') => enable = false,
+                        Some(':') => break,
+                        Some(')') => {
+                            // Flags for the rest of the enclosing group.
+                            *flags = inner;
+                            return Ok(None);
+                        }
+                        Some(c) => return Err(Self::unsupported(&format!("`(?{c}`"))),
+                        None => return Err("unclosed group".to_string()),
+                    }
+                }
+            }
+        }
+        let node = self.alternation(&mut inner)?;
+        if !self.eat(')') {
+            return Err("unclosed group".to_string());
+        }
+        Ok(Some(node))
+    }
+
+    /// The repetition operators following `atom`, if any.
+    fn repetition(&mut self, atom: Node) -> Result<Node, String> {
+        let (min, max) = match self.peek() {
+            Some('*') => (0, None),
+            Some('+') => (1, None),
+            Some('?') => (0, Some(1)),
+            Some('{') => {
+                let end = self.chars[self.i..]
+                    .iter()
+                    .position(|&c| c == '}')
+                    .ok_or("unclosed counted repetition")?;
+                let inner: String = self.chars[self.i + 1..self.i + end].iter().collect();
+                let number = |text: &str| {
+                    text.trim()
+                        .parse::<u32>()
+                        .map_err(|_| format!("invalid counted repetition {{{inner}}}"))
+                };
+                let (min, max) = match inner.split_once(',') {
+                    None => (number(&inner)?, Some(number(&inner)?)),
+                    Some((min, max)) if max.trim().is_empty() => (number(min)?, None),
+                    Some((min, max)) => (number(min)?, Some(number(max)?)),
+                };
+                self.i += end;
+                (min, max)
+            }
+            _ => return Ok(atom),
+        };
+        self.bump();
+        let greedy = !self.eat('?');
+        Ok(Node::Repeat {
+            node: Box::new(atom),
+            min,
+            max,
+            greedy,
+        })
+    }
+
+    /// An escape sequence, after its `\`.
+    fn escape(&mut self, in_class: bool) -> Result<Escaped, String> {
+        let c = self.bump().ok_or("the pattern ends with `\\`")?;
+        let class = |item| Ok(Escaped::Class(item));
+        match c {
+            'd' => class(ClassItem::Digit { negated: false }),
+            'D' => class(ClassItem::Digit { negated: true }),
+            'w' => class(ClassItem::Word { negated: false }),
+            'W' => class(ClassItem::Word { negated: true }),
+            's' => class(ClassItem::Space { negated: false }),
+            'S' => class(ClassItem::Space { negated: true }),
+            'b' | 'B' if !in_class => Ok(Escaped::Node(Node::WordBoundary { negated: c == 'B' })),
+            'A' if !in_class => Ok(Escaped::Node(Node::Start { multi_line: false })),
+            'z' if !in_class => Ok(Escaped::Node(Node::End { multi_line: false })),
+            'n' => Ok(Escaped::Char('\n')),
+            't' => Ok(Escaped::Char('\t')),
+            'r' => Ok(Escaped::Char('\r')),
+            'f' => Ok(Escaped::Char('\x0C')),
+            'v' => Ok(Escaped::Char('\x0B')),
+            'a' => Ok(Escaped::Char('\x07')),
+            'x' | 'u' | 'U' => {
+                let digits: String = if self.eat('{') {
+                    let mut digits = String::new();
+                    while let Some(c) = self.bump().filter(|c| *c != '}') {
+                        digits.push(c);
+                    }
+                    digits
+                } else {
+                    let count = match c {
+                        'x' => 2,
+                        'u' => 4,
+                        _ => 8,
+                    };
+                    (0..count).filter_map(|_| self.bump()).collect()
+                };
+                u32::from_str_radix(&digits, 16)
+                    .ok()
+                    .and_then(char::from_u32)
+                    .map(Escaped::Char)
+                    .ok_or_else(|| format!("invalid escape \\{c}{digits}"))
+            }
+            'p' | 'P' => Err(Self::unsupported("Unicode classes")),
+            c if c.is_ascii_alphanumeric() => Err(Self::unsupported(&format!("`\\{c}`"))),
+            c => Ok(Escaped::Char(c)),
+        }
+    }
+
+    /// A character class, after its `[`.
+    fn class(&mut self) -> Result<Class, String> {
+        let negated = self.eat('^');
+        let mut items = Vec::new();
+        let mut first = true;
+        loop {
+            let c = self.bump().ok_or("unclosed character class")?;
+            let start = match c {
+                ']' if !first => return Ok(Class { negated, items }),
+                '[' if self.eat(':') => {
+                    let end = self.chars[self.i..]
+                        .windows(2)
+                        .position(|pair| pair == [':', ']'])
+                        .ok_or("unclosed POSIX class")?;
+                    let name: String = self.chars[self.i..self.i + end].iter().collect();
+                    self.i += end + 2;
+                    let (negated, name) = match name.strip_prefix('^') {
+                        Some(name) => (true, name.to_string()),
+                        None => (false, name),
+                    };
+                    let posix =
+                        Class::posix(&name).ok_or_else(|| format!("unknown POSIX class {name}"))?;
+                    items.push(ClassItem::Nested(Class {
+                        negated,
+                        items: posix,
+                    }));
+                    first = false;
+                    continue;
+                }
+                '[' => {
+                    items.push(ClassItem::Nested(self.class()?));
+                    first = false;
+                    continue;
+                }
+                '&' | '-' | '~' if self.peek() == Some(c) && !first => {
+                    return Err(Self::unsupported("class set operations"));
+                }
+                '\\' => match self.escape(true)? {
+                    Escaped::Char(c) => c,
+                    Escaped::Class(item) => {
+                        items.push(item);
+                        first = false;
+                        continue;
+                    }
+                    Escaped::Node(_) => unreachable!("assertions are not parsed in classes"),
+                },
+                c => c,
+            };
+            first = false;
+            let is_range = self.peek() == Some('-') && self.chars.get(self.i + 1) != Some(&']');
+            if !is_range {
+                items.push(ClassItem::Range(start, start));
+                continue;
+            }
+            self.bump();
+            let end = match self.bump().ok_or("unclosed character class")? {
+                '\\' => match self.escape(true)? {
+                    Escaped::Char(c) => c,
+                    _ => return Err("invalid class range".to_string()),
+                },
+                c => c,
+            };
+            if end < start {
+                return Err(format!("invalid class range {start}-{end}"));
+            }
+            items.push(ClassItem::Range(start, end));
+        }
+    }
+}
+
+/// A compiled pattern.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Pattern(Node);
+
+impl Pattern {
+    /// Compile `pattern`, failing on syntax the matcher does not cover.
+    pub(crate) fn parse(pattern: &str) -> Result<Self, String> {
+        let mut parser = Parser {
+            chars: pattern.chars().collect(),
+            i: 0,
+        };
+        let node = parser.alternation(&mut Flags::default())?;
+        match parser.peek() {
+            None => Ok(Self(node)),
+            Some(_) => Err("unmatched `)`".to_string()),
+        }
+    }
+
+    /// Whether a match of the pattern in `text` spans it entirely, `text` standing
+    /// alone. Patterns backtracking too much to tell are taken as not matching.
+    pub(crate) fn matches_whole(&self, text: &str) -> bool {
+        let text: Vec<char> = text.chars().collect();
+        let matcher = Matcher {
+            text: &text,
+            steps: Cell::new(0),
+        };
+        matcher.at(&self.0, 0, &mut |end| end == text.len())
+    }
+}
+
+struct Matcher<'t> {
+    text: &'t [char],
+    steps: Cell<usize>,
+}
+
+impl Matcher<'_> {
+    fn is_word_at(&self, pos: usize) -> bool {
+        self.text.get(pos).copied().is_some_and(is_word)
+    }
+
+    /// Whether `node` matches at `pos`, with `k` accepting the position after it.
+    fn at(&self, node: &Node, pos: usize, k: &mut dyn FnMut(usize) -> bool) -> bool {
+        self.steps.set(self.steps.get() + 1);
+        if self.steps.get() > STEP_LIMIT {
+            return false;
+        }
+        let next = self.text.get(pos).copied();
+        match node {
+            Node::Char {
+                c,
+                case_insensitive,
+            } => {
+                next.is_some_and(|next| {
+                    next == *c || *case_insensitive && next.to_lowercase().eq(c.to_lowercase())
+                }) && k(pos + 1)
+            }
+            Node::Any { dot_all } => {
+                next.is_some_and(|next| *dot_all || next != '\n') && k(pos + 1)
+            }
+            Node::Class {
+                class,
+                case_insensitive,
+            } => {
+                next.is_some_and(|next| {
+                    if *case_insensitive {
+                        class.contains_any_case(next)
+                    } else {
+                        class.contains(next)
+                    }
+                }) && k(pos + 1)
+            }
+            Node::Start { multi_line } => {
+                (pos == 0 || *multi_line && self.text[pos - 1] == '\n') && k(pos)
+            }
+            Node::End { multi_line } => {
+                (pos == self.text.len() || *multi_line && next == Some('\n')) && k(pos)
+            }
+            Node::WordBoundary { negated } => {
+                let boundary = (pos > 0 && self.is_word_at(pos - 1)) != self.is_word_at(pos);
+                boundary != *negated && k(pos)
+            }
+            Node::Concat(items) => self.sequence(items, pos, k),
+            Node::Alternation(branches) => branches.iter().any(|branch| self.at(branch, pos, k)),
+            Node::Repeat {
+                node,
+                min,
+                max,
+                greedy,
+            } => self.repeat(node, *min, *max, *greedy, 0, pos, k),
+        }
+    }
+
+    fn sequence(&self, items: &[Node], pos: usize, k: &mut dyn FnMut(usize) -> bool) -> bool {
+        match items.split_first() {
+            None => k(pos),
+            Some((first, rest)) => self.at(first, pos, &mut |next| self.sequence(rest, next, k)),
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn repeat(
+        &self,
+        node: &Node,
+        min: u32,
+        max: Option<u32>,
+        greedy: bool,
+        count: u32,
+        pos: usize,
+        k: &mut dyn FnMut(usize) -> bool,
+    ) -> bool {
+        let more = max.map_or(true, |max| count < max);
+        let again = |k: &mut dyn FnMut(usize) -> bool| {
+            more && self.at(node, pos, &mut |next| {
+                // An iteration matching nothing cannot make progress past the minimum.
+                (next != pos || count < min)
+                    && self.repeat(node, min, max, greedy, count + 1, next, k)
+            })
+        };
+        if count < min {
+            again(k)
+        } else if greedy {
+            again(k) || k(pos)
+        } else {
+            k(pos) || again(k)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Pattern;
+
+    fn whole(pattern: &str, text: &str) -> bool {
+        Pattern::parse(pattern)
+            .unwrap_or_else(|e| panic!("{pattern}: {e}"))
+            .matches_whole(text)
+    }
+
+    #[test]
+    fn matching() {
+        let table: &[(&str, &str, bool)] = &[
+            (r"\b[A-Z]{3}\b", "ODF", true),
+            (r"\b[A-Z]{3}\b", "ODFs", false),
+            (r"[A-Z]+", "ODF", true),
+            (r"[a-z]+", "ODF", false),
+            (r"(?i)[a-z]+", "ODF", true),
+            (r"(?i:odf)", "ODF", true),
+            (r"odf", "ODF", false),
+            (r"\d+\.\d+", "1.25", true),
+            (r"0x[0-9a-fA-F]+", "0xDEADbeef", true),
+            (r"\b[0-9A-Fa-f]{7,40}\b", "deadbeef", true),
+            (r"\b[0-9A-Fa-f]{7,40}\b", "dead", false),
+            (r"ab|cd", "cd", true),
+            (r"a(b|c)*d", "abcbd", true),
+            (r"a.+?z", "abcz", true),
+            (r"^\w+$", "snake_case", true),
+            (r"[[:upper:]][[:lower:]]+", "Kubernetes", true),
+            (r"[^\s]+", "no space", false),
+            (r"(?x) O D F  # spaced", "ODF", true),
+            (r"\x41\u{42}C", "ABC", true),
+            (r"a{2}", "aa", true),
+            (r"a{2}", "aaa", false),
+            (r"a{2,}", "aaaa", true),
+            (r"(?:a|)+b", "b", true),
+            (r"(?<id>k8s)", "k8s", true),
+            (r"(?Rm)^.*$", "line", true),
+            (r"[\]a-]+", "a]-", true),
+        ];
+        for (pattern, text, expected) in table {
+            assert_eq!(whole(pattern, text), *expected, "{pattern} on {text}");
+        }
+    }
+
+    #[test]
+    fn unsupported() {
+        for pattern in [r"\p{Han}+", r"(?U)a+", r"[a&&b]", r"\e", r"(?=a)"] {
+            assert!(Pattern::parse(pattern).is_err(), "{pattern}");
+        }
+        for pattern in [r"(a", r"a)", r"[a", r"+a", r"a{2"] {
+            assert!(Pattern::parse(pattern).is_err(), "{pattern}");
+        }
+    }
+
+    #[test]
+    fn backtracking_gives_up() {
+        let text = "a".repeat(30) + "b";
+        assert!(!whole(r"(a*)*c", &text));
+    }
+}
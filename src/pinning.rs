@@ -0,0 +1,248 @@
+//! Which typos-lsp version a worktree asks for, when several sources express one.
+
+use std::fmt;
+
+use crate::{lockfile, tool_versions};
+
+/// A version requested through the settings.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum VersionPreference {
+    /// Follow the latest release.
+    Latest,
+    /// Stay on the release with this tag.
+    Pinned(String),
+}
+
+impl VersionPreference {
+    /// Parse a `version` setting, release tags being normalized to their `v` prefix.
+    pub(crate) fn parse(value: &str) -> Option<Self> {
+        let value = value.trim();
+        match value {
+            "" => None,
+            "latest" => Some(Self::Latest),
+            tag if tag.starts_with('v') => Some(Self::Pinned(tag.to_string())),
+            version => Some(Self::Pinned(format!("v{version}"))),
+        }
+    }
+
+    /// The tag to install, `None` meaning the latest release.
+    pub(crate) fn tag(&self) -> Option<&str> {
+        match self {
+            Self::Latest => None,
+            Self::Pinned(tag) => Some(tag),
+        }
+    }
+}
+
+impl fmt::Display for VersionPreference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Latest => f.write_str("latest"),
+            Self::Pinned(tag) => f.write_str(tag),
+        }
+    }
+}
+
+/// The version preference in effect for a worktree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct VersionDecision {
+    pub preference: VersionPreference,
+    /// Set when the user and the project disagree, describing how it was settled.
+    pub notice: Option<String>,
+}
+
+/// Settle the version preferences of the user and of the project settings.
+///
+/// A project pin wins for its worktree, so that every contributor gets the same
+/// diagnostics, and so does a project asking for the latest release. Without any
+/// preference, the latest release is used.
+pub(crate) fn decide(
+    user: Option<&VersionPreference>,
+    project: Option<&VersionPreference>,
+) -> VersionDecision {
+    match (user, project) {
+        (Some(user), Some(project)) if user != project => VersionDecision {
+            preference: project.clone(),
+            notice: Some(format!(
+                "the project settings request typos-lsp {project} while the user settings \
+                 request {user}: using {project} for this worktree"
+            )),
+        },
+        (_, Some(preference)) | (Some(preference), None) => VersionDecision {
+            preference: preference.clone(),
+            notice: None,
+        },
+        (None, None) => VersionDecision {
+            preference: VersionPreference::Latest,
+            notice: None,
+        },
+    }
+}
+
+/// Where the release to install is pinned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum PinSource {
+    /// The `typos-extension.lock` file of the worktree.
+    Lockfile,
+    /// The `version` setting.
+    Settings,
+    /// The `typos-lsp` line of the worktree `.tool-versions` file.
+    ToolVersions,
+    /// The typos CLI version pinned by the project, with `sync_with_cli`.
+    Cli,
+}
+
+impl PinSource {
+    /// What the source is called in messages.
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            Self::Lockfile => lockfile::FILE_NAME,
+            Self::Settings => "version",
+            Self::ToolVersions => tool_versions::FILE_NAME,
+            Self::Cli => "sync_with_cli",
+        }
+    }
+}
+
+/// The release tags pinned by each source of a worktree, if any.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct PinCandidates {
+    pub lockfile: Option<String>,
+    pub settings: Option<String>,
+    pub tool_versions: Option<String>,
+}
+
+/// Pick the release to install, `None` meaning the latest one.
+///
+/// The lockfile wins, as it describes the exact installation to replay, then the
+/// `version` setting, the explicit choice of the editor user, then `.tool-versions`,
+/// shared with the other tools of the project, and finally the release matching the
+/// typos CLI, only looked up through `cli` when nothing else pins a release.
+pub(crate) fn pinned_release(
+    candidates: &PinCandidates,
+    cli: impl FnOnce() -> Option<String>,
+) -> Option<(String, PinSource)> {
+    let PinCandidates {
+        lockfile,
+        settings,
+        tool_versions,
+    } = candidates;
+    [
+        (lockfile, PinSource::Lockfile),
+        (settings, PinSource::Settings),
+        (tool_versions, PinSource::ToolVersions),
+    ]
+    .into_iter()
+    .find_map(|(tag, source)| Some((tag.clone()?, source)))
+    .or_else(|| cli().map(|tag| (tag, PinSource::Cli)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decide, pinned_release, PinCandidates, PinSource, VersionPreference};
+
+    fn pin(tag: &str) -> VersionPreference {
+        VersionPreference::Pinned(tag.to_string())
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(
+            VersionPreference::parse("latest"),
+            Some(VersionPreference::Latest)
+        );
+        assert_eq!(VersionPreference::parse("v0.1.34"), Some(pin("v0.1.34")));
+        assert_eq!(VersionPreference::parse(" 0.1.34 "), Some(pin("v0.1.34")));
+        assert_eq!(VersionPreference::parse(""), None);
+    }
+
+    #[test]
+    fn none_vs_none() {
+        let decision = decide(None, None);
+        assert_eq!(decision.preference, VersionPreference::Latest);
+        assert_eq!(decision.notice, None);
+    }
+
+    #[test]
+    fn none_vs_pin() {
+        let decision = decide(None, Some(&pin("v0.1.30")));
+        assert_eq!(decision.preference, pin("v0.1.30"));
+        assert_eq!(decision.notice, None);
+        let decision = decide(Some(&pin("v0.1.30")), None);
+        assert_eq!(decision.preference, pin("v0.1.30"));
+        assert_eq!(decision.notice, None);
+    }
+
+    #[test]
+    fn latest_vs_pin() {
+        let decision = decide(Some(&VersionPreference::Latest), Some(&pin("v0.1.30")));
+        assert_eq!(decision.preference, pin("v0.1.30"));
+        assert_eq!(
+            decision.notice.as_deref(),
+            Some(
+                "the project settings request typos-lsp v0.1.30 while the user settings \
+                 request latest: using v0.1.30 for this worktree"
+            )
+        );
+        let decision = decide(Some(&pin("v0.1.30")), Some(&VersionPreference::Latest));
+        assert_eq!(decision.preference, VersionPreference::Latest);
+        assert!(decision.notice.is_some());
+    }
+
+    #[test]
+    fn pin_vs_pin() {
+        let decision = decide(Some(&pin("v0.1.31")), Some(&pin("v0.1.30")));
+        assert_eq!(decision.preference, pin("v0.1.30"));
+        assert!(decision.notice.is_some());
+        let decision = decide(Some(&pin("v0.1.30")), Some(&pin("v0.1.30")));
+        assert_eq!(decision.preference, pin("v0.1.30"));
+        assert_eq!(decision.notice, None);
+    }
+
+    #[test]
+    fn pin_precedence() {
+        let tag = |tag: &str| Some(tag.to_string());
+        for lockfile in [None, tag("v0.1.20")] {
+            for settings in [None, tag("v0.1.21")] {
+                for tool_versions in [None, tag("v0.1.22")] {
+                    for sync_with_cli in [false, true] {
+                        let candidates = PinCandidates {
+                            lockfile: lockfile.clone(),
+                            settings: settings.clone(),
+                            tool_versions: tool_versions.clone(),
+                        };
+                        let expected = if lockfile.is_some() {
+                            Some(("v0.1.20", PinSource::Lockfile))
+                        } else if settings.is_some() {
+                            Some(("v0.1.21", PinSource::Settings))
+                        } else if tool_versions.is_some() {
+                            Some(("v0.1.22", PinSource::ToolVersions))
+                        } else if sync_with_cli {
+                            Some(("v0.1.23", PinSource::Cli))
+                        } else {
+                            None
+                        };
+                        let pinned = pinned_release(&candidates, || {
+                            sync_with_cli.then(|| "v0.1.23".to_string())
+                        });
+                        assert_eq!(
+                            pinned.as_ref().map(|(tag, source)| (tag.as_str(), *source)),
+                            expected,
+                            "{candidates:?} sync_with_cli={sync_with_cli}"
+                        );
+                    }
+                }
+            }
+        }
+
+        // The typos CLI pin is not looked up when another source wins.
+        let candidates = PinCandidates {
+            tool_versions: tag("v0.1.22"),
+            ..PinCandidates::default()
+        };
+        assert_eq!(
+            pinned_release(&candidates, || panic!("looked up the typos CLI pin")),
+            Some(("v0.1.22".to_string(), PinSource::ToolVersions))
+        );
+    }
+}
@@ -0,0 +1,122 @@
+//! Per-OS blocks in the `lsp.<server>` settings, so that one shared settings file
+//! works on every platform.
+//!
+//! `binary`, `initialization_options` and `settings` may each hold `macos`, `linux`
+//! and `windows` objects, e.g. `"binary": {"path": "typos-lsp", "windows": {"path":
+//! "C:\\tools\\typos-lsp.exe"}}`. The block of the current platform is deep-merged over
+//! its section, and the other blocks are dropped, before anything else reads the
+//! settings.
+
+use zed_extension_api::{serde_json::Value, Os};
+
+use crate::json_merge::deep_merge;
+
+/// The sections that may hold per-OS blocks.
+const SECTIONS: &[&str] = &["binary", "initialization_options", "settings"];
+
+/// The keys of the per-OS blocks.
+const PLATFORM_KEYS: &[&str] = &["macos", "linux", "windows"];
+
+fn platform_key(platform: Os) -> &'static str {
+    match platform {
+        Os::Mac => "macos",
+        Os::Linux => "linux",
+        Os::Windows => "windows",
+    }
+}
+
+/// The `lsp.<server>` settings with the per-OS blocks of `platform` applied.
+pub(crate) fn resolve(mut settings: Value, platform: Os) -> Value {
+    for section in SECTIONS {
+        let Some(section) = settings.get_mut(*section) else {
+            continue;
+        };
+        let Value::Object(members) = section else {
+            continue;
+        };
+        let mut block = None;
+        for key in PLATFORM_KEYS {
+            let value = members.remove(*key);
+            if *key == platform_key(platform) {
+                block = value;
+            }
+        }
+        if let Some(block) = block {
+            deep_merge(section, block);
+        }
+    }
+    settings
+}
+
+#[cfg(test)]
+mod tests {
+    use zed_extension_api::{serde_json::json, Os};
+
+    use super::resolve;
+
+    #[test]
+    fn platform_blocks() {
+        let settings = json!({
+            "binary": {
+                "path": "/usr/local/bin/typos-lsp",
+                "arguments": ["--stdio"],
+                "windows": {"path": "C:\\tools\\typos-lsp.exe"},
+                "macos": {"path": "/opt/homebrew/bin/typos-lsp"},
+            },
+            "initialization_options": {"diagnosticSeverity": "Warning"},
+            "settings": {
+                "asset_preference": ["gnu", "musl"],
+                "corrections": {"teh": "the"},
+                "linux": {"asset_preference": ["musl"], "corrections": {"adn": "and"}},
+            },
+        });
+        assert_eq!(
+            resolve(settings.clone(), Os::Windows),
+            json!({
+                "binary": {"path": "C:\\tools\\typos-lsp.exe", "arguments": ["--stdio"]},
+                "initialization_options": {"diagnosticSeverity": "Warning"},
+                "settings": {
+                    "asset_preference": ["gnu", "musl"],
+                    "corrections": {"teh": "the"},
+                },
+            })
+        );
+        assert_eq!(
+            resolve(settings.clone(), Os::Mac),
+            json!({
+                "binary": {"path": "/opt/homebrew/bin/typos-lsp", "arguments": ["--stdio"]},
+                "initialization_options": {"diagnosticSeverity": "Warning"},
+                "settings": {
+                    "asset_preference": ["gnu", "musl"],
+                    "corrections": {"teh": "the"},
+                },
+            })
+        );
+        // Objects are merged member by member, arrays replaced as a whole.
+        assert_eq!(
+            resolve(settings, Os::Linux),
+            json!({
+                "binary": {"path": "/usr/local/bin/typos-lsp", "arguments": ["--stdio"]},
+                "initialization_options": {"diagnosticSeverity": "Warning"},
+                "settings": {
+                    "asset_preference": ["musl"],
+                    "corrections": {"teh": "the", "adn": "and"},
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn other_values_are_untouched() {
+        for platform in [Os::Mac, Os::Linux, Os::Windows] {
+            assert_eq!(resolve(json!(null), platform), json!(null));
+            assert_eq!(
+                resolve(json!({"binary": "typos-lsp"}), platform),
+                json!({"binary": "typos-lsp"})
+            );
+            // Only the sections hold per-OS blocks.
+            let nested = json!({"settings": {"corrections": {"windows": "Windows"}}});
+            assert_eq!(resolve(nested.clone(), platform), nested);
+        }
+    }
+}
@@ -0,0 +1,144 @@
+//! Release lookups refused by the rate limit of the GitHub API.
+//!
+//! Without a token, GitHub allows 60 API requests per hour to a network, which
+//! machines sharing an address use up quickly. Once a lookup is refused, the time it
+//! may be tried again is recorded in the work directory, and GitHub is not asked again
+//! before it, by any server or Zed process: an installed typos-lsp is used meanwhile.
+//! The lookup API only returns the error text, so the limit is recognized from it, and
+//! its reset taken from it when it tells.
+
+use std::{path::Path, time::Duration};
+
+use crate::{error::TyposExtensionError, host::Fs, log};
+
+/// Records until when release lookups are not tried, in seconds since the Unix epoch.
+pub(crate) const FILE_NAME: &str = "rate-limited-until.txt";
+
+/// How long lookups wait for when the error does not tell, the window of the limit.
+const DEFAULT_BACKOFF: Duration = Duration::from_secs(60 * 60);
+
+/// Errors telling that the rate limit was hit, lowercase.
+const RATE_LIMITED: &[&str] = &[
+    "rate limit",
+    "ratelimit",
+    "rate-limit",
+    "429",
+    "too many requests",
+];
+
+/// Whether the lookup failing with `error` was refused by the rate limit.
+pub(crate) fn is_rate_limited(error: &str) -> bool {
+    let error = error.to_lowercase();
+    RATE_LIMITED.iter().any(|pattern| error.contains(pattern))
+}
+
+/// The number following `header` in `error`, if any.
+fn header_value(error: &str, header: &str) -> Option<u64> {
+    let (_, rest) = error.split_once(header)?;
+    let digits: String = rest
+        .trim_start_matches(|c: char| c == ':' || c == '=' || c == '"' || c.is_whitespace())
+        .chars()
+        .take_while(char::is_ascii_digit)
+        .collect();
+    digits.parse().ok()
+}
+
+/// Until when lookups wait after `error` at `now`: the reset it tells, or an hour.
+pub(crate) fn backoff_until(error: &str, now: u64) -> u64 {
+    let error = error.to_lowercase();
+    let told = header_value(&error, "x-ratelimit-reset")
+        .filter(|&reset| reset > now)
+        .or_else(|| header_value(&error, "retry-after").map(|after| now + after.max(1)));
+    told.unwrap_or(now + DEFAULT_BACKOFF.as_secs())
+}
+
+/// Until when lookups wait, as recorded in the work directory, if they still do at
+/// `now`.
+pub(crate) fn waiting_until(fs: &dyn Fs, now: u64) -> Option<u64> {
+    let content = fs.read_to_string(Path::new(FILE_NAME)).ok()?;
+    let until = content.trim().parse().ok()?;
+    // A wait longer than a day comes from a clock set back, and is not kept.
+    (now < until && until - now <= DEFAULT_BACKOFF.as_secs() * 24).then_some(until)
+}
+
+/// Record that lookups wait until `until`.
+pub(crate) fn record(fs: &dyn Fs, until: u64) {
+    if let Err(e) = fs.write_atomically(Path::new(FILE_NAME), format!("{until}\n").as_bytes()) {
+        log::warn(format!(
+            "failed to record when typos-lsp releases can be looked up again: {e}"
+        ));
+    }
+}
+
+/// Forget the wait, once a lookup succeeded.
+pub(crate) fn clear(fs: &dyn Fs) {
+    if fs.is_file(Path::new(FILE_NAME)) {
+        fs.remove_file(Path::new(FILE_NAME)).ok();
+    }
+}
+
+/// The error of a lookup refused, or skipped, until `until` at `now`.
+pub(crate) fn error(until: u64, now: u64) -> TyposExtensionError {
+    TyposExtensionError::RateLimited {
+        retry_at: until,
+        minutes: until.saturating_sub(now).div_ceil(60),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{backoff_until, clear, is_rate_limited, record, waiting_until, FILE_NAME};
+    use crate::{host::StdFs, test_support::TempDir};
+
+    #[test]
+    fn recognized_errors() {
+        for error in [
+            "API rate limit exceeded for 203.0.113.7. (But here's the good news: \
+             Authenticated requests get a higher rate limit.)",
+            "status 403: x-ratelimit-remaining: 0",
+            "HTTP 429 Too Many Requests",
+            "You have exceeded a secondary rate limit",
+        ] {
+            assert!(is_rate_limited(error), "{error}");
+        }
+        for error in ["connection refused", "404 Not Found", "offline"] {
+            assert!(!is_rate_limited(error), "{error}");
+        }
+    }
+
+    #[test]
+    fn backoff() {
+        let now = 1_700_000_000;
+        assert_eq!(backoff_until("API rate limit exceeded", now), now + 3600);
+        assert_eq!(
+            backoff_until("rate limited, X-RateLimit-Reset: 1700000600", now),
+            now + 600
+        );
+        // A reset already past is not trusted.
+        assert_eq!(
+            backoff_until("rate limited, x-ratelimit-reset: 1600000000", now),
+            now + 3600
+        );
+        assert_eq!(
+            backoff_until("429 Too Many Requests, Retry-After: 120", now),
+            now + 120
+        );
+    }
+
+    #[test]
+    fn persistence() {
+        let temp = TempDir::new("rate-limit");
+        let fs = StdFs::new(temp.path());
+        assert_eq!(waiting_until(&fs, 100_000), None);
+
+        record(&fs, 100_000);
+        assert_eq!(waiting_until(&fs, 99_400), Some(100_000));
+        assert_eq!(waiting_until(&fs, 100_000), None);
+        // A clock set back by more than a day does not wait for it.
+        assert_eq!(waiting_until(&fs, 100_000 - 25 * 3600), None);
+
+        clear(&fs);
+        assert!(!temp.path().join(FILE_NAME).exists());
+        clear(&fs);
+    }
+}
@@ -0,0 +1,201 @@
+//! Checking release archives against their published SHA-256 checksums, on a best-effort
+//! basis.
+//!
+//! typos-lsp releases may publish a `<archive>.sha256` asset next to each archive. The
+//! extension API only extracts an archive while downloading it, and the extension has no
+//! way to extract one itself, so the bytes extracted are never hashed: a release with a
+//! checksum is downloaded once as it is to be checked, then again to be extracted. A
+//! mismatch fails the installation before anything is extracted, which catches an asset
+//! corrupted or replaced on the server, but not a second download altered on its way.
+//! Releases without a checksum are installed unchecked, as they used to be.
+
+use std::path::Path;
+
+use zed_extension_api::{DownloadedFileType, GithubReleaseAsset};
+
+use crate::{
+    hashing,
+    host::{Downloader, Fs},
+    messages,
+};
+
+/// The downloaded archive while it is checked, relative to the work directory.
+pub(crate) const ARCHIVE_FILE: &str = "checked-archive.download";
+
+/// The downloaded checksum of the archive while it is checked.
+pub(crate) const CHECKSUM_FILE: &str = "checked-archive.sha256";
+
+/// The checksum asset of the archive `archive`, if the release publishes one.
+pub(crate) fn asset<'a>(
+    assets: &'a [GithubReleaseAsset],
+    archive: &str,
+) -> Option<&'a GithubReleaseAsset> {
+    let name = format!("{archive}.sha256");
+    assets.iter().find(|asset| asset.name == name)
+}
+
+/// The digest of a checksum file of the archive `archive`, in the `sha256sum` format: the
+/// hexadecimal digest, optionally followed by the file name.
+pub(crate) fn parse(content: &str, archive: &str) -> Result<String, String> {
+    let mut fields = content.split_whitespace();
+    let digest = fields.next().unwrap_or_default().to_ascii_lowercase();
+    if digest.len() != 64 || !digest.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(messages::checksum_not_sha256(archive, content.trim()));
+    }
+    // `*` marks a file read in binary mode.
+    match fields.next().map(|name| name.trim_start_matches('*')) {
+        Some(name) if Path::new(name).file_name() != Some(archive.as_ref()) => {
+            Err(messages::checksum_of_other_file(archive, name))
+        }
+        _ => Ok(digest),
+    }
+}
+
+/// Download the archive `archive` and fail unless it matches the digest of `checksum`,
+/// `explain` describing download failures. Nothing is left in the work directory.
+///
+/// The download checked is not the one extracted afterwards, see the module
+/// documentation.
+pub(crate) fn check<E: From<String>>(
+    fs: &dyn Fs,
+    downloader: &dyn Downloader,
+    archive: &GithubReleaseAsset,
+    checksum: &GithubReleaseAsset,
+    explain: impl Fn(&str, String) -> E,
+) -> Result<(), E> {
+    let result = (|| {
+        downloader
+            .download(
+                &checksum.download_url,
+                CHECKSUM_FILE,
+                DownloadedFileType::Uncompressed,
+            )
+            .map_err(|e| explain(&checksum.download_url, e))?;
+        let content = fs
+            .read_to_string(Path::new(CHECKSUM_FILE))
+            .map_err(|e| messages::checksum_unreadable(&archive.name, e))?;
+        let expected = parse(&content, &archive.name)?;
+        downloader
+            .download(
+                &archive.download_url,
+                ARCHIVE_FILE,
+                DownloadedFileType::Uncompressed,
+            )
+            .map_err(|e| explain(&archive.download_url, e))?;
+        let found = hashing::sha256_file(fs, Path::new(ARCHIVE_FILE))?;
+        if found != expected {
+            return Err(
+                messages::archive_checksum_mismatch(&archive.name, &expected, &found).into(),
+            );
+        }
+        Ok(())
+    })();
+    for file in [CHECKSUM_FILE, ARCHIVE_FILE] {
+        fs.remove_file(Path::new(file)).ok();
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use zed_extension_api::GithubReleaseAsset;
+
+    use super::{asset, check, parse, ARCHIVE_FILE, CHECKSUM_FILE};
+    use crate::{
+        hashing,
+        host::StdFs,
+        test_support::{FakeDownloader, TempDir},
+    };
+
+    const ARCHIVE: &str = "typos-lsp-v0.1.23-x86_64-unknown-linux-gnu.tar.gz";
+
+    fn release_asset(name: &str) -> GithubReleaseAsset {
+        GithubReleaseAsset {
+            name: name.to_string(),
+            download_url: format!("https://example.com/{name}"),
+        }
+    }
+
+    #[test]
+    fn checksum_assets() {
+        let assets = [
+            release_asset(ARCHIVE),
+            release_asset(&format!("{ARCHIVE}.sha256")),
+        ];
+        assert_eq!(
+            asset(&assets, ARCHIVE).map(|asset| asset.name.as_str()),
+            Some("typos-lsp-v0.1.23-x86_64-unknown-linux-gnu.tar.gz.sha256")
+        );
+        assert!(asset(&assets[..1], ARCHIVE).is_none());
+    }
+
+    #[test]
+    fn checksum_files() {
+        let digest = hashing::sha256_hex(b"archive");
+        for content in [
+            digest.clone(),
+            format!("{digest}\n"),
+            format!("{digest}  {ARCHIVE}\n"),
+            format!("{digest} *{ARCHIVE}\n"),
+            format!("{digest}  dist/{ARCHIVE}\n"),
+            format!("{}  {ARCHIVE}\n", digest.to_uppercase()),
+        ] {
+            assert_eq!(parse(&content, ARCHIVE), Ok(digest.clone()), "{content}");
+        }
+        assert_eq!(
+            parse(&format!("{digest}  typos-lsp.zip"), ARCHIVE),
+            Err(format!(
+                "the checksum published for {ARCHIVE} is the one of typos-lsp.zip"
+            ))
+        );
+        assert!(parse("", ARCHIVE).is_err());
+        assert!(parse("not-a-digest", ARCHIVE).is_err());
+        assert!(parse(&digest[1..], ARCHIVE).is_err());
+    }
+
+    #[test]
+    fn verification() {
+        let temp = TempDir::new("release-checksums");
+        let fs = StdFs::new(temp.path());
+        let downloader = FakeDownloader::new(temp.path());
+        let (archive, checksum) = (
+            release_asset(ARCHIVE),
+            release_asset(&format!("{ARCHIVE}.sha256")),
+        );
+        downloader.serve(&archive.download_url, b"archive");
+        let explain = |url: &str, error: String| format!("{url}: {error}");
+
+        let digest = hashing::sha256_hex(b"archive");
+        downloader.serve(
+            &checksum.download_url,
+            format!("{digest}  {ARCHIVE}").as_bytes(),
+        );
+        assert_eq!(
+            check(&fs, &downloader, &archive, &checksum, explain),
+            Ok(())
+        );
+
+        let other = hashing::sha256_hex(b"other");
+        downloader.serve(&checksum.download_url, other.as_bytes());
+        assert_eq!(
+            check(&fs, &downloader, &archive, &checksum, explain),
+            Err(format!(
+                "the downloaded {ARCHIVE} does not match its published checksum: expected \
+                 sha256 {other}, found {digest}. Nothing was installed; the download may \
+                 have been corrupted or tampered with"
+            ))
+        );
+
+        downloader.fail_with(Some("connection reset"));
+        assert_eq!(
+            check(&fs, &downloader, &archive, &checksum, explain),
+            Err(format!("{}: connection reset", checksum.download_url))
+        );
+        for file in [ARCHIVE_FILE, CHECKSUM_FILE] {
+            assert!(!temp.path().join(file).exists(), "{file}");
+        }
+        assert_eq!(fs::read_dir(temp.path()).unwrap().count(), 0);
+    }
+}
@@ -0,0 +1,321 @@
+//! What a change of the settings takes to apply to a running server.
+//!
+//! Zed asks for the workspace configuration again whenever the settings change, and
+//! pushes it to typos-lsp. Everything else typos-lsp gets when it starts, from the
+//! command and the initialization options, so changing it requires a restart.
+
+use zed_extension_api::serde_json::{Map, Value};
+
+use crate::log;
+
+/// How a changed setting reaches the server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Reload {
+    /// Sent to the running server with the workspace configuration.
+    Push,
+    /// Part of the command or the initialization options, read at startup.
+    Restart,
+    /// Used by the extension alone, which applies it by itself.
+    Internal,
+}
+
+/// The class of the settings under each JSON pointer, the longest match winning.
+const CLASSES: &[(&str, Reload)] = &[
+    ("/binary", Reload::Restart),
+    ("/initialization_options", Reload::Restart),
+    // Anything else under `settings` is for typos-lsp.
+    ("/settings", Reload::Push),
+    ("/settings/expose_meta", Reload::Push),
+    ("/settings/version", Reload::Restart),
+    ("/settings/sync_with_cli", Reload::Restart),
+    ("/settings/asset_preference", Reload::Restart),
+    ("/settings/asset_name", Reload::Restart),
+    ("/settings/update_timing", Reload::Restart),
+    ("/settings/update_check_interval_hours", Reload::Restart),
+    ("/settings/prefer_prerelease", Reload::Restart),
+    ("/settings/auto_update", Reload::Restart),
+    ("/settings/download", Reload::Restart),
+    ("/settings/env", Reload::Restart),
+    ("/settings/log_level", Reload::Restart),
+    ("/settings/log_file", Reload::Restart),
+    ("/settings/ignore_system_version", Reload::Restart),
+    ("/settings/arguments_mode", Reload::Restart),
+    ("/settings/trust_project_binary", Reload::Restart),
+    ("/settings/allow_worktree_writes", Reload::Internal),
+    // A disabled worktree gets no server, which starts again once enabled.
+    ("/settings/enabled", Reload::Restart),
+    ("/settings/dry_run", Reload::Restart),
+    ("/settings/cleanup", Reload::Restart),
+    ("/settings/keep_versions", Reload::Restart),
+    ("/settings/max_settings_size_kib", Reload::Internal),
+    // Rendered into the custom `config` initialization option.
+    ("/settings/paths_and_filenames", Reload::Restart),
+    ("/settings/config_toml", Reload::Restart),
+    ("/settings/config_files", Reload::Restart),
+    ("/settings/word_lists", Reload::Restart),
+    ("/settings/corrections_file", Reload::Restart),
+    ("/settings/corrections", Reload::Restart),
+    ("/settings/extend_words", Reload::Restart),
+    ("/settings/extend_ignore_identifiers", Reload::Restart),
+    ("/settings/exclude", Reload::Restart),
+    ("/settings/community_dictionary", Reload::Restart),
+    ("/settings/disable_default_excludes", Reload::Restart),
+    // Mapped to the `diagnosticSeverity` initialization option.
+    ("/settings/severity", Reload::Restart),
+    // Merged into the initialization options.
+    ("/settings/options_file", Reload::Restart),
+    ("/settings/verbose_startup_log", Reload::Internal),
+    ("/settings/export_lockfile", Reload::Internal),
+    ("/settings/local_metrics", Reload::Internal),
+    ("/settings/cache_report", Reload::Internal),
+    ("/settings/write_effective_config", Reload::Internal),
+];
+
+/// The class of the setting at `pointer`. Settings unknown to the extension outside of
+/// `settings` are assumed to be read at startup.
+pub(crate) fn classify(pointer: &str) -> Reload {
+    CLASSES
+        .iter()
+        .filter(|(prefix, _)| {
+            pointer
+                .strip_prefix(prefix)
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+        })
+        .max_by_key(|(prefix, _)| prefix.len())
+        .map_or(Reload::Restart, |(_, reload)| *reload)
+}
+
+/// The JSON pointers of the values that differ between `before` and `after`, down to
+/// object members. Arrays change as a whole.
+pub(crate) fn changed_pointers(before: &Value, after: &Value) -> Vec<String> {
+    let mut changed = Vec::new();
+    diff("", before, after, &mut changed);
+    changed.sort();
+    changed
+}
+
+fn diff(pointer: &str, before: &Value, after: &Value, changed: &mut Vec<String>) {
+    if before == after {
+        return;
+    }
+    let empty = Map::new();
+    match (members(before, &empty), members(after, &empty)) {
+        (Some(before), Some(after)) => {
+            let keys = before
+                .keys()
+                .chain(after.keys().filter(|key| !before.contains_key(*key)));
+            for key in keys {
+                let (old, new) = (
+                    before.get(key).unwrap_or(&Value::Null),
+                    after.get(key).unwrap_or(&Value::Null),
+                );
+                diff(&format!("{pointer}/{}", escape(key)), old, new, changed);
+            }
+        }
+        _ => changed.push(pointer.to_string()),
+    }
+}
+
+/// The members of an object, a missing setting being treated as an object of unset
+/// ones.
+fn members<'a>(value: &'a Value, empty: &'a Map<String, Value>) -> Option<&'a Map<String, Value>> {
+    match value {
+        Value::Object(members) => Some(members),
+        Value::Null => Some(empty),
+        _ => None,
+    }
+}
+
+/// A key as a JSON pointer token.
+fn escape(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+/// The setting at `pointer` as users write it, e.g. `settings.version`.
+fn setting_name(pointer: &str) -> String {
+    pointer.trim_start_matches('/').replace('/', ".")
+}
+
+/// What to log about the `changed` settings, one message for each class.
+pub(crate) fn messages(changed: &[String]) -> Vec<(log::Level, String)> {
+    let names = |class: Reload| {
+        let names: Vec<String> = changed
+            .iter()
+            .filter(|pointer| classify(pointer) == class)
+            .map(|pointer| format!("`{}`", setting_name(pointer)))
+            .collect();
+        (!names.is_empty()).then(|| names.join(", "))
+    };
+    let mut messages = Vec::new();
+    if let Some(names) = names(Reload::Restart) {
+        messages.push((
+            log::Level::Warn,
+            format!(
+                "changed {names}, which typos-lsp reads at startup: run \
+                 `editor: restart language server` to apply the change"
+            ),
+        ));
+    }
+    if let Some(names) = names(Reload::Push) {
+        messages.push((
+            log::Level::Info,
+            format!("changed {names}: sent to the running typos-lsp"),
+        ));
+    }
+    if let Some(names) = names(Reload::Internal) {
+        messages.push((
+            log::Level::Info,
+            format!("changed {names}: applied by the extension, nothing else to do"),
+        ));
+    }
+    messages
+}
+
+#[cfg(test)]
+mod tests {
+    use zed_extension_api::serde_json::json;
+
+    use super::{changed_pointers, classify, messages, Reload, CLASSES};
+    use crate::log::Level;
+
+    #[test]
+    fn classification() {
+        for (pointer, reload) in [
+            ("/binary/path", Reload::Restart),
+            ("/binary/arguments", Reload::Restart),
+            (
+                "/initialization_options/diagnosticSeverity",
+                Reload::Restart,
+            ),
+            ("/initialization_options/config", Reload::Restart),
+            ("/settings/version", Reload::Restart),
+            ("/settings/config_files", Reload::Restart),
+            ("/settings/write_effective_config", Reload::Internal),
+            ("/settings/local_metrics", Reload::Internal),
+            ("/settings/expose_meta", Reload::Push),
+            ("/settings/logLevel", Reload::Push),
+            // Not a prefix on a key boundary.
+            ("/settings/versions", Reload::Push),
+            ("/enable_lsp_tasks", Reload::Restart),
+        ] {
+            assert_eq!(classify(pointer), reload, "{pointer}");
+        }
+    }
+
+    #[test]
+    fn every_extension_setting_is_classified() {
+        let defaults = json!({
+            "verbose_startup_log": true,
+            "sync_with_cli": false,
+            "version": "latest",
+            "asset_preference": ["gnu"],
+            "asset_name": "",
+            "update_timing": "on_open",
+            "prefer_prerelease": false,
+            "auto_update": true,
+            "download": {"base_url": "", "release_api_url": ""},
+            "env": {},
+            "log_level": "warn",
+            "log_file": "",
+            "ignore_system_version": false,
+            "export_lockfile": false,
+            "local_metrics": false,
+            "expose_meta": false,
+            "enabled": true,
+            "dry_run": false,
+            "cleanup": false,
+            "max_settings_size_kib": 512,
+            "write_effective_config": false,
+            "paths_and_filenames": "inherit",
+            "config_toml": "",
+            "config_files": [],
+            "word_lists": [],
+            "corrections_file": "",
+            "corrections": {},
+            "extend_words": {},
+            "extend_ignore_identifiers": [],
+            "exclude": [],
+            "community_dictionary": false,
+            "disable_default_excludes": false,
+            "severity": "hint",
+            "options_file": "",
+            "trust_project_binary": false,
+            "allow_worktree_writes": [],
+        });
+        for key in defaults.as_object().unwrap().keys() {
+            let pointer = format!("/settings/{key}");
+            assert!(
+                CLASSES.iter().any(|(prefix, _)| *prefix == pointer),
+                "{pointer}"
+            );
+        }
+    }
+
+    #[test]
+    fn changes() {
+        let before = json!({
+            "binary": {"path": "/opt/typos-lsp"},
+            "settings": {"version": "v0.1.30", "local_metrics": false, "logLevel": "info"},
+        });
+        assert!(changed_pointers(&before, &before).is_empty());
+
+        let after = json!({
+            "binary": {"path": "/opt/typos-lsp"},
+            "initialization_options": {},
+            "settings": {
+                "version": "v0.1.31",
+                "local_metrics": false,
+                "config_files": ["a.toml"],
+                "a/b": 1,
+            },
+        });
+        assert_eq!(
+            changed_pointers(&before, &after),
+            [
+                "/settings/a~1b",
+                "/settings/config_files",
+                "/settings/logLevel",
+                "/settings/version",
+            ]
+        );
+        // Arrays change as a whole.
+        assert_eq!(
+            changed_pointers(
+                &json!({"binary": {"arguments": ["--a"]}}),
+                &json!({"binary": {"arguments": ["--a", "--b"]}}),
+            ),
+            ["/binary/arguments"]
+        );
+    }
+
+    #[test]
+    fn messages_by_class() {
+        let before = json!({"settings": {"version": "v0.1.30", "logLevel": "info"}});
+        let after = json!({
+            "binary": {"path": "/opt/typos-lsp"},
+            "settings": {"version": "v0.1.31", "logLevel": "debug", "local_metrics": true},
+        });
+        assert_eq!(
+            messages(&changed_pointers(&before, &after)),
+            [
+                (
+                    Level::Warn,
+                    "changed `binary.path`, `settings.version`, which typos-lsp reads at \
+                     startup: run `editor: restart language server` to apply the change"
+                        .to_string()
+                ),
+                (
+                    Level::Info,
+                    "changed `settings.logLevel`: sent to the running typos-lsp".to_string()
+                ),
+                (
+                    Level::Info,
+                    "changed `settings.local_metrics`: applied by the extension, nothing else \
+                     to do"
+                        .to_string()
+                ),
+            ]
+        );
+        assert!(messages(&[]).is_empty());
+    }
+}
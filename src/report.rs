@@ -0,0 +1,350 @@
+//! Human readable summaries of what the extension decided, for Zed's logs and for the
+//! plan of a dry run.
+
+use std::fmt;
+
+use zed_extension_api::serde_json::{json, Value};
+
+/// Where the typos-lsp binary comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Provenance {
+    /// Set by the user in `binary.path`.
+    Settings,
+    /// Found on the worktree's `$PATH`.
+    Path,
+    /// Reused from a previous download, without looking up the releases.
+    Cached,
+    /// Downloaded from the GitHub releases.
+    Downloaded,
+}
+
+impl fmt::Display for Provenance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Settings => "settings",
+            Self::Path => "PATH",
+            Self::Cached => "cached",
+            Self::Downloaded => "downloaded",
+        })
+    }
+}
+
+impl Provenance {
+    /// Whether the binary was installed by the extension, in its work directory.
+    pub(crate) fn is_installed(self) -> bool {
+        matches!(self, Self::Cached | Self::Downloaded)
+    }
+}
+
+/// The state line logged when a server is started.
+#[derive(Debug)]
+pub(crate) struct StartupState<'a> {
+    pub extension_version: &'a str,
+    pub server_version: Option<&'a str>,
+    pub provenance: Provenance,
+    pub binary_path: &'a str,
+    pub config: Option<&'a str>,
+    /// The initialization option carrying the configuration, if any is handed over.
+    pub channel: Option<&'a str>,
+    pub extra_words: usize,
+    pub severity: &'a str,
+}
+
+/// The fields describing the binary, shared by the startup state and the plan.
+fn write_binary(
+    f: &mut fmt::Formatter<'_>,
+    extension_version: &str,
+    server_version: Option<&str>,
+    provenance: Provenance,
+    binary_path: &str,
+) -> fmt::Result {
+    write!(
+        f,
+        "extension={extension_version} server={server} source={provenance} \
+         binary={binary_path}",
+        server = server_version.unwrap_or("unknown"),
+    )
+}
+
+impl fmt::Display for StartupState<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("starting typos-lsp: ")?;
+        write_binary(
+            f,
+            self.extension_version,
+            self.server_version,
+            self.provenance,
+            self.binary_path,
+        )?;
+        write!(
+            f,
+            " config={config} channel={channel} extra_words={words} severity={severity}",
+            config = self.config.unwrap_or("default"),
+            channel = self.channel.unwrap_or("none"),
+            words = self.extra_words,
+            severity = self.severity,
+        )
+    }
+}
+
+/// A change to the disk the extension would make to start a server, skipped by a dry
+/// run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Step {
+    /// Download a release asset into a directory of the work directory.
+    Download { url: String, directory: String },
+    /// Remove a previous installation.
+    Remove(String),
+    /// Remove a previous installation once no server of the session runs it.
+    DeferRemoval(String),
+    /// Write the custom configuration handed to typos-lsp.
+    WriteConfig { path: String, content: String },
+    /// Write a file into the worktree, relative to its root.
+    WriteWorktreeFile(String),
+    /// Write the effective configuration, at this absolute path.
+    WriteEffectiveConfig(String),
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Download { url, directory } => write!(f, "download {url} into {directory}"),
+            Self::Remove(directory) => write!(f, "remove {directory}"),
+            Self::DeferRemoval(directory) => {
+                write!(f, "remove {directory} once no server runs it")
+            }
+            Self::WriteConfig { path, content } => {
+                write!(f, "write the custom configuration {path}:")?;
+                for line in content.lines() {
+                    write!(f, "\n      {line}")?;
+                }
+                Ok(())
+            }
+            Self::WriteWorktreeFile(path) => write!(f, "write {path} into the worktree"),
+            Self::WriteEffectiveConfig(path) => {
+                write!(f, "write the effective configuration {path}")
+            }
+        }
+    }
+}
+
+/// What starting a server would take, reported instead of starting it by `dry_run`.
+#[derive(Debug)]
+pub(crate) struct Plan<'a> {
+    pub extension_version: &'a str,
+    pub server_version: Option<&'a str>,
+    pub provenance: Provenance,
+    pub binary_path: &'a str,
+    pub arguments: &'a [String],
+    pub steps: &'a [Step],
+}
+
+impl fmt::Display for Plan<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("dry run, typos-lsp was not started: ")?;
+        write_binary(
+            f,
+            self.extension_version,
+            self.server_version,
+            self.provenance,
+            self.binary_path,
+        )?;
+        write!(f, " arguments={:?}", self.arguments)?;
+        if self.steps.is_empty() {
+            return f.write_str("\nnothing would be downloaded, written or removed");
+        }
+        f.write_str("\nthe extension would:")?;
+        for step in self.steps {
+            write!(f, "\n  - {step}")?;
+        }
+        Ok(())
+    }
+}
+
+/// The key of the binary description in the workspace configuration, which typos-lsp
+/// does not know about and ignores.
+pub(crate) const META_KEY: &str = "_meta";
+
+/// The binary a server was started with, for scripts checking which one Zed runs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct BinaryMeta {
+    pub provenance: Provenance,
+    pub path: String,
+    /// The release tag, unknown for binaries found on the `$PATH`.
+    pub version: Option<String>,
+}
+
+impl BinaryMeta {
+    /// Add the description to the workspace configuration `settings`, unless no server
+    /// was started yet.
+    pub(crate) fn expose(meta: Option<&Self>, settings: Value) -> Value {
+        let Some(meta) = meta else {
+            return settings;
+        };
+        let mut settings = match settings {
+            Value::Object(settings) => settings,
+            _ => Default::default(),
+        };
+        settings.insert(
+            META_KEY.to_string(),
+            json!({
+                "extension": "typos",
+                "provenance": meta.provenance.to_string(),
+                "path": meta.path,
+                "version": meta.version,
+            }),
+        );
+        Value::Object(settings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use zed_extension_api::serde_json::json;
+
+    use super::{BinaryMeta, Plan, Provenance, StartupState, Step};
+
+    #[test]
+    fn startup_state_downloaded() {
+        let state = StartupState {
+            extension_version: "0.0.4",
+            server_version: Some("v0.1.23"),
+            provenance: Provenance::Downloaded,
+            binary_path: "typos-lsp-v0.1.23/typos-lsp",
+            config: Some("/work/proj/typos.toml"),
+            channel: Some("config"),
+            extra_words: 3,
+            severity: "Warning",
+        };
+        assert_eq!(
+            state.to_string(),
+            "starting typos-lsp: extension=0.0.4 server=v0.1.23 source=downloaded \
+             binary=typos-lsp-v0.1.23/typos-lsp config=/work/proj/typos.toml channel=config \
+             extra_words=3 severity=Warning"
+        );
+    }
+
+    #[test]
+    fn startup_state_path() {
+        let state = StartupState {
+            extension_version: "0.0.4",
+            server_version: None,
+            provenance: Provenance::Path,
+            binary_path: "/usr/bin/typos-lsp",
+            config: None,
+            channel: None,
+            extra_words: 0,
+            severity: "Error",
+        };
+        assert_eq!(
+            state.to_string(),
+            "starting typos-lsp: extension=0.0.4 server=unknown source=PATH \
+             binary=/usr/bin/typos-lsp config=default channel=none extra_words=0 severity=Error"
+        );
+    }
+
+    #[test]
+    fn plan_with_steps() {
+        let steps = [
+            Step::Download {
+                url: "https://example.com/typos-lsp-v0.1.23-x86_64-unknown-linux-gnu.tar.gz"
+                    .to_string(),
+                directory: "typos-lsp-0.1.23".to_string(),
+            },
+            Step::Remove("typos-lsp-0.1.21".to_string()),
+            Step::DeferRemoval("typos-lsp-0.1.22".to_string()),
+            Step::WriteConfig {
+                path: "/work/generated-config/0123456789abcdef.toml".to_string(),
+                content: "[default.extend-words]\nkubectl = \"kubectl\"\n".to_string(),
+            },
+            Step::WriteWorktreeFile("typos-extension.lock".to_string()),
+            Step::WriteEffectiveConfig(
+                "/work/effective-configs/0123456789abcdef/typos-effective.toml".to_string(),
+            ),
+        ];
+        let plan = Plan {
+            extension_version: "0.0.4",
+            server_version: Some("v0.1.23"),
+            provenance: Provenance::Downloaded,
+            binary_path: "typos-lsp-0.1.23/typos-lsp",
+            arguments: &["--log-level".to_string(), "warn".to_string()],
+            steps: &steps,
+        };
+        assert_eq!(
+            plan.to_string(),
+            "dry run, typos-lsp was not started: extension=0.0.4 server=v0.1.23 \
+             source=downloaded binary=typos-lsp-0.1.23/typos-lsp \
+             arguments=[\"--log-level\", \"warn\"]\n\
+             the extension would:\n  \
+             - download https://example.com/typos-lsp-v0.1.23-x86_64-unknown-linux-gnu.tar.gz \
+             into typos-lsp-0.1.23\n  \
+             - remove typos-lsp-0.1.21\n  \
+             - remove typos-lsp-0.1.22 once no server runs it\n  \
+             - write the custom configuration /work/generated-config/0123456789abcdef.toml:\n      \
+             [default.extend-words]\n      \
+             kubectl = \"kubectl\"\n  \
+             - write typos-extension.lock into the worktree\n  \
+             - write the effective configuration \
+             /work/effective-configs/0123456789abcdef/typos-effective.toml"
+        );
+    }
+
+    #[test]
+    fn plan_without_steps() {
+        let plan = Plan {
+            extension_version: "0.0.4",
+            server_version: None,
+            provenance: Provenance::Path,
+            binary_path: "/usr/bin/typos-lsp",
+            arguments: &[],
+            steps: &[],
+        };
+        assert_eq!(
+            plan.to_string(),
+            "dry run, typos-lsp was not started: extension=0.0.4 server=unknown source=PATH \
+             binary=/usr/bin/typos-lsp arguments=[]\n\
+             nothing would be downloaded, written or removed"
+        );
+    }
+
+    #[test]
+    fn exposed_meta() {
+        let meta = BinaryMeta {
+            provenance: Provenance::Downloaded,
+            path: "/work/typos-lsp-0.1.23/typos-lsp".to_string(),
+            version: Some("v0.1.23".to_string()),
+        };
+        assert_eq!(
+            BinaryMeta::expose(Some(&meta), json!({"user": 1})),
+            json!({
+                "user": 1,
+                "_meta": {
+                    "extension": "typos",
+                    "provenance": "downloaded",
+                    "path": "/work/typos-lsp-0.1.23/typos-lsp",
+                    "version": "v0.1.23",
+                },
+            })
+        );
+        let meta = BinaryMeta {
+            provenance: Provenance::Path,
+            path: "/usr/bin/typos-lsp".to_string(),
+            version: None,
+        };
+        assert_eq!(
+            BinaryMeta::expose(Some(&meta), json!(null)),
+            json!({
+                "_meta": {
+                    "extension": "typos",
+                    "provenance": "PATH",
+                    "path": "/usr/bin/typos-lsp",
+                    "version": null,
+                },
+            })
+        );
+        assert_eq!(
+            BinaryMeta::expose(None, json!({"user": 1})),
+            json!({"user": 1})
+        );
+    }
+}
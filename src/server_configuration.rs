@@ -0,0 +1,155 @@
+//! The configuration of a server, which its initialization options and its workspace
+//! configuration are both projected from.
+//!
+//! Zed asks for the two separately: the initialization options when the server starts,
+//! the workspace configuration then and whenever the settings change. Derived on their
+//! own, they drift as settings layer up, e.g. a `config` set in the settings would not
+//! be the path resolved for the initialization options, or a `diagnosticSeverity` would
+//! miss the `severity` setting. Both come from one [`ServerConfiguration`] instead, and
+//! an option present in both shapes has the same value in each: the one resolved for
+//! the initialization options.
+
+use zed_extension_api::serde_json::{Map, Value};
+
+/// What typos-lsp is configured with for one worktree.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ServerConfiguration {
+    /// The initialization options, with the configuration channel and the severity
+    /// resolved.
+    options: Map<String, Value>,
+    /// The `settings` of the server.
+    settings: Map<String, Value>,
+}
+
+impl ServerConfiguration {
+    /// The configuration of resolved initialization `options` and server `settings`,
+    /// either of which counts as empty unless it is an object.
+    pub(crate) fn new(options: Value, settings: Value) -> Self {
+        let members = |value: Value| match value {
+            Value::Object(members) => members,
+            _ => Map::new(),
+        };
+        Self {
+            options: members(options),
+            settings: members(settings),
+        }
+    }
+
+    /// The shape handed to typos-lsp when it initializes.
+    pub(crate) fn initialization_options(&self) -> Value {
+        Value::Object(self.options.clone())
+    }
+
+    /// The shape pushed to typos-lsp as workspace configuration: the settings, with the
+    /// options they share with the initialization options taken from those.
+    pub(crate) fn workspace_configuration(&self) -> Value {
+        let mut settings = self.settings.clone();
+        for (key, value) in &mut settings {
+            if let Some(option) = self.options.get(key) {
+                value.clone_from(option);
+            }
+        }
+        Value::Object(settings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use zed_extension_api::serde_json::{json, Map, Value};
+
+    use super::ServerConfiguration;
+
+    #[test]
+    fn projections() {
+        let configuration = ServerConfiguration::new(
+            json!({"config": "/work/typos.toml", "diagnosticSeverity": "Error"}),
+            json!({"config": "typos.toml", "logLevel": "debug", "version": "v0.1.23"}),
+        );
+        assert_eq!(
+            configuration.initialization_options(),
+            json!({"config": "/work/typos.toml", "diagnosticSeverity": "Error"})
+        );
+        assert_eq!(
+            configuration.workspace_configuration(),
+            json!({"config": "/work/typos.toml", "logLevel": "debug", "version": "v0.1.23"})
+        );
+
+        let empty = ServerConfiguration::new(Value::Null, json!("settings"));
+        assert_eq!(empty.initialization_options(), json!({}));
+        assert_eq!(empty.workspace_configuration(), json!({}));
+    }
+
+    /// Settings and options drawn from a few keys and values, deterministically.
+    struct Inputs(u64);
+
+    impl Inputs {
+        fn next(&mut self) -> u64 {
+            // xorshift64
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn value(&mut self, depth: u32) -> Value {
+            match self.next() % if depth == 0 { 4 } else { 6 } {
+                0 => Value::Null,
+                1 => json!(self.next() % 2 == 0),
+                2 => json!(self.next() % 100),
+                3 => json!(["Error", "Hint", "typos.toml", ""][(self.next() % 4) as usize]),
+                4 => Value::Array(
+                    (0..self.next() % 3)
+                        .map(|_| self.value(depth - 1))
+                        .collect(),
+                ),
+                _ => self.object(depth - 1),
+            }
+        }
+
+        fn object(&mut self, depth: u32) -> Value {
+            const KEYS: [&str; 6] = [
+                "config",
+                "diagnosticSeverity",
+                "logLevel",
+                "trace.server",
+                "version",
+                "expose_meta",
+            ];
+            let mut members = Map::new();
+            for _ in 0..self.next() % 5 {
+                let key = KEYS[(self.next() % KEYS.len() as u64) as usize];
+                members.insert(key.to_string(), self.value(depth));
+            }
+            Value::Object(members)
+        }
+    }
+
+    #[test]
+    fn shared_options_agree() {
+        let mut inputs = Inputs(0x5eed_1234_abcd_0001);
+        for case in 0..500 {
+            let (options, settings) = (inputs.object(2), inputs.object(2));
+            let configuration = ServerConfiguration::new(options.clone(), settings.clone());
+            let initialization = configuration.initialization_options();
+            let workspace = configuration.workspace_configuration();
+            let (initialization, workspace) = (
+                initialization.as_object().unwrap(),
+                workspace.as_object().unwrap(),
+            );
+            for (key, value) in workspace {
+                if let Some(option) = initialization.get(key) {
+                    assert_eq!(value, option, "case {case}: {key}");
+                }
+            }
+            // Nothing is lost or added by either projection.
+            assert_eq!(
+                &Value::Object(initialization.clone()),
+                &options,
+                "case {case}"
+            );
+            let keys = |value: &Value| value.as_object().unwrap().keys().cloned().collect();
+            let expected: Vec<String> = keys(&settings);
+            assert_eq!(workspace.keys().cloned().collect::<Vec<_>>(), expected);
+        }
+    }
+}
@@ -0,0 +1,135 @@
+//! The `log_level` and `log_file` settings, for typos-lsp to tell what it does.
+//!
+//! typos-lsp logs to its standard error, which Zed shows in its language server logs,
+//! at the level `RUST_LOG` sets. `log_level` sets that variable for typos-lsp alone.
+//! `log_file` appends the standard error to a file instead, e.g. to attach it to a bug
+//! report: typos-lsp has no option for it, so it is started through `/bin/sh`, which
+//! redirects it. Windows has no such shell, and the setting is ignored there.
+
+use std::path::Path;
+
+use zed_extension_api::{serde_json::Value, Os};
+
+use crate::coercion;
+
+/// The levels `log_level` accepts.
+pub(crate) const LEVELS: &[&str] = &["error", "warn", "info", "debug", "trace"];
+
+/// The shell redirecting the standard error of typos-lsp, and its script: the log file
+/// comes first, then the command.
+const SHELL: &str = "/bin/sh";
+const REDIRECT: &str = "log_file=$1; shift; exec \"$@\" 2>>\"$log_file\"";
+
+/// The level of the `log_level` setting, an error listing the accepted ones when it is
+/// none of them.
+pub(crate) fn level(value: Option<&Value>) -> Result<Option<&'static str>, String> {
+    let invalid = |found: String| {
+        let accepted: Vec<String> = LEVELS.iter().map(|level| format!("{level:?}")).collect();
+        format!(
+            "`settings.log_level` is {found}, expected one of {}",
+            accepted.join(", ")
+        )
+    };
+    match value {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::String(name)) => LEVELS
+            .iter()
+            .find(|level| **level == name)
+            .map(|level| Some(*level))
+            .ok_or_else(|| invalid(format!("{name:?}"))),
+        Some(value) => Err(invalid(format!(
+            "the {} {value}",
+            coercion::type_name(value)
+        ))),
+    }
+}
+
+/// The `RUST_LOG` variable logging typos-lsp at `level`.
+pub(crate) fn filter(level: &str) -> (String, String) {
+    ("RUST_LOG".to_string(), format!("typos_lsp={level}"))
+}
+
+/// The command and arguments starting `command` with `arguments` on `platform`, with its
+/// standard error appended to `log_file`, relative to the worktree `root` unless
+/// absolute. `None` where it cannot be redirected.
+pub(crate) fn redirect(
+    command: String,
+    arguments: Vec<String>,
+    log_file: &str,
+    root: &str,
+    platform: Os,
+) -> Option<(String, Vec<String>)> {
+    if platform == Os::Windows {
+        return None;
+    }
+    let log_file = Path::new(root).join(log_file);
+    let mut args = vec![
+        "-c".to_string(),
+        REDIRECT.to_string(),
+        SHELL.to_string(),
+        log_file.to_str()?.to_string(),
+        command,
+    ];
+    args.extend(arguments);
+    Some((SHELL.to_string(), args))
+}
+
+/// Why `log_file` is ignored on Windows.
+pub(crate) const UNSUPPORTED: &str = "ignoring `settings.log_file` on Windows, where the \
+     standard error of typos-lsp cannot be redirected: find its logs in Zed's language \
+     server logs";
+
+#[cfg(test)]
+mod tests {
+    use zed_extension_api::{serde_json::json, Os};
+
+    use super::{filter, level, redirect};
+
+    #[test]
+    fn levels() {
+        assert_eq!(level(None), Ok(None));
+        assert_eq!(level(Some(&json!("debug"))), Ok(Some("debug")));
+        let expected = "expected one of \"error\", \"warn\", \"info\", \"debug\", \"trace\"";
+        assert_eq!(
+            level(Some(&json!("verbose"))),
+            Err(format!("`settings.log_level` is \"verbose\", {expected}"))
+        );
+        assert_eq!(
+            level(Some(&json!(3))),
+            Err(format!("`settings.log_level` is the number 3, {expected}"))
+        );
+        assert_eq!(
+            filter("trace"),
+            ("RUST_LOG".to_string(), "typos_lsp=trace".to_string())
+        );
+    }
+
+    #[test]
+    fn redirection() {
+        let command = |log_file, platform| {
+            redirect(
+                "/bin/typos-lsp".to_string(),
+                vec!["--stdio".to_string()],
+                log_file,
+                "/work/project",
+                platform,
+            )
+        };
+        let (shell, args) = command("logs/typos-lsp.log", Os::Linux).unwrap();
+        assert_eq!(shell, "/bin/sh");
+        assert_eq!(
+            args,
+            [
+                "-c",
+                "log_file=$1; shift; exec \"$@\" 2>>\"$log_file\"",
+                "/bin/sh",
+                "/work/project/logs/typos-lsp.log",
+                "/bin/typos-lsp",
+                "--stdio",
+            ]
+        );
+        let (_, args) = command("/tmp/typos lsp.log", Os::Mac).unwrap();
+        assert_eq!(args[3], "/tmp/typos lsp.log");
+        assert_eq!(command("typos-lsp.log", Os::Windows), None);
+    }
+}
@@ -0,0 +1,1782 @@
+//! Settings of the extension itself, read from `lsp.typos.settings`.
+//!
+//! Zed only exposes `binary`, `initialization_options` and `settings` for a language
+//! server, so every knob that is not meant for typos-lsp lives under `settings`.
+
+use std::{collections::BTreeMap, fmt::Display, sync::Mutex};
+
+use zed_extension_api::{
+    serde_json::{self, Value},
+    settings::LspSettings,
+    LanguageServerId, Os, Worktree,
+};
+
+use crate::{
+    coercion, corrections,
+    default_args::ArgumentsMode,
+    effective_config,
+    error::TyposExtensionError,
+    excludes, hashing, ignore_patterns, json_merge, jsonc, log, messages,
+    mirror::{self, Mirror},
+    options_file::OptionsFile,
+    path_diagnostics::PathsAndFilenames,
+    paths,
+    pinning::{self, VersionDecision, VersionPreference},
+    platform_settings, reload, server_logs, settings_size,
+    update_timing::{self, UpdateTiming},
+    write_consent::{self, Consent},
+    LinuxLibc,
+};
+
+/// The project settings file, relative to the worktree root.
+const PROJECT_SETTINGS_FILE: &str = ".zed/settings.json";
+
+/// Settings a checked-in project file must not be able to set on its own, as they
+/// decide what gets executed or downloaded. They are only honored from the user
+/// settings, unless the user sets `trust_project_binary`.
+const USER_ONLY_SETTINGS: &[&str] = &[
+    "/binary/path",
+    "/binary/arguments",
+    "/settings/download",
+    "/settings/env",
+    "/settings/log_file",
+    "/settings/trust_project_binary",
+    "/settings/allow_worktree_writes",
+];
+
+/// The user-only settings granting trust, which `trust_project_binary` does not extend
+/// to the project.
+const CONSENT_SETTINGS: &[&str] = &[
+    "/settings/trust_project_binary",
+    "/settings/allow_worktree_writes",
+];
+
+/// The `diagnosticSeverity` typos-lsp gets unless the settings set another one, as
+/// misspellings are seldom errors, and drowned the actual ones in the diagnostics.
+pub(crate) const DEFAULT_SEVERITY: &str = "Hint";
+
+/// The initialization options typos-lsp gets unless the settings set them otherwise.
+pub(crate) fn default_initialization_options() -> Value {
+    serde_json::json!({"diagnosticSeverity": DEFAULT_SEVERITY})
+}
+
+/// The names the `severity` setting accepts, in any case, and the `diagnosticSeverity`
+/// typos-lsp expects for each.
+const SEVERITY_NAMES: &[(&str, &str)] = &[
+    ("error", "Error"),
+    ("warning", "Warning"),
+    ("info", "Information"),
+    ("information", "Information"),
+    ("hint", "Hint"),
+];
+
+/// The `lsp.<server>` settings of a worktree, with the scope policies applied.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct WorktreeSettings {
+    /// The `binary`, `initialization_options` and `settings` of the server.
+    lsp: Value,
+    pub extension: ExtensionSettings,
+    /// The typos-lsp version requested by the user and project settings.
+    pub version: VersionDecision,
+    /// The [`write_consent::WRITE_SETTINGS`] turned on by the project settings.
+    project_writes: Vec<&'static str>,
+    /// The `options_file` as last read, when set.
+    pub options_file: Option<OptionsFile>,
+}
+
+/// The settings Zed last parsed for a server and worktree, and the parse error last
+/// logged for them since.
+#[derive(Debug, Default)]
+struct LastParsed {
+    settings: Option<Value>,
+    error: Option<String>,
+}
+
+/// The [`LastParsed`] settings of each server and worktree, during this session.
+static LAST_PARSED: Mutex<BTreeMap<String, LastParsed>> = Mutex::new(BTreeMap::new());
+
+/// The settings to use for the server `server` in the worktree at `root`, given the
+/// `merged` settings or the error Zed returned when parsing them, and the warning to log,
+/// if any.
+///
+/// A settings file that does not parse leaves the last settings that did in place, or
+/// the defaults when none did. The error is reported once until the settings change,
+/// which either fixes it or changes the error.
+fn last_known_good(
+    server: &str,
+    root: &str,
+    merged: Result<Value, String>,
+) -> (Value, Option<String>) {
+    let mut last_parsed = LAST_PARSED.lock().unwrap_or_else(|e| e.into_inner());
+    let last = last_parsed.entry(format!("{server}\t{root}")).or_default();
+    match merged {
+        Ok(settings) => {
+            *last = LastParsed {
+                settings: Some(settings.clone()),
+                error: None,
+            };
+            (settings, None)
+        }
+        Err(error) => {
+            let warning = (last.error.as_ref() != Some(&error))
+                .then(|| messages::unparsable_settings(server, &error, last.settings.is_some()));
+            last.error = Some(error);
+            (last.settings.clone().unwrap_or(Value::Null), warning)
+        }
+    }
+}
+
+impl WorktreeSettings {
+    pub(crate) fn for_worktree(server_id: &LanguageServerId, worktree: &Worktree) -> Self {
+        let root = paths::canonical_root(&worktree.root_path());
+        let (merged, warning) = last_known_good(
+            server_id.as_ref(),
+            &root,
+            LspSettings::for_worktree(server_id.as_ref(), worktree)
+                .map(|settings| serde_json::to_value(settings).unwrap_or(Value::Null)),
+        );
+        if let Some(warning) = warning {
+            warn_once(messages::for_worktree(&root, warning));
+        }
+        let platform = zed_extension_api::current_platform().0;
+        let project = project_settings(
+            server_id.as_ref(),
+            worktree.read_text_file(PROJECT_SETTINGS_FILE),
+        );
+        let mut settings = match project {
+            Ok(project) => Self::resolve_for(merged, project, platform),
+            Err(error) => {
+                warn_once(messages::for_worktree(
+                    &root,
+                    messages::unparsable_project_settings(PROJECT_SETTINGS_FILE, &error),
+                ));
+                Self::resolve_without_project(merged, platform)
+            }
+        };
+        settings.options_file = settings
+            .extension
+            .options_file
+            .as_deref()
+            .map(|path| OptionsFile::from_file(path, worktree.read_text_file(path)));
+        settings
+    }
+
+    /// Apply the per-OS blocks of `platform` to the `merged` and `project` settings,
+    /// merge the project ones over the others, then [`Self::resolve`] them.
+    ///
+    /// Zed hands out the settings merged already, but keeps the user value of a key the
+    /// project sets to `null`, and a user per-OS block wins over the project base. The
+    /// project settings are merged again so that they win, and `null` unsets a key,
+    /// except for the user-only settings, which [`Self::resolve`] decides on.
+    fn resolve_for(merged: Value, project: Option<Value>, platform: Os) -> Self {
+        let project = project.map(|project| platform_settings::resolve(project, platform));
+        let mut merged = platform_settings::resolve(merged, platform);
+        if let Some(project) = &project {
+            let mut overrides = project.clone();
+            for pointer in USER_ONLY_SETTINGS {
+                remove_pointer(&mut overrides, pointer);
+            }
+            json_merge::deep_merge(&mut merged, overrides);
+        }
+        Self::resolve(merged, project.as_ref())
+    }
+
+    /// Like [`Self::resolve_for`], when the project settings file does not parse: any
+    /// user-only setting may come from it, so none is applied.
+    fn resolve_without_project(merged: Value, platform: Os) -> Self {
+        let mut merged = platform_settings::resolve(merged, platform);
+        for pointer in USER_ONLY_SETTINGS {
+            remove_pointer(&mut merged, pointer);
+        }
+        Self::resolve(merged, None)
+    }
+
+    /// Apply the scope policies to the `merged` settings Zed resolved for the worktree,
+    /// `project` being the part of them coming from the project settings file.
+    ///
+    /// A user-only setting whose value comes from the project is dropped: Zed does not
+    /// expose the user value it replaced, so it is treated as unset.
+    pub(crate) fn resolve(mut merged: Value, project: Option<&Value>) -> Self {
+        let from_project = |pointer: &str, merged: &Value| {
+            project
+                .and_then(|project| project.pointer(pointer))
+                .map_or(false, |value| merged.pointer(pointer) == Some(value))
+        };
+        let trusted = merged
+            .pointer("/settings/trust_project_binary")
+            .and_then(Value::as_bool)
+            .unwrap_or(false)
+            && !from_project("/settings/trust_project_binary", &merged);
+
+        for pointer in USER_ONLY_SETTINGS {
+            let grants_trust = CONSENT_SETTINGS.contains(pointer);
+            if from_project(pointer, &merged) && (grants_trust || !trusted) {
+                warn_once(messages::user_only_setting(
+                    &pointer.trim_start_matches('/').replace('/', "."),
+                    PROJECT_SETTINGS_FILE,
+                    !grants_trust,
+                ));
+                remove_pointer(&mut merged, pointer);
+            }
+        }
+
+        // Zed does not expose a user value shadowed by the project one, so the user
+        // preference is only known when the project does not set any.
+        let version_at = |settings: Option<&Value>| {
+            let version = settings.and_then(|settings| settings.pointer("/settings/version"));
+            warn_invalid(coercion::string("settings.version", version))
+                .and_then(|version| VersionPreference::parse(&version))
+        };
+        let project_version = version_at(project);
+        let user_version =
+            version_at(Some(&merged)).filter(|_| !from_project("/settings/version", &merged));
+        let version = pinning::decide(user_version.as_ref(), project_version.as_ref());
+
+        let extension = ExtensionSettings::from_value(merged.get("settings"));
+        let project_writes = write_consent::WRITE_SETTINGS
+            .iter()
+            .copied()
+            .filter(|setting| {
+                let enabled = match *setting {
+                    "export_lockfile" => extension.export_lockfile,
+                    _ => true,
+                };
+                enabled && from_project(&format!("/settings/{setting}"), &merged)
+            })
+            .collect();
+        Self {
+            lsp: merged,
+            extension,
+            version,
+            project_writes,
+            options_file: None,
+        }
+    }
+
+    /// Whether the extension may write into the worktree at `root`.
+    pub(crate) fn write_consent(&self, root: &str) -> Consent {
+        Consent::decide(
+            root,
+            &self.extension.allow_worktree_writes,
+            self.project_writes.clone(),
+        )
+    }
+
+    /// The options sent to typos-lsp when initializing it: the configured ones merged
+    /// over those of `options_file`, then over the defaults of the extension, the
+    /// `severity` setting included.
+    ///
+    /// The diagnostic severity is normalized to the name typos-lsp expects, and set
+    /// back to the default when invalid.
+    pub(crate) fn initialization_options(&self) -> Value {
+        let mut defaults = default_initialization_options();
+        if let Ok(Some(severity)) = self.severity() {
+            defaults["diagnosticSeverity"] = severity.into();
+        }
+        let mut options = defaults.clone();
+        if let Some(file) = self
+            .options_file
+            .as_ref()
+            .and_then(|file| file.options.clone())
+        {
+            json_merge::deep_merge(&mut options, file);
+        }
+        if let Some(configured) = self.lsp.get("initialization_options").cloned() {
+            json_merge::deep_merge(&mut options, configured);
+        }
+        if let Some(options) = options.as_object_mut() {
+            if let Some(Value::String(config)) = options.get_mut("config") {
+                *config = paths::normalize(config);
+            }
+            let key = "diagnosticSeverity";
+            match coercion::severity(key, options.get(key)) {
+                Ok(Some(severity)) => {
+                    options.insert(key.to_string(), severity.into());
+                }
+                Ok(None) => {}
+                Err(warning) => {
+                    warn_once(warning);
+                    options.insert(key.to_string(), defaults[key].take());
+                }
+            }
+        }
+        options
+    }
+
+    /// The `diagnosticSeverity` the `severity` setting stands for, an error listing the
+    /// accepted names when it is none of them. An explicit `diagnosticSeverity` wins.
+    pub(crate) fn severity(&self) -> Result<Option<&'static str>, String> {
+        let accepted: Vec<&str> = SEVERITY_NAMES.iter().map(|(name, _)| *name).collect();
+        match self.lsp.pointer("/settings/severity") {
+            None | Some(Value::Null) => Ok(None),
+            Some(Value::String(name)) => SEVERITY_NAMES
+                .iter()
+                .find(|(accepted, _)| accepted.eq_ignore_ascii_case(name))
+                .map(|(_, severity)| Some(*severity))
+                .ok_or_else(|| messages::invalid_severity(&format!("{name:?}"), &accepted)),
+            Some(value) => Err(messages::invalid_severity(
+                &format!("the {} {value}", coercion::type_name(value)),
+                &accepted,
+            )),
+        }
+    }
+
+    /// The level typos-lsp logs at, as the `log_level` setting sets it, an error listing
+    /// the accepted levels when it is none of them.
+    pub(crate) fn log_level(&self) -> Result<Option<&'static str>, String> {
+        server_logs::level(self.lsp.pointer("/settings/log_level"))
+    }
+
+    /// The `binary.path` of a typos-lsp binary to start instead of looking one up.
+    pub(crate) fn binary_path(&self) -> Option<String> {
+        let path = self.lsp.pointer("/binary/path");
+        warn_invalid(coercion::string("binary.path", path))
+            .map(|path| paths::normalize(&path))
+            .filter(|path| !path.is_empty())
+    }
+
+    /// The `binary.arguments` to start typos-lsp with.
+    pub(crate) fn binary_arguments(&self) -> Vec<String> {
+        let arguments = self.lsp.pointer("/binary/arguments");
+        warn_invalid(coercion::string_list("binary.arguments", arguments)).unwrap_or_default()
+    }
+
+    /// The JSON pointers of the settings that differ from `previous`, `options_file`
+    /// included when the content of the file changed.
+    pub(crate) fn changes_since(&self, previous: &Self) -> Vec<String> {
+        let mut changed = reload::changed_pointers(&previous.lsp, &self.lsp);
+        let hash = |settings: &Self| {
+            settings
+                .options_file
+                .as_ref()
+                .and_then(|file| file.sha256.clone())
+        };
+        let pointer = "/settings/options_file";
+        if hash(self) != hash(previous) && !changed.iter().any(|changed| changed == pointer) {
+            changed.push(pointer.to_string());
+            changed.sort();
+        }
+        changed
+    }
+
+    /// The subtrees of the settings larger than `max_settings_size_kib`, with their size.
+    pub(crate) fn oversized(&self) -> Vec<(String, usize)> {
+        let limit = self.extension.max_settings_size_kib.saturating_mul(1024);
+        settings_size::oversized(&self.lsp, usize::try_from(limit).unwrap_or(usize::MAX))
+    }
+
+    /// The hash of the settings, and of the content of `options_file`, telling whether
+    /// they changed.
+    pub(crate) fn fingerprint(&self) -> String {
+        match self
+            .options_file
+            .as_ref()
+            .and_then(|file| file.sha256.as_deref())
+        {
+            Some(options_file) => {
+                hashing::sha256_json(&serde_json::json!([self.lsp, options_file]))
+            }
+            None => hashing::sha256_json(&self.lsp),
+        }
+    }
+
+    /// The settings sent to typos-lsp as workspace configuration.
+    pub(crate) fn workspace_settings(&self) -> Value {
+        self.lsp.get("settings").cloned().unwrap_or_default()
+    }
+}
+
+/// The settings of `server` in the project settings file, as `read` from the worktree:
+/// `None` when there is no such file or it does not set any, an error when it does not
+/// parse.
+fn project_settings(server: &str, read: Result<String, String>) -> Result<Option<Value>, String> {
+    let Ok(text) = read else {
+        return Ok(None);
+    };
+    let project = jsonc::parse(&text)?;
+    Ok(project.get("lsp").and_then(|lsp| lsp.get(server)).cloned())
+}
+
+/// Remove the value at a JSON pointer, if any.
+fn remove_pointer(value: &mut Value, pointer: &str) {
+    let Some((parent, key)) = pointer.rsplit_once('/') else {
+        return;
+    };
+    if let Some(Value::Object(parent)) = value.pointer_mut(parent) {
+        parent.remove(key);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ExtensionSettings {
+    /// Whether typos-lsp runs for the worktree at all.
+    pub enabled: bool,
+    /// Log a summary of the resolved state whenever the server is started.
+    pub verbose_startup_log: bool,
+    /// Pin typos-lsp to the release matching the typos CLI version pinned by the project.
+    pub sync_with_cli: bool,
+    /// The Linux builds to install, in order of preference.
+    pub asset_preference: Option<Vec<LinuxLibc>>,
+    /// Write `typos-extension.lock` into the worktree, describing the installation.
+    pub export_lockfile: bool,
+    /// Record how long installation and startup phases take, in the work directory.
+    pub local_metrics: bool,
+    /// Log what the work directory holds whenever the server is started.
+    pub cache_report: bool,
+    /// Where to write the effective typos configuration, relative to the worktree root.
+    pub write_effective_config: Option<String>,
+    /// Whether typos found in file names and paths are reported.
+    pub paths_and_filenames: PathsAndFilenames,
+    /// An inline typos configuration, instead of a `config` file.
+    pub config_toml: Option<String>,
+    /// Worktree configuration files merged into one, later files winning.
+    pub config_files: Vec<String>,
+    /// Describe the running binary under `_meta` in the workspace configuration.
+    pub expose_meta: bool,
+    /// Report what starting the server would take instead of starting it.
+    pub dry_run: bool,
+    /// The release asset to install, bypassing the platform detection.
+    pub asset_name: Option<String>,
+    /// Whether a newer release is installed when a project opens or later on.
+    pub update_timing: UpdateTiming,
+    /// How long the last lookup of the latest release is trusted for, in hours.
+    pub update_check_interval_hours: u64,
+    /// Whether the latest release may be a pre-release.
+    pub prefer_prerelease: bool,
+    /// Whether newer releases are installed, rather than keeping to an installed one.
+    pub auto_update: bool,
+    /// Where releases are looked up and downloaded from instead of GitHub.
+    pub download: Mirror,
+    /// Environment variables typos-lsp is started with, over the shell environment.
+    pub env: BTreeMap<String, String>,
+    /// The file the standard error of typos-lsp is appended to, relative to the worktree
+    /// root.
+    pub log_file: Option<String>,
+    /// Whether a typos-lsp found on the `$PATH` is ignored for the managed installations.
+    pub ignore_system_version: bool,
+    /// How `binary.arguments` combine with the arguments the extension adds.
+    pub arguments_mode: ArgumentsMode,
+    /// Whether the extension removes what it no longer needs from its work directory.
+    pub cleanup: Cleanup,
+    /// Whether the extension removes what it wrote for the worktree instead of starting
+    /// the server.
+    pub remove_worktree_artifacts: bool,
+    /// How many installed typos-lsp versions the cleanup keeps, the newest ones, counting
+    /// the one installed.
+    pub keep_versions: u64,
+    /// The size of settings subtrees, in KiB, above which they are reported and their
+    /// translation cached.
+    pub max_settings_size_kib: u64,
+    /// The word lists of `.typos/words.d/` to accept, by file name.
+    pub word_lists: Vec<String>,
+    /// A file of `wrong=right` corrections, relative to the worktree root.
+    pub corrections_file: Option<String>,
+    /// Corrections by misspelling, winning over the ones of `corrections_file`.
+    pub corrections: BTreeMap<String, String>,
+    /// Words added to `[default.extend-words]`, below the corrections.
+    pub extend_words: BTreeMap<String, String>,
+    /// Patterns of identifiers typos ignores, added to `extend-ignore-identifiers-re`.
+    pub extend_ignore_identifiers: Vec<String>,
+    /// Globs of the files typos skips, added to `[files]` `extend-exclude`.
+    pub exclude: Vec<String>,
+    /// A JSON file of initialization options, relative to the worktree root.
+    pub options_file: Option<String>,
+    /// Whether the community dictionary is accepted below the configured words.
+    pub community_dictionary: bool,
+    /// Whether the files typos skips by default are checked.
+    pub disable_default_excludes: bool,
+    /// The worktrees the project settings may make the extension write into.
+    pub allow_worktree_writes: Vec<String>,
+}
+
+/// What the extension removes from its work directory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) enum Cleanup {
+    /// The previous installations and incomplete downloads, once no longer needed.
+    #[default]
+    Auto,
+    /// Nothing at all, to inspect the work directory between sessions.
+    Off,
+}
+
+impl Cleanup {
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "auto" => Some(Self::Auto),
+            "off" => Some(Self::Off),
+            _ => None,
+        }
+    }
+}
+
+impl Default for ExtensionSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            verbose_startup_log: true,
+            sync_with_cli: false,
+            asset_preference: None,
+            export_lockfile: false,
+            local_metrics: false,
+            cache_report: false,
+            write_effective_config: None,
+            paths_and_filenames: PathsAndFilenames::Inherit,
+            config_toml: None,
+            config_files: Vec::new(),
+            expose_meta: false,
+            dry_run: false,
+            asset_name: None,
+            update_timing: UpdateTiming::OnOpen,
+            update_check_interval_hours: update_timing::DEFAULT_CHECK_INTERVAL_HOURS,
+            prefer_prerelease: false,
+            auto_update: true,
+            download: Mirror::default(),
+            env: BTreeMap::new(),
+            log_file: None,
+            ignore_system_version: false,
+            arguments_mode: ArgumentsMode::Append,
+            cleanup: Cleanup::Auto,
+            remove_worktree_artifacts: false,
+            keep_versions: 1,
+            max_settings_size_kib: settings_size::DEFAULT_LIMIT_KIB,
+            word_lists: Vec::new(),
+            corrections_file: None,
+            corrections: BTreeMap::new(),
+            extend_words: BTreeMap::new(),
+            extend_ignore_identifiers: Vec::new(),
+            exclude: Vec::new(),
+            options_file: None,
+            community_dictionary: false,
+            disable_default_excludes: false,
+            allow_worktree_writes: Vec::new(),
+        }
+    }
+}
+
+impl ExtensionSettings {
+    /// Read the extension settings, using defaults for anything missing or invalid.
+    pub(crate) fn from_value(settings: Option<&Value>) -> Self {
+        let mut extension_settings = Self::default();
+        let Some(settings) = settings else {
+            return extension_settings;
+        };
+        let boolean = |key: &str| warn_invalid(coercion::boolean(key, settings.get(key)));
+
+        if let Some(enabled) = boolean("enabled") {
+            extension_settings.enabled = enabled;
+        }
+        if let Some(verbose) = boolean("verbose_startup_log") {
+            extension_settings.verbose_startup_log = verbose;
+        }
+        if let Some(sync) = boolean("sync_with_cli") {
+            extension_settings.sync_with_cli = sync;
+        }
+        if let Some(metrics) = boolean("local_metrics") {
+            extension_settings.local_metrics = metrics;
+        }
+        if let Some(report) = boolean("cache_report") {
+            extension_settings.cache_report = report;
+        }
+        if let Some(export) = boolean("export_lockfile") {
+            extension_settings.export_lockfile = export;
+        }
+        if let Some(expose) = boolean("expose_meta") {
+            extension_settings.expose_meta = expose;
+        }
+        if let Some(dry_run) = boolean("dry_run") {
+            extension_settings.dry_run = dry_run;
+        }
+        if let Some(prefer) = boolean("prefer_prerelease") {
+            extension_settings.prefer_prerelease = prefer;
+        }
+        if let Some(update) = boolean("auto_update") {
+            extension_settings.auto_update = update;
+        }
+        if let Some(ignore) = boolean("ignore_system_version") {
+            extension_settings.ignore_system_version = ignore;
+        }
+        if let Some(community) = boolean("community_dictionary") {
+            extension_settings.community_dictionary = community;
+        }
+        if let Some(disable) = boolean("disable_default_excludes") {
+            extension_settings.disable_default_excludes = disable;
+        }
+        let key = "cleanup";
+        if let Some(value) = warn_invalid(coercion::string(key, settings.get(key))) {
+            match Cleanup::parse(&value) {
+                Some(cleanup) => extension_settings.cleanup = cleanup,
+                None => warn_once(TyposExtensionError::invalid_setting(
+                    key,
+                    messages::unexpected_value("\"auto\" or \"off\"", &value),
+                )),
+            }
+        }
+        if let Some(remove) = boolean("remove_worktree_artifacts") {
+            extension_settings.remove_worktree_artifacts = remove;
+        }
+        let key = "keep_versions";
+        match warn_invalid(coercion::unsigned(key, settings.get(key))) {
+            Some(0) => warn_once(TyposExtensionError::invalid_setting(
+                key,
+                "expected at least 1, the version in use",
+            )),
+            Some(count) => extension_settings.keep_versions = count,
+            None => {}
+        }
+        let key = "max_settings_size_kib";
+        if let Some(limit) = warn_invalid(coercion::unsigned(key, settings.get(key))) {
+            extension_settings.max_settings_size_kib = limit;
+        }
+        let key = "write_effective_config";
+        extension_settings.write_effective_config = match settings.get(key) {
+            Some(Value::String(path)) if coercion::boolean(key, settings.get(key)).is_err() => {
+                Some(paths::normalize(path))
+            }
+            value => warn_invalid(coercion::boolean(key, value).map_err(|_| {
+                TyposExtensionError::invalid_setting(key, "expected a boolean or a path")
+            }))
+            .and_then(|enabled| enabled.then(|| effective_config::DEFAULT_PATH.to_string())),
+        };
+        let key = "paths_and_filenames";
+        if let Some(value) = warn_invalid(coercion::string(key, settings.get(key))) {
+            match PathsAndFilenames::parse(&value) {
+                Some(setting) => extension_settings.paths_and_filenames = setting,
+                None => warn_once(TyposExtensionError::invalid_setting(
+                    key,
+                    messages::unexpected_value("\"off\", \"hint\" or \"inherit\"", &value),
+                )),
+            }
+        }
+        let key = "update_timing";
+        if let Some(value) = warn_invalid(coercion::string(key, settings.get(key))) {
+            match UpdateTiming::parse(&value) {
+                Some(timing) => extension_settings.update_timing = timing,
+                None => warn_once(TyposExtensionError::invalid_setting(
+                    key,
+                    messages::unexpected_value("\"on_open\" or \"deferred\"", &value),
+                )),
+            }
+        }
+        let key = "update_check_interval_hours";
+        if let Some(hours) = warn_invalid(coercion::unsigned(key, settings.get(key))) {
+            extension_settings.update_check_interval_hours = hours;
+        }
+        let key = "arguments_mode";
+        if let Some(value) = warn_invalid(coercion::string(key, settings.get(key))) {
+            match ArgumentsMode::parse(&value) {
+                Some(mode) => extension_settings.arguments_mode = mode,
+                None => warn_once(TyposExtensionError::invalid_setting(
+                    key,
+                    messages::unexpected_value("\"append\", \"replace\" or \"none\"", &value),
+                )),
+            }
+        }
+        let key = mirror::SETTING;
+        if let Some(entries) = warn_invalid(coercion::string_map(key, settings.get(key))) {
+            if let Some(mirror) = warn_invalid(Mirror::parse(&entries).map(Some)) {
+                extension_settings.download = mirror;
+            }
+        }
+        if let Some(env) = warn_invalid(coercion::string_map("env", settings.get("env"))) {
+            extension_settings.env = env;
+        }
+        extension_settings.log_file =
+            warn_invalid(coercion::string("log_file", settings.get("log_file")))
+                .map(|path| paths::normalize(&path))
+                .filter(|path| !path.is_empty());
+        extension_settings.asset_name =
+            warn_invalid(coercion::string("asset_name", settings.get("asset_name")))
+                .filter(|name| !name.is_empty());
+        extension_settings.corrections_file = warn_invalid(coercion::string(
+            "corrections_file",
+            settings.get("corrections_file"),
+        ))
+        .map(|path| paths::normalize(&path))
+        .filter(|path| !path.is_empty());
+        extension_settings.options_file = warn_invalid(coercion::string(
+            "options_file",
+            settings.get("options_file"),
+        ))
+        .map(|path| paths::normalize(&path))
+        .filter(|path| !path.is_empty());
+        let key = "corrections";
+        if let Some(corrections) = warn_invalid(coercion::string_map(key, settings.get(key))) {
+            extension_settings.corrections = corrections
+                .into_iter()
+                .filter(|(wrong, right)| {
+                    let error = corrections::invalid(wrong, right);
+                    if let Some(error) = error {
+                        warn_once(messages::ignored_entry(key, "entry", wrong, error));
+                    }
+                    error.is_none()
+                })
+                .collect();
+        }
+        let key = "extend_words";
+        if let Some(words) = warn_invalid(coercion::string_map(key, settings.get(key))) {
+            extension_settings.extend_words = words
+                .into_iter()
+                .filter(|(word, _)| {
+                    let error = if word.is_empty() {
+                        Some("the word is empty")
+                    } else if word.chars().any(char::is_whitespace) {
+                        Some("the word contains spaces")
+                    } else {
+                        None
+                    };
+                    if let Some(error) = error {
+                        warn_once(messages::ignored_entry(key, "entry", word, error));
+                    }
+                    error.is_none()
+                })
+                .collect();
+        }
+        let key = "extend_ignore_identifiers";
+        if let Some(patterns) = warn_invalid(coercion::string_list(key, settings.get(key))) {
+            extension_settings.extend_ignore_identifiers = patterns
+                .into_iter()
+                .filter(|pattern| match ignore_patterns::check(pattern) {
+                    Ok(()) => true,
+                    Err(error) => {
+                        warn_once(messages::ignored_entry(key, "pattern", pattern, error));
+                        false
+                    }
+                })
+                .collect();
+        }
+        let key = "exclude";
+        if let Some(globs) = warn_invalid(coercion::string_list(key, settings.get(key))) {
+            extension_settings.exclude = globs
+                .into_iter()
+                .filter(|glob| match excludes::check(glob) {
+                    Ok(()) => true,
+                    Err(error) => {
+                        warn_once(messages::ignored_entry(key, "glob", glob, error));
+                        false
+                    }
+                })
+                .collect();
+        }
+        extension_settings.config_toml =
+            warn_invalid(coercion::string("config_toml", settings.get("config_toml")));
+        if let Some(files) = warn_invalid(coercion::string_list(
+            "config_files",
+            settings.get("config_files"),
+        )) {
+            extension_settings.config_files =
+                files.iter().map(|file| paths::normalize(file)).collect();
+        }
+        if let Some(names) = warn_invalid(coercion::string_list(
+            "word_lists",
+            settings.get("word_lists"),
+        )) {
+            extension_settings.word_lists =
+                names.iter().map(|name| paths::normalize(name)).collect();
+        }
+        let key = write_consent::SETTING;
+        if let Some(allowed) = warn_invalid(coercion::string_list(key, settings.get(key))) {
+            extension_settings.allow_worktree_writes =
+                allowed.iter().map(|root| paths::normalize(root)).collect();
+        }
+        let preference =
+            coercion::string_list("asset_preference", settings.get("asset_preference"));
+        if let Some(preference) = warn_invalid(preference) {
+            let preference: Vec<LinuxLibc> = preference
+                .iter()
+                .filter_map(|entry| {
+                    let libc = LinuxLibc::parse(entry);
+                    if libc.is_none() {
+                        warn_once(messages::unknown_asset_preference(entry));
+                    }
+                    libc
+                })
+                .collect();
+            if !preference.is_empty() {
+                extension_settings.asset_preference = Some(preference);
+            }
+        }
+        extension_settings
+    }
+}
+
+/// Log the warning of an invalid setting, which is then treated as unset.
+fn warn_invalid<T>(value: Result<Option<T>, TyposExtensionError>) -> Option<T> {
+    value.unwrap_or_else(|warning| {
+        warn_once(warning);
+        None
+    })
+}
+
+/// Log `warning` the first time it is seen: settings are read again for every start and
+/// configuration request, and each warning names the setting and the value it is about.
+fn warn_once(warning: impl Display) {
+    let warning = warning.to_string();
+    log::once(warning_key(&warning), log::Level::Warn, warning);
+}
+
+fn warning_key(warning: &str) -> String {
+    format!("setting:{warning}")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use zed_extension_api::{serde_json::json, Os};
+
+    use super::{
+        last_known_good, project_settings, warning_key, Cleanup, ExtensionSettings,
+        WorktreeSettings,
+    };
+    use crate::{
+        default_args::ArgumentsMode, error::TyposExtensionError, log, messages, mirror::Mirror,
+        options_file::OptionsFile, path_diagnostics::PathsAndFilenames, pinning::VersionPreference,
+        update_timing::UpdateTiming, write_consent::Consent, LinuxLibc,
+    };
+
+    #[test]
+    fn version_preferences() {
+        let merged = json!({"settings": {"version": "v0.1.30"}});
+        let settings = WorktreeSettings::resolve(merged.clone(), None);
+        assert_eq!(
+            settings.version.preference,
+            VersionPreference::Pinned("v0.1.30".to_string())
+        );
+        assert_eq!(settings.version.notice, None);
+
+        let settings = WorktreeSettings::resolve(merged.clone(), Some(&merged));
+        assert_eq!(
+            settings.version.preference,
+            VersionPreference::Pinned("v0.1.30".to_string())
+        );
+        assert_eq!(settings.version.notice, None);
+
+        let settings = WorktreeSettings::resolve(json!({}), None);
+        assert_eq!(settings.version.preference, VersionPreference::Latest);
+    }
+
+    #[test]
+    fn user_binary_is_honored() {
+        let merged = json!({"binary": {"path": "/opt/typos-lsp", "arguments": ["--x"]}});
+        let settings = WorktreeSettings::resolve(merged.clone(), None);
+        assert_eq!(settings.lsp, merged);
+        assert_eq!(settings.binary_path().as_deref(), Some("/opt/typos-lsp"));
+        assert_eq!(settings.binary_arguments(), vec!["--x".to_string()]);
+        let settings = WorktreeSettings::resolve(json!({"binary": {"path": ""}}), None);
+        assert_eq!(settings.binary_path(), None);
+        let project = json!({"initialization_options": {"config": "typos.toml"}});
+        let settings = WorktreeSettings::resolve(merged.clone(), Some(&project));
+        assert_eq!(settings.lsp, merged);
+    }
+
+    #[test]
+    fn project_binary_path_is_dropped() {
+        let merged = json!({
+            "binary": {"path": "./evil", "arguments": ["--x"]},
+            "initialization_options": {"diagnosticSeverity": "Hint"},
+        });
+        let project = json!({"binary": {"path": "./evil"}});
+        let settings = WorktreeSettings::resolve(merged, Some(&project));
+        assert_eq!(
+            settings.lsp,
+            json!({
+                "binary": {"arguments": ["--x"]},
+                "initialization_options": {"diagnosticSeverity": "Hint"},
+            })
+        );
+    }
+
+    #[test]
+    fn project_binary_arguments_are_dropped() {
+        let merged = json!({"binary": {"arguments": ["--config", "/tmp/x"]}});
+        let project = json!({"binary": {"arguments": ["--config", "/tmp/x"]}});
+        let settings = WorktreeSettings::resolve(merged, Some(&project));
+        assert_eq!(settings.lsp, json!({"binary": {}}));
+    }
+
+    #[test]
+    fn project_mirror_is_dropped() {
+        let merged = json!({"settings": {"download": {"base_url": "https://evil.example"}}});
+        let settings = WorktreeSettings::resolve(merged.clone(), Some(&merged));
+        assert_eq!(settings.lsp, json!({"settings": {}}));
+        assert_eq!(settings.extension.download, Default::default());
+    }
+
+    #[test]
+    fn env() {
+        assert!(ExtensionSettings::default().env.is_empty());
+        let settings = json!({"env": {"RUST_LOG": "typos_lsp=debug", "LANG": ""}});
+        assert_eq!(
+            ExtensionSettings::from_value(Some(&settings)).env,
+            BTreeMap::from([
+                ("LANG".to_string(), String::new()),
+                ("RUST_LOG".to_string(), "typos_lsp=debug".to_string()),
+            ])
+        );
+        let settings = json!({"env": {"RUST_LOG": 1}});
+        assert!(ExtensionSettings::from_value(Some(&settings))
+            .env
+            .is_empty());
+    }
+
+    #[test]
+    fn download() {
+        let settings = json!({"download": {
+            "base_url": "https://mirror.corp/typos-lsp/",
+            "release_api_url": "https://mirror.corp/api/releases",
+        }});
+        assert_eq!(
+            ExtensionSettings::from_value(Some(&settings)).download,
+            Mirror {
+                base_url: Some("https://mirror.corp/typos-lsp".to_string()),
+                release_api_url: Some("https://mirror.corp/api/releases".to_string()),
+            }
+        );
+        let settings = json!({"download": {"base_url": "mirror.corp"}});
+        assert_eq!(
+            ExtensionSettings::from_value(Some(&settings)).download,
+            Mirror::default()
+        );
+    }
+
+    #[test]
+    fn user_trust_allows_project_binary() {
+        let merged = json!({
+            "binary": {"path": "./tools/typos-lsp"},
+            "settings": {"trust_project_binary": true},
+        });
+        let project = json!({"binary": {"path": "./tools/typos-lsp"}});
+        let settings = WorktreeSettings::resolve(merged.clone(), Some(&project));
+        assert_eq!(settings.lsp, merged);
+    }
+
+    #[test]
+    fn platform_blocks_follow_scope_policy() {
+        // A project block for one platform is as untrusted as the project base.
+        let merged = json!({"binary": {
+            "path": "/usr/local/bin/typos-lsp",
+            "windows": {"path": "C:\\evil.exe"},
+        }});
+        let project = json!({"binary": {"windows": {"path": "C:\\evil.exe"}}});
+        for (platform, expected) in [
+            (Os::Windows, json!({"binary": {}})),
+            (
+                Os::Mac,
+                json!({"binary": {"path": "/usr/local/bin/typos-lsp"}}),
+            ),
+            (
+                Os::Linux,
+                json!({"binary": {"path": "/usr/local/bin/typos-lsp"}}),
+            ),
+        ] {
+            let settings =
+                WorktreeSettings::resolve_for(merged.clone(), Some(project.clone()), platform);
+            assert_eq!(settings.lsp, expected, "{platform:?}");
+        }
+
+        // A user block for one platform wins over the project base on that platform.
+        let merged = json!({"binary": {
+            "path": "./evil",
+            "windows": {"path": "C:\\tools\\typos-lsp.exe"},
+        }});
+        let project = json!({"binary": {"path": "./evil"}});
+        for (platform, expected) in [
+            (
+                Os::Windows,
+                json!({"binary": {"path": "C:\\tools\\typos-lsp.exe"}}),
+            ),
+            (Os::Mac, json!({"binary": {}})),
+            (Os::Linux, json!({"binary": {}})),
+        ] {
+            let settings =
+                WorktreeSettings::resolve_for(merged.clone(), Some(project.clone()), platform);
+            assert_eq!(settings.lsp, expected, "{platform:?}");
+        }
+
+        // Settings the project may set apply from its blocks too.
+        let project = json!({"settings": {"macos": {"version": "v0.1.30"}}});
+        for (platform, expected) in [
+            (Os::Mac, VersionPreference::Pinned("v0.1.30".to_string())),
+            (Os::Linux, VersionPreference::Latest),
+            (Os::Windows, VersionPreference::Latest),
+        ] {
+            let settings =
+                WorktreeSettings::resolve_for(project.clone(), Some(project.clone()), platform);
+            assert_eq!(settings.version.preference, expected, "{platform:?}");
+        }
+    }
+
+    #[test]
+    fn project_writes_need_consent() {
+        let withheld = |settings: Vec<&'static str>| Consent::Withheld { settings };
+        let merged = json!({"settings": {
+            "export_lockfile": true,
+            "write_effective_config": ".typos-effective.toml",
+            "local_metrics": true,
+        }});
+        let settings = WorktreeSettings::resolve(merged.clone(), None);
+        assert_eq!(settings.write_consent("/work/p"), Consent::Granted);
+
+        // The effective configuration is only written to the work directory.
+        let settings = WorktreeSettings::resolve(merged.clone(), Some(&merged));
+        assert_eq!(
+            settings.write_consent("/work/p"),
+            withheld(vec!["export_lockfile"])
+        );
+        let project = json!({"settings": {"export_lockfile": true}});
+        let settings = WorktreeSettings::resolve(merged.clone(), Some(&project));
+        assert_eq!(
+            settings.write_consent("/work/p"),
+            withheld(vec!["export_lockfile"])
+        );
+        // Turning a write off needs no consent.
+        let off = json!({"settings": {"export_lockfile": false}});
+        let settings = WorktreeSettings::resolve(off.clone(), Some(&off));
+        assert_eq!(settings.write_consent("/work/p"), Consent::Granted);
+
+        // The user allows the worktree.
+        let mut allowed = merged.clone();
+        allowed["settings"]["allow_worktree_writes"] = json!(["/work/p"]);
+        let settings = WorktreeSettings::resolve(allowed.clone(), Some(&merged));
+        assert_eq!(settings.write_consent("/work/p"), Consent::Granted);
+        assert_eq!(
+            settings.write_consent("/work/other"),
+            withheld(vec!["export_lockfile"])
+        );
+
+        // The project cannot allow itself, even when it is trusted with the binary.
+        allowed["settings"]["trust_project_binary"] = json!(true);
+        let project = json!({"settings": {
+            "export_lockfile": true,
+            "allow_worktree_writes": ["/work/p"],
+        }});
+        let settings = WorktreeSettings::resolve(allowed, Some(&project));
+        assert!(settings.extension.allow_worktree_writes.is_empty());
+        assert_eq!(
+            settings.write_consent("/work/p"),
+            withheld(vec!["export_lockfile"])
+        );
+    }
+
+    #[test]
+    fn project_cannot_trust_itself() {
+        let merged = json!({
+            "binary": {"path": "./evil"},
+            "settings": {"trust_project_binary": true},
+        });
+        let project = merged.clone();
+        let settings = WorktreeSettings::resolve(merged, Some(&project));
+        assert_eq!(settings.lsp, json!({"binary": {}, "settings": {}}));
+    }
+
+    #[test]
+    fn shared_settings_merge_from_both() {
+        let merged = json!({
+            "initialization_options": {"diagnosticSeverity": "Warning", "config": "t.toml"},
+            "settings": {"verbose_startup_log": false},
+        });
+        let project = json!({"initialization_options": {"config": "t.toml"}});
+        let settings = WorktreeSettings::resolve(merged, Some(&project));
+        assert_eq!(
+            settings.initialization_options(),
+            json!({"diagnosticSeverity": "Warning", "config": "t.toml"})
+        );
+        assert!(!settings.extension.verbose_startup_log);
+    }
+
+    #[test]
+    fn enabled() {
+        assert!(WorktreeSettings::resolve(json!({}), None).extension.enabled);
+        let settings = WorktreeSettings::resolve(json!({"settings": {"enabled": false}}), None);
+        assert!(!settings.extension.enabled);
+        let project = json!({"settings": {"enabled": false}});
+        let settings = WorktreeSettings::resolve_for(json!({}), Some(project), Os::Linux);
+        assert!(!settings.extension.enabled);
+        let project = json!({"settings": {"enabled": true}});
+        let user = json!({"settings": {"enabled": false}});
+        let settings = WorktreeSettings::resolve_for(user, Some(project), Os::Linux);
+        assert!(settings.extension.enabled);
+        // An invalid value is ignored with a warning.
+        let settings = WorktreeSettings::resolve(json!({"settings": {"enabled": "no"}}), None);
+        assert!(settings.extension.enabled);
+    }
+
+    #[test]
+    fn malformed_project_settings() {
+        let server = "typos";
+        assert_eq!(
+            project_settings(server, Err("no such file".to_string())),
+            Ok(None)
+        );
+        assert_eq!(
+            project_settings(server, Ok("{\"lsp\": {\"other\": {}}}".to_string())),
+            Ok(None)
+        );
+        assert_eq!(
+            project_settings(
+                server,
+                Ok("{\"lsp\": {\"typos\": {\"binary\": {}}}} // ok".to_string())
+            ),
+            Ok(Some(json!({"binary": {}})))
+        );
+        assert!(project_settings(server, Ok("{\"lsp\": {".to_string())).is_err());
+
+        // The project may be where these come from, so none is applied.
+        let merged = json!({
+            "binary": {"path": "/project/typos-lsp", "arguments": ["--x"]},
+            "settings": {
+                "trust_project_binary": true,
+                "allow_worktree_writes": true,
+                "logLevel": "warn",
+                "linux": {"download": false},
+            },
+        });
+        let settings = WorktreeSettings::resolve_without_project(merged, Os::Linux);
+        assert_eq!(
+            settings.lsp,
+            json!({"binary": {}, "settings": {"logLevel": "warn"}})
+        );
+    }
+
+    #[test]
+    fn project_settings_merge_over_user_ones() {
+        let merged = json!({
+            "initialization_options": {
+                "diagnosticSeverity": "Hint",
+                "config": "typos.toml",
+                "trace": {"server": "off", "format": "text"},
+            },
+            "settings": {
+                "logLevel": "info",
+                "asset_preference": ["gnu", "musl"],
+                "macos": {"logLevel": "debug"},
+            },
+            "binary": {"path": "/usr/local/bin/typos-lsp"},
+        });
+        let project = json!({
+            "initialization_options": {
+                "config": null,
+                "trace": {"server": "verbose", "format": null},
+            },
+            "settings": {"logLevel": "warn", "asset_preference": ["musl"]},
+            "binary": {"path": null},
+        });
+        let settings = WorktreeSettings::resolve_for(merged, Some(project), Os::Mac);
+        assert_eq!(
+            settings.lsp,
+            json!({
+                "initialization_options": {
+                    "diagnosticSeverity": "Hint",
+                    "trace": {"server": "verbose"},
+                },
+                "settings": {"logLevel": "warn", "asset_preference": ["musl"]},
+                // The project cannot unset the user binary either.
+                "binary": {"path": "/usr/local/bin/typos-lsp"},
+            })
+        );
+        // Both the initialization options and the workspace configuration see it.
+        assert_eq!(
+            settings.initialization_options()["trace"],
+            json!({"server": "verbose"})
+        );
+        assert_eq!(settings.initialization_options().get("config"), None);
+        assert_eq!(settings.workspace_settings()["logLevel"], json!("warn"));
+    }
+
+    #[test]
+    fn unparsable_settings() {
+        let resolve = |merged| WorktreeSettings::resolve(merged, None).extension;
+        let root = "/work/unparsable";
+        let error = "expected `,` or `}` at line 4 column 7".to_string();
+
+        // Nothing parsed yet: the defaults.
+        let (merged, warning) = last_known_good("typos", root, Err(error.clone()));
+        assert_eq!(resolve(merged), ExtensionSettings::default());
+        assert_eq!(
+            warning.as_deref(),
+            Some(
+                "the `lsp.typos` settings do not parse, using the defaults until they are \
+                 fixed: expected `,` or `}` at line 4 column 7"
+            )
+        );
+
+        // Unlike settings that are empty, which are no error.
+        let empty = json!({"binary": null, "initialization_options": null, "settings": null});
+        let (merged, warning) = last_known_good("typos", root, Ok(empty));
+        assert_eq!(resolve(merged), ExtensionSettings::default());
+        assert_eq!(warning, None);
+
+        let valid = json!({"settings": {"dry_run": true}});
+        let (merged, warning) = last_known_good("typos", root, Ok(valid.clone()));
+        assert_eq!((merged, warning), (valid.clone(), None));
+
+        let (merged, warning) = last_known_good("typos", root, Err(error.clone()));
+        assert!(resolve(merged).dry_run);
+        assert!(warning.unwrap().contains("using the last ones that did"));
+        // Reported once until the settings change.
+        let (merged, warning) = last_known_good("typos", root, Err(error.clone()));
+        assert_eq!((merged, warning), (valid.clone(), None));
+        let (_, warning) = last_known_good("typos", root, Err("trailing comma".to_string()));
+        assert!(warning.is_some());
+        last_known_good("typos", root, Ok(valid.clone()));
+        let (_, warning) = last_known_good("typos", root, Err(error.clone()));
+        assert!(warning.is_some());
+
+        // Per server and worktree.
+        let (merged, _) = last_known_good("typos", "/work/other", Err(error.clone()));
+        assert_eq!(merged, json!(null));
+        let (merged, _) = last_known_good("typos-fork", root, Err(error));
+        assert_eq!(merged, json!(null));
+    }
+
+    #[test]
+    fn defaults() {
+        assert_eq!(
+            ExtensionSettings::from_value(None),
+            ExtensionSettings::default()
+        );
+        assert!(ExtensionSettings::from_value(Some(&json!({}))).verbose_startup_log);
+    }
+
+    #[test]
+    fn invalid_settings_warn_once_per_value() {
+        let warning = |value: &str| {
+            TyposExtensionError::invalid_setting(
+                "update_timing",
+                messages::unexpected_value("\"on_open\" or \"deferred\"", value),
+            )
+            .to_string()
+        };
+        let settings = json!({"update_timing": "at_dawn"});
+        for _ in 0..2 {
+            assert_eq!(
+                ExtensionSettings::from_value(Some(&settings)).update_timing,
+                UpdateTiming::default()
+            );
+        }
+        // Already logged by the first read, and not for other values.
+        let seen = |value| !log::once(warning_key(&warning(value)), log::Level::Debug, "");
+        assert!(seen("at_dawn"));
+        assert!(!seen("at_dusk"));
+    }
+
+    #[test]
+    fn verbose_startup_log() {
+        let settings = json!({"verbose_startup_log": false});
+        assert!(!ExtensionSettings::from_value(Some(&settings)).verbose_startup_log);
+    }
+
+    #[test]
+    fn asset_preference() {
+        let settings = json!({"asset_preference": ["musl", "static", "gnu"]});
+        assert_eq!(
+            ExtensionSettings::from_value(Some(&settings)).asset_preference,
+            Some(vec![LinuxLibc::Musl, LinuxLibc::Gnu])
+        );
+        let settings = json!({"asset_preference": ["static"]});
+        assert_eq!(
+            ExtensionSettings::from_value(Some(&settings)).asset_preference,
+            None
+        );
+    }
+
+    #[test]
+    fn invalid_types_are_ignored() {
+        let settings = json!({
+            "verbose_startup_log": "false",
+            "sync_with_cli": 1,
+            "asset_preference": "musl",
+            "export_lockfile": {"nested": true},
+        });
+        let settings = ExtensionSettings::from_value(Some(&settings));
+        assert!(!settings.verbose_startup_log);
+        assert!(!settings.sync_with_cli);
+        assert!(!settings.export_lockfile);
+        assert_eq!(settings.asset_preference, Some(vec![LinuxLibc::Musl]));
+    }
+
+    #[test]
+    fn expose_meta() {
+        assert!(!ExtensionSettings::default().expose_meta);
+        let settings = json!({"expose_meta": true});
+        assert!(ExtensionSettings::from_value(Some(&settings)).expose_meta);
+    }
+
+    #[test]
+    fn prefer_prerelease() {
+        assert!(!ExtensionSettings::default().prefer_prerelease);
+        let setting = |value| {
+            ExtensionSettings::from_value(Some(&json!({"prefer_prerelease": value})))
+                .prefer_prerelease
+        };
+        assert!(setting(json!(true)));
+        assert!(setting(json!("true")));
+        assert!(!setting(json!("rc")));
+    }
+
+    #[test]
+    fn community_dictionary() {
+        assert!(!ExtensionSettings::default().community_dictionary);
+        let settings = json!({"community_dictionary": true});
+        assert!(ExtensionSettings::from_value(Some(&settings)).community_dictionary);
+    }
+
+    #[test]
+    fn disable_default_excludes() {
+        assert!(!ExtensionSettings::default().disable_default_excludes);
+        let settings = json!({"disable_default_excludes": true});
+        assert!(ExtensionSettings::from_value(Some(&settings)).disable_default_excludes);
+    }
+
+    #[test]
+    fn auto_update() {
+        assert!(ExtensionSettings::default().auto_update);
+        let settings = json!({"auto_update": false});
+        assert!(!ExtensionSettings::from_value(Some(&settings)).auto_update);
+    }
+
+    #[test]
+    fn ignore_system_version() {
+        assert!(!ExtensionSettings::default().ignore_system_version);
+        let setting = |value| {
+            ExtensionSettings::from_value(Some(&json!({"ignore_system_version": value})))
+                .ignore_system_version
+        };
+        assert!(setting(json!(true)));
+        assert!(!setting(json!(false)));
+        assert!(!setting(json!("/usr/bin")));
+    }
+
+    #[test]
+    fn asset_name() {
+        let name =
+            |value| ExtensionSettings::from_value(Some(&json!({"asset_name": value}))).asset_name;
+        assert_eq!(
+            name(json!(
+                "typos-lsp-{version}-x86_64-unknown-linux-musl.tar.gz"
+            ))
+            .as_deref(),
+            Some("typos-lsp-{version}-x86_64-unknown-linux-musl.tar.gz")
+        );
+        assert_eq!(name(json!("")), None);
+        assert_eq!(name(json!(1)), None);
+    }
+
+    #[test]
+    fn update_timing() {
+        let setting = |value| {
+            ExtensionSettings::from_value(Some(&json!({"update_timing": value}))).update_timing
+        };
+        assert_eq!(setting(json!("deferred")), UpdateTiming::Deferred);
+        assert_eq!(setting(json!("on_open")), UpdateTiming::OnOpen);
+        assert_eq!(setting(json!("idle")), UpdateTiming::OnOpen);
+        assert_eq!(setting(json!(true)), UpdateTiming::OnOpen);
+    }
+
+    #[test]
+    fn update_check_interval() {
+        let setting = |value| {
+            ExtensionSettings::from_value(Some(&json!({"update_check_interval_hours": value})))
+                .update_check_interval_hours
+        };
+        assert_eq!(setting(json!(0)), 0);
+        assert_eq!(setting(json!(168)), 168);
+        assert_eq!(setting(json!("weekly")), 24);
+        assert_eq!(setting(json!(-1)), 24);
+    }
+
+    #[test]
+    fn arguments_mode() {
+        let setting = |value| {
+            ExtensionSettings::from_value(Some(&json!({"arguments_mode": value}))).arguments_mode
+        };
+        assert_eq!(setting(json!("none")), ArgumentsMode::None);
+        assert_eq!(setting(json!("replace")), ArgumentsMode::Replace);
+        assert_eq!(setting(json!("append")), ArgumentsMode::Append);
+        assert_eq!(setting(json!("keep")), ArgumentsMode::Append);
+    }
+
+    #[test]
+    fn size_limit() {
+        let large = json!({"settings": {"extend-words": ["a".repeat(2048)]}});
+        let settings = WorktreeSettings::resolve(large.clone(), None);
+        assert_eq!(settings.extension.max_settings_size_kib, 512);
+        assert!(settings.oversized().is_empty());
+
+        let mut limited = large;
+        limited["settings"]["max_settings_size_kib"] = json!(1);
+        let settings = WorktreeSettings::resolve(limited, None);
+        assert_eq!(
+            settings.oversized(),
+            [("/settings/extend-words/0".to_string(), 2050)]
+        );
+    }
+
+    #[test]
+    fn corrections() {
+        let defaults = ExtensionSettings::default();
+        assert_eq!(defaults.corrections_file, None);
+        assert!(defaults.corrections.is_empty());
+
+        let settings = json!({
+            "corrections_file": "ci/corrections.txt",
+            "corrections": {"teh": "the", "te h": "the", "adn": ""},
+        });
+        let settings = ExtensionSettings::from_value(Some(&settings));
+        assert_eq!(
+            settings.corrections_file.as_deref(),
+            Some("ci/corrections.txt")
+        );
+        assert_eq!(
+            settings.corrections,
+            BTreeMap::from([("teh".to_string(), "the".to_string())])
+        );
+
+        let settings = json!({"corrections_file": "", "corrections": ["teh"]});
+        let settings = ExtensionSettings::from_value(Some(&settings));
+        assert_eq!(settings.corrections_file, None);
+        assert!(settings.corrections.is_empty());
+    }
+
+    #[test]
+    fn extend_settings() {
+        let defaults = ExtensionSettings::default();
+        assert!(defaults.extend_words.is_empty());
+        assert!(defaults.extend_ignore_identifiers.is_empty());
+
+        let settings = json!({
+            "extend_words": {"ODF": "ODF", "typ": "typ", "two words": "x", "ned": ""},
+            "extend_ignore_identifiers": ["ba", "(?<=x)y", "^x_"],
+        });
+        let settings = ExtensionSettings::from_value(Some(&settings));
+        assert_eq!(
+            settings.extend_words,
+            BTreeMap::from([
+                ("ODF".to_string(), "ODF".to_string()),
+                ("ned".to_string(), String::new()),
+                ("typ".to_string(), "typ".to_string()),
+            ])
+        );
+        assert_eq!(settings.extend_ignore_identifiers, ["ba", "^x_"]);
+
+        let settings = json!({"extend_words": ["ODF"], "extend_ignore_identifiers": "ba"});
+        let settings = ExtensionSettings::from_value(Some(&settings));
+        assert!(settings.extend_words.is_empty());
+        assert_eq!(settings.extend_ignore_identifiers, ["ba"]);
+    }
+
+    #[test]
+    fn exclude() {
+        assert!(ExtensionSettings::default().exclude.is_empty());
+        let settings = json!({"exclude": ["**/vendor/**", "*.pb.go", "src/[ab.rs", ""]});
+        let settings = ExtensionSettings::from_value(Some(&settings));
+        assert_eq!(settings.exclude, ["**/vendor/**", "*.pb.go"]);
+        let settings = ExtensionSettings::from_value(Some(&json!({"exclude": "CHANGELOG.md"})));
+        assert_eq!(settings.exclude, ["CHANGELOG.md"]);
+    }
+
+    #[test]
+    fn cleanup() {
+        assert_eq!(ExtensionSettings::default().cleanup, Cleanup::Auto);
+        let setting =
+            |value| ExtensionSettings::from_value(Some(&json!({"cleanup": value}))).cleanup;
+        assert_eq!(setting(json!("auto")), Cleanup::Auto);
+        assert_eq!(setting(json!("off")), Cleanup::Off);
+        assert_eq!(setting(json!("never")), Cleanup::Auto);
+        assert_eq!(setting(json!(1)), Cleanup::Auto);
+        // Removing the files written into the worktree is a setting of its own.
+        assert_eq!(setting(json!(true)), Cleanup::Auto);
+        assert!(!ExtensionSettings::default().remove_worktree_artifacts);
+        let settings = json!({"remove_worktree_artifacts": true});
+        let settings = ExtensionSettings::from_value(Some(&settings));
+        assert!(settings.remove_worktree_artifacts);
+        assert_eq!(settings.cleanup, Cleanup::Auto);
+    }
+
+    #[test]
+    fn keep_versions() {
+        assert_eq!(ExtensionSettings::default().keep_versions, 1);
+        let setting = |value| {
+            ExtensionSettings::from_value(Some(&json!({"keep_versions": value}))).keep_versions
+        };
+        assert_eq!(setting(json!(3)), 3);
+        assert_eq!(setting(json!("2")), 2);
+        assert_eq!(setting(json!(0)), 1);
+        assert_eq!(setting(json!(-1)), 1);
+    }
+
+    #[test]
+    fn dry_run() {
+        assert!(!ExtensionSettings::default().dry_run);
+        let settings = json!({"dry_run": "true"});
+        assert!(ExtensionSettings::from_value(Some(&settings)).dry_run);
+        let settings = json!({"dry_run": 2});
+        assert!(!ExtensionSettings::from_value(Some(&settings)).dry_run);
+    }
+
+    #[test]
+    fn effective_config_path() {
+        let path = |value| {
+            ExtensionSettings::from_value(Some(&json!({"write_effective_config": value})))
+                .write_effective_config
+        };
+        assert_eq!(
+            path(json!(true)),
+            Some(".zed/typos-effective.toml".to_string())
+        );
+        assert_eq!(
+            path(json!("true")),
+            Some(".zed/typos-effective.toml".to_string())
+        );
+        assert_eq!(
+            path(json!("ci/typos.toml")),
+            Some("ci/typos.toml".to_string())
+        );
+        assert_eq!(path(json!(false)), None);
+        assert_eq!(path(json!(1)), None);
+        assert_eq!(ExtensionSettings::default().write_effective_config, None);
+    }
+
+    #[test]
+    fn paths_and_filenames() {
+        let setting = |value| {
+            ExtensionSettings::from_value(Some(&json!({"paths_and_filenames": value})))
+                .paths_and_filenames
+        };
+        assert_eq!(setting(json!("off")), PathsAndFilenames::Off);
+        assert_eq!(setting(json!("loud")), PathsAndFilenames::Inherit);
+        assert_eq!(setting(json!(false)), PathsAndFilenames::Inherit);
+    }
+
+    #[test]
+    fn config_sources() {
+        let settings = json!({
+            "config_toml": "[default]\nlocale = \"en-gb\"\n",
+            "config_files": "base.toml",
+        });
+        let settings = ExtensionSettings::from_value(Some(&settings));
+        assert_eq!(
+            settings.config_toml.as_deref(),
+            Some("[default]\nlocale = \"en-gb\"\n")
+        );
+        assert_eq!(settings.config_files, vec!["base.toml".to_string()]);
+
+        let settings = json!({"config_toml": true, "config_files": [1]});
+        let settings = ExtensionSettings::from_value(Some(&settings));
+        assert_eq!(settings.config_toml, None);
+        assert!(settings.config_files.is_empty());
+    }
+
+    #[test]
+    fn user_paths_are_normalized() {
+        let merged = json!({
+            "binary": {"path": "./tools//typos-lsp"},
+            "initialization_options": {"config": "ci\\typos.toml\\"},
+            "settings": {
+                "config_files": ["./base.toml", "ci/"],
+                "word_lists": ["./infra.txt"],
+                "corrections_file": "./corrections.txt",
+                "write_effective_config": "./out//typos.toml",
+                "allow_worktree_writes": ["/work/project/"],
+            },
+        });
+        let settings = WorktreeSettings::resolve(merged, None);
+        assert_eq!(settings.binary_path().as_deref(), Some("tools/typos-lsp"));
+        assert_eq!(
+            settings.initialization_options(),
+            json!({"config": "ci\\typos.toml", "diagnosticSeverity": "Hint"})
+        );
+        let extension = &settings.extension;
+        assert_eq!(extension.config_files, ["base.toml", "ci"]);
+        assert_eq!(extension.word_lists, ["infra.txt"]);
+        assert_eq!(
+            extension.corrections_file.as_deref(),
+            Some("corrections.txt")
+        );
+        assert_eq!(
+            extension.write_effective_config.as_deref(),
+            Some("out/typos.toml")
+        );
+        assert_eq!(extension.allow_worktree_writes, ["/work/project"]);
+    }
+
+    #[test]
+    fn default_initialization_options() {
+        let settings = WorktreeSettings::resolve(json!({}), None);
+        assert_eq!(
+            settings.initialization_options(),
+            json!({"diagnosticSeverity": "Hint"})
+        );
+        // Configuring one option keeps the defaults of the others.
+        let merged = json!({"initialization_options": {"logLevel": "warn"}});
+        let settings = WorktreeSettings::resolve(merged, None);
+        assert_eq!(
+            settings.initialization_options(),
+            json!({"diagnosticSeverity": "Hint", "logLevel": "warn"})
+        );
+        let merged = json!({"initialization_options": {"diagnosticSeverity": "Error"}});
+        let settings = WorktreeSettings::resolve(merged, None);
+        assert_eq!(
+            settings.initialization_options(),
+            json!({"diagnosticSeverity": "Error"})
+        );
+    }
+
+    #[test]
+    fn options_file() {
+        let setting = |value| {
+            ExtensionSettings::from_value(Some(&json!({"options_file": value}))).options_file
+        };
+        assert_eq!(
+            setting(json!(".zed/typos-options.json")).as_deref(),
+            Some(".zed/typos-options.json")
+        );
+        assert_eq!(setting(json!("")), None);
+        assert_eq!(setting(json!(true)), None);
+
+        // Below the inline options, over the defaults and the `severity` setting.
+        let merged = json!({
+            "initialization_options": {"logLevel": "warn", "config": "typos.toml"},
+            "settings": {"options_file": "options.json", "severity": "info"},
+        });
+        let mut settings = WorktreeSettings::resolve(merged, None);
+        settings.options_file = Some(OptionsFile::parse(
+            "options.json",
+            r#"{"logLevel": "debug", "diagnosticSeverity": "Error", "trace": {"server": "off"}}"#,
+        ));
+        assert_eq!(
+            settings.initialization_options(),
+            json!({
+                "config": "typos.toml",
+                "diagnosticSeverity": "Error",
+                "logLevel": "warn",
+                "trace": {"server": "off"},
+            })
+        );
+
+        // A file that cannot be used leaves the inline options alone.
+        for file in [
+            OptionsFile::parse("options.json", "{\"logLevel\": "),
+            OptionsFile::from_file("options.json", Err("not found".into())),
+        ] {
+            settings.options_file = Some(file);
+            assert_eq!(
+                settings.initialization_options(),
+                json!({
+                    "config": "typos.toml",
+                    "diagnosticSeverity": "Information",
+                    "logLevel": "warn",
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn options_file_changes() {
+        let merged = json!({"settings": {"options_file": "options.json"}});
+        let before = WorktreeSettings::resolve(merged.clone(), None);
+        let mut after = WorktreeSettings::resolve(merged, None);
+        assert!(after.changes_since(&before).is_empty());
+
+        after.options_file = Some(OptionsFile::parse("options.json", "{}"));
+        assert_eq!(after.changes_since(&before), ["/settings/options_file"]);
+        let before = after.clone();
+        assert!(after.changes_since(&before).is_empty());
+        after.options_file = Some(OptionsFile::parse(
+            "options.json",
+            "{\"logLevel\": \"info\"}",
+        ));
+        assert_eq!(after.changes_since(&before), ["/settings/options_file"]);
+    }
+
+    #[test]
+    fn severity_setting() {
+        for (name, severity) in [
+            ("error", "Error"),
+            ("Warning", "Warning"),
+            ("info", "Information"),
+            ("INFO", "Information"),
+            ("information", "Information"),
+            ("hint", "Hint"),
+        ] {
+            let merged = json!({"settings": {"severity": name}});
+            let settings = WorktreeSettings::resolve(merged, None);
+            assert_eq!(settings.severity(), Ok(Some(severity)), "{name}");
+            assert_eq!(
+                settings.initialization_options(),
+                json!({"diagnosticSeverity": severity}),
+                "{name}"
+            );
+        }
+
+        // An explicit `diagnosticSeverity` wins, and an invalid one falls back to it.
+        let merged = json!({
+            "initialization_options": {"diagnosticSeverity": "Error"},
+            "settings": {"severity": "info"},
+        });
+        let settings = WorktreeSettings::resolve(merged, None);
+        assert_eq!(
+            settings.initialization_options(),
+            json!({"diagnosticSeverity": "Error"})
+        );
+        let merged = json!({
+            "initialization_options": {"diagnosticSeverity": "loud"},
+            "settings": {"severity": "warning"},
+        });
+        let settings = WorktreeSettings::resolve(merged, None);
+        assert_eq!(
+            settings.initialization_options(),
+            json!({"diagnosticSeverity": "Warning"})
+        );
+
+        let settings = WorktreeSettings::resolve(json!({}), None);
+        assert_eq!(settings.severity(), Ok(None));
+        let merged = json!({"settings": {"severity": "loud"}});
+        let settings = WorktreeSettings::resolve(merged, None);
+        assert_eq!(
+            settings.severity(),
+            Err(
+                "`settings.severity` is \"loud\", expected one of \"error\", \"warning\", \
+                 \"info\", \"information\", \"hint\", in any case"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            settings.initialization_options(),
+            json!({"diagnosticSeverity": "Hint"})
+        );
+        let merged = json!({"settings": {"severity": 2}});
+        let error = WorktreeSettings::resolve(merged, None)
+            .severity()
+            .unwrap_err();
+        assert!(error.contains("is the number 2"), "{error}");
+    }
+
+    #[test]
+    fn numeric_severity() {
+        let merged = json!({"initialization_options": {"diagnosticSeverity": 2, "config": "a"}});
+        let settings = WorktreeSettings::resolve(merged, None);
+        assert_eq!(
+            settings.initialization_options(),
+            json!({"diagnosticSeverity": "Warning", "config": "a"})
+        );
+
+        let merged = json!({"initialization_options": {"diagnosticSeverity": [1]}});
+        let settings = WorktreeSettings::resolve(merged, None);
+        assert_eq!(
+            settings.initialization_options(),
+            json!({"diagnosticSeverity": "Hint"})
+        );
+
+        let merged = json!({"settings": {"version": 0.1}});
+        let settings = WorktreeSettings::resolve(merged, None);
+        assert_eq!(settings.version.preference, VersionPreference::Latest);
+    }
+
+    #[test]
+    fn sync_with_cli() {
+        assert!(!ExtensionSettings::default().sync_with_cli);
+        let settings = json!({"sync_with_cli": true});
+        assert!(ExtensionSettings::from_value(Some(&settings)).sync_with_cli);
+    }
+}
@@ -0,0 +1,206 @@
+//! Settings too large to be handled as a whole on every configuration request.
+//!
+//! Zed asks for the workspace configuration whenever any setting changes, and the
+//! extension reads, compares and sends back the whole `lsp.typos` tree each time. That is
+//! fine for hand-written settings, not for megabytes of words pasted into them: those are
+//! reported, and their translation is cached by the hash of the settings.
+
+use zed_extension_api::serde_json::Value;
+
+/// The default for `max_settings_size_kib`.
+pub(crate) const DEFAULT_LIMIT_KIB: u64 = 512;
+
+/// The length of `value` serialized as compact JSON, without serializing it.
+pub(crate) fn size(value: &Value) -> usize {
+    match value {
+        Value::Null => 4,
+        Value::Bool(true) => 4,
+        Value::Bool(false) => 5,
+        Value::Number(number) => number.to_string().len(),
+        Value::String(text) => string_size(text),
+        // Brackets and commas.
+        Value::Array(items) => {
+            2 + items.len().saturating_sub(1) + items.iter().map(size).sum::<usize>()
+        }
+        // Braces, commas and colons.
+        Value::Object(members) => {
+            2 + members.len().saturating_sub(1)
+                + members
+                    .iter()
+                    .map(|(key, value)| string_size(key) + 1 + size(value))
+                    .sum::<usize>()
+        }
+    }
+}
+
+fn string_size(text: &str) -> usize {
+    2 + text
+        .chars()
+        .map(|c| match c {
+            '"' | '\\' | '\n' | '\r' | '\t' | '\u{08}' | '\u{0c}' => 2,
+            c if c < ' ' => 6,
+            c => c.len_utf8(),
+        })
+        .sum::<usize>()
+}
+
+/// The JSON pointers and sizes of the smallest subtrees of `value` larger than `limit`
+/// bytes, which are the ones to move out of the settings.
+pub(crate) fn oversized(value: &Value, limit: usize) -> Vec<(String, usize)> {
+    let mut found = Vec::new();
+    collect("", value, limit, &mut found);
+    found
+}
+
+/// Whether a subtree larger than `limit` was found within `value`.
+fn collect(pointer: &str, value: &Value, limit: usize, found: &mut Vec<(String, usize)>) -> bool {
+    let size = size(value);
+    if size <= limit {
+        return false;
+    }
+    let mut nested = false;
+    match value {
+        Value::Object(members) => {
+            for (key, member) in members {
+                let pointer = format!("{pointer}/{}", key.replace('~', "~0").replace('/', "~1"));
+                nested |= collect(&pointer, member, limit, found);
+            }
+        }
+        Value::Array(items) => {
+            for (index, item) in items.iter().enumerate() {
+                nested |= collect(&format!("{pointer}/{index}"), item, limit, found);
+            }
+        }
+        _ => {}
+    }
+    if !nested {
+        found.push((pointer.to_string(), size));
+    }
+    true
+}
+
+/// The warning about the `oversized` settings.
+pub(crate) fn warning(oversized: &[(String, usize)]) -> String {
+    let subtrees: Vec<String> = oversized
+        .iter()
+        .map(|(pointer, size)| {
+            format!(
+                "`{}` ({} KiB)",
+                pointer.trim_start_matches('/').replace('/', "."),
+                size.div_ceil(1024)
+            )
+        })
+        .collect();
+    format!(
+        "the typos settings are large: {}. Zed sends them on every change, move word lists \
+         to `.typos/words.d/` files listed in `word_lists`, or to files listed in \
+         `config_files` instead, or raise `max_settings_size_kib`",
+        subtrees.join(", ")
+    )
+}
+
+/// The workspace configuration last translated from settings too large to translate on
+/// every request, by the hash of those settings.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub(crate) struct Cache(Option<(String, Value)>);
+
+impl Cache {
+    /// The configuration translated from settings of hash `fingerprint`, if it is the
+    /// last one.
+    pub(crate) fn get(&self, fingerprint: &str) -> Option<&Value> {
+        self.0
+            .as_ref()
+            .filter(|(cached, _)| cached == fingerprint)
+            .map(|(_, value)| value)
+    }
+
+    pub(crate) fn set(&mut self, fingerprint: String, value: Value) {
+        self.0 = Some((fingerprint, value));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use zed_extension_api::serde_json::{json, Value};
+
+    use super::{oversized, size, warning, Cache};
+    use crate::hashing;
+
+    /// Settings holding a word list of `count` words, as pasted into `settings.json`.
+    fn pasted_words(count: usize) -> Value {
+        let words: Vec<String> = (0..count).map(|i| format!("word{i:06}")).collect();
+        json!({
+            "settings": {
+                "logLevel": "info",
+                "extend-words": words,
+            },
+            "initialization_options": {"diagnosticSeverity": "Hint"},
+        })
+    }
+
+    #[test]
+    fn sizes() {
+        for value in [
+            json!(null),
+            json!(false),
+            json!(-12.5),
+            json!("quote \" backslash \\ newline \n bell \u{7} é"),
+            json!([]),
+            json!({}),
+            json!({"a": [1, true, {"b": null}], "c~/": "d"}),
+            pasted_words(100),
+        ] {
+            assert_eq!(size(&value), value.to_string().len(), "{value}");
+        }
+    }
+
+    #[test]
+    fn oversized_subtrees() {
+        let settings = pasted_words(100_000);
+        assert!(size(&settings) > 1024 * 1024);
+        let limit = 512 * 1024;
+        let found = oversized(&settings, limit);
+        assert_eq!(found.len(), 1);
+        let (pointer, found_size) = &found[0];
+        assert_eq!(pointer, "/settings/extend-words");
+        assert_eq!(*found_size, size(&settings["settings"]["extend-words"]));
+        assert_eq!(
+            warning(&found),
+            format!(
+                "the typos settings are large: `settings.extend-words` ({} KiB). Zed sends them \
+                 on every change, move word lists to `.typos/words.d/` files listed in \
+                 `word_lists`, or to files listed in `config_files` instead, or raise \
+                 `max_settings_size_kib`",
+                found_size.div_ceil(1024)
+            )
+        );
+
+        assert!(oversized(&pasted_words(100), limit).is_empty());
+        // A single huge string has no smaller subtree.
+        let inline = json!({"settings": {"config_toml": "x".repeat(limit)}});
+        assert_eq!(
+            oversized(&inline, limit),
+            [("/settings/config_toml".to_string(), limit + 2)]
+        );
+    }
+
+    #[test]
+    fn cached_translation() {
+        let settings = pasted_words(50_000);
+        let fingerprint = hashing::sha256_json(&settings);
+        let mut cache = Cache::default();
+        assert_eq!(cache.get(&fingerprint), None);
+
+        cache.set(fingerprint.clone(), settings["settings"].clone());
+        assert_eq!(cache.get(&fingerprint), Some(&settings["settings"]));
+        // Same settings, same hash: served from the cache.
+        assert_eq!(
+            cache.get(&hashing::sha256_json(&pasted_words(50_000))),
+            Some(&settings["settings"])
+        );
+        // Any change misses it.
+        let mut changed = settings.clone();
+        changed["settings"]["logLevel"] = json!("debug");
+        assert_eq!(cache.get(&hashing::sha256_json(&changed)), None);
+    }
+}
@@ -0,0 +1,195 @@
+//! Other spell checkers likely running next to typos-lsp in a worktree.
+//!
+//! The extension cannot see which language servers Zed starts, but the ones checking
+//! spelling leave traces: their configuration in the worktree, or their settings under
+//! `lsp`. When found, the same word may well be flagged twice, so a one-time advisory
+//! tells how to scope typos-lsp.
+
+use std::fmt;
+
+use crate::log;
+
+/// A spell checker with a Zed extension.
+struct Spellchecker {
+    name: &'static str,
+    /// The names of its language servers, under which Zed settings configure them.
+    servers: &'static [&'static str],
+    /// Its configuration files, relative to the worktree root.
+    config_files: &'static [&'static str],
+}
+
+const KNOWN: &[Spellchecker] = &[
+    Spellchecker {
+        name: "CSpell",
+        servers: &["cspell"],
+        config_files: &[
+            "cspell.json",
+            ".cspell.json",
+            "cSpell.json",
+            ".cSpell.json",
+            "cspell.config.json",
+            "cspell.config.yaml",
+            "cspell.config.yml",
+            "cspell.yaml",
+            "cspell.yml",
+            ".vscode/cspell.json",
+        ],
+    },
+    Spellchecker {
+        name: "Harper",
+        servers: &["harper-ls"],
+        config_files: &[".harper-dictionary.txt"],
+    },
+    Spellchecker {
+        name: "Codebook",
+        servers: &["codebook"],
+        config_files: &["codebook.toml", ".codebook.toml"],
+    },
+];
+
+/// A spell checker found in a worktree, with what gave it away.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Detected {
+    pub name: &'static str,
+    pub evidence: Evidence,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Evidence {
+    /// A configuration file of the worktree.
+    ConfigFile(&'static str),
+    /// Zed settings under `lsp.<server>`.
+    Settings(&'static str),
+}
+
+impl fmt::Display for Detected {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.evidence {
+            Evidence::ConfigFile(file) => write!(f, "{} ({file})", self.name),
+            Evidence::Settings(server) => write!(f, "{} (`lsp.{server}` settings)", self.name),
+        }
+    }
+}
+
+/// The spell checkers likely active in a worktree, `has_file` telling whether a
+/// worktree-relative file exists and `configured` whether Zed settings configure a
+/// language server. Each is reported once, config files first.
+pub(crate) fn detect(
+    has_file: impl Fn(&str) -> bool,
+    configured: impl Fn(&str) -> bool,
+) -> Vec<Detected> {
+    KNOWN
+        .iter()
+        .filter_map(|spellchecker| {
+            let evidence = spellchecker
+                .config_files
+                .iter()
+                .find(|file| has_file(file))
+                .map(|file| Evidence::ConfigFile(file))
+                .or_else(|| {
+                    spellchecker
+                        .servers
+                        .iter()
+                        .find(|server| configured(server))
+                        .map(|server| Evidence::Settings(server))
+                })?;
+            Some(Detected {
+                name: spellchecker.name,
+                evidence,
+            })
+        })
+        .collect()
+}
+
+/// What to suggest to users of the `detected` spell checkers.
+pub(crate) fn advisory(detected: &[Detected]) -> Option<String> {
+    if detected.is_empty() {
+        return None;
+    }
+    let names: Vec<String> = detected.iter().map(Detected::to_string).collect();
+    Some(format!(
+        "another spell checker seems active in this worktree: {}. Words may be flagged twice; \
+         to avoid it, turn typos off for some languages with `\"language_servers\": \
+         [\"!typos\", \"...\"]` under `languages.<language>`, or lower its severity with \
+         `\"diagnosticSeverity\": \"Hint\"` under `lsp.typos.initialization_options`",
+        names.join(", ")
+    ))
+}
+
+/// Log the advisory for the `detected` spell checkers, once for the worktree at `root`,
+/// returning whether it was logged.
+pub(crate) fn advise(root: &str, detected: &[Detected]) -> bool {
+    advisory(detected).is_some_and(|advisory| {
+        log::once(
+            format!("spellcheckers:{root}"),
+            log::Level::Info,
+            format!("{root}: {advisory}"),
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{advise, advisory, detect, Detected, Evidence};
+
+    #[test]
+    fn detection() {
+        let detected = detect(
+            |file| [".cspell.json", "cspell.yaml", "codebook.toml"].contains(&file),
+            |server| server == "harper-ls" || server == "cspell",
+        );
+        assert_eq!(
+            detected,
+            [
+                Detected {
+                    name: "CSpell",
+                    evidence: Evidence::ConfigFile(".cspell.json"),
+                },
+                Detected {
+                    name: "Harper",
+                    evidence: Evidence::Settings("harper-ls"),
+                },
+                Detected {
+                    name: "Codebook",
+                    evidence: Evidence::ConfigFile("codebook.toml"),
+                },
+            ]
+        );
+        assert!(detect(|file| file == "typos.toml", |server| server == "typos").is_empty());
+    }
+
+    #[test]
+    fn advisory_content() {
+        assert_eq!(advisory(&[]), None);
+        let detected = [
+            Detected {
+                name: "CSpell",
+                evidence: Evidence::ConfigFile("cspell.json"),
+            },
+            Detected {
+                name: "Harper",
+                evidence: Evidence::Settings("harper-ls"),
+            },
+        ];
+        assert_eq!(
+            advisory(&detected).unwrap(),
+            "another spell checker seems active in this worktree: CSpell (cspell.json), \
+             Harper (`lsp.harper-ls` settings). Words may be flagged twice; to avoid it, turn \
+             typos off for some languages with `\"language_servers\": [\"!typos\", \"...\"]` \
+             under `languages.<language>`, or lower its severity with \
+             `\"diagnosticSeverity\": \"Hint\"` under `lsp.typos.initialization_options`"
+        );
+    }
+
+    #[test]
+    fn advised_once_per_worktree() {
+        let detected = [Detected {
+            name: "Codebook",
+            evidence: Evidence::ConfigFile("codebook.toml"),
+        }];
+        assert!(!advise("/work/advised", &[]));
+        assert!(advise("/work/advised", &detected));
+        assert!(!advise("/work/advised", &detected));
+        assert!(advise("/work/advised-too", &detected));
+    }
+}
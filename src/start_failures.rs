@@ -0,0 +1,108 @@
+//! Setting aside the installations of typos-lsp that exit right after starting.
+//!
+//! A binary panicking on a CPU feature it lacks, or missing a library only found at
+//! runtime, stops before initializing. Running it with `--version` right after it is
+//! installed would tell at once, but needs processes, which the extension API the
+//! extension is built against does not allow yet: that is reported as not available,
+//! and the extension only learns of the failure from the probe of its start (see
+//! [`crate::libc_fallback`]), once the server is started again. Each failed start is
+//! counted in the manifest of the installation until one succeeds. A glibc build is
+//! replaced by the musl build right away, as an older system glibc is the usual cause;
+//! any other installation is started again until it failed [`THRESHOLD`] times in a
+//! row, and then set aside for the newest installation that did not.
+
+use crate::manifest::Manifest;
+
+/// Noticing a failing installation before Zed starts it, as reported when processes
+/// cannot be run.
+pub(crate) const FEATURE: &str =
+    "detecting a typos-lsp that exits right after starting with a `--version` probe";
+
+/// What happens when processes cannot be run.
+pub(crate) const WITHOUT_PROCESSES: &str =
+    "such an installation is only noticed once the server is started again without having \
+     initialized";
+
+/// The consecutive failed starts after which an installation is set aside.
+pub(crate) const THRESHOLD: u32 = 2;
+
+/// What to start after an installation failed to start.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Strategy {
+    /// The same installation, as the failure may have had another cause.
+    Retry,
+    /// The musl build of the same release.
+    AlternateVariant,
+    /// The newest installation that was not set aside, if any.
+    PreviousVersion,
+}
+
+/// The strategy after `failures` consecutive failed starts of an installation, which
+/// may be replaced by the musl build when `musl_allowed`.
+pub(crate) fn decide(failures: u32, musl_allowed: bool) -> Strategy {
+    if musl_allowed {
+        Strategy::AlternateVariant
+    } else if failures < THRESHOLD {
+        Strategy::Retry
+    } else {
+        Strategy::PreviousVersion
+    }
+}
+
+/// Whether the installation `manifest` describes is set aside.
+pub(crate) fn set_aside(manifest: &Manifest) -> bool {
+    manifest.failed_starts >= THRESHOLD
+}
+
+/// What happens next, after the release `tag` failed to start `failures` times in a
+/// row, for strategies other than [`Strategy::AlternateVariant`].
+pub(crate) fn notice(tag: &str, failures: u32, strategy: Strategy) -> String {
+    let next = match strategy {
+        Strategy::Retry => "starting it again",
+        Strategy::AlternateVariant => "installing the musl build",
+        Strategy::PreviousVersion => {
+            "setting it aside for the newest previous installation; remove its directory \
+             from the extension directory to install it again"
+        }
+    };
+    format!(
+        "typos-lsp {tag} stopped before initializing ({failures} time{s} in a row): {next}",
+        s = if failures == 1 { "" } else { "s" },
+    )
+}
+
+/// Why the release `tag`, set aside, is not started but `previous` is.
+pub(crate) fn using_previous(tag: &str, previous: &str) -> String {
+    format!(
+        "typos-lsp {tag} failed to start {THRESHOLD} times in a row, using the installed \
+         typos-lsp {previous} instead"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decide, notice, Strategy};
+
+    #[test]
+    fn strategies() {
+        assert_eq!(decide(1, true), Strategy::AlternateVariant);
+        assert_eq!(decide(3, true), Strategy::AlternateVariant);
+        assert_eq!(decide(1, false), Strategy::Retry);
+        assert_eq!(decide(2, false), Strategy::PreviousVersion);
+        assert_eq!(decide(5, false), Strategy::PreviousVersion);
+    }
+
+    #[test]
+    fn notices() {
+        assert_eq!(
+            notice("v0.1.23", 1, Strategy::Retry),
+            "typos-lsp v0.1.23 stopped before initializing (1 time in a row): starting it again"
+        );
+        assert_eq!(
+            notice("v0.1.23", 2, Strategy::PreviousVersion),
+            "typos-lsp v0.1.23 stopped before initializing (2 times in a row): setting it \
+             aside for the newest previous installation; remove its directory from the \
+             extension directory to install it again"
+        );
+    }
+}
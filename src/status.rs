@@ -0,0 +1,216 @@
+//! The installation status Zed shows for each language server.
+//!
+//! Every update goes through [`StatusTracker`], which drops repeated statuses, keeps an
+//! installation attempt from going back to checking for updates once it downloads,
+//! and always ends an attempt on a final status so that none is left on screen. An
+//! update that failed ends on its failure even when an installed release is used
+//! instead, so that the failure shows.
+
+use std::{collections::HashMap, fmt::Display};
+
+use zed_extension_api::LanguageServerInstallationStatus;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Status {
+    /// Nothing to show, the server is ready or was never installed.
+    None,
+    CheckingForUpdate,
+    Downloading,
+    Failed(String),
+}
+
+impl Status {
+    pub(crate) fn to_zed(&self) -> LanguageServerInstallationStatus {
+        match self {
+            Self::None => LanguageServerInstallationStatus::None,
+            Self::CheckingForUpdate => LanguageServerInstallationStatus::CheckingForUpdate,
+            Self::Downloading => LanguageServerInstallationStatus::Downloading,
+            Self::Failed(error) => LanguageServerInstallationStatus::Failed(error.clone()),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Tracked {
+    /// The status last sent to Zed, `None` being what it starts with.
+    last: Option<Status>,
+    /// Whether the current attempt reached `Downloading`.
+    downloading: bool,
+    /// Whether the current attempt failed over to another binary.
+    failed_over: bool,
+}
+
+/// The statuses sent for each language server ID.
+#[derive(Debug, Default)]
+pub(crate) struct StatusTracker {
+    servers: HashMap<String, Tracked>,
+}
+
+impl StatusTracker {
+    /// Start an installation attempt for the server.
+    pub(crate) fn begin(&mut self, language_server_id: &str) {
+        let tracked = self
+            .servers
+            .entry(language_server_id.to_string())
+            .or_default();
+        tracked.downloading = false;
+        tracked.failed_over = false;
+    }
+
+    /// Whether the current attempt of the server reached `Downloading`.
+    pub(crate) fn downloading(&self, language_server_id: &str) -> bool {
+        self.servers
+            .get(language_server_id)
+            .is_some_and(|tracked| tracked.downloading)
+    }
+
+    /// End the current attempt on `error` although it hands out a binary, e.g. when an
+    /// update failed and the installed release is used instead.
+    pub(crate) fn fail_over(&mut self, language_server_id: &str, error: String) -> Option<Status> {
+        let status = self.update(language_server_id, Status::Failed(error));
+        if let Some(tracked) = self.servers.get_mut(language_server_id) {
+            tracked.failed_over = true;
+        }
+        status
+    }
+
+    /// The status to send to Zed for `status`, if any.
+    pub(crate) fn update(&mut self, language_server_id: &str, status: Status) -> Option<Status> {
+        let tracked = self
+            .servers
+            .entry(language_server_id.to_string())
+            .or_default();
+        match status {
+            Status::CheckingForUpdate if tracked.downloading => return None,
+            Status::Downloading => tracked.downloading = true,
+            Status::None | Status::Failed(_) => tracked.downloading = false,
+            Status::CheckingForUpdate => {}
+        }
+        let last = tracked.last.as_ref().unwrap_or(&Status::None);
+        if *last == status {
+            return None;
+        }
+        tracked.last = Some(status.clone());
+        Some(status)
+    }
+
+    /// End the attempt with the final status of its `result`.
+    pub(crate) fn finish<T, E: Display>(
+        &mut self,
+        language_server_id: &str,
+        result: &Result<T, E>,
+    ) -> Option<Status> {
+        let failed_over = self
+            .servers
+            .get(language_server_id)
+            .is_some_and(|tracked| tracked.failed_over);
+        let status = match result {
+            Ok(_) if failed_over => return None,
+            Ok(_) => Status::None,
+            Err(error) => Status::Failed(error.to_string()),
+        };
+        self.update(language_server_id, status)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Status, StatusTracker};
+
+    #[test]
+    fn duplicates_are_dropped() {
+        let mut tracker = StatusTracker::default();
+        tracker.begin("typos");
+        assert_eq!(tracker.update("typos", Status::None), None);
+        assert_eq!(
+            tracker.update("typos", Status::CheckingForUpdate),
+            Some(Status::CheckingForUpdate)
+        );
+        assert_eq!(tracker.update("typos", Status::CheckingForUpdate), None);
+        assert_eq!(
+            tracker.update("typos", Status::Downloading),
+            Some(Status::Downloading)
+        );
+        assert_eq!(tracker.update("typos", Status::Downloading), None);
+    }
+
+    #[test]
+    fn no_checking_after_downloading() {
+        let mut tracker = StatusTracker::default();
+        tracker.begin("typos");
+        tracker.update("typos", Status::CheckingForUpdate);
+        tracker.update("typos", Status::Downloading);
+        assert_eq!(tracker.update("typos", Status::CheckingForUpdate), None);
+        assert_eq!(
+            tracker.finish("typos", &Ok::<(), String>(())),
+            Some(Status::None)
+        );
+
+        // The next attempt starts over.
+        tracker.begin("typos");
+        assert_eq!(
+            tracker.update("typos", Status::CheckingForUpdate),
+            Some(Status::CheckingForUpdate)
+        );
+    }
+
+    #[test]
+    fn attempts_end_on_a_final_status() {
+        let mut tracker = StatusTracker::default();
+        tracker.begin("typos");
+        tracker.update("typos", Status::Downloading);
+        assert_eq!(
+            tracker.finish::<(), String>("typos", &Err("offline".to_string())),
+            Some(Status::Failed("offline".to_string()))
+        );
+        // Failing again the same way is not shown twice.
+        tracker.begin("typos");
+        assert_eq!(
+            tracker.finish::<(), String>("typos", &Err("offline".to_string())),
+            None
+        );
+        // An attempt served from the cache clears the failure, and nothing else.
+        tracker.begin("typos");
+        assert_eq!(
+            tracker.finish("typos", &Ok::<(), String>(())),
+            Some(Status::None)
+        );
+        tracker.begin("typos");
+        assert_eq!(tracker.finish("typos", &Ok::<(), String>(())), None);
+    }
+
+    #[test]
+    fn servers_are_tracked_separately() {
+        let mut tracker = StatusTracker::default();
+        tracker.begin("typos");
+        tracker.begin("typos-fork");
+        tracker.update("typos", Status::Downloading);
+        assert_eq!(
+            tracker.update("typos-fork", Status::CheckingForUpdate),
+            Some(Status::CheckingForUpdate)
+        );
+        assert_eq!(tracker.update("typos", Status::CheckingForUpdate), None);
+    }
+
+    #[test]
+    fn failed_updates_show_after_failing_over() {
+        let mut tracker = StatusTracker::default();
+        tracker.begin("typos");
+        tracker.update("typos", Status::Downloading);
+        assert!(tracker.downloading("typos"));
+        let failed = Status::Failed("update failed".to_string());
+        assert_eq!(
+            tracker.fail_over("typos", "update failed".to_string()),
+            Some(failed)
+        );
+        assert_eq!(tracker.finish("typos", &Ok::<(), String>(())), None);
+
+        // Until an attempt succeeds.
+        tracker.begin("typos");
+        assert!(!tracker.downloading("typos"));
+        assert_eq!(
+            tracker.finish("typos", &Ok::<(), String>(())),
+            Some(Status::None)
+        );
+    }
+}
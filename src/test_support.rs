@@ -0,0 +1,499 @@
+//! Helpers shared by the unit tests.
+
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex, MutexGuard,
+    },
+};
+
+use zed_extension_api::{DownloadedFileType, GithubRelease, GithubReleaseAsset};
+
+use crate::{
+    host::{Downloader, Fs, Http, ReleaseProvider, Runner, StdFs},
+    version::Version,
+};
+
+/// A directory removed with its content when dropped.
+pub(crate) struct TempDir(PathBuf);
+
+impl TempDir {
+    pub(crate) fn new(name: &str) -> Self {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let path = std::env::temp_dir().join(format!(
+            "zed-typos-{name}-{pid}-{count}",
+            pid = std::process::id(),
+            count = COUNTER.fetch_add(1, Ordering::Relaxed),
+        ));
+        fs::remove_dir_all(&path).ok();
+        fs::create_dir_all(&path).expect("could not create temporary directory");
+        Self(path)
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        fs::remove_dir_all(&self.0).ok();
+    }
+}
+
+/// Releases served from memory, the last one being the latest, or the last one that is
+/// not a pre-release.
+#[derive(Clone, Default)]
+pub(crate) struct FakeReleases(Arc<Mutex<FakeReleasesState>>);
+
+#[derive(Default)]
+struct FakeReleasesState {
+    releases: Vec<GithubRelease>,
+    failure: Option<String>,
+    lookups: usize,
+}
+
+impl FakeReleases {
+    /// Publish a release with assets for every platform.
+    pub(crate) fn publish(&self, version: &str) {
+        let names = [
+            "x86_64-unknown-linux-gnu.tar.gz",
+            "x86_64-unknown-linux-musl.tar.gz",
+            "aarch64-apple-darwin.tar.gz",
+            "x86_64-pc-windows-msvc.zip",
+        ]
+        .map(|target| format!("typos-lsp-{version}-{target}"));
+        self.publish_assets(version, &names.each_ref().map(String::as_str));
+    }
+
+    /// Publish a release with the assets `names` alone.
+    pub(crate) fn publish_assets(&self, version: &str, names: &[&str]) {
+        let assets = names
+            .iter()
+            .map(|name| GithubReleaseAsset {
+                download_url: format!("https://example.com/{name}"),
+                name: name.to_string(),
+            })
+            .collect();
+        self.state().releases.push(GithubRelease {
+            version: version.to_string(),
+            assets,
+        });
+    }
+
+    /// Add the asset `name` to the published release `version`.
+    pub(crate) fn publish_asset(&self, version: &str, name: &str) {
+        let mut state = self.state();
+        let release = state
+            .releases
+            .iter_mut()
+            .find(|release| release.version == version)
+            .expect("release not published");
+        release.assets.push(GithubReleaseAsset {
+            download_url: format!("https://example.com/{name}"),
+            name: name.to_string(),
+        });
+    }
+
+    /// Make the following lookups fail with `error`, or succeed again with `None`.
+    pub(crate) fn fail_with(&self, error: Option<&str>) {
+        self.state().failure = error.map(str::to_string);
+    }
+
+    pub(crate) fn lookups(&self) -> usize {
+        self.state().lookups
+    }
+
+    fn state(&self) -> MutexGuard<'_, FakeReleasesState> {
+        self.0.lock().expect("fake releases poisoned")
+    }
+}
+
+impl ReleaseProvider for FakeReleases {
+    fn latest_release(
+        &self,
+        _repository: &str,
+        pre_release: bool,
+    ) -> Result<GithubRelease, String> {
+        let mut state = self.state();
+        state.lookups += 1;
+        if let Some(error) = &state.failure {
+            return Err(error.clone());
+        }
+        state
+            .releases
+            .iter()
+            .rev()
+            .find(|release| {
+                pre_release
+                    || Version::parse(&release.version)
+                        .map_or(true, |version| version.pre.is_none())
+            })
+            .cloned()
+            .ok_or("no release".to_string())
+    }
+
+    fn release_by_tag(&self, _repository: &str, tag: &str) -> Result<GithubRelease, String> {
+        let mut state = self.state();
+        state.lookups += 1;
+        if let Some(error) = &state.failure {
+            return Err(error.clone());
+        }
+        state
+            .releases
+            .iter()
+            .find(|release| release.version == tag)
+            .cloned()
+            .ok_or("release not found".to_string())
+    }
+}
+
+/// "Extracts" an archive into a directory under `root` as a `typos-lsp` file holding
+/// the download URL, not executable until made so. Files downloaded as they are hold
+/// the URL too. Either holds what is served at the URL instead, if anything.
+#[derive(Clone)]
+pub(crate) struct FakeDownloader {
+    root: PathBuf,
+    state: Arc<Mutex<FakeDownloaderState>>,
+}
+
+#[derive(Default)]
+struct FakeDownloaderState {
+    failure: Option<String>,
+    /// How many more downloads fail, when not all of them do.
+    failures_left: Option<usize>,
+    downloads: Vec<String>,
+    entries: Option<Vec<String>>,
+    served: HashMap<String, Vec<u8>>,
+    /// Why files cannot be made executable, if they cannot.
+    executable_failure: Option<String>,
+}
+
+impl FakeDownloader {
+    pub(crate) fn new(root: &Path) -> Self {
+        Self {
+            root: root.to_path_buf(),
+            state: Default::default(),
+        }
+    }
+
+    /// Make the following downloads fail with `error`, or succeed again with `None`.
+    pub(crate) fn fail_with(&self, error: Option<&str>) {
+        let mut state = self.state();
+        state.failure = error.map(str::to_string);
+        state.failures_left = None;
+    }
+
+    /// Make the next `count` downloads fail with `error`, and the following ones succeed.
+    pub(crate) fn fail_times(&self, count: usize, error: &str) {
+        let mut state = self.state();
+        state.failure = (count > 0).then(|| error.to_string());
+        state.failures_left = Some(count);
+    }
+
+    /// Extract the following downloads to `entries`, relative to the destination,
+    /// rather than to a lone `typos-lsp` binary.
+    pub(crate) fn extract_to(&self, entries: &[&str]) {
+        self.state().entries = Some(entries.iter().map(|entry| entry.to_string()).collect());
+    }
+
+    /// Make marking files as executable fail with `error`, or succeed again with `None`.
+    pub(crate) fn fail_make_executable(&self, error: Option<&str>) {
+        self.state().executable_failure = error.map(str::to_string);
+    }
+
+    /// Serve `content` at `url`, as the file downloaded or the entries extracted.
+    pub(crate) fn serve(&self, url: &str, content: &[u8]) {
+        self.state()
+            .served
+            .insert(url.to_string(), content.to_vec());
+    }
+
+    /// The URLs downloaded so far.
+    pub(crate) fn downloads(&self) -> Vec<String> {
+        self.state().downloads.clone()
+    }
+
+    fn state(&self) -> MutexGuard<'_, FakeDownloaderState> {
+        self.state.lock().expect("fake downloader poisoned")
+    }
+}
+
+impl Downloader for FakeDownloader {
+    fn download(
+        &self,
+        url: &str,
+        destination: &str,
+        file_type: DownloadedFileType,
+    ) -> Result<(), String> {
+        let mut state = self.state();
+        let uncompressed = matches!(file_type, DownloadedFileType::Uncompressed);
+        if let Some(error) = &state.failure {
+            // Like a download or an extraction failing halfway.
+            if uncompressed {
+                fs::write(self.root.join(destination), "").ok();
+            } else {
+                fs::create_dir_all(self.root.join(destination)).ok();
+            }
+            let error = error.clone();
+            if let Some(left) = state.failures_left.as_mut() {
+                *left -= 1;
+                if *left == 0 {
+                    state.failure = None;
+                }
+            }
+            return Err(error);
+        }
+        state.downloads.push(url.to_string());
+        if uncompressed {
+            let content = state
+                .served
+                .get(url)
+                .cloned()
+                .unwrap_or_else(|| url.as_bytes().to_vec());
+            return fs::write(self.root.join(destination), content).map_err(|e| e.to_string());
+        }
+        let directory = self.root.join(destination);
+        let entries = state
+            .entries
+            .clone()
+            .unwrap_or_else(|| vec!["typos-lsp".to_string()]);
+        for entry in entries {
+            let path = directory.join(entry);
+            fs::create_dir_all(path.parent().unwrap_or(&directory)).map_err(|e| e.to_string())?;
+            let content = state.served.get(url).map_or(url.as_bytes(), Vec::as_slice);
+            fs::write(path, content).map_err(|e| e.to_string())?;
+        }
+        Ok(())
+    }
+
+    fn make_executable(&self, path: &str) -> Result<(), String> {
+        if let Some(error) = &self.state().executable_failure {
+            return Err(error.clone());
+        }
+        let path = self.root.join(path);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o755))
+                .map_err(|e| e.to_string())?;
+        }
+        #[cfg(not(unix))]
+        fs::metadata(&path).map_err(|e| e.to_string())?;
+        Ok(())
+    }
+}
+
+/// Serves bodies from memory, failing with "404 Not Found" for other URLs.
+#[derive(Clone, Default)]
+pub(crate) struct FakeHttp(Arc<Mutex<FakeHttpState>>);
+
+#[derive(Default)]
+struct FakeHttpState {
+    served: HashMap<String, String>,
+    requests: Vec<String>,
+}
+
+impl FakeHttp {
+    /// Serve `body` at `url`.
+    pub(crate) fn serve(&self, url: &str, body: &str) {
+        self.state()
+            .served
+            .insert(url.to_string(), body.to_string());
+    }
+
+    /// The URLs requested so far.
+    pub(crate) fn requests(&self) -> Vec<String> {
+        self.state().requests.clone()
+    }
+
+    fn state(&self) -> MutexGuard<'_, FakeHttpState> {
+        self.0.lock().expect("fake HTTP client poisoned")
+    }
+}
+
+impl Http for FakeHttp {
+    fn get(&self, url: &str) -> Result<Vec<u8>, String> {
+        let mut state = self.state();
+        state.requests.push(url.to_string());
+        state
+            .served
+            .get(url)
+            .map(|body| body.as_bytes().to_vec())
+            .ok_or_else(|| "404 Not Found".to_string())
+    }
+}
+
+/// "Runs" the binaries under `root` by printing their content, failing for empty ones
+/// like a binary crashing right away.
+#[derive(Clone)]
+pub(crate) struct FakeRunner {
+    root: PathBuf,
+    runs: Arc<Mutex<Vec<String>>>,
+}
+
+impl FakeRunner {
+    pub(crate) fn new(root: &Path) -> Self {
+        Self {
+            root: root.to_path_buf(),
+            runs: Default::default(),
+        }
+    }
+
+    /// The binaries run so far.
+    pub(crate) fn runs(&self) -> Vec<String> {
+        self.runs.lock().expect("fake runner poisoned").clone()
+    }
+}
+
+impl Runner for FakeRunner {
+    fn available(&self) -> bool {
+        true
+    }
+
+    fn run(&self, path: &str, _arguments: &[&str]) -> Result<String, String> {
+        self.runs
+            .lock()
+            .expect("fake runner poisoned")
+            .push(path.to_string());
+        match fs::read_to_string(self.root.join(path)) {
+            Ok(output) if !output.is_empty() => Ok(output),
+            Ok(_) => Err("exit status: 101".to_string()),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+}
+
+/// A filesystem whose writes all fail, like a read-only overlay.
+pub(crate) struct ReadOnlyFs(pub StdFs);
+
+impl Fs for ReadOnlyFs {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        self.0.read(path)
+    }
+
+    fn write(&self, _path: &Path, _contents: &[u8]) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            "read-only file system",
+        ))
+    }
+
+    fn create_new(&self, _path: &Path, _contents: &[u8]) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            "read-only file system",
+        ))
+    }
+
+    fn create_dir_all(&self, _path: &Path) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            "read-only file system",
+        ))
+    }
+
+    fn is_file(&self, path: &Path) -> bool {
+        self.0.is_file(path)
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<String>> {
+        self.0.read_dir(path)
+    }
+
+    fn rename(&self, _from: &Path, _to: &Path) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            "read-only file system",
+        ))
+    }
+
+    fn remove_file(&self, path: &Path) -> io::Result<()> {
+        self.0.remove_file(path)
+    }
+
+    fn remove_dir_all(&self, path: &Path) -> io::Result<()> {
+        self.0.remove_dir_all(path)
+    }
+}
+
+/// A filesystem reporting the available space, which the removal of a directory frees
+/// a fixed amount of. Space checks and removals are recorded in order.
+#[derive(Clone)]
+pub(crate) struct SpaceFs(Arc<SpaceFsState>);
+
+struct SpaceFsState {
+    fs: StdFs,
+    available: Mutex<u64>,
+    freed_per_directory: u64,
+    events: Mutex<Vec<String>>,
+}
+
+impl SpaceFs {
+    pub(crate) fn new(root: &Path, available: u64, freed_per_directory: u64) -> Self {
+        Self(Arc::new(SpaceFsState {
+            fs: StdFs::new(root),
+            available: Mutex::new(available),
+            freed_per_directory,
+            events: Mutex::new(Vec::new()),
+        }))
+    }
+
+    pub(crate) fn events(&self) -> Vec<String> {
+        self.0.events.lock().unwrap().clone()
+    }
+
+    fn record(&self, event: String) {
+        self.0.events.lock().unwrap().push(event);
+    }
+}
+
+impl Fs for SpaceFs {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        self.0.fs.read(path)
+    }
+
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        self.0.fs.write(path, contents)
+    }
+
+    fn create_new(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        self.0.fs.create_new(path, contents)
+    }
+
+    fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        self.0.fs.create_dir_all(path)
+    }
+
+    fn is_file(&self, path: &Path) -> bool {
+        self.0.fs.is_file(path)
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<String>> {
+        self.0.fs.read_dir(path)
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        self.0.fs.rename(from, to)
+    }
+
+    fn remove_file(&self, path: &Path) -> io::Result<()> {
+        self.0.fs.remove_file(path)
+    }
+
+    fn remove_dir_all(&self, path: &Path) -> io::Result<()> {
+        self.0.fs.remove_dir_all(path)?;
+        *self.0.available.lock().unwrap() += self.0.freed_per_directory;
+        self.record(format!("remove {}", path.display()));
+        Ok(())
+    }
+
+    fn available_space(&self) -> Option<u64> {
+        let available = *self.0.available.lock().unwrap();
+        self.record(format!("available {available}"));
+        Some(available)
+    }
+}
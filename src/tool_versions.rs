@@ -0,0 +1,47 @@
+//! typos-lsp versions declared for asdf or mise in a `.tool-versions` file.
+//!
+//! Each line names a tool followed by its versions, the first one being preferred, and
+//! `#` starts a comment. Only `typos-lsp` lines are read; versions that are no release,
+//! such as `system`, `ref:<commit>` or `path:<dir>`, do not pin anything.
+
+/// The file, relative to the worktree root.
+pub(crate) const FILE_NAME: &str = ".tool-versions";
+
+const TOOL: &str = "typos-lsp";
+
+/// The release tag pinned for typos-lsp by the content of a `.tool-versions` file.
+pub(crate) fn parse(content: &str) -> Option<String> {
+    let line = content
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or_default())
+        .find(|line| line.split_whitespace().next() == Some(TOOL))?;
+    let version = line.split_whitespace().nth(1)?;
+    if version == "system" || version.contains(':') {
+        return None;
+    }
+    Some(match version.strip_prefix('v') {
+        Some(_) => version.to_string(),
+        None => format!("v{version}"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse;
+
+    #[test]
+    fn parsing() {
+        assert_eq!(parse("typos-lsp 0.1.27\n"), Some("v0.1.27".to_string()));
+        assert_eq!(
+            parse("# Pinned for the team\nnodejs 20.11.0\n\n  typos-lsp\tv0.1.27   0.1.26 # fallback\n"),
+            Some("v0.1.27".to_string())
+        );
+        assert_eq!(parse("typos 1.24.1\n"), None);
+        assert_eq!(parse("# typos-lsp 0.1.27\n"), None);
+        assert_eq!(parse("typos-lsp\n"), None);
+        assert_eq!(parse("typos-lsp system\n"), None);
+        assert_eq!(parse("typos-lsp ref:4f2c1a9\n"), None);
+        assert_eq!(parse("typos-lsp path:/opt/typos-lsp\n"), None);
+        assert_eq!(parse(""), None);
+    }
+}
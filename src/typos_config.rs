@@ -0,0 +1,893 @@
+//! typos configurations, generated from the extension settings or merged from files.
+//!
+//! typos-lsp reads a single custom configuration file next to the ones it discovers in
+//! the worktree, so the options derived from settings are rendered into one file in
+//! the work directory and passed as the `config` initialization option.
+//!
+//! Only the subset of TOML typos configurations use is parsed: tables, dotted and
+//! quoted keys, strings, booleans, integers and arrays.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Write,
+    iter::Peekable,
+    path::Path,
+    str::Chars,
+};
+
+use crate::{case_fold, hashing, host::Fs, messages};
+
+/// Where generated configurations are kept, in the work directory.
+pub(crate) const GENERATED_DIR: &str = "generated-config";
+
+/// A value of a typos configuration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum TomlValue {
+    Bool(bool),
+    Integer(i64),
+    String(String),
+    Array(Vec<TomlValue>),
+}
+
+impl TomlValue {
+    /// An array of strings.
+    #[cfg(test)]
+    pub(crate) fn strings<S: Into<String>>(values: impl IntoIterator<Item = S>) -> Self {
+        Self::Array(
+            values
+                .into_iter()
+                .map(|value| Self::String(value.into()))
+                .collect(),
+        )
+    }
+
+    fn render(&self) -> String {
+        match self {
+            Self::Bool(value) => value.to_string(),
+            Self::Integer(value) => value.to_string(),
+            Self::String(value) => quote(value),
+            Self::Array(values) => {
+                let items: Vec<String> = values.iter().map(TomlValue::render).collect();
+                format!("[{}]", items.join(", "))
+            }
+        }
+    }
+}
+
+/// Whether `c` is invisible, or reorders the text around it, such as the marks and
+/// overrides of bidirectional text. Valid in TOML strings, such characters would make
+/// the generated file read differently from what typos gets, so they are escaped.
+fn is_invisible(c: char) -> bool {
+    matches!(
+        c,
+        '\u{61C}'
+            | '\u{200B}'..='\u{200F}'
+            | '\u{2028}'..='\u{202E}'
+            | '\u{2060}'..='\u{2069}'
+            | '\u{FEFF}'
+            | '\u{E0000}'..='\u{E007F}'
+    )
+}
+
+/// A TOML basic string.
+pub(crate) fn quote(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() || is_invisible(c) => {
+                let code = c as u32;
+                if code > 0xFFFF {
+                    write!(quoted, "\\U{code:08X}")
+                } else {
+                    write!(quoted, "\\u{code:04X}")
+                }
+                .expect("writing to a String does not fail")
+            }
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+fn is_bare_key_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '-' || c == '_'
+}
+
+/// A key as written in TOML, quoted unless it is a bare key.
+fn render_key(key: &str) -> String {
+    if !key.is_empty() && key.chars().all(is_bare_key_char) {
+        key.to_string()
+    } else {
+        quote(key)
+    }
+}
+
+/// The canonical name of a table from its keys, e.g. `type.yaml`.
+fn table_name<S: AsRef<str>>(keys: &[S]) -> String {
+    let keys: Vec<String> = keys.iter().map(|key| render_key(key.as_ref())).collect();
+    keys.join(".")
+}
+
+/// Tables of typos options, e.g. `default` or `type.yaml`, rendered deterministically.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct TyposConfig {
+    /// By canonical table name, with unquoted keys.
+    tables: BTreeMap<String, BTreeMap<String, TomlValue>>,
+}
+
+impl TyposConfig {
+    /// Parse a typos configuration file, e.g. `_typos.toml`.
+    ///
+    /// Inline tables and arrays of tables, which typos does not use, are rejected.
+    pub(crate) fn parse(text: &str) -> Result<Self, String> {
+        Parser::new(text).parse()
+    }
+
+    /// Set `key` in `table`, given by its canonical name.
+    pub(crate) fn set(&mut self, table: &str, key: &str, value: TomlValue) {
+        self.tables
+            .entry(table.to_string())
+            .or_default()
+            .insert(key.to_string(), value);
+    }
+
+    pub(crate) fn get(&self, table: &str, key: &str) -> Option<&TomlValue> {
+        self.tables.get(table)?.get(key)
+    }
+
+    /// Set `word` in the word table `table`, e.g. `default.extend-words`, replacing the
+    /// case variants typos would take for the same word.
+    pub(crate) fn set_word(&mut self, table: &str, word: &str, value: TomlValue) {
+        let entries = self.tables.entry(table.to_string()).or_default();
+        entries.retain(|key, _| !case_fold::same_word(key, word));
+        entries.insert(word.to_string(), value);
+    }
+
+    /// The entry of `word`, or of a case variant of it, in the word table `table`.
+    pub(crate) fn get_word(&self, table: &str, word: &str) -> Option<(&str, &TomlValue)> {
+        let entries = self.tables.get(table)?;
+        if let Some((key, value)) = entries.get_key_value(word) {
+            return Some((key.as_str(), value));
+        }
+        let folded = case_fold::fold(word);
+        entries
+            .iter()
+            .find(|(key, _)| case_fold::fold(key) == folded)
+            .map(|(key, value)| (key.as_str(), value))
+    }
+
+    /// The values of `key` in every table that has it, by canonical table name.
+    pub(crate) fn entries<'a>(
+        &'a self,
+        key: &'a str,
+    ) -> impl Iterator<Item = (&'a str, &'a TomlValue)> + 'a {
+        self.tables
+            .iter()
+            .filter_map(move |(table, entries)| Some((table.as_str(), entries.get(key)?)))
+    }
+
+    /// The tables, by canonical name, with their entries by unquoted key.
+    pub(crate) fn tables(&self) -> impl Iterator<Item = (&str, &BTreeMap<String, TomlValue>)> {
+        self.tables
+            .iter()
+            .map(|(table, entries)| (table.as_str(), entries))
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.tables.values().all(BTreeMap::is_empty)
+    }
+
+    /// Layer `other` over this configuration: its values win, except that the arrays
+    /// of `extend-*` keys, as their name says, extend the ones below.
+    pub(crate) fn merge(&mut self, other: &TyposConfig) {
+        for (table, entries) in &other.tables {
+            let merged = self.tables.entry(table.clone()).or_default();
+            for (key, value) in entries {
+                match (merged.get_mut(key), value) {
+                    (Some(TomlValue::Array(below)), TomlValue::Array(above))
+                        if key.starts_with("extend-") =>
+                    {
+                        for item in above {
+                            if !below.contains(item) {
+                                below.push(item.clone());
+                            }
+                        }
+                    }
+                    _ => {
+                        if case_fold::is_word_table(table) {
+                            merged.retain(|below, _| !case_fold::same_word(below, key));
+                        }
+                        merged.insert(key.clone(), value.clone());
+                    }
+                }
+            }
+        }
+    }
+
+    pub(crate) fn render(&self) -> String {
+        let mut rendered = String::new();
+        for (table, entries) in self
+            .tables
+            .iter()
+            .filter(|(_, entries)| !entries.is_empty())
+        {
+            if !rendered.is_empty() {
+                rendered.push('\n');
+            }
+            writeln!(rendered, "[{table}]").expect("writing to a String does not fail");
+            Self::render_entries(&mut rendered, entries);
+        }
+        rendered
+    }
+
+    fn render_entries(rendered: &mut String, entries: &BTreeMap<String, TomlValue>) {
+        for (key, value) in entries {
+            writeln!(rendered, "{} = {}", render_key(key), value.render())
+                .expect("writing to a String does not fail");
+        }
+    }
+
+    /// Layer these options over the configuration file `project`, keeping its layout.
+    ///
+    /// The merge is line based: keys go right below the project header of their table,
+    /// replacing the project keys of the same name, and tables the project does not
+    /// have are appended. Dotted keys, inline tables and values spanning several lines
+    /// in the project file are not recognized.
+    pub(crate) fn merge_into(&self, project: &str) -> String {
+        let mut merged = String::new();
+        let mut spliced = BTreeSet::new();
+        // The table the current line belongs to, and its entries.
+        let mut current: Option<(String, &BTreeMap<String, TomlValue>)> = None;
+        for line in project.lines() {
+            if let Some(table) = table_header(line) {
+                merged.push_str(line);
+                merged.push('\n');
+                current = self
+                    .tables
+                    .get(&table)
+                    .map(|entries| (table.clone(), entries));
+                if let Some((_, entries)) = &current {
+                    Self::render_entries(&mut merged, entries);
+                    spliced.insert(table);
+                }
+                continue;
+            }
+            let replaced =
+                current
+                    .as_ref()
+                    .zip(line_key(line))
+                    .map_or(false, |((table, entries), key)| {
+                        if case_fold::is_word_table(table) {
+                            entries
+                                .keys()
+                                .any(|entry| case_fold::same_word(entry, &key))
+                        } else {
+                            entries.contains_key(&key)
+                        }
+                    });
+            if !replaced {
+                merged.push_str(line);
+                merged.push('\n');
+            }
+        }
+        let mut appended = self.clone();
+        appended.tables.retain(|table, _| !spliced.contains(table));
+        let appended = appended.render();
+        if !appended.is_empty() {
+            if !merged.is_empty() {
+                merged.push('\n');
+            }
+            merged.push_str(&appended);
+        }
+        merged
+    }
+}
+
+/// The path of the configuration of the worktree at `root`, relative to the work
+/// directory.
+pub(crate) fn path(root: &str) -> String {
+    format!(
+        "{GENERATED_DIR}/{}.toml",
+        &hashing::sha256_hex(root.as_bytes())[..16]
+    )
+}
+
+/// Write `content` as the configuration of the worktree at `root` into the work
+/// directory, when it changed, and return its path relative to the work directory.
+pub(crate) fn write(fs: &dyn Fs, root: &str, content: &str) -> Result<String, String> {
+    let path = path(root);
+    if fs.read_to_string(Path::new(&path)).ok().as_deref() != Some(content) {
+        fs.create_dir_all(Path::new(GENERATED_DIR))
+            .and_then(|()| fs.write(Path::new(&path), content.as_bytes()))
+            .map_err(|e| messages::write_failed(Path::new(&path), e))?;
+    }
+    Ok(path)
+}
+
+/// The unquoted key of a `key = value` line, unless dotted.
+fn line_key(line: &str) -> Option<String> {
+    let mut parser = Parser::new(line);
+    let mut keys = parser.key().ok()?;
+    (keys.len() == 1 && parser.bump() == Some('=')).then(|| keys.remove(0))
+}
+
+/// The canonical table name of a `[table]` header line, array of tables excluded.
+fn table_header(line: &str) -> Option<String> {
+    let line = line.trim_start();
+    if !line.starts_with('[') || line.starts_with("[[") {
+        return None;
+    }
+    let mut parser = Parser::new(line);
+    parser.bump();
+    let keys = parser.key().ok()?;
+    (parser.bump() == Some(']')).then(|| table_name(&keys))
+}
+
+/// A recursive descent parser over the whole text, tracking lines for errors.
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+    line: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(text: &'a str) -> Self {
+        Self {
+            chars: text.chars().peekable(),
+            line: 1,
+        }
+    }
+
+    fn error(&self, message: impl std::fmt::Display) -> String {
+        format!("line {}: {message}", self.line)
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.chars.peek().copied()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.chars.next();
+        if c == Some('\n') {
+            self.line += 1;
+        }
+        c
+    }
+
+    fn eat(&mut self, expected: char) -> bool {
+        let found = self.peek() == Some(expected);
+        if found {
+            self.bump();
+        }
+        found
+    }
+
+    fn eat_str(&mut self, expected: &str) -> bool {
+        let mut lookahead = self.chars.clone();
+        let found = expected.chars().all(|c| lookahead.next() == Some(c));
+        if found {
+            expected.chars().for_each(|_| {
+                self.bump();
+            });
+        }
+        found
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t')) {
+            self.bump();
+        }
+    }
+
+    /// Skip whitespace, line breaks and comments, as allowed between lines and array
+    /// items.
+    fn skip_blank(&mut self) {
+        loop {
+            match self.peek() {
+                Some(' ' | '\t' | '\r' | '\n') => {
+                    self.bump();
+                }
+                Some('#') => self.skip_comment(),
+                _ => return,
+            }
+        }
+    }
+
+    fn skip_comment(&mut self) {
+        while !matches!(self.peek(), None | Some('\n')) {
+            self.bump();
+        }
+    }
+
+    /// Expect the end of the line, after an optional comment.
+    fn end_of_line(&mut self) -> Result<(), String> {
+        self.skip_whitespace();
+        if self.peek() == Some('#') {
+            self.skip_comment();
+        }
+        self.eat('\r');
+        match self.peek() {
+            None => Ok(()),
+            Some('\n') => {
+                self.bump();
+                Ok(())
+            }
+            Some(c) => Err(self.error(format!("unexpected {c:?}"))),
+        }
+    }
+
+    fn parse(mut self) -> Result<TyposConfig, String> {
+        let mut config = TyposConfig::default();
+        let mut table: Vec<String> = Vec::new();
+        let mut headers = BTreeSet::new();
+        loop {
+            self.skip_blank();
+            match self.peek() {
+                None => break,
+                Some('[') => {
+                    self.bump();
+                    if self.peek() == Some('[') {
+                        return Err(self.error("arrays of tables are not supported"));
+                    }
+                    table = self.key()?;
+                    if !self.eat(']') {
+                        return Err(self.error("expected ']' closing the table header"));
+                    }
+                    let name = table_name(&table);
+                    if !headers.insert(name.clone()) {
+                        return Err(self.error(format!("table [{name}] is defined twice")));
+                    }
+                    config.tables.entry(name).or_default();
+                }
+                Some(_) => {
+                    let mut key = self.key()?;
+                    if !self.eat('=') {
+                        return Err(self.error("expected '=' after the key"));
+                    }
+                    self.skip_whitespace();
+                    let value = self.value()?;
+                    let name = key.pop().expect("keys are never empty");
+                    let path: Vec<&String> = table.iter().chain(&key).collect();
+                    let entries = config.tables.entry(table_name(&path)).or_default();
+                    if entries.insert(name.clone(), value).is_some() {
+                        return Err(self.error(format!("key {name:?} is defined twice")));
+                    }
+                }
+            }
+            self.end_of_line()?;
+        }
+        config.tables.retain(|_, entries| !entries.is_empty());
+        Ok(config)
+    }
+
+    /// A possibly dotted key, as its unquoted parts, and the whitespace after it.
+    fn key(&mut self) -> Result<Vec<String>, String> {
+        let mut keys = Vec::new();
+        loop {
+            self.skip_whitespace();
+            let key = match self.peek() {
+                Some('"') => {
+                    self.bump();
+                    self.basic_string()?
+                }
+                Some('\'') => {
+                    self.bump();
+                    self.literal_string()?
+                }
+                _ => {
+                    let mut key = String::new();
+                    while let Some(c) = self.peek().filter(|c| is_bare_key_char(*c)) {
+                        key.push(c);
+                        self.bump();
+                    }
+                    if key.is_empty() {
+                        return Err(self.error("expected a key"));
+                    }
+                    key
+                }
+            };
+            keys.push(key);
+            self.skip_whitespace();
+            if !self.eat('.') {
+                return Ok(keys);
+            }
+        }
+    }
+
+    fn value(&mut self) -> Result<TomlValue, String> {
+        if self.eat_str("\"\"\"") {
+            return self.multiline_string('"').map(TomlValue::String);
+        }
+        if self.eat_str("'''") {
+            return self.multiline_string('\'').map(TomlValue::String);
+        }
+        match self.peek() {
+            Some('"') => {
+                self.bump();
+                self.basic_string().map(TomlValue::String)
+            }
+            Some('\'') => {
+                self.bump();
+                self.literal_string().map(TomlValue::String)
+            }
+            Some('[') => {
+                self.bump();
+                self.array()
+            }
+            Some('{') => Err(self.error("inline tables are not supported")),
+            _ => {
+                let mut word = String::new();
+                while let Some(c) = self
+                    .peek()
+                    .filter(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '+' | '-' | '.'))
+                {
+                    word.push(c);
+                    self.bump();
+                }
+                match word.as_str() {
+                    "true" => Ok(TomlValue::Bool(true)),
+                    "false" => Ok(TomlValue::Bool(false)),
+                    "" => Err(self.error("expected a value")),
+                    word => word
+                        .replace('_', "")
+                        .parse()
+                        .map(TomlValue::Integer)
+                        .map_err(|_| self.error(format!("unsupported value {word:?}"))),
+                }
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<TomlValue, String> {
+        let mut items = Vec::new();
+        loop {
+            self.skip_blank();
+            if self.eat(']') {
+                return Ok(TomlValue::Array(items));
+            }
+            items.push(self.value()?);
+            self.skip_blank();
+            if !self.eat(',') && self.peek() != Some(']') {
+                return Err(self.error("expected ',' or ']' in the array"));
+            }
+        }
+    }
+
+    /// The character escaped by a backslash in a basic string.
+    fn escape(&mut self) -> Result<char, String> {
+        match self.bump() {
+            Some('"') => Ok('"'),
+            Some('\\') => Ok('\\'),
+            Some('n') => Ok('\n'),
+            Some('t') => Ok('\t'),
+            Some('r') => Ok('\r'),
+            Some('b') => Ok('\u{8}'),
+            Some('f') => Ok('\u{c}'),
+            Some('u') => self.unicode_escape(4),
+            Some('U') => self.unicode_escape(8),
+            c => Err(self.error(format!("invalid escape {c:?}"))),
+        }
+    }
+
+    fn unicode_escape(&mut self, digits: usize) -> Result<char, String> {
+        let hex: String = (0..digits).filter_map(|_| self.bump()).collect();
+        u32::from_str_radix(&hex, 16)
+            .ok()
+            .and_then(char::from_u32)
+            .ok_or_else(|| self.error(format!("invalid unicode escape {hex:?}")))
+    }
+
+    fn basic_string(&mut self) -> Result<String, String> {
+        let mut value = String::new();
+        loop {
+            if matches!(self.peek(), None | Some('\n')) {
+                return Err(self.error("unterminated string"));
+            }
+            match self.bump() {
+                Some('"') => return Ok(value),
+                Some('\\') => value.push(self.escape()?),
+                Some(c) => value.push(c),
+                None => {}
+            }
+        }
+    }
+
+    fn literal_string(&mut self) -> Result<String, String> {
+        let mut value = String::new();
+        loop {
+            match self.peek() {
+                None | Some('\n') => return Err(self.error("unterminated string")),
+                Some('\'') => {
+                    self.bump();
+                    return Ok(value);
+                }
+                Some(c) => {
+                    self.bump();
+                    value.push(c);
+                }
+            }
+        }
+    }
+
+    /// A string delimited by three `quote`s, escapes only processed for basic ones.
+    fn multiline_string(&mut self, quote: char) -> Result<String, String> {
+        let delimiter: String = [quote; 3].iter().collect();
+        // A line break right after the opening delimiter is trimmed.
+        self.eat('\r');
+        self.eat('\n');
+        let mut value = String::new();
+        loop {
+            if self.eat_str(&delimiter) {
+                return Ok(value);
+            }
+            match self.bump() {
+                Some('\\') if quote == '"' => {
+                    if matches!(self.peek(), Some(' ' | '\t' | '\r' | '\n')) {
+                        // A line ending backslash trims the whitespace up to the next
+                        // character.
+                        while matches!(self.peek(), Some(' ' | '\t' | '\r' | '\n')) {
+                            self.bump();
+                        }
+                    } else {
+                        value.push(self.escape()?);
+                    }
+                }
+                None => return Err(self.error("unterminated string")),
+                Some(c) => value.push(c),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TomlValue, TyposConfig};
+    use crate::{host::StdFs, test_support::TempDir};
+
+    fn config() -> TyposConfig {
+        let mut config = TyposConfig::default();
+        config.set("default", "check-filename", TomlValue::Bool(false));
+        config.set(
+            "default",
+            "extend-ignore-re",
+            TomlValue::strings([r"a\b", "\"q\""]),
+        );
+        config.set("type.yaml", "check-file", TomlValue::Bool(true));
+        config
+    }
+
+    #[test]
+    fn rendering() {
+        assert_eq!(
+            config().render(),
+            "[default]\n\
+             check-filename = false\n\
+             extend-ignore-re = [\"a\\\\b\", \"\\\"q\\\"\"]\n\
+             \n\
+             [type.yaml]\n\
+             check-file = true\n"
+        );
+        assert!(TyposConfig::default().is_empty());
+        assert_eq!(TyposConfig::default().render(), "");
+
+        let mut words = TyposConfig::default();
+        words.set(
+            "default.extend-words",
+            "teh",
+            TomlValue::String("teh".into()),
+        );
+        words.set("default.extend-words", "a b", TomlValue::Integer(1));
+        assert_eq!(
+            words.render(),
+            "[default.extend-words]\n\"a b\" = 1\nteh = \"teh\"\n"
+        );
+    }
+
+    #[test]
+    fn merging() {
+        let project = "# project\n\
+                       [default]\n\
+                       check-filename = true\n\
+                       locale = \"en-gb\"\n\
+                       \n\
+                       [files]\n\
+                       extend-exclude = [\"*.lock\"]\n";
+        assert_eq!(
+            config().merge_into(project),
+            "# project\n\
+             [default]\n\
+             check-filename = false\n\
+             extend-ignore-re = [\"a\\\\b\", \"\\\"q\\\"\"]\n\
+             locale = \"en-gb\"\n\
+             \n\
+             [files]\n\
+             extend-exclude = [\"*.lock\"]\n\
+             \n\
+             [type.yaml]\n\
+             check-file = true\n"
+        );
+        assert_eq!(config().merge_into(""), config().render());
+        // Merging into the merged file changes nothing.
+        let merged = config().merge_into(project);
+        assert_eq!(config().merge_into(&merged), merged);
+    }
+
+    #[test]
+    fn unicode_words() {
+        const WORDS: &str = "default.extend-words";
+        let accept = |config: &mut TyposConfig, word: &str| {
+            config.set_word(WORDS, word, TomlValue::String(word.to_string()))
+        };
+        let mut config = TyposConfig::default();
+        for word in [
+            "naïve",
+            "nai\u{308}ve",
+            "Größe",
+            "GRÖSSE",
+            "שלום",
+            "𐐨𐐨",
+            "ab\u{202E}cd",
+            "x\u{E0041}",
+        ] {
+            accept(&mut config, word);
+        }
+        // A case variant replaces the word, while a decomposed one is another word.
+        assert_eq!(config.get(WORDS, "Größe"), None);
+        assert_eq!(
+            config.get_word(WORDS, "größe"),
+            Some(("GRÖSSE", &TomlValue::String("GRÖSSE".to_string())))
+        );
+        assert_eq!(config.get_word(WORDS, "NAÏVE").unwrap().0, "naïve");
+        assert_eq!(config.get_word(WORDS, "naive"), None);
+
+        let rendered = config.render();
+        assert_eq!(
+            rendered,
+            "[default.extend-words]\n\
+             \"GRÖSSE\" = \"GRÖSSE\"\n\
+             \"ab\\u202Ecd\" = \"ab\\u202Ecd\"\n\
+             \"nai\u{308}ve\" = \"nai\u{308}ve\"\n\
+             \"naïve\" = \"naïve\"\n\
+             \"x\\U000E0041\" = \"x\\U000E0041\"\n\
+             \"שלום\" = \"שלום\"\n\
+             \"𐐨𐐨\" = \"𐐨𐐨\"\n"
+        );
+        assert_eq!(TyposConfig::parse(&rendered).as_ref(), Ok(&config));
+
+        // Escaped or not, the case variants of a project word are replaced.
+        let project = "[default.extend-words]\n\
+                       \"gr\\u00F6\\u00DFe\" = \"gr\\u00F6\\u00DFe\"\n\
+                       'NAÏVE' = \"naive\"\n\
+                       # \"naïve\" = \"naïve\"\n\
+                       other = \"other\"\n";
+        let merged = config.merge_into(project);
+        assert!(!merged.contains("u00F6"), "{merged}");
+        assert!(!merged.contains("'NAÏVE'"), "{merged}");
+        assert!(merged.contains("# \"naïve\" = \"naïve\"\n"), "{merged}");
+        assert!(merged.contains("other = \"other\"\n"), "{merged}");
+        let mut layered = TyposConfig::parse(project).unwrap();
+        layered.merge(&config);
+        assert_eq!(layered.get_word(WORDS, "grösse").unwrap().0, "GRÖSSE");
+        assert_eq!(layered.get_word(WORDS, "naïve").unwrap().0, "naïve");
+        assert!(layered.get(WORDS, "other").is_some());
+    }
+
+    #[test]
+    fn parsing() {
+        let text = r#"
+# A comment
+[files]
+extend-exclude = [
+    "*.lock", # why
+    'vendor/**',
+]
+
+[default]
+check-filename = false # inline
+extend-ignore-re = ['''\d+''', """
+multi \
+  line"""]
+max = 1_000
+
+[default.extend-words]
+teh = "teh"
+"ba z" = "ba\tzé"
+
+[type]
+po.check-file = false
+"#;
+        let config = TyposConfig::parse(text).unwrap();
+        assert_eq!(
+            config.get("files", "extend-exclude"),
+            Some(&TomlValue::strings(["*.lock", "vendor/**"]))
+        );
+        assert_eq!(
+            config.get("default", "extend-ignore-re"),
+            Some(&TomlValue::strings([r"\d+", "multi line"]))
+        );
+        assert_eq!(
+            config.get("default", "max"),
+            Some(&TomlValue::Integer(1000))
+        );
+        assert_eq!(
+            config.get("default.extend-words", "ba z"),
+            Some(&TomlValue::String("ba\tzé".to_string()))
+        );
+        assert_eq!(
+            config.get("type.po", "check-file"),
+            Some(&TomlValue::Bool(false))
+        );
+        // What is rendered reads back the same, and renders to the same bytes.
+        let rendered = config.render();
+        assert_eq!(TyposConfig::parse(&rendered).as_ref(), Ok(&config));
+        assert_eq!(TyposConfig::parse(&rendered).unwrap().render(), rendered);
+    }
+
+    #[test]
+    fn parse_errors() {
+        let table = [
+            ("[[type]]\n", "line 1: arrays of tables are not supported"),
+            ("a = { b = 1 }\n", "line 1: inline tables are not supported"),
+            ("a = 1\na = 2\n", "line 2: key \"a\" is defined twice"),
+            ("[a]\n[a]\n", "line 2: table [a] is defined twice"),
+            ("[a\n", "line 1: expected ']' closing the table header"),
+            ("a = \"b\n", "line 1: unterminated string"),
+            ("a = [1 2]\n", "line 1: expected ',' or ']' in the array"),
+            ("a = 1 b\n", "line 1: unexpected 'b'"),
+            ("a = 1.5\n", "line 1: unsupported value \"1.5\""),
+            ("a 1\n", "line 1: expected '=' after the key"),
+            ("= 1\n", "line 1: expected a key"),
+        ];
+        for (text, expected) in table {
+            assert_eq!(
+                TyposConfig::parse(text),
+                Err(expected.to_string()),
+                "{text:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn layering() {
+        let mut config = TyposConfig::parse(
+            "[default]\nlocale = \"en-us\"\nextend-ignore-re = [\"a\"]\n\
+             [files]\nignore-vcs = true\n",
+        )
+        .unwrap();
+        config.merge(
+            &TyposConfig::parse(
+                "[default]\nlocale = \"en-gb\"\nextend-ignore-re = [\"b\", \"a\"]\n",
+            )
+            .unwrap(),
+        );
+        assert_eq!(
+            config.render(),
+            "[default]\n\
+             extend-ignore-re = [\"a\", \"b\"]\n\
+             locale = \"en-gb\"\n\
+             \n\
+             [files]\n\
+             ignore-vcs = true\n"
+        );
+    }
+
+    #[test]
+    fn written_once() {
+        let temp = TempDir::new("generated-config");
+        let fs = StdFs::new(temp.path());
+        let content = config().render();
+        let path = super::write(&fs, "/project", &content).unwrap();
+        assert!(path.starts_with("generated-config/"));
+        assert_eq!(
+            std::fs::read_to_string(temp.path().join(&path)).unwrap(),
+            content
+        );
+        assert_eq!(super::write(&fs, "/project", &content).unwrap(), path);
+        assert_ne!(super::write(&fs, "/other", &content).unwrap(), path);
+    }
+}
@@ -0,0 +1,254 @@
+//! When a newer typos-lsp release gets installed.
+//!
+//! By default the latest release is looked up and downloaded when a project opens. With
+//! deferred updates, an installed typos-lsp is used right away instead, and the update
+//! is recorded as pending until a later resolution, once a grace period has elapsed, or
+//! the next time Zed starts.
+//!
+//! Either way, the latest release is looked up at most once per check interval, across
+//! sessions and Zed processes: within it, the release found by the last lookup is used
+//! when it is installed. A lookup including pre-releases only counts for the next ones
+//! including them, and the other way around.
+
+use std::{
+    path::Path,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{host::Fs, log, messages};
+
+/// Records since when an update is pending, in seconds since the Unix epoch.
+pub(crate) const PENDING_FILE: &str = "pending-update.txt";
+
+/// How long a pending update waits for, at the least, within a session.
+pub(crate) const GRACE_PERIOD: Duration = Duration::from_secs(10 * 60);
+
+/// Records the latest release found by the last lookup, and when it happened.
+pub(crate) const CHECK_FILE: &str = "last-update-check.txt";
+
+/// Ends the record of a lookup including pre-releases.
+const PRE_RELEASE_MARKER: &str = "pre-release";
+
+/// How long the last lookup of the latest release is trusted for by default, in hours.
+pub(crate) const DEFAULT_CHECK_INTERVAL_HOURS: u64 = 24;
+
+/// The current time, in seconds since the Unix epoch.
+pub(crate) fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) enum UpdateTiming {
+    /// Install the latest release when resolving the binary.
+    #[default]
+    OnOpen,
+    /// Keep using an installed release, updating it at a later resolution.
+    Deferred,
+}
+
+impl UpdateTiming {
+    pub(crate) fn parse(value: &str) -> Option<Self> {
+        match value {
+            "on_open" => Some(Self::OnOpen),
+            "deferred" => Some(Self::Deferred),
+            _ => None,
+        }
+    }
+}
+
+/// An update that was put off, since `since` in seconds since the Unix epoch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct PendingUpdate {
+    pub since: u64,
+}
+
+impl PendingUpdate {
+    /// The pending update recorded in the work directory, if any.
+    pub(crate) fn read(fs: &dyn Fs) -> Option<Self> {
+        let content = fs.read_to_string(Path::new(PENDING_FILE)).ok()?;
+        let since = content.trim().parse().ok()?;
+        Some(Self { since })
+    }
+
+    /// Record that an update is pending since `now`, unless one already is.
+    pub(crate) fn record(fs: &dyn Fs, now: u64) -> Self {
+        if let Some(pending) = Self::read(fs) {
+            return pending;
+        }
+        if let Err(e) = fs.write_atomically(Path::new(PENDING_FILE), format!("{now}\n").as_bytes())
+        {
+            log::warn(format!(
+                "failed to record the pending typos-lsp update: {e}"
+            ));
+        }
+        Self { since: now }
+    }
+
+    /// Forget the pending update, once the latest release was looked up.
+    pub(crate) fn clear(fs: &dyn Fs) {
+        fs.remove_file(Path::new(PENDING_FILE)).ok();
+    }
+
+    /// Whether the update is to be made at `now`, in a session started at `session_start`.
+    pub(crate) fn is_due(&self, now: u64, session_start: u64) -> bool {
+        self.since < session_start || now.saturating_sub(self.since) >= GRACE_PERIOD.as_secs()
+    }
+}
+
+/// The last successful lookup of the latest release.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct LastCheck {
+    /// The tag of the latest release then.
+    pub version: String,
+    /// When it was looked up, in seconds since the Unix epoch.
+    pub at: u64,
+    /// Whether pre-releases were looked up too.
+    pub pre_release: bool,
+}
+
+impl LastCheck {
+    /// The last lookup recorded in the work directory, if any.
+    pub(crate) fn read(fs: &dyn Fs) -> Option<Self> {
+        let content = fs.read_to_string(Path::new(CHECK_FILE)).ok()?;
+        let mut fields = content.trim().split('\t');
+        let (version, at) = (fields.next()?, fields.next()?);
+        let pre_release = match fields.next() {
+            None => false,
+            Some(PRE_RELEASE_MARKER) => true,
+            Some(_) => return None,
+        };
+        Some(Self {
+            version: version.to_string(),
+            at: at.parse().ok()?,
+            pre_release,
+        })
+    }
+
+    /// Record that the latest release was `version` at `now`, pre-releases included
+    /// when `pre_release`.
+    pub(crate) fn record(fs: &dyn Fs, version: &str, now: u64, pre_release: bool) {
+        let marker = if pre_release {
+            format!("\t{PRE_RELEASE_MARKER}")
+        } else {
+            String::new()
+        };
+        let content = format!("{version}\t{now}{marker}\n");
+        if let Err(e) = fs.write_atomically(Path::new(CHECK_FILE), content.as_bytes()) {
+            log::warn(messages::record_failed("the typos-lsp update check", e));
+        }
+    }
+
+    /// Whether the lookup can still be trusted at `now`, `interval` after it at most. A
+    /// clock set back makes it stale rather than trusted for longer.
+    pub(crate) fn is_fresh(&self, now: u64, interval: Duration) -> bool {
+        now >= self.at && now - self.at < interval.as_secs()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, time::Duration};
+
+    use super::{LastCheck, PendingUpdate, UpdateTiming, CHECK_FILE, GRACE_PERIOD, PENDING_FILE};
+    use crate::{host::StdFs, test_support::TempDir};
+
+    #[test]
+    fn parse() {
+        assert_eq!(UpdateTiming::parse("on_open"), Some(UpdateTiming::OnOpen));
+        assert_eq!(
+            UpdateTiming::parse("deferred"),
+            Some(UpdateTiming::Deferred)
+        );
+        assert_eq!(UpdateTiming::parse("later"), None);
+    }
+
+    #[test]
+    fn persistence() {
+        let temp = TempDir::new("pending-update");
+        let fs = StdFs::new(temp.path());
+        assert_eq!(PendingUpdate::read(&fs), None);
+
+        assert_eq!(
+            PendingUpdate::record(&fs, 1000),
+            PendingUpdate { since: 1000 }
+        );
+        // The first deferral is kept, so that the grace period does not restart.
+        assert_eq!(
+            PendingUpdate::record(&fs, 1500),
+            PendingUpdate { since: 1000 }
+        );
+        assert_eq!(
+            PendingUpdate::read(&fs),
+            Some(PendingUpdate { since: 1000 })
+        );
+
+        PendingUpdate::clear(&fs);
+        assert_eq!(PendingUpdate::read(&fs), None);
+
+        fs::write(temp.path().join(PENDING_FILE), "soon").unwrap();
+        assert_eq!(PendingUpdate::read(&fs), None);
+    }
+
+    #[test]
+    fn due() {
+        let grace = GRACE_PERIOD.as_secs();
+        let pending = PendingUpdate { since: 1000 };
+        assert!(!pending.is_due(1000, 900));
+        assert!(!pending.is_due(1000 + grace - 1, 900));
+        assert!(pending.is_due(1000 + grace, 900));
+        // Recorded by a previous session.
+        assert!(pending.is_due(1001, 1001));
+        // A clock set back does not make it due.
+        assert!(!pending.is_due(10, 900));
+    }
+
+    #[test]
+    fn last_check_persistence() {
+        let temp = TempDir::new("last-update-check");
+        let fs = StdFs::new(temp.path());
+        assert_eq!(LastCheck::read(&fs), None);
+
+        LastCheck::record(&fs, "v0.1.23", 1000, false);
+        assert_eq!(
+            LastCheck::read(&fs),
+            Some(LastCheck {
+                version: "v0.1.23".to_string(),
+                at: 1000,
+                pre_release: false,
+            })
+        );
+        LastCheck::record(&fs, "v0.2.0-rc.1", 2000, true);
+        assert_eq!(
+            fs::read_to_string(temp.path().join(CHECK_FILE)).unwrap(),
+            "v0.2.0-rc.1\t2000\tpre-release\n"
+        );
+        assert_eq!(
+            LastCheck::read(&fs).map(|check| check.pre_release),
+            Some(true)
+        );
+        fs::write(temp.path().join(CHECK_FILE), "v0.1.23\tyesterday\n").unwrap();
+        assert_eq!(LastCheck::read(&fs), None);
+        fs::write(temp.path().join(CHECK_FILE), "v0.1.23\t1000\tbeta\n").unwrap();
+        assert_eq!(LastCheck::read(&fs), None);
+    }
+
+    #[test]
+    fn last_check_staleness() {
+        let hour = 3600;
+        let check = LastCheck {
+            version: "v0.1.23".to_string(),
+            at: 1000,
+            pre_release: false,
+        };
+        let day = Duration::from_secs(24 * hour);
+        assert!(check.is_fresh(1000, day));
+        assert!(check.is_fresh(1000 + 24 * hour - 1, day));
+        assert!(!check.is_fresh(1000 + 24 * hour, day));
+        // A clock set back.
+        assert!(!check.is_fresh(999, day));
+        // No interval always checks.
+        assert!(!check.is_fresh(1000, Duration::ZERO));
+    }
+}
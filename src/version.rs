@@ -0,0 +1,244 @@
+//! typos-lsp versions, as parsed from release tags and version directory names.
+//!
+//! Release tags are only used to talk to GitHub: the on-disk layout, cleanups and the
+//! persisted state all go through [`Version`], so that `v0.1.23` and `0.1.23` are the
+//! same installation.
+
+use std::{cmp::Ordering, fmt};
+
+/// The prefix of every version directory in the work directory.
+pub(crate) const DIR_PREFIX: &str = "typos-lsp-";
+
+/// The suffix of the directory a version is extracted into before it is complete.
+pub(crate) const STAGING_SUFFIX: &str = ".partial";
+
+/// A semantic version, build metadata ignored.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct Version {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+    /// The pre-release identifiers, e.g. `rc.1`.
+    pub pre: Option<String>,
+}
+
+impl Version {
+    /// Parse a release tag, with or without its `v` prefix.
+    pub(crate) fn parse(tag: &str) -> Option<Self> {
+        let tag = tag.trim();
+        let tag = tag
+            .strip_prefix('v')
+            .or_else(|| tag.strip_prefix('V'))
+            .unwrap_or(tag);
+        let tag = tag.split_once('+').map_or(tag, |(version, _build)| version);
+        let (core, pre) = match tag.split_once('-') {
+            Some((core, pre)) => (core, Some(pre)),
+            None => (tag, None),
+        };
+        if let Some(pre) = pre {
+            let valid = |identifier: &str| {
+                !identifier.is_empty()
+                    && identifier
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '-')
+            };
+            if !pre.split('.').all(valid) {
+                return None;
+            }
+        }
+        let mut numbers = core.split('.').map(|number| {
+            if number.is_empty() || !number.bytes().all(|b| b.is_ascii_digit()) {
+                return None;
+            }
+            number.parse::<u64>().ok()
+        });
+        let version = Self {
+            major: numbers.next()??,
+            minor: numbers.next()??,
+            patch: numbers.next()??,
+            pre: pre.map(str::to_string),
+        };
+        numbers.next().is_none().then_some(version)
+    }
+
+    /// Parse the name of a version directory, canonical or as named by earlier
+    /// releases of the extension after the raw release tag, e.g. `typos-lsp-v0.1.23`.
+    pub(crate) fn from_dir_name(name: &str) -> Option<Self> {
+        if name.ends_with(STAGING_SUFFIX) {
+            return None;
+        }
+        Self::parse(name.strip_prefix(DIR_PREFIX)?)
+    }
+
+    /// Parse the name of the staging directory of a version, left by an interrupted
+    /// installation.
+    pub(crate) fn from_staging_dir_name(name: &str) -> Option<Self> {
+        Self::from_dir_name(name.strip_suffix(STAGING_SUFFIX)?)
+    }
+
+    /// The canonical name of the directory this version is installed in.
+    pub(crate) fn dir_name(&self) -> String {
+        format!("{DIR_PREFIX}{self}")
+    }
+
+    /// The name of the directory this version is extracted into, then renamed to
+    /// [`Self::dir_name`] once complete.
+    pub(crate) fn staging_dir_name(&self) -> String {
+        format!("{}{STAGING_SUFFIX}", self.dir_name())
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)?;
+        if let Some(pre) = &self.pre {
+            write!(f, "-{pre}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.major, self.minor, self.patch)
+            .cmp(&(other.major, other.minor, other.patch))
+            .then_with(|| match (&self.pre, &other.pre) {
+                (None, None) => Ordering::Equal,
+                // A pre-release comes before its release.
+                (None, Some(_)) => Ordering::Greater,
+                (Some(_), None) => Ordering::Less,
+                (Some(a), Some(b)) => compare_pre(a, b),
+            })
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Pre-release identifiers compare numerically when numeric, numeric ones first.
+///
+/// Identifiers only differing by leading zeros are told apart by their text, to stay
+/// consistent with equality.
+fn compare_pre(a: &str, b: &str) -> Ordering {
+    let (raw_a, raw_b) = (a, b);
+    let mut a = a.split('.');
+    let mut b = b.split('.');
+    loop {
+        let ordering = match (a.next(), b.next()) {
+            (None, None) => return raw_a.cmp(raw_b),
+            (None, Some(_)) => Ordering::Less,
+            (Some(_), None) => Ordering::Greater,
+            (Some(a), Some(b)) => match (a.parse::<u64>(), b.parse::<u64>()) {
+                (Ok(a), Ok(b)) => a.cmp(&b),
+                (Ok(_), Err(_)) => Ordering::Less,
+                (Err(_), Ok(_)) => Ordering::Greater,
+                (Err(_), Err(_)) => a.cmp(b),
+            },
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Version;
+
+    fn version(major: u64, minor: u64, patch: u64, pre: Option<&str>) -> Version {
+        Version {
+            major,
+            minor,
+            patch,
+            pre: pre.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn parse_tags() {
+        let table = [
+            ("v0.1.23", Some(version(0, 1, 23, None))),
+            ("0.1.23", Some(version(0, 1, 23, None))),
+            ("V1.0.0", Some(version(1, 0, 0, None))),
+            ("v0.2.0-rc.1", Some(version(0, 2, 0, Some("rc.1")))),
+            ("0.2.0-beta-2", Some(version(0, 2, 0, Some("beta-2")))),
+            ("v0.1.23+build.5", Some(version(0, 1, 23, None))),
+            ("v0.1", None),
+            ("v0.1.23.4", None),
+            ("v0.1.x", None),
+            ("v0.1.+3", None),
+            ("v0.1.23-", None),
+            ("v0.1.23-rc..1", None),
+            ("nightly", None),
+            ("", None),
+        ];
+        for (tag, expected) in table {
+            assert_eq!(Version::parse(tag), expected, "{tag}");
+        }
+    }
+
+    #[test]
+    fn directory_names() {
+        let release = version(0, 1, 23, None);
+        assert_eq!(release.dir_name(), "typos-lsp-0.1.23");
+        assert_eq!(
+            version(0, 2, 0, Some("rc.1")).dir_name(),
+            "typos-lsp-0.2.0-rc.1"
+        );
+        assert_eq!(
+            Version::from_dir_name("typos-lsp-v0.2.0-rc.1+build.4"),
+            Some(version(0, 2, 0, Some("rc.1")))
+        );
+        for name in ["typos-lsp-0.1.23", "typos-lsp-v0.1.23"] {
+            assert_eq!(
+                Version::from_dir_name(name),
+                Some(release.clone()),
+                "{name}"
+            );
+        }
+        for name in [
+            "typos-lsp-nightly",
+            "typos-lsp-v0.1",
+            "typos-lsp",
+            "v0.1.23",
+            "deferred-cleanup.txt",
+            "typos-lsp-0.1.23.partial",
+            "typos-lsp-0.2.0-rc.1.partial",
+        ] {
+            assert_eq!(Version::from_dir_name(name), None, "{name}");
+        }
+
+        let candidate = version(0, 2, 0, Some("rc.1"));
+        assert_eq!(candidate.staging_dir_name(), "typos-lsp-0.2.0-rc.1.partial");
+        assert_eq!(
+            Version::from_staging_dir_name(&candidate.staging_dir_name()),
+            Some(candidate)
+        );
+        assert_eq!(Version::from_staging_dir_name("typos-lsp-0.1.23"), None);
+        assert_eq!(Version::from_staging_dir_name("notes.partial"), None);
+    }
+
+    #[test]
+    fn ordering() {
+        let ordered = [
+            "0.1.9",
+            "0.1.10",
+            "0.2.0-alpha",
+            "0.2.0-alpha.1",
+            "0.2.0-alpha.beta",
+            "0.2.0-beta.2",
+            "0.2.0-beta.11",
+            "0.2.0-rc.1",
+            "0.2.0",
+            "1.0.0",
+        ]
+        .map(|tag| Version::parse(tag).unwrap());
+        for pair in ordered.windows(2) {
+            assert!(pair[0] < pair[1], "{} < {}", pair[0], pair[1]);
+        }
+        assert_eq!(Version::parse("v0.1.23"), Version::parse("0.1.23"));
+    }
+}
@@ -0,0 +1,240 @@
+//! What the extension resolves when Zed loads it, before any worktree asks for a server.
+//!
+//! `new` is the only point before the first `language_server_command`, and it knows no
+//! worktree. Whatever depends on one waits for it: the settings, and with them the
+//! release to install and whether to clean up, the `$PATH` binary, and the platform the
+//! server runs on, which a container or a remote host changes. What only depends on the
+//! work directory is done eagerly instead: the state left by earlier sessions is read,
+//! and the installations are scanned, the binary of each checked against the digest its
+//! manifest recorded.
+//!
+//! The first resolution of the session uses that snapshot when it installs for the
+//! platform Zed runs on, rather than reading the work directory again, and replaces a
+//! binary found corrupted. Anything changing the work directory before then drops it.
+
+use std::{collections::BTreeMap, path::Path};
+
+use zed_extension_api::{Architecture, Os};
+
+use crate::{
+    hashing,
+    host::Fs,
+    log,
+    manifest::Manifest,
+    migration,
+    update_timing::{LastCheck, PendingUpdate},
+    version::Version,
+    TyposExtension,
+};
+
+/// The work directory as the extension found it when it loaded.
+#[derive(Debug)]
+pub(crate) struct Warmup {
+    /// The platform the binaries were looked for, the one Zed runs on.
+    platform: (Os, Architecture),
+    pub last_check: Option<LastCheck>,
+    pub pending_update: Option<PendingUpdate>,
+    installations: BTreeMap<Version, Installation>,
+}
+
+/// An installation found by the scan.
+#[derive(Debug)]
+pub(crate) struct Installation {
+    pub manifest: Manifest,
+    /// The binary, relative to the work directory.
+    pub binary: String,
+    /// Whether the binary matches the digest of the manifest, when it recorded one.
+    pub intact: bool,
+}
+
+impl Warmup {
+    /// Scan the work directory `fs` for binaries running on `platform`. Nothing is
+    /// scanned while the layout awaits a migration, which the settings of a worktree
+    /// may turn off.
+    pub(crate) fn scan(fs: &dyn Fs, platform: (Os, Architecture)) -> Option<Self> {
+        if !migration::is_current(fs) {
+            return None;
+        }
+        let installations = fs
+            .read_dir(Path::new("."))
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|name| {
+                let version = Version::from_dir_name(name)?;
+                let installation = Installation::scan(fs, name, platform)?;
+                Some((version, installation))
+            })
+            .collect();
+        Some(Self {
+            platform,
+            last_check: LastCheck::read(fs),
+            pending_update: PendingUpdate::read(fs),
+            installations,
+        })
+    }
+
+    /// Whether the snapshot holds for binaries running on `platform`.
+    pub(crate) fn serves(&self, platform: (Os, Architecture)) -> bool {
+        self.platform == platform
+    }
+
+    /// The installed versions, intact or not.
+    pub(crate) fn versions(&self) -> impl Iterator<Item = &Version> {
+        self.installations.keys()
+    }
+
+    pub(crate) fn installation(&self, version: &Version) -> Option<&Installation> {
+        self.installations.get(version)
+    }
+
+    /// Whether the binary of `version` was found corrupted, to be installed again.
+    pub(crate) fn is_corrupted(&self, version: &Version) -> bool {
+        self.installation(version)
+            .is_some_and(|installation| !installation.intact)
+    }
+
+    /// Forget the installations in the directories `names`, removed since the scan.
+    pub(crate) fn forget(&mut self, names: &[String]) {
+        self.installations
+            .retain(|version, _| !names.contains(&version.dir_name()));
+    }
+}
+
+impl Installation {
+    /// The installation in the version directory `dir`, unless its binary is missing.
+    fn scan(fs: &dyn Fs, dir: &str, platform: (Os, Architecture)) -> Option<Self> {
+        let manifest = Manifest::read(fs, Path::new(dir))?;
+        let within = TyposExtension::binary_path_within_archive(&platform.0, &platform.1).ok()?;
+        let binary = Path::new(dir).join(within);
+        if !fs.is_file(&binary) {
+            return None;
+        }
+        let binary = binary
+            .to_str()
+            .expect("Could not convert binary path to str")
+            .to_string();
+        let intact = match &manifest.sha256 {
+            Some(expected) => match hashing::sha256_file(fs, Path::new(&binary)) {
+                Ok(found) if &found == expected => true,
+                Ok(found) => {
+                    log::warn(corrupted(&binary, expected, &found));
+                    false
+                }
+                Err(e) => {
+                    log::warn(e);
+                    false
+                }
+            },
+            None => true,
+        };
+        Some(Self {
+            manifest,
+            binary,
+            intact,
+        })
+    }
+}
+
+fn corrupted(binary: &str, expected: &str, found: &str) -> String {
+    format!(
+        "{binary} does not match the digest recorded when it was installed: expected sha256 \
+         {expected}, found {found}. It is installed again when a server needs it"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use zed_extension_api::{Architecture, Os};
+
+    use super::Warmup;
+    use crate::{
+        hashing,
+        host::StdFs,
+        manifest::Manifest,
+        migration,
+        test_support::TempDir,
+        update_timing::{LastCheck, PendingUpdate, CHECK_FILE, PENDING_FILE},
+        version::Version,
+    };
+
+    const LINUX: (Os, Architecture) = (Os::Linux, Architecture::X8664);
+
+    fn installed(temp: &TempDir, tag: &str, content: &[u8], sha256: Option<String>) {
+        let version = Version::parse(tag).unwrap();
+        let dir = temp.path().join(version.dir_name());
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("typos-lsp"), content).unwrap();
+        Manifest {
+            version: tag.to_string(),
+            asset: format!("typos-lsp-{tag}-x86_64-unknown-linux-gnu.tar.gz"),
+            variant: Some("gnu".to_string()),
+            sha256,
+            worktree: None,
+            last_used: None,
+            failed_starts: 0,
+            extension_version: None,
+            platform: None,
+        }
+        .write(&StdFs::new(temp.path()), &dir)
+        .unwrap();
+    }
+
+    #[test]
+    fn scan() {
+        let temp = TempDir::new("warmup-scan");
+        let fs = StdFs::new(temp.path());
+        installed(&temp, "v0.1.22", b"binary", None);
+        installed(
+            &temp,
+            "v0.1.23",
+            b"binary",
+            Some(hashing::sha256_hex(b"binary")),
+        );
+        installed(
+            &temp,
+            "v0.1.24",
+            b"corrupted",
+            Some(hashing::sha256_hex(b"binary")),
+        );
+        // Interrupted, or for another platform.
+        fs::create_dir_all(temp.path().join("typos-lsp-0.1.25")).unwrap();
+        fs::create_dir_all(temp.path().join("typos-lsp-0.1.26.partial")).unwrap();
+        fs::write(temp.path().join(CHECK_FILE), "v0.1.23\t1000\n").unwrap();
+        fs::write(temp.path().join(PENDING_FILE), "900\n").unwrap();
+
+        // Not before the layout is migrated.
+        assert!(Warmup::scan(&fs, LINUX).is_none());
+        fs::write(temp.path().join(migration::LAYOUT_FILE), "2").unwrap();
+
+        let mut warmup = Warmup::scan(&fs, LINUX).unwrap();
+        assert!(warmup.serves(LINUX));
+        assert!(!warmup.serves((Os::Linux, Architecture::Aarch64)));
+        assert_eq!(
+            warmup.last_check,
+            Some(LastCheck {
+                version: "v0.1.23".to_string(),
+                at: 1000,
+                pre_release: false,
+            })
+        );
+        assert_eq!(warmup.pending_update, Some(PendingUpdate { since: 900 }));
+        let versions: Vec<String> = warmup.versions().map(Version::dir_name).collect();
+        assert_eq!(
+            versions,
+            ["typos-lsp-0.1.22", "typos-lsp-0.1.23", "typos-lsp-0.1.24"]
+        );
+        let installation = warmup
+            .installation(&Version::parse("v0.1.23").unwrap())
+            .unwrap();
+        assert_eq!(installation.binary, "typos-lsp-0.1.23/typos-lsp");
+        for (tag, corrupted) in [("v0.1.22", false), ("v0.1.23", false), ("v0.1.24", true)] {
+            let version = Version::parse(tag).unwrap();
+            assert_eq!(warmup.is_corrupted(&version), corrupted, "{tag}");
+        }
+
+        warmup.forget(&["typos-lsp-0.1.22".to_string()]);
+        assert_eq!(warmup.versions().count(), 2);
+    }
+}
@@ -0,0 +1,159 @@
+//! Word lists dropped into `.typos/words.d/` at the worktree root.
+//!
+//! Every fragment of the directory holds accepted words, one per line, with `#`
+//! starting a comment. The words of all fragments are added to the generated
+//! configuration as `[default.extend-words]`, so a team can own its own fragment the
+//! way `sudoers.d` works.
+//!
+//! The extension can read the files of a worktree but not list its directories, so the
+//! fragments are the ones the `word_lists` setting names.
+
+use std::collections::BTreeSet;
+
+use crate::typos_config::{TomlValue, TyposConfig};
+
+/// The fragments directory, relative to the worktree root.
+const DIR: &str = ".typos/words.d";
+
+/// The words of the fragments, and the fragments that could not be read.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct Fragments {
+    pub words: BTreeSet<String>,
+    pub errors: Vec<String>,
+}
+
+/// The path of the fragment `name`, relative to the worktree root.
+pub(crate) fn path(name: &str) -> String {
+    format!("{DIR}/{name}")
+}
+
+impl Fragments {
+    /// The fragments named by `word_lists`, from their `content` as the worktree read
+    /// them.
+    ///
+    /// A fragment that cannot be read is reported and skipped, the others still apply.
+    pub(crate) fn from_files<'a>(
+        files: impl IntoIterator<Item = (&'a str, Result<String, String>)>,
+    ) -> Self {
+        let mut fragments = Self::default();
+        for (name, content) in files {
+            let content = match content {
+                Ok(content) => content,
+                Err(e) => {
+                    fragments
+                        .errors
+                        .push(format!("cannot read {}: {e}", path(name)));
+                    continue;
+                }
+            };
+            let words = content
+                .lines()
+                .map(|line| line.split_once('#').map_or(line, |(words, _)| words).trim())
+                .filter(|word| !word.is_empty());
+            fragments.words.extend(words.map(str::to_string));
+        }
+        fragments
+    }
+
+    /// Accept the words in the generated configuration, one of the case variants of a
+    /// word only, as typos takes them for the same word.
+    pub(crate) fn apply(&self, config: &mut TyposConfig) {
+        for word in &self.words {
+            config.set_word(
+                "default.extend-words",
+                word,
+                TomlValue::String(word.clone()),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Fragments;
+    use crate::typos_config::TyposConfig;
+
+    fn read(files: &[(&'static str, Result<&str, &str>)]) -> Fragments {
+        Fragments::from_files(files.iter().map(|(name, content)| {
+            let content = content.map(str::to_string).map_err(str::to_string);
+            (*name, content)
+        }))
+    }
+
+    fn words(fragments: &Fragments) -> Vec<&str> {
+        fragments.words.iter().map(String::as_str).collect()
+    }
+
+    #[test]
+    fn no_fragments() {
+        assert_eq!(read(&[]), Fragments::default());
+        assert_eq!(read(&[("empty.txt", Ok(""))]), Fragments::default());
+    }
+
+    #[test]
+    fn fragments_are_unioned() {
+        let fragments = read(&[
+            (
+                "infra.txt",
+                Ok("# Owned by infra\nterraform\nkubectl # CLI\n\n"),
+            ),
+            ("web.txt", Ok("  kubectl\nwebpack\r\n")),
+        ]);
+        assert_eq!(words(&fragments), ["kubectl", "terraform", "webpack"]);
+        assert!(fragments.errors.is_empty());
+
+        let mut config = TyposConfig::default();
+        fragments.apply(&mut config);
+        assert_eq!(
+            config.render(),
+            "[default.extend-words]\n\
+             kubectl = \"kubectl\"\n\
+             terraform = \"terraform\"\n\
+             webpack = \"webpack\"\n"
+        );
+
+        // The result does not depend on which fragment holds which word.
+        let swapped = read(&[
+            ("a.txt", Ok("webpack\nkubectl\n")),
+            ("b.txt", Ok("terraform\n")),
+        ]);
+        assert_eq!(swapped.words, fragments.words);
+    }
+
+    #[test]
+    fn unreadable_fragments_are_skipped() {
+        let fragments = read(&[
+            ("missing.txt", Err("No such file or directory")),
+            ("good.txt", Ok("zed\n")),
+        ]);
+        assert_eq!(words(&fragments), ["zed"]);
+        assert_eq!(
+            fragments.errors,
+            ["cannot read .typos/words.d/missing.txt: No such file or directory"]
+        );
+    }
+
+    #[test]
+    fn unicode_words() {
+        let fragments = read(&[
+            ("de.txt", Ok("Größe\nnaïve\n")),
+            ("other.txt", Ok("GRÖSSE\nשלום # shalom\n𐐨𐐨\n")),
+        ]);
+        assert_eq!(
+            words(&fragments),
+            ["GRÖSSE", "Größe", "naïve", "שלום", "𐐨𐐨"]
+        );
+
+        // typos takes case variants for the same word, only one is accepted.
+        let mut config = TyposConfig::default();
+        fragments.apply(&mut config);
+        assert_eq!(
+            config.render(),
+            "[default.extend-words]\n\
+             \"Größe\" = \"Größe\"\n\
+             \"naïve\" = \"naïve\"\n\
+             \"שלום\" = \"שלום\"\n\
+             \"𐐨𐐨\" = \"𐐨𐐨\"\n"
+        );
+    }
+}
@@ -0,0 +1,220 @@
+//! Words both accepted through `extend-words` and ignored through `extend-ignore-re`.
+//!
+//! The extension forwards both as they are, and typos decides: it skips the text
+//! matching an ignore pattern before looking words up, so such a word is ignored
+//! wherever the pattern matches and its `extend-words` entry only counts elsewhere. As
+//! the interplay surprises users, each overlap is logged once, naming both sides.
+
+use crate::{
+    cli_sync,
+    pattern_match::Pattern,
+    typos_config::{TomlValue, TyposConfig},
+};
+
+const WORDS_KEY: &str = "extend-words";
+const PATTERNS_KEY: &str = "extend-ignore-re";
+
+/// An `extend-words` entry matched by an `extend-ignore-re` pattern.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Overlap {
+    pub word: String,
+    /// The table of the word, e.g. `default.extend-words`.
+    pub words_table: String,
+    pub pattern: String,
+    /// The table of the pattern, e.g. `type.md`.
+    pub patterns_table: String,
+}
+
+/// The pairs of `words` and `patterns` where the pattern matches the word standing
+/// alone, in the order of `words`.
+pub(crate) fn overlaps<'a>(
+    words: &[&'a str],
+    patterns: &'a [(String, Pattern)],
+) -> Vec<(&'a str, &'a str)> {
+    words
+        .iter()
+        .flat_map(|word| {
+            patterns
+                .iter()
+                .filter(|(_, pattern)| pattern.matches_whole(word))
+                .map(|(source, _)| (*word, source.as_str()))
+        })
+        .collect()
+}
+
+/// The file type a table applies to, `None` for `default`, which applies to all.
+fn file_type(table: &str) -> Option<&str> {
+    table.strip_prefix("type.")?.split('.').next()
+}
+
+/// The overlaps of a configuration, between the words and patterns of the same file
+/// type or of `default`.
+///
+/// Patterns the extension cannot match, e.g. using Unicode classes, are left out.
+pub(crate) fn detect(config: &TyposConfig) -> Vec<Overlap> {
+    let patterns: Vec<(&str, Vec<(String, Pattern)>)> = config
+        .entries(PATTERNS_KEY)
+        .filter_map(|(table, value)| match value {
+            TomlValue::Array(patterns) => Some((table, patterns)),
+            _ => None,
+        })
+        .map(|(table, patterns)| {
+            let compiled = patterns
+                .iter()
+                .filter_map(|pattern| match pattern {
+                    TomlValue::String(pattern) => {
+                        Some((pattern.clone(), Pattern::parse(pattern).ok()?))
+                    }
+                    _ => None,
+                })
+                .collect();
+            (table, compiled)
+        })
+        .collect();
+    let mut found = Vec::new();
+    for (words_table, entries) in config.tables() {
+        let Some(scope) = words_table.strip_suffix(&format!(".{WORDS_KEY}")) else {
+            continue;
+        };
+        let words: Vec<&str> = entries.keys().map(String::as_str).collect();
+        for (patterns_table, compiled) in &patterns {
+            let applies = match (file_type(scope), file_type(patterns_table)) {
+                (Some(words_type), Some(patterns_type)) => words_type == patterns_type,
+                _ => true,
+            };
+            if !applies {
+                continue;
+            }
+            for (word, pattern) in overlaps(&words, compiled) {
+                found.push(Overlap {
+                    word: word.to_string(),
+                    words_table: words_table.to_string(),
+                    pattern: pattern.to_string(),
+                    patterns_table: patterns_table.to_string(),
+                });
+            }
+        }
+    }
+    found
+}
+
+impl Overlap {
+    /// What typos does with the overlap, for the typos-lsp release `server_version`
+    /// when it is known.
+    pub(crate) fn message(&self, server_version: Option<&str>) -> String {
+        let typos = match server_version {
+            Some(tag) => match cli_sync::cli_release_for(tag) {
+                Some(cli) => format!("typos {cli}, which typos-lsp {tag} embeds,"),
+                None => format!("the typos version embedded by typos-lsp {tag}"),
+            },
+            None => "typos".to_string(),
+        };
+        format!(
+            "`{word}` is in `{words_table}` and matches the `{patterns_table}.{PATTERNS_KEY}` \
+             pattern {pattern:?}: {typos} skips the text matching `{PATTERNS_KEY}` before \
+             looking words up, so `{word}` is ignored wherever the pattern matches and its \
+             `{WORDS_KEY}` entry only applies elsewhere; both are kept",
+            word = self.word,
+            words_table = self.words_table,
+            patterns_table = self.patterns_table,
+            pattern = self.pattern,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{detect, overlaps, Overlap};
+    use crate::{pattern_match::Pattern, typos_config::TyposConfig};
+
+    fn compiled(patterns: &[&str]) -> Vec<(String, Pattern)> {
+        patterns
+            .iter()
+            .map(|pattern| (pattern.to_string(), Pattern::parse(pattern).unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn word_list_against_patterns() {
+        let patterns = compiled(&[r"\b[A-Z]{3}\b", r"0x[0-9a-f]+", r"(?i)k8s"]);
+        assert_eq!(
+            overlaps(&["ODF", "teh", "K8S", "ODFs", "0xdead"], &patterns),
+            [
+                ("ODF", r"\b[A-Z]{3}\b"),
+                ("K8S", "(?i)k8s"),
+                ("0xdead", "0x[0-9a-f]+"),
+            ]
+        );
+        assert!(overlaps(&["ODF"], &[]).is_empty());
+    }
+
+    #[test]
+    fn configurations() {
+        let config = TyposConfig::parse(
+            "[default]\nextend-ignore-re = ['''\\b[A-Z]{3}\\b''', '''\\p{Han}+''']\n\
+             [default.extend-words]\nODF = \"ODF\"\nteh = \"teh\"\n\
+             [type.md]\nextend-ignore-re = ['''[a-z]+''']\n\
+             [type.md.extend-words]\nwip = \"wip\"\n\
+             [type.py.extend-words]\nnd = \"nd\"\n",
+        )
+        .unwrap();
+        assert_eq!(
+            detect(&config),
+            [
+                Overlap {
+                    word: "ODF".to_string(),
+                    words_table: "default.extend-words".to_string(),
+                    pattern: r"\b[A-Z]{3}\b".to_string(),
+                    patterns_table: "default".to_string(),
+                },
+                Overlap {
+                    word: "teh".to_string(),
+                    words_table: "default.extend-words".to_string(),
+                    pattern: "[a-z]+".to_string(),
+                    patterns_table: "type.md".to_string(),
+                },
+                Overlap {
+                    word: "wip".to_string(),
+                    words_table: "type.md.extend-words".to_string(),
+                    pattern: "[a-z]+".to_string(),
+                    patterns_table: "type.md".to_string(),
+                },
+            ]
+        );
+        assert!(
+            detect(&TyposConfig::parse("[default.extend-words]\nODF = \"ODF\"\n").unwrap())
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn messages() {
+        let overlap = Overlap {
+            word: "ODF".to_string(),
+            words_table: "default.extend-words".to_string(),
+            pattern: r"\b[A-Z]{3}\b".to_string(),
+            patterns_table: "default".to_string(),
+        };
+        assert_eq!(
+            overlap.message(Some("v0.1.23")),
+            "`ODF` is in `default.extend-words` and matches the `default.extend-ignore-re` \
+             pattern \"\\\\b[A-Z]{3}\\\\b\": typos 1.24, which typos-lsp v0.1.23 embeds, skips \
+             the text matching `extend-ignore-re` before looking words up, so `ODF` is \
+             ignored wherever the pattern matches and its `extend-words` entry only applies \
+             elsewhere; both are kept"
+        );
+        assert!(overlap
+            .message(Some("v0.9.0"))
+            .contains(": the typos version embedded by typos-lsp v0.9.0 skips the text"));
+        assert!(overlap.message(None).contains(": typos skips the text"));
+    }
+
+    #[test]
+    fn unicode_words() {
+        let patterns = compiled(&[r"(?i)größe", r"\bna\w+\b", r"[a-z]{5}"]);
+        assert_eq!(
+            overlaps(&["GRÖẞE", "GRÖSSE", "naïve", "שלום", "𐐨𐐨"], &patterns),
+            [("GRÖẞE", "(?i)größe"), ("naïve", r"\bna\w+\b")]
+        );
+    }
+}
@@ -0,0 +1,89 @@
+//! The extension work directory, which typos-lsp is installed into.
+//!
+//! Zed runs the extension with its work directory as the current directory, which
+//! nothing keeps from changing while the extension is loaded. The directory is resolved
+//! once, when the extension loads: the file operations of the installations are rooted
+//! in it, and the paths handed to Zed or typos-lsp are made absolute from it rather than
+//! from the current directory.
+
+use std::{
+    env, fs,
+    path::{Component, Path, PathBuf},
+};
+
+/// The work directory, as the current directory is when the extension loads, with its
+/// symlinks resolved when they can be.
+pub(crate) fn resolve() -> PathBuf {
+    let current = env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+    fs::canonicalize(&current).unwrap_or(current)
+}
+
+/// `path`, relative to the work directory `root`, made absolute, `None` when it is not
+/// UTF-8. Absolute paths, e.g. of `$PATH` binaries, are returned as they are.
+pub(crate) fn absolute(root: &Path, path: &str) -> Option<String> {
+    if Path::new(path).is_absolute() {
+        return Some(path.to_string());
+    }
+    root.join(path).to_str().map(str::to_string)
+}
+
+/// Whether `path`, relative to the work directory `root` or absolute, lies within it.
+/// Paths are compared as they are written, and a `..` component may lead anywhere.
+pub(crate) fn contains(root: &Path, path: &str) -> bool {
+    let path = Path::new(path);
+    if path.as_os_str().is_empty() || path.components().any(|c| c == Component::ParentDir) {
+        return false;
+    }
+    if path.has_root() {
+        path.starts_with(root)
+    } else {
+        path.components()
+            .all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::{absolute, contains, resolve};
+
+    const ROOT: &str = "/home/user/.local/share/zed/extensions/work/typos";
+
+    #[test]
+    fn absolute_paths() {
+        let root = Path::new(ROOT);
+        assert_eq!(
+            absolute(root, "typos-lsp-0.1.23/typos-lsp").as_deref(),
+            Some("/home/user/.local/share/zed/extensions/work/typos/typos-lsp-0.1.23/typos-lsp")
+        );
+        assert_eq!(
+            absolute(root, "/usr/bin/typos-lsp").as_deref(),
+            Some("/usr/bin/typos-lsp")
+        );
+        assert_eq!(absolute(root, ".").as_deref(), Some(&*format!("{ROOT}/.")));
+        assert!(Path::new(&absolute(&resolve(), "typos-lsp").unwrap()).is_absolute());
+    }
+
+    #[test]
+    fn contained_paths() {
+        let root = Path::new(ROOT);
+        for path in [
+            "typos-lsp-0.1.23/typos-lsp",
+            "./typos-lsp-0.1.23/typos-lsp",
+            "/home/user/.local/share/zed/extensions/work/typos/typos-lsp-0.1.23/typos-lsp",
+        ] {
+            assert!(contains(root, path), "{path}");
+        }
+        for path in [
+            "",
+            "../typos-lsp-0.1.23/typos-lsp",
+            "typos-lsp-0.1.23/../../typos-lsp",
+            "/usr/bin/typos-lsp",
+            "/home/user/.local/share/zed/extensions/work/typos-other/typos-lsp",
+            "/home/user/.local/share/zed/extensions/work/typos/../typos-lsp",
+        ] {
+            assert!(!contains(root, path), "{path}");
+        }
+    }
+}
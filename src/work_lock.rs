@@ -0,0 +1,319 @@
+//! Serializing installations and cleanups across Zed processes.
+//!
+//! Separate Zed processes may share the extension work directory, and one removing the
+//! installations it does not use could remove another's half-extracted download. Both
+//! only happen while holding a lock file, created exclusively, naming the extension
+//! instance holding it and since when. WASI has no process IDs to tell whether the
+//! holder still runs, so a lock held for longer than [`STALE_AFTER`] is taken to be
+//! left behind, e.g. by a crashed process, and taken over. Waiting for the lock and
+//! taking it over are both logged, naming the holder.
+
+use std::{io, path::Path, time::Duration};
+
+use crate::{host::Fs, log, messages};
+
+/// The lock file, relative to the work directory.
+pub(crate) const FILE_NAME: &str = "install.lock";
+
+/// How long a lock is held at most, longer than a download and extraction take.
+pub(crate) const STALE_AFTER: Duration = Duration::from_secs(10 * 60);
+
+/// How long an installation waits for the lock before failing.
+pub(crate) const WAIT_LIMIT: Duration = Duration::from_secs(2 * 60);
+
+/// How long to wait between attempts to take the lock.
+pub(crate) const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// The extension instance holding the lock.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Holder {
+    pub owner: String,
+    /// When the lock was taken, in seconds since the Unix epoch.
+    pub since: u64,
+}
+
+impl Holder {
+    fn parse(content: &str) -> Option<Self> {
+        let (owner, since) = content.trim().split_once('\t')?;
+        Some(Self {
+            owner: owner.to_string(),
+            since: since.parse().ok()?,
+        })
+    }
+
+    fn render(&self) -> String {
+        format!("{}\t{}\n", self.owner, self.since)
+    }
+
+    fn is_stale(&self, now: u64) -> bool {
+        now.saturating_sub(self.since) > STALE_AFTER.as_secs()
+    }
+}
+
+/// The lock of the work directory, released when dropped.
+pub(crate) struct WorkLock<'a> {
+    fs: &'a dyn Fs,
+    owner: String,
+}
+
+/// The outcome of an attempt to take the lock.
+pub(crate) enum Attempt<'a> {
+    Acquired(WorkLock<'a>),
+    Held(Holder),
+}
+
+impl<'a> WorkLock<'a> {
+    /// Take the lock for `owner` at `now`, unless another instance holds it. A lock file
+    /// that cannot be read, or is stale, is taken over.
+    pub(crate) fn try_acquire(fs: &'a dyn Fs, owner: &str, now: u64) -> io::Result<Attempt<'a>> {
+        let path = Path::new(FILE_NAME);
+        let holder = Holder {
+            owner: owner.to_string(),
+            since: now,
+        };
+        for _ in 0..2 {
+            match fs.create_new(path, holder.render().as_bytes()) {
+                Ok(()) => {
+                    return Ok(Attempt::Acquired(Self {
+                        fs,
+                        owner: owner.to_string(),
+                    }))
+                }
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {}
+                Err(e) => return Err(e),
+            }
+            let content = match fs.read_to_string(path) {
+                Ok(content) => content,
+                // Released meanwhile.
+                Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e),
+            };
+            if let Some(current) = Holder::parse(&content).filter(|current| !current.is_stale(now))
+            {
+                return Ok(Attempt::Held(current));
+            }
+            // Unless another instance took it over first.
+            if fs.read_to_string(path).ok().as_deref() == Some(content.as_str()) {
+                log::warn(taking_over(Holder::parse(&content).as_ref(), now));
+                fs.remove_file(path).ok();
+            }
+        }
+        match fs
+            .read_to_string(path)
+            .ok()
+            .and_then(|content| Holder::parse(&content))
+        {
+            Some(current) => Ok(Attempt::Held(current)),
+            None => Err(io::Error::other(messages::work_lock_unsettled())),
+        }
+    }
+
+    /// Take the lock for `owner`, waiting for [`WAIT_LIMIT`] at most while another
+    /// instance holds it, `clock` telling the time and `sleep` waiting.
+    pub(crate) fn acquire(
+        fs: &'a dyn Fs,
+        owner: &str,
+        clock: &dyn Fn() -> u64,
+        sleep: &dyn Fn(Duration),
+    ) -> Result<Self, String> {
+        let start = clock();
+        let mut waiting = false;
+        loop {
+            let now = clock();
+            let holder =
+                match Self::try_acquire(fs, owner, now).map_err(messages::work_lock_failed)? {
+                    Attempt::Acquired(lock) => return Ok(lock),
+                    Attempt::Held(holder) => holder,
+                };
+            if now.saturating_sub(start) >= WAIT_LIMIT.as_secs() {
+                return Err(messages::work_lock_wait_exceeded(
+                    now.saturating_sub(holder.since),
+                ));
+            }
+            if !waiting {
+                waiting = true;
+                log::info(waiting_for(&holder, now));
+            }
+            sleep(POLL_INTERVAL);
+        }
+    }
+}
+
+impl Drop for WorkLock<'_> {
+    fn drop(&mut self) {
+        let path = Path::new(FILE_NAME);
+        let held = self
+            .fs
+            .read_to_string(path)
+            .ok()
+            .and_then(|content| Holder::parse(&content))
+            .is_some_and(|holder| holder.owner == self.owner);
+        // A lock taken over as stale belongs to another instance now.
+        if held {
+            self.fs.remove_file(path).ok();
+        }
+    }
+}
+
+/// Waiting at `now` for the lock `holder` took.
+fn waiting_for(holder: &Holder, now: u64) -> String {
+    messages::work_lock_waiting(&holder.owner, now.saturating_sub(holder.since))
+}
+
+/// Taking over at `now` the stale lock of `holder`, unknown when its file cannot be read.
+fn taking_over(holder: Option<&Holder>, now: u64) -> String {
+    messages::work_lock_taken_over(
+        holder.map(|holder| (holder.owner.as_str(), now.saturating_sub(holder.since))),
+    )
+}
+
+/// An identifier of this extension instance, for the lock file.
+pub(crate) fn instance_id() -> String {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_nanos());
+    format!("{nanos:x}")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, fs, time::Duration};
+
+    use super::{
+        taking_over, waiting_for, Attempt, Holder, WorkLock, FILE_NAME, POLL_INTERVAL, STALE_AFTER,
+        WAIT_LIMIT,
+    };
+    use crate::{host::StdFs, test_support::TempDir};
+
+    const NOW: u64 = 1_700_000_000;
+
+    fn holder(attempt: Attempt) -> Holder {
+        match attempt {
+            Attempt::Acquired(_) => panic!("the lock was acquired"),
+            Attempt::Held(holder) => holder,
+        }
+    }
+
+    fn lock_content(temp: &TempDir) -> Option<String> {
+        fs::read_to_string(temp.path().join(FILE_NAME)).ok()
+    }
+
+    #[test]
+    fn exclusive_until_released() {
+        let temp = TempDir::new("work-lock");
+        let fs = StdFs::new(temp.path());
+        let Attempt::Acquired(lock) = WorkLock::try_acquire(&fs, "a", NOW).unwrap() else {
+            panic!("the lock was held");
+        };
+        assert_eq!(lock_content(&temp).as_deref(), Some("a\t1700000000\n"));
+        assert_eq!(
+            holder(WorkLock::try_acquire(&fs, "b", NOW + 5).unwrap()),
+            Holder {
+                owner: "a".to_string(),
+                since: NOW,
+            }
+        );
+        drop(lock);
+        assert_eq!(lock_content(&temp), None);
+        assert!(matches!(
+            WorkLock::try_acquire(&fs, "b", NOW + 5).unwrap(),
+            Attempt::Acquired(_)
+        ));
+    }
+
+    #[test]
+    fn stale_locks_are_taken_over() {
+        let temp = TempDir::new("work-lock-stale");
+        let fs = StdFs::new(temp.path());
+        let path = temp.path().join(FILE_NAME);
+        fs::write(&path, format!("a\t{NOW}\n")).unwrap();
+        let expiry = NOW + STALE_AFTER.as_secs();
+        assert_eq!(
+            holder(WorkLock::try_acquire(&fs, "b", expiry).unwrap()).owner,
+            "a"
+        );
+        let Attempt::Acquired(lock) = WorkLock::try_acquire(&fs, "b", expiry + 1).unwrap() else {
+            panic!("the stale lock was not taken over");
+        };
+        assert_eq!(lock_content(&temp), Some(format!("b\t{}\n", expiry + 1)));
+
+        // The previous holder releasing it leaves the lock taken over alone.
+        drop(WorkLock {
+            fs: &fs,
+            owner: "a".to_string(),
+        });
+        assert_eq!(lock_content(&temp), Some(format!("b\t{}\n", expiry + 1)));
+        drop(lock);
+        assert_eq!(lock_content(&temp), None);
+
+        // So is a lock file that cannot be read.
+        fs::write(&path, "").unwrap();
+        assert!(matches!(
+            WorkLock::try_acquire(&fs, "c", NOW).unwrap(),
+            Attempt::Acquired(_)
+        ));
+    }
+
+    #[test]
+    fn contention_waits_then_fails() {
+        let temp = TempDir::new("work-lock-wait");
+        let fs = StdFs::new(temp.path());
+        let path = temp.path().join(FILE_NAME);
+        fs::write(&path, format!("a\t{NOW}\n")).unwrap();
+        let now = Cell::new(NOW);
+        let sleeps = Cell::new(0);
+
+        // Released by the holder while waiting.
+        let sleep = |duration: Duration| {
+            assert_eq!(duration, POLL_INTERVAL);
+            sleeps.set(sleeps.get() + 1);
+            now.set(now.get() + 1);
+            if sleeps.get() == 3 {
+                fs::remove_file(&path).unwrap();
+            }
+        };
+        let lock = WorkLock::acquire(&fs, "b", &|| now.get(), &sleep).unwrap();
+        assert_eq!(sleeps.get(), 3);
+        assert_eq!(lock_content(&temp), Some(format!("b\t{}\n", NOW + 3)));
+        drop(lock);
+
+        // Held for longer than the wait limit.
+        fs::write(&path, format!("a\t{NOW}\n")).unwrap();
+        sleeps.set(0);
+        let sleep = |_| {
+            sleeps.set(sleeps.get() + 1);
+            now.set(now.get() + 10);
+        };
+        let error = WorkLock::acquire(&fs, "b", &|| now.get(), &sleep)
+            .err()
+            .unwrap();
+        assert_eq!(sleeps.get(), WAIT_LIMIT.as_secs() / 10);
+        assert!(
+            error.starts_with("another Zed process has been installing typos-lsp for 123s"),
+            "{error}"
+        );
+        assert_eq!(lock_content(&temp), Some(format!("a\t{NOW}\n")));
+    }
+
+    #[test]
+    fn messages_name_the_holder() {
+        let holder = Holder {
+            owner: "18c2f".to_string(),
+            since: NOW,
+        };
+        assert_eq!(
+            waiting_for(&holder, NOW + 4),
+            "waiting for another Zed process to finish installing typos-lsp: extension \
+             instance 18c2f has held install.lock for 4s"
+        );
+        assert_eq!(
+            taking_over(Some(&holder), NOW + 601),
+            "taking over install.lock, held by extension instance 18c2f for 601s, longer than \
+             an installation takes: the Zed process holding it likely quit during one"
+        );
+        assert_eq!(
+            taking_over(None, NOW),
+            "taking over install.lock, which cannot be read"
+        );
+    }
+}
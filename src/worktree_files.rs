@@ -0,0 +1,533 @@
+//! Files the extension writes into worktrees.
+//!
+//! The extension sandbox usually has no write access outside of its work directory,
+//! so a copy of every file is kept there and the user is told where to find it when
+//! writing into the worktree fails.
+//!
+//! Every file written is registered in the work directory with the hash of its content,
+//! so that the extension can clean up after itself without ever removing a file the
+//! user wrote or edited.
+//!
+//! These files end up committed, so what is written must only depend on its inputs:
+//! renderers sort keys and leave timestamps to the work directory, and every file ends
+//! with a single line break.
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use crate::{hashing, host::Fs, log, messages, write_consent::Consent};
+
+/// Where copies of the files meant for worktrees are kept, in the work directory.
+pub(crate) const FALLBACK_DIR: &str = "worktree-files";
+
+/// The files written into worktrees, as lines of root, relative path and SHA-256 of the
+/// content written, separated by tabs.
+pub(crate) const REGISTRY_FILE: &str = "worktree-files.tsv";
+
+/// The outcome of writing a worktree file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Written {
+    /// The file already had this content, nothing was written.
+    Unchanged,
+    /// The file was written in the worktree.
+    Worktree(PathBuf),
+    /// The worktree could not be written, a copy was kept in the work directory, at
+    /// this path relative to it.
+    Fallback(PathBuf),
+    /// The user did not consent to the write, which was only logged.
+    Withheld,
+}
+
+/// `content` ending with a single line break, or empty.
+pub(crate) fn normalized(content: &str) -> String {
+    let content = content.trim_end_matches(['\n', '\r']);
+    if content.is_empty() {
+        String::new()
+    } else {
+        format!("{content}\n")
+    }
+}
+
+/// Write `content` to `relative_path` in the worktree at `root`, whose filesystem is
+/// `worktree`, [`normalized`], unless `current`ly it already has this content, to avoid
+/// waking up file watchers for nothing, or unless `consent` is withheld.
+pub(crate) fn write_if_changed(
+    consent: &Consent,
+    worktree: &dyn Fs,
+    work_dir: &dyn Fs,
+    root: &Path,
+    relative_path: &str,
+    content: &str,
+    current: Option<&str>,
+) -> Result<Written, String> {
+    let content = &normalized(content);
+    if current == Some(content) {
+        return Ok(Written::Unchanged);
+    }
+    if let Some(withheld) = consent.withheld(root, relative_path) {
+        log::once(
+            format!("withheld-write:{}:{relative_path}", root.display()),
+            log::Level::Warn,
+            withheld,
+        );
+        return Ok(Written::Withheld);
+    }
+    let target = root.join(relative_path);
+    match write_atomically(worktree, Path::new(relative_path), content) {
+        Ok(()) => {
+            let mut registry = Registry::read(work_dir);
+            registry.insert(root, relative_path, hashing::sha256_hex(content.as_bytes()));
+            registry.write(work_dir);
+            Ok(Written::Worktree(target))
+        }
+        Err(e) => {
+            let fallback = fallback_path(root, relative_path);
+            write_atomically(work_dir, &fallback, content)?;
+            log::info(messages::worktree_write_fallback(&target, e, &fallback));
+            Ok(Written::Fallback(fallback))
+        }
+    }
+}
+
+/// What cleaning up a worktree did.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub(crate) struct Cleaned {
+    /// The files removed, as the extension wrote them.
+    pub removed: Vec<PathBuf>,
+    /// The files kept as they were changed since, by the user or another tool.
+    pub kept: Vec<PathBuf>,
+}
+
+/// Remove the files registered for the worktree at `root`, whose filesystem is
+/// `worktree`, that still have the content the extension wrote, and their fallback
+/// copies. `read` reads a file of the worktree. Every entry of the worktree is then
+/// forgotten, whether its file was removed, kept or already gone.
+pub(crate) fn clean(
+    worktree: &dyn Fs,
+    work_dir: &dyn Fs,
+    root: &Path,
+    read: impl Fn(&str) -> Option<String>,
+) -> Cleaned {
+    let mut registry = Registry::read(work_dir);
+    let mut cleaned = Cleaned::default();
+    for (relative_path, sha256) in registry.take_root(root) {
+        work_dir
+            .remove_file(&fallback_path(root, &relative_path))
+            .ok();
+        let target = root.join(&relative_path);
+        let Some(content) = read(&relative_path) else {
+            continue;
+        };
+        if hashing::sha256_hex(content.as_bytes()) != sha256 {
+            cleaned.kept.push(target);
+            continue;
+        }
+        match worktree.remove_file(Path::new(&relative_path)) {
+            Ok(()) => {
+                remove_empty_parents(worktree, Path::new(&relative_path));
+                cleaned.removed.push(target);
+            }
+            Err(e) => {
+                log::warn(messages::remove_failed(&target, e));
+                cleaned.kept.push(target);
+            }
+        }
+    }
+    registry.write(work_dir);
+    cleaned
+}
+
+/// Remove the directories left empty above `file`, relative to the worktree root.
+fn remove_empty_parents(worktree: &dyn Fs, file: &Path) {
+    let mut dir = file.parent();
+    while let Some(current) = dir.filter(|dir| !dir.as_os_str().is_empty()) {
+        let empty = worktree
+            .read_dir(current)
+            .is_ok_and(|entries| entries.is_empty());
+        if !empty || worktree.remove_dir_all(current).is_err() {
+            break;
+        }
+        dir = current.parent();
+    }
+}
+
+/// The registered files, by worktree root and relative path.
+#[derive(Debug, Default)]
+struct Registry(BTreeMap<(String, String), String>);
+
+impl Registry {
+    fn read(work_dir: &dyn Fs) -> Self {
+        let content = work_dir
+            .read_to_string(Path::new(REGISTRY_FILE))
+            .unwrap_or_default();
+        Self(
+            content
+                .lines()
+                .filter_map(|line| {
+                    let mut fields = line.split('\t');
+                    let (root, path, sha256) = (fields.next()?, fields.next()?, fields.next()?);
+                    Some(((root.to_string(), path.to_string()), sha256.to_string()))
+                })
+                .collect(),
+        )
+    }
+
+    fn write(&self, work_dir: &dyn Fs) {
+        let content: String = self
+            .0
+            .iter()
+            .flat_map(|((root, path), sha256)| [root, "\t", path, "\t", sha256, "\n"])
+            .collect();
+        if let Err(e) = work_dir.write(Path::new(REGISTRY_FILE), content.as_bytes()) {
+            log::warn(messages::worktree_files_record_failed(e));
+        }
+    }
+
+    fn key(root: &Path, relative_path: &str) -> (String, String) {
+        (
+            root.to_string_lossy().into_owned(),
+            relative_path.to_string(),
+        )
+    }
+
+    fn insert(&mut self, root: &Path, relative_path: &str, sha256: String) {
+        self.0.insert(Self::key(root, relative_path), sha256);
+    }
+
+    /// Remove the entries of the worktree at `root`, returning their paths and hashes.
+    fn take_root(&mut self, root: &Path) -> Vec<(String, String)> {
+        let root = root.to_string_lossy();
+        let keys: Vec<(String, String)> = self
+            .0
+            .keys()
+            .filter(|(entry_root, _)| *entry_root == root)
+            .cloned()
+            .collect();
+        keys.into_iter()
+            .filter_map(|key| {
+                let sha256 = self.0.remove(&key)?;
+                Some((key.1, sha256))
+            })
+            .collect()
+    }
+}
+
+fn fallback_path(root: &Path, relative_path: &str) -> PathBuf {
+    Path::new(FALLBACK_DIR)
+        .join(hashing::sha256_hex(root.to_string_lossy().as_bytes()))
+        .join(relative_path)
+}
+
+/// Write `path` of `fs` through a temporary file renamed over it, so that readers never
+/// see a partially written file.
+fn write_atomically(fs: &dyn Fs, path: &Path, content: &str) -> Result<(), String> {
+    if let Some(parent) = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        fs.create_dir_all(parent)
+            .map_err(|e| messages::create_failed(parent, e))?;
+    }
+    fs.write_atomically(path, content.as_bytes())
+        .map_err(|e| messages::write_failed(path, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::{clean, normalized, write_if_changed, Cleaned, Written, REGISTRY_FILE};
+    use crate::{
+        host::StdFs,
+        test_support::{ReadOnlyFs, TempDir},
+        write_consent::Consent,
+    };
+
+    #[test]
+    fn writes_then_no_op() {
+        let temp = TempDir::new("worktree-files");
+        let root = temp.path().join("project");
+        let work_dir = temp.path().join("work");
+        fs::create_dir_all(&root).unwrap();
+
+        let written = write_if_changed(
+            &Consent::Granted,
+            &StdFs::new(&root),
+            &StdFs::new(&work_dir),
+            &root,
+            "a/b.toml",
+            "x = 1\n",
+            None,
+        )
+        .unwrap();
+        assert_eq!(written, Written::Worktree(root.join("a/b.toml")));
+        assert_eq!(
+            fs::read_to_string(root.join("a/b.toml")).unwrap(),
+            "x = 1\n"
+        );
+
+        let written = write_if_changed(
+            &Consent::Granted,
+            &StdFs::new(&root),
+            &StdFs::new(&work_dir),
+            &root,
+            "a/b.toml",
+            "x = 1\n",
+            Some("x = 1\n"),
+        )
+        .unwrap();
+        assert_eq!(written, Written::Unchanged);
+    }
+
+    #[test]
+    fn withheld_writes() {
+        let temp = TempDir::new("worktree-files-withheld");
+        let root = temp.path().join("project");
+        let work_dir = temp.path().join("work");
+        fs::create_dir_all(&root).unwrap();
+        fs::create_dir_all(&work_dir).unwrap();
+        let consent = Consent::Withheld {
+            settings: vec!["export_lockfile"],
+        };
+
+        let written = write_if_changed(
+            &consent,
+            &StdFs::new(&root),
+            &StdFs::new(&work_dir),
+            &root,
+            "a.lock",
+            "x = 1\n",
+            None,
+        );
+        assert_eq!(written, Ok(Written::Withheld));
+        assert!(!root.join("a.lock").exists());
+        assert!(!work_dir.join(REGISTRY_FILE).exists());
+        // Nothing to withhold when the file already has the content.
+        let written = write_if_changed(
+            &consent,
+            &StdFs::new(&root),
+            &StdFs::new(&work_dir),
+            &root,
+            "a.lock",
+            "x = 1\n",
+            Some("x = 1\n"),
+        );
+        assert_eq!(written, Ok(Written::Unchanged));
+    }
+
+    #[test]
+    fn single_trailing_line_break() {
+        assert_eq!(normalized("x = 1"), "x = 1\n");
+        assert_eq!(normalized("x = 1\n\n"), "x = 1\n");
+        assert_eq!(normalized("x = 1\r\n"), "x = 1\n");
+        assert_eq!(normalized("\n"), "");
+
+        let temp = TempDir::new("worktree-files-line-break");
+        let root = temp.path().join("project");
+        let work_dir = temp.path().join("work");
+        fs::create_dir_all(&root).unwrap();
+        fs::create_dir_all(&work_dir).unwrap();
+        write_if_changed(
+            &Consent::Granted,
+            &StdFs::new(&root),
+            &StdFs::new(&work_dir),
+            &root,
+            "a.toml",
+            "x = 1\n\n",
+            None,
+        )
+        .unwrap();
+        let current = fs::read_to_string(root.join("a.toml")).unwrap();
+        assert_eq!(current, "x = 1\n");
+        // The same content, rendered with another ending, is not written again.
+        let written = write_if_changed(
+            &Consent::Granted,
+            &StdFs::new(&root),
+            &StdFs::new(&work_dir),
+            &root,
+            "a.toml",
+            "x = 1",
+            Some(&current),
+        );
+        assert_eq!(written, Ok(Written::Unchanged));
+        assert_eq!(
+            clean(&StdFs::new(&root), &StdFs::new(&work_dir), &root, |path| {
+                fs::read_to_string(root.join(path)).ok()
+            }),
+            Cleaned {
+                removed: vec![root.join("a.toml")],
+                kept: Vec::new(),
+            }
+        );
+    }
+
+    #[test]
+    fn falls_back_to_work_dir() {
+        let temp = TempDir::new("worktree-files-fallback");
+        let root = temp.path().join("project");
+        let work_dir = temp.path().join("work");
+        fs::create_dir_all(&root).unwrap();
+
+        let written = write_if_changed(
+            &Consent::Granted,
+            &ReadOnlyFs(StdFs::new(&root)),
+            &StdFs::new(&work_dir),
+            &root,
+            "a/b.toml",
+            "x = 1\n",
+            None,
+        );
+        let Ok(Written::Fallback(path)) = written else {
+            panic!("no fallback: {written:?}");
+        };
+        assert!(path.is_relative() && path.ends_with("a/b.toml"), "{path:?}");
+        assert_eq!(fs::read_to_string(work_dir.join(path)).unwrap(), "x = 1\n");
+        assert!(!root.join("a").exists());
+        assert!(!work_dir.join(REGISTRY_FILE).exists());
+    }
+
+    #[test]
+    fn registry_follows_writes() {
+        let temp = TempDir::new("worktree-files-registry");
+        let root = temp.path().join("project");
+        let work_dir = temp.path().join("work");
+        fs::create_dir_all(&root).unwrap();
+        fs::create_dir_all(&work_dir).unwrap();
+        let registry = || fs::read_to_string(work_dir.join(REGISTRY_FILE)).unwrap_or_default();
+
+        write_if_changed(
+            &Consent::Granted,
+            &StdFs::new(&root),
+            &StdFs::new(&work_dir),
+            &root,
+            "a.toml",
+            "a = 1\n",
+            None,
+        )
+        .unwrap();
+        write_if_changed(
+            &Consent::Granted,
+            &StdFs::new(&root),
+            &StdFs::new(&work_dir),
+            &root,
+            "a.toml",
+            "a = 2\n",
+            Some("a = 1\n"),
+        )
+        .unwrap();
+        assert_eq!(
+            registry(),
+            format!(
+                "{}\ta.toml\t{}\n",
+                root.display(),
+                crate::hashing::sha256_hex(b"a = 2\n")
+            )
+        );
+        // Content found as it is was not written by the extension.
+        write_if_changed(
+            &Consent::Granted,
+            &StdFs::new(&root),
+            &StdFs::new(&work_dir),
+            &root,
+            "b.toml",
+            "b = 1\n",
+            Some("b = 1\n"),
+        )
+        .unwrap();
+        assert!(!registry().contains("b.toml"));
+
+        clean(&StdFs::new(&root), &StdFs::new(&work_dir), &root, |path| {
+            fs::read_to_string(root.join(path)).ok()
+        });
+        assert_eq!(registry(), "");
+    }
+
+    #[test]
+    fn cleans_only_unchanged_files() {
+        let temp = TempDir::new("worktree-files-clean");
+        let (root, other) = (temp.path().join("project"), temp.path().join("other"));
+        let work_dir = temp.path().join("work");
+        for dir in [&root, &other, &work_dir] {
+            fs::create_dir_all(dir).unwrap();
+        }
+        fs::create_dir_all(root.join(".zed")).unwrap();
+        fs::write(root.join(".zed/settings.json"), "{}").unwrap();
+
+        for path in [
+            ".zed/typos-effective.toml",
+            "ci/typos/effective.toml",
+            "edited.lock",
+        ] {
+            write_if_changed(
+                &Consent::Granted,
+                &StdFs::new(&root),
+                &StdFs::new(&work_dir),
+                &root,
+                path,
+                "generated\n",
+                None,
+            )
+            .unwrap();
+        }
+        write_if_changed(
+            &Consent::Granted,
+            &StdFs::new(&root),
+            &StdFs::new(&work_dir),
+            &root,
+            "gone.lock",
+            "generated\n",
+            None,
+        )
+        .unwrap();
+        write_if_changed(
+            &Consent::Granted,
+            &StdFs::new(&other),
+            &StdFs::new(&work_dir),
+            &other,
+            "other.lock",
+            "generated\n",
+            None,
+        )
+        .unwrap();
+        fs::write(root.join("edited.lock"), "edited by hand\n").unwrap();
+        fs::remove_file(root.join("gone.lock")).unwrap();
+        // A user file at a path the extension never wrote to.
+        fs::write(root.join("typos.toml"), "generated\n").unwrap();
+
+        let mut cleaned = clean(&StdFs::new(&root), &StdFs::new(&work_dir), &root, |path| {
+            fs::read_to_string(root.join(path)).ok()
+        });
+        cleaned.removed.sort();
+        assert_eq!(
+            cleaned,
+            Cleaned {
+                removed: vec![
+                    root.join(".zed/typos-effective.toml"),
+                    root.join("ci/typos/effective.toml"),
+                ],
+                kept: vec![root.join("edited.lock")],
+            }
+        );
+        assert!(root.join(".zed/settings.json").exists());
+        assert!(!root.join("ci").exists());
+        assert!(root.join("typos.toml").exists());
+        assert_eq!(
+            fs::read_to_string(root.join("edited.lock")).unwrap(),
+            "edited by hand\n"
+        );
+        // Other worktrees are left alone and registered still.
+        assert!(other.join("other.lock").exists());
+        let registry = fs::read_to_string(work_dir.join(REGISTRY_FILE)).unwrap();
+        assert_eq!(registry.lines().count(), 1);
+        assert!(registry.contains("other.lock"));
+
+        assert_eq!(
+            clean(&StdFs::new(&root), &StdFs::new(&work_dir), &root, |path| {
+                fs::read_to_string(root.join(path)).ok()
+            }),
+            Cleaned::default()
+        );
+    }
+}
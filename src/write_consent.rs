@@ -0,0 +1,130 @@
+//! The user's agreement to writes into a worktree requested by its project settings.
+//!
+//! A checked-in `.zed/settings.json` can turn on the features writing into the
+//! repository. The extension cannot prompt, so it only writes for such a worktree once
+//! the user lists it under `allow_worktree_writes` in the user settings, which Zed keeps
+//! across sessions. Until then, what would have been written is logged instead.
+
+use std::path::Path;
+
+use crate::paths;
+
+/// The user-only setting listing the worktrees the project settings may write into.
+pub(crate) const SETTING: &str = "allow_worktree_writes";
+
+/// The settings turning on a feature that writes into the worktree.
+pub(crate) const WRITE_SETTINGS: &[&str] = &["export_lockfile"];
+
+/// Whether the extension may write into a worktree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Consent {
+    /// The writes were turned on by the user, or the user allowed the worktree.
+    Granted,
+    /// The project settings turned on the writes of `settings`, for a worktree the user
+    /// did not allow.
+    Withheld { settings: Vec<&'static str> },
+}
+
+impl Consent {
+    /// The consent for the worktree at `root`, whose project settings turned on
+    /// `requested` among [`WRITE_SETTINGS`], `allowed` being the worktrees listed in the
+    /// user settings.
+    pub(crate) fn decide(root: &str, allowed: &[String], requested: Vec<&'static str>) -> Self {
+        // Entries may be spelled differently from the root, symlinks aside.
+        let is_root = |entry: &String| {
+            let entry = paths::lexical(Path::new(entry.trim()));
+            !entry.as_os_str().is_empty() && entry == paths::lexical(Path::new(root))
+        };
+        if requested.is_empty() || allowed.iter().any(is_root) {
+            Self::Granted
+        } else {
+            Self::Withheld {
+                settings: requested,
+            }
+        }
+    }
+
+    /// What was not done, writing `path` into the worktree at `root`, when withheld.
+    pub(crate) fn withheld(&self, root: &Path, path: &str) -> Option<String> {
+        let Self::Withheld { settings } = self else {
+            return None;
+        };
+        let settings: Vec<String> = settings
+            .iter()
+            .map(|setting| format!("`settings.{setting}`"))
+            .collect();
+        Some(format!(
+            "not writing {path} into {root}: {settings} in the project settings ask for it. \
+             Add {root:?} to `settings.{SETTING}` in your user settings to allow it",
+            root = root.display(),
+            settings = settings.join(" and "),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::Path};
+
+    use super::Consent;
+    use crate::test_support::TempDir;
+
+    #[test]
+    fn decisions() {
+        let allowed = vec!["/work/allowed/".to_string(), String::new()];
+        assert_eq!(
+            Consent::decide("/work/other", &allowed, vec![]),
+            Consent::Granted
+        );
+        assert_eq!(
+            Consent::decide("/work/allowed", &allowed, vec!["export_lockfile"]),
+            Consent::Granted
+        );
+        assert_eq!(
+            Consent::decide("/work/other", &allowed, vec!["export_lockfile"]),
+            Consent::Withheld {
+                settings: vec!["export_lockfile"]
+            }
+        );
+        // An empty entry allows nothing.
+        assert_eq!(
+            Consent::decide("/", &allowed, vec!["export_lockfile"]),
+            Consent::Withheld {
+                settings: vec!["export_lockfile"]
+            }
+        );
+    }
+
+    #[test]
+    fn allowed_through_another_path() {
+        let temp = TempDir::new("write-consent");
+        let root = temp.path().join("project");
+        fs::create_dir_all(&root).unwrap();
+        let spelled = temp.path().join("project/../project/./");
+        let allowed = vec![spelled.to_str().unwrap().to_string()];
+        assert_eq!(
+            Consent::decide(root.to_str().unwrap(), &allowed, vec!["export_lockfile"]),
+            Consent::Granted
+        );
+    }
+
+    #[test]
+    fn withheld_message() {
+        assert_eq!(
+            Consent::Granted.withheld(Path::new("/work/p"), "a.lock"),
+            None
+        );
+        let consent = Consent::Withheld {
+            settings: vec!["export_lockfile"],
+        };
+        assert_eq!(
+            consent.withheld(Path::new("/work/p"), "typos-extension.lock"),
+            Some(
+                "not writing typos-extension.lock into /work/p: `settings.export_lockfile` in \
+                 the project settings ask for it. Add \"/work/p\" to \
+                 `settings.allow_worktree_writes` in your user settings to allow it"
+                    .to_string()
+            )
+        );
+    }
+}